@@ -0,0 +1,440 @@
+/*
+  ____                 __               __                __
+ / __ \__ _____ ____  / /___ ____ _    / /  ___  ___ ____/ /__ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ _ \/ _ `/ _  / -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/\___/\_,_/\_,_/\__/_/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Versioning for the info blocks each stage hands to the next.
+//!
+//! Every block starts with a [`BootInfoHeader`], and is only read after its magic, version
+//! and size have been checked. A block's layout may only grow at its end without bumping
+//! its version, anything else has to bump the version. Readers accept any block at least
+//! [`BootInfo::MIN_SIZE`] long, so fields added to the end are read with
+//! [`BootInfo::appended`] since a stage built before them won't have filled them in.
+//! Optional data is added as [`BootTag`]s hanging off of the header instead, which readers
+//! skip if they don't know them.
+
+/// The magic of [`crate::Stage16toStage32`]
+pub const STAGE16_TO_STAGE32_MAGIC: u32 = u32::from_le_bytes(*b"QS32");
+/// The magic of [`crate::Stage32toStage64`]
+pub const STAGE32_TO_STAGE64_MAGIC: u32 = u32::from_le_bytes(*b"QS64");
+/// The magic of [`crate::KernelBootHeader`]
+pub const KERNEL_BOOT_HEADER_MAGIC: u32 = u32::from_le_bytes(*b"QKBH");
+
+/// Ends the list of tags
+pub const BOOT_TAG_END: u32 = 0;
+/// The name and version of the bootloader, as utf8
+pub const BOOT_TAG_BOOTLOADER_NAME: u32 = 1;
+
+/// # Boot Info Header
+/// The start of every info block passed between stages.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootInfoHeader {
+    pub magic: u32,
+    pub version: u16,
+    reserved: u16,
+    /// The size of the whole block, including this header.
+    pub total_size: u32,
+    reserved2: u32,
+    /// The address of the first [`BootTag`], or zero if there are none.
+    pub extensions: u64,
+}
+
+/// An info block is not what this stage expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootInfoError {
+    /// This is not the block we expected, or it is garbage
+    BadMagic { expected: u32, found: u32 },
+    /// The block was laid out by a stage built for another version
+    UnsupportedVersion { expected: u16, found: u16 },
+    /// The block is missing fields we need
+    TooSmall { expected: u32, found: u32 },
+}
+
+impl core::fmt::Display for BootInfoError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadMagic { expected, found } => {
+                write!(
+                    f,
+                    "expected magic {expected:#010x}, but found {found:#010x}"
+                )
+            }
+            Self::UnsupportedVersion { expected, found } => {
+                write!(f, "expected version {expected}, but found version {found}")
+            }
+            Self::TooSmall { expected, found } => {
+                write!(
+                    f,
+                    "expected at least {expected} bytes, but found {found} bytes"
+                )
+            }
+        }
+    }
+}
+
+impl BootInfoHeader {
+    /// Make a header for a block of `total_size` bytes.
+    pub const fn new(magic: u32, version: u16, total_size: usize) -> Self {
+        Self {
+            magic,
+            version,
+            reserved: 0,
+            total_size: total_size as u32,
+            reserved2: 0,
+            extensions: 0,
+        }
+    }
+
+    /// Point this header at the first of a list of tags, see [`BootTagWriter::finish`].
+    pub const fn with_extensions(mut self, extensions: u64) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Check this header belongs to a block we can read `min_size` bytes from.
+    pub fn check(&self, magic: u32, version: u16, min_size: usize) -> Result<(), BootInfoError> {
+        if self.magic != magic {
+            return Err(BootInfoError::BadMagic {
+                expected: magic,
+                found: self.magic,
+            });
+        }
+
+        if self.version != version {
+            return Err(BootInfoError::UnsupportedVersion {
+                expected: version,
+                found: self.version,
+            });
+        }
+
+        if (self.total_size as usize) < min_size {
+            return Err(BootInfoError::TooSmall {
+                expected: min_size as u32,
+                found: self.total_size,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// An info block that is passed between stages.
+pub trait BootInfo: Sized {
+    const MAGIC: u32;
+    /// Bumped on any change to the block, other than adding fields to its end.
+    const VERSION: u16;
+    /// The size of the block when [`Self::VERSION`] was introduced.
+    ///
+    /// This only changes along with the version, so blocks made by stages built before a
+    /// field was appended are still accepted. Once a field is appended, this becomes the
+    /// `offset_of!` that field instead of the block's size.
+    const MIN_SIZE: usize;
+
+    fn header(&self) -> &BootInfoHeader;
+
+    /// Make the header a stage should give this block.
+    fn new_header() -> BootInfoHeader {
+        BootInfoHeader::new(Self::MAGIC, Self::VERSION, size_of::<Self>())
+    }
+
+    /// Check this block's header was made for this version of the block.
+    fn validate(&self) -> Result<(), BootInfoError> {
+        self.header()
+            .check(Self::MAGIC, Self::VERSION, Self::MIN_SIZE)
+    }
+
+    /// Get the block at `ptr`, only reading the rest of it once its header was checked.
+    ///
+    /// # Safety
+    /// `ptr` must point to at least a readable [`BootInfoHeader`], and to `size_of::<Self>()`
+    /// readable bytes if the header is valid, even if the block is shorter.
+    unsafe fn from_ptr<'a>(ptr: *const Self) -> Result<&'a Self, BootInfoError> {
+        let header = unsafe { &*ptr.cast::<BootInfoHeader>() };
+        header.check(Self::MAGIC, Self::VERSION, Self::MIN_SIZE)?;
+
+        Ok(unsafe { &*ptr })
+    }
+
+    /// Read `field`, a field of this block past [`Self::MIN_SIZE`], if the stage that made
+    /// the block knew about it.
+    fn appended<T: Copy>(&self, field: &T) -> Option<T> {
+        let offset = (field as *const T).addr() - (self as *const Self).addr();
+        debug_assert!(offset + size_of::<T>() <= size_of::<Self>());
+
+        (offset + size_of::<T>() <= self.header().total_size as usize).then_some(*field)
+    }
+
+    /// Iterate over the tags attached to this block.
+    ///
+    /// # Safety
+    /// The tags must still be in accessible memory.
+    unsafe fn tags(&self) -> BootTags<'_> {
+        BootTags {
+            next: self.header().extensions as usize as *const BootTag,
+            _lifetime: core::marker::PhantomData,
+        }
+    }
+}
+
+/// # Boot Tag
+/// The start of an optional entry attached to an info block, followed by its payload.
+///
+/// Tags are placed one after another, each starting on an 8 byte boundary, until a tag of
+/// kind [`BOOT_TAG_END`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct BootTag {
+    pub kind: u32,
+    /// The size of the tag, including this header.
+    pub size: u32,
+}
+
+/// Iterates over the `(kind, payload)` of each tag in a list.
+pub struct BootTags<'a> {
+    next: *const BootTag,
+    _lifetime: core::marker::PhantomData<&'a [u8]>,
+}
+
+impl<'a> Iterator for BootTags<'a> {
+    type Item = (u32, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+
+        let tag = unsafe { *self.next };
+        if tag.kind == BOOT_TAG_END || (tag.size as usize) < size_of::<BootTag>() {
+            self.next = core::ptr::null();
+            return None;
+        }
+
+        let payload = unsafe {
+            core::slice::from_raw_parts(
+                self.next.cast::<u8>().add(size_of::<BootTag>()),
+                tag.size as usize - size_of::<BootTag>(),
+            )
+        };
+        self.next = unsafe {
+            self.next
+                .cast::<u8>()
+                .add((tag.size as usize).next_multiple_of(8))
+                .cast()
+        };
+
+        Some((tag.kind, payload))
+    }
+}
+
+/// The tag buffer is too small for another tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfTagSpace;
+
+/// Builds a list of tags inside of a buffer.
+pub struct BootTagWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> BootTagWriter<'a> {
+    /// Write tags into `buffer`, which must be 8 byte aligned.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        debug_assert!(buffer.as_ptr().align_offset(8) == 0);
+        Self { buffer, len: 0 }
+    }
+
+    /// Add a tag with `payload`.
+    pub fn push(&mut self, kind: u32, payload: &[u8]) -> Result<(), OutOfTagSpace> {
+        let size = size_of::<BootTag>() + payload.len();
+        let end = self.len + size.next_multiple_of(8);
+
+        // Always leave room for the end tag
+        if end + size_of::<BootTag>() > self.buffer.len() {
+            return Err(OutOfTagSpace);
+        }
+
+        self.write_header(kind, size);
+        self.buffer[self.len + size_of::<BootTag>()..self.len + size].copy_from_slice(payload);
+        self.len = end;
+
+        Ok(())
+    }
+
+    /// End the list, returning the address to put in [`BootInfoHeader::extensions`].
+    pub fn finish(mut self) -> u64 {
+        self.write_header(BOOT_TAG_END, size_of::<BootTag>());
+        self.buffer.as_ptr() as usize as u64
+    }
+
+    fn write_header(&mut self, kind: u32, size: usize) {
+        self.buffer[self.len..self.len + 4].copy_from_slice(&kind.to_ne_bytes());
+        self.buffer[self.len + 4..self.len + 8].copy_from_slice(&(size as u32).to_ne_bytes());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[repr(C)]
+    struct TestBlock {
+        header: BootInfoHeader,
+        value: u64,
+    }
+
+    impl BootInfo for TestBlock {
+        const MAGIC: u32 = u32::from_le_bytes(*b"TEST");
+        const VERSION: u16 = 2;
+        const MIN_SIZE: usize = size_of::<Self>();
+
+        fn header(&self) -> &BootInfoHeader {
+            &self.header
+        }
+    }
+
+    /// `TestBlock` after a field was appended to it
+    #[repr(C)]
+    struct NewTestBlock {
+        header: BootInfoHeader,
+        value: u64,
+        appended: u64,
+    }
+
+    impl BootInfo for NewTestBlock {
+        const MAGIC: u32 = TestBlock::MAGIC;
+        const VERSION: u16 = TestBlock::VERSION;
+        const MIN_SIZE: usize = TestBlock::MIN_SIZE;
+
+        fn header(&self) -> &BootInfoHeader {
+            &self.header
+        }
+    }
+
+    #[test]
+    fn test_old_block_new_reader() {
+        let old = NewTestBlock {
+            header: TestBlock::new_header(),
+            value: 42,
+            appended: 0xdead,
+        };
+        let block = unsafe { NewTestBlock::from_ptr(&old) }.unwrap();
+        assert_eq!(block.value, 42);
+        assert_eq!(block.appended(&block.appended), None);
+
+        let new = NewTestBlock {
+            header: NewTestBlock::new_header(),
+            value: 42,
+            appended: 7,
+        };
+        let block = unsafe { NewTestBlock::from_ptr(&new) }.unwrap();
+        assert_eq!(block.appended(&block.appended), Some(7));
+    }
+
+    #[test]
+    fn test_new_block_old_reader() {
+        let new = NewTestBlock {
+            header: NewTestBlock::new_header(),
+            value: 42,
+            appended: 7,
+        };
+        let block = unsafe { TestBlock::from_ptr((&raw const new).cast()) }.unwrap();
+        assert_eq!(block.value, 42);
+    }
+
+    #[test]
+    fn test_header_checks() {
+        let block = TestBlock {
+            header: TestBlock::new_header(),
+            value: 42,
+        };
+        assert_eq!(unsafe { TestBlock::from_ptr(&block) }.unwrap().value, 42);
+
+        let old = TestBlock {
+            header: BootInfoHeader::new(TestBlock::MAGIC, 1, size_of::<TestBlock>()),
+            value: 0,
+        };
+        assert!(matches!(
+            unsafe { TestBlock::from_ptr(&old) },
+            Err(BootInfoError::UnsupportedVersion {
+                expected: 2,
+                found: 1
+            })
+        ));
+
+        let short = TestBlock {
+            header: BootInfoHeader::new(TestBlock::MAGIC, 2, size_of::<BootInfoHeader>()),
+            value: 0,
+        };
+        assert!(matches!(
+            unsafe { TestBlock::from_ptr(&short) },
+            Err(BootInfoError::TooSmall { .. })
+        ));
+
+        let garbage = TestBlock {
+            header: BootInfoHeader::new(0, 2, size_of::<TestBlock>()),
+            value: 0,
+        };
+        assert!(matches!(
+            unsafe { TestBlock::from_ptr(&garbage) },
+            Err(BootInfoError::BadMagic { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tags_round_trip() {
+        let mut buffer = [0u64; 8];
+        let buffer = unsafe {
+            core::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), buffer.len() * 8)
+        };
+
+        let mut writer = BootTagWriter::new(buffer);
+        writer.push(BOOT_TAG_BOOTLOADER_NAME, b"quantum").unwrap();
+        writer.push(77, &[1, 2, 3]).unwrap();
+        let extensions = writer.finish();
+
+        let block = TestBlock {
+            header: TestBlock::new_header().with_extensions(extensions),
+            value: 0,
+        };
+        let mut tags = unsafe { block.tags() };
+        assert_eq!(
+            tags.next(),
+            Some((BOOT_TAG_BOOTLOADER_NAME, &b"quantum"[..]))
+        );
+        assert_eq!(tags.next(), Some((77, &[1, 2, 3][..])));
+        assert_eq!(tags.next(), None);
+    }
+
+    #[test]
+    fn test_tags_out_of_space() {
+        let mut buffer = [0u64; 2];
+        let buffer = unsafe {
+            core::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), buffer.len() * 8)
+        };
+
+        let mut writer = BootTagWriter::new(buffer);
+        assert_eq!(writer.push(1, &[0; 16]), Err(OutOfTagSpace));
+        assert_eq!(writer.push(1, &[]), Ok(()));
+    }
+}
@@ -0,0 +1,194 @@
+/*
+  ____                 __               __                __
+ / __ \__ _____ ____  / /___ ____ _    / /  ___  ___ ____/ /__ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ _ \/ _ `/ _  / -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/\___/\_,_/\_,_/\__/_/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use core::fmt::Display;
+
+/// A simple allocator that only ever moves forward, used while loading the
+/// bootloader stages.
+pub struct BumpAlloc {
+    start: u64,
+    current_ptr: u64,
+    end: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BumpAllocError {
+    /// The new pointer is past the end of the allocation area.
+    PastEnd { requested: u64, end: u64 },
+    /// The new pointer is behind the current pointer, and would hand out memory that
+    /// was already allocated.
+    Overlapping { requested: u64, current: u64 },
+}
+
+impl Display for BumpAllocError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::PastEnd { requested, end } => write!(
+                f,
+                "Cannot move to {requested:#010x}, allocation area ends at {end:#010x}"
+            ),
+            Self::Overlapping { requested, current } => write!(
+                f,
+                "Cannot move back to {requested:#010x}, memory up to {current:#010x} is already allocated"
+            ),
+        }
+    }
+}
+
+impl BumpAlloc {
+    pub unsafe fn new(current_ptr: u64, size: u64) -> Self {
+        Self {
+            start: current_ptr,
+            current_ptr,
+            end: current_ptr + size,
+        }
+    }
+
+    pub unsafe fn allocate(&mut self, size: usize) -> Option<&'static mut [u8]> {
+        let bumped_ptr = self.current_ptr + (size as u64);
+        if bumped_ptr > self.end {
+            return None;
+        }
+
+        let allocation_start = self.current_ptr;
+        self.current_ptr = bumped_ptr;
+
+        Some(core::slice::from_raw_parts_mut(
+            allocation_start as *mut u8,
+            size,
+        ))
+    }
+
+    /// Allocate `size` bytes starting at a multiple of `alignment`.
+    ///
+    /// The pointer is only moved if the allocation fits, so a failed allocation does not
+    /// waste the padding.
+    pub unsafe fn allocate_aligned(
+        &mut self,
+        size: usize,
+        alignment: usize,
+    ) -> Option<&'static mut [u8]> {
+        let previous_ptr = self.current_ptr;
+        self.align_ptr_to(alignment);
+
+        let allocation = self.allocate(size);
+        if allocation.is_none() {
+            self.current_ptr = previous_ptr;
+        }
+
+        allocation
+    }
+
+    /// Get the address of the next allocation.
+    pub const fn current_ptr(&self) -> u64 {
+        self.current_ptr
+    }
+
+    /// How many bytes have been handed out, including alignment padding.
+    pub const fn used(&self) -> u64 {
+        self.current_ptr - self.start
+    }
+
+    /// How many bytes are left before the end of the allocation area.
+    pub const fn remaining(&self) -> u64 {
+        self.end - self.current_ptr
+    }
+
+    /// Move the next allocation to `new_ptr`, skipping the memory in between.
+    ///
+    /// This is used to place things at a fixed address, so it is an error for that
+    /// address to already be allocated.
+    pub fn push_ptr_to(&mut self, new_ptr: *mut u8) -> Result<(), BumpAllocError> {
+        let requested = new_ptr as u64;
+
+        if requested > self.end {
+            return Err(BumpAllocError::PastEnd {
+                requested,
+                end: self.end,
+            });
+        }
+
+        if requested < self.current_ptr {
+            return Err(BumpAllocError::Overlapping {
+                requested,
+                current: self.current_ptr,
+            });
+        }
+
+        self.current_ptr = requested;
+        Ok(())
+    }
+
+    pub fn align_ptr_to(&mut self, alignment: usize) {
+        self.current_ptr = self.current_ptr.next_multiple_of(alignment as u64);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_allocate_aligned() {
+        let mut area = [0u8; 256];
+        let start = area.as_mut_ptr() as u64;
+        let mut alloc = unsafe { BumpAlloc::new(start, 256) };
+
+        unsafe { alloc.allocate(3) }.unwrap();
+        let aligned = unsafe { alloc.allocate_aligned(16, 64) }.unwrap();
+
+        assert_eq!(aligned.as_ptr() as u64 % 64, 0);
+        assert_eq!(alloc.used() + alloc.remaining(), 256);
+
+        // A failed allocation should not move the pointer
+        let used = alloc.used();
+        assert!(unsafe { alloc.allocate_aligned(1024, 64) }.is_none());
+        assert_eq!(alloc.used(), used);
+    }
+
+    #[test]
+    fn test_push_ptr_to() {
+        let mut area = [0u8; 256];
+        let start = area.as_mut_ptr();
+        let mut alloc = unsafe { BumpAlloc::new(start as u64, 256) };
+
+        unsafe { alloc.allocate(32) }.unwrap();
+
+        assert_eq!(
+            alloc.push_ptr_to(unsafe { start.add(16) }),
+            Err(BumpAllocError::Overlapping {
+                requested: start as u64 + 16,
+                current: start as u64 + 32
+            })
+        );
+        assert!(matches!(
+            alloc.push_ptr_to(unsafe { start.add(512) }),
+            Err(BumpAllocError::PastEnd { .. })
+        ));
+
+        alloc.push_ptr_to(unsafe { start.add(128) }).unwrap();
+        assert_eq!(alloc.used(), 128);
+    }
+}
@@ -0,0 +1,121 @@
+/*
+  ____                 __               __                __
+ / __ \__ _____ ____  / /___ ____ _    / /  ___  ___ ____/ /__ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ _ \/ _ `/ _  / -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/\___/\_,_/\_,_/\__/_/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+/// Max number of bytes in the kernel command line, the rest is dropped.
+pub const MAX_CMDLINE_LEN: usize = 128;
+
+/// # Kernel Command Line
+/// Whitespace separated options for the kernel, either a flag (`debugcon`) or a
+/// value (`log=warn`).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct KernelCmdline {
+    bytes: [u8; MAX_CMDLINE_LEN],
+    len: usize,
+}
+
+impl KernelCmdline {
+    /// A command line without any options
+    pub const fn empty() -> Self {
+        Self {
+            bytes: [0; MAX_CMDLINE_LEN],
+            len: 0,
+        }
+    }
+
+    /// Copy `cmdline` in, truncating it to [`MAX_CMDLINE_LEN`] bytes.
+    pub fn new(cmdline: &str) -> Self {
+        let mut len = cmdline.len().min(MAX_CMDLINE_LEN);
+        while !cmdline.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut bytes = [0; MAX_CMDLINE_LEN];
+        bytes[..len].copy_from_slice(&cmdline.as_bytes()[..len]);
+
+        Self { bytes, len }
+    }
+
+    /// The full command line
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len.min(MAX_CMDLINE_LEN)]).unwrap_or("")
+    }
+
+    /// Each option in the command line
+    pub fn options(&self) -> impl Iterator<Item = &str> {
+        self.as_str().split_whitespace()
+    }
+
+    /// Is the flag `name` set?
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.options().any(|option| option == name)
+    }
+
+    /// The value of the option `name`, if it was given one
+    pub fn value_of(&self, name: &str) -> Option<&str> {
+        self.options().find_map(|option| {
+            option
+                .split_once('=')
+                .and_then(|(key, value)| (key == name).then_some(value))
+        })
+    }
+}
+
+impl Default for KernelCmdline {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl core::fmt::Debug for KernelCmdline {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("KernelCmdline")
+            .field(&self.as_str())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cmdline_options() {
+        let cmdline = KernelCmdline::new("debugcon  log=warn");
+
+        assert!(cmdline.has_flag("debugcon"));
+        assert!(!cmdline.has_flag("log"));
+        assert_eq!(cmdline.value_of("log"), Some("warn"));
+        assert_eq!(cmdline.value_of("debugcon"), None);
+        assert_eq!(KernelCmdline::empty().options().count(), 0);
+    }
+
+    #[test]
+    fn test_cmdline_truncates() {
+        let long = [b'a'; MAX_CMDLINE_LEN + 10];
+        let long = core::str::from_utf8(&long).unwrap();
+        assert_eq!(KernelCmdline::new(long).as_str().len(), MAX_CMDLINE_LEN);
+    }
+}
@@ -25,58 +25,179 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 #![no_std]
 
-use bios::{
-    memory::MemoryEntry,
-    video::{VesaMode, VesaModeId},
+use bios::memory::MemoryEntry;
+use boot_info::{
+    BootInfo, BootInfoHeader, KERNEL_BOOT_HEADER_MAGIC, STAGE16_TO_STAGE32_MAGIC,
+    STAGE32_TO_STAGE64_MAGIC,
 };
+use cmdline::KernelCmdline;
 use mem::phys::PhysMemoryMap;
+use progress::BootMode;
+use video::VideoInformation;
+
+pub mod boot_info;
+pub mod bump_alloc;
+pub mod cmdline;
+pub mod progress;
+pub mod video;
 
 /// Amount of regions contained in the inital phys memory map.
 pub const MEMORY_REGIONS: usize = 64;
 
 /// Kernel fn ptr
+///
+/// # Entry Contract
+/// Stage64 enters the kernel at its ELF entry point in long mode with:
+///  - `rdi` holding a pointer to the [`KernelBootHeader`].
+///  - `rsp` set to the top of the kernel's stack, aligned as if the entry was `call`ed
+///    (`rsp + 8` is 16 byte aligned).
+///  - `rbp` zeroed, so stack traces end at the kernel's entry.
+///  - `cs` as GDT entry 1 and all data segments as GDT entry 2, both in ring0.
+///  - Interrupts disabled.
+///  - The kernel's exe, stack, heap, and initfs mapped to the regions given in the
+///    [`KernelBootHeader`], and the first 1Gib of physical memory identity mapped.
 pub type KernelEntryFn = extern "C" fn(u64) -> !;
 
 /// # Max Memory Map Entries
 /// This is the max number of entries that can fit in the Stage-to-Stage info block.
 ///
 /// ONLY USED FOR `MemoryEntry`!
-pub const MAX_MEMORY_MAP_ENTRIES: usize = 16;
+pub const MAX_MEMORY_MAP_ENTRIES: usize = 20;
 
 /// # `Stage16` to `Stage32` Info Block
 /// Used for sending data between these stages.
-#[repr(C)]
+///
+/// Aligned to 8 bytes so its size is the same for the 16bit and 32bit stages.
+#[repr(C, align(8))]
 pub struct Stage16toStage32 {
+    pub header: BootInfoHeader,
     pub bootloader_stack_ptr: (u64, u64),
     pub stage32_ptr: (u64, u64),
     pub stage64_ptr: (u64, u64),
     pub kernel_ptr: (u64, u64),
     pub initfs_ptr: (u64, u64),
     pub memory_map: [MemoryEntry; MAX_MEMORY_MAP_ENTRIES],
-    pub video_mode: Option<(VesaModeId, VesaMode)>,
+    pub video: Option<VideoInformation>,
+    pub checksums: BootChecksums,
+    pub boot_mode: BootMode,
+    pub cmdline: KernelCmdline,
 }
 
 /// # `Stage32` to `Stage64` Info Block
 /// Used for sending data between these stages.
-#[repr(C)]
+///
+/// Aligned to 8 bytes so its size is the same for the 32bit and 64bit stages.
+#[repr(C, align(8))]
 pub struct Stage32toStage64 {
+    pub header: BootInfoHeader,
     pub bootloader_stack_ptr: (u64, u64),
     pub stage32_ptr: (u64, u64),
     pub stage64_ptr: (u64, u64),
     pub kernel_ptr: (u64, u64),
     pub initfs_ptr: (u64, u64),
+    pub page_tables_ptr: (u64, u64),
     pub memory_map: [MemoryEntry; MAX_MEMORY_MAP_ENTRIES],
-    pub video_mode: Option<(VesaModeId, VesaMode)>,
+    pub video: Option<VideoInformation>,
+    pub checksums: BootChecksums,
+    pub boot_mode: BootMode,
+    pub cmdline: KernelCmdline,
+}
+
+/// # Boot Artifact Checksums
+/// The expected CRC32 of each artifact loaded by stage16, so that each stage can verify
+/// the next before jumping into it.
+///
+/// A checksum is `None` when the config did not provide one.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BootChecksums {
+    pub stage64: Option<u32>,
+    pub kernel: Option<u32>,
+    pub initfs: Option<u32>,
+}
+
+/// A loaded artifact did not match its expected checksum.
+#[derive(Debug, Clone, Copy)]
+pub struct ChecksumMismatch {
+    pub expected: u32,
+    pub found: u32,
+}
+
+impl core::fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "expected crc32 {:#010x}, but found {:#010x}",
+            self.expected, self.found
+        )
+    }
+}
+
+/// Verify the artifact loaded at `(ptr, len)` against its `expected` checksum.
+///
+/// # Safety
+/// `(ptr, len)` must be memory that is currently accessible.
+pub unsafe fn verify_artifact(
+    artifact: (u64, u64),
+    expected: Option<u32>,
+) -> Result<(), ChecksumMismatch> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+
+    let bytes =
+        unsafe { core::slice::from_raw_parts(artifact.0 as *const u8, artifact.1 as usize) };
+    let found = util::crc32::crc32(bytes);
+
+    if found == expected {
+        Ok(())
+    } else {
+        Err(ChecksumMismatch { expected, found })
+    }
 }
 
 /// # `Stage64` to `Kernel` Info Block
+#[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct KernelBootHeader {
+    pub header: BootInfoHeader,
     pub phys_mem_map: &'static PhysMemoryMap<MEMORY_REGIONS>,
-    pub video_mode: Option<(VesaModeId, VesaMode)>,
+    pub video: Option<VideoInformation>,
     pub kernel_elf: (u64, usize),
     pub kernel_exe: (u64, usize),
     pub kernel_stack: (u64, usize),
     pub kernel_init_heap: (u64, usize),
     pub initfs_ptr: (u64, usize),
+    pub boot_mode: BootMode,
+    pub cmdline: KernelCmdline,
+}
+
+impl BootInfo for Stage16toStage32 {
+    const MAGIC: u32 = STAGE16_TO_STAGE32_MAGIC;
+    const VERSION: u16 = 2;
+    const MIN_SIZE: usize = size_of::<Self>();
+
+    fn header(&self) -> &BootInfoHeader {
+        &self.header
+    }
+}
+
+impl BootInfo for Stage32toStage64 {
+    const MAGIC: u32 = STAGE32_TO_STAGE64_MAGIC;
+    const VERSION: u16 = 2;
+    const MIN_SIZE: usize = size_of::<Self>();
+
+    fn header(&self) -> &BootInfoHeader {
+        &self.header
+    }
+}
+
+impl BootInfo for KernelBootHeader {
+    const MAGIC: u32 = KERNEL_BOOT_HEADER_MAGIC;
+    const VERSION: u16 = 2;
+    const MIN_SIZE: usize = size_of::<Self>();
+
+    fn header(&self) -> &BootInfoHeader {
+        &self.header
+    }
 }
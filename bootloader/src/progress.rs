@@ -0,0 +1,204 @@
+/*
+  ____                 __               __                __
+ / __ \__ _____ ____  / /___ ____ _    / /  ___  ___ ____/ /__ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ _ \/ _ `/ _  / -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/\___/\_,_/\_,_/\__/_/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::video::VideoInformation;
+use bootgfx::{image::Image, Color, Framebuffer};
+
+/// # Boot Mode
+/// How boot progress is shown on the screen.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BootMode {
+    /// Write each boot stage as a line of text
+    #[default]
+    Verbose,
+    /// Draw a splash screen with a progress bar
+    Splash,
+}
+
+impl BootMode {
+    /// Parse the `boot-mode` config option
+    pub fn parse(option: &str) -> Option<Self> {
+        match option {
+            "verbose" => Some(Self::Verbose),
+            "splash" => Some(Self::Splash),
+            _ => None,
+        }
+    }
+}
+
+/// # Boot Stage
+/// The stages of boot, in the order they are reported.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BootStage {
+    Stage32,
+    Decompress,
+    Stage64,
+    KernelEntry,
+    Memory,
+    Scheduler,
+    Userspace,
+}
+
+impl BootStage {
+    /// The last stage of boot
+    pub const LAST: Self = Self::Userspace;
+
+    /// How far through the boot this stage is, in percent
+    pub const fn percent(self) -> usize {
+        (self as usize + 1) * 100 / (Self::LAST as usize + 1)
+    }
+
+    /// A short description of this stage
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Stage32 => "Entering stage32",
+            Self::Decompress => "Decompressing kernel and initfs",
+            Self::Stage64 => "Entering stage64",
+            Self::KernelEntry => "Entering kernel",
+            Self::Memory => "Initializing memory",
+            Self::Scheduler => "Starting scheduler",
+            Self::Userspace => "Starting userspace",
+        }
+    }
+}
+
+/// # Boot Screen
+/// Reports boot progress onto the framebuffer, each stage of boot creates its own
+/// `BootScreen` since the framebuffer is mapped differently in each.
+pub struct BootScreen {
+    framebuffer: Framebuffer,
+    mode: BootMode,
+}
+
+impl BootScreen {
+    const BAR_LENGTH: usize = 300;
+    const BAR_HEIGHT: usize = 12;
+    const LINE_HEIGHT: usize = 10;
+
+    /// # New
+    /// Create a boot screen for the framebuffer at `framebuffer_ptr`.
+    ///
+    /// # Safety
+    /// `framebuffer_ptr` must point to the framebuffer described by `video`.
+    pub unsafe fn new(framebuffer_ptr: *mut u8, video: &VideoInformation, mode: BootMode) -> Self {
+        Self {
+            framebuffer: Framebuffer::new(
+                framebuffer_ptr,
+                video.width as usize,
+                video.height as usize,
+                video.pitch as usize,
+                video.format,
+            ),
+            mode,
+        }
+    }
+
+    /// # Clear
+    /// Fill the screen with the background color.
+    pub fn clear(&mut self) {
+        let (width, height) = (self.framebuffer.width(), self.framebuffer.height());
+        self.framebuffer
+            .draw_rec(0, 0, width, height, Color::QUANTUM_BACKGROUND);
+    }
+
+    /// # Report
+    /// Show that boot has reached `stage`.
+    pub fn report(&mut self, stage: BootStage) {
+        match self.mode {
+            BootMode::Verbose => {
+                let y = 10 + stage as usize * Self::LINE_HEIGHT;
+
+                self.framebuffer.draw_str(10, y, "[    ]", Color::WHITE);
+                self.draw_percent(10 + 8, y, stage.percent());
+                self.framebuffer
+                    .draw_str(10 + 56, y, stage.name(), Color::WHITE);
+            }
+            BootMode::Splash => {
+                let x = self.framebuffer.width().saturating_sub(Self::BAR_LENGTH) / 2;
+                let y = (self.framebuffer.height() * 3) / 4;
+
+                self.framebuffer.draw_progress_bar(
+                    x,
+                    y,
+                    Self::BAR_LENGTH,
+                    Self::BAR_HEIGHT,
+                    stage.percent(),
+                    Color::ALOE_GREEN,
+                    Color::DARK_GREY,
+                );
+            }
+        }
+    }
+
+    /// # Show Splash
+    /// Draw the splash image in the center of the screen, does nothing in verbose mode.
+    pub fn show_splash(&mut self, image: &Image) {
+        if self.mode != BootMode::Splash {
+            return;
+        }
+
+        let x = self.framebuffer.width().saturating_sub(image.width()) / 2;
+        let y = (self.framebuffer.height() / 2).saturating_sub(image.height() / 2);
+        self.framebuffer.draw_image(x, y, image);
+    }
+
+    /// The framebuffer this screen is drawing into
+    pub const fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// The boot mode this screen is drawing in
+    pub const fn mode(&self) -> BootMode {
+        self.mode
+    }
+
+    /// Draw `percent` right aligned in 4 characters
+    fn draw_percent(&mut self, x: usize, y: usize, percent: usize) {
+        let digits = [percent / 100, (percent / 10) % 10, percent % 10];
+        let first_digit = digits.iter().position(|&d| d != 0).unwrap_or(2);
+
+        for (i, digit) in digits.iter().enumerate().skip(first_digit) {
+            let c = char::from_digit(*digit as u32, 10).unwrap_or('?');
+            self.framebuffer
+                .draw_glyph(x + i * 8, y, c, Color::ALOE_GREEN);
+        }
+        self.framebuffer
+            .draw_glyph(x + 24, y, '%', Color::ALOE_GREEN);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stage_percent() {
+        assert_eq!(BootStage::Stage32.percent(), 14);
+        assert_eq!(BootStage::Memory.percent(), 71);
+        assert_eq!(BootStage::LAST.percent(), 100);
+    }
+}
@@ -0,0 +1,93 @@
+/*
+  ____                 __               __                __
+ / __ \__ _____ ____  / /___ ____ _    / /  ___  ___ ____/ /__ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ _ \/ _ `/ _  / -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/\___/\_,_/\_,_/\__/_/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use bios::video::{VesaMode, VesaModeId};
+use bootgfx::{Channel, PixelFormat};
+
+/// # Video Information
+/// The linear framebuffer the bootloader set up, and how its pixels are laid out.
+///
+/// Aligned to 8 bytes so its layout is the same for every stage.
+#[repr(C, align(8))]
+#[derive(Debug, Clone, Copy)]
+pub struct VideoInformation {
+    /// The physical address of the framebuffer
+    pub phys_addr: u64,
+    /// Where the framebuffer is mapped in the current stage's page tables.
+    ///
+    /// The bootloader identity maps it, the kernel must map it again itself.
+    pub virt_addr: u64,
+    pub width: u32,
+    pub height: u32,
+    /// The number of bytes between the start of each row, this can be more than
+    /// `width * bytes_per_pixel`
+    pub pitch: u32,
+    pub format: PixelFormat,
+    /// The vesa mode that was set
+    pub mode_id: VesaModeId,
+}
+
+impl VideoInformation {
+    /// The `attributes` bit set when the mode has a linear framebuffer
+    const VESA_LINEAR_FRAMEBUFFER: u16 = 1 << 7;
+
+    /// # From Vesa
+    /// Describe the framebuffer of the vesa mode `mode`, if we know how to draw into it.
+    pub fn from_vesa(mode_id: VesaModeId, mode: &VesaMode) -> Option<Self> {
+        if mode.attributes & Self::VESA_LINEAR_FRAMEBUFFER == 0 || mode.framebuffer == 0 {
+            return None;
+        }
+
+        let format = match (mode.bpp, mode.red_mask, mode.green_mask, mode.blue_mask) {
+            // Some BIOSes leave the masks empty for the common formats
+            (32, 0, 0, 0) => PixelFormat::XRGB8888,
+            (24, 0, 0, 0) => PixelFormat::RGB888,
+            (_, 0, _, _) | (_, _, 0, _) | (_, _, _, 0) => return None,
+            (bits_per_pixel @ 8..=32, ..) => PixelFormat {
+                bits_per_pixel,
+                red: Channel::new(mode.red_pos, mode.red_mask),
+                green: Channel::new(mode.green_pos, mode.green_mask),
+                blue: Channel::new(mode.blue_pos, mode.blue_mask),
+            },
+            _ => return None,
+        };
+
+        Some(Self {
+            phys_addr: mode.framebuffer as u64,
+            virt_addr: mode.framebuffer as u64,
+            width: mode.width as u32,
+            height: mode.height as u32,
+            pitch: mode.pitch as u32,
+            format,
+            mode_id,
+        })
+    }
+
+    /// # Size
+    /// The number of bytes the framebuffer takes up.
+    pub const fn size(&self) -> usize {
+        self.pitch as usize * self.height as usize
+    }
+}
@@ -23,47 +23,33 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-pub struct BumpAlloc {
-    current_ptr: u64,
-    end: u64,
-}
-
-impl BumpAlloc {
-    pub unsafe fn new(current_ptr: u64, size: u64) -> Self {
-        Self {
-            current_ptr,
-            end: current_ptr + size,
-        }
-    }
-
-    pub unsafe fn allocate(&mut self, size: usize) -> Option<&'static mut [u8]> {
-        let bumped_ptr = self.current_ptr + (size as u64);
-        if bumped_ptr > self.end {
-            return None;
-        }
+use arch::rtc::read_rtc;
+use bios::timer;
+use core::time::Duration;
 
-        let allocation_start = self.current_ptr;
-        self.current_ptr = bumped_ptr;
+static mut BOOT_TICKS: u32 = 0;
+static mut BOOT_UNIX_TIME: u64 = 0;
 
-        Some(core::slice::from_raw_parts_mut(
-            allocation_start as *mut u8,
-            size,
-        ))
-    }
+/// Start the boot clock, and attach it to the debug output's timestamps.
+pub fn init() {
+    unsafe { BOOT_TICKS = timer::ticks() };
+    lignan::stream::set_timestamp_fn(since_boot);
+}
 
-    pub fn push_ptr_to(&mut self, new_ptr: *mut u8) {
-        if new_ptr as u64 > self.end {
-            panic!("Cannot push ptr past end of allocation area!");
-        }
+/// Read the RTC, and attach it to the debug output's wall time.
+pub fn init_wall_clock() {
+    let rtc = read_rtc();
+    unsafe { BOOT_UNIX_TIME = rtc.to_unix().saturating_sub(since_boot().as_secs()) };
+    lignan::stream::set_wall_clock_fn(now_unix);
+}
 
-        self.current_ptr = new_ptr as u64;
-    }
+/// The time since the loader started
+pub fn since_boot() -> Duration {
+    timer::elapsed_since(unsafe { BOOT_TICKS })
+}
 
-    pub fn align_ptr_to(&mut self, alignment: usize) {
-        unsafe {
-            self.current_ptr = (self.current_ptr as *const u8)
-                .add((self.current_ptr as *const u8).align_offset(alignment))
-                as u64
-        };
-    }
+/// Seconds since the unix epoch
+pub fn now_unix() -> u64 {
+    let boot_unix_time = unsafe { BOOT_UNIX_TIME };
+    boot_unix_time + since_boot().as_secs()
 }
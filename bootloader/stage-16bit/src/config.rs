@@ -23,6 +23,8 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
+use bootloader::progress::BootMode;
+
 #[repr(C)]
 #[derive(Default)]
 pub struct BootloaderConfig<'a> {
@@ -31,6 +33,12 @@ pub struct BootloaderConfig<'a> {
     pub kernel: &'a str,
     pub expected_vbe_mode: Option<(u16, u16)>,
     pub initfs: &'a str,
+    pub bootloader32_crc32: Option<u32>,
+    pub bootloader64_crc32: Option<u32>,
+    pub kernel_crc32: Option<u32>,
+    pub initfs_crc32: Option<u32>,
+    pub boot_mode: BootMode,
+    pub cmdline: &'a str,
 }
 
 impl<'a> BootloaderConfig<'a> {
@@ -40,19 +48,23 @@ impl<'a> BootloaderConfig<'a> {
         for (first_option, second_option) in file
             .split('\n')
             .filter(|line| !line.is_empty() && line.is_ascii())
-            .filter_map(|line| {
-                let mut option_split = line.split('=');
-                match (option_split.next(), option_split.next()) {
-                    (Some(first_str), Some(second_str)) => Some((first_str, second_str)),
-                    _ => None,
-                }
-            })
+            .filter_map(|line| line.split_once('='))
         {
             match first_option {
                 "bootloader32" => config.bootloader32 = second_option,
                 "bootloader64" => config.bootloader64 = second_option,
                 "kernel" => config.kernel = second_option,
                 "initfs" => config.initfs = second_option,
+                "bootloader32-crc32" => config.bootloader32_crc32 = parse_crc32(second_option),
+                "bootloader64-crc32" => config.bootloader64_crc32 = parse_crc32(second_option),
+                "kernel-crc32" => config.kernel_crc32 = parse_crc32(second_option),
+                "initfs-crc32" => config.initfs_crc32 = parse_crc32(second_option),
+                "cmdline" => config.cmdline = second_option.trim(),
+                "boot-mode" => {
+                    if let Some(boot_mode) = BootMode::parse(second_option) {
+                        config.boot_mode = boot_mode;
+                    }
+                }
                 "vbe-mode" => {
                     let mut info_split = second_option.split('x');
                     let (horz_str, vert_str) = (
@@ -74,3 +86,7 @@ impl<'a> BootloaderConfig<'a> {
         Some(config)
     }
 }
+
+fn parse_crc32(option: &str) -> Option<u32> {
+    u32::from_str_radix(option.trim_start_matches("0x"), 16).ok()
+}
@@ -26,27 +26,34 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 #![no_std]
 #![no_main]
 
-use crate::{disk::BiosDisk, mbr::Mbr};
-use bios::memory::MemoryEntry;
+use crate::disk::BiosDisk;
+use bios::memory::free_region_containing;
 use bios::video::Vesa;
-use bootloader::Stage16toStage32;
-use bump_alloc::BumpAlloc;
+use bootloader::bump_alloc::BumpAlloc;
+use bootloader::{
+    boot_info::BootInfo, cmdline::KernelCmdline, verify_artifact, video::VideoInformation,
+    BootChecksums, Stage16toStage32,
+};
 use config::BootloaderConfig;
 use fs::fatfs::Fat;
 use fs::io::Read;
+use fs::partition::Partition;
 use lignan::make_debug;
-use lignan::{debug_ready, logln};
+use lignan::{debug_ready, logln, warnln};
 use serial::Serial;
 use unreal::enter_unreal;
+use util::bytes::HumanBytes;
 
-mod bump_alloc;
+mod clock;
 mod config;
 mod disk;
-mod mbr;
 mod memory;
 mod panic;
 mod unreal;
 
+/// Where stage32 is linked to run from
+const STAGE32_START: usize = 0x00200000;
+
 make_debug! {
     "Serial": Option<Serial> = Serial::probe_first(serial::baud::SerialBaud::Baud115200);
 }
@@ -62,52 +69,48 @@ extern "C" fn entry(disk_id: u16) {
 
 #[debug_ready]
 fn main(disk_id: u16) -> ! {
+    clock::init();
+    clock::init_wall_clock();
+    lignan::stream::update_all_stream_connections(|connection| {
+        connection
+            .with_timestamps(true)
+            .with_wall_time(true)
+            .with_deltas(cfg!(feature = "timestamp-deltas"))
+    });
+
     logln!("Quantum Loader");
 
     // - Memory Setup
     let memory_map = crate::memory::memory_map();
 
-    let ideal_region = memory_map
-        .iter()
-        .find(|region| {
-            region.region_type == MemoryEntry::REGION_FREE
-                && region.base_address >= (1024 * 1024)
-                && region.region_length >= (1024 * 1024 * 16)
-        })
-        .expect("Cannot find high memory above 1MB!");
+    // The later stages are loaded at a fixed address, so allocate from the memory around it
+    let (ideal_base, ideal_len) = free_region_containing(memory_map, STAGE32_START as u64)
+        .expect("Cannot find free memory for the later stages!");
 
-    let mut alloc =
-        unsafe { BumpAlloc::new(ideal_region.base_address, ideal_region.region_length) };
+    let mut alloc = unsafe { BumpAlloc::new(ideal_base, ideal_len) };
 
     // - Filesystem Enumeration
 
-    // FIXME: We need to figure out a new way of handing partitions from mbr
-    //        since partitions currently cannot be used to create Fats that
-    //        escape this closure. This means we need to create a new Fat
-    //        which should be avoided if its already known to be valid.
-    let mut mbr = Mbr::new(BiosDisk::new(disk_id)).expect("Cannot read MBR!");
-    let partition_number = (0..4)
-        .into_iter()
+    let mut fatfs = (0..4)
         .find_map(|part_number| {
-            let Some(partition) = mbr.partition(part_number) else {
-                return None;
-            };
-
+            let partition = Partition::from_mbr(BiosDisk::new(disk_id), part_number).ok()?;
             let mut fat = Fat::new(partition).ok()?;
-            fat.entry_of("bootloader/qconfig.cfg")
-                .ok()
-                .map(|_| part_number)
+
+            fat.entry_of("bootloader/qconfig.cfg").ok()?;
+            Some(fat)
         })
         .expect("Cannot find valid FAT Partition!");
 
-    let mut fatfs = Fat::new(mbr.partition(partition_number).unwrap()).unwrap();
+    if !fatfs.is_volume_clean().unwrap_or(true) {
+        warnln!("Boot partition was not cleanly unmounted!");
+    }
 
     // - Config File
     let mut qconfig = fatfs.open("bootloader/qconfig.cfg").unwrap();
     let qconfig_filesize = qconfig.filesize();
     let qconfig_buffer = unsafe { alloc.allocate(qconfig_filesize) }.unwrap();
     qconfig
-        .read(qconfig_buffer)
+        .read_exact(qconfig_buffer)
         .expect("Unable to read qconfig!");
 
     let qconfig = core::str::from_utf8(&qconfig_buffer).unwrap();
@@ -119,14 +122,16 @@ fn main(disk_id: u16) -> ! {
     let vesa = Vesa::quarry().ok();
 
     // - Stage-to-Stage
-    alloc.align_ptr_to(align_of::<Stage16toStage32>());
-
     let stage_to_stage = unsafe {
         &mut *(alloc
-            .allocate(size_of::<Stage16toStage32>())
+            .allocate_aligned(
+                size_of::<Stage16toStage32>(),
+                align_of::<Stage16toStage32>(),
+            )
             .expect("Unable to allocate Stage-to-Stage!")
             .as_mut_ptr() as *mut Stage16toStage32)
     };
+    stage_to_stage.header = Stage16toStage32::new_header();
 
     unsafe {
         core::ptr::copy_nonoverlapping(
@@ -136,34 +141,42 @@ fn main(disk_id: u16) -> ! {
         )
     };
 
-    if let Some((closest_video_id, closest_video_info)) = vesa
+    if let Some(video) = vesa
         .and_then(|vesa| {
             vesa.modes()
                 .filter_map(|id| id.querry().ok().map(|mode| (id, mode)))
-                .filter(|(_, mode)| mode.bpp == 32)
-                .reduce(|closest_mode, (id, mode)| {
-                    if closest_mode.1.width.abs_diff(want_x) > mode.width.abs_diff(want_x)
-                        && closest_mode.1.height.abs_diff(want_y) > mode.height.abs_diff(want_y)
-                    {
-                        (id, mode)
+                .filter(|(_, mode)| matches!(mode.bpp, 24 | 32))
+                .filter_map(|(id, mode)| VideoInformation::from_vesa(id, &mode))
+                .reduce(|closest, video| {
+                    let closer = closest.width.abs_diff(want_x as u32)
+                        > video.width.abs_diff(want_x as u32)
+                        && closest.height.abs_diff(want_y as u32)
+                            > video.height.abs_diff(want_y as u32);
+                    let same_size_deeper = (closest.width, closest.height)
+                        == (video.width, video.height)
+                        && closest.format.bits_per_pixel < video.format.bits_per_pixel;
+
+                    if closer || same_size_deeper {
+                        video
                     } else {
-                        closest_mode
+                        closest
                     }
                 })
         })
-        .and_then(|(video_id, video_info)| video_id.set().ok().map(|_| (video_id, video_info)))
+        .and_then(|video| video.mode_id.set().ok().map(|_| video))
     {
-        stage_to_stage.video_mode = Some((closest_video_id, closest_video_info));
+        stage_to_stage.video = Some(video);
 
         logln!(
-            "Optimal Video Mode id={:#04x}: {}x{} {}bbp",
-            closest_video_id.get_id(),
-            closest_video_info.width,
-            closest_video_info.height,
-            closest_video_info.bpp
+            "Optimal Video Mode id={:#04x}: {}x{} {}bbp (pitch={})",
+            video.mode_id.get_id(),
+            video.width,
+            video.height,
+            video.format.bits_per_pixel,
+            video.pitch
         );
     } else {
-        stage_to_stage.video_mode = None;
+        stage_to_stage.video = None;
         logln!("Video mode failed!");
     }
 
@@ -172,9 +185,10 @@ fn main(disk_id: u16) -> ! {
         .open(qconfig.bootloader32)
         .expect("Unable to find bootloader32");
 
-    // Our bootloader needs to be at 0x00200000
-    let bootloader32_entrypoint = 0x00200000 as *mut u8;
-    alloc.push_ptr_to(bootloader32_entrypoint);
+    let bootloader32_entrypoint = STAGE32_START as *mut u8;
+    alloc
+        .push_ptr_to(bootloader32_entrypoint)
+        .unwrap_or_else(|err| panic!("Cannot place stage32: {err}"));
 
     logln!(
         "Loading stage32 '{}' ({} Bytes)",
@@ -183,7 +197,7 @@ fn main(disk_id: u16) -> ! {
     );
     let bootloader32_buffer = unsafe { alloc.allocate(bootloader32.filesize()) }.unwrap();
     bootloader32
-        .read(bootloader32_buffer)
+        .read_exact(bootloader32_buffer)
         .expect("Unable to read bootloader32");
 
     // - Bootloader64
@@ -193,7 +207,9 @@ fn main(disk_id: u16) -> ! {
 
     // Our bootloader needs to be at 0x00400000
     let bootloader64_entrypoint = 0x00400000 as *mut u8;
-    alloc.push_ptr_to(bootloader64_entrypoint);
+    alloc
+        .push_ptr_to(bootloader64_entrypoint)
+        .unwrap_or_else(|err| panic!("Cannot place stage64: {err}"));
 
     logln!(
         "Loading stage64 '{}' ({} Bytes)",
@@ -202,12 +218,14 @@ fn main(disk_id: u16) -> ! {
     );
     let bootloader64_buffer = unsafe { alloc.allocate(bootloader64.filesize()) }.unwrap();
     bootloader64
-        .read(bootloader64_buffer)
+        .read_exact(bootloader64_buffer)
         .expect("Unable to read bootloader64");
 
     // kernel elf file
     let kernel_offset = 0x00500000 as *mut u8;
-    alloc.push_ptr_to(kernel_offset);
+    alloc
+        .push_ptr_to(kernel_offset)
+        .unwrap_or_else(|err| panic!("Cannot place kernel: {err}"));
 
     let mut kernel_file = fatfs.open(qconfig.kernel).expect("Unable to find kernel");
 
@@ -218,14 +236,11 @@ fn main(disk_id: u16) -> ! {
     );
     let kernel_buffer = unsafe { alloc.allocate(kernel_file.filesize()) }.unwrap();
     kernel_file
-        .read(kernel_buffer)
+        .read_exact(kernel_buffer)
         .expect("Unable to read kernel");
 
     let stack_region = unsafe { alloc.allocate(1024 * 1024) }.unwrap();
 
-    // The initfs needs to be 2Mib page aligned
-    alloc.align_ptr_to(1024 * 1024 * 2);
-
     // Initfs region
     let mut initfs_file = fatfs
         .open(qconfig.initfs)
@@ -236,9 +251,11 @@ fn main(disk_id: u16) -> ! {
         qconfig.initfs,
         initfs_file.filesize()
     );
-    let initfs_buffer = unsafe { alloc.allocate(initfs_file.filesize()) }.unwrap();
+    // The initfs needs to be 2Mib page aligned
+    let initfs_buffer =
+        unsafe { alloc.allocate_aligned(initfs_file.filesize(), 1024 * 1024 * 2) }.unwrap();
     initfs_file
-        .read(initfs_buffer)
+        .read_exact(initfs_buffer)
         .expect("Unable to read initfs");
 
     stage_to_stage.bootloader_stack_ptr = (stack_region.as_ptr() as u64, 1024 * 1024);
@@ -252,6 +269,25 @@ fn main(disk_id: u16) -> ! {
     );
     stage_to_stage.kernel_ptr = (kernel_buffer.as_ptr() as u64, kernel_buffer.len() as u64);
     stage_to_stage.initfs_ptr = (initfs_buffer.as_ptr() as u64, initfs_buffer.len() as u64);
+    stage_to_stage.checksums = BootChecksums {
+        stage64: qconfig.bootloader64_crc32,
+        kernel: qconfig.kernel_crc32,
+        initfs: qconfig.initfs_crc32,
+    };
+    stage_to_stage.boot_mode = qconfig.boot_mode;
+    stage_to_stage.cmdline = KernelCmdline::new(qconfig.cmdline);
+
+    if let Err(mismatch) =
+        unsafe { verify_artifact(stage_to_stage.stage32_ptr, qconfig.bootloader32_crc32) }
+    {
+        panic!("Stage32 is corrupted, refusing to jump to it: {mismatch}");
+    }
+
+    logln!(
+        "Loader memory   : {} used, {} remaining",
+        HumanBytes::from(alloc.used()),
+        HumanBytes::from(alloc.remaining())
+    );
 
     unsafe {
         unreal::enter_stage2(
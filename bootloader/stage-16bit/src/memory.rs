@@ -24,14 +24,16 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 */
 
 use bios::memory::MemoryEntry;
+use bootloader::MAX_MEMORY_MAP_ENTRIES;
 use core::mem::MaybeUninit;
 
 #[no_mangle]
-static mut MEMORY_MAP_AREA: MaybeUninit<[MemoryEntry; 16]> = MaybeUninit::zeroed();
+static mut MEMORY_MAP_AREA: MaybeUninit<[MemoryEntry; MAX_MEMORY_MAP_ENTRIES]> =
+    MaybeUninit::zeroed();
 
 #[allow(static_mut_refs)]
 pub fn memory_map() -> &'static [MemoryEntry] {
     let stable_regions =
-        unsafe { bios::memory::read_mapping(MEMORY_MAP_AREA.assume_init_mut()) }.unwrap();
+        unsafe { bios::memory::read_memory_map(MEMORY_MAP_AREA.assume_init_mut()) }.unwrap();
     unsafe { &MEMORY_MAP_AREA.assume_init_mut()[..stable_regions] }
 }
@@ -0,0 +1,92 @@
+/*
+  ____                 __               __                __
+ / __ \__ _____ ____  / /___ ____ _    / /  ___  ___ ____/ /__ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ _ \/ _ `/ _  / -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/\___/\_,_/\_,_/\__/_/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use bios::memory::MemoryEntry;
+use bootloader::Stage16toStage32;
+use lignan::{log, logln};
+use util::{align_to, bytes::HumanBytes, consts::PAGE_2M};
+
+/// Decompress the kernel and initfs if they were compressed, updating their pointers
+/// to point to the decompressed data.
+///
+/// Decompressed artifacts are placed in free memory after everything stage16 loaded.
+pub fn decompress_artifacts(s2s: &mut Stage16toStage32) {
+    let mut next_free = [
+        s2s.bootloader_stack_ptr,
+        s2s.stage32_ptr,
+        s2s.stage64_ptr,
+        s2s.kernel_ptr,
+        s2s.initfs_ptr,
+    ]
+    .iter()
+    .map(|&(start, len)| start + len)
+    .max()
+    .unwrap_or(0);
+
+    s2s.kernel_ptr = decompress_into("kernel", s2s.kernel_ptr, &mut next_free, &s2s.memory_map);
+    s2s.initfs_ptr = decompress_into("initfs", s2s.initfs_ptr, &mut next_free, &s2s.memory_map);
+}
+
+/// Decompress `artifact` at the next 2Mib aligned address after `next_free`.
+fn decompress_into(
+    name: &str,
+    artifact: (u64, u64),
+    next_free: &mut u64,
+    memory_map: &[MemoryEntry],
+) -> (u64, u64) {
+    let input =
+        unsafe { core::slice::from_raw_parts(artifact.0 as *const u8, artifact.1 as usize) };
+
+    if !lzss::is_compressed(input) {
+        return artifact;
+    }
+
+    let len = lzss::decompressed_len(input).unwrap() as u64;
+    let dest = align_to(*next_free, PAGE_2M);
+
+    // We are still in protected mode, so we cannot write above 4Gib
+    assert!(
+        memory_map.iter().any(|region| {
+            region.region_type == MemoryEntry::REGION_FREE
+                && region.base_address <= dest
+                && region.base_address + region.region_length >= dest + len
+        }) && dest + len <= u32::MAX as u64,
+        "Not enough free memory to decompress {name}!"
+    );
+
+    log!(
+        "Decompressing {name} ({} -> {})...",
+        HumanBytes::from(artifact.1),
+        HumanBytes::from(len)
+    );
+    let output = unsafe { core::slice::from_raw_parts_mut(dest as *mut u8, len as usize) };
+    if let Err(err) = lzss::decompress(input, output) {
+        panic!("Unable to decompress {name}: {err}");
+    }
+    logln!("OK");
+
+    *next_free = dest + len;
+    (dest, len)
+}
@@ -34,11 +34,15 @@ use arch::{
     gdt::{CodeSegmentDesc, DataSegmentDesc, GlobalDescriptorTable},
     registers::{Segment, SegmentRegisters},
 };
-use bootgfx::{Color, Framebuffer};
-use bootloader::{Stage16toStage32, Stage32toStage64};
+use bootloader::{
+    boot_info::BootInfo,
+    progress::{BootScreen, BootStage},
+    verify_artifact, Stage16toStage32, Stage32toStage64,
+};
 use lignan::{debug_ready, logln, make_debug};
 use serial::{baud::SerialBaud, Serial};
 
+mod decompress;
 #[cfg(feature = "multiboot")]
 mod multiboot;
 mod paging;
@@ -57,7 +61,7 @@ make_debug! {
 #[link_section = ".start"]
 #[cfg(not(feature = "multiboot"))]
 extern "C" fn _start(stage_to_stage: u32) {
-    main(unsafe { &(*(stage_to_stage as *const Stage16toStage32)) });
+    main(unsafe { &mut (*(stage_to_stage as *mut Stage16toStage32)) });
     panic!("Main should not return");
 }
 
@@ -65,41 +69,38 @@ extern "C" fn _start(stage_to_stage: u32) {
 #[link_section = ".start"]
 #[cfg(feature = "multiboot")]
 extern "C" fn _start() {
-    let stage_to_stage = init_multiboot!();
+    let mut stage_to_stage = init_multiboot!();
 
-    main(&stage_to_stage);
+    main(&mut stage_to_stage);
     panic!("Main should not return");
 }
 
 #[debug_ready]
-fn main(stage_to_stage: &Stage16toStage32) {
+fn main(stage_to_stage: &mut Stage16toStage32) {
+    if let Err(err) = stage_to_stage.validate() {
+        panic!("Unable to read the info block from stage16: {err}");
+    }
+
     // This cpu must support PAE
     ensure_support_for!(arch::supports::CpuFeature::SupportsPae);
 
-    if let Some(video_mode) = stage_to_stage.video_mode {
-        let mut framebuffer = unsafe {
-            Framebuffer::new_linear(
-                video_mode.1.framebuffer as *mut u32,
-                32,
-                video_mode.1.height as usize,
-                video_mode.1.width as usize,
-            )
-        };
-
-        framebuffer.draw_rec(
-            1,
-            1,
-            framebuffer.width(),
-            framebuffer.height(),
-            Color::QUANTUM_BACKGROUND,
-        );
-
-        framebuffer.draw_glyph(10, 10, 'Q', Color::WHITE);
-        framebuffer.draw_glyph(20, 10, 'O', Color::WHITE);
-        framebuffer.draw_glyph(30, 10, 'S', Color::WHITE);
+    let mut boot_screen = stage_to_stage.video.map(|video| unsafe {
+        BootScreen::new(
+            video.virt_addr as usize as *mut u8,
+            &video,
+            stage_to_stage.boot_mode,
+        )
+    });
+
+    if let Some(boot_screen) = boot_screen.as_mut() {
+        boot_screen.clear();
+        boot_screen.report(BootStage::Stage32);
+        boot_screen.report(BootStage::Decompress);
     }
 
-    unsafe { paging::enable_paging() };
+    decompress::decompress_artifacts(stage_to_stage);
+
+    let page_tables = unsafe { paging::enable_paging(stage_to_stage) };
 
     // load gdt
     unsafe {
@@ -129,17 +130,29 @@ fn main(stage_to_stage: &Stage16toStage32) {
     unsafe {
         let s2s = &mut *S2S.get();
 
+        s2s.header = Stage32toStage64::new_header();
         s2s.bootloader_stack_ptr = stage_to_stage.bootloader_stack_ptr;
         s2s.stage32_ptr = stage_to_stage.stage32_ptr;
         s2s.stage64_ptr = stage_to_stage.stage64_ptr;
         s2s.kernel_ptr = stage_to_stage.kernel_ptr;
         s2s.initfs_ptr = stage_to_stage.initfs_ptr;
+        s2s.page_tables_ptr = page_tables;
         s2s.memory_map = stage_to_stage.memory_map;
-        s2s.video_mode = stage_to_stage.video_mode.clone();
+        s2s.video = stage_to_stage.video;
+        s2s.checksums = stage_to_stage.checksums;
+        s2s.boot_mode = stage_to_stage.boot_mode;
+        s2s.cmdline = stage_to_stage.cmdline;
 
         logln!("Built Stage32to64!");
     }
 
+    // verify stage64
+    if let Err(mismatch) =
+        unsafe { verify_artifact(stage_to_stage.stage64_ptr, stage_to_stage.checksums.stage64) }
+    {
+        panic!("Stage64 is corrupted, refusing to jump to it: {mismatch}");
+    }
+
     // jump to stage64
     logln!(
         "Jumping to stage64! -- 0x{:016x}",
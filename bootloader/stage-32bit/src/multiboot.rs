@@ -23,7 +23,10 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use bootloader::{Stage16toStage32, MAX_MEMORY_MAP_ENTRIES};
+use bootloader::{
+    boot_info::BootInfo, cmdline::KernelCmdline, progress::BootMode, BootChecksums,
+    Stage16toStage32, MAX_MEMORY_MAP_ENTRIES,
+};
 use core::{mem::ManuallyDrop, ptr::null};
 use lignan::logln;
 
@@ -537,13 +540,27 @@ pub fn get_stage_to_stage_from_multiboot_header(header: *const Multiboot1Info) -
         unreachable!("Cannot match compile time length amount of elements!");
     };
 
+    // The command line is only valid when the bootloader sets flags bit 2
+    let cmdline = if header_ref.flags & (1 << 2) != 0 && header_ref.cmdline != 0 {
+        unsafe { core::ffi::CStr::from_ptr(header_ref.cmdline as *const i8) }
+            .to_str()
+            .map(KernelCmdline::new)
+            .unwrap_or_default()
+    } else {
+        KernelCmdline::empty()
+    };
+
     Stage16toStage32 {
+        header: Stage16toStage32::new_header(),
         bootloader_stack_ptr: (stack_ptr as u64, INIT_STACK.len() as u64),
         stage32_ptr: (stage32_ptr, stage32_len),
         stage64_ptr: (stage64_ptr, stage64_len),
         kernel_ptr: (kernel_ptr, kernel_len),
         initfs_ptr: (initfs_ptr, initfs_len),
         memory_map: e820_map,
-        video_mode: None,
+        video: None,
+        checksums: BootChecksums::default(),
+        boot_mode: BootMode::default(),
+        cmdline,
     }
 }
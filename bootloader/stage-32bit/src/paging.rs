@@ -28,62 +28,170 @@ use arch::{
     registers::{cr0, cr3, cr4, ia32_efer, Segment, SegmentRegisters},
     CpuPrivilege,
 };
-use core::cell::SyncUnsafeCell;
+use bios::memory::MemoryEntry;
+use bootloader::{bump_alloc::BumpAlloc, Stage16toStage32};
 use lignan::{log, logln};
-use util::consts::{GIB, MIB};
-
-/// Amount of Gib to identity map
-const IDMAP_GIG_AMOUNT: usize = 1;
+use util::consts::{MIB, PAGE_4K};
+
+/// The size of each identity mapped page.
+const PAGE_2M: u64 = 2 * MIB as u64;
+
+/// Builds the identity mapped page tables out of free memory.
+///
+/// Tables are only allocated when a region of memory needs them, so the amount of memory
+/// we are able to map is only limited by the memory we have to put tables in.
+pub struct PageTableBuilder {
+    alloc: BumpAlloc,
+    tables_start: u64,
+    lvl4: *mut PageMapLvl4,
+}
 
-static TABLE_LVL4: SyncUnsafeCell<PageMapLvl4> = SyncUnsafeCell::new(PageMapLvl4::new());
-static TABLE_LVL3: SyncUnsafeCell<PageMapLvl3> = SyncUnsafeCell::new(PageMapLvl3::new());
-static TABLE_LVL2: SyncUnsafeCell<[PageMapLvl2; IDMAP_GIG_AMOUNT]> =
-    SyncUnsafeCell::new([PageMapLvl2::new(); IDMAP_GIG_AMOUNT]);
+impl PageTableBuilder {
+    /// Create a new builder that will allocate its tables from the first free region
+    /// after everything stage16 loaded.
+    pub fn new(s2s: &Stage16toStage32) -> Self {
+        let loaded_end = [
+            s2s.bootloader_stack_ptr,
+            s2s.stage32_ptr,
+            s2s.stage64_ptr,
+            s2s.kernel_ptr,
+            s2s.initfs_ptr,
+        ]
+        .iter()
+        .map(|&(start, len)| start + len)
+        .max()
+        .unwrap_or(0);
+
+        let (tables_start, tables_len) = s2s
+            .memory_map
+            .iter()
+            .filter(|region| region.region_type == MemoryEntry::REGION_FREE)
+            .find_map(|region| {
+                let start = region.base_address.max(loaded_end).max(MIB as u64);
+                let start = start.next_multiple_of(PAGE_4K as u64);
+                let end = region.base_address + region.region_length;
+
+                // We must be able to access the tables from protected mode
+                let end = end.min(u32::MAX as u64);
+
+                (end > start && end - start >= PAGE_2M).then_some((start, end - start))
+            })
+            .expect("Cannot find free memory for page tables!");
+
+        let mut builder = Self {
+            alloc: unsafe { BumpAlloc::new(tables_start, tables_len) },
+            tables_start,
+            lvl4: core::ptr::null_mut(),
+        };
+
+        builder.lvl4 = builder.alloc_table();
+        builder
+    }
 
-pub fn identity_map() {
-    for gig in 0..IDMAP_GIG_AMOUNT {
-        let table_ptr = unsafe { &raw mut (*TABLE_LVL2.get())[gig] };
+    /// Allocate a new zeroed table.
+    fn alloc_table<T>(&mut self) -> *mut T {
+        let table = unsafe { self.alloc.allocate_aligned(size_of::<T>(), PAGE_4K) }
+            .expect("Ran out of memory for page tables!");
+        table.fill(0);
 
-        for mb2 in 0..512 {
-            let phy_addr = (mb2 as u64 * 2 * (MIB as u64)) + (gig as u64 * (GIB as u64));
+        table.as_mut_ptr() as *mut T
+    }
 
-            let lvl2_entry = PageEntry2M::new()
+    /// Get the lvl2 table that maps `addr`, making new tables if they are not present.
+    fn lvl2_for(&mut self, addr: u64) -> *mut PageMapLvl2 {
+        let lvl4_index = ((addr >> 39) & 0x1FF) as usize;
+        let lvl3_index = ((addr >> 30) & 0x1FF) as usize;
+
+        let lvl4_entry = unsafe { (*self.lvl4).get(lvl4_index) };
+        let lvl3 = if lvl4_entry.is_present_set() {
+            lvl4_entry.get_next_entry_phy_address() as *mut PageMapLvl3
+        } else {
+            let lvl3: *mut PageMapLvl3 = self.alloc_table();
+            let entry = PageEntryLvl4::new()
                 .set_present_flag(true)
                 .set_read_write_flag(true)
-                .set_phy_address(phy_addr);
+                .set_next_entry_phy_address(lvl3 as u64);
+
+            unsafe { (*self.lvl4).store(entry, lvl4_index) };
+            lvl3
+        };
+
+        let lvl3_entry = unsafe { (*lvl3).get(lvl3_index) };
+        if lvl3_entry.is_present_set() {
+            lvl3_entry.get_next_entry_phy_address() as *mut PageMapLvl2
+        } else {
+            let lvl2: *mut PageMapLvl2 = self.alloc_table();
+            let entry = PageEntryLvl3::new()
+                .set_present_flag(true)
+                .set_read_write_flag(true)
+                .set_next_entry_phy_address(lvl2 as u64);
 
-            unsafe { (*table_ptr).store(lvl2_entry, mb2) };
+            unsafe { (*lvl3).store(entry, lvl3_index) };
+            lvl2
         }
+    }
 
-        let lvl3_entry = PageEntryLvl3::new()
-            .set_present_flag(true)
-            .set_read_write_flag(true)
-            .set_next_entry_phy_address(unsafe { (*table_ptr).table_ptr() });
+    /// Identity map the region `start..end` with 2Mib pages.
+    pub fn identity_map(&mut self, start: u64, end: u64) {
+        let mut addr = start - (start % PAGE_2M);
 
-        unsafe { (*TABLE_LVL3.get()).store(lvl3_entry, gig) };
+        while addr < end {
+            let lvl2 = self.lvl2_for(addr);
+            let entry = PageEntry2M::new()
+                .set_present_flag(true)
+                .set_read_write_flag(true)
+                .set_phy_address(addr);
+
+            unsafe { (*lvl2).store(entry, ((addr >> 21) & 0x1FF) as usize) };
+            addr += PAGE_2M;
+        }
     }
 
-    let lvl4_entry = PageEntryLvl4::new()
-        .set_present_flag(true)
-        .set_read_write_flag(true)
-        .set_next_entry_phy_address(unsafe { (*TABLE_LVL3.get()).table_ptr() });
+    /// The physical region used by all the page tables.
+    pub fn tables_region(&self) -> (u64, u64) {
+        (
+            self.tables_start,
+            self.alloc.current_ptr() - self.tables_start,
+        )
+    }
 
-    unsafe { (*TABLE_LVL4.get()).store(lvl4_entry, 0) };
+    /// The physical address of the lvl4 table.
+    pub fn table_ptr(&self) -> u64 {
+        self.lvl4 as u64
+    }
 }
 
-pub unsafe fn set_page_base_reg() {
-    let phy_addr = unsafe { (*TABLE_LVL4.get()).table_ptr() };
+/// Identity map all usable memory and the framebuffer.
+pub fn identity_map(s2s: &Stage16toStage32) -> PageTableBuilder {
+    let mut builder = PageTableBuilder::new(s2s);
+
+    let memory_end = s2s
+        .memory_map
+        .iter()
+        .filter(|region| region.region_length != 0)
+        .filter(|region| region.region_type == MemoryEntry::REGION_FREE)
+        .map(|region| region.base_address + region.region_length)
+        .max()
+        .unwrap_or(0)
+        // Always map the first 1Gib since stage64 expects to be able to access low memory
+        .max(1024 * MIB as u64);
+
+    builder.identity_map(0, memory_end);
+
+    if let Some(video) = s2s.video {
+        builder.identity_map(video.phys_addr, video.phys_addr + video.size() as u64);
+    }
 
-    cr3::set_page_directory_base_register(phy_addr);
+    builder
 }
 
-pub unsafe fn enable_paging() {
+pub unsafe fn enable_paging(s2s: &Stage16toStage32) -> (u64, u64) {
     log!("Identity Mapping Regions...");
-    identity_map();
+    let builder = identity_map(s2s);
     logln!("OK");
 
     log!("Setting Paging Base Register...");
-    set_page_base_reg();
+    cr3::set_page_directory_base_register(builder.table_ptr());
     logln!("OK");
 
     log!("Disabling Paging...");
@@ -109,4 +217,6 @@ pub unsafe fn enable_paging() {
     log!("Reloading Segments...");
     SegmentRegisters::set_data_segments(Segment::new(2, CpuPrivilege::Ring0));
     logln!("OK");
+
+    builder.tables_region()
 }
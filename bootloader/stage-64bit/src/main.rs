@@ -27,7 +27,17 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 #![no_std]
 #![feature(sync_unsafe_cell)]
 
-use bootloader::{KernelBootHeader, KernelEntryFn, MEMORY_REGIONS, Stage32toStage64};
+use arch::{
+    CpuPrivilege,
+    gdt::{CodeSegmentDesc, DataSegmentDesc, GlobalDescriptorTable},
+    registers::{Segment, SegmentRegisters},
+};
+use bootloader::{
+    KernelBootHeader, KernelEntryFn, MEMORY_REGIONS, Stage32toStage64,
+    boot_info::{BOOT_TAG_BOOTLOADER_NAME, BootInfo, BootTagWriter},
+    progress::{BootScreen, BootStage},
+    verify_artifact,
+};
 use core::{arch::asm, cell::SyncUnsafeCell};
 use elf::{
     Elf,
@@ -42,7 +52,7 @@ use serial::{Serial, baud::SerialBaud};
 use util::{
     align_to,
     bytes::HumanBytes,
-    consts::{MIB, PAGE_2M},
+    consts::{MIB, PAGE_2M, PAGE_4K},
     is_align_to,
 };
 
@@ -55,6 +65,10 @@ make_debug! {
 static MEMORY_MAP: SyncUnsafeCell<PhysMemoryMap<MEMORY_REGIONS>> =
     SyncUnsafeCell::new(PhysMemoryMap::new());
 static KERNEL_INFO: SyncUnsafeCell<Option<KernelBootHeader>> = SyncUnsafeCell::new(None);
+/// The tags attached to the kernel's info block, as `u64`s to keep them 8 byte aligned
+static KERNEL_TAGS: SyncUnsafeCell<[u64; 32]> = SyncUnsafeCell::new([0; 32]);
+static GDT: SyncUnsafeCell<GlobalDescriptorTable<3>> =
+    SyncUnsafeCell::new(GlobalDescriptorTable::new());
 
 #[unsafe(no_mangle)]
 #[unsafe(link_section = ".start")]
@@ -66,8 +80,33 @@ extern "C" fn _start(stage_to_stage: u64) {
 #[debug_ready]
 fn main(stage_to_stage: &Stage32toStage64) {
     logln!("Stage64!");
+    if let Err(err) = stage_to_stage.validate() {
+        panic!("Unable to read the info block from stage32: {err}");
+    }
+
+    if let Some(video) = stage_to_stage.video {
+        // The framebuffer is still identity mapped from stage32
+        let mut boot_screen = unsafe {
+            BootScreen::new(video.virt_addr as *mut u8, &video, stage_to_stage.boot_mode)
+        };
+        boot_screen.report(BootStage::Stage64);
+    }
+
     let (kernel_elf_ptr, kernel_elf_size) = stage_to_stage.kernel_ptr;
 
+    log!("Verifying kernel and initfs...");
+    if let Err(mismatch) =
+        unsafe { verify_artifact(stage_to_stage.kernel_ptr, stage_to_stage.checksums.kernel) }
+    {
+        panic!("Kernel is corrupted, refusing to load it: {mismatch}");
+    }
+    if let Err(mismatch) =
+        unsafe { verify_artifact(stage_to_stage.initfs_ptr, stage_to_stage.checksums.initfs) }
+    {
+        panic!("Initfs is corrupted, refusing to load it: {mismatch}");
+    }
+    logln!("OK");
+
     let elf = Elf::new(unsafe {
         core::slice::from_raw_parts(kernel_elf_ptr as *const u8, kernel_elf_size as usize)
     });
@@ -88,7 +127,7 @@ fn main(stage_to_stage: &Stage32toStage64) {
     unsafe { paging::load_page_tables() };
     logln!("OK");
 
-    let _elf_header = match elf.header() {
+    let elf_header = match elf.header() {
         Ok(elf::tables::ElfHeader::Header64(h)) if h.arch() == ArchKind::X64 && h.is_le() => h,
         _ => panic!("Kernel's elf is not valid!"),
     };
@@ -114,13 +153,41 @@ fn main(stage_to_stage: &Stage32toStage64) {
     .unwrap();
     logln!(") -- OK");
 
+    log!("Protecting kernel segments...");
+    let segments = elf
+        .program_headers()
+        .expect("Unable to read the Kernel's program headers!");
+    unsafe { paging::protect_kernel_exe(virt_info, segments.iter()) };
+    logln!("OK");
+
+    let entry_point = elf_header.entry_point();
+    assert!(
+        entry_point >= virt_info.exe_start_virt && entry_point < virt_info.exe_end_virt,
+        "Kernel's entry point ({entry_point:#018x}) is not inside of its exe!"
+    );
+
+    log!("Loading final GDT...");
+    unsafe { load_final_gdt() };
+    logln!("OK");
+
     unsafe {
         let mm = &mut *MEMORY_MAP.get();
         let s2k = &mut *KERNEL_INFO.get();
+        let tags = &mut *KERNEL_TAGS.get();
+
+        let mut tag_writer = BootTagWriter::new(core::slice::from_raw_parts_mut(
+            tags.as_mut_ptr().cast(),
+            size_of_val(tags),
+        ));
+        let _ = tag_writer.push(
+            BOOT_TAG_BOOTLOADER_NAME,
+            concat!("Quantum Loader v", env!("CARGO_PKG_VERSION")).as_bytes(),
+        );
 
         *s2k = Some(KernelBootHeader {
+            header: KernelBootHeader::new_header().with_extensions(tag_writer.finish()),
             phys_mem_map: mm,
-            video_mode: stage_to_stage.video_mode,
+            video: stage_to_stage.video,
             kernel_elf: (kernel_elf_ptr, kernel_elf_size as usize),
             kernel_exe: (
                 virt_info.exe_start_virt,
@@ -138,16 +205,58 @@ fn main(stage_to_stage: &Stage32toStage64) {
                 virt_info.initfs_start_virt,
                 (virt_info.initfs_end_virt - virt_info.initfs_start_virt) as usize,
             ),
+            boot_mode: stage_to_stage.boot_mode,
+            cmdline: stage_to_stage.cmdline,
         });
 
         jmp_to_kernel(
-            virt_info.exe_start_virt as *const KernelEntryFn,
+            entry_point as *const KernelEntryFn,
             virt_info.stack_end_virt,
             s2k.as_ref().unwrap(),
         );
     }
 }
 
+/// Load the GDT the kernel will be entered with.
+///
+/// Stage32's GDT lives in memory the kernel is free to reclaim, so we need to make sure
+/// the kernel is entered using segments that we own.
+unsafe fn load_final_gdt() {
+    let gdt = unsafe { &mut *GDT.get() };
+
+    gdt.store(
+        1,
+        CodeSegmentDesc::new64()
+            .set_accessed_flag(true)
+            .set_present_flag(true)
+            .set_writable_flag(true),
+    );
+    gdt.store(
+        2,
+        DataSegmentDesc::new64()
+            .set_accessed_flag(true)
+            .set_present_flag(true)
+            .set_writable_flag(true),
+    );
+
+    unsafe {
+        gdt.pack().load();
+
+        // Reload `cs` with a far return
+        asm!(
+            "push {code}",
+            "lea {tmp}, [rip + 2f]",
+            "push {tmp}",
+            "retfq",
+            "2:",
+            code = in(reg) Segment::new(1, CpuPrivilege::Ring0).0 as u64,
+            tmp = out(reg) _,
+        );
+        SegmentRegisters::set_data_segments(Segment::new(2, CpuPrivilege::Ring0));
+    }
+}
+
+/// Jump into the kernel following the contract documented on [`KernelEntryFn`].
 unsafe fn jmp_to_kernel(
     fn_ptr: *const KernelEntryFn,
     kernel_stack_ptr: u64,
@@ -159,9 +268,16 @@ unsafe fn jmp_to_kernel(
         kernel_stack_ptr,
         s2k as *const _ as u64
     );
+    assert!(
+        is_align_to(kernel_stack_ptr, 16),
+        "Kernel's stack must be 16 byte aligned!"
+    );
+
     unsafe {
         asm!(
+            "cli",
             "mov rsp, {stack}",
+            "xor rbp, rbp",
             "call {kern:r}",
             in("rdi") s2k,
             kern = in(reg) fn_ptr,
@@ -179,7 +295,12 @@ fn build_memory_map(
     unsafe {
         let mm = &mut *MEMORY_MAP.get();
 
-        for memory_region in s2s.memory_map.iter() {
+        // Some firmware reports empty entries, which have nothing to add
+        for memory_region in s2s
+            .memory_map
+            .iter()
+            .filter(|entry| entry.region_length != 0)
+        {
             mm.add_region(memory_region)
                 .expect("Unable to build kernel's memory map!");
         }
@@ -238,6 +359,32 @@ fn build_memory_map(
         })
         .expect("Unable to add bootloader's stack to memory map");
 
+        let (tables_start, tables_len) = s2s.page_tables_ptr;
+        mm.add_region(PhysMemoryEntry {
+            kind: PhysMemoryKind::PageTables,
+            start: (tables_start as usize).into(),
+            end: PhysAddr::from((tables_start + tables_len) as usize),
+        })
+        .expect("Unable to add stage32's page tables to memory map");
+
+        // This is placed before anything the kernel gets, and from the top down, so it
+        // lands in the same place each boot and the last boot's log can be found again.
+        let pstore_pages = mm
+            .find_last_continuous_of(
+                PhysMemoryKind::Free,
+                memory_layout::PSTORE_SIZE,
+                PAGE_4K,
+                PhysAddr::from(memory_layout::IDENTITY_MAP.len()),
+            )
+            .map(|p| PhysMemoryEntry {
+                kind: PhysMemoryKind::Pstore,
+                ..p
+            });
+        match pstore_pages {
+            Some(pstore_pages) => mm.add_region(pstore_pages).unwrap(),
+            None => logln!("Unable to find a region for the pstore"),
+        }
+
         let kernels_pages = mm
             .find_continuous_of(
                 PhysMemoryKind::Free,
@@ -270,7 +417,7 @@ fn build_memory_map(
         let kernels_heap_pages = mm
             .find_continuous_of(
                 PhysMemoryKind::Free,
-                PAGE_2M * 8,
+                memory_layout::KERNEL_INIT_HEAP_SIZE,
                 PAGE_2M,
                 PhysAddr::from(1 * MIB),
             )
@@ -27,15 +27,17 @@ use core::cell::SyncUnsafeCell;
 
 use arch::{
     paging64::{PageEntry2M, PageEntryLvl3, PageEntryLvl4, PageMapLvl2, PageMapLvl3, PageMapLvl4},
-    registers::cr3,
+    registers::{cr3, ia32_efer},
 };
+use elf::tables::{ElfGenProgramHeader, SegmentKind};
+use memory_layout::{IDENTITY_MAP, KERNEL_IMAGE};
 use util::{
     consts::{GIB, MIB, PAGE_2M},
     is_align_to,
 };
 
 /// Amount of Gib to identity map
-const IDMAP_GIG_AMOUNT: usize = 1;
+const IDMAP_GIG_AMOUNT: usize = IDENTITY_MAP.len() / GIB;
 
 // Main Table
 static TABLE_LVL4: SyncUnsafeCell<PageMapLvl4> = SyncUnsafeCell::new(PageMapLvl4::new());
@@ -102,6 +104,10 @@ pub fn build_page_tables(c: PageTableConfig) -> KernelVirtInfo {
     assert!(is_align_to(c.kernel_exe_phys.0, PAGE_2M));
     assert!(is_align_to(c.kernel_stack_phys.0, PAGE_2M));
     assert!(is_align_to(c.kernel_virt, PAGE_2M));
+    assert_eq!(
+        c.kernel_virt as usize, KERNEL_IMAGE.start,
+        "Kernel is not linked at the start of its virtual region"
+    );
 
     // ID MAP
     for gig in 0..IDMAP_GIG_AMOUNT {
@@ -226,6 +232,12 @@ pub fn build_page_tables(c: PageTableConfig) -> KernelVirtInfo {
 
     unsafe { (*TABLE_LVL4.get()).store(lvl4_entry, tbl4_offset) };
 
+    let total_pages = exe_pages + stack_pages + heap_pages + initfs_pages + 3;
+    assert!(
+        KERNEL_IMAGE.contains_range(KERNEL_IMAGE.start, total_pages * PAGE_2M),
+        "Kernel, its stack, heap, and initfs do not fit in the kernel's virtual region"
+    );
+
     KernelVirtInfo {
         exe_start_virt: c.kernel_virt,
         exe_end_virt: c.kernel_virt + (exe_pages * PAGE_2M) as u64,
@@ -241,6 +253,55 @@ pub fn build_page_tables(c: PageTableConfig) -> KernelVirtInfo {
     }
 }
 
+/// Apply the permissions of the kernel's segments to the pages that contain them.
+///
+/// The kernel is mapped with 2Mib pages, so a page is only made read-only or non-executable
+/// when no segment within that page needs otherwise.
+pub unsafe fn protect_kernel_exe(
+    info: KernelVirtInfo,
+    segments: impl Iterator<Item = ElfGenProgramHeader>,
+) {
+    let tbl2_offset =
+        PageMapLvl2::addr2index(info.exe_start_virt % PageMapLvl2::SIZE_FOR_TABLE).unwrap();
+    let exe_pages = ((info.exe_end_virt - info.exe_start_virt) as usize) / PAGE_2M;
+
+    let mut writable = [false; 512];
+    let mut executable = [false; 512];
+
+    for segment in segments.filter(|h| h.segment_kind() == SegmentKind::Load) {
+        let start = segment.expected_vaddr();
+        let end = start + segment.in_mem_size() as u64;
+
+        if end <= start {
+            continue;
+        }
+
+        let first_page = ((start - info.exe_start_virt) as usize) / PAGE_2M;
+        let last_page = ((end - 1 - info.exe_start_virt) as usize) / PAGE_2M;
+
+        for page in first_page..=last_page {
+            writable[page] |= segment.is_writable();
+            executable[page] |= segment.is_executable();
+        }
+    }
+
+    // `execute_disable` is a reserved bit unless NXE is enabled
+    unsafe { ia32_efer::set_no_execute_flag(true) };
+
+    for page in 0..exe_pages {
+        let table = unsafe { &mut *TABLE_LVL2_KERN.get() };
+        let entry = PageEntry2M::convert_entry(table.get(page + tbl2_offset))
+            .expect("Kernel's exe should only be mapped with 2Mib pages")
+            .set_read_write_flag(writable[page])
+            .set_execute_disable_flag(!executable[page]);
+
+        table.store(entry, page + tbl2_offset);
+    }
+
+    // Flush the TLB for the new permissions
+    unsafe { load_page_tables() };
+}
+
 pub unsafe fn load_page_tables() {
     let phy_addr = unsafe { (*TABLE_LVL4.get()).table_ptr() };
 
@@ -0,0 +1,183 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Speculative execution controls.
+//!
+//! Detection of the `IA32_SPEC_CTRL`, `IA32_PRED_CMD`, and `IA32_ARCH_CAPABILITIES` MSRs,
+//! and the primitives to use them. Which of these to turn on is up to the kernel.
+
+use crate::{
+    registers::{read_msr, write_msr},
+    supports::{cpuid, CpuidRequest},
+};
+
+/// Controls indirect branch and store bypass speculation
+pub const IA32_SPEC_CTRL: u32 = 0x48;
+/// Write only, issues prediction barriers
+pub const IA32_PRED_CMD: u32 = 0x49;
+/// Reports which speculation issues this cpu is not affected by
+pub const IA32_ARCH_CAPABILITIES: u32 = 0x10A;
+
+/// `IA32_SPEC_CTRL`: Indirect Branch Restricted Speculation
+pub const SPEC_CTRL_IBRS: u64 = 1 << 0;
+/// `IA32_SPEC_CTRL`: Single Thread Indirect Branch Predictors
+pub const SPEC_CTRL_STIBP: u64 = 1 << 1;
+/// `IA32_SPEC_CTRL`: Speculative Store Bypass Disable
+pub const SPEC_CTRL_SSBD: u64 = 1 << 2;
+
+/// `IA32_PRED_CMD`: Indirect Branch Prediction Barrier
+const PRED_CMD_IBPB: u64 = 1 << 0;
+
+/// `IA32_ARCH_CAPABILITIES`: not affected by rogue data cache loads (Meltdown)
+const ARCH_CAP_RDCL_NO: u64 = 1 << 0;
+/// `IA32_ARCH_CAPABILITIES`: IBRS only needs to be set once
+const ARCH_CAP_IBRS_ALL: u64 = 1 << 1;
+/// `IA32_ARCH_CAPABILITIES`: not affected by speculative store bypass
+const ARCH_CAP_SSB_NO: u64 = 1 << 4;
+
+/// The speculation controls this cpu supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpeculationControls {
+    /// `IA32_SPEC_CTRL.IBRS` can be set
+    pub ibrs: bool,
+    /// `IA32_SPEC_CTRL.STIBP` can be set
+    pub stibp: bool,
+    /// `IA32_PRED_CMD.IBPB` can be issued
+    pub ibpb: bool,
+    /// `IA32_SPEC_CTRL.SSBD` can be set
+    pub ssbd: bool,
+    /// `IA32_ARCH_CAPABILITIES` can be read
+    pub arch_capabilities: bool,
+}
+
+impl SpeculationControls {
+    /// Decode the controls from `cpuid` leaf `7` `edx`, and leaf `0x80000008` `ebx`.
+    ///
+    /// Intel reports these in leaf `7`, AMD reports them in the extended leaf.
+    pub const fn from_cpuid(leaf7_edx: u32, ext_ebx: u32) -> Self {
+        const fn bit(reg: u32, bit: u32) -> bool {
+            reg & (1 << bit) != 0
+        }
+
+        Self {
+            ibrs: bit(leaf7_edx, 26) || bit(ext_ebx, 14),
+            ibpb: bit(leaf7_edx, 26) || bit(ext_ebx, 12),
+            stibp: bit(leaf7_edx, 27) || bit(ext_ebx, 15),
+            ssbd: bit(leaf7_edx, 31) || bit(ext_ebx, 24),
+            arch_capabilities: bit(leaf7_edx, 29),
+        }
+    }
+
+    /// Query `cpuid` for the controls this cpu supports
+    pub fn detect() -> Self {
+        let (.., leaf7_edx) = cpuid(CpuidRequest::ExtendedFeature);
+        let (_, ext_ebx, ..) = cpuid(CpuidRequest::AddressSize);
+
+        Self::from_cpuid(leaf7_edx, ext_ebx)
+    }
+
+    /// Does any control exist that would need `IA32_SPEC_CTRL`
+    pub const fn has_spec_ctrl(&self) -> bool {
+        self.ibrs || self.stibp || self.ssbd
+    }
+}
+
+/// What `IA32_ARCH_CAPABILITIES` says this cpu is not affected by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArchCapabilities {
+    /// Not affected by Meltdown, so user page tables don't need to hide the kernel
+    pub rdcl_no: bool,
+    /// IBRS protects the kernel for as long as it stays set (enhanced IBRS)
+    pub ibrs_all: bool,
+    /// Not affected by speculative store bypass
+    pub ssb_no: bool,
+}
+
+impl ArchCapabilities {
+    /// Decode the raw `IA32_ARCH_CAPABILITIES` value
+    pub const fn from_raw(raw: u64) -> Self {
+        Self {
+            rdcl_no: raw & ARCH_CAP_RDCL_NO != 0,
+            ibrs_all: raw & ARCH_CAP_IBRS_ALL != 0,
+            ssb_no: raw & ARCH_CAP_SSB_NO != 0,
+        }
+    }
+
+    /// Read `IA32_ARCH_CAPABILITIES`, if `controls` says it exists
+    pub fn read(controls: &SpeculationControls) -> Option<Self> {
+        controls
+            .arch_capabilities
+            .then(|| Self::from_raw(unsafe { read_msr(IA32_ARCH_CAPABILITIES) }))
+    }
+}
+
+/// Set and clear bits in `IA32_SPEC_CTRL`, returning the new value.
+///
+/// # Safety
+/// The cpu must support every bit being set.
+pub unsafe fn update_spec_ctrl(set: u64, clear: u64) -> u64 {
+    let value = (unsafe { read_msr(IA32_SPEC_CTRL) } & !clear) | set;
+    unsafe { write_msr(IA32_SPEC_CTRL, value) };
+
+    value
+}
+
+/// Stop indirect branches after this point from using predictions made before it.
+///
+/// # Safety
+/// The cpu must support IBPB.
+#[inline(always)]
+pub unsafe fn indirect_branch_prediction_barrier() {
+    unsafe { write_msr(IA32_PRED_CMD, PRED_CMD_IBPB) };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_controls_from_either_vendor() {
+        let intel = SpeculationControls::from_cpuid((1 << 26) | (1 << 29) | (1 << 31), 0);
+        assert!(intel.ibrs && intel.ibpb && intel.ssbd && intel.arch_capabilities);
+        assert!(!intel.stibp);
+
+        let amd = SpeculationControls::from_cpuid(0, (1 << 12) | (1 << 24));
+        assert!(amd.ibpb && amd.ssbd);
+        assert!(!amd.ibrs && !amd.arch_capabilities);
+        assert!(amd.has_spec_ctrl());
+
+        assert_eq!(
+            SpeculationControls::from_cpuid(0, 0),
+            SpeculationControls::default()
+        );
+    }
+
+    #[test]
+    fn test_arch_capabilities() {
+        let caps = ArchCapabilities::from_raw(ARCH_CAP_RDCL_NO | ARCH_CAP_SSB_NO);
+        assert!(caps.rdcl_no && caps.ssb_no);
+        assert!(!caps.ibrs_all);
+    }
+}
@@ -0,0 +1,83 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use core::arch::asm;
+
+/// Enable interrupts and halt until the next one arrives.
+///
+/// `sti` only takes effect after the instruction following it, so an interrupt that
+/// arrives between checking for work (with interrupts disabled) and halting will still
+/// wake this `hlt` instead of being handled before it.
+///
+/// # Safety
+/// Enables interrupts.
+#[inline(always)]
+pub unsafe fn enable_interrupts_and_halt() {
+    unsafe { asm!("sti", "hlt", options(nomem, nostack)) };
+}
+
+/// Halt this cpu forever.
+///
+/// Interrupts are disabled first, so only an NMI or reset can wake it.
+pub fn halt_forever() -> ! {
+    loop {
+        unsafe { asm!("cli", "hlt", options(nomem, nostack)) };
+    }
+}
+
+/// Arm address monitoring on the cache line containing `addr`.
+///
+/// A following `mwait` returns once this line is written to.
+///
+/// # Safety
+/// `addr` must be a valid address, and the cpu must support `monitor`.
+#[inline(always)]
+pub unsafe fn monitor(addr: *const u8) {
+    unsafe {
+        asm!("monitor", in("rax") addr, in("ecx") 0, in("edx") 0, options(nostack, readonly))
+    };
+}
+
+/// Wait for the monitored line to be written, or for an interrupt, in the C-state
+/// described by `hint`.
+///
+/// Interrupts wake the cpu even while they are disabled, they are then handled once
+/// interrupts are enabled again.
+///
+/// # Safety
+/// The cpu must support `mwait`.
+#[inline(always)]
+pub unsafe fn mwait(hint: u32) {
+    // ecx bit 0: treat interrupts as break events even when masked
+    unsafe { asm!("mwait", in("eax") hint, in("ecx") 1, options(nomem, nostack)) };
+}
+
+/// Make the `mwait` hint for the given C-state and sub-state.
+///
+/// C1 is `cstate = 1`, and so on.
+#[inline]
+pub const fn mwait_hint(cstate: u8, sub_state: u8) -> u32 {
+    (((cstate.saturating_sub(1)) as u32 & 0xF) << 4) | (sub_state as u32 & 0xF)
+}
@@ -355,6 +355,7 @@ impl InterruptFlags {
 pub struct InterruptInfo<'a> {
     pub context: &'a ProcessContext,
     pub flags: InterruptFlags,
+    raw_context: *const ProcessContext,
 }
 
 impl<'a> InterruptInfo<'a> {
@@ -362,16 +363,30 @@ impl<'a> InterruptInfo<'a> {
         Self {
             context: unsafe { &*context },
             flags: InterruptFlags::convert_from_interrupt(irq_id, 0),
+            raw_context: context,
         }
     }
 
     pub fn convert_from_e(irq_id: u8, context: *const ProcessContext) -> Self {
+        let raw_context = context;
         let context = unsafe { &*context };
         Self {
             context,
             flags: InterruptFlags::convert_from_interrupt(irq_id, context.exception_code),
+            raw_context,
         }
     }
+
+    /// Change the instruction ptr this interrupt will return to.
+    ///
+    /// # Safety
+    /// `rip` must be a valid place to continue executing the interrupted context with its
+    /// current registers and stack.
+    pub unsafe fn redirect_return(&self, rip: u64) {
+        // The context lives on the interrupt stack, and is restored by the interrupt wrapper
+        // once the handler returns.
+        unsafe { core::ptr::addr_of_mut!((*self.raw_context.cast_mut()).rip).write_volatile(rip) };
+    }
 }
 
 #[macro_export]
@@ -445,6 +460,10 @@ macro_rules! raw_int_asm {
                     r#"
                     .align 16
                     cld
+                    test qword ptr [rsp + 8], 3    # came from userspace?
+                    jz 3f
+                    swapgs
+                3:
                     push 0
                     push rax
                     push rbx
@@ -482,6 +501,10 @@ macro_rules! raw_int_asm {
                     pop rax
                     add rsp, 8                     # pop 0
 
+                    test qword ptr [rsp + 8], 3    # returning to userspace?
+                    jz 4f
+                    swapgs
+                4:
                     iretq
                 "#,
                     handler = sym handler
@@ -503,6 +526,10 @@ macro_rules! raw_int_asm {
                     r#"
                     .align 16
                     cld
+                    test qword ptr [rsp + 16], 3   # came from userspace?
+                    jz 3f
+                    swapgs
+                3:
                     push rax
                     push rbx
                     push rcx
@@ -539,6 +566,10 @@ macro_rules! raw_int_asm {
                     pop rax
                     add rsp, 8                     # pop errno
 
+                    test qword ptr [rsp + 8], 3    # returning to userspace?
+                    jz 4f
+                    swapgs
+                4:
                     iretq
                 "#,
                     handler = sym handler
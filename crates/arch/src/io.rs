@@ -28,7 +28,7 @@ use core::{
     ops::{Add, Sub},
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct IOPort(u16);
 
@@ -45,6 +45,12 @@ impl IOPort {
         Self(port)
     }
 
+    /// # Port
+    /// Get the port number on the CPU IO bus.
+    pub const fn port(self) -> u16 {
+        self.0
+    }
+
     /// # Read Byte
     /// Read a byte from the CPU IO bus.
     #[inline(always)]
@@ -78,6 +84,23 @@ impl IOPort {
     pub unsafe fn write_word(self, word: u16) {
         asm!("out dx, ax", in("dx") self.0, in("ax") word, options(nomem, nostack, preserves_flags));
     }
+
+    /// # Read Dword
+    /// Read a dword from the CPU IO bus.
+    #[inline(always)]
+    pub unsafe fn read_dword(self) -> u32 {
+        let mut port_value;
+
+        asm!("in eax, dx", out("eax") port_value, in("dx") self.0, options(nomem, nostack, preserves_flags));
+        return port_value;
+    }
+
+    /// # Write Dword
+    /// Writes a dword to the CPU IO bus.
+    #[inline(always)]
+    pub unsafe fn write_dword(self, dword: u32) {
+        asm!("out dx, eax", in("dx") self.0, in("eax") dword, options(nomem, nostack, preserves_flags));
+    }
 }
 
 impl Add<u16> for IOPort {
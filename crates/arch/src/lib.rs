@@ -27,13 +27,18 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 #![feature(abi_x86_interrupt)]
 
 pub mod gdt;
+pub mod hardening;
+pub mod idle;
 pub mod idt64;
 pub mod io;
 pub mod locks;
+pub mod mtrr;
 pub mod paging64;
 pub mod pic8259;
 pub mod pit825x;
 pub mod registers;
+pub mod rtc;
+pub mod stack_walk;
 pub mod supports;
 pub mod tss64;
 
@@ -90,6 +95,33 @@ pub mod interrupts {
     }
 }
 
+/// Supervisor Mode Access Prevention (SMAP) controls
+pub mod user_access {
+    /// Is the supervisor currently allowed to access user pages?
+    #[inline(always)]
+    pub fn is_user_access_allowed() -> bool {
+        super::registers::eflags::is_alignment_check_set()
+    }
+
+    /// Allow the supervisor to access user pages (`stac`).
+    ///
+    /// # Safety
+    /// The cpu must support SMAP, otherwise this will raise an invalid opcode.
+    #[inline(always)]
+    pub unsafe fn allow_user_access() {
+        core::arch::asm!("stac", options(nostack));
+    }
+
+    /// Prevent the supervisor from accessing user pages (`clac`).
+    ///
+    /// # Safety
+    /// The cpu must support SMAP, otherwise this will raise an invalid opcode.
+    #[inline(always)]
+    pub unsafe fn deny_user_access() {
+        core::arch::asm!("clac", options(nostack));
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CpuPrivilege {
     Ring0,
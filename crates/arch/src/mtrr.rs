@@ -0,0 +1,419 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Memory Type Range Registers.
+//!
+//! The MTRRs give ranges of physical memory a memory type, which is combined with the type
+//! a page gets from the PAT. Firmware sets these up, so we mostly just read them back,
+//! but [`MtrrMap::sanitize`] can turn off variable ranges that can't have a defined meaning.
+
+use crate::registers::{cr0, cr3, read_msr, write_msr};
+
+const IA32_MTRRCAP: u32 = 0xFE;
+const IA32_MTRR_DEF_TYPE: u32 = 0x2FF;
+const IA32_MTRR_PHYSBASE0: u32 = 0x200;
+/// The fixed range MSRs, and how many bytes each of their 8 entries cover
+const FIXED_RANGE_MSRS: [(u32, u64); 11] = [
+    (0x250, 0x10000),
+    (0x258, 0x4000),
+    (0x259, 0x4000),
+    (0x268, 0x1000),
+    (0x269, 0x1000),
+    (0x26A, 0x1000),
+    (0x26B, 0x1000),
+    (0x26C, 0x1000),
+    (0x26D, 0x1000),
+    (0x26E, 0x1000),
+    (0x26F, 0x1000),
+];
+/// Everything below this address is covered by the fixed ranges
+const FIXED_RANGE_END: u64 = 0x100000;
+
+/// The most variable ranges we keep track of
+pub const MAX_VARIABLE_RANGES: usize = 16;
+
+/// A memory type an MTRR can give memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MemoryType {
+    Uncacheable = 0,
+    WriteCombining = 1,
+    WriteThrough = 4,
+    WriteProtected = 5,
+    WriteBack = 6,
+}
+
+impl MemoryType {
+    /// Get the memory type for its encoding, or `None` if it is reserved
+    pub const fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(Self::Uncacheable),
+            1 => Some(Self::WriteCombining),
+            4 => Some(Self::WriteThrough),
+            5 => Some(Self::WriteProtected),
+            6 => Some(Self::WriteBack),
+            _ => None,
+        }
+    }
+
+    /// The short name of this type, like `WB`
+    pub const fn short_name(self) -> &'static str {
+        match self {
+            Self::Uncacheable => "UC",
+            Self::WriteCombining => "WC",
+            Self::WriteThrough => "WT",
+            Self::WriteProtected => "WP",
+            Self::WriteBack => "WB",
+        }
+    }
+
+    /// Do ranges of type `self` and `other` have a defined meaning where they overlap?
+    pub const fn can_overlap(self, other: Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::Uncacheable, _)
+                | (_, Self::Uncacheable)
+                | (Self::WriteThrough, Self::WriteBack)
+                | (Self::WriteBack, Self::WriteThrough)
+        ) || self as u8 == other as u8
+    }
+}
+
+/// A variable range, covering every address where `addr & mask == base & mask`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariableRange {
+    pub base: u64,
+    /// The mask, with every bit above the cpu's physical address size set
+    pub mask: u64,
+    /// The memory type, as it is encoded in the MTRR
+    pub raw_type: u8,
+}
+
+impl VariableRange {
+    /// The memory type of this range, or `None` if the firmware gave it a reserved type
+    pub const fn memory_type(&self) -> Option<MemoryType> {
+        MemoryType::from_raw(self.raw_type)
+    }
+
+    /// Does this range cover `addr`?
+    pub const fn contains(&self, addr: u64) -> bool {
+        addr & self.mask == self.base & self.mask
+    }
+
+    /// The size of this range, or `None` if its mask leaves holes in it
+    pub const fn size(&self) -> Option<u64> {
+        let inverse = !self.mask;
+        if inverse & inverse.wrapping_add(1) != 0 {
+            return None;
+        }
+
+        Some(inverse.wrapping_add(1))
+    }
+
+    /// Do `self` and `other` cover any of the same addresses?
+    pub const fn overlaps(&self, other: &Self) -> bool {
+        // Two ranges share an address when they agree on every bit both masks care about
+        let shared_mask = self.mask & other.mask;
+        self.base & shared_mask == other.base & shared_mask
+    }
+}
+
+/// Why [`MtrrMap::sanitize`] turned off a variable range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizeReason {
+    /// The range has a reserved memory type
+    ReservedType(u8),
+    /// The range overlaps the range at this index, and their types don't mix
+    ConflictsWith(usize),
+}
+
+/// A snapshot of this cpu's MTRRs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MtrrMap {
+    /// The physical address size of the cpu, in bits
+    pub phys_bits: usize,
+    /// Are the MTRRs turned on at all?
+    pub enabled: bool,
+    /// Are the fixed ranges turned on?
+    pub fixed_enabled: bool,
+    /// The type of memory no range covers
+    pub default_type: MemoryType,
+    /// The number of variable ranges this cpu has
+    pub variable_count: usize,
+    /// The variable ranges that are turned on
+    pub variable: [Option<VariableRange>; MAX_VARIABLE_RANGES],
+    /// The type of each entry of the fixed ranges, in order
+    pub fixed: [u8; 88],
+}
+
+impl MtrrMap {
+    /// Read this cpu's MTRRs.
+    ///
+    /// # Safety
+    /// The cpu must support MTRRs, and `phys_bits` must be its physical address size.
+    pub unsafe fn read(phys_bits: usize) -> Self {
+        let capabilities = unsafe { read_msr(IA32_MTRRCAP) };
+        let def_type = unsafe { read_msr(IA32_MTRR_DEF_TYPE) };
+        let above_phys = !((1u64 << phys_bits) - 1);
+
+        let variable_count = ((capabilities & 0xFF) as usize).min(MAX_VARIABLE_RANGES);
+        let variable = core::array::from_fn(|index| {
+            if index >= variable_count {
+                return None;
+            }
+
+            let msr = IA32_MTRR_PHYSBASE0 + index as u32 * 2;
+            let (base, mask) = unsafe { (read_msr(msr), read_msr(msr + 1)) };
+
+            // Bit 11 of the mask marks the range as valid
+            (mask & (1 << 11) != 0).then_some(VariableRange {
+                base: base & !0xFFF & !above_phys,
+                mask: (mask & !0xFFF) | above_phys,
+                raw_type: base as u8,
+            })
+        });
+
+        let has_fixed = capabilities & (1 << 8) != 0;
+        let mut fixed = [MemoryType::Uncacheable as u8; 88];
+        if has_fixed {
+            for (i, (msr, _)) in FIXED_RANGE_MSRS.iter().enumerate() {
+                fixed[i * 8..i * 8 + 8].copy_from_slice(&unsafe { read_msr(*msr) }.to_le_bytes());
+            }
+        }
+
+        Self {
+            phys_bits,
+            enabled: def_type & (1 << 11) != 0,
+            fixed_enabled: has_fixed && def_type & (1 << 10) != 0,
+            default_type: MemoryType::from_raw(def_type as u8).unwrap_or(MemoryType::Uncacheable),
+            variable_count,
+            variable,
+            fixed,
+        }
+    }
+
+    /// The memory type the MTRRs give `addr`.
+    ///
+    /// Where ranges overlap in a way that has no defined meaning, this gives `Uncacheable`.
+    pub fn memory_type(&self, addr: u64) -> MemoryType {
+        if !self.enabled {
+            return MemoryType::Uncacheable;
+        }
+
+        if self.fixed_enabled && addr < FIXED_RANGE_END {
+            return self.fixed_memory_type(addr);
+        }
+
+        let mut found: Option<MemoryType> = None;
+        for range in self
+            .variable
+            .iter()
+            .flatten()
+            .filter(|range| range.contains(addr))
+        {
+            let kind = range.memory_type().unwrap_or(MemoryType::Uncacheable);
+
+            found = Some(match (found, kind) {
+                (None, kind) => kind,
+                (Some(MemoryType::Uncacheable), _) | (_, MemoryType::Uncacheable) => {
+                    MemoryType::Uncacheable
+                }
+                (Some(MemoryType::WriteThrough), MemoryType::WriteBack)
+                | (Some(MemoryType::WriteBack), MemoryType::WriteThrough) => {
+                    MemoryType::WriteThrough
+                }
+                (Some(found), kind) if found == kind => kind,
+                _ => MemoryType::Uncacheable,
+            });
+        }
+
+        found.unwrap_or(self.default_type)
+    }
+
+    fn fixed_memory_type(&self, addr: u64) -> MemoryType {
+        let mut start = 0;
+        for (i, (_, entry_len)) in FIXED_RANGE_MSRS.iter().enumerate() {
+            let end = start + entry_len * 8;
+            if addr < end {
+                let raw = self.fixed[i * 8 + ((addr - start) / entry_len) as usize];
+                return MemoryType::from_raw(raw).unwrap_or(MemoryType::Uncacheable);
+            }
+            start = end;
+        }
+
+        self.default_type
+    }
+
+    /// Turn off variable ranges that can't have a defined meaning, calling `on_removed`
+    /// with the index of each.
+    ///
+    /// This only changes this snapshot, see [`MtrrMap::write_variable_ranges`] to apply it.
+    pub fn sanitize(&mut self, mut on_removed: impl FnMut(usize, VariableRange, SanitizeReason)) {
+        for index in 0..self.variable.len() {
+            let Some(range) = self.variable[index] else {
+                continue;
+            };
+
+            let Some(kind) = range.memory_type() else {
+                self.variable[index] = None;
+                on_removed(index, range, SanitizeReason::ReservedType(range.raw_type));
+                continue;
+            };
+
+            let conflict = self.variable[..index]
+                .iter()
+                .enumerate()
+                .filter_map(|(other_index, other)| other.map(|other| (other_index, other)))
+                .find(|(_, other)| {
+                    other.overlaps(&range)
+                        && other
+                            .memory_type()
+                            .is_some_and(|other_kind| !other_kind.can_overlap(kind))
+                });
+
+            if let Some((other_index, _)) = conflict {
+                self.variable[index] = None;
+                on_removed(index, range, SanitizeReason::ConflictsWith(other_index));
+            }
+        }
+    }
+
+    /// Write this snapshot's variable ranges back into the cpu.
+    ///
+    /// Follows the sequence from the Intel SDM (11.11.7.2), caches are flushed and turned
+    /// off while the ranges change.
+    ///
+    /// # Safety
+    /// Interrupts must be disabled, no other cpu may be running, and the snapshot must have
+    /// come from this cpu's [`MtrrMap::read`].
+    pub unsafe fn write_variable_ranges(&self) {
+        let old_cr0 = cr0::read();
+        let above_phys = !((1u64 << self.phys_bits) - 1);
+
+        unsafe {
+            // Caches off, then flush them and the TLB
+            cr0::write((old_cr0 | (1 << 30)) & !(1 << 29));
+            core::arch::asm!("wbinvd");
+            cr3::write(cr3::read());
+
+            let def_type = read_msr(IA32_MTRR_DEF_TYPE);
+            write_msr(IA32_MTRR_DEF_TYPE, def_type & !(1 << 11));
+
+            for (index, range) in self.variable.iter().enumerate().take(self.variable_count) {
+                let msr = IA32_MTRR_PHYSBASE0 + index as u32 * 2;
+
+                match range {
+                    Some(range) => {
+                        write_msr(msr, range.base | range.raw_type as u64);
+                        // The bits above the physical address size are reserved
+                        write_msr(msr + 1, (range.mask & !above_phys) | (1 << 11));
+                    }
+                    None => write_msr(msr + 1, 0),
+                }
+            }
+
+            core::arch::asm!("wbinvd");
+            cr3::write(cr3::read());
+            write_msr(IA32_MTRR_DEF_TYPE, def_type);
+            cr0::write(old_cr0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ABOVE_PHYS: u64 = !((1 << 36) - 1);
+
+    fn range(base: u64, len: u64, kind: MemoryType) -> Option<VariableRange> {
+        Some(VariableRange {
+            base,
+            mask: !(len - 1) | ABOVE_PHYS,
+            raw_type: kind as u8,
+        })
+    }
+
+    fn map(variable: &[Option<VariableRange>]) -> MtrrMap {
+        let mut map = MtrrMap {
+            phys_bits: 36,
+            enabled: true,
+            fixed_enabled: true,
+            default_type: MemoryType::Uncacheable,
+            variable_count: variable.len(),
+            variable: [None; MAX_VARIABLE_RANGES],
+            fixed: [MemoryType::WriteBack as u8; 88],
+        };
+        map.variable[..variable.len()].copy_from_slice(variable);
+        map
+    }
+
+    #[test]
+    fn test_memory_type() {
+        let map = map(&[
+            range(0, 0x8000_0000, MemoryType::WriteBack),
+            range(0x7000_0000, 0x1000_0000, MemoryType::Uncacheable),
+            range(0x1000_0000, 0x1000_0000, MemoryType::WriteThrough),
+        ]);
+
+        assert_eq!(map.memory_type(0x1000), MemoryType::WriteBack);
+        assert_eq!(map.memory_type(0x200000), MemoryType::WriteBack);
+        assert_eq!(map.memory_type(0x7100_0000), MemoryType::Uncacheable);
+        assert_eq!(map.memory_type(0x1800_0000), MemoryType::WriteThrough);
+        assert_eq!(map.memory_type(0x9000_0000), MemoryType::Uncacheable);
+        assert_eq!(
+            range(0, 0x8000_0000, MemoryType::WriteBack).unwrap().size(),
+            Some(0x8000_0000)
+        );
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let mut map = map(&[
+            range(0, 0x8000_0000, MemoryType::WriteBack),
+            range(0x4000_0000, 0x1000_0000, MemoryType::WriteCombining),
+            range(0x9000_0000, 0x1000_0000, MemoryType::WriteCombining),
+            Some(VariableRange {
+                base: 0xA000_0000,
+                mask: !0xFFF_FFFF,
+                raw_type: 2,
+            }),
+        ]);
+
+        let mut removed = [None; 4];
+        map.sanitize(|index, _, reason| removed[index] = Some(reason));
+
+        assert_eq!(
+            removed,
+            [
+                None,
+                Some(SanitizeReason::ConflictsWith(0)),
+                None,
+                Some(SanitizeReason::ReservedType(2))
+            ]
+        );
+        assert!(map.variable[2].is_some());
+    }
+}
@@ -27,8 +27,22 @@ use crate::{interrupts::assert_interrupts, io::IOPort};
 
 const CHANNEL_0_DATA: IOPort = IOPort::new(0x40);
 const _CHANNEL_1_DATA: IOPort = IOPort::new(0x41);
-const _CHANNEL_2_DATA: IOPort = IOPort::new(0x42);
+const CHANNEL_2_DATA: IOPort = IOPort::new(0x42);
 const COMMAND: IOPort = IOPort::new(0x43);
+/// Port B of the PS/2 controller, which connects channel 2 to the PC speaker
+const SPEAKER_CONTROL: IOPort = IOPort::new(0x61);
+
+/// Bit 0 of `SPEAKER_CONTROL` gates channel 2, letting it count
+pub const CHANNEL_2_GATE_BIT: u8 = 1 << 0;
+/// Bit 1 of `SPEAKER_CONTROL` connects the output of channel 2 to the speaker
+pub const SPEAKER_DATA_BIT: u8 = 1 << 1;
+/// Bit 5 of `SPEAKER_CONTROL` reads back the output of channel 2
+const CHANNEL_2_OUTPUT_BIT: u8 = 1 << 5;
+/// How long to wait for channel 2's output to change before giving up
+const MAX_OUTPUT_SPINS: usize = 1_000_000;
+
+/// The frequency the PIT counts down at
+pub const PIT_BASE_HZ: u32 = 1193182;
 
 #[repr(u8)]
 #[derive(Clone, Copy, Debug)]
@@ -59,22 +73,39 @@ pub enum PitOperatingMode {
     HardwareStrobe = 5,
 }
 
-pub fn pit_command(
+/// The byte to write to the command port to set up `channel`
+pub const fn command_byte(
     channel: PitSelectChannel,
     access: PitAccessMode,
     mode: PitOperatingMode,
     bcd_mode: bool,
-) {
+) -> u8 {
     let bcd_bit = if bcd_mode { 1 } else { 0 };
     let mode_bit = (mode as u8) << 1;
     let access_bit = (access as u8) << 4;
     let channel_bit = (channel as u8) << 6;
 
+    bcd_bit | mode_bit | access_bit | channel_bit
+}
+
+pub fn pit_command(
+    channel: PitSelectChannel,
+    access: PitAccessMode,
+    mode: PitOperatingMode,
+    bcd_mode: bool,
+) {
     unsafe {
-        COMMAND.write_byte(bcd_bit | mode_bit | access_bit | channel_bit);
+        COMMAND.write_byte(command_byte(channel, access, mode, bcd_mode));
     }
 }
 
+/// The reload count that makes a channel's square wave closest to `hz`.
+///
+/// Frequencies below what the PIT can divide down to are raised to the lowest it can play.
+pub fn square_wave_divisor(hz: u32) -> u16 {
+    (PIT_BASE_HZ / hz.max(1)).clamp(1, u16::MAX as u32) as u16
+}
+
 /// Set the pit reload count.
 ///
 /// # Interrupts
@@ -102,3 +133,71 @@ pub fn set_pit_hz(hz: f32) -> f32 {
 
     1193182_f32 / (int_div as f32)
 }
+
+/// Start the PC speaker playing a square wave of `hz`.
+///
+/// Frequencies below what the PIT can divide down to are raised to the lowest it can play.
+pub fn speaker_play(hz: u32) {
+    start_channel_2(hz, CHANNEL_2_GATE_BIT | SPEAKER_DATA_BIT);
+}
+
+/// Stop the PC speaker.
+pub fn speaker_stop() {
+    stop_channel_2();
+}
+
+/// Spin for at least `ms` milliseconds, without making a sound.
+///
+/// This works before the timer is running and with interrupts disabled, but it shares
+/// channel 2 with the PC speaker, so it cannot be used while the speaker is playing.
+pub fn spin_delay_ms(ms: u32) {
+    start_channel_2(1000, CHANNEL_2_GATE_BIT);
+    channel_2_wait_cycles(ms);
+    stop_channel_2();
+}
+
+fn start_channel_2(hz: u32, control_bits: u8) {
+    let divisor = square_wave_divisor(hz);
+
+    pit_command(
+        PitSelectChannel::Channel2,
+        PitAccessMode::AccessLoHi,
+        PitOperatingMode::SquareWave,
+        false,
+    );
+
+    unsafe {
+        CHANNEL_2_DATA.write_byte((divisor & 0xFF) as u8);
+        CHANNEL_2_DATA.write_byte(((divisor >> 8) & 0xFF) as u8);
+
+        let control = SPEAKER_CONTROL.read_byte() & !(CHANNEL_2_GATE_BIT | SPEAKER_DATA_BIT);
+        SPEAKER_CONTROL.write_byte(control | control_bits);
+    }
+}
+
+fn stop_channel_2() {
+    unsafe {
+        let control = SPEAKER_CONTROL.read_byte();
+        SPEAKER_CONTROL.write_byte(control & !(CHANNEL_2_GATE_BIT | SPEAKER_DATA_BIT));
+    }
+}
+
+/// Spin until channel 2 has output `cycles` periods of its square wave.
+///
+/// This can be used as a delay while the speaker is playing, without needing interrupts.
+/// Returns early if the output never changes, like on machines where it is not wired up.
+pub fn channel_2_wait_cycles(cycles: u32) {
+    let wait_for = |high: bool| {
+        (0..MAX_OUTPUT_SPINS).any(|_| {
+            let output = unsafe { SPEAKER_CONTROL.read_byte() } & CHANNEL_2_OUTPUT_BIT != 0;
+            core::hint::spin_loop();
+            output == high
+        })
+    };
+
+    for _ in 0..cycles {
+        if !wait_for(true) || !wait_for(false) {
+            return;
+        }
+    }
+}
@@ -537,6 +537,144 @@ pub mod ia32_efer {
     }
 }
 
+/// The Page Attribute Table, 8 memory types selected by a page's `PAT`, `PCD`, and `PWT` bits
+pub mod ia32_pat {
+    use super::{read_msr, write_msr};
+
+    /// Strong uncacheable
+    pub const UNCACHEABLE: u8 = 0x00;
+    /// Write combining
+    pub const WRITE_COMBINING: u8 = 0x01;
+    /// Write through
+    pub const WRITE_THROUGH: u8 = 0x04;
+    /// Write protected
+    pub const WRITE_PROTECTED: u8 = 0x05;
+    /// Write back
+    pub const WRITE_BACK: u8 = 0x06;
+    /// Uncacheable, but can be overridden by MTRRs
+    pub const UNCACHED_MINUS: u8 = 0x07;
+
+    /// Build a PAT value from its 8 entries
+    pub const fn from_entries(entries: [u8; 8]) -> u64 {
+        let mut value = 0;
+        let mut i = 0;
+        while i < 8 {
+            value |= (entries[i] as u64) << (i * 8);
+            i += 1;
+        }
+
+        value
+    }
+
+    #[inline(always)]
+    pub fn read() -> u64 {
+        unsafe { read_msr(0x277) }
+    }
+
+    #[inline(always)]
+    pub unsafe fn write(value: u64) {
+        write_msr(0x277, value);
+    }
+}
+
+/// The base address of the `FS` segment
+pub mod ia32_fs_base {
+    use super::{read_msr, write_msr};
+
+    #[inline(always)]
+    pub fn read() -> u64 {
+        unsafe { read_msr(0xC0000100) }
+    }
+
+    #[inline(always)]
+    pub unsafe fn write(value: u64) {
+        write_msr(0xC0000100, value);
+    }
+}
+
+/// The base address of the `GS` segment
+pub mod ia32_gs_base {
+    use super::{read_msr, write_msr};
+
+    #[inline(always)]
+    pub fn read() -> u64 {
+        unsafe { read_msr(0xC0000101) }
+    }
+
+    #[inline(always)]
+    pub unsafe fn write(value: u64) {
+        write_msr(0xC0000101, value);
+    }
+}
+
+/// The base address of the `GS` segment that will be swapped in with `swapgs`
+pub mod ia32_kernel_gs_base {
+    use super::{read_msr, write_msr};
+
+    #[inline(always)]
+    pub fn read() -> u64 {
+        unsafe { read_msr(0xC0000102) }
+    }
+
+    #[inline(always)]
+    pub unsafe fn write(value: u64) {
+        write_msr(0xC0000102, value);
+    }
+}
+
+/// Access to the `FS` and `GS` segment bases.
+///
+/// If `cr4.fsgsbase` is enabled these use the `RDFSBASE`/`WRFSBASE` family of instructions,
+/// otherwise they fall back to reading and writing the MSRs.
+#[cfg(target_pointer_width = "64")]
+pub mod segment_base {
+    use super::{cr4, ia32_fs_base, ia32_gs_base};
+
+    /// Read the current `FS` base
+    #[inline]
+    pub fn read_fs_base() -> u64 {
+        if cr4::is_fsgsbase_set() {
+            let value: u64;
+            unsafe { core::arch::asm!("rdfsbase {0}", out(reg) value) };
+            value
+        } else {
+            ia32_fs_base::read()
+        }
+    }
+
+    /// Set the current `FS` base
+    #[inline]
+    pub unsafe fn write_fs_base(value: u64) {
+        if cr4::is_fsgsbase_set() {
+            unsafe { core::arch::asm!("wrfsbase {0}", in(reg) value) };
+        } else {
+            unsafe { ia32_fs_base::write(value) };
+        }
+    }
+
+    /// Read the current `GS` base
+    #[inline]
+    pub fn read_gs_base() -> u64 {
+        if cr4::is_fsgsbase_set() {
+            let value: u64;
+            unsafe { core::arch::asm!("rdgsbase {0}", out(reg) value) };
+            value
+        } else {
+            ia32_gs_base::read()
+        }
+    }
+
+    /// Set the current `GS` base
+    #[inline]
+    pub unsafe fn write_gs_base(value: u64) {
+        if cr4::is_fsgsbase_set() {
+            unsafe { core::arch::asm!("wrgsbase {0}", in(reg) value) };
+        } else {
+            unsafe { ia32_gs_base::write(value) };
+        }
+    }
+}
+
 pub mod amd_syscall {
     use crate::CpuPrivilege;
 
@@ -0,0 +1,178 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::io::IOPort;
+
+const CMOS_SELECT: IOPort = IOPort::new(0x70);
+const CMOS_DATA: IOPort = IOPort::new(0x71);
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+
+/// Status A bit set while the RTC is updating its registers
+const UPDATE_IN_PROGRESS: u8 = 1 << 7;
+/// Status B bit set when hours are in 24 hour format
+const HOUR_FORMAT_24: u8 = 1 << 1;
+/// Status B bit set when registers are in binary instead of BCD
+const BINARY_MODE: u8 = 1 << 2;
+/// Hours bit set for PM times in 12 hour format
+const HOUR_PM: u8 = 1 << 7;
+
+/// A calendar date and time read from the RTC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RtcTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl RtcTime {
+    /// Seconds since 1970-01-01 00:00:00 UTC, assuming the RTC is set to UTC
+    pub const fn to_unix(&self) -> u64 {
+        // Howard Hinnant's `days_from_civil`, shifted so the year starts in March
+        let year = if self.month <= 2 {
+            self.year as i64 - 1
+        } else {
+            self.year as i64
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let day_of_year =
+            (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era - 719468;
+
+        days as u64 * 86400 + self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64
+    }
+}
+
+unsafe fn read_cmos(reg: u8) -> u8 {
+    // Keep NMIs enabled (bit 7 clear)
+    CMOS_SELECT.write_byte(reg & 0x7F);
+    CMOS_DATA.read_byte()
+}
+
+unsafe fn read_raw() -> [u8; 6] {
+    while read_cmos(REG_STATUS_A) & UPDATE_IN_PROGRESS != 0 {}
+
+    [
+        read_cmos(REG_SECONDS),
+        read_cmos(REG_MINUTES),
+        read_cmos(REG_HOURS),
+        read_cmos(REG_DAY),
+        read_cmos(REG_MONTH),
+        read_cmos(REG_YEAR),
+    ]
+}
+
+const fn from_bcd(value: u8) -> u8 {
+    (value & 0x0F) + (value >> 4) * 10
+}
+
+/// Read the current time from the CMOS real time clock.
+///
+/// The RTC has no century register we can rely on, so years are assumed to be 20xx.
+pub fn read_rtc() -> RtcTime {
+    // The registers could change while we read them, so read until two reads agree
+    let (raw, status_b) = unsafe {
+        let mut raw = read_raw();
+        loop {
+            let again = read_raw();
+            if again == raw {
+                break;
+            }
+            raw = again;
+        }
+
+        (raw, read_cmos(REG_STATUS_B))
+    };
+
+    let [mut second, mut minute, mut hour, mut day, mut month, mut year] = raw;
+    let pm = hour & HOUR_PM != 0;
+    hour &= !HOUR_PM;
+
+    if status_b & BINARY_MODE == 0 {
+        second = from_bcd(second);
+        minute = from_bcd(minute);
+        hour = from_bcd(hour);
+        day = from_bcd(day);
+        month = from_bcd(month);
+        year = from_bcd(year);
+    }
+
+    if status_b & HOUR_FORMAT_24 == 0 {
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+
+    RtcTime {
+        year: 2000 + year as u16,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_unix() {
+        let epoch = RtcTime {
+            year: 1970,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        };
+        assert_eq!(epoch.to_unix(), 0);
+
+        let leap_day = RtcTime {
+            year: 2024,
+            month: 2,
+            day: 29,
+            hour: 13,
+            minute: 37,
+            second: 42,
+        };
+        assert_eq!(leap_day.to_unix(), 1709213862);
+    }
+}
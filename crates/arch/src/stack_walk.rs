@@ -0,0 +1,100 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Walking the frame pointer chain of the stack the cpu is running on.
+//!
+//! Frame pointers are only a convention, a function built without them (or a corrupt
+//! stack) leaves anything in `rbp`. So the walk only follows frames within the bounds of
+//! the running stack, which whoever owns the stacks tells us with [`set_stack_bounds`].
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// Finds the bounds of the stack the cpu is running on, see [`set_stack_bounds`]
+static STACK_BOUNDS: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Use `bounds` to find the stack the cpu is running on.
+///
+/// `bounds` is called from anywhere a walk is, including interrupt handlers and the
+/// allocator, so it must not lock or allocate. Until this is called, walks record nothing.
+pub fn set_stack_bounds(bounds: fn() -> Option<Range<u64>>) {
+    STACK_BOUNDS.store(bounds as *mut (), Ordering::Release);
+}
+
+/// The stack the cpu is running on, if it is known
+fn current_stack() -> Option<Range<u64>> {
+    let bounds = STACK_BOUNDS.load(Ordering::Acquire);
+    if bounds.is_null() {
+        return None;
+    }
+
+    let bounds: fn() -> Option<Range<u64>> = unsafe { core::mem::transmute(bounds) };
+    bounds()
+}
+
+/// Record the return addresses of the caller's call stack into `frames`, innermost first.
+///
+/// Unused entries are left as they were, so `frames` should start zeroed.
+#[inline(always)]
+pub fn capture_frames(frames: &mut [u64]) {
+    let frame: u64;
+    unsafe { core::arch::asm!("mov {}, rbp", out(reg) frame) };
+
+    walk_frames(frame, frames);
+}
+
+/// Record the return addresses of the frame chain starting at `frame` into `frames`.
+///
+/// Each frame is the caller's `rbp` followed by the return address. Both have to be
+/// within the running stack, and every frame has to be further up the stack than the one
+/// before it, otherwise the walk ends there.
+pub fn walk_frames(mut frame: u64, frames: &mut [u64]) {
+    let Some(stack) = current_stack() else {
+        return;
+    };
+
+    for return_address in frames.iter_mut() {
+        let in_stack = frame >= stack.start
+            && frame
+                .checked_add(2 * size_of::<u64>() as u64)
+                .is_some_and(|end| end <= stack.end);
+        if !in_stack || !frame.is_multiple_of(size_of::<u64>() as u64) {
+            break;
+        }
+
+        let (caller_frame, caller) = unsafe {
+            let frame = frame as *const u64;
+            (frame.read(), frame.add(1).read())
+        };
+
+        *return_address = caller;
+        if caller_frame <= frame {
+            break;
+        }
+        frame = caller_frame;
+    }
+}
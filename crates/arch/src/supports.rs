@@ -87,6 +87,11 @@ pub enum CpuFeature {
     SupportsTm,
     SupportsIa64,
     SupportsPbe,
+    SupportsFsgsbase,
+    SupportsSmep,
+    SupportsSmap,
+    SupportsUmip,
+    SupportsPage1G,
 }
 
 #[non_exhaustive]
@@ -95,6 +100,9 @@ pub enum CpuidRequest {
     VenderString,
     AddressSize,
     Feature,
+    ExtendedFeature,
+    ExtendedProcessorInfo,
+    MonitorMwait,
     None,
 }
 
@@ -151,6 +159,9 @@ impl CpuidRequest {
         match self {
             Self::VenderString => (0, 0, 0, 0),
             Self::Feature => (1, 0, 0, 0),
+            Self::ExtendedFeature => (7, 0, 0, 0),
+            Self::MonitorMwait => (5, 0, 0, 0),
+            Self::ExtendedProcessorInfo => (0x80000001, 0, 0, 0),
             Self::AddressSize => (0x80000008, 0, 0, 0),
             _ => panic!("todo"),
         }
@@ -270,9 +281,50 @@ pub fn does_cpu_support(feature: CpuFeature) -> bool {
         CpuFeature::SupportsTm => edx & (1 << 29) != 0,
         CpuFeature::SupportsIa64 => edx & (1 << 30) != 0,
         CpuFeature::SupportsPbe => edx & (1 << 31) != 0,
+
+        CpuFeature::SupportsFsgsbase => {
+            let (_, ebx, _, _) = cpuid(CpuidRequest::ExtendedFeature);
+            ebx & (1 << 0) != 0
+        }
+        CpuFeature::SupportsSmep => {
+            let (_, ebx, _, _) = cpuid(CpuidRequest::ExtendedFeature);
+            ebx & (1 << 7) != 0
+        }
+        CpuFeature::SupportsSmap => {
+            let (_, ebx, _, _) = cpuid(CpuidRequest::ExtendedFeature);
+            ebx & (1 << 20) != 0
+        }
+        CpuFeature::SupportsUmip => {
+            let (_, _, ecx, _) = cpuid(CpuidRequest::ExtendedFeature);
+            ecx & (1 << 2) != 0
+        }
+        CpuFeature::SupportsPage1G => {
+            let (_, _, _, edx) = cpuid(CpuidRequest::ExtendedProcessorInfo);
+            edx & (1 << 26) != 0
+        }
     }
 }
 
+/// Get the number of mwait sub-states this processor has for each C-state.
+///
+/// Index 0 is C0, index 1 is C1, and so on. Returns `None` if this processor does not
+/// enumerate its mwait C-states.
+#[inline]
+pub fn mwait_sub_states() -> Option<[u8; 8]> {
+    let (max_leaf, ..) = cpuid(CpuidRequest::VenderString);
+    if max_leaf < 5 {
+        return None;
+    }
+
+    let (_, _, ecx, edx) = cpuid(CpuidRequest::MonitorMwait);
+    // Bit 0 says if the C-state enumeration in edx is valid
+    if ecx & 1 == 0 {
+        return None;
+    }
+
+    Some(core::array::from_fn(|i| ((edx >> (i * 4)) & 0xF) as u8))
+}
+
 /// Get the number of bits for this processors physical address size
 #[inline]
 pub fn physical_address_size_bits() -> usize {
@@ -339,6 +339,41 @@ pub static BUILT_IN_FONT: [[u8; 13]; 96] = [
     ],
 ];
 
+/// Glyphs outside of ascii, in the same layout as [`BUILT_IN_FONT`].
+#[link_section = ".font"]
+pub static EXTRA_GLYPHS: [(char, [u8; 13]); 5] = [
+    (
+        '\u{B0}', // degree
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x38, 0x6c, 0x6c, 0x38,
+        ],
+    ),
+    (
+        '\u{B1}', // plus-minus
+        [
+            0x00, 0x00, 0xff, 0x00, 0x18, 0x18, 0xff, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '\u{B5}', // micro
+        [
+            0xc0, 0xc0, 0xfe, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0x00, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '\u{B7}', // middle dot
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        char::REPLACEMENT_CHARACTER,
+        [
+            0x00, 0x00, 0xfe, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0xfe,
+        ],
+    ),
+];
+
 /// # Built In Font
 /// A font that is compiled into the executable. Useful for boot-loader's and early
 /// kernel font.
@@ -356,6 +391,20 @@ impl BinFont {
     /// # Get Glyph
     /// Gets the glyph data for a given input char.
     pub fn get_glyph(c: char) -> Option<&'static [u8; Self::HEIGHT]> {
-        BUILT_IN_FONT.get(c as usize - 32)
+        match c {
+            ' '..='~' => BUILT_IN_FONT.get(c as usize - 32),
+            _ => EXTRA_GLYPHS
+                .iter()
+                .find(|(glyph_char, _)| *glyph_char == c)
+                .map(|(_, glyph)| glyph),
+        }
+    }
+
+    /// # Get Glyph Or Replacement
+    /// Gets the glyph data for `c`, or the replacement glyph if the font doesn't have it.
+    pub fn get_glyph_or_replacement(c: char) -> &'static [u8; Self::HEIGHT] {
+        Self::get_glyph(c)
+            .or_else(|| Self::get_glyph(char::REPLACEMENT_CHARACTER))
+            .unwrap()
     }
 }
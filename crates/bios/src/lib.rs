@@ -385,6 +385,37 @@ pub mod video {
     }
 }
 
+/// The BIOS's timer tick count, which IRQ0 keeps updated in the BDA.
+pub mod timer {
+    use core::time::Duration;
+
+    /// Ticks since midnight, as a u32
+    const BDA_TIMER_TICKS: *const u32 = 0x46C as *const u32;
+    /// The tick count wraps back to zero after this many ticks (24 hours)
+    pub const TICKS_PER_DAY: u32 = 0x1800B0;
+    /// The PIT runs the BIOS timer at 1193182 / 65536 Hz
+    const NS_PER_TICK: u64 = 54_925_493;
+
+    /// The current tick count
+    pub fn ticks() -> u32 {
+        unsafe { BDA_TIMER_TICKS.read_volatile() }
+    }
+
+    /// The time that has passed since the tick count was `start`.
+    ///
+    /// This can only tell up to a day has passed, since the tick count wraps at midnight.
+    pub fn elapsed_since(start: u32) -> Duration {
+        let now = ticks();
+        let elapsed = if now >= start {
+            now - start
+        } else {
+            now + (TICKS_PER_DAY - start)
+        };
+
+        Duration::from_nanos(elapsed as u64 * NS_PER_TICK)
+    }
+}
+
 pub mod disk {
     use crate::BiosStatus;
     use core::ptr::addr_of;
@@ -475,8 +506,41 @@ pub mod memory {
     impl MemoryEntry {
         pub const REGION_RESERVED: u32 = 0x2;
         pub const REGION_FREE: u32 = 0x1;
+
+        /// ACPI 3.0 extended attribute marking the entry as valid.
+        const ATTRIBUTE_ENABLED: u32 = 0x1;
+
+        const fn new(base_address: u64, region_length: u64, region_type: u32) -> Self {
+            Self {
+                base_address,
+                region_length,
+                region_type,
+                acpi_attributes: Self::ATTRIBUTE_ENABLED,
+            }
+        }
+
+        const fn end(&self) -> u64 {
+            self.base_address + self.region_length
+        }
     }
 
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * KIB;
+
+    /// The BIOS data area word holding the segment of the EBDA.
+    const BDA_EBDA_SEGMENT: *const u16 = 0x40E as *const u16;
+    /// The BIOS data area word holding the KiB of conventional memory below the EBDA.
+    const BDA_CONVENTIONAL_KIB: *const u16 = 0x413 as *const u16;
+
+    /// The region some old chipsets leave unmapped for ISA devices.
+    const ISA_HOLE: (u64, u64) = (15 * MIB, 16 * MIB);
+
+    /// Start of the video memory and option ROMs, which are never usable RAM.
+    const UPPER_MEMORY_START: u64 = 0xA0000;
+
+    /// How many entries [`read_memory_map`] may add on top of the firmware's map.
+    pub const RESERVED_ENTRIES: usize = 4;
+
     // FIXME: We should not be returning a Result with BiosStatus as the error, but instead
     //        it should be a type containing the error kind.
     unsafe fn read_region(ptr: *mut MemoryEntry, ebx: u32) -> Result<u32, BiosStatus> {
@@ -525,4 +589,303 @@ pub mod memory {
 
         Ok(memory.len())
     }
+
+    /// # Read E801
+    /// Reads the KiB of memory between 1MiB and 16MiB, and the amount of 64KiB blocks
+    /// above 16MiB using Bios-Call-0x15's 0xE801 command.
+    pub fn read_e801() -> Result<(u64, u64), BiosStatus> {
+        use crate::int_0x15;
+        use arch::registers::Regs32;
+
+        let mut regs = Regs32 {
+            eax: 0xE801,
+            ..Regs32::default()
+        };
+
+        match unsafe { int_0x15(&mut regs, 0) } {
+            BiosStatus::Success => (),
+            err => return Err(err),
+        }
+
+        // Some BIOSes only fill in AX/BX, and others only CX/DX
+        let (low_kib, high_blocks) = if regs.ecx != 0 || regs.edx != 0 {
+            (regs.ecx & 0xFFFF, regs.edx & 0xFFFF)
+        } else {
+            (regs.eax & 0xFFFF, regs.ebx & 0xFFFF)
+        };
+
+        Ok((low_kib as u64, high_blocks as u64))
+    }
+
+    /// # Read 88h
+    /// Reads the KiB of memory above 1MiB using Bios-Call-0x15's 0x88 command.
+    ///
+    /// This can report at most 64MiB.
+    pub fn read_88h() -> Result<u64, BiosStatus> {
+        use crate::int_0x15;
+        use arch::registers::Regs32;
+
+        let mut regs = Regs32 {
+            eax: 0x8800,
+            ..Regs32::default()
+        };
+
+        match unsafe { int_0x15(&mut regs, 0) } {
+            BiosStatus::Success => (),
+            err => return Err(err),
+        }
+        Ok((regs.eax & 0xFFFF) as u64)
+    }
+
+    /// Push `entry` to the end of the map, dropping it if it is empty or the map is
+    /// already full.
+    fn push_entry(memory: &mut [MemoryEntry], len: &mut usize, entry: MemoryEntry) {
+        if entry.region_length == 0 {
+            return;
+        }
+
+        if let Some(slot) = memory.get_mut(*len) {
+            *slot = entry;
+            *len += 1;
+        }
+    }
+
+    /// How much memory the legacy BIOS calls found above 1MiB.
+    #[derive(Clone, Copy, Debug)]
+    enum LegacySizes {
+        /// The KiB between 1MiB and 16MiB, and the 64KiB blocks above 16MiB
+        E801 { low_kib: u64, high_blocks: u64 },
+        /// The KiB above 1MiB
+        Extended88h { kib: u64 },
+    }
+
+    /// Build a memory map from E801, or 88h if that is not supported either.
+    fn read_legacy_mapping(memory: &mut [MemoryEntry]) -> Result<usize, BiosStatus> {
+        let sizes = match read_e801() {
+            Ok((low_kib, high_blocks)) => LegacySizes::E801 {
+                low_kib,
+                high_blocks,
+            },
+            Err(_) => LegacySizes::Extended88h { kib: read_88h()? },
+        };
+
+        Ok(build_legacy_mapping(
+            memory,
+            conventional_memory_end(),
+            sizes,
+        ))
+    }
+
+    /// Lay out the free memory the legacy BIOS calls found, returning how many entries
+    /// were written to `memory`.
+    fn build_legacy_mapping(
+        memory: &mut [MemoryEntry],
+        conventional_end: u64,
+        sizes: LegacySizes,
+    ) -> usize {
+        let mut len = 0;
+        let free = MemoryEntry::REGION_FREE;
+
+        push_entry(
+            memory,
+            &mut len,
+            MemoryEntry::new(0, conventional_end, free),
+        );
+
+        match sizes {
+            LegacySizes::E801 {
+                low_kib,
+                high_blocks,
+            } => {
+                push_entry(memory, &mut len, MemoryEntry::new(MIB, low_kib * KIB, free));
+                push_entry(
+                    memory,
+                    &mut len,
+                    MemoryEntry::new(16 * MIB, high_blocks * 64 * KIB, free),
+                );
+            }
+            LegacySizes::Extended88h { kib } => {
+                push_entry(memory, &mut len, MemoryEntry::new(MIB, kib * KIB, free));
+            }
+        }
+
+        len
+    }
+
+    /// # Free Region Containing
+    /// Find the free memory around `addr`, as its `(base, length)`.
+    ///
+    /// The region is cut short where a reserved entry overlaps it, since reserved memory
+    /// takes priority over free memory in the map.
+    pub fn free_region_containing(memory: &[MemoryEntry], addr: u64) -> Option<(u64, u64)> {
+        let region = memory.iter().find(|entry| {
+            entry.region_type == MemoryEntry::REGION_FREE
+                && (entry.base_address..entry.end()).contains(&addr)
+        })?;
+
+        let mut start = region.base_address;
+        let mut end = region.end();
+        for reserved in memory
+            .iter()
+            .filter(|entry| entry.region_type != MemoryEntry::REGION_FREE)
+        {
+            if reserved.base_address >= end || reserved.end() <= start {
+                continue;
+            }
+
+            if reserved.end() <= addr {
+                start = reserved.end();
+            } else if reserved.base_address > addr {
+                end = reserved.base_address;
+            } else {
+                return None;
+            }
+        }
+
+        Some((start, end - start))
+    }
+
+    /// The end of conventional memory, which is where the EBDA starts.
+    fn conventional_memory_end() -> u64 {
+        let ebda = unsafe { BDA_EBDA_SEGMENT.read_volatile() } as u64 * 16;
+        let conventional = unsafe { BDA_CONVENTIONAL_KIB.read_volatile() } as u64 * KIB;
+
+        // The EBDA is always somewhere in the top 128KiB of conventional memory, anything
+        // else means the BDA was not filled in
+        [ebda, conventional]
+            .into_iter()
+            .filter(|addr| (0x80000..UPPER_MEMORY_START).contains(addr))
+            .min()
+            .unwrap_or(0x9FC00)
+    }
+
+    /// # Read Memory Map
+    /// Reads the computer's memory map, falling back to older BIOS calls when E820 fails
+    /// or reports no memory above 1MiB.
+    ///
+    /// The BIOS data areas and upper memory are always reported as reserved, and so is the
+    /// 15MiB to 16MiB ISA hole when nothing else describes it. Overlapping entries are left
+    /// for the consumer to resolve, with reserved memory taking priority.
+    ///
+    /// The last [`RESERVED_ENTRIES`] entries of `memory` are kept for the extra regions.
+    pub fn read_memory_map(memory: &mut [MemoryEntry]) -> Result<usize, BiosStatus> {
+        let firmware_len = memory.len().saturating_sub(RESERVED_ENTRIES);
+        let (firmware, _) = memory.split_at_mut(firmware_len);
+
+        let mut len = match read_mapping(firmware) {
+            Ok(len)
+                if firmware[..len].iter().any(|entry| {
+                    entry.region_type == MemoryEntry::REGION_FREE && entry.end() > MIB
+                }) =>
+            {
+                len
+            }
+            _ => read_legacy_mapping(firmware)?,
+        };
+
+        let reserved = MemoryEntry::REGION_RESERVED;
+        let ebda_start = conventional_memory_end();
+
+        // Interrupt vector table and BIOS data area
+        push_entry(memory, &mut len, MemoryEntry::new(0, 0x500, reserved));
+        push_entry(
+            memory,
+            &mut len,
+            MemoryEntry::new(ebda_start, UPPER_MEMORY_START - ebda_start, reserved),
+        );
+        push_entry(
+            memory,
+            &mut len,
+            MemoryEntry::new(UPPER_MEMORY_START, MIB - UPPER_MEMORY_START, reserved),
+        );
+
+        let has_memory_above_hole = memory[..len]
+            .iter()
+            .any(|entry| entry.region_type == MemoryEntry::REGION_FREE && entry.end() > ISA_HOLE.1);
+        let hole_described = memory[..len]
+            .iter()
+            .any(|entry| entry.base_address < ISA_HOLE.1 && entry.end() > ISA_HOLE.0);
+
+        if has_memory_above_hole && !hole_described {
+            push_entry(
+                memory,
+                &mut len,
+                MemoryEntry::new(ISA_HOLE.0, ISA_HOLE.1 - ISA_HOLE.0, reserved),
+            );
+        }
+
+        Ok(len)
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        extern crate std;
+        use std::vec::Vec;
+
+        const EMPTY: MemoryEntry = MemoryEntry::new(0, 0, 0);
+
+        fn ranges(memory: &[MemoryEntry]) -> Vec<(u64, u64)> {
+            memory
+                .iter()
+                .map(|entry| (entry.base_address, entry.region_length))
+                .collect()
+        }
+
+        #[test]
+        fn test_e801_mapping() {
+            let mut memory = [EMPTY; 8];
+            let sizes = LegacySizes::E801 {
+                low_kib: 15 * 1024,
+                high_blocks: 16,
+            };
+            let len = build_legacy_mapping(&mut memory, 0x9FC00, sizes);
+
+            assert_eq!(
+                ranges(&memory[..len]),
+                [(0, 0x9FC00), (MIB, 15 * MIB), (16 * MIB, MIB)]
+            );
+            assert_eq!(
+                free_region_containing(&memory[..len], 0x200000),
+                Some((MIB, 15 * MIB))
+            );
+        }
+
+        #[test]
+        fn test_empty_sizes_are_skipped() {
+            let mut memory = [EMPTY; 8];
+            let sizes = LegacySizes::E801 {
+                low_kib: 0,
+                high_blocks: 0,
+            };
+            let len = build_legacy_mapping(&mut memory, 0x9FC00, sizes);
+            assert_eq!(ranges(&memory[..len]), [(0, 0x9FC00)]);
+
+            let sizes = LegacySizes::Extended88h { kib: 0 };
+            let len = build_legacy_mapping(&mut memory, 0x9FC00, sizes);
+            assert_eq!(ranges(&memory[..len]), [(0, 0x9FC00)]);
+            assert_eq!(free_region_containing(&memory[..len], 0x200000), None);
+        }
+
+        #[test]
+        fn test_free_region_avoids_reserved() {
+            let free = MemoryEntry::REGION_FREE;
+            let reserved = MemoryEntry::REGION_RESERVED;
+            let memory = [
+                MemoryEntry::new(MIB, 31 * MIB, free),
+                MemoryEntry::new(ISA_HOLE.0, ISA_HOLE.1 - ISA_HOLE.0, reserved),
+                MemoryEntry::new(MIB, MIB / 2, reserved),
+            ];
+
+            assert_eq!(
+                free_region_containing(&memory, 0x200000),
+                Some((MIB + MIB / 2, 13 * MIB + MIB / 2))
+            );
+            assert_eq!(
+                free_region_containing(&memory, 20 * MIB),
+                Some((16 * MIB, 16 * MIB))
+            );
+            assert_eq!(free_region_containing(&memory, 15 * MIB), None);
+        }
+    }
 }
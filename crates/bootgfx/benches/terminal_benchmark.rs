@@ -0,0 +1,76 @@
+use bootgfx::{
+    Color, Framebuffer, PixelFormat,
+    glyph_cache::GlyphCache,
+    terminal::{CELL_HEIGHT, CELL_WIDTH, Terminal},
+};
+use core::fmt::Write;
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const WIDTH: usize = 1920;
+const HEIGHT: usize = 1080;
+const LINE: &str = "[ 12.345678] + Mapped 1920x1080 32bpp framebuffer at 0xffffff0000000000\n";
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Framebuffer Console");
+    let mut memory = vec![0u8; WIDTH * HEIGHT * 4];
+    // `memory` stands in for the screen, but it is normal memory where reads are as fast
+    // as writes. A real framebuffer is write-combining and reading it back is very slow, so
+    // like the kernel's consoles this draws through a shadow and never reads `memory`.
+    let mut shadow = vec![0u8; WIDTH * HEIGHT * 4];
+    let mut framebuffer = unsafe {
+        Framebuffer::new(
+            memory.as_mut_ptr(),
+            WIDTH,
+            HEIGHT,
+            WIDTH * 4,
+            PixelFormat::XRGB8888,
+        )
+        .with_shadow(shadow.as_mut_ptr())
+    };
+
+    let mut terminal = Terminal::new(0, 0);
+    terminal.fit_to(&framebuffer);
+    let (columns, rows) = (terminal.columns(), terminal.rows());
+
+    // How every cell was drawn before the glyph cache, for comparison
+    group.bench_function("Redraw screen glyph by glyph", |f| {
+        f.iter(|| {
+            for y in 0..rows {
+                framebuffer.draw_rec(
+                    0,
+                    y * CELL_HEIGHT,
+                    columns * CELL_WIDTH,
+                    CELL_HEIGHT,
+                    Color::QUANTUM_BACKGROUND,
+                );
+                for (x, c) in LINE.chars().take(columns).enumerate() {
+                    framebuffer.draw_glyph(x * CELL_WIDTH, y * CELL_HEIGHT, c, Color::WHITE);
+                }
+            }
+        })
+    });
+
+    let mut glyphs = GlyphCache::new();
+    group.bench_function("Redraw screen from glyph cache", |f| {
+        for _ in 0..rows {
+            terminal.write_str(LINE).unwrap();
+        }
+
+        f.iter(|| {
+            terminal.invalidate();
+            terminal.render(&mut framebuffer, &mut glyphs);
+        })
+    });
+
+    group.bench_function("Scroll one line", |f| {
+        f.iter(|| {
+            terminal.write_str(LINE).unwrap();
+            terminal.render(&mut framebuffer, &mut glyphs);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,245 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{Color, Framebuffer, Rect};
+
+/// # Cursor Width
+/// The width of the cursor's bitmap in pixels.
+pub const CURSOR_WIDTH: usize = 11;
+/// # Cursor Height
+/// The height of the cursor's bitmap in pixels.
+pub const CURSOR_HEIGHT: usize = 17;
+
+/// An arrow pointing at its top left corner, `X` is the outline, `O` is the fill, and
+/// `.` lets what is under the cursor show through.
+const ARROW: [&[u8; CURSOR_WIDTH]; CURSOR_HEIGHT] = [
+    b"X..........",
+    b"XX.........",
+    b"XOX........",
+    b"XOOX.......",
+    b"XOOOX......",
+    b"XOOOOX.....",
+    b"XOOOOOX....",
+    b"XOOOOOOX...",
+    b"XOOOOOOOX..",
+    b"XOOOOOOOOX.",
+    b"XOOOOOXXXXX",
+    b"XOOXOOX....",
+    b"XOX.XOOX...",
+    b"XX..XOOX...",
+    b"X....XOOX..",
+    b".....XOOX..",
+    b"......XX...",
+];
+
+/// # Cursor
+/// A mouse pointer drawn in software over whatever is on the framebuffer.
+///
+/// The pixels under the cursor are saved before it is drawn and put back when it moves or
+/// is hidden, so moving it only touches the pixels it covers. Anything else drawing onto
+/// the framebuffer under the cursor should [`Cursor::hide`] it first, and
+/// [`Cursor::show`] it again after.
+pub struct Cursor {
+    x: isize,
+    y: isize,
+    visible: bool,
+    /// The part of the screen the saved pixels came from, while the cursor is drawn
+    saved_area: Option<Rect>,
+    saved: [Color; CURSOR_WIDTH * CURSOR_HEIGHT],
+}
+
+impl Cursor {
+    /// # New
+    /// A hidden cursor at the top left of the screen.
+    pub const fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            visible: false,
+            saved_area: None,
+            saved: [Color(0); CURSOR_WIDTH * CURSOR_HEIGHT],
+        }
+    }
+
+    /// # Position
+    /// Where the tip of the cursor points.
+    pub const fn position(&self) -> (isize, isize) {
+        (self.x, self.y)
+    }
+
+    /// # Is Visible
+    /// Should the cursor be drawn?
+    pub const fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// # Area
+    /// The part of the screen the cursor's bitmap covers.
+    pub fn area(&self) -> Rect {
+        Rect::new(self.x, self.y, CURSOR_WIDTH, CURSOR_HEIGHT)
+            .expect("The cursor's bitmap is never empty")
+    }
+
+    /// # Show
+    /// Start drawing the cursor, returning the area of the framebuffer that changed.
+    pub fn show(&mut self, framebuffer: &mut Framebuffer) -> Option<Rect> {
+        self.visible = true;
+        self.draw(framebuffer)
+    }
+
+    /// # Hide
+    /// Stop drawing the cursor, putting back what was under it. Returns the area of the
+    /// framebuffer that changed.
+    pub fn hide(&mut self, framebuffer: &mut Framebuffer) -> Option<Rect> {
+        self.visible = false;
+        self.restore(framebuffer)
+    }
+
+    /// # Move To
+    /// Point the cursor at `x`, `y`, returning the area of the framebuffer that changed.
+    pub fn move_to(&mut self, framebuffer: &mut Framebuffer, x: isize, y: isize) -> Option<Rect> {
+        if (x, y) == (self.x, self.y) {
+            return None;
+        }
+
+        let restored = self.restore(framebuffer);
+        self.x = x;
+        self.y = y;
+        let drawn = self.visible.then(|| self.draw(framebuffer)).flatten();
+
+        match (restored, drawn) {
+            (Some(restored), Some(drawn)) => Some(restored.union(&drawn)),
+            (restored, drawn) => restored.or(drawn),
+        }
+    }
+
+    /// # Forget Screen
+    /// The framebuffer was replaced or redrawn under the cursor, so what was saved under
+    /// it is stale. The next [`Cursor::show`] saves the pixels again.
+    pub fn forget_screen(&mut self) {
+        self.saved_area = None;
+    }
+
+    /// Save what is under the cursor, and draw it on top
+    fn draw(&mut self, framebuffer: &mut Framebuffer) -> Option<Rect> {
+        // Put back what is under the cursor first, so it is never saved over itself
+        self.restore(framebuffer);
+
+        let area = self
+            .area()
+            .clip(framebuffer.width(), framebuffer.height())?;
+        for (x, y, index) in self.pixels(area) {
+            self.saved[index] = framebuffer.read_pixel(x, y).unwrap_or(Color(0));
+
+            let (column, row) = (index % CURSOR_WIDTH, index / CURSOR_WIDTH);
+            match ARROW[row][column] {
+                b'X' => framebuffer.draw_pixel(x, y, Color::from_rgb(0, 0, 0)),
+                b'O' => framebuffer.draw_pixel(x, y, Color::WHITE),
+                _ => (),
+            }
+        }
+
+        self.saved_area = Some(area);
+        Some(area)
+    }
+
+    /// Put back the pixels saved under the cursor, if it is drawn
+    fn restore(&mut self, framebuffer: &mut Framebuffer) -> Option<Rect> {
+        let area = self.saved_area.take()?;
+        for (x, y, index) in self.pixels(area) {
+            framebuffer.draw_pixel(x, y, self.saved[index]);
+        }
+
+        Some(area)
+    }
+
+    /// Each pixel of `area` (which is on the screen), and its index into the bitmap
+    fn pixels(&self, area: Rect) -> impl Iterator<Item = (usize, usize, usize)> {
+        // The clipped area never starts before the cursor
+        let first_column = area.x.abs_diff(self.x);
+        let first_row = area.y.abs_diff(self.y);
+
+        (0..area.height.get()).flat_map(move |row| {
+            (0..area.width.get()).map(move |column| {
+                let index = (first_row + row) * CURSOR_WIDTH + first_column + column;
+
+                (area.x as usize + column, area.y as usize + row, index)
+            })
+        })
+    }
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{rect, PixelFormat};
+
+    const SIZE: usize = 32;
+
+    #[test]
+    fn test_moving_restores_pixels() {
+        let mut memory = [0u32; SIZE * SIZE];
+        memory
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, pixel)| *pixel = i as u32);
+        let original = memory;
+
+        let mut framebuffer = unsafe {
+            Framebuffer::new(
+                memory.as_mut_ptr().cast(),
+                SIZE,
+                SIZE,
+                SIZE * 4,
+                PixelFormat::XRGB8888,
+            )
+        };
+        let mut cursor = Cursor::new();
+
+        assert_eq!(cursor.show(&mut framebuffer), rect!(0, 0, 11, 17));
+        assert_eq!(framebuffer.read_pixel(0, 0).unwrap().0, 0xFF000000);
+        assert_eq!(framebuffer.read_pixel(1, 2).unwrap().0, 0xFFFFFFFF);
+
+        // Partly off the left and bottom of the screen
+        assert_eq!(
+            cursor.move_to(&mut framebuffer, -4, 20),
+            rect!(-4, 0, 15, 32)
+                .unwrap()
+                .intersect(&framebuffer.bounds().unwrap())
+        );
+        assert_eq!(framebuffer.read_pixel(0, 0).unwrap().0 & 0xFFFFFF, 0);
+        cursor.move_to(&mut framebuffer, SIZE as isize - 1, SIZE as isize - 1);
+        assert_eq!(cursor.hide(&mut framebuffer), rect!(31, 31, 1, 1));
+
+        drop(framebuffer);
+        assert_eq!(memory, original);
+    }
+}
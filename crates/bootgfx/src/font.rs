@@ -0,0 +1,138 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use binfont::BinFont;
+
+/// # Glyph
+/// The pixels of one character, each row is a byte with the leftmost pixel in the top bit.
+#[derive(Clone, Copy)]
+pub struct Glyph<'a> {
+    /// The rows of pixels, bottom row first
+    pub rows: &'a [u8],
+    /// The first column of each row that is drawn
+    pub left: usize,
+    /// How many pixels to move right after drawing this glyph
+    pub advance: usize,
+}
+
+/// # Font
+/// A bitmap font with glyphs up to 8 pixels wide.
+pub trait Font {
+    /// # Height
+    /// The height of every glyph in pixels.
+    fn height(&self) -> usize;
+
+    /// # Glyph
+    /// The glyph for `c`, or a replacement glyph if the font doesn't have one.
+    fn glyph(&self, c: char) -> Glyph<'_>;
+
+    /// # Measure
+    /// The width in pixels `text` takes up when drawn in this font.
+    fn measure(&self, text: &str) -> usize {
+        text.chars().map(|c| self.glyph(c).advance).sum()
+    }
+}
+
+/// # Monospace
+/// The built-in font, where every glyph is [`BinFont::WIDTH`] pixels wide.
+pub struct Monospace;
+
+impl Font for Monospace {
+    fn height(&self) -> usize {
+        BinFont::HEIGHT
+    }
+
+    fn glyph(&self, c: char) -> Glyph<'_> {
+        Glyph {
+            rows: BinFont::get_glyph_or_replacement(c),
+            left: 0,
+            advance: BinFont::WIDTH,
+        }
+    }
+}
+
+/// # Proportional
+/// The built-in font, where each glyph is only as wide as its pixels with a pixel of
+/// spacing after it.
+pub struct Proportional;
+
+impl Proportional {
+    /// The advance of glyphs without any pixels, like space
+    pub const BLANK_ADVANCE: usize = BinFont::WIDTH / 2;
+}
+
+impl Font for Proportional {
+    fn height(&self) -> usize {
+        BinFont::HEIGHT
+    }
+
+    fn glyph(&self, c: char) -> Glyph<'_> {
+        let rows = BinFont::get_glyph_or_replacement(c);
+        let used_columns = rows.iter().fold(0, |used, row| used | row);
+
+        if used_columns == 0 {
+            return Glyph {
+                rows,
+                left: 0,
+                advance: Self::BLANK_ADVANCE,
+            };
+        }
+
+        let left = used_columns.leading_zeros() as usize;
+        let right = BinFont::WIDTH - used_columns.trailing_zeros() as usize;
+
+        Glyph {
+            rows,
+            left,
+            advance: right - left + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_proportional_advance() {
+        let narrow = Proportional.glyph('i');
+        let wide = Proportional.glyph('W');
+
+        assert!(narrow.advance < wide.advance);
+        assert!(wide.advance <= BinFont::WIDTH + 1);
+        assert_eq!(Proportional.glyph(' ').advance, Proportional::BLANK_ADVANCE);
+        assert_eq!(Monospace.measure("iW"), 2 * BinFont::WIDTH);
+        assert_eq!(Proportional.measure("iW"), narrow.advance + wide.advance);
+    }
+
+    #[test]
+    fn test_missing_glyphs_are_replaced() {
+        let replacement = Monospace.glyph(char::REPLACEMENT_CHARACTER).rows;
+
+        assert_eq!(Monospace.glyph('\u{1F980}').rows, replacement);
+        assert_eq!(Monospace.glyph('\x07').rows, replacement);
+        assert_ne!(Monospace.glyph('\u{B5}').rows, replacement);
+    }
+}
@@ -0,0 +1,196 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    font::{Font, Monospace},
+    terminal::{CELL_HEIGHT, CELL_WIDTH},
+    Color, PixelFormat,
+};
+
+/// The number of glyphs the cache holds, enough for every ASCII character in one color.
+pub const GLYPH_CACHE_SLOTS: usize = 128;
+/// The most bytes a cell can take, at 4 bytes per pixel.
+const CELL_BYTES: usize = CELL_WIDTH * CELL_HEIGHT * 4;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct GlyphKey {
+    c: char,
+    foreground: u32,
+    background: u32,
+}
+
+struct CacheSlot {
+    key: Option<GlyphKey>,
+    pixels: [u8; CELL_BYTES],
+}
+
+/// # Glyph Cache
+/// Whole terminal cells (a glyph over its background), already encoded into the
+/// framebuffer's pixel format, so drawing a cell is a copy of each of its rows.
+///
+/// Each glyph can only be in one slot, so a glyph replaces whichever glyph was using its
+/// slot before.
+pub struct GlyphCache {
+    format: Option<PixelFormat>,
+    slots: [CacheSlot; GLYPH_CACHE_SLOTS],
+    hits: u64,
+    misses: u64,
+}
+
+impl GlyphCache {
+    pub const fn new() -> Self {
+        Self {
+            format: None,
+            slots: [const {
+                CacheSlot {
+                    key: None,
+                    pixels: [0; CELL_BYTES],
+                }
+            }; GLYPH_CACHE_SLOTS],
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// # Cell
+    /// The pixels of a cell showing `c`, packed into rows of [`CELL_WIDTH`] pixels in
+    /// `format`.
+    pub fn cell(
+        &mut self,
+        c: char,
+        foreground: Color,
+        background: Color,
+        format: PixelFormat,
+    ) -> &[u8] {
+        if self.format != Some(format) {
+            self.slots.iter_mut().for_each(|slot| slot.key = None);
+            self.format = Some(format);
+        }
+
+        let key = GlyphKey {
+            c,
+            foreground: foreground.0,
+            background: background.0,
+        };
+        // For a single pair of colors, every ASCII character gets its own slot
+        let index = (c as u32 ^ key.foreground.rotate_left(7) ^ key.background.rotate_left(13))
+            as usize
+            % GLYPH_CACHE_SLOTS;
+
+        let slot = &mut self.slots[index];
+        let len = CELL_WIDTH * CELL_HEIGHT * format.bytes_per_pixel();
+        if slot.key == Some(key) {
+            self.hits += 1;
+            return &slot.pixels[..len];
+        }
+
+        self.misses += 1;
+        slot.key = Some(key);
+        encode_cell(&mut slot.pixels[..len], c, foreground, background, format);
+
+        &slot.pixels[..len]
+    }
+
+    /// # Stats
+    /// How many cells were found in the cache, and how many had to be drawn.
+    pub const fn stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+}
+
+impl Default for GlyphCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draw the cell for `c` into `pixels`
+fn encode_cell(
+    pixels: &mut [u8],
+    c: char,
+    foreground: Color,
+    background: Color,
+    format: PixelFormat,
+) {
+    let bytes_per_pixel = format.bytes_per_pixel();
+    let foreground = format.encode(foreground).to_le_bytes();
+    let background = format.encode(background).to_le_bytes();
+    let glyph = Monospace.glyph(c);
+
+    for (y, row_pixels) in pixels
+        .chunks_exact_mut(CELL_WIDTH * bytes_per_pixel)
+        .enumerate()
+    {
+        // Glyph rows are stored bottom row first, and are shorter than the cell
+        let row = glyph
+            .rows
+            .len()
+            .checked_sub(y + 1)
+            .map_or(0, |index| glyph.rows[index]);
+
+        for (x, pixel) in row_pixels.chunks_exact_mut(bytes_per_pixel).enumerate() {
+            let color = if (row >> (7 - x)) & 1 != 0 {
+                &foreground
+            } else {
+                &background
+            };
+
+            pixel.copy_from_slice(&color[..bytes_per_pixel]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cache_hits_same_glyph() {
+        let mut cache = GlyphCache::new();
+        let format = PixelFormat::XRGB8888;
+
+        let first = cache.cell('A', Color::WHITE, Color(0), format).to_vec();
+        let second = cache.cell('A', Color::WHITE, Color(0), format).to_vec();
+        assert_eq!(first, second);
+        assert_eq!(cache.stats(), (1, 1));
+
+        // Same character, different color, must not reuse the white glyph
+        let green = cache
+            .cell('A', Color::ALOE_GREEN, Color(0), format)
+            .to_vec();
+        assert_ne!(first, green);
+        assert_eq!(cache.stats(), (1, 2));
+    }
+
+    #[test]
+    fn test_blank_cell_is_background() {
+        let mut cache = GlyphCache::new();
+        let background = Color::from_rgb(1, 2, 3);
+        let cell = cache.cell(' ', Color::WHITE, background, PixelFormat::RGB888);
+
+        assert_eq!(cell.len(), CELL_WIDTH * CELL_HEIGHT * 3);
+        assert!(cell.as_chunks::<3>().0.iter().all(|pixel| pixel == &[3, 2, 1]));
+    }
+}
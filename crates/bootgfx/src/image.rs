@@ -0,0 +1,421 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Color;
+
+#[cfg(any(test, feature = "alloc"))]
+mod png;
+
+/// # Image Error
+/// Why an image could not be parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageError {
+    /// The image does not start with the magic of a supported format.
+    InvalidMagic,
+    /// The header is missing a field, or a field is not valid.
+    InvalidHeader,
+    /// Only 8-bit color channels (max value of 255) are supported.
+    UnsupportedMaxValue,
+    /// The image uses a feature of its format that is not supported, like BMP compression
+    /// or interlaced PNGs.
+    UnsupportedFormat,
+    /// The compressed pixels are corrupt.
+    InvalidData,
+    /// There are fewer pixels than the header says there should be.
+    Truncated,
+    /// The image is wider or taller than [`MAX_DIMENSION`].
+    TooLarge,
+}
+
+/// The largest width or height of an image that will be parsed.
+pub const MAX_DIMENSION: usize = 16384;
+
+/// # Image
+/// A decoded image, borrowing its pixels from the image file when it can.
+pub struct Image<'a> {
+    width: usize,
+    height: usize,
+    pixels: Pixels<'a>,
+}
+
+/// Where an image's pixels are stored, and how they are laid out.
+enum Pixels<'a> {
+    /// Packed 8-bit RGB, top row first.
+    Rgb(&'a [u8]),
+    /// 8-bit BGR(X) rows, each padded to `stride` bytes.
+    Bgr {
+        data: &'a [u8],
+        bytes_per_pixel: usize,
+        stride: usize,
+        bottom_up: bool,
+    },
+    /// Already decoded colors, top row first.
+    #[cfg(any(test, feature = "alloc"))]
+    Decoded(alloc::vec::Vec<Color>),
+}
+
+impl<'a> Image<'a> {
+    /// # Parse
+    /// Parse a PPM, BMP, or (with the `alloc` feature) PNG image, picking the format from
+    /// the magic at the start of `bytes`.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ImageError> {
+        match bytes {
+            [b'P', b'6', ..] => Self::parse_ppm(bytes),
+            [b'B', b'M', ..] => Self::parse_bmp(bytes),
+            #[cfg(any(test, feature = "alloc"))]
+            _ if bytes.starts_with(&png::SIGNATURE) => Self::parse_png(bytes),
+            _ => Err(ImageError::InvalidMagic),
+        }
+    }
+
+    /// # Parse PPM
+    /// Parse a binary PPM (`P6`) image with 8-bit color channels.
+    pub fn parse_ppm(bytes: &'a [u8]) -> Result<Self, ImageError> {
+        if !bytes.starts_with(b"P6") {
+            return Err(ImageError::InvalidMagic);
+        }
+
+        let mut cursor = 2;
+        let mut next_field = || -> Result<usize, ImageError> {
+            // Skip whitespace and comments
+            loop {
+                match bytes.get(cursor) {
+                    Some(b) if b.is_ascii_whitespace() => cursor += 1,
+                    Some(b'#') => {
+                        while bytes.get(cursor).is_some_and(|&b| b != b'\n') {
+                            cursor += 1;
+                        }
+                    }
+                    Some(_) => break,
+                    None => return Err(ImageError::InvalidHeader),
+                }
+            }
+
+            let start = cursor;
+            while bytes.get(cursor).is_some_and(|b| b.is_ascii_digit()) {
+                cursor += 1;
+            }
+
+            core::str::from_utf8(&bytes[start..cursor])
+                .ok()
+                .and_then(|field| field.parse().ok())
+                .ok_or(ImageError::InvalidHeader)
+        };
+
+        let width = next_field()?;
+        let height = next_field()?;
+        let max_value = next_field()?;
+
+        if max_value != 255 {
+            return Err(ImageError::UnsupportedMaxValue);
+        }
+
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(ImageError::TooLarge);
+        }
+
+        // A single whitespace byte separates the header from the pixels
+        let pixels = bytes.get(cursor + 1..).ok_or(ImageError::Truncated)?;
+        let pixels_len = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or(ImageError::TooLarge)?;
+        if pixels.len() < pixels_len {
+            return Err(ImageError::Truncated);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels: Pixels::Rgb(&pixels[..pixels_len]),
+        })
+    }
+
+    /// # Parse BMP
+    /// Parse an uncompressed 24-bit or 32-bit BMP image.
+    pub fn parse_bmp(bytes: &'a [u8]) -> Result<Self, ImageError> {
+        if !bytes.starts_with(b"BM") {
+            return Err(ImageError::InvalidMagic);
+        }
+
+        let field = |offset: usize| -> Result<u32, ImageError> {
+            bytes
+                .get(offset..offset + 4)
+                .map(|field| u32::from_le_bytes([field[0], field[1], field[2], field[3]]))
+                .ok_or(ImageError::InvalidHeader)
+        };
+
+        let data_offset = field(10)? as usize;
+        let info_header_len = field(14)?;
+        let width = field(18)? as i32;
+        let height = field(22)? as i32;
+        let bits_per_pixel = field(28)? as u16;
+        let compression = field(30)?;
+
+        // Only the `BITMAPINFOHEADER` and newer headers are supported, not the OS/2 ones
+        if info_header_len < 40 {
+            return Err(ImageError::UnsupportedFormat);
+        }
+
+        if width <= 0 || height == 0 {
+            return Err(ImageError::InvalidHeader);
+        }
+
+        // 32-bit images with bitfields almost always use the BGRA layout, so we assume it
+        let bytes_per_pixel = match (bits_per_pixel, compression) {
+            (24, 0) => 3,
+            (32, 0 | 3) => 4,
+            _ => return Err(ImageError::UnsupportedFormat),
+        };
+
+        // A positive height means the rows are stored from the bottom of the image up
+        let bottom_up = height > 0;
+        let (width, height) = (width as usize, height.unsigned_abs() as usize);
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(ImageError::TooLarge);
+        }
+
+        let row_len = width
+            .checked_mul(bytes_per_pixel)
+            .ok_or(ImageError::TooLarge)?;
+        let stride = row_len.next_multiple_of(4);
+        let data_len = stride
+            .checked_mul(height - 1)
+            .and_then(|len| len.checked_add(row_len))
+            .ok_or(ImageError::TooLarge)?;
+
+        let data = bytes
+            .get(data_offset..)
+            .filter(|data| data.len() >= data_len)
+            .ok_or(ImageError::Truncated)?;
+
+        Ok(Self {
+            width,
+            height,
+            pixels: Pixels::Bgr {
+                data,
+                bytes_per_pixel,
+                stride,
+                bottom_up,
+            },
+        })
+    }
+
+    /// # Parse PNG
+    /// Parse a non-interlaced PNG image with 8-bit color channels.
+    #[cfg(any(test, feature = "alloc"))]
+    pub fn parse_png(bytes: &'a [u8]) -> Result<Self, ImageError> {
+        let (width, height, colors) = png::decode(bytes)?;
+
+        Ok(Self {
+            width,
+            height,
+            pixels: Pixels::Decoded(colors),
+        })
+    }
+
+    /// # Width
+    /// Get the width of the image in pixels.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// Get the height of the image in pixels.
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Pixel
+    /// Get the color of the pixel at some position in the image.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        match &self.pixels {
+            Pixels::Rgb(pixels) => {
+                let offset = (y * self.width + x) * 3;
+                Color::from_rgb(pixels[offset], pixels[offset + 1], pixels[offset + 2])
+            }
+            Pixels::Bgr {
+                data,
+                bytes_per_pixel,
+                stride,
+                bottom_up,
+            } => {
+                let row = if *bottom_up { self.height - 1 - y } else { y };
+                let offset = row * stride + x * bytes_per_pixel;
+                Color::from_rgb(data[offset + 2], data[offset + 1], data[offset])
+            }
+            #[cfg(any(test, feature = "alloc"))]
+            Pixels::Decoded(colors) => colors[y * self.width + x],
+        }
+    }
+}
+
+/// # Encode PPM
+/// Encode `width` by `height` pixels as a binary PPM (`P6`) image, each encoded piece
+/// of the image is passed to `out` as it is made.
+pub fn encode_ppm(
+    width: usize,
+    height: usize,
+    pixel: impl Fn(usize, usize) -> Color,
+    mut out: impl FnMut(&[u8]),
+) {
+    out(b"P6\n");
+    write_decimal(width, &mut out);
+    out(b" ");
+    write_decimal(height, &mut out);
+    out(b"\n255\n");
+
+    for y in 0..height {
+        for x in 0..width {
+            let Color(color) = pixel(x, y);
+            out(&[(color >> 16) as u8, (color >> 8) as u8, color as u8]);
+        }
+    }
+}
+
+fn write_decimal(value: usize, out: &mut impl FnMut(&[u8])) {
+    let mut digits = [0; 20];
+    let mut start = digits.len();
+    let mut value = value;
+
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+
+        if value == 0 {
+            break;
+        }
+    }
+
+    out(&digits[start..]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_ppm() {
+        let ppm = b"P6\n# a comment\n2 1\n255\n\xFF\x00\x00\x00\x00\xFF";
+        let image = Image::parse_ppm(ppm).unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixel(0, 0).0, 0xFFFF0000);
+        assert_eq!(image.pixel(1, 0).0, 0xFF0000FF);
+
+        assert_eq!(
+            Image::parse_ppm(b"P3\n1 1\n255\n").err(),
+            Some(ImageError::InvalidMagic)
+        );
+        assert_eq!(
+            Image::parse_ppm(b"P6\n2 2\n255\n\x00").err(),
+            Some(ImageError::Truncated)
+        );
+        assert_eq!(
+            Image::parse_ppm(b"P6\n4294967296 4294967296\n255\n\x00").err(),
+            Some(ImageError::TooLarge)
+        );
+    }
+
+    #[test]
+    fn test_parse_bmp() {
+        // 2x2, 24-bit, stored bottom up
+        let bmp = [
+            0x42, 0x4d, 0x46, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x36, 0x00, 0x00, 0x00,
+            0x28, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00,
+            0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0xff, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00,
+        ];
+        let image = Image::parse(&bmp).unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.pixel(0, 0).0, 0xFF0000FF);
+        assert_eq!(image.pixel(1, 0).0, 0xFFFFFFFF);
+        assert_eq!(image.pixel(0, 1).0, 0xFFFF0000);
+        assert_eq!(image.pixel(1, 1).0, 0xFF00FF00);
+
+        assert_eq!(
+            Image::parse_bmp(&bmp[..60]).err(),
+            Some(ImageError::Truncated)
+        );
+
+        let mut huge = bmp;
+        huge[18..22].copy_from_slice(&0x7FFF_FFFFu32.to_le_bytes());
+        assert_eq!(Image::parse_bmp(&huge).err(), Some(ImageError::TooLarge));
+    }
+
+    #[test]
+    fn test_parse_png() {
+        // 2x2 RGBA, using the sub and up filters
+        let png = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00,
+            0x00, 0x72, 0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x18, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0xda, 0x63, 0xfc, 0xcf, 0xc0, 0xf0, 0x9f, 0xf1, 0x3f, 0x43, 0x23, 0x13, 0x23, 0xc3,
+            0x7f, 0x10, 0x6c, 0x00, 0x00, 0x3c, 0x9e, 0x07, 0x01, 0x3a, 0x96, 0xbd, 0x1b, 0x00,
+            0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        let image = Image::parse(&png).unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.pixel(0, 0).0, 0xFFFF0000);
+        assert_eq!(image.pixel(1, 0).0, 0x8000FF00);
+        assert_eq!(image.pixel(0, 1).0, 0xFF0000FF);
+        assert_eq!(image.pixel(1, 1).0, 0x00FFFFFF);
+
+        assert_eq!(
+            Image::parse_png(&png[..40]).err(),
+            Some(ImageError::Truncated)
+        );
+
+        // The header CRC is not checked, so only the size needs to change
+        let mut huge = png;
+        huge[16..24].copy_from_slice(&[0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x40, 0x00]);
+        assert_eq!(Image::parse_png(&huge).err(), Some(ImageError::TooLarge));
+    }
+
+    #[test]
+    fn test_encode_ppm() {
+        let mut buffer = [0; 64];
+        let mut len = 0;
+        encode_ppm(
+            12,
+            1,
+            |x, _| Color::from_rgb(x as u8, 0, 0xFF),
+            |bytes| {
+                buffer[len..len + bytes.len()].copy_from_slice(bytes);
+                len += bytes.len();
+            },
+        );
+
+        assert!(buffer.starts_with(b"P6\n12 1\n255\n"));
+
+        let image = Image::parse_ppm(&buffer[..len]).unwrap();
+        assert_eq!((image.width(), image.height()), (12, 1));
+        assert_eq!(image.pixel(5, 0).0, Color::from_rgb(5, 0, 0xFF).0);
+    }
+}
@@ -0,0 +1,227 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{ImageError, MAX_DIMENSION};
+use crate::Color;
+use alloc::{vec, vec::Vec};
+use inflate::InflateError;
+
+/// The bytes every PNG image starts with.
+pub const SIGNATURE: [u8; 8] = *b"\x89PNG\r\n\x1a\n";
+
+/// The most pixels a PNG image can decode into, 64MiB of colors.
+const MAX_DECODED_PIXELS: usize = 4096 * 4096;
+
+/// The kinds of color a PNG image can store.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ColorType {
+    Grayscale,
+    Rgb,
+    Palette,
+    GrayscaleAlpha,
+    Rgba,
+}
+
+impl ColorType {
+    fn from_byte(byte: u8) -> Result<Self, ImageError> {
+        match byte {
+            0 => Ok(Self::Grayscale),
+            2 => Ok(Self::Rgb),
+            3 => Ok(Self::Palette),
+            4 => Ok(Self::GrayscaleAlpha),
+            6 => Ok(Self::Rgba),
+            _ => Err(ImageError::InvalidHeader),
+        }
+    }
+
+    /// The amount of bytes each pixel takes with 8-bit channels.
+    const fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Grayscale | Self::Palette => 1,
+            Self::GrayscaleAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4,
+        }
+    }
+}
+
+/// Decode a PNG image into its width, height, and colors.
+pub fn decode(bytes: &[u8]) -> Result<(usize, usize, Vec<Color>), ImageError> {
+    if !bytes.starts_with(&SIGNATURE) {
+        return Err(ImageError::InvalidMagic);
+    }
+
+    let mut chunks = &bytes[SIGNATURE.len()..];
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+
+    loop {
+        let (len, rest) = chunks
+            .split_first_chunk::<4>()
+            .ok_or(ImageError::Truncated)?;
+        let len = u32::from_be_bytes(*len) as usize;
+        let (kind, rest) = rest.split_first_chunk::<4>().ok_or(ImageError::Truncated)?;
+        let data = rest.get(..len).ok_or(ImageError::Truncated)?;
+
+        // Chunk CRCs are not checked, the zlib stream has its own checksum for the pixels
+        chunks = rest.get(len + 4..).ok_or(ImageError::Truncated)?;
+
+        match kind {
+            b"IHDR" => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => (),
+        }
+    }
+
+    let header = header
+        .filter(|header| header.len() == 13)
+        .ok_or(ImageError::InvalidHeader)?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let (bit_depth, color_type) = (header[8], ColorType::from_byte(header[9])?);
+    let (compression, filter, interlace) = (header[10], header[11], header[12]);
+
+    if width == 0 || height == 0 {
+        return Err(ImageError::InvalidHeader);
+    }
+
+    if bit_depth != 8 || compression != 0 || filter != 0 || interlace != 0 {
+        return Err(ImageError::UnsupportedFormat);
+    }
+
+    // Every pixel is decoded into memory, so the header decides how much we allocate
+    if width > MAX_DIMENSION
+        || height > MAX_DIMENSION
+        || width
+            .checked_mul(height)
+            .is_none_or(|pixels| pixels > MAX_DECODED_PIXELS)
+    {
+        return Err(ImageError::TooLarge);
+    }
+
+    let bytes_per_pixel = color_type.bytes_per_pixel();
+    let stride = width
+        .checked_mul(bytes_per_pixel)
+        .ok_or(ImageError::TooLarge)?;
+    let filtered_len = stride
+        .checked_add(1)
+        .and_then(|row| row.checked_mul(height))
+        .ok_or(ImageError::TooLarge)?;
+
+    // Each row starts with a byte saying which filter it uses
+    let mut filtered = vec![0; filtered_len];
+    let len = inflate::zlib_decompress(&compressed, &mut filtered).map_err(|err| match err {
+        InflateError::Truncated => ImageError::Truncated,
+        _ => ImageError::InvalidData,
+    })?;
+
+    if len != filtered.len() {
+        return Err(ImageError::Truncated);
+    }
+
+    let pixels = unfilter(&filtered, stride, bytes_per_pixel)?;
+    let colors = pixels
+        .chunks_exact(bytes_per_pixel)
+        .map(|pixel| match (color_type, pixel) {
+            (ColorType::Grayscale, &[gray]) => Ok(Color::from_rgba(gray, gray, gray, 0xFF)),
+            (ColorType::GrayscaleAlpha, &[gray, alpha]) => {
+                Ok(Color::from_rgba(gray, gray, gray, alpha))
+            }
+            (ColorType::Rgb, &[red, green, blue]) => Ok(Color::from_rgb(red, green, blue)),
+            (ColorType::Rgba, &[red, green, blue, alpha]) => {
+                Ok(Color::from_rgba(red, green, blue, alpha))
+            }
+            (ColorType::Palette, &[index]) => {
+                let index = index as usize;
+                let rgb = palette
+                    .get(index * 3..index * 3 + 3)
+                    .ok_or(ImageError::InvalidData)?;
+                let alpha = transparency.get(index).copied().unwrap_or(0xFF);
+
+                Ok(Color::from_rgba(rgb[0], rgb[1], rgb[2], alpha))
+            }
+            _ => unreachable!("pixel does not match its color type"),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((width, height, colors))
+}
+
+/// Undo the per-row filters PNG applies before compressing.
+fn unfilter(filtered: &[u8], stride: usize, bytes_per_pixel: usize) -> Result<Vec<u8>, ImageError> {
+    let mut pixels = vec![0; (filtered.len() / (stride + 1)) * stride];
+
+    for (y, row) in filtered.chunks_exact(stride + 1).enumerate() {
+        let (filter, row) = (row[0], &row[1..]);
+        let (above, current) = pixels.split_at_mut(y * stride);
+        let above = above.get(above.len().wrapping_sub(stride)..);
+        let current = &mut current[..stride];
+
+        for i in 0..stride {
+            let left = i
+                .checked_sub(bytes_per_pixel)
+                .map_or(0, |left| current[left]);
+            let up = above.map_or(0, |above| above[i]);
+            let up_left = i
+                .checked_sub(bytes_per_pixel)
+                .and_then(|left| above.map(|above| above[left]))
+                .unwrap_or(0);
+
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(ImageError::InvalidData),
+            };
+
+            current[i] = row[i].wrapping_add(predicted);
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Pick whichever of the neighbouring bytes is closest to `left + up - up_left`.
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let left_distance = (estimate - left as i16).abs();
+    let up_distance = (estimate - up as i16).abs();
+    let up_left_distance = (estimate - up_left as i16).abs();
+
+    if left_distance <= up_distance && left_distance <= up_left_distance {
+        left
+    } else if up_distance <= up_left_distance {
+        up
+    } else {
+        up_left
+    }
+}
@@ -25,12 +25,22 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 #![no_std]
 
-use core::ptr::write_volatile;
+#[cfg(any(test, feature = "alloc"))]
+extern crate alloc;
 
-use binfont::BinFont;
+use core::ptr::{read_volatile, write_volatile};
 
+pub mod cursor;
+pub mod font;
+pub mod glyph_cache;
+pub mod image;
+pub mod rect;
 pub mod terminal;
 
+use font::{Font, Monospace};
+use image::Image;
+pub use rect::Rect;
+
 /// # Color
 /// A color in the binary format (u32 - r: u8, g: u8, b: u8, alpha: u8).
 #[derive(Clone, Copy)]
@@ -39,63 +49,408 @@ pub struct Color(pub u32);
 impl Color {
     pub const WHITE: Self = Self(0xFFFFFFFF);
     pub const QUANTUM_BACKGROUND: Self = Self(0xFF121212);
+    pub const ALOE_GREEN: Self = Self(0xFF5FAF5F);
+    pub const DARK_GREY: Self = Self(0xFF303030);
+
+    /// # From RGB
+    /// Make an opaque color from its red, green, and blue parts.
+    pub const fn from_rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self(0xFF000000 | (red as u32) << 16 | (green as u32) << 8 | blue as u32)
+    }
+
+    /// # From RGBA
+    /// Make a color from its red, green, blue, and alpha parts.
+    pub const fn from_rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        Self((alpha as u32) << 24 | (red as u32) << 16 | (green as u32) << 8 | blue as u32)
+    }
+
+    /// # Alpha
+    /// How opaque this color is, from `0` (fully transparent) to `255`.
+    pub const fn alpha(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    /// # Blend Over
+    /// Draw this color over `below`, mixing them by this color's alpha.
+    pub fn blend_over(self, below: Self) -> Self {
+        let alpha = self.alpha() as u32;
+        let mix = |shift: u32| {
+            let top = (self.0 >> shift) & 0xFF;
+            let bottom = (below.0 >> shift) & 0xFF;
+            ((top * alpha + bottom * (255 - alpha)) / 255) << shift
+        };
+
+        Self(0xFF000000 | mix(16) | mix(8) | mix(0))
+    }
+}
+
+/// # Channel
+/// Where one color channel sits within a pixel.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Channel {
+    /// The bit this channel starts at
+    pub position: u8,
+    /// The number of bits in this channel
+    pub size: u8,
+}
+
+impl Channel {
+    pub const fn new(position: u8, size: u8) -> Self {
+        Self { position, size }
+    }
+
+    /// # Encode
+    /// Place the 8-bit `value` into this channel.
+    const fn encode(self, value: u8) -> u32 {
+        if self.size == 0 {
+            return 0;
+        }
+
+        let scaled = if self.size >= 8 {
+            (value as u32) << (self.size - 8)
+        } else {
+            value as u32 >> (8 - self.size)
+        };
+
+        scaled << self.position
+    }
+
+    /// # Decode
+    /// Read this channel out of `pixel` as an 8-bit value.
+    const fn decode(self, pixel: u32) -> u8 {
+        if self.size == 0 {
+            return 0;
+        }
+
+        let value = (pixel >> self.position) & (u32::MAX >> (32 - self.size as u32));
+        if self.size >= 8 {
+            (value >> (self.size - 8)) as u8
+        } else {
+            // Repeat the top bits into the bottom, so full intensity stays `0xFF`
+            let value = value << (8 - self.size);
+            (value | (value >> self.size)) as u8
+        }
+    }
+}
+
+/// # Pixel Format
+/// How a color is laid out in the framebuffer's memory.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelFormat {
+    pub bits_per_pixel: u8,
+    pub red: Channel,
+    pub green: Channel,
+    pub blue: Channel,
+}
+
+impl PixelFormat {
+    /// 32-bits per pixel, blue in the lowest byte and the top byte unused
+    pub const XRGB8888: Self = Self {
+        bits_per_pixel: 32,
+        red: Channel::new(16, 8),
+        green: Channel::new(8, 8),
+        blue: Channel::new(0, 8),
+    };
+
+    /// 24-bits per pixel, stored as blue, green, then red in memory
+    pub const RGB888: Self = Self {
+        bits_per_pixel: 24,
+        ..Self::XRGB8888
+    };
+
+    /// # Bytes Per Pixel
+    /// The number of bytes each pixel takes in memory.
+    pub const fn bytes_per_pixel(&self) -> usize {
+        (self.bits_per_pixel as usize).div_ceil(8)
+    }
+
+    /// # Is BGR
+    /// Is red in the low bits of the pixel, instead of blue?
+    pub const fn is_bgr(&self) -> bool {
+        self.red.position < self.blue.position
+    }
+
+    /// # Encode
+    /// Convert `color` into this format's pixel value.
+    pub const fn encode(&self, color: Color) -> u32 {
+        let [blue, green, red, _] = color.0.to_le_bytes();
+
+        self.red.encode(red) | self.green.encode(green) | self.blue.encode(blue)
+    }
+
+    /// # Decode
+    /// Convert a pixel in this format back into an opaque color.
+    pub const fn decode(&self, pixel: u32) -> Color {
+        Color::from_rgb(
+            self.red.decode(pixel),
+            self.green.decode(pixel),
+            self.blue.decode(pixel),
+        )
+    }
 }
 
 /// # Framebuffer
 /// A `struct` to draw graphics into framebuffer.
+///
+/// Framebuffer memory is usually mapped write-combining, where reads are uncached and very
+/// slow. Give it a shadow with [`Framebuffer::with_shadow`] and everything is read back from
+/// the shadow instead.
 pub struct Framebuffer {
-    buffer: *mut Color,
+    buffer: *mut u8,
+    /// A copy of the screen in normal memory, kept in sync with `buffer`
+    shadow: Option<*mut u8>,
     height: usize,
     width: usize,
+    pitch: usize,
+    format: PixelFormat,
 }
 
 impl Framebuffer {
-    pub const ALLOWED_BPP: usize = 32;
-
-    /// # New Linear
-    /// Make a new framebuffer based off a linear framebuffer with zero blanking or padding.
-    pub unsafe fn new_linear(
-        buffer: *mut u32,
-        bits_per_pixel: u8,
-        height: usize,
+    /// # New
+    /// Make a new framebuffer based off a linear framebuffer, each row starts `pitch` bytes
+    /// after the last.
+    ///
+    /// # Safety
+    /// `buffer` must point to `pitch * height` bytes of framebuffer memory.
+    pub unsafe fn new(
+        buffer: *mut u8,
         width: usize,
+        height: usize,
+        pitch: usize,
+        format: PixelFormat,
     ) -> Self {
-        assert_eq!(
-            bits_per_pixel,
-            Self::ALLOWED_BPP as u8,
-            "Only 32-bits per pixel is supported!"
+        assert!(
+            (1..=4).contains(&format.bytes_per_pixel()),
+            "Unsupported pixel format {format:?}"
+        );
+        assert!(
+            pitch >= width * format.bytes_per_pixel(),
+            "Framebuffer rows are shorter than its width"
         );
 
         Framebuffer {
-            buffer: buffer.cast(),
+            buffer,
+            shadow: None,
             height,
             width,
+            pitch,
+            format,
+        }
+    }
+
+    /// # With Shadow
+    /// Keep a copy of the screen in `shadow`, and read back from it instead of the
+    /// framebuffer. What is on the screen now is copied into `shadow` first.
+    ///
+    /// # Safety
+    /// `shadow` must point to `pitch * height` bytes that nothing else uses while this
+    /// framebuffer does.
+    pub unsafe fn with_shadow(mut self, shadow: *mut u8) -> Self {
+        unsafe { core::ptr::copy_nonoverlapping(self.buffer, shadow, self.pitch * self.height) };
+        self.shadow = Some(shadow);
+        self
+    }
+
+    /// # Readable
+    /// Where the screen's contents are read back from, the shadow if there is one.
+    fn readable(&self) -> *const u8 {
+        self.shadow.unwrap_or(self.buffer)
+    }
+
+    /// # Copy To
+    /// Copy `len` bytes from `source` to `offset` bytes into the framebuffer and its shadow.
+    ///
+    /// # Safety
+    /// `offset..offset + len` must be within the framebuffer, and `source` must not overlap it.
+    unsafe fn copy_to(&mut self, offset: usize, source: *const u8, len: usize) {
+        unsafe {
+            if let Some(shadow) = self.shadow {
+                core::ptr::copy_nonoverlapping(source, shadow.add(offset), len);
+            }
+            core::ptr::copy_nonoverlapping(source, self.buffer.add(offset), len);
         }
     }
 
     /// # Draw Pixel
     /// Draw a pixel of a color onto the framebuffer.
     pub fn draw_pixel(&mut self, x: usize, y: usize, color: Color) {
-        if x > self.width || y > self.height {
+        if x >= self.width || y >= self.height {
             return;
         }
 
-        let verticality_to_linearity = y * self.width;
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let pixel = self.format.encode(color);
+        let offset = y * self.pitch + x * bytes_per_pixel;
         unsafe {
-            write_volatile(self.buffer.add(verticality_to_linearity + x), color);
+            if let Some(shadow) = self.shadow {
+                core::ptr::copy_nonoverlapping(
+                    pixel.to_le_bytes().as_ptr(),
+                    shadow.add(offset),
+                    bytes_per_pixel,
+                );
+            }
+
+            let ptr = self.buffer.add(offset);
+
+            if bytes_per_pixel == 4 && ptr.cast::<u32>().is_aligned() {
+                write_volatile(ptr.cast::<u32>(), pixel);
+            } else {
+                for (i, byte) in pixel.to_le_bytes()[..bytes_per_pixel].iter().enumerate() {
+                    write_volatile(ptr.add(i), *byte);
+                }
+            }
         };
     }
 
+    /// # Read Pixel
+    /// Read back the color of a pixel on the framebuffer.
+    pub fn read_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let mut bytes = [0; 4];
+        unsafe {
+            let ptr = self.readable().add(y * self.pitch + x * bytes_per_pixel);
+
+            for (i, byte) in bytes[..bytes_per_pixel].iter_mut().enumerate() {
+                *byte = read_volatile(ptr.add(i));
+            }
+        }
+
+        Some(self.format.decode(u32::from_le_bytes(bytes)))
+    }
+
+    /// # Snapshot PPM
+    /// Encode what is currently on the screen as a binary PPM (`P6`) image, each encoded
+    /// piece of the image is passed to `out` as it is made.
+    pub fn snapshot_ppm(&self, out: impl FnMut(&[u8])) {
+        image::encode_ppm(
+            self.width,
+            self.height,
+            |x, y| self.read_pixel(x, y).unwrap_or(Color(0)),
+            out,
+        );
+    }
+
     /// # Draw Rectangle
-    /// Draw a rectangle of a color onto the framebuffer.
+    /// Draw a rectangle of a color onto the framebuffer, see [`Framebuffer::fill_rect`].
     pub fn draw_rec(&mut self, x: usize, y: usize, length: usize, height: usize, color: Color) {
-        // TODO: Use memory functions to speed this up. However, this may never
-        //       be used so I don't want to optimize it until it gets used out-
-        //       side the bootloader.
+        if let Some(rect) = rect!(x, y, length, height) {
+            self.fill_rect(rect, color);
+        }
+    }
 
-        for y in y..(y + height) {
-            for x in x..(x + length) {
-                self.draw_pixel(x, y, color);
+    /// # Bounds
+    /// The area of the framebuffer, `None` if it has no pixels.
+    pub fn bounds(&self) -> Option<Rect> {
+        rect!(0, 0, self.width, self.height)
+    }
+
+    /// # Fill Rect
+    /// Fill `rect` with a color, clipped to the framebuffer. Returns the area that was
+    /// drawn, which is `None` if none of `rect` is on the screen.
+    ///
+    /// The first row is drawn a pixel at a time, and then copied into the rest from the
+    /// shadow if there is one.
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) -> Option<Rect> {
+        let clipped = rect.clip(self.width, self.height)?;
+        let (x, y) = (clipped.x as usize, clipped.y as usize);
+        let (length, height) = (clipped.width.get(), clipped.height.get());
+
+        for x in x..(x + length) {
+            self.draw_pixel(x, y, color);
+        }
+
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let first_row = y * self.pitch + x * bytes_per_pixel;
+        for row in 1..height {
+            unsafe {
+                self.copy_to(
+                    first_row + row * self.pitch,
+                    self.readable().add(first_row),
+                    length * bytes_per_pixel,
+                );
+            }
+        }
+
+        Some(clipped)
+    }
+
+    /// # Blit
+    /// Copy a `width` by `height` block of pixels onto the framebuffer, see
+    /// [`Framebuffer::blit_rect`].
+    pub fn blit(&mut self, x: usize, y: usize, width: usize, height: usize, pixels: &[u8]) {
+        if let Some(rect) = rect!(x, y, width, height) {
+            self.blit_rect(rect, pixels);
+        }
+    }
+
+    /// # Blit Rect
+    /// Copy a block of pixels that are already in this framebuffer's format into `rect`,
+    /// `pixels` is packed with no padding between rows. Only the part on the screen is
+    /// copied, and the area it covered is returned.
+    ///
+    /// # Panics
+    /// If `pixels` is too short to fill `rect`.
+    pub fn blit_rect(&mut self, rect: Rect, pixels: &[u8]) -> Option<Rect> {
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let row_len = rect
+            .width
+            .get()
+            .checked_mul(bytes_per_pixel)
+            .expect("Blit is too wide");
+        assert!(
+            row_len
+                .checked_mul(rect.height.get())
+                .is_some_and(|len| pixels.len() >= len),
+            "Not enough pixels to blit"
+        );
+
+        let clipped = rect.clip(self.width, self.height)?;
+        let (x, y) = (clipped.x as usize, clipped.y as usize);
+        // Where the visible part starts within `pixels`
+        let skipped_columns = clipped.x.abs_diff(rect.x);
+        let skipped_rows = clipped.y.abs_diff(rect.y);
+        let visible_len = clipped.width.get() * bytes_per_pixel;
+
+        for row in 0..clipped.height.get() {
+            let source = (skipped_rows + row) * row_len + skipped_columns * bytes_per_pixel;
+
+            unsafe {
+                self.copy_to(
+                    (y + row) * self.pitch + x * bytes_per_pixel,
+                    pixels[source..source + visible_len].as_ptr(),
+                    visible_len,
+                );
+            }
+        }
+
+        Some(clipped)
+    }
+
+    /// # Copy Rows
+    /// Move `rows` rows of pixels starting at row `from` so they start at row `to`, the
+    /// rows are allowed to overlap.
+    ///
+    /// With a shadow the rows are moved within the shadow, and then only written to the
+    /// framebuffer. Without one they are read back from the framebuffer.
+    pub fn copy_rows(&mut self, from: usize, to: usize, rows: usize) {
+        let rows = rows
+            .min(self.height.saturating_sub(from))
+            .min(self.height.saturating_sub(to));
+        let (from, to, len) = (from * self.pitch, to * self.pitch, rows * self.pitch);
+
+        unsafe {
+            match self.shadow {
+                Some(shadow) => {
+                    core::ptr::copy(shadow.add(from), shadow.add(to), len);
+                    core::ptr::copy_nonoverlapping(shadow.add(to), self.buffer.add(to), len);
+                }
+                None => core::ptr::copy(self.buffer.add(from), self.buffer.add(to), len),
             }
         }
     }
@@ -103,17 +458,101 @@ impl Framebuffer {
     /// # Draw Glyph
     /// Draw a glyph at some position on the screen.
     pub fn draw_glyph(&mut self, x: usize, y: usize, c: char, color: Color) {
-        let Some(glyph) = BinFont::get_glyph(c) else {
-            return;
-        };
+        self.draw_char(&Monospace, x, y, c, color);
+    }
+
+    /// # Draw Char
+    /// Draw `c` in `font` at some position on the screen, returning how far the next
+    /// character should be drawn to the right.
+    pub fn draw_char(
+        &mut self,
+        font: &impl Font,
+        x: usize,
+        y: usize,
+        c: char,
+        color: Color,
+    ) -> usize {
+        let glyph = font.glyph(c);
 
-        for (y_offset, y_char) in glyph.iter().copied().rev().enumerate() {
-            for bit in 0..8 {
-                if (y_char >> (7 - bit)) & 1 != 0 {
-                    self.draw_pixel(x + bit, y + y_offset, color);
+        for (y_offset, row) in glyph.rows.iter().copied().rev().enumerate() {
+            for bit in glyph.left..8 {
+                if (row >> (7 - bit)) & 1 != 0 {
+                    self.draw_pixel(
+                        x.saturating_add(bit - glyph.left),
+                        y.saturating_add(y_offset),
+                        color,
+                    );
                 }
             }
         }
+
+        glyph.advance
+    }
+
+    /// # Draw String
+    /// Draw a line of text starting at some position on the screen.
+    pub fn draw_str(&mut self, x: usize, y: usize, s: &str, color: Color) {
+        self.draw_text(&Monospace, x, y, s, color);
+    }
+
+    /// # Draw Text
+    /// Draw a line of text in `font` starting at some position on the screen, returning
+    /// its width in pixels.
+    pub fn draw_text(
+        &mut self,
+        font: &impl Font,
+        x: usize,
+        y: usize,
+        s: &str,
+        color: Color,
+    ) -> usize {
+        s.chars().fold(0, |width, c| {
+            width + self.draw_char(font, x + width, y, c, color)
+        })
+    }
+
+    /// # Draw Image
+    /// Draw an image with its top left corner at some position on the screen.
+    pub fn draw_image(&mut self, x: usize, y: usize, image: &Image) {
+        for y_offset in 0..image.height() {
+            for x_offset in 0..image.width() {
+                let (x, y) = (x.saturating_add(x_offset), y.saturating_add(y_offset));
+                let color = image.pixel(x_offset, y_offset);
+
+                match color.alpha() {
+                    0 => (),
+                    0xFF => self.draw_pixel(x, y, color),
+                    _ => {
+                        let below = self.read_pixel(x, y).unwrap_or(Color(0));
+                        self.draw_pixel(x, y, color.blend_over(below));
+                    }
+                }
+            }
+        }
+    }
+
+    /// # Draw Progress Bar
+    /// Draw a bar that is `percent` full, with a border of the background color.
+    pub fn draw_progress_bar(
+        &mut self,
+        x: usize,
+        y: usize,
+        length: usize,
+        height: usize,
+        percent: usize,
+        fill: Color,
+        background: Color,
+    ) {
+        self.draw_rec(x, y, length, height, background);
+
+        let filled = (length.saturating_sub(2) * percent.min(100)) / 100;
+        self.draw_rec(
+            x.saturating_add(1),
+            y.saturating_add(1),
+            filled,
+            height.saturating_sub(2),
+            fill,
+        );
     }
 
     /// # Height
@@ -127,4 +566,133 @@ impl Framebuffer {
     pub const fn width(&self) -> usize {
         self.width
     }
+
+    /// # Pitch
+    /// Get the number of bytes between the start of each row.
+    pub const fn pitch(&self) -> usize {
+        self.pitch
+    }
+
+    /// # Format
+    /// Get the layout of each pixel.
+    pub const fn format(&self) -> PixelFormat {
+        self.format
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pixel_format_round_trip() {
+        let color = Color::from_rgb(0x12, 0x34, 0x56);
+        let bgr = PixelFormat {
+            red: Channel::new(0, 8),
+            blue: Channel::new(16, 8),
+            ..PixelFormat::XRGB8888
+        };
+
+        assert_eq!(PixelFormat::XRGB8888.encode(color), 0x123456);
+        assert_eq!(bgr.encode(color), 0x563412);
+        assert!(bgr.is_bgr());
+        assert_eq!(bgr.decode(bgr.encode(color)).0, color.0);
+
+        let rgb565 = PixelFormat {
+            bits_per_pixel: 16,
+            red: Channel::new(11, 5),
+            green: Channel::new(5, 6),
+            blue: Channel::new(0, 5),
+        };
+        assert_eq!(rgb565.encode(Color::WHITE), 0xFFFF);
+        assert_eq!(rgb565.decode(0xFFFF).0, Color::WHITE.0);
+    }
+
+    #[test]
+    fn test_framebuffer_honors_pitch() {
+        // 3 pixels of 24-bits each, padded out to 11 bytes per row
+        let mut memory = [0u8; 11 * 2];
+        let mut framebuffer =
+            unsafe { Framebuffer::new(memory.as_mut_ptr(), 3, 2, 11, PixelFormat::RGB888) };
+
+        framebuffer.draw_pixel(2, 1, Color::from_rgb(1, 2, 3));
+        framebuffer.draw_pixel(3, 1, Color::WHITE);
+        assert_eq!(framebuffer.read_pixel(2, 1).unwrap().0, 0xFF010203);
+
+        assert_eq!(&memory[11 + 6..11 + 9], &[3, 2, 1]);
+        assert!(memory[11 + 9..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_shadow_is_read_instead_of_the_screen() {
+        let mut screen = [0u32; 4 * 4];
+        let mut shadow = [0u32; 4 * 4];
+        let mut framebuffer = unsafe {
+            Framebuffer::new(
+                screen.as_mut_ptr().cast(),
+                4,
+                4,
+                4 * 4,
+                PixelFormat::XRGB8888,
+            )
+            .with_shadow(shadow.as_mut_ptr().cast())
+        };
+
+        framebuffer.fill_rect(rect!(0, 2, 4, 2).unwrap(), Color::WHITE);
+        framebuffer.draw_pixel(1, 3, Color::from_rgb(1, 2, 3));
+        assert_eq!(screen, shadow);
+
+        // Anything the screen has that the shadow doesn't is never read back
+        screen[3 * 4] = 0xBAD;
+        assert_eq!(framebuffer.read_pixel(0, 3).unwrap().0, Color::WHITE.0);
+
+        framebuffer.copy_rows(2, 0, 2);
+        assert_eq!(screen[..8], shadow[8..]);
+        assert_eq!(screen[4 + 1], 0x010203);
+        assert_eq!(screen[4], 0xFFFFFF);
+    }
+
+    #[test]
+    fn test_drawing_is_clipped() {
+        // A row of padding after the framebuffer, that nothing should draw into
+        let mut memory = [0u32; 4 * 5];
+        let mut framebuffer = unsafe {
+            Framebuffer::new(
+                memory.as_mut_ptr().cast(),
+                4,
+                4,
+                4 * 4,
+                PixelFormat::XRGB8888,
+            )
+        };
+
+        assert_eq!(
+            framebuffer.fill_rect(rect!(-2, 2, 100, 100).unwrap(), Color::WHITE),
+            rect!(0, 2, 4, 2)
+        );
+        assert_eq!(
+            framebuffer.fill_rect(rect!(4, 0, 1, 1).unwrap(), Color::WHITE),
+            None
+        );
+        framebuffer.draw_rec(usize::MAX, usize::MAX, usize::MAX, 8, Color::WHITE);
+
+        let pixels = [0x11u32, 0x22, 0x33, 0x44];
+        assert_eq!(
+            framebuffer.blit_rect(rect!(-1, -1, 2, 2).unwrap(), unsafe {
+                core::slice::from_raw_parts(pixels.as_ptr().cast(), 16)
+            }),
+            rect!(0, 0, 1, 1)
+        );
+        framebuffer.blit(3, 3, 2, 2, unsafe {
+            core::slice::from_raw_parts(pixels.as_ptr().cast(), 16)
+        });
+
+        const WHITE: u32 = 0xFFFFFF;
+        assert_eq!(&memory[..4], &[0x44, 0, 0, 0]);
+        assert_eq!(
+            &memory[8..16],
+            &[WHITE, WHITE, WHITE, WHITE, WHITE, WHITE, WHITE, 0x11]
+        );
+        assert!(memory[16..].iter().all(|&pixel| pixel == 0));
+    }
 }
@@ -0,0 +1,184 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use core::num::NonZeroUsize;
+
+/// # Rect
+/// An area of the screen that is never empty.
+///
+/// A rect can start above or to the left of the screen, or hang off its far edges. Drawing
+/// clips it to the part that is on the framebuffer, so nothing outside of it is touched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: isize,
+    pub y: isize,
+    pub width: NonZeroUsize,
+    pub height: NonZeroUsize,
+}
+
+/// # Rect
+/// Make an `Option<Rect>` from any integer types, `None` if the rect is empty, or a value
+/// does not fit.
+///
+/// ```
+/// # use bootgfx::rect;
+/// assert!(rect!(-4, 10, 20u32, 5u8).is_some());
+/// assert!(rect!(0, 0, 0, 10).is_none());
+/// assert!(rect!(0, 0, -1, 10).is_none());
+/// ```
+#[macro_export]
+macro_rules! rect {
+    ($x:expr, $y:expr, $width:expr, $height:expr $(,)?) => {
+        $crate::Rect::try_from_parts($x, $y, $width, $height)
+    };
+}
+
+impl Rect {
+    /// # New
+    /// Make a rect, `None` if `width` or `height` is zero.
+    pub const fn new(x: isize, y: isize, width: usize, height: usize) -> Option<Self> {
+        match (NonZeroUsize::new(width), NonZeroUsize::new(height)) {
+            (Some(width), Some(height)) => Some(Self::from_size(x, y, width, height)),
+            _ => None,
+        }
+    }
+
+    /// # From Size
+    /// Make a rect out of dimensions that are already known to not be zero.
+    pub const fn from_size(x: isize, y: isize, width: NonZeroUsize, height: NonZeroUsize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// # Try From Parts
+    /// Make a rect from any integer types, `None` if the rect is empty or a value does not
+    /// fit. This is what [`rect!`](crate::rect) uses.
+    pub fn try_from_parts<X, Y, W, H>(x: X, y: Y, width: W, height: H) -> Option<Self>
+    where
+        isize: TryFrom<X> + TryFrom<Y>,
+        usize: TryFrom<W> + TryFrom<H>,
+    {
+        Self::new(
+            isize::try_from(x).ok()?,
+            isize::try_from(y).ok()?,
+            usize::try_from(width).ok()?,
+            usize::try_from(height).ok()?,
+        )
+    }
+
+    /// # Right
+    /// The column just past this rect's right edge, saturating instead of overflowing.
+    pub const fn right(&self) -> isize {
+        self.x.saturating_add_unsigned(self.width.get())
+    }
+
+    /// # Bottom
+    /// The row just past this rect's bottom edge, saturating instead of overflowing.
+    pub const fn bottom(&self) -> isize {
+        self.y.saturating_add_unsigned(self.height.get())
+    }
+
+    /// # Intersect
+    /// The area covered by both rects, if they overlap.
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if left >= right || top >= bottom {
+            return None;
+        }
+
+        Rect::new(left, top, right.abs_diff(left), bottom.abs_diff(top))
+    }
+
+    /// # Union
+    /// The smallest rect covering both rects.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let left = self.x.min(other.x);
+        let top = self.y.min(other.y);
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+
+        Rect::new(left, top, right.abs_diff(left), bottom.abs_diff(top))
+            .expect("Neither rect is empty, so neither is their union")
+    }
+
+    /// # Clip
+    /// The part of this rect inside a `width` by `height` area starting at `0, 0`. The
+    /// clipped rect never starts at a negative position.
+    pub fn clip(&self, width: usize, height: usize) -> Option<Rect> {
+        let bounds = Rect::new(
+            0,
+            0,
+            width.min(isize::MAX as usize),
+            height.min(isize::MAX as usize),
+        )?;
+
+        self.intersect(&bounds)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_clip() {
+        let rect = rect!(-5, 2, 10, 100).unwrap();
+
+        assert_eq!(rect.clip(8, 8), rect!(0, 2, 5, 6));
+        assert_eq!(rect.clip(0, 8), None);
+        assert_eq!(rect!(8, 0, 4, 4).unwrap().clip(8, 8), None);
+    }
+
+    #[test]
+    fn test_huge_rects_do_not_overflow() {
+        let rect = Rect::new(isize::MAX - 1, isize::MIN, usize::MAX, usize::MAX).unwrap();
+
+        assert_eq!(rect.right(), isize::MAX);
+        assert_eq!(rect.bottom(), isize::MAX);
+        assert_eq!(rect.clip(8, 8), None);
+        assert_eq!(
+            Rect::new(isize::MIN, isize::MIN, usize::MAX, usize::MAX)
+                .unwrap()
+                .clip(8, 8),
+            rect!(0, 0, 8, 8)
+        );
+    }
+
+    #[test]
+    fn test_rect_macro_rejects_bad_values() {
+        assert_eq!(rect!(0, 0, 0, 4), None);
+        assert_eq!(rect!(0, 0, -2, 4), None);
+        assert_eq!(rect!(u64::MAX, 0, 1, 1), None);
+        assert!(rect!(-3i8, 1u16, 2u32, 5usize).is_some());
+    }
+}
@@ -22,3 +22,426 @@ NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FO
 DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
+
+use crate::{glyph_cache::GlyphCache, Color, Framebuffer, Rect};
+use util::utf8::Utf8Decoder;
+
+/// The max number of columns a terminal can have.
+pub const MAX_COLUMNS: usize = 160;
+/// The max number of rows a terminal can have, each row has a bit in the dirty mask.
+pub const MAX_ROWS: usize = 64;
+/// The width of each cell in pixels.
+pub const CELL_WIDTH: usize = 8;
+/// The height of each cell in pixels, the font is 13 pixels tall with a pixel of spacing.
+pub const CELL_HEIGHT: usize = 14;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EscapeState {
+    None,
+    Escape,
+    Sequence,
+}
+
+/// # Terminal
+/// A grid of text that can be written to like a console, and drawn onto a framebuffer.
+///
+/// Only rows that changed since the last [`Terminal::render`] are redrawn, and scrolling
+/// moves the rows already on the framebuffer up instead of redrawing them. ANSI escape
+/// sequences (like colors) are skipped. Bytes are decoded as UTF-8, characters the font
+/// doesn't have are drawn as a replacement glyph.
+pub struct Terminal {
+    cells: [[char; MAX_COLUMNS]; MAX_ROWS],
+    columns: usize,
+    rows: usize,
+    cursor_x: usize,
+    cursor_y: usize,
+    dirty_rows: u64,
+    /// Rows scrolled since the last render, that the framebuffer still has to move up
+    scrolled: usize,
+    escape: EscapeState,
+    utf8: Utf8Decoder,
+    foreground: Color,
+    background: Color,
+}
+
+impl Terminal {
+    /// # New
+    /// Make a terminal of `columns` by `rows` cells, clamped to the max size.
+    pub const fn new(columns: usize, rows: usize) -> Self {
+        Self {
+            cells: [[' '; MAX_COLUMNS]; MAX_ROWS],
+            columns: if columns < MAX_COLUMNS {
+                columns
+            } else {
+                MAX_COLUMNS
+            },
+            rows: if rows < MAX_ROWS { rows } else { MAX_ROWS },
+            cursor_x: 0,
+            cursor_y: 0,
+            dirty_rows: u64::MAX,
+            scrolled: 0,
+            escape: EscapeState::None,
+            utf8: Utf8Decoder::new(),
+            foreground: Color::WHITE,
+            background: Color::QUANTUM_BACKGROUND,
+        }
+    }
+
+    /// # Fit To
+    /// Resize to cover as much of `framebuffer` as possible, clearing the terminal.
+    pub fn fit_to(&mut self, framebuffer: &Framebuffer) {
+        self.columns = (framebuffer.width() / CELL_WIDTH).min(MAX_COLUMNS);
+        self.rows = (framebuffer.height() / CELL_HEIGHT).min(MAX_ROWS);
+        self.clear();
+    }
+
+    /// # Refit To
+    /// Resize to cover as much of `framebuffer` as possible, like after its mode changed,
+    /// keeping the text that still fits.
+    pub fn refit_to(&mut self, framebuffer: &Framebuffer) {
+        self.resize(
+            framebuffer.width() / CELL_WIDTH,
+            framebuffer.height() / CELL_HEIGHT,
+        );
+    }
+
+    /// # Resize
+    /// Change to `columns` by `rows` cells, clamped to the max size, keeping the text that
+    /// still fits.
+    ///
+    /// Text past the new last column is cut off. With fewer rows, rows are dropped from the
+    /// top so the cursor stays on screen.
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        let columns = columns.min(MAX_COLUMNS);
+        let rows = rows.min(MAX_ROWS);
+
+        let dropped = (self.cursor_y + 1).saturating_sub(rows).min(self.rows);
+        self.cells.copy_within(dropped..self.rows, 0);
+        for (y, row) in self.cells.iter_mut().enumerate() {
+            let keep = if y < self.rows - dropped && y < rows {
+                columns
+            } else {
+                0
+            };
+            row[keep..].fill(' ');
+        }
+
+        self.columns = columns;
+        self.rows = rows;
+        self.cursor_y = (self.cursor_y - dropped).min(rows.saturating_sub(1));
+        self.cursor_x = self.cursor_x.min(columns);
+        self.scrolled = 0;
+        self.invalidate();
+    }
+
+    /// # Columns
+    pub const fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// # Rows
+    pub const fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// # Cursor
+    /// The column and row the next character will be written to.
+    pub const fn cursor(&self) -> (usize, usize) {
+        (self.cursor_x, self.cursor_y)
+    }
+
+    /// # Cell
+    /// The character at a column and row.
+    pub fn cell(&self, x: usize, y: usize) -> Option<char> {
+        (x < self.columns && y < self.rows).then(|| self.cells[y][x])
+    }
+
+    /// # Clear
+    /// Blank every cell and move the cursor home.
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|row| row.fill(' '));
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.invalidate();
+    }
+
+    /// # Invalidate
+    /// Redraw every row on the next render, like after something else drew over it.
+    pub fn invalidate(&mut self) {
+        self.dirty_rows = u64::MAX;
+    }
+
+    /// # Write Byte
+    /// Write a single byte of UTF-8 at the cursor.
+    ///
+    /// Characters split across several calls are joined back together, invalid bytes are
+    /// written as [`char::REPLACEMENT_CHARACTER`].
+    pub fn write_byte(&mut self, byte: u8) {
+        let mut utf8 = self.utf8;
+        utf8.push(byte, |c| self.write_char(c));
+        self.utf8 = utf8;
+    }
+
+    /// # Write Char
+    /// Write a single character at the cursor.
+    pub fn write_char(&mut self, c: char) {
+        if self.columns == 0 || self.rows == 0 {
+            return;
+        }
+
+        match (self.escape, c) {
+            (EscapeState::None, '\x1B') => self.escape = EscapeState::Escape,
+            (EscapeState::Escape, '[') => self.escape = EscapeState::Sequence,
+            (EscapeState::Escape, _) => self.escape = EscapeState::None,
+            (EscapeState::Sequence, '\x40'..='\x7E') => self.escape = EscapeState::None,
+            (EscapeState::Sequence, _) => (),
+            (EscapeState::None, '\n') => self.new_line(),
+            (EscapeState::None, '\r') => self.cursor_x = 0,
+            (EscapeState::None, '\t') => {
+                for _ in 0..(8 - self.cursor_x % 8) {
+                    self.write_char(' ');
+                }
+            }
+            (EscapeState::None, '\x08') => {
+                if self.cursor_x > 0 {
+                    self.cursor_x -= 1;
+                    self.put(' ');
+                }
+            }
+            (EscapeState::None, c) => {
+                if self.cursor_x >= self.columns {
+                    self.new_line();
+                }
+
+                self.put(if c.is_control() {
+                    char::REPLACEMENT_CHARACTER
+                } else {
+                    c
+                });
+                self.cursor_x += 1;
+            }
+        }
+    }
+
+    /// # Render
+    /// Draw each row that changed since the last render onto `framebuffer`, using `glyphs`
+    /// for the cells.
+    ///
+    /// Returns the area of the framebuffer that was drawn to, `None` if nothing changed.
+    pub fn render(
+        &mut self,
+        framebuffer: &mut Framebuffer,
+        glyphs: &mut GlyphCache,
+    ) -> Option<Rect> {
+        let all_rows = u64::MAX >> (64 - self.rows.max(1));
+        let mut damaged = self.dirty_rows & all_rows;
+
+        // Moving rows that will all be redrawn anyway would be wasted work
+        if self.scrolled > 0 && self.scrolled < self.rows && damaged != all_rows {
+            framebuffer.copy_rows(
+                self.scrolled * CELL_HEIGHT,
+                0,
+                (self.rows - self.scrolled) * CELL_HEIGHT,
+            );
+            damaged = all_rows;
+        }
+        self.scrolled = 0;
+
+        let format = framebuffer.format();
+        for y in (0..self.rows).filter(|&y| self.dirty_rows & (1 << y) != 0) {
+            for (x, &cell) in self.cells[y][..self.columns].iter().enumerate() {
+                let pixels = glyphs.cell(cell, self.foreground, self.background, format);
+                framebuffer.blit(
+                    x * CELL_WIDTH,
+                    y * CELL_HEIGHT,
+                    CELL_WIDTH,
+                    CELL_HEIGHT,
+                    pixels,
+                );
+            }
+        }
+
+        self.dirty_rows = 0;
+
+        if damaged == 0 {
+            return None;
+        }
+
+        let first_row = damaged.trailing_zeros() as usize;
+        let last_row = 63 - damaged.leading_zeros() as usize;
+        Rect::try_from_parts(
+            0,
+            first_row * CELL_HEIGHT,
+            self.columns * CELL_WIDTH,
+            (last_row + 1 - first_row) * CELL_HEIGHT,
+        )
+    }
+
+    fn put(&mut self, c: char) {
+        self.cells[self.cursor_y][self.cursor_x] = c;
+        self.dirty_rows |= 1 << self.cursor_y;
+    }
+
+    fn new_line(&mut self) {
+        self.cursor_x = 0;
+
+        if self.cursor_y + 1 < self.rows {
+            self.cursor_y += 1;
+            return;
+        }
+
+        // Scroll everything up a row, each row's dirty bit moves up with it
+        self.cells.copy_within(1..self.rows, 0);
+        self.cells[self.rows - 1].fill(' ');
+        self.dirty_rows = (self.dirty_rows >> 1) | (1 << (self.rows - 1));
+        self.scrolled += 1;
+    }
+}
+
+impl core::fmt::Write for Terminal {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        s.chars().for_each(|c| self.write_char(c));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::fmt::Write;
+
+    fn row(terminal: &Terminal, y: usize) -> [char; 4] {
+        core::array::from_fn(|x| terminal.cell(x, y).unwrap())
+    }
+
+    #[test]
+    fn test_terminal_wraps_and_scrolls() {
+        let mut terminal = Terminal::new(4, 2);
+        write!(terminal, "abcdef").unwrap();
+
+        assert_eq!(row(&terminal, 0), ['a', 'b', 'c', 'd']);
+        assert_eq!(row(&terminal, 1), ['e', 'f', ' ', ' ']);
+
+        write!(terminal, "\nxy").unwrap();
+        assert_eq!(row(&terminal, 0), ['e', 'f', ' ', ' ']);
+        assert_eq!(row(&terminal, 1), ['x', 'y', ' ', ' ']);
+        assert_eq!(terminal.cursor(), (2, 1));
+    }
+
+    #[test]
+    fn test_terminal_skips_escapes() {
+        let mut terminal = Terminal::new(4, 1);
+        write!(terminal, "\x1b[1;32mok\x1b[0m\x08!").unwrap();
+
+        assert_eq!(row(&terminal, 0), ['o', '!', ' ', ' ']);
+    }
+
+    #[test]
+    fn test_terminal_decodes_utf8() {
+        let mut terminal = Terminal::new(4, 1);
+        write!(terminal, "±").unwrap();
+        "µs".bytes().for_each(|byte| terminal.write_byte(byte));
+        terminal.write_byte(0xFF);
+
+        assert_eq!(row(&terminal, 0), ['±', 'µ', 's', '\u{FFFD}']);
+    }
+
+    #[test]
+    fn test_terminal_scrolls_by_copying_rows() {
+        const WIDTH: usize = 4 * CELL_WIDTH;
+        const HEIGHT: usize = 3 * CELL_HEIGHT;
+
+        let mut glyphs = GlyphCache::new();
+        let mut scrolled = [0u8; WIDTH * HEIGHT * 4];
+        let mut redrawn = [0u8; WIDTH * HEIGHT * 4];
+        let framebuffer = |memory: &mut [u8]| unsafe {
+            Framebuffer::new(
+                memory.as_mut_ptr(),
+                WIDTH,
+                HEIGHT,
+                WIDTH * 4,
+                crate::PixelFormat::XRGB8888,
+            )
+        };
+
+        let mut terminal = Terminal::new(4, 3);
+        write!(terminal, "ab\ncd\nef").unwrap();
+        terminal.render(&mut framebuffer(&mut scrolled), &mut glyphs);
+        write!(terminal, "\ngh\nij").unwrap();
+        assert_eq!(terminal.scrolled, 2);
+        terminal.render(&mut framebuffer(&mut scrolled), &mut glyphs);
+
+        terminal.invalidate();
+        terminal.render(&mut framebuffer(&mut redrawn), &mut glyphs);
+        assert!(scrolled == redrawn);
+    }
+
+    #[test]
+    fn test_terminal_render_returns_damage() {
+        const WIDTH: usize = 4 * CELL_WIDTH;
+        const HEIGHT: usize = 3 * CELL_HEIGHT;
+
+        let mut glyphs = GlyphCache::new();
+        let mut memory = [0u8; WIDTH * HEIGHT * 4];
+        let mut framebuffer = unsafe {
+            Framebuffer::new(
+                memory.as_mut_ptr(),
+                WIDTH,
+                HEIGHT,
+                WIDTH * 4,
+                crate::PixelFormat::XRGB8888,
+            )
+        };
+
+        let mut terminal = Terminal::new(4, 3);
+        assert_eq!(
+            terminal.render(&mut framebuffer, &mut glyphs),
+            Rect::new(0, 0, WIDTH, HEIGHT)
+        );
+        assert_eq!(terminal.render(&mut framebuffer, &mut glyphs), None);
+
+        write!(terminal, "\nab").unwrap();
+        assert_eq!(
+            terminal.render(&mut framebuffer, &mut glyphs),
+            Rect::new(0, CELL_HEIGHT as isize, WIDTH, CELL_HEIGHT)
+        );
+
+        // Scrolling moves every row
+        write!(terminal, "\n\ncd").unwrap();
+        assert_eq!(
+            terminal.render(&mut framebuffer, &mut glyphs),
+            Rect::new(0, 0, WIDTH, HEIGHT)
+        );
+    }
+
+    #[test]
+    fn test_terminal_resize_keeps_text() {
+        let mut terminal = Terminal::new(4, 3);
+        write!(terminal, "ab\ncd\nefgh").unwrap();
+
+        terminal.resize(2, 2);
+        assert_eq!((terminal.columns(), terminal.rows()), (2, 2));
+        assert_eq!(terminal.cell(0, 0), Some('c'));
+        assert_eq!(terminal.cell(1, 1), Some('f'));
+        assert_eq!(terminal.cursor(), (2, 1));
+
+        // Text cut off by the smaller size does not come back
+        terminal.resize(4, 3);
+        assert_eq!(row(&terminal, 0), ['c', 'd', ' ', ' ']);
+        assert_eq!(row(&terminal, 1), ['e', 'f', ' ', ' ']);
+        assert_eq!(row(&terminal, 2), [' ', ' ', ' ', ' ']);
+
+        write!(terminal, "!").unwrap();
+        assert_eq!(row(&terminal, 1), ['e', 'f', '!', ' ']);
+    }
+
+    #[test]
+    fn test_terminal_clamps_size() {
+        let terminal = Terminal::new(1000, 1000);
+        assert_eq!(
+            (terminal.columns(), terminal.rows()),
+            (MAX_COLUMNS, MAX_ROWS)
+        );
+        assert_eq!(terminal.cell(MAX_COLUMNS, 0), None);
+    }
+}
@@ -0,0 +1,358 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! A bounded, multi-producer single-consumer channel.
+//!
+//! Senders wait for room once `capacity` values are queued, so a slow receiver slows
+//! down its producers instead of letting the queue grow forever.
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use core::{
+    fmt::Debug,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use kinases::spin::mutex::SpinMutex;
+
+/// The receiver was dropped, so `.0` could not be sent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// Why [`Sender::try_send`] could not send a value, the value is given back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel is at its capacity
+    Full(T),
+    /// The receiver was dropped
+    Disconnected(T),
+}
+
+/// Why [`Receiver::try_recv`] could not receive a value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// Nothing is queued right now
+    Empty,
+    /// Nothing is queued, and every sender was dropped
+    Disconnected,
+}
+
+struct Channel<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+    senders: usize,
+    receiver_alive: bool,
+    /// The receiver, if it is waiting for a value
+    recv_waker: Option<Waker>,
+    /// Senders waiting for room in the queue
+    send_wakers: VecDeque<Waker>,
+}
+
+type Shared<T> = Arc<SpinMutex<Channel<T>>>;
+
+/// Create a channel that holds at most `capacity` values before senders have to wait.
+///
+/// # Panics
+/// If `capacity` is zero.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "A channel needs room for at least one value!");
+
+    let shared = Arc::new(SpinMutex::new(Channel {
+        queue: VecDeque::with_capacity(capacity),
+        capacity,
+        senders: 1,
+        receiver_alive: true,
+        recv_waker: None,
+        send_wakers: VecDeque::new(),
+    }));
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+/// The sending half of a channel, clone it for more producers
+pub struct Sender<T> {
+    shared: Shared<T>,
+}
+
+/// The receiving half of a channel
+pub struct Receiver<T> {
+    shared: Shared<T>,
+}
+
+// Values are only ever reached through the channel's lock, and move between tasks whole
+unsafe impl<T: Send> Send for Sender<T> {}
+unsafe impl<T: Send> Sync for Sender<T> {}
+unsafe impl<T: Send> Send for Receiver<T> {}
+unsafe impl<T: Send> Sync for Receiver<T> {}
+
+impl<T> Sender<T> {
+    /// Send `value`, waiting for room in the channel if it is full.
+    ///
+    /// Returns the value back if the receiver was dropped.
+    pub fn send(&self, value: T) -> SendFuture<'_, T> {
+        SendFuture {
+            sender: self,
+            value: Some(value),
+        }
+    }
+
+    /// Send `value` if there is room in the channel right now
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut channel = self.shared.lock();
+
+        if !channel.receiver_alive {
+            return Err(TrySendError::Disconnected(value));
+        }
+
+        if channel.queue.len() >= channel.capacity {
+            return Err(TrySendError::Full(value));
+        }
+
+        channel.queue.push_back(value);
+        if let Some(waker) = channel.recv_waker.take() {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Was the receiver dropped?
+    pub fn is_closed(&self) -> bool {
+        !self.shared.lock().receiver_alive
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut channel = self.shared.lock();
+        channel.senders -= 1;
+
+        if channel.senders != 0 {
+            return;
+        }
+
+        // The receiver needs to find out nothing else is coming
+        if let Some(waker) = channel.recv_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sender")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// A future sending a value on a channel, see [`Sender::send`]
+pub struct SendFuture<'a, T> {
+    sender: &'a Sender<T>,
+    value: Option<T>,
+}
+
+// The value is never pinned, it is only moved into the channel
+impl<T> Unpin for SendFuture<'_, T> {}
+
+impl<T> Future for SendFuture<'_, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let value = this
+            .value
+            .take()
+            .expect("SendFuture polled after it completed!");
+
+        match this.sender.try_send(value) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(TrySendError::Disconnected(value)) => Poll::Ready(Err(SendError(value))),
+            Err(TrySendError::Full(value)) => {
+                let mut channel = this.sender.shared.lock();
+                this.value = Some(value);
+
+                // The receiver could have made room since `try_send` looked
+                if channel.queue.len() < channel.capacity {
+                    drop(channel);
+                    cx.waker().wake_by_ref();
+                } else {
+                    channel.send_wakers.push_back(cx.waker().clone());
+                }
+
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Wait for the next value, or `None` once every sender was dropped and the channel
+    /// is empty.
+    pub fn recv(&mut self) -> RecvFuture<'_, T> {
+        RecvFuture { receiver: self }
+    }
+
+    /// Take the next value if one is queued
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut channel = self.shared.lock();
+
+        match channel.queue.pop_front() {
+            Some(value) => {
+                // There is room for one more now. Every waiting sender is woken, as one
+                // that stopped waiting would otherwise swallow the wakeup.
+                channel.send_wakers.drain(..).for_each(|waker| waker.wake());
+
+                Ok(value)
+            }
+            None if channel.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// How many values are waiting to be received
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    /// Are no values waiting to be received?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut channel = self.shared.lock();
+        channel.receiver_alive = false;
+
+        // Waiting senders would never get room, so tell them the channel is closed
+        channel.send_wakers.drain(..).for_each(|waker| waker.wake());
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Receiver")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// A future receiving a value from a channel, see [`Receiver::recv`]
+pub struct RecvFuture<'a, T> {
+    receiver: &'a mut Receiver<T>,
+}
+
+impl<T> Future for RecvFuture<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        match this.receiver.try_recv() {
+            Ok(value) => Poll::Ready(Some(value)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+            Err(TryRecvError::Empty) => {
+                let mut channel = this.receiver.shared.lock();
+
+                // A value or the last sender's drop could have come since `try_recv`
+                if channel.queue.is_empty() && channel.senders != 0 {
+                    channel.recv_waker = Some(cx.waker().clone());
+                } else {
+                    drop(channel);
+                    cx.waker().wake_by_ref();
+                }
+
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Chloroplast;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_pipeline() {
+        let runtime = Chloroplast::new();
+        let (sender, mut receiver) = channel(2);
+
+        for producer in 0..3 {
+            let sender = sender.clone();
+            runtime.spawn(async move {
+                for value in 0..10 {
+                    sender.send(producer * 100 + value).await.unwrap();
+                }
+            });
+        }
+        drop(sender);
+
+        let mut received = runtime.block_on(async move {
+            let mut received = Vec::new();
+            while let Some(value) = receiver.recv().await {
+                received.push(value);
+            }
+
+            received
+        });
+        received.sort();
+
+        let expected: Vec<i32> = (0..3)
+            .flat_map(|producer| (0..10).map(move |value| producer * 100 + value))
+            .collect();
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_backpressure() {
+        let (sender, mut receiver) = channel(1);
+
+        assert_eq!(sender.try_send(1), Ok(()));
+        assert_eq!(sender.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        drop(receiver);
+        assert_eq!(sender.try_send(3), Err(TrySendError::Disconnected(3)));
+    }
+}
@@ -0,0 +1,218 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! A channel for sending a single value, like the response to a request.
+
+use alloc::sync::Arc;
+use core::{
+    fmt::Debug,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use kinases::spin::mutex::SpinMutex;
+
+/// The sender was dropped without sending a value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecvError;
+
+struct Channel<T> {
+    value: Option<T>,
+    /// Set once the sender sent its value, or was dropped
+    sender_done: bool,
+    receiver_alive: bool,
+    waker: Option<Waker>,
+}
+
+type Shared<T> = Arc<SpinMutex<Channel<T>>>;
+
+/// Create a channel for a single value
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(SpinMutex::new(Channel {
+        value: None,
+        sender_done: false,
+        receiver_alive: true,
+        waker: None,
+    }));
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+/// Sends the channel's value
+pub struct Sender<T> {
+    shared: Shared<T>,
+}
+
+/// Awaits the channel's value
+///
+/// Awaiting this gives the value, or [`RecvError`] if the sender was dropped first.
+pub struct Receiver<T> {
+    shared: Shared<T>,
+}
+
+// The value is only ever reached through the channel's lock, and moves between tasks whole
+unsafe impl<T: Send> Send for Sender<T> {}
+unsafe impl<T: Send> Sync for Sender<T> {}
+unsafe impl<T: Send> Send for Receiver<T> {}
+unsafe impl<T: Send> Sync for Receiver<T> {}
+
+impl<T> Sender<T> {
+    /// Send `value`, giving it back if the receiver was dropped
+    pub fn send(self, value: T) -> Result<(), T> {
+        let mut channel = self.shared.lock();
+
+        if !channel.receiver_alive {
+            return Err(value);
+        }
+
+        channel.value = Some(value);
+        channel.sender_done = true;
+        if let Some(waker) = channel.waker.take() {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Was the receiver dropped? Nobody is waiting for the value if so.
+    pub fn is_closed(&self) -> bool {
+        !self.shared.lock().receiver_alive
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut channel = self.shared.lock();
+        if channel.sender_done {
+            return;
+        }
+
+        channel.sender_done = true;
+        if let Some(waker) = channel.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sender")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Take the value if it was sent
+    ///
+    /// Returns `Ok(None)` if the sender might still send it.
+    pub fn try_recv(&mut self) -> Result<Option<T>, RecvError> {
+        let mut channel = self.shared.lock();
+
+        match channel.value.take() {
+            Some(value) => Ok(Some(value)),
+            None if channel.sender_done => Err(RecvError),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_alive = false;
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Receiver")
+            .field("sender_done", &self.shared.lock().sender_done)
+            .finish()
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut channel = self.shared.lock();
+
+        match channel.value.take() {
+            Some(value) => Poll::Ready(Ok(value)),
+            None if channel.sender_done => Poll::Ready(Err(RecvError)),
+            None => {
+                channel.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Chloroplast, channel::mpsc};
+
+    #[test]
+    fn test_request_response() {
+        let runtime = Chloroplast::new();
+        let (requests, mut incoming) = mpsc::channel::<(i32, Sender<i32>)>(4);
+
+        runtime.spawn(async move {
+            while let Some((request, respond)) = incoming.recv().await {
+                let _ = respond.send(request * 2);
+            }
+        });
+
+        let responses = runtime.block_on(async move {
+            let mut responses = [0; 3];
+            for (request, response) in responses.iter_mut().enumerate() {
+                let (respond, receiver) = channel();
+                requests.send((request as i32, respond)).await.unwrap();
+                *response = receiver.await.unwrap();
+            }
+
+            responses
+        });
+
+        assert_eq!(responses, [0, 2, 4]);
+    }
+
+    #[test]
+    fn test_sender_dropped() {
+        let runtime = Chloroplast::new();
+        let (sender, receiver) = channel::<i32>();
+
+        runtime.spawn(async move {
+            drop(sender);
+        });
+
+        assert_eq!(runtime.block_on(receiver), Err(RecvError));
+    }
+}
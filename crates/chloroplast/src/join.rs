@@ -0,0 +1,101 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::vtask::{AnonTask, RunResult};
+use core::{
+    fmt::Debug,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Why a task didn't give its output to its [`JoinHandle`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinError {
+    /// The task was canceled, or the runtime shut down before it finished
+    Canceled,
+}
+
+/// A handle to a spawned task, that can wait for its output.
+///
+/// Awaiting the handle waits for the task to be run by its runtime, it never polls the
+/// task itself. Dropping the handle detaches the task, it keeps running without anyone
+/// waiting for its output.
+pub struct JoinHandle<T> {
+    task: AnonTask,
+    _ph: PhantomData<T>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Create a handle for `task`
+    ///
+    /// # Safety
+    /// `T` must be the output type of `task`'s future.
+    pub(crate) unsafe fn new(task: AnonTask) -> Self {
+        Self {
+            task,
+            _ph: PhantomData,
+        }
+    }
+
+    /// Has the task finished or been canceled?
+    pub fn is_finished(&self) -> bool {
+        self.task.status() != RunResult::Pending
+    }
+
+    /// Cancel the task, awaiting this handle returns [`JoinError::Canceled`] unless it
+    /// already finished.
+    pub fn cancel(&self) {
+        self.task.cancel();
+    }
+
+    fn result(&self) -> Result<T, JoinError> {
+        // A task canceled while it was finishing has no output to take
+        unsafe { self.task.take_output() }.ok_or(JoinError::Canceled)
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // The waker is set before looking at the status, so a task finishing in between
+        // still wakes us
+        unsafe { self.task.vtable_set_waker(cx.waker().clone()) };
+
+        match self.task.status() {
+            RunResult::Pending => Poll::Pending,
+            RunResult::Finished | RunResult::Canceled => Poll::Ready(self.result()),
+        }
+    }
+}
+
+impl<T> Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("status", &self.task.status())
+            .finish()
+    }
+}
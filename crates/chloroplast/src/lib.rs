@@ -32,14 +32,18 @@ use alloc::{
     sync::Arc,
 };
 use core::sync::atomic::{AtomicBool, Ordering};
+use join::JoinHandle;
 use kinases::spin::mutex::SpinMutex;
 use runner::TaskRunner;
 use runtime::{GuardedJob, GuardedJobStatus, RuntimeSupport};
 use task::Task;
 
+pub mod channel;
+pub mod join;
 pub mod runner;
 pub mod runtime;
 pub mod task;
+pub mod timer;
 pub mod vtask;
 
 #[derive(Clone)]
@@ -47,6 +51,7 @@ pub struct Chloroplast {
     needs_poll: Arc<SpinMutex<VecDeque<vtask::AnonTask>>>,
     waiting: Arc<SpinMutex<BTreeSet<vtask::AnonTask>>>,
     shutting_down: Arc<AtomicBool>,
+    idle: Option<fn()>,
 }
 
 impl RuntimeSupport for Chloroplast {
@@ -89,10 +94,26 @@ impl Chloroplast {
             needs_poll: Arc::new(SpinMutex::new(VecDeque::new())),
             waiting: Arc::new(SpinMutex::new(BTreeSet::new())),
             shutting_down: Arc::new(AtomicBool::new(false)),
+            idle: None,
         }
     }
 
-    pub fn spawn<F>(&self, future: F) -> Task<F, Self, F::Output>
+    /// Create a runtime that calls `idle` whenever no task can make progress.
+    ///
+    /// `idle` should block until something that might wake a task happens, like a timer
+    /// expiring or a message arriving. Without it the runtime spins until a task is woken.
+    pub fn with_idle(idle: fn()) -> Self {
+        let mut runtime = Self::new();
+        runtime.idle = Some(idle);
+
+        runtime
+    }
+
+    /// Run `future` on this runtime alongside every other task.
+    ///
+    /// The task runs even if the returned handle is dropped. A panic in the task ends the
+    /// whole program, like any other panic.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
     where
         F: Future + Send + 'static,
         F::Output: Send + 'static,
@@ -100,7 +121,7 @@ impl Chloroplast {
         let new_task = Task::new(future, self.clone());
         self.needs_poll.lock().push_back(new_task.anon_task());
 
-        new_task
+        unsafe { JoinHandle::new(new_task.anon_task()) }
     }
 
     pub fn new_runner(&self) -> TaskRunner<Self> {
@@ -111,6 +132,32 @@ impl Chloroplast {
         self.needs_poll.lock().len() == 0 && self.shutting_down.load(Ordering::Relaxed)
     }
 
+    /// Does any task need to be polled right now?
+    fn has_awoken_tasks(&self) -> bool {
+        !self.needs_poll.lock().is_empty()
+    }
+
+    /// Wait for something to wake a task, if this runtime knows how to
+    fn park(&self) {
+        if let Some(idle) = self.idle {
+            idle();
+        }
+    }
+
+    /// Run every spawned task on this thread until all of them have finished.
+    pub fn run(&self) {
+        let mut runner = self.new_runner();
+
+        while self.has_awoken_tasks() || !self.waiting.lock().is_empty() {
+            if !self.has_awoken_tasks() {
+                self.park();
+                continue;
+            }
+
+            runner.drive_execution();
+        }
+    }
+
     pub fn block_on<F>(&self, future: F) -> F::Output
     where
         F: Future + Send + 'static,
@@ -121,6 +168,11 @@ impl Chloroplast {
 
         let mut runner = self.new_runner();
         while !new_task.is_completed() {
+            if !self.has_awoken_tasks() {
+                self.park();
+                continue;
+            }
+
             runner.drive_execution();
         }
 
@@ -137,7 +189,10 @@ impl Chloroplast {
 
 impl Drop for Chloroplast {
     fn drop(&mut self) {
-        self.shutdown();
+        // Every task holds a clone of its runtime, so only the last one shuts it down
+        if Arc::strong_count(&self.shutting_down) == 1 {
+            self.shutdown();
+        }
     }
 }
 
@@ -196,10 +251,7 @@ mod test {
             assert_eq!(test_async(0).await, 10);
         });
 
-        let mut runner = runtime.new_runner();
-        while !runtime.is_work_finished() {
-            runner.drive_execution();
-        }
+        runtime.run();
     }
 
     #[test]
@@ -218,6 +270,37 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_join_handle() {
+        let runtime = Chloroplast::new();
+
+        let first = runtime.spawn(async {
+            Yield::new().await;
+            test_async(1).await
+        });
+        let second = runtime.spawn(async { test_async(2).await });
+
+        assert_eq!(
+            runtime.block_on(async move { (first.await, second.await) }),
+            (Ok(11), Ok(12))
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        let runtime = Chloroplast::new();
+
+        let forever = runtime.spawn(async {
+            loop {
+                Yield::new().await;
+            }
+        });
+        forever.cancel();
+
+        assert_eq!(runtime.block_on(forever), Err(join::JoinError::Canceled));
+        runtime.run();
+    }
+
     #[test]
     fn test_multi_threading() {
         let runtime = Chloroplast::new();
@@ -0,0 +1,118 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use core::{
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+/// A source of monotonic time for timer futures
+pub trait Clock {
+    /// Nanoseconds since some fixed point, this must never go backwards
+    fn monotonic_ns(&self) -> u64;
+
+    /// Wake `waker` once the clock reaches `deadline_ns`.
+    ///
+    /// This is called every time a timer is polled before its deadline, so registering
+    /// the same waker for the same deadline again should not add another wakeup.
+    fn wake_at(&self, deadline_ns: u64, waker: &Waker);
+}
+
+/// A future that completes once its clock reaches a deadline
+#[derive(Debug)]
+pub struct Sleep<C: Clock> {
+    clock: C,
+    deadline_ns: u64,
+}
+
+impl<C: Clock> Sleep<C> {
+    /// Complete once `duration` has passed on `clock`
+    pub fn new(clock: C, duration: Duration) -> Self {
+        let deadline_ns = clock
+            .monotonic_ns()
+            .saturating_add(duration.as_nanos() as u64);
+
+        Self { clock, deadline_ns }
+    }
+
+    /// The time left until this future completes
+    pub fn remaining(&self) -> Duration {
+        Duration::from_nanos(self.deadline_ns.saturating_sub(self.clock.monotonic_ns()))
+    }
+}
+
+impl<C: Clock + Unpin> Future for Sleep<C> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.clock.monotonic_ns() >= self.deadline_ns {
+            return Poll::Ready(());
+        }
+
+        self.clock.wake_at(self.deadline_ns, cx.waker());
+        Poll::Pending
+    }
+}
+
+/// Complete once `duration` has passed on `clock`
+pub fn sleep<C: Clock>(clock: C, duration: Duration) -> Sleep<C> {
+    Sleep::new(clock, duration)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Chloroplast;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    extern crate std;
+
+    static NOW: AtomicU64 = AtomicU64::new(0);
+
+    #[derive(Clone, Copy)]
+    struct TestClock;
+
+    impl Clock for TestClock {
+        fn monotonic_ns(&self) -> u64 {
+            // Every look at the clock moves time forward by 1ms
+            NOW.fetch_add(1_000_000, Ordering::Relaxed)
+        }
+
+        fn wake_at(&self, _deadline_ns: u64, waker: &Waker) {
+            // Time only moves when the clock is read, so poll again right away
+            waker.wake_by_ref();
+        }
+    }
+
+    #[test]
+    fn test_sleep() {
+        let runtime = Chloroplast::new();
+
+        let start = NOW.load(Ordering::Relaxed);
+        runtime.block_on(sleep(TestClock, Duration::from_millis(10)));
+
+        assert!(NOW.load(Ordering::Relaxed) - start >= 10_000_000);
+    }
+}
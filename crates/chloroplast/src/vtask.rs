@@ -294,6 +294,40 @@ impl AnonTask {
             (self.mem_ptr.as_ref().vtable.override_status)(self.mem_ptr.as_ptr().cast(), status)
         }
     }
+
+    /// Get the current status of this task
+    pub fn status(&self) -> RunResult {
+        unsafe { self.mem_ptr.as_ref().state.status() }
+    }
+
+    /// Cancel this task if it hasn't finished yet.
+    ///
+    /// The task is woken so the runtime sees the cancel, and whoever is waiting on
+    /// it is told. Its future is dropped once nothing references the task anymore.
+    pub fn cancel(&self) {
+        if self.status() != RunResult::Pending {
+            return;
+        }
+
+        unsafe {
+            self.vtable_override_status(RunResult::Canceled);
+            self.vtable_wake_ref();
+        }
+    }
+
+    /// Take the output of this task if it finished, and nobody took it before.
+    ///
+    /// # Safety
+    /// `Output` must be the output type of the future this task was created with.
+    pub unsafe fn take_output<Output>(&self) -> Option<Output> {
+        unsafe {
+            if !self.mem_ptr.as_ref().state.try_consume() {
+                return None;
+            }
+
+            (*self.vtable_output::<Output>()).get().replace(None)
+        }
+    }
 }
 
 impl Drop for AnonTask {
@@ -46,6 +46,17 @@ impl ElfOwned {
         Self { array: inner_array }
     }
 
+    /// Read an `len` byte Elf file from `reader` into an aligned buffer
+    #[cfg(feature = "io")]
+    pub fn read_from(reader: &mut impl fs::io::Read, len: usize) -> fs::error::Result<ElfOwned> {
+        let mut inner_array: Vec<u64> = alloc::vec![0; len.div_ceil(8)];
+        let aligned_u8_slice =
+            unsafe { core::slice::from_raw_parts_mut(inner_array.as_mut_ptr().cast(), len) };
+        reader.read_exact(aligned_u8_slice)?;
+
+        Ok(Self { array: inner_array })
+    }
+
     pub fn as_bytes<'a>(&'a self) -> &'a [u8] {
         unsafe {
             core::slice::from_raw_parts(self.array.as_ptr() as *const u8, self.array.len() * 8)
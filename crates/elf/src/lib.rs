@@ -31,12 +31,14 @@ use core::fmt::Display;
 pub mod elf_owned;
 pub mod tables;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ElfErrorKind {
     NotEnoughBytes,
     NotAligned,
     IncorrectBitMode,
     Invalid,
+    MissingSection,
+    UnsupportedRelocation,
 }
 
 impl core::error::Error for ElfErrorKind {}
@@ -88,11 +90,41 @@ impl<'a> Elf<'a> {
     }
 
     pub fn program_header_slice(&self, header: &tables::ElfGenProgramHeader) -> Result<&'a [u8]> {
+        self.bytes_at(header.in_elf_offset(), header.in_elf_size())
+    }
+
+    /// Get `len` bytes at `offset` in the elf file.
+    fn bytes_at(&self, offset: usize, len: usize) -> Result<&'a [u8]> {
+        let end = offset
+            .checked_add(len)
+            .ok_or(ElfErrorKind::NotEnoughBytes)?;
+
         self.elf_file
-            .get(header.in_elf_offset()..header.in_elf_offset() + header.in_elf_size())
+            .get(offset..end)
             .ok_or(ElfErrorKind::NotEnoughBytes)
     }
 
+    /// Get a table of `count` entries of `T` at `offset` in the elf file.
+    fn table_at<T>(&self, offset: usize, count: usize, entry_size: usize) -> Result<&'a [T]> {
+        if count == 0 {
+            return Ok(&[]);
+        }
+        if entry_size != size_of::<T>() {
+            return Err(ElfErrorKind::Invalid);
+        }
+
+        let len = count
+            .checked_mul(entry_size)
+            .ok_or(ElfErrorKind::NotEnoughBytes)?;
+        let table = self.bytes_at(offset, len)?;
+
+        if table.as_ptr() as usize % align_of::<T>() != 0 {
+            return Err(ElfErrorKind::NotAligned);
+        }
+
+        Ok(unsafe { core::slice::from_raw_parts(table.as_ptr().cast(), count) })
+    }
+
     pub fn vaddr_range(&self) -> Result<(usize, usize)> {
         let mut lowest_addr = u64::MAX;
         let mut highest_addr = 0;
@@ -102,12 +134,21 @@ impl<'a> Elf<'a> {
             .filter(|h| h.segment_kind() == tables::SegmentKind::Load)
             .for_each(|h| {
                 lowest_addr = lowest_addr.min(h.expected_vaddr());
-                highest_addr = highest_addr.max(h.expected_vaddr() + h.in_mem_size() as u64);
+                highest_addr =
+                    highest_addr.max(h.expected_vaddr().saturating_add(h.in_mem_size() as u64));
             });
 
         Ok((lowest_addr as usize, highest_addr as usize))
     }
 
+    /// Get the thread local storage template's program header, if this elf has one.
+    pub fn tls_header(&self) -> Result<Option<tables::ElfGenProgramHeader>> {
+        Ok(self
+            .program_headers()?
+            .iter()
+            .find(|h| h.segment_kind() == tables::SegmentKind::Tls))
+    }
+
     pub fn exe_size(&self) -> Result<usize> {
         let (lowest_addr, highest_addr) = self.vaddr_range()?;
 
@@ -162,16 +203,175 @@ impl<'a> Elf<'a> {
             ),
         };
 
-        let program_header_slice = &self.elf_file[offset..(offset + (n_entries * entry_size))];
-
         match header {
-            tables::ElfHeader::Header64(_) => Ok(tables::ElfProgramHeaders::ProgHeader64(unsafe {
-                core::slice::from_raw_parts(program_header_slice.as_ptr().cast(), n_entries)
-            })),
-            tables::ElfHeader::Header32(_) => Ok(tables::ElfProgramHeaders::ProgHeader32(unsafe {
-                core::slice::from_raw_parts(program_header_slice.as_ptr().cast(), n_entries)
-            })),
+            tables::ElfHeader::Header64(_) => Ok(tables::ElfProgramHeaders::ProgHeader64(
+                self.table_at(offset, n_entries, entry_size)?,
+            )),
+            tables::ElfHeader::Header32(_) => Ok(tables::ElfProgramHeaders::ProgHeader32(
+                self.table_at(offset, n_entries, entry_size)?,
+            )),
+        }
+    }
+
+    /// Get all of this elf's section headers.
+    ///
+    /// Sections are only supported on 64-bit elf files.
+    pub fn section_headers(&self) -> Result<&'a [tables::SectionHeader64]> {
+        let tables::ElfHeader::Header64(header) = self.header()? else {
+            return Err(ElfErrorKind::IncorrectBitMode);
+        };
+
+        self.table_at(
+            header.section_header_offset() as usize,
+            header.section_header_count(),
+            header.section_header_size(),
+        )
+    }
+
+    /// Get the bytes of a section in the elf file.
+    pub fn section_slice(&self, section: &tables::SectionHeader64) -> Result<&'a [u8]> {
+        if section.section_kind() == tables::SectionKind::NoBits {
+            return Ok(&[]);
+        }
+
+        self.bytes_at(section.in_elf_offset(), section.in_elf_size())
+    }
+
+    /// Get the name of a section.
+    pub fn section_name(&self, section: &tables::SectionHeader64) -> Result<&'a str> {
+        let tables::ElfHeader::Header64(header) = self.header()? else {
+            return Err(ElfErrorKind::IncorrectBitMode);
+        };
+        let name_table = self
+            .section_headers()?
+            .get(header.section_name_table_index())
+            .ok_or(ElfErrorKind::MissingSection)?;
+
+        string_at(self.section_slice(name_table)?, section.name_offset())
+    }
+
+    /// Find the first section with `name`.
+    pub fn section_by_name(&self, name: &str) -> Result<&'a tables::SectionHeader64> {
+        self.section_headers()?
+            .iter()
+            .find(|section| self.section_name(section).is_ok_and(|n| n == name))
+            .ok_or(ElfErrorKind::MissingSection)
+    }
+
+    /// Get this elf's symbol table.
+    pub fn symbols(&self) -> Result<ElfSymbols<'a>> {
+        let sections = self.section_headers()?;
+        let symbol_table = sections
+            .iter()
+            .find(|section| section.section_kind() == tables::SectionKind::SymbolTable)
+            .ok_or(ElfErrorKind::MissingSection)?;
+        let string_table = sections
+            .get(symbol_table.link())
+            .ok_or(ElfErrorKind::MissingSection)?;
+
+        let entry_size = size_of::<tables::Symbol64>();
+        if symbol_table.entry_size() != entry_size {
+            return Err(ElfErrorKind::Invalid);
+        }
+
+        Ok(ElfSymbols {
+            symbols: self.table_at(
+                symbol_table.in_elf_offset(),
+                symbol_table.in_elf_size() / entry_size,
+                entry_size,
+            )?,
+            strings: self.section_slice(string_table)?,
+        })
+    }
+
+    /// Get the relocations in a `Rela` section.
+    pub fn relocations_of(
+        &self,
+        section: &tables::SectionHeader64,
+    ) -> Result<&'a [tables::Rela64]> {
+        if section.section_kind() != tables::SectionKind::Rela {
+            return Err(ElfErrorKind::Invalid);
         }
+
+        let entry_size = size_of::<tables::Rela64>();
+        if section.entry_size() != entry_size {
+            return Err(ElfErrorKind::Invalid);
+        }
+
+        self.table_at(
+            section.in_elf_offset(),
+            section.in_elf_size() / entry_size,
+            entry_size,
+        )
+    }
+
+    /// Apply all of this elf's relocations as if it was loaded `base` bytes past its expected
+    /// vaddr.
+    ///
+    /// `write_fn` is called with the expected vaddr and the value that should be written to it.
+    /// Only relocations that do not depend on symbols are currently supported.
+    pub fn relocate<F>(&self, base: u64, mut write_fn: F) -> Result<()>
+    where
+        F: FnMut(u64, u64) -> Result<()>,
+    {
+        self.section_headers()?
+            .iter()
+            .filter(|section| section.section_kind() == tables::SectionKind::Rela)
+            .try_for_each(|section| {
+                self.relocations_of(section)?.iter().try_for_each(|rela| {
+                    match rela.relocation_kind() {
+                        tables::RelocationKind::None => Ok(()),
+                        tables::RelocationKind::Relative => {
+                            write_fn(rela.offset(), base.wrapping_add_signed(rela.addend()))
+                        }
+                        _ => Err(ElfErrorKind::UnsupportedRelocation),
+                    }
+                })
+            })
+    }
+}
+
+/// Get the null terminated string at `offset` in a string table.
+fn string_at(table: &[u8], offset: usize) -> Result<&str> {
+    let bytes = table.get(offset..).ok_or(ElfErrorKind::NotEnoughBytes)?;
+
+    core::ffi::CStr::from_bytes_until_nul(bytes)
+        .map_err(|_| ElfErrorKind::Invalid)?
+        .to_str()
+        .map_err(|_| ElfErrorKind::Invalid)
+}
+
+/// An elf's symbol table, and the strings for its names.
+#[derive(Debug, Clone, Copy)]
+pub struct ElfSymbols<'a> {
+    symbols: &'a [tables::Symbol64],
+    strings: &'a [u8],
+}
+
+impl<'a> ElfSymbols<'a> {
+    pub fn iter(&self) -> impl Iterator<Item = &'a tables::Symbol64> + use<'a> {
+        self.symbols.iter()
+    }
+
+    /// Get the name of a symbol.
+    pub fn name_of(&self, symbol: &tables::Symbol64) -> Result<&'a str> {
+        string_at(self.strings, symbol.name_offset())
+    }
+
+    /// Find the symbol with `name`.
+    pub fn by_name(&self, name: &str) -> Option<&'a tables::Symbol64> {
+        self.iter()
+            .find(|symbol| self.name_of(symbol).is_ok_and(|n| n == name))
+    }
+
+    /// Find the function or object symbol containing `addr`.
+    pub fn containing(&self, addr: u64) -> Option<&'a tables::Symbol64> {
+        self.iter().find(|symbol| {
+            matches!(
+                symbol.symbol_kind(),
+                tables::SymbolKind::Func | tables::SymbolKind::Object
+            ) && symbol.contains(addr)
+        })
     }
 }
 
@@ -181,3 +381,162 @@ impl core::fmt::Debug for Elf<'_> {
         f.debug_struct("Elf").finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+    use std::vec::Vec;
+
+    /// A symbol we know will be in the test binary's symbol table.
+    #[unsafe(no_mangle)]
+    #[inline(never)]
+    extern "C" fn elf_test_marker() -> u64 {
+        core::hint::black_box(0x1234)
+    }
+
+    /// Read the running test binary, which is our corpus of a real world elf file.
+    fn corpus() -> Vec<u64> {
+        let bytes = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        aligned(&bytes)
+    }
+
+    fn aligned(bytes: &[u8]) -> Vec<u64> {
+        let mut buffer = std::vec![0u64; bytes.len().div_ceil(8)];
+        unsafe {
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer.as_mut_ptr().cast(), bytes.len())
+        };
+        buffer
+    }
+
+    fn as_bytes(buffer: &[u64], len: usize) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(buffer.as_ptr().cast(), len) }
+    }
+
+    fn corpus_len() -> usize {
+        std::fs::metadata(std::env::current_exe().unwrap())
+            .unwrap()
+            .len() as usize
+    }
+
+    #[test]
+    fn parse_corpus_headers() {
+        let buffer = corpus();
+        let elf = Elf::new(as_bytes(&buffer, corpus_len()));
+
+        match elf.header() {
+            Ok(tables::ElfHeader::Header64(h)) => {
+                assert_eq!(h.arch(), tables::ArchKind::X64);
+                assert!(h.is_le());
+            }
+            _ => panic!("Test binary should be a 64-bit elf"),
+        }
+
+        assert!(
+            elf.program_headers()
+                .unwrap()
+                .iter()
+                .any(|h| h.segment_kind() == tables::SegmentKind::Load)
+        );
+        assert!(elf.section_by_name(".text").unwrap().is_executable());
+    }
+
+    #[test]
+    fn find_corpus_symbol() {
+        let buffer = corpus();
+        let elf = Elf::new(as_bytes(&buffer, corpus_len()));
+        let symbols = elf.symbols().unwrap();
+
+        let marker = symbols.by_name("elf_test_marker").unwrap();
+        assert_eq!(marker.symbol_kind(), tables::SymbolKind::Func);
+        assert_eq!(symbols.name_of(marker), Ok("elf_test_marker"));
+        assert_eq!(
+            symbols
+                .containing(marker.value())
+                .map(|s| symbols.name_of(s)),
+            Some(Ok("elf_test_marker"))
+        );
+
+        assert_eq!(elf_test_marker(), 0x1234);
+    }
+
+    #[test]
+    fn relocate_corpus() {
+        let buffer = corpus();
+        let elf = Elf::new(as_bytes(&buffer, corpus_len()));
+
+        let relative_count = elf
+            .section_headers()
+            .unwrap()
+            .iter()
+            .filter(|s| s.section_kind() == tables::SectionKind::Rela)
+            .flat_map(|s| elf.relocations_of(s).unwrap())
+            .filter(|r| r.relocation_kind() == tables::RelocationKind::Relative)
+            .count();
+
+        let mut written = 0;
+        let result = elf.relocate(0x1000, |_, value| {
+            assert!(value >= 0x1000);
+            written += 1;
+            Ok(())
+        });
+
+        // The test binary might contain relocations that need symbols
+        match result {
+            Ok(()) => assert_eq!(written, relative_count),
+            Err(err) => assert!(matches!(err, ElfErrorKind::UnsupportedRelocation)),
+        }
+    }
+
+    /// Run every parser over `bytes`, which should never panic.
+    fn parse_everything(bytes: &[u8]) {
+        let elf = Elf::new(bytes);
+
+        let _ = elf.header();
+        let _ = elf.vaddr_range();
+        let _ = elf.tls_header();
+        if let Ok(headers) = elf.program_headers() {
+            headers.iter().for_each(|h| {
+                let _ = elf.program_header_slice(&h);
+            });
+        }
+        if let Ok(sections) = elf.section_headers() {
+            sections.iter().for_each(|s| {
+                let _ = elf.section_name(s);
+                let _ = elf.section_slice(s);
+                let _ = elf.relocations_of(s);
+            });
+        }
+        if let Ok(symbols) = elf.symbols() {
+            symbols.iter().for_each(|s| {
+                let _ = symbols.name_of(s);
+            });
+        }
+        let _ = elf.relocate(0, |_, _| Ok(()));
+    }
+
+    #[test]
+    fn truncated_corpus() {
+        let buffer = corpus();
+
+        for len in (0..512).chain((512..corpus_len()).step_by(4099)) {
+            parse_everything(as_bytes(&buffer, len));
+        }
+    }
+
+    #[test]
+    fn corrupted_corpus() {
+        let len = corpus_len();
+        let original = corpus();
+
+        // Corrupt each byte of the elf header, and the start of the program headers
+        for offset in 0..256 {
+            for value in [0x00, 0x7F, 0xFF] {
+                let mut buffer = original.clone();
+                unsafe { *buffer.as_mut_ptr().cast::<u8>().add(offset) = value };
+
+                parse_everything(as_bytes(&buffer, len));
+            }
+        }
+    }
+}
@@ -134,6 +134,23 @@ impl Elf64Header {
     pub const fn entry_point(&self) -> u64 {
         self.entry_offset
     }
+
+    pub const fn section_header_offset(&self) -> u64 {
+        self.section_header_offset
+    }
+
+    pub const fn section_header_count(&self) -> usize {
+        self.section_header_entries as usize
+    }
+
+    pub const fn section_header_size(&self) -> usize {
+        self.section_header_entry_size as usize
+    }
+
+    /// The index of the section containing the names of all other sections.
+    pub const fn section_name_table_index(&self) -> usize {
+        self.string_table_offset as usize
+    }
 }
 
 impl<'a> TryFrom<&'a [u8]> for &'a Elf64Header {
@@ -382,6 +399,9 @@ pub enum SegmentKind {
     Dynamic,
     Interp,
     Note,
+    Shlib,
+    ProgramHeader,
+    Tls,
     Unknown(u32),
 }
 
@@ -393,6 +413,9 @@ impl From<u32> for SegmentKind {
             2 => Self::Dynamic,
             3 => Self::Interp,
             4 => Self::Note,
+            5 => Self::Shlib,
+            6 => Self::ProgramHeader,
+            7 => Self::Tls,
             v => Self::Unknown(v),
         }
     }
@@ -544,3 +567,243 @@ impl ElfGenProgramHeader {
         self.alignment
     }
 }
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SectionHeader64 {
+    name_offset: u32,
+    section_kind: u32,
+    flags: u64,
+    s_addr: u64,
+    s_offset: u64,
+    s_size: u64,
+    link: u32,
+    info: u32,
+    alignment: u64,
+    entry_size: u64,
+}
+
+impl SectionHeader64 {
+    pub fn section_kind(&self) -> SectionKind {
+        self.section_kind.into()
+    }
+
+    /// The offset of this section's name in the section name string table.
+    pub const fn name_offset(&self) -> usize {
+        self.name_offset as usize
+    }
+
+    pub const fn is_writable(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    pub const fn is_alloc(&self) -> bool {
+        self.flags & 2 != 0
+    }
+
+    pub const fn is_executable(&self) -> bool {
+        self.flags & 4 != 0
+    }
+
+    pub const fn expected_vaddr(&self) -> u64 {
+        self.s_addr
+    }
+
+    pub const fn in_elf_offset(&self) -> usize {
+        self.s_offset as usize
+    }
+
+    pub const fn in_elf_size(&self) -> usize {
+        self.s_size as usize
+    }
+
+    /// The index of the section this section depends on (e.g. a symbol table's string table).
+    pub const fn link(&self) -> usize {
+        self.link as usize
+    }
+
+    pub const fn info(&self) -> u32 {
+        self.info
+    }
+
+    pub const fn alignment(&self) -> u64 {
+        self.alignment
+    }
+
+    pub const fn entry_size(&self) -> usize {
+        self.entry_size as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SectionKind {
+    Null,
+    ProgramBits,
+    SymbolTable,
+    StringTable,
+    Rela,
+    Hash,
+    Dynamic,
+    Note,
+    NoBits,
+    Rel,
+    DynamicSymbolTable,
+    Unknown(u32),
+}
+
+impl From<u32> for SectionKind {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Null,
+            1 => Self::ProgramBits,
+            2 => Self::SymbolTable,
+            3 => Self::StringTable,
+            4 => Self::Rela,
+            5 => Self::Hash,
+            6 => Self::Dynamic,
+            7 => Self::Note,
+            8 => Self::NoBits,
+            9 => Self::Rel,
+            11 => Self::DynamicSymbolTable,
+            v => Self::Unknown(v),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Symbol64 {
+    name_offset: u32,
+    info: u8,
+    other: u8,
+    section_index: u16,
+    value: u64,
+    size: u64,
+}
+
+impl Symbol64 {
+    /// The offset of this symbol's name in its symbol table's string table.
+    pub const fn name_offset(&self) -> usize {
+        self.name_offset as usize
+    }
+
+    pub fn symbol_kind(&self) -> SymbolKind {
+        (self.info & 0xF).into()
+    }
+
+    pub fn binding(&self) -> SymbolBinding {
+        (self.info >> 4).into()
+    }
+
+    pub const fn section_index(&self) -> usize {
+        self.section_index as usize
+    }
+
+    pub const fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Check if `addr` is within this symbol.
+    pub const fn contains(&self, addr: u64) -> bool {
+        addr >= self.value && addr - self.value < self.size
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    NoType,
+    Object,
+    Func,
+    Section,
+    File,
+    Common,
+    Tls,
+    Unknown(u8),
+}
+
+impl From<u8> for SymbolKind {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::NoType,
+            1 => Self::Object,
+            2 => Self::Func,
+            3 => Self::Section,
+            4 => Self::File,
+            5 => Self::Common,
+            6 => Self::Tls,
+            v => Self::Unknown(v),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolBinding {
+    Local,
+    Global,
+    Weak,
+    Unknown(u8),
+}
+
+impl From<u8> for SymbolBinding {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Local,
+            1 => Self::Global,
+            2 => Self::Weak,
+            v => Self::Unknown(v),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Rela64 {
+    r_offset: u64,
+    info: u64,
+    addend: i64,
+}
+
+impl Rela64 {
+    /// The vaddr this relocation should be applied to.
+    pub const fn offset(&self) -> u64 {
+        self.r_offset
+    }
+
+    pub fn relocation_kind(&self) -> RelocationKind {
+        ((self.info & 0xFFFF_FFFF) as u32).into()
+    }
+
+    pub const fn symbol_index(&self) -> usize {
+        (self.info >> 32) as usize
+    }
+
+    pub const fn addend(&self) -> i64 {
+        self.addend
+    }
+}
+
+/// x86_64 relocation kinds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelocationKind {
+    None,
+    /// `S + A`
+    Abs64,
+    /// `B + A`
+    Relative,
+    Unknown(u32),
+}
+
+impl From<u32> for RelocationKind {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::None,
+            1 => Self::Abs64,
+            8 => Self::Relative,
+            v => Self::Unknown(v),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use fs::error::Result;
+use fs::fatfs::Fat;
+use fs::io::{Read, Seek, SeekFrom};
+use fs::std_io::StdReadSeek;
+use std::cell::Cell;
+use std::io::Cursor;
+use std::rc::Rc;
+
+const SECTOR: usize = 512;
+const CLUSTER_SECTORS: usize = 2;
+const CLUSTER: usize = SECTOR * CLUSTER_SECTORS;
+const CLUSTERS: usize = 8000;
+const ROOT_ENTRIES: usize = 512;
+const FAT_SECTORS: usize = ((CLUSTERS + 2) * 2).div_ceil(SECTOR);
+const ROOT_SECTORS: usize = ROOT_ENTRIES * 32 / SECTOR;
+const DATA_START: usize = (1 + FAT_SECTORS + ROOT_SECTORS) * SECTOR;
+const TOTAL_SECTORS: usize = 1 + FAT_SECTORS + ROOT_SECTORS + CLUSTERS * CLUSTER_SECTORS;
+
+/// Clusters in each file
+const FILE_CLUSTERS: usize = 256;
+
+/// Counts every read the filesystem makes, each of which would be a BIOS or ATA round trip
+struct CountingDisk {
+    inner: StdReadSeek<Cursor<Rc<[u8]>>>,
+    reads: Rc<Cell<usize>>,
+}
+
+impl Read for CountingDisk {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.reads.set(self.reads.get() + 1);
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for CountingDisk {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.inner.seek(pos)
+    }
+
+    fn stream_position(&mut self) -> u64 {
+        self.inner.stream_position()
+    }
+}
+
+/// Build a FAT16 image with `linear.bin` stored in one run of clusters, and `scatter.bin`
+/// stored in every other cluster.
+fn build_image() -> Rc<[u8]> {
+    let mut image = vec![0u8; TOTAL_SECTORS * SECTOR];
+
+    image[0] = 0xEB;
+    image[11..13].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+    image[13] = CLUSTER_SECTORS as u8;
+    image[14..16].copy_from_slice(&1u16.to_le_bytes());
+    image[16] = 1;
+    image[17..19].copy_from_slice(&(ROOT_ENTRIES as u16).to_le_bytes());
+    image[19..21].copy_from_slice(&(TOTAL_SECTORS as u16).to_le_bytes());
+    image[22..24].copy_from_slice(&(FAT_SECTORS as u16).to_le_bytes());
+
+    let linear: Vec<u16> = (2..2 + FILE_CLUSTERS as u16).collect();
+    let scatter: Vec<u16> = (0..FILE_CLUSTERS as u16)
+        .map(|index| 1000 + index * 2)
+        .collect();
+
+    for (file_index, (name, chain)) in [("linear.bin", &linear), ("scatter.bin", &scatter)]
+        .into_iter()
+        .enumerate()
+    {
+        for (index, &cluster) in chain.iter().enumerate() {
+            let next = chain.get(index + 1).copied().unwrap_or(0xFFFF);
+            let entry = SECTOR + cluster as usize * 2;
+            image[entry..entry + 2].copy_from_slice(&next.to_le_bytes());
+
+            let data = DATA_START + (cluster as usize - 2) * CLUSTER;
+            image[data..data + CLUSTER].fill(index as u8);
+        }
+
+        let root = (1 + FAT_SECTORS) * SECTOR + file_index * 64;
+        let lfn = &mut image[root..root + 32];
+        lfn[0] = 0x41;
+        lfn[11] = 0x0F;
+        for (char_index, c) in name.bytes().enumerate() {
+            let offset = match char_index {
+                0..=4 => 1 + char_index * 2,
+                5..=10 => 14 + (char_index - 5) * 2,
+                _ => 28 + (char_index - 11) * 2,
+            };
+            lfn[offset] = c;
+        }
+
+        let file = &mut image[root + 32..root + 64];
+        file[..11].copy_from_slice(b"FILE    BIN");
+        file[11] = 0x20;
+        file[26..28].copy_from_slice(&chain[0].to_le_bytes());
+        file[28..32].copy_from_slice(&((FILE_CLUSTERS * CLUSTER) as u32).to_le_bytes());
+    }
+
+    image.into()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let image = build_image();
+    let reads = Rc::new(Cell::new(0));
+    let mut fat = Fat::new(CountingDisk {
+        inner: StdReadSeek::new(Cursor::new(image)),
+        reads: reads.clone(),
+    })
+    .unwrap();
+    let mut buffer = vec![0u8; FILE_CLUSTERS * CLUSTER];
+
+    let mut group = c.benchmark_group("Fat Read");
+
+    for name in ["linear.bin", "scatter.bin"] {
+        reads.set(0);
+        fat.open(name).unwrap().read(&mut buffer).unwrap();
+        println!(
+            "Reading {name} ({FILE_CLUSTERS} clusters) took {} disk reads",
+            reads.get()
+        );
+
+        group.bench_function(name, |f| {
+            f.iter(|| fat.open(name).unwrap().read(&mut buffer).unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,125 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Walk arbitrary directories and cluster chains on a valid FAT16 volume.
+//!
+//! The BPB is fixed, so every input reaches the directory and FAT parsers. The
+//! rest of the disk is the input repeated, so the FAT, root directory and the
+//! data clusters are all fuzzed at once.
+
+#![no_main]
+
+use fs::{
+    error::{FsError, Result},
+    fatfs::Fat,
+    io::Read,
+    partition::{Partition, PartitionKind},
+    read_block::BlockDevice,
+};
+use libfuzzer_sys::fuzz_target;
+
+const SECTOR_SIZE: usize = 512;
+const TOTAL_SECTORS: u16 = 10240;
+
+const PATHS: &[&str] = &[
+    "hello.txt",
+    "a file with a long name.txt",
+    "docs/readme.md",
+    "docs/../docs/./readme.md",
+    "a/b/c/d/e/f",
+];
+
+/// A FAT16 BPB with 2 sector clusters, 1 reserved sector, 2 FATs of 20
+/// sectors and 512 root entries.
+fn boot_sector() -> [u8; SECTOR_SIZE] {
+    let mut sector = [0; SECTOR_SIZE];
+
+    sector[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+    sector[3..11].copy_from_slice(b"FUZZING ");
+    sector[11..13].copy_from_slice(&(SECTOR_SIZE as u16).to_le_bytes());
+    sector[13] = 2;
+    sector[14..16].copy_from_slice(&1u16.to_le_bytes());
+    sector[16] = 2;
+    sector[17..19].copy_from_slice(&512u16.to_le_bytes());
+    sector[19..21].copy_from_slice(&TOTAL_SECTORS.to_le_bytes());
+    sector[21] = 0xF8;
+    sector[22..24].copy_from_slice(&20u16.to_le_bytes());
+    sector[38] = 0x29;
+    sector[43..54].copy_from_slice(b"FUZZ       ");
+    sector[54..62].copy_from_slice(b"FAT16   ");
+    sector[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    sector
+}
+
+/// Sector 0 is the boot sector, every other sector is taken from the input.
+struct FuzzDisk<'a> {
+    boot: [u8; SECTOR_SIZE],
+    data: &'a [u8],
+    sector: [u8; SECTOR_SIZE],
+}
+
+impl BlockDevice for FuzzDisk<'_> {
+    const BLOCK_SIZE: usize = SECTOR_SIZE;
+
+    fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+        if block_offset >= TOTAL_SECTORS as u64 {
+            return Err(FsError::EndOfFile);
+        }
+
+        if block_offset == 0 {
+            return Ok(&self.boot);
+        }
+
+        let start = (block_offset as usize - 1) * SECTOR_SIZE;
+        for (index, byte) in self.sector.iter_mut().enumerate() {
+            *byte = self.data[(start + index) % self.data.len()];
+        }
+
+        Ok(&self.sector)
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+
+    let disk = FuzzDisk {
+        boot: boot_sector(),
+        data,
+        sector: [0; SECTOR_SIZE],
+    };
+    let length = TOTAL_SECTORS as u64 * SECTOR_SIZE as u64;
+    let mut fat = Fat::new(Partition::new(disk, PartitionKind::Whole, 0, length))
+        .expect("The fixed BPB should always be valid");
+
+    let mut buffer = [0; 4096];
+    for path in PATHS {
+        if let Ok(mut file) = fat.open(path) {
+            let _ = file.read(&mut buffer);
+        }
+    }
+});
@@ -0,0 +1,626 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    error::{FsError, Result},
+    read_block::BlockDevice,
+};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// # Request Id
+/// Handed out by `RequestQueue::submit` so completions can be matched to their request.
+pub type RequestId = u32;
+
+/// # Scheduler Kind
+/// How a `RequestQueue` picks the order it sends requests to its device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulerKind {
+    /// Send requests in the order they were submitted.
+    ///
+    /// Devices without a seek penalty (virtio, NVMe, ram disks) gain nothing from
+    /// reordering.
+    Noop,
+    /// Sweep across the disk in rising block order (C-LOOK), merging requests that touch
+    /// into one read.
+    Elevator,
+}
+
+/// # Queue Stats
+/// Counters for how well a `RequestQueue` managed to merge its requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Requests accepted by `submit`.
+    pub submitted: u64,
+    /// Reads sent to the device.
+    pub dispatched: u64,
+    /// Requests that were served by another request's read.
+    pub merged: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    id: RequestId,
+    first_block: u64,
+    blocks: u64,
+}
+
+impl Pending {
+    const fn end(&self) -> u64 {
+        self.first_block + self.blocks
+    }
+}
+
+/// # Request Queue
+/// Collects block reads for one device and sends them in the order its `SchedulerKind`
+/// prefers.
+///
+/// At most `DEPTH` requests can be waiting at once.
+pub struct RequestQueue<const DEPTH: usize = 32> {
+    kind: SchedulerKind,
+    pending: [Option<Pending>; DEPTH],
+    len: usize,
+    next_id: RequestId,
+    head: u64,
+    stats: QueueStats,
+}
+
+impl<const DEPTH: usize> RequestQueue<DEPTH> {
+    /// Create an empty queue using `kind`.
+    pub const fn new(kind: SchedulerKind) -> Self {
+        Self {
+            kind,
+            pending: [None; DEPTH],
+            len: 0,
+            next_id: 0,
+            head: 0,
+            stats: QueueStats {
+                submitted: 0,
+                dispatched: 0,
+                merged: 0,
+            },
+        }
+    }
+
+    /// Create an empty queue using the scheduler `Device` prefers.
+    pub const fn for_device<Device: BlockDevice>() -> Self {
+        Self::new(Device::SCHEDULER)
+    }
+
+    /// The scheduler this queue is using.
+    pub const fn kind(&self) -> SchedulerKind {
+        self.kind
+    }
+
+    /// Requests waiting to be dispatched.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn stats(&self) -> QueueStats {
+        self.stats
+    }
+
+    /// Queue a read of `blocks` blocks starting at `first_block`.
+    ///
+    /// Returns `None` when the queue is full or `blocks` is zero.
+    pub fn submit(&mut self, first_block: u64, blocks: u64) -> Option<RequestId> {
+        if self.len == DEPTH || blocks == 0 {
+            return None;
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        // Slots are kept packed in submission order, which is what `Noop` dispatches in
+        self.pending[self.len] = Some(Pending {
+            id,
+            first_block,
+            blocks,
+        });
+        self.len += 1;
+        self.stats.submitted += 1;
+
+        Some(id)
+    }
+
+    fn pending(&self) -> impl Iterator<Item = (usize, &Pending)> {
+        self.pending[..self.len]
+            .iter()
+            .enumerate()
+            .filter_map(|(index, pending)| pending.as_ref().map(|pending| (index, pending)))
+    }
+
+    /// The slot to dispatch next.
+    fn next_slot(&self) -> Option<usize> {
+        match self.kind {
+            SchedulerKind::Noop => (self.len > 0).then_some(0),
+            SchedulerKind::Elevator => {
+                // Keep sweeping upwards from the last read, wrapping back to the lowest
+                // request once nothing is left above it.
+                let lowest = |above: u64| {
+                    self.pending()
+                        .filter(|(_, pending)| pending.first_block >= above)
+                        .min_by_key(|(_, pending)| pending.first_block)
+                        .map(|(index, _)| index)
+                };
+
+                lowest(self.head).or_else(|| lowest(0))
+            }
+        }
+    }
+
+    /// Send every waiting request to `device`, calling `complete` with the bytes of each
+    /// request as it finishes.
+    ///
+    /// `scratch` holds each read, so it bounds how many blocks can be merged together and
+    /// must fit the largest request. If the device fails, the requests of the failing read
+    /// are dropped and the rest stay queued.
+    pub fn run<Device: BlockDevice>(
+        &mut self,
+        device: &mut Device,
+        scratch: &mut [u8],
+        mut complete: impl FnMut(RequestId, &[u8]),
+    ) -> Result<()> {
+        let max_blocks = (scratch.len() / Device::BLOCK_SIZE) as u64;
+
+        while let Some(slot) = self.next_slot() {
+            let primary = self.pending[slot].ok_or(FsError::InvalidInput)?;
+
+            if primary.blocks > max_blocks {
+                return Err(FsError::InvalidInput);
+            }
+
+            let mut members = [false; DEPTH];
+            members[slot] = true;
+            let mut first_block = primary.first_block;
+            let mut end = primary.end();
+
+            if self.kind == SchedulerKind::Elevator {
+                // Growing the read can bring new requests into reach, so keep going until
+                // nothing else touches it.
+                let mut grew = true;
+                while grew {
+                    grew = false;
+
+                    for index in 0..self.len {
+                        let Some(pending) = self.pending[index] else {
+                            continue;
+                        };
+
+                        if members[index]
+                            || pending.first_block > end
+                            || pending.end() < first_block
+                        {
+                            continue;
+                        }
+
+                        let merged_first = first_block.min(pending.first_block);
+                        let merged_end = end.max(pending.end());
+                        if merged_end - merged_first > max_blocks {
+                            continue;
+                        }
+
+                        members[index] = true;
+                        first_block = merged_first;
+                        end = merged_end;
+                        grew = true;
+                    }
+                }
+            }
+
+            let bytes = (end - first_block) as usize * Device::BLOCK_SIZE;
+            let read = device.read_blocks(first_block, &mut scratch[..bytes]);
+
+            let mut served = 0u64;
+            for index in 0..self.len {
+                if !members[index] {
+                    continue;
+                }
+
+                if let (Ok(()), Some(pending)) = (&read, self.pending[index]) {
+                    let start = (pending.first_block - first_block) as usize * Device::BLOCK_SIZE;
+                    let len = pending.blocks as usize * Device::BLOCK_SIZE;
+                    complete(pending.id, &scratch[start..start + len]);
+                }
+
+                self.pending[index] = None;
+                served += 1;
+            }
+
+            self.compact();
+            self.head = end;
+            self.stats.dispatched += 1;
+            self.stats.merged += served - 1;
+
+            read?;
+        }
+
+        Ok(())
+    }
+
+    /// Drop every waiting request without reading it.
+    pub fn clear(&mut self) {
+        self.pending = [None; DEPTH];
+        self.len = 0;
+    }
+
+    /// Close the gaps left by dispatched requests, keeping submission order.
+    fn compact(&mut self) {
+        let mut kept = 0;
+
+        for index in 0..self.len {
+            if let Some(pending) = self.pending[index].take() {
+                self.pending[kept] = Some(pending);
+                kept += 1;
+            }
+        }
+
+        self.len = kept;
+    }
+}
+
+/// How many blocks a `ScheduledDisk` can read with one command.
+#[cfg(feature = "alloc")]
+const SCRATCH_BLOCKS: usize = 128;
+
+/// # Scheduled Disk
+/// Sends every read of `Device` through a `RequestQueue`, using the scheduler `Device`
+/// prefers.
+///
+/// Each buffer of a vectored read is its own request, so the elevator merges the ones that
+/// touch into as few reads as the scratch buffer allows. Writes go straight to the device,
+/// which is safe since every read is finished before the read call returns.
+#[cfg(feature = "alloc")]
+pub struct ScheduledDisk<Device: BlockDevice, const DEPTH: usize = 32> {
+    device: Device,
+    queue: RequestQueue<DEPTH>,
+    scratch: Vec<u8>,
+    block: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl<Device: BlockDevice, const DEPTH: usize> ScheduledDisk<Device, DEPTH> {
+    pub fn new(device: Device) -> Self {
+        Self {
+            device,
+            queue: RequestQueue::for_device::<Device>(),
+            scratch: vec![0; SCRATCH_BLOCKS * Device::BLOCK_SIZE],
+            block: vec![0; Device::BLOCK_SIZE],
+        }
+    }
+
+    pub const fn stats(&self) -> QueueStats {
+        self.queue.stats()
+    }
+
+    pub fn get_ref(&self) -> &Device {
+        &self.device
+    }
+
+    /// Send every submitted request to the device, copying each into its buffer.
+    fn run(&mut self, waiting: &mut Vec<(RequestId, &mut [u8])>) -> Result<()> {
+        let read = self
+            .queue
+            .run(&mut self.device, &mut self.scratch, |id, bytes| {
+                if let Some((_, buf)) = waiting.iter_mut().find(|(waiting, _)| *waiting == id) {
+                    buf.copy_from_slice(bytes);
+                }
+            });
+
+        // The buffers of the requests left behind by a failed read are about to go away
+        if read.is_err() {
+            self.queue.clear();
+        }
+
+        waiting.clear();
+        read
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<Device: BlockDevice, const DEPTH: usize> BlockDevice for ScheduledDisk<Device, DEPTH> {
+    const BLOCK_SIZE: usize = Device::BLOCK_SIZE;
+    // The requests were already ordered here
+    const SCHEDULER: SchedulerKind = SchedulerKind::Noop;
+
+    fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+        let mut block = core::mem::take(&mut self.block);
+        let read = self.read_blocks(block_offset, &mut block);
+        self.block = block;
+
+        read.map(|()| &self.block[..])
+    }
+
+    fn read_blocks(&mut self, block_offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.read_blocks_vectored(block_offset, &mut [buf])
+    }
+
+    fn read_blocks_vectored(&mut self, block_offset: u64, bufs: &mut [&mut [u8]]) -> Result<()> {
+        let max_len = self.scratch.len();
+        let mut waiting = Vec::new();
+        let mut block = block_offset;
+
+        for chunk in bufs.iter_mut().flat_map(|buf| buf.chunks_mut(max_len)) {
+            let blocks = (chunk.len() / Device::BLOCK_SIZE) as u64;
+            let id = match self.queue.submit(block, blocks) {
+                Some(id) => id,
+                None => {
+                    self.run(&mut waiting)?;
+                    self.queue
+                        .submit(block, blocks)
+                        .ok_or(FsError::InvalidInput)?
+                }
+            };
+
+            waiting.push((id, chunk));
+            block += blocks;
+        }
+
+        self.run(&mut waiting)
+    }
+
+    fn write_blocks(&mut self, block_offset: u64, buf: &[u8]) -> Result<()> {
+        self.device.write_blocks(block_offset, buf)
+    }
+
+    fn write_blocks_vectored(&mut self, block_offset: u64, bufs: &[&[u8]]) -> Result<()> {
+        self.device.write_blocks_vectored(block_offset, bufs)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.device.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec::Vec;
+
+    /// Each block is filled with its own index, and every read is recorded.
+    struct Disk {
+        block: [u8; 4],
+        reads: Vec<(u64, usize)>,
+    }
+
+    impl Disk {
+        fn new() -> Self {
+            Self {
+                block: [0; 4],
+                reads: Vec::new(),
+            }
+        }
+    }
+
+    impl BlockDevice for Disk {
+        const BLOCK_SIZE: usize = 4;
+
+        fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+            if block_offset >= 64 {
+                return Err(FsError::EndOfFile);
+            }
+
+            self.block = [block_offset as u8; 4];
+            Ok(&self.block)
+        }
+
+        fn read_blocks(&mut self, block_offset: u64, buf: &mut [u8]) -> Result<()> {
+            self.reads
+                .push((block_offset, buf.len() / Self::BLOCK_SIZE));
+
+            for (index, chunk) in buf.chunks_mut(Self::BLOCK_SIZE).enumerate() {
+                chunk.copy_from_slice(self.read_block(block_offset + index as u64)?);
+            }
+
+            Ok(())
+        }
+    }
+
+    fn run_all<const DEPTH: usize>(
+        queue: &mut RequestQueue<DEPTH>,
+        disk: &mut Disk,
+        scratch_blocks: usize,
+    ) -> Vec<(RequestId, Vec<u8>)> {
+        let mut scratch = std::vec![0; scratch_blocks * Disk::BLOCK_SIZE];
+        let mut done = Vec::new();
+
+        queue
+            .run(disk, &mut scratch, |id, bytes| {
+                done.push((id, bytes.to_vec()))
+            })
+            .unwrap();
+
+        done
+    }
+
+    #[test]
+    fn test_noop_keeps_submission_order() {
+        let mut disk = Disk::new();
+        let mut queue = RequestQueue::<8>::new(SchedulerKind::Noop);
+
+        queue.submit(10, 1).unwrap();
+        queue.submit(2, 1).unwrap();
+        queue.submit(3, 1).unwrap();
+
+        let done = run_all(&mut queue, &mut disk, 8);
+
+        assert_eq!(disk.reads, [(10, 1), (2, 1), (3, 1)]);
+        assert_eq!(
+            done.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(queue.stats().merged, 0);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_elevator_sorts_and_merges() {
+        let mut disk = Disk::new();
+        let mut queue = RequestQueue::<8>::new(SchedulerKind::Elevator);
+
+        let far = queue.submit(20, 2).unwrap();
+        let second = queue.submit(5, 1).unwrap();
+        let first = queue.submit(4, 1).unwrap();
+        let third = queue.submit(6, 2).unwrap();
+
+        let done = run_all(&mut queue, &mut disk, 8);
+
+        assert_eq!(disk.reads, [(4, 4), (20, 2)]);
+        assert_eq!(queue.stats().dispatched, 2);
+        assert_eq!(queue.stats().merged, 2);
+
+        let bytes_of = |id| done.iter().find(|(done, _)| *done == id).unwrap().1.clone();
+        assert_eq!(bytes_of(first), [4; 4]);
+        assert_eq!(bytes_of(second), [5; 4]);
+        assert_eq!(bytes_of(third), [6, 6, 6, 6, 7, 7, 7, 7]);
+        assert_eq!(bytes_of(far), [20, 20, 20, 20, 21, 21, 21, 21]);
+    }
+
+    #[test]
+    fn test_elevator_merges_overlapping() {
+        let mut disk = Disk::new();
+        let mut queue = RequestQueue::<8>::new(SchedulerKind::Elevator);
+
+        queue.submit(8, 4).unwrap();
+        queue.submit(9, 1).unwrap();
+        queue.submit(11, 3).unwrap();
+
+        let done = run_all(&mut queue, &mut disk, 8);
+
+        assert_eq!(disk.reads, [(8, 6)]);
+        assert_eq!(done.len(), 3);
+        assert_eq!(done[1].1, [9; 4]);
+    }
+
+    #[test]
+    fn test_elevator_sweeps_from_head() {
+        let mut disk = Disk::new();
+        let mut queue = RequestQueue::<8>::new(SchedulerKind::Elevator);
+
+        queue.submit(30, 1).unwrap();
+        run_all(&mut queue, &mut disk, 8);
+
+        queue.submit(2, 1).unwrap();
+        queue.submit(40, 1).unwrap();
+        queue.submit(35, 1).unwrap();
+        run_all(&mut queue, &mut disk, 8);
+
+        assert_eq!(disk.reads, [(30, 1), (35, 1), (40, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn test_merging_limited_by_scratch() {
+        let mut disk = Disk::new();
+        let mut queue = RequestQueue::<8>::new(SchedulerKind::Elevator);
+
+        for block in 0..6 {
+            queue.submit(block, 1).unwrap();
+        }
+
+        run_all(&mut queue, &mut disk, 4);
+
+        assert_eq!(disk.reads, [(0, 4), (4, 2)]);
+    }
+
+    #[test]
+    fn test_full_queue_and_oversized_requests() {
+        let mut disk = Disk::new();
+        let mut queue = RequestQueue::<2>::new(SchedulerKind::Elevator);
+
+        assert!(queue.submit(0, 0).is_none());
+        queue.submit(0, 8).unwrap();
+        queue.submit(1, 1).unwrap();
+        assert!(queue.submit(2, 1).is_none());
+
+        let mut scratch = [0; 4 * Disk::BLOCK_SIZE];
+        assert!(matches!(
+            queue.run(&mut disk, &mut scratch, |_, _| {}),
+            Err(FsError::InvalidInput)
+        ));
+    }
+
+    #[test]
+    fn test_scheduled_disk_merges_vectored_reads() {
+        let mut disk = ScheduledDisk::<_, 4>::new(Disk::new());
+
+        let mut first = [0; 8];
+        let mut second = [0; 4];
+        let mut third = [0; 12];
+        disk.read_blocks_vectored(3, &mut [&mut first, &mut second, &mut third])
+            .unwrap();
+
+        assert_eq!(first, [3, 3, 3, 3, 4, 4, 4, 4]);
+        assert_eq!(second, [5; 4]);
+        assert_eq!(third, [6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 8]);
+        assert_eq!(disk.get_ref().reads, [(3, 6)]);
+        assert_eq!(disk.stats().merged, 2);
+
+        assert_eq!(disk.read_block(9).unwrap(), [9; 4]);
+        assert!(disk.read_block(100).is_err());
+        assert_eq!(disk.read_block(10).unwrap(), [10; 4]);
+    }
+
+    #[test]
+    fn test_scheduled_disk_fills_queue() {
+        let mut disk = ScheduledDisk::<_, 2>::new(Disk::new());
+
+        let mut bufs = [[0; 4]; 5];
+        let mut bufs = bufs.iter_mut().map(|buf| &mut buf[..]).collect::<Vec<_>>();
+        disk.read_blocks_vectored(0, &mut bufs).unwrap();
+
+        assert_eq!(
+            bufs.concat(),
+            (0..5).flat_map(|block| [block; 4]).collect::<Vec<_>>()
+        );
+        assert_eq!(disk.get_ref().reads, [(0, 2), (2, 2), (4, 1)]);
+    }
+
+    #[test]
+    fn test_failed_read_keeps_other_requests() {
+        let mut disk = Disk::new();
+        let mut queue = RequestQueue::<4>::new(SchedulerKind::Noop);
+
+        queue.submit(100, 1).unwrap();
+        queue.submit(1, 1).unwrap();
+
+        let mut scratch = [0; 4 * Disk::BLOCK_SIZE];
+        let mut done = 0;
+        assert!(queue
+            .run(&mut disk, &mut scratch, |_, _| done += 1)
+            .is_err());
+        assert_eq!(done, 0);
+        assert_eq!(queue.len(), 1);
+
+        queue
+            .run(&mut disk, &mut scratch, |_, _| done += 1)
+            .unwrap();
+        assert_eq!(done, 1);
+    }
+}
@@ -30,6 +30,49 @@ pub enum FsError {
     InvalidInput,
     NotFound,
     NotSupported,
+    /// The filesystem's structures on disk are malformed or inconsistent.
+    Corrupt,
+    /// The volume was mounted read-only, so it cannot be changed.
+    ReadOnly,
+    /// Something already exists where a new file or directory was to be made.
+    AlreadyExists,
+    /// There is no free space left on the volume.
+    NoSpace,
+    /// The disk itself reported that a command failed.
+    DiskError(DiskError),
+}
+
+/// # Disk Error
+/// The reason a disk gave for failing a command.
+///
+/// ATA devices report errors as a bitmask in their error register; when more than
+/// one bit is set the most severe one is reported here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskError {
+    /// The sector was marked bad by the host and cannot be read.
+    BadBlock,
+    /// The data could not be recovered with ECC.
+    Uncorrectable,
+    /// The requested sector ID was not found.
+    IdNotFound,
+    /// No address mark was found after the ID field.
+    AddressMarkNotFound,
+    /// Track 0 could not be found during a recalibrate.
+    Track0NotFound,
+    /// The media was changed and the command was not run.
+    MediaChanged,
+    /// The host asked to remove the media.
+    MediaChangeRequest,
+    /// There is no media in a removable drive.
+    NoMedia,
+    /// The command was aborted, either because it is unsupported or it failed.
+    Aborted,
+    /// The device set its fault bit without saying why.
+    DeviceFault,
+    /// The device never became ready.
+    Timeout,
+    /// There is no device at this location.
+    NoDevice,
 }
 
 pub type Result<T> = core::result::Result<T, FsError>;
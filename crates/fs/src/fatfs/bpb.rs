@@ -23,10 +23,13 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use super::{ClusterId, FatKind, ReadSeek};
+use super::{
+    format::{FormatOptions, Layout, BACKUP_BOOT_SECTOR, FS_INFO_SECTOR},
+    ClusterId, FatKind, ReadSeek,
+};
 use crate::error::{FsError, Result};
 use crate::io::SeekFrom;
-use core::ops::RangeInclusive;
+use core::{mem::size_of, ops::RangeInclusive};
 
 #[repr(C, packed)]
 #[derive(Clone, Copy)]
@@ -64,7 +67,7 @@ struct Bpb16 {
 struct Bpb32 {
     fat_size: u32,
     ext_flags: u16,
-    fat_version: u32,
+    fat_version: u16,
     root_cluster: u32,
     fs_info: u16,
     boot_sector: u16,
@@ -101,7 +104,7 @@ impl Bpb {
         let mut sector_buffer = [0u8; 512];
 
         disk.seek(SeekFrom::Start(0))?;
-        disk.read(&mut sector_buffer)?;
+        disk.read_exact(&mut sector_buffer)?;
 
         let bpb: Self = unsafe { *sector_buffer.as_ptr().cast() };
 
@@ -110,14 +113,115 @@ impl Bpb {
             return Err(FsError::InvalidInput);
         }
 
+        if !bpb.sectors_per_cluster.is_power_of_two()
+            || bpb.reserved_sectors == 0
+            || bpb.number_fats == 0
+            || bpb.fat_sectors() == 0
+            || bpb.total_sectors() <= bpb.overhead_sectors()
+        {
+            return Err(FsError::Corrupt);
+        }
+
+        // Sectors are always read 512 bytes at a time
+        if bpb.bytes_per_sector != 512 {
+            return Err(FsError::NotSupported);
+        }
+
+        if matches!(bpb.kind(), FatKind::Fat32) && !bpb.is_valid_cluster(bpb.root_cluster()) {
+            return Err(FsError::Corrupt);
+        }
+
         Ok(bpb)
     }
 
+    /// The BPB of a new volume laid out like `layout`
+    pub(super) fn for_layout(layout: &Layout, options: &FormatOptions) -> Self {
+        let volume_label = options.label.unwrap_or(*b"NO NAME    ");
+        let fits_fat16 = layout.total_sectors <= u16::MAX as u32;
+
+        let (jmp_boot, extended) = match layout.kind {
+            FatKind::Fat12 | FatKind::Fat16 => (
+                [0xEB, 0x3C, 0x90],
+                ExtendedBpb {
+                    fat16: Bpb16 {
+                        drive_number: 0x80,
+                        reserved: 0,
+                        boot_signature: 0x29,
+                        volume_id: options.volume_id,
+                        volume_label,
+                        fs_str: match layout.kind {
+                            FatKind::Fat12 => *b"FAT12   ",
+                            _ => *b"FAT16   ",
+                        },
+                    },
+                },
+            ),
+            FatKind::Fat32 => (
+                [0xEB, 0x58, 0x90],
+                ExtendedBpb {
+                    fat32: Bpb32 {
+                        fat_size: layout.fat_sectors,
+                        ext_flags: 0,
+                        fat_version: 0,
+                        root_cluster: Layout::ROOT_CLUSTER,
+                        fs_info: FS_INFO_SECTOR,
+                        boot_sector: BACKUP_BOOT_SECTOR,
+                        reserved: [0; 12],
+                        drive_number: 0x80,
+                        reserved2: 0,
+                        boot_signature: 0x29,
+                        volume_id: options.volume_id,
+                        volume_label,
+                        fs_str: *b"FAT32   ",
+                    },
+                },
+            ),
+        };
+        let is_fat32 = matches!(layout.kind, FatKind::Fat32);
+
+        Self {
+            jmp_boot,
+            oem_name: *b"MSWIN4.1",
+            bytes_per_sector: 512,
+            sectors_per_cluster: layout.cluster_sectors,
+            reserved_sectors: layout.reserved_sectors,
+            number_fats: Layout::NUMBER_FATS,
+            root_entries: layout.root_entries,
+            total_sectors_fat16: if fits_fat16 && !is_fat32 {
+                layout.total_sectors as u16
+            } else {
+                0
+            },
+            media_type: Layout::MEDIA_FIXED_DISK,
+            fat_sectors_fat16: if is_fat32 {
+                0
+            } else {
+                layout.fat_sectors as u16
+            },
+            sectors_per_track: 63,
+            head_count: 255,
+            hidden_sectors: options.hidden_sectors,
+            total_sectors_fat32: if fits_fat16 && !is_fat32 {
+                0
+            } else {
+                layout.total_sectors
+            },
+            extended,
+        }
+    }
+
+    /// This BPB as it is stored at the start of the boot sector
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts((self as *const Self).cast::<u8>(), size_of::<Self>())
+        }
+    }
+
     pub fn sector_size(&self) -> usize {
         self.bytes_per_sector as usize
     }
 
-    fn root_sectors(&self) -> usize {
+    pub(super) fn root_sectors(&self) -> usize {
         // 3.5 Determination of FAT type when mounting the Volume (page: 14)
         ((self.root_entries as usize * Self::ROOT_ENTRY_SIZE)
             + (self.bytes_per_sector as usize - 1))
@@ -132,7 +236,7 @@ impl Bpb {
         }
     }
 
-    fn fat_sectors(&self) -> usize {
+    pub(super) fn fat_sectors(&self) -> usize {
         if self.fat_sectors_fat16 != 0 {
             self.fat_sectors_fat16 as usize
         } else {
@@ -140,17 +244,31 @@ impl Bpb {
         }
     }
 
-    fn clusters(&self) -> usize {
-        let data_sectors = self.total_sectors()
-            - (self.reserved_sectors as usize
-                + (self.number_fats as usize * self.fat_sectors())
-                + self.root_sectors());
+    /// The sectors before the first cluster
+    fn overhead_sectors(&self) -> usize {
+        self.reserved_sectors as usize
+            + (self.number_fats as usize * self.fat_sectors())
+            + self.root_sectors()
+    }
+
+    pub fn clusters(&self) -> usize {
+        let data_sectors = self.total_sectors().saturating_sub(self.overhead_sectors());
 
         data_sectors / (self.sectors_per_cluster as usize)
     }
 
+    /// If `cluster` is one of the volume's data clusters
+    pub fn is_valid_cluster(&self, cluster: ClusterId) -> bool {
+        (2..self.clusters() as u64 + 2).contains(&(cluster as u64))
+    }
+
     pub fn kind(&self) -> FatKind {
-        match self.clusters() {
+        Self::kind_of(self.clusters())
+    }
+
+    /// The kind of FAT a volume with `clusters` data clusters is
+    pub(super) fn kind_of(clusters: usize) -> FatKind {
+        match clusters {
             ..=Self::FAT12_CLUSTERS => FatKind::Fat12,
             ..=Self::FAT16_CLUSTERS => FatKind::Fat16,
             _ => FatKind::Fat32,
@@ -164,6 +282,19 @@ impl Bpb {
         }
     }
 
+    /// How many copies of the FAT the volume keeps
+    pub(super) fn fat_count(&self) -> usize {
+        self.number_fats as usize
+    }
+
+    /// The sector of FAT32's FSInfo structure, which caches how many clusters are free
+    pub(super) fn fs_info_sector(&self) -> Option<u64> {
+        match self.safe_extended() {
+            ExtendedKind::Fat16(_) => None,
+            ExtendedKind::Fat32(ext) => Some(ext.fs_info as u64).filter(|&sector| sector != 0),
+        }
+    }
+
     pub fn fat_range(&self) -> RangeInclusive<u64> {
         let fat_start = self.reserved_sectors as u64;
         let fat_end = fat_start + (self.fat_sectors() as u64);
@@ -173,8 +304,8 @@ impl Bpb {
 
     pub fn volume_label<'a>(&'a self) -> &'a str {
         match self.safe_extended() {
-            ExtendedKind::Fat16(ext) => core::str::from_utf8(&ext.volume_label).unwrap(),
-            ExtendedKind::Fat32(ext) => core::str::from_utf8(&ext.volume_label).unwrap(),
+            ExtendedKind::Fat16(ext) => core::str::from_utf8(&ext.volume_label).unwrap_or(""),
+            ExtendedKind::Fat32(ext) => core::str::from_utf8(&ext.volume_label).unwrap_or(""),
         }
     }
 
@@ -201,12 +332,4 @@ impl Bpb {
     pub fn cluster_sectors(&self) -> usize {
         self.sectors_per_cluster as usize
     }
-
-    pub fn fat_entry_bytes(&self) -> usize {
-        match self.kind() {
-            FatKind::Fat12 => todo!("Fat12 not impl"),
-            FatKind::Fat16 => 2,
-            FatKind::Fat32 => 4,
-        }
-    }
 }
@@ -0,0 +1,302 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{bpb::Bpb, inode::ATTR_VOLUME_ID, FatEntry, FatKind};
+use crate::{
+    error::{FsError, Result},
+    io::{Seek, SeekFrom, Write},
+};
+
+const SECTOR_SIZE: usize = 512;
+/// The size of one entry in a directory
+const DIR_ENTRY_SIZE: usize = 32;
+
+/// FAT32 keeps a copy of its boot sector, and of its FSInfo sector after it
+pub(super) const BACKUP_BOOT_SECTOR: u16 = 6;
+pub(super) const FS_INFO_SECTOR: u16 = 1;
+
+/// FSInfo signatures, and where they go in its sector
+pub(super) const FS_INFO_LEAD_SIGNATURE: (usize, u32) = (0, 0x4161_5252);
+const FS_INFO_STRUCT_SIGNATURE: (usize, u32) = (484, 0x6141_7272);
+const FS_INFO_TRAIL_SIGNATURE: (usize, u32) = (508, 0xAA55_0000);
+pub(super) const FS_INFO_FREE_CLUSTERS: usize = 488;
+const FS_INFO_NEXT_FREE: usize = 492;
+
+/// # Format Options
+/// How [`Fat::format`](super::Fat::format) lays out a new volume.
+#[derive(Debug, Clone, Copy)]
+pub struct FormatOptions {
+    pub(super) sectors: Option<u32>,
+    pub(super) cluster_sectors: Option<u8>,
+    pub(super) label: Option<[u8; 11]>,
+    pub(super) volume_id: u32,
+    pub(super) hidden_sectors: u32,
+}
+
+impl FormatOptions {
+    /// Use the whole disk, with the smallest clusters that give the asked for [`FatKind`].
+    pub const fn new() -> Self {
+        Self {
+            sectors: None,
+            cluster_sectors: None,
+            label: None,
+            volume_id: 0,
+            hidden_sectors: 0,
+        }
+    }
+
+    /// Only use the first `sectors` of the disk.
+    pub const fn with_sectors(mut self, sectors: u32) -> Self {
+        self.sectors = Some(sectors);
+        self
+    }
+
+    /// Use clusters of `sectors` sectors, this must be a power of two.
+    pub const fn with_cluster_sectors(mut self, sectors: u8) -> Self {
+        self.cluster_sectors = Some(sectors);
+        self
+    }
+
+    /// Name the volume, the name is upper cased and cut to 11 chars.
+    pub fn with_label(mut self, label: &str) -> Self {
+        let mut name = [b' '; 11];
+        name.iter_mut()
+            .zip(label.bytes())
+            .for_each(|(name, byte)| *name = byte.to_ascii_uppercase());
+
+        self.label = Some(name);
+        self
+    }
+
+    /// Set the serial number the volume is told apart from others with.
+    pub const fn with_volume_id(mut self, volume_id: u32) -> Self {
+        self.volume_id = volume_id;
+        self
+    }
+
+    /// Set how many sectors of the disk come before the volume, like the first sector of
+    /// the partition it is in.
+    pub const fn with_hidden_sectors(mut self, sectors: u32) -> Self {
+        self.hidden_sectors = sectors;
+        self
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where everything goes on a new volume
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Layout {
+    pub(super) kind: FatKind,
+    pub(super) total_sectors: u32,
+    pub(super) cluster_sectors: u8,
+    pub(super) reserved_sectors: u16,
+    pub(super) fat_sectors: u32,
+    pub(super) root_entries: u16,
+    pub(super) clusters: u32,
+}
+
+impl Layout {
+    pub(super) const NUMBER_FATS: u8 = 2;
+    pub(super) const MEDIA_FIXED_DISK: u8 = 0xF8;
+    /// Where the root directory starts on FAT32, FAT12 and FAT16 have a fixed root
+    pub(super) const ROOT_CLUSTER: u32 = 2;
+
+    /// Lay out a `kind` volume of `total_sectors`, with clusters of `cluster_sectors`
+    /// or the smallest clusters that give that kind.
+    fn new(kind: FatKind, total_sectors: u32, cluster_sectors: Option<u8>) -> Result<Self> {
+        if cluster_sectors.is_some_and(|sectors| !sectors.is_power_of_two()) {
+            return Err(FsError::InvalidInput);
+        }
+
+        (0..8)
+            .map(|shift| 1 << shift)
+            .filter(|&sectors| cluster_sectors.is_none_or(|wanted| wanted == sectors))
+            .filter_map(|sectors| Self::with_cluster_sectors(kind, total_sectors, sectors))
+            .find(|layout| Bpb::kind_of(layout.clusters as usize) == kind)
+            .ok_or(FsError::InvalidInput)
+    }
+
+    fn with_cluster_sectors(
+        kind: FatKind,
+        total_sectors: u32,
+        cluster_sectors: u8,
+    ) -> Option<Self> {
+        let (reserved_sectors, root_entries) = match kind {
+            FatKind::Fat12 | FatKind::Fat16 => (1, 512),
+            FatKind::Fat32 => (32, 0),
+        };
+        let root_sectors =
+            (root_entries as u64 * DIR_ENTRY_SIZE as u64).div_ceil(SECTOR_SIZE as u64);
+
+        // Sizing the FAT as if it took up no room overestimates it slightly, which is fine
+        let most_clusters = (total_sectors as u64)
+            .checked_sub(reserved_sectors as u64 + root_sectors)?
+            / cluster_sectors as u64;
+        let fat_bytes = match kind {
+            FatKind::Fat12 => ((most_clusters + 2) * 3).div_ceil(2),
+            FatKind::Fat16 => (most_clusters + 2) * 2,
+            FatKind::Fat32 => (most_clusters + 2) * 4,
+        };
+        let fat_sectors = fat_bytes.div_ceil(SECTOR_SIZE as u64);
+
+        let overhead =
+            reserved_sectors as u64 + Self::NUMBER_FATS as u64 * fat_sectors + root_sectors;
+        let clusters = (total_sectors as u64).checked_sub(overhead)? / cluster_sectors as u64;
+        if clusters == 0 || clusters + 2 > FatEntry::FAT32_MAX as u64 {
+            return None;
+        }
+
+        Some(Self {
+            kind,
+            total_sectors,
+            cluster_sectors,
+            reserved_sectors,
+            fat_sectors: fat_sectors as u32,
+            root_entries,
+            clusters: clusters as u32,
+        })
+    }
+
+    fn root_sectors(&self) -> u64 {
+        (self.root_entries as u64 * DIR_ENTRY_SIZE as u64).div_ceil(SECTOR_SIZE as u64)
+    }
+
+    fn first_fat_sector(&self, fat: u8) -> u64 {
+        self.reserved_sectors as u64 + fat as u64 * self.fat_sectors as u64
+    }
+
+    /// The first sector of the root directory, and how many sectors it takes up
+    fn root_directory(&self) -> (u64, u64) {
+        let after_fats = self.first_fat_sector(Self::NUMBER_FATS);
+
+        match self.kind {
+            FatKind::Fat12 | FatKind::Fat16 => (after_fats, self.root_sectors()),
+            // The root is the first cluster
+            FatKind::Fat32 => (after_fats, self.cluster_sectors as u64),
+        }
+    }
+
+    /// The first two FAT entries, which hold the media type and the clean shutdown bit
+    fn reserved_fat_entries(&self) -> [u8; 12] {
+        let mut entries = [0; 12];
+        match self.kind {
+            FatKind::Fat12 => {
+                // Entries 0 and 1 are packed into 3 bytes, `0xFF8` then `0xFFF`
+                entries[..3].copy_from_slice(&[Self::MEDIA_FIXED_DISK, 0xFF, 0xFF]);
+            }
+            FatKind::Fat16 => {
+                entries[..2]
+                    .copy_from_slice(&(0xFF00 | Self::MEDIA_FIXED_DISK as u16).to_le_bytes());
+                entries[2..4].copy_from_slice(&(FatEntry::FAT16_EOF as u16).to_le_bytes());
+            }
+            FatKind::Fat32 => {
+                entries[..4]
+                    .copy_from_slice(&(0x0FFF_FF00 | Self::MEDIA_FIXED_DISK as u32).to_le_bytes());
+                entries[4..8].copy_from_slice(&FatEntry::FAT32_EOF.to_le_bytes());
+                // The root directory is a single cluster
+                entries[8..12].copy_from_slice(&FatEntry::FAT32_EOF.to_le_bytes());
+            }
+        }
+
+        entries
+    }
+}
+
+fn write_sector<Disk: Write + Seek>(
+    disk: &mut Disk,
+    sector: u64,
+    data: &[u8; SECTOR_SIZE],
+) -> Result<()> {
+    disk.seek(SeekFrom::Start(sector * SECTOR_SIZE as u64))?;
+    disk.write_all(data)
+}
+
+/// Write an empty `kind` volume to the start of `disk`.
+pub(super) fn format<Disk: Write + Seek>(
+    disk: &mut Disk,
+    kind: FatKind,
+    options: &FormatOptions,
+) -> Result<()> {
+    let total_sectors = match options.sectors {
+        Some(sectors) => sectors,
+        None => {
+            let bytes = disk.seek(SeekFrom::End(0))?;
+            u32::try_from(bytes / SECTOR_SIZE as u64).map_err(|_| FsError::NotSupported)?
+        }
+    };
+    let layout = Layout::new(kind, total_sectors, options.cluster_sectors)?;
+    let (root_start, root_sectors) = layout.root_directory();
+
+    // Everything before the data clusters starts out empty, so the FATs are all free
+    let zeros = [0; SECTOR_SIZE];
+    disk.seek(SeekFrom::Start(0))?;
+    for _ in 0..root_start + root_sectors {
+        disk.write_all(&zeros)?;
+    }
+
+    let mut boot_sector = [0; SECTOR_SIZE];
+    let bpb = Bpb::for_layout(&layout, options);
+    boot_sector[..bpb.as_bytes().len()].copy_from_slice(bpb.as_bytes());
+    boot_sector[510..].copy_from_slice(&[0x55, 0xAA]);
+    write_sector(disk, 0, &boot_sector)?;
+
+    for fat in 0..Layout::NUMBER_FATS {
+        let mut first_sector = [0; SECTOR_SIZE];
+        let entries = layout.reserved_fat_entries();
+        first_sector[..entries.len()].copy_from_slice(&entries);
+        write_sector(disk, layout.first_fat_sector(fat), &first_sector)?;
+    }
+
+    if let Some(label) = options.label {
+        let mut root = [0; SECTOR_SIZE];
+        root[..11].copy_from_slice(&label);
+        root[11] = ATTR_VOLUME_ID;
+        write_sector(disk, root_start, &root)?;
+    }
+
+    if let FatKind::Fat32 = kind {
+        let mut fs_info = [0; SECTOR_SIZE];
+        let mut put = |offset: usize, value: u32| {
+            fs_info[offset..offset + 4].copy_from_slice(&value.to_le_bytes())
+        };
+        put(FS_INFO_LEAD_SIGNATURE.0, FS_INFO_LEAD_SIGNATURE.1);
+        put(FS_INFO_STRUCT_SIGNATURE.0, FS_INFO_STRUCT_SIGNATURE.1);
+        put(FS_INFO_TRAIL_SIGNATURE.0, FS_INFO_TRAIL_SIGNATURE.1);
+        // Only the root directory's cluster is used
+        put(FS_INFO_FREE_CLUSTERS, layout.clusters - 1);
+        put(FS_INFO_NEXT_FREE, Layout::ROOT_CLUSTER + 1);
+
+        write_sector(disk, FS_INFO_SECTOR as u64, &fs_info)?;
+        write_sector(disk, (BACKUP_BOOT_SECTOR + FS_INFO_SECTOR) as u64, &fs_info)?;
+        write_sector(disk, BACKUP_BOOT_SECTOR as u64, &boot_sector)?;
+    }
+
+    disk.flush()
+}
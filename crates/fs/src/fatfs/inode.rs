@@ -27,6 +27,19 @@ use super::ClusterId;
 use crate::error::FsError;
 use core::mem::size_of;
 
+pub(super) const ATTR_VOLUME_ID: u8 = 0x08;
+pub(super) const ATTR_DIRECTORY: u8 = 0x10;
+/// Set on a new or changed file, so backup tools know to look at it
+pub(super) const ATTR_ARCHIVE: u8 = 0x20;
+
+/// Bits of the reserved byte Windows NT uses to show an 8.3 name in lower case, so
+/// names like `kernel.log` don't need a long file name
+const CASE_LOWER_BASE: u8 = 0x08;
+const CASE_LOWER_EXT: u8 = 0x10;
+
+/// The chars besides letters and digits an 8.3 name may contain
+const SHORT_NAME_SYMBOLS: &[u8] = b"!#$%&'()-@^_`{}~";
+
 #[derive(Clone, Copy, Debug)]
 pub enum Inode {
     Dir(DirectoryEntry),
@@ -109,12 +122,12 @@ impl<'a> TryFrom<&'a [u8]> for Inode {
     type Error = FsError;
     fn try_from(value: &'a [u8]) -> Result<Inode, Self::Error> {
         let value = value.as_ref();
-        assert!(
-            value.len() >= size_of::<DirectoryEntry>(),
-            "Byte stream for Inode cannot be less than Inode's size! buf.len() = {}, while size_of::<DirectoryEntry> = {}", value.len(), size_of::<DirectoryEntry>()
-        );
+        if value.len() < size_of::<DirectoryEntry>() {
+            return Err(FsError::Corrupt);
+        }
 
-        if value.iter().all(|&item| item == 0) {
+        // Unused and deleted entries
+        if value.iter().all(|&item| item == 0) || value[0] == 0xE5 {
             return Err(FsError::NotFound);
         }
 
@@ -133,7 +146,113 @@ impl<'a> TryFrom<&'a [u8]> for Inode {
 }
 
 impl DirectoryEntry {
+    /// A new empty entry named by the 8.3 `name`, with `case` from [`short_name_of`]
+    pub(super) fn new(name: [u8; 11], case: u8, attributes: u8) -> Self {
+        Self {
+            name,
+            attributes,
+            reserved: case,
+            time_tenth: 0,
+            creation_time: 0,
+            creation_date: 0,
+            last_access_date: 0,
+            cluster_high: 0,
+            modified_time: 0,
+            modified_date: 0,
+            cluster_low: 0,
+            file_size: 0,
+        }
+    }
+
     pub fn cluster_id(&self) -> ClusterId {
         self.cluster_low as u32 | ((self.cluster_high as u32) << 16)
     }
+
+    pub(super) fn set_cluster_id(&mut self, cluster: ClusterId) {
+        self.cluster_low = cluster as u16;
+        self.cluster_high = (cluster >> 16) as u16;
+    }
+
+    pub fn file_size(&self) -> u32 {
+        self.file_size
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.attributes & ATTR_DIRECTORY != 0
+    }
+
+    pub(super) fn is_volume_label(&self) -> bool {
+        self.attributes & (ATTR_VOLUME_ID | ATTR_DIRECTORY) == ATTR_VOLUME_ID
+    }
+
+    /// Rename this entry to the 8.3 `name`, with `case` from [`short_name_of`]
+    pub(super) fn set_short_name(&mut self, name: [u8; 11], case: u8) {
+        self.name = name;
+        self.reserved = case;
+    }
+
+    /// The 8.3 name of this entry as `NAME.EXT`, and how many bytes of it are used
+    pub(super) fn short_name(&self) -> ([u8; 12], usize) {
+        let mut name = [0; 12];
+        let base = self.name[..8].trim_ascii_end();
+        let ext = self.name[8..].trim_ascii_end();
+
+        name[..base.len()].copy_from_slice(base);
+        if ext.is_empty() {
+            return (name, base.len());
+        }
+
+        name[base.len()] = b'.';
+        name[base.len() + 1..][..ext.len()].copy_from_slice(ext);
+        (name, base.len() + 1 + ext.len())
+    }
+
+    /// This entry as it is stored in a directory
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts((self as *const Self).cast::<u8>(), size_of::<Self>())
+        }
+    }
+}
+
+/// # Short Name Of
+/// The 8.3 name `name` is stored as, and the case bits of the entry's reserved byte.
+///
+/// Returns `None` if `name` does not fit in 8.3, which would need a long file name.
+pub(super) fn short_name_of(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, ext) = match name.rsplit_once('.') {
+        Some((base, ext)) => (base, ext),
+        None => (name, ""),
+    };
+
+    if !(1..=8).contains(&base.len()) || ext.len() > 3 {
+        return None;
+    }
+
+    let valid = |byte: u8| byte.is_ascii_alphanumeric() || SHORT_NAME_SYMBOLS.contains(&byte);
+    let mut short = [b' '; 11];
+    let mut case = 0;
+
+    for (part, range, lower_bit) in [(base, 0..8, CASE_LOWER_BASE), (ext, 8..11, CASE_LOWER_EXT)] {
+        if !part.bytes().all(valid) {
+            return None;
+        }
+
+        // Each part has one case bit, so it can't mix cases
+        let has_lower = part.bytes().any(|byte| byte.is_ascii_lowercase());
+        if has_lower && part.bytes().any(|byte| byte.is_ascii_uppercase()) {
+            return None;
+        }
+
+        if has_lower {
+            case |= lower_bit;
+        }
+
+        short[range]
+            .iter_mut()
+            .zip(part.bytes())
+            .for_each(|(short, byte)| *short = byte.to_ascii_uppercase());
+    }
+
+    Some((short, case))
 }
@@ -30,14 +30,18 @@ use crate::{
 };
 use crate::{
     fatfs::inode::{DirectoryEntry, Inode},
-    io::{Read, Seek},
+    io::{take_io_vecs, Read, Seek, Write, MAX_IO_VECS},
 };
-use core::{cell::SyncUnsafeCell, fmt::Debug, mem::size_of};
+use core::{fmt::Debug, mem::size_of};
 
 mod bpb;
+mod format;
 mod inode;
+mod write;
 
-#[derive(Debug)]
+pub use format::FormatOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FatKind {
     Fat12,
     Fat16,
@@ -50,10 +54,30 @@ impl<T: Read + Seek> ReadSeek for T {}
 pub struct Fat<Part: ReadSeek> {
     disk: Part,
     bpb: Bpb,
+    /// The last sectors of the FAT we read, and the first sector they start at
+    fat_cache: Option<(u64, [u8; FAT_CACHE_BYTES])>,
+    /// Where to start looking for a free cluster
+    next_free: ClusterId,
+    /// If the volume was changed since it was mounted or last synced
+    dirty: bool,
 }
 
 type ClusterId = u32;
 
+/// The number of FAT sectors read at once, so following a chain doesn't need a disk read
+/// for every cluster
+const FAT_CACHE_SECTORS: usize = 4;
+const FAT_CACHE_BYTES: usize = FAT_CACHE_SECTORS * 512;
+
+/// The size of one entry in a directory
+const DIR_ENTRY_SIZE: usize = size_of::<DirectoryEntry>();
+const DIR_ENTRIES_PER_SECTOR: u64 = 512 / DIR_ENTRY_SIZE as u64;
+
+/// Max number of LFN entries that can make up a single name
+const LFN_MAX_ENTRIES: usize = 20;
+/// Max number of chars in a long file name
+const LFN_MAX_CHARS: usize = LFN_MAX_ENTRIES * 13;
+
 #[derive(Debug, Clone, Copy)]
 enum FatEntry {
     Free,
@@ -77,34 +101,51 @@ impl FatEntry {
     const FAT32_MAX: u32 = 0xffffff4;
     const FAT32_RESERVED_END: u32 = 0xffffff6;
     const FAT32_DEFECTIVE: u32 = Self::FAT32_RESERVED_END + 1;
-    const FAT32_EOF: u32 = u32::MAX;
+    const FAT32_EOF: u32 = 0xfffffff;
+    /// FAT32 entries are only 28 bits, the top 4 bits are reserved
+    const FAT32_MASK: u32 = 0xfffffff;
 
     fn from_fat16(id: ClusterId) -> FatEntry {
         match id {
             Self::FREE_CLUSTER => FatEntry::Free,
             Self::ALLOCATED_CLUSTER_BEGIN..=Self::FAT16_MAX => FatEntry::Next(id),
-            ..=Self::FAT16_RESERVED_END => FatEntry::Reserved,
             Self::FAT16_DEFECTIVE => FatEntry::Defective,
-            Self::FAT16_EOF => FatEntry::EOF,
-            _ => unreachable!("ClusterID Unknown"),
+            // Any value past the defective marker ends the chain
+            Self::FAT16_DEFECTIVE..=Self::FAT16_EOF => FatEntry::EOF,
+            _ => FatEntry::Reserved,
         }
     }
 
     fn from_fat32(id: ClusterId) -> FatEntry {
-        match id {
+        match id & Self::FAT32_MASK {
             Self::FREE_CLUSTER => FatEntry::Free,
-            Self::ALLOCATED_CLUSTER_BEGIN..=Self::FAT32_MAX => FatEntry::Next(id),
-            ..=Self::FAT32_RESERVED_END => FatEntry::Reserved,
+            id @ Self::ALLOCATED_CLUSTER_BEGIN..=Self::FAT32_MAX => FatEntry::Next(id),
             Self::FAT32_DEFECTIVE => FatEntry::Defective,
-            Self::FAT32_EOF => FatEntry::EOF,
-            _ => unreachable!("ClusterID Unknown"),
+            // Any value past the defective marker ends the chain
+            Self::FAT32_DEFECTIVE..=Self::FAT32_EOF => FatEntry::EOF,
+            _ => FatEntry::Reserved,
         }
     }
 }
 
+/// # Dir Slot
+/// Where an entry is in its directory, so it can be changed later.
+#[derive(Debug, Clone, Copy)]
+struct DirSlot {
+    /// The first cluster of the directory holding the entry, `0` for the fixed root of
+    /// FAT12 and FAT16
+    dir: ClusterId,
+    /// The index of the first entry making up this one, its first LFN entry if it has any
+    first_index: u64,
+    /// The index of the entry itself
+    index: u64,
+    entry: DirectoryEntry,
+}
+
 pub struct FatFile<'a, Part: ReadSeek> {
     filesize: usize,
     start_cluster: ClusterId,
+    slot: DirSlot,
     last_cluster: Option<(ClusterId, u64)>,
     fatfs: &'a mut Fat<Part>,
     seek: u64,
@@ -122,10 +163,13 @@ where
     Part: ReadSeek,
 {
     fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
-        match pos {
-            SeekFrom::Start(pos) => self.seek = pos,
-            _ => todo!("SeekFrom is not fully implemented, only Start(x) is implemented"),
+        self.seek = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(offset) => self.seek.checked_add_signed(offset),
+            SeekFrom::End(offset) => (self.filesize as u64).checked_add_signed(offset),
         }
+        .ok_or(FsError::InvalidInput)?;
+
         Ok(self.seek)
     }
 
@@ -134,65 +178,123 @@ where
     }
 }
 
+impl<'a, Part: ReadSeek> FatFile<'a, Part> {
+    /// Find the run of clusters that follow each other on disk from the seek position,
+    /// returning where it starts on disk and how many of the next `len` bytes it holds.
+    fn next_run(&mut self, len: u64) -> Result<(u64, u64)> {
+        let cluster_bytes =
+            (self.fatfs.bpb.cluster_sectors() * self.fatfs.bpb.sector_size()) as u64;
+
+        let (cluster_id, offset) = match self.last_cluster {
+            Some((last_cluster, last_seek)) if last_seek <= self.seek => {
+                (last_cluster, self.seek - last_seek)
+            }
+            _ => (self.start_cluster, self.seek),
+        };
+
+        let (cluster, cluster_offset) = self.fatfs.cluster_of_offset(cluster_id, offset)?;
+
+        // Clusters that follow each other on disk are read together
+        let clusters_wanted = (cluster_offset + len).div_ceil(cluster_bytes);
+        let (run_clusters, run_last) = self.fatfs.contiguous_run(cluster, clusters_wanted)?;
+
+        // Remember where the last cluster we read starts, not where we are in it, so
+        // later reads can walk the chain from it
+        let run_start_seek = self.seek - cluster_offset;
+        self.last_cluster = Some((
+            run_last,
+            run_start_seek + (run_clusters - 1) * cluster_bytes,
+        ));
+
+        let disk_loc = self.fatfs.bpb.cluster_physical_loc(cluster) + cluster_offset;
+        let bytes_until_run_end = run_clusters * cluster_bytes - cluster_offset;
+
+        Ok((disk_loc, bytes_until_run_end.min(len)))
+    }
+}
+
 impl<'a, Part> Read for FatFile<'a, Part>
 where
     Part: ReadSeek,
 {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let cluster_bytes =
-            (self.fatfs.bpb.cluster_sectors() * self.fatfs.bpb.sector_size()) as u64;
         let mut bytes_read = 0;
 
-        loop {
-            let (cluster_id, offset) = match self.last_cluster {
-                Some((last_cluster, last_seek)) if last_seek <= self.seek => {
-                    (last_cluster, self.seek - last_seek)
-                }
-                _ => (self.start_cluster, self.seek),
-            };
-
-            let cluster_info = self.fatfs.cluster_of_offset(cluster_id, offset)?;
-            self.last_cluster = Some((cluster_info.0, self.seek));
-
-            let disk_loc = self.fatfs.bpb.cluster_physical_loc(cluster_info.0) + cluster_info.1;
+        while bytes_read < buf.len() {
+            let (disk_loc, run_bytes) = self.next_run((buf.len() - bytes_read) as u64)?;
 
             self.fatfs.disk.seek(SeekFrom::Start(disk_loc))?;
-            let bytes_until_cluster_end = cluster_bytes - cluster_info.1;
-            let bytes_until_read_end = bytes_until_cluster_end.min((buf.len() - bytes_read) as u64);
-
             self.fatfs
                 .disk
-                .read(&mut buf[bytes_read..bytes_read + bytes_until_read_end as usize])?;
+                .read_exact(&mut buf[bytes_read..bytes_read + run_bytes as usize])?;
 
-            bytes_read += bytes_until_read_end as usize;
-            self.seek += bytes_until_read_end;
+            bytes_read += run_bytes as usize;
+            self.seek += run_bytes;
+        }
 
-            assert!(
-                bytes_read <= buf.len(),
-                "Attemped to more bytes ({}) than buffer's capacity ({})!",
-                bytes_read,
-                buf.len()
-            );
+        Ok(bytes_read)
+    }
+
+    /// Each run of clusters is read with one vectored read of the disk, no matter how many
+    /// of `bufs` it covers.
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        let mut bytes_read = 0;
+
+        for chunk in bufs.chunks_mut(MAX_IO_VECS) {
+            let mut remaining: usize = chunk.iter().map(|buf| buf.len()).sum();
+            let mut pending: [&mut [u8]; MAX_IO_VECS] = Default::default();
+            for (pending, buf) in pending.iter_mut().zip(chunk.iter_mut()) {
+                *pending = buf;
+            }
 
-            if bytes_read == buf.len() {
-                return Ok(bytes_read);
+            while remaining != 0 {
+                let (disk_loc, run_bytes) = self.next_run(remaining as u64)?;
+
+                let mut batch: [&mut [u8]; MAX_IO_VECS] = Default::default();
+                let taken = take_io_vecs(&mut pending, run_bytes as usize, &mut batch);
+
+                self.fatfs.disk.seek(SeekFrom::Start(disk_loc))?;
+                self.fatfs.disk.read_vectored_exact(&mut batch[..taken])?;
+
+                bytes_read += run_bytes as usize;
+                remaining -= run_bytes as usize;
+                self.seek += run_bytes;
             }
         }
+
+        Ok(bytes_read)
     }
 }
 
-static FAT_BLOCK_RESERVE: SyncUnsafeCell<(u64, [u8; 512])> = SyncUnsafeCell::new((0, [0; 512]));
-
 impl<Part: ReadSeek> Fat<Part> {
     pub fn new(mut disk: Part) -> Result<Self> {
         let bpb = Bpb::new(&mut disk)?;
 
-        Ok(Self { disk, bpb })
+        Ok(Self {
+            disk,
+            bpb,
+            fat_cache: None,
+            next_free: FatEntry::ALLOCATED_CLUSTER_BEGIN,
+            dirty: false,
+        })
     }
 
     fn read_fat(&mut self, id: ClusterId) -> Result<FatEntry> {
+        Ok(match self.bpb.kind() {
+            FatKind::Fat16 => FatEntry::from_fat16(self.read_raw_fat(id)?),
+            FatKind::Fat32 => FatEntry::from_fat32(self.read_raw_fat(id)?),
+            FatKind::Fat12 => return Err(FsError::NotSupported),
+        })
+    }
+
+    fn read_raw_fat(&mut self, id: ClusterId) -> Result<u32> {
+        let entry_bytes = match self.bpb.kind() {
+            FatKind::Fat12 => return Err(FsError::NotSupported),
+            FatKind::Fat16 => 2,
+            FatKind::Fat32 => 4,
+        };
         let fat_region = self.bpb.fat_range();
-        let entries_per_sector = (self.bpb.sector_size()) / self.bpb.fat_entry_bytes();
+        let entries_per_sector = self.bpb.sector_size() / entry_bytes;
 
         let entry_sector = (id / entries_per_sector as u32) as u64 + *fat_region.start();
         let entry_offset = (id % entries_per_sector as u32) as usize;
@@ -201,33 +303,38 @@ impl<Part: ReadSeek> Fat<Part> {
             return Err(FsError::InvalidInput);
         }
 
-        if entry_sector != unsafe { (&*FAT_BLOCK_RESERVE.get()).0 } {
-            self.disk.seek(SeekFrom::Start(
-                entry_sector * self.bpb.sector_size() as u64,
-            ))?;
-            unsafe {
-                self.disk
-                    .read(&mut (&mut *FAT_BLOCK_RESERVE.get()).1.as_mut())?;
-                (&mut *FAT_BLOCK_RESERVE.get()).0 = entry_sector;
+        let cache = match self.fat_cache {
+            Some((first_sector, ref cache))
+                if (first_sector..first_sector + FAT_CACHE_SECTORS as u64)
+                    .contains(&entry_sector) =>
+            {
+                (first_sector, cache)
             }
-        }
+            _ => {
+                // Read ahead of the entry, as chains mostly move forward through the FAT
+                let sectors = (*fat_region.end() - entry_sector + 1).min(FAT_CACHE_SECTORS as u64);
+                let mut cache = [0; FAT_CACHE_BYTES];
+                self.disk.seek(SeekFrom::Start(
+                    entry_sector * self.bpb.sector_size() as u64,
+                ))?;
+                self.disk.read_exact(&mut cache[..sectors as usize * 512])?;
+
+                let (first_sector, cache) = self.fat_cache.insert((entry_sector, cache));
+                (*first_sector, &*cache)
+            }
+        };
+        let sector = &cache.1[(entry_sector - cache.0) as usize * 512..][..512];
 
         Ok(match self.bpb.kind() {
-            FatKind::Fat16 => unsafe {
-                let arr = core::slice::from_raw_parts(
-                    (&*FAT_BLOCK_RESERVE.get()).1.as_ptr() as *const u16,
-                    256,
-                );
-                FatEntry::from_fat16(arr[entry_offset] as u32)
-            },
-            FatKind::Fat32 => unsafe {
-                let arr = core::slice::from_raw_parts(
-                    (&*FAT_BLOCK_RESERVE.get()).1.as_ptr() as *const u32,
-                    128,
-                );
-                FatEntry::from_fat32(arr[entry_offset])
-            },
-            FatKind::Fat12 => todo!("Support reading FAT12"),
+            FatKind::Fat16 => {
+                let entry = &sector[entry_offset * 2..][..2];
+                u16::from_le_bytes(entry.try_into().unwrap()) as u32
+            }
+            FatKind::Fat32 => {
+                let entry = &sector[entry_offset * 4..][..4];
+                u32::from_le_bytes(entry.try_into().unwrap())
+            }
+            FatKind::Fat12 => return Err(FsError::NotSupported),
         })
     }
 
@@ -240,7 +347,12 @@ impl<Part: ReadSeek> Fat<Part> {
         let mut total_offset = 0;
         let cluster_size_bytes = self.bpb.cluster_sectors() as u64 * self.bpb.sector_size() as u64;
 
-        loop {
+        // A chain can never be longer than the volume, so a longer one must loop
+        for _ in 0..=self.bpb.clusters() {
+            if !self.bpb.is_valid_cluster(search_cluster) {
+                return Err(FsError::Corrupt);
+            }
+
             if offset - total_offset < cluster_size_bytes {
                 return Ok((search_cluster, offset % cluster_size_bytes));
             }
@@ -251,21 +363,68 @@ impl<Part: ReadSeek> Fat<Part> {
                     total_offset += cluster_size_bytes;
                 }
                 FatEntry::EOF => return Err(FsError::EndOfFile),
-                _ => return Err(FsError::ReadError),
+                _ => return Err(FsError::Corrupt),
             }
         }
+
+        Err(FsError::Corrupt)
+    }
+
+    /// Count how many clusters from `cluster` onwards follow each other on disk, up to
+    /// `max_clusters`, returning the count and the last cluster of the run.
+    fn contiguous_run(
+        &mut self,
+        cluster: ClusterId,
+        max_clusters: u64,
+    ) -> Result<(u64, ClusterId)> {
+        let mut last = cluster;
+        let mut clusters = 1;
+
+        while clusters < max_clusters {
+            match self.read_fat(last)? {
+                FatEntry::Next(next) if next == last + 1 && self.bpb.is_valid_cluster(next) => {
+                    last = next;
+                    clusters += 1;
+                }
+                // Anything else is left for `cluster_of_offset` to follow, or report
+                _ => break,
+            }
+        }
+
+        Ok((clusters, last))
+    }
+
+    /// # Is Volume Clean
+    /// Check the 'clean shutdown' bit stored in the second FAT entry.
+    ///
+    /// This bit is cleared before a volume is first written to, and set again by
+    /// [`Fat::sync`]. If its not set, the volume was not cleanly unmounted and might
+    /// contain half written data.
+    pub fn is_volume_clean(&mut self) -> Result<bool> {
+        let clean_bit = match self.bpb.kind() {
+            FatKind::Fat16 => 1 << 15,
+            FatKind::Fat32 => 1 << 27,
+            FatKind::Fat12 => return Ok(true),
+        };
+
+        Ok(self.read_raw_fat(1)? & clean_bit != 0)
     }
 
     pub fn volume_label<'a>(&'a self) -> &'a str {
         self.bpb.volume_label()
     }
 
+    pub fn kind(&self) -> FatKind {
+        self.bpb.kind()
+    }
+
     pub fn open<'a>(&'a mut self, name: &str) -> Result<FatFile<'a, Part>> {
-        let entry_info = self.entry_of(name)?;
+        let slot = self.lookup(name)?;
 
         Ok(FatFile {
-            filesize: entry_info.file_size as usize,
-            start_cluster: entry_info.cluster_id(),
+            filesize: slot.entry.file_size as usize,
+            start_cluster: slot.entry.cluster_id(),
+            slot,
             fatfs: self,
             seek: 0,
             last_cluster: None,
@@ -273,92 +432,164 @@ impl<Part: ReadSeek> Fat<Part> {
     }
 
     pub fn entry_of(&mut self, name: &str) -> Result<DirectoryEntry> {
-        assert_eq!(
-            self.bpb.cluster_sectors(),
-            2,
-            "TODO: Expecting cluster size to be 2 sectors"
-        );
+        Ok(self.lookup(name)?.entry)
+    }
 
-        let mut path = name.split('/').filter(|str| !str.is_empty()).peekable();
-        let mut inode_cluster = self.bpb.root_cluster();
-        let mut data = [0u8; 1024];
+    /// Find the entry at `path`, following each directory in it from the root.
+    fn lookup(&mut self, path: &str) -> Result<DirSlot> {
+        let mut path = path.split('/').filter(|str| !str.is_empty()).peekable();
+        let mut dir = self.bpb.root_cluster();
 
-        'outer: loop {
+        loop {
             let Some(path_part) = path.next() else {
-                unreachable!("path_part is somehow none");
+                return Err(FsError::NotFound);
+            };
+            let slot = self.find_in_dir(dir, path_part.trim())?;
+
+            if path.peek().is_none() {
+                return Ok(slot);
+            }
+
+            // Files cannot have other files after it in the path
+            if !slot.entry.is_dir() {
+                return Err(FsError::NotFound);
+            }
+
+            dir = self.dir_cluster_of(&slot.entry)?;
+        }
+    }
+
+    /// The first cluster of the directory `entry` is for
+    fn dir_cluster_of(&self, entry: &DirectoryEntry) -> Result<ClusterId> {
+        // '..' entries pointing at the root use cluster 0
+        match entry.cluster_id() {
+            0 => Ok(self.bpb.root_cluster()),
+            cluster if self.bpb.is_valid_cluster(cluster) => Ok(cluster),
+            _ => Err(FsError::Corrupt),
+        }
+    }
+
+    /// Where the `sector`th sector of the directory starting at `dir` is on the disk, or
+    /// `None` if the directory ends before it.
+    fn dir_sector_loc(&mut self, dir: ClusterId, sector: u64) -> Result<Option<u64>> {
+        let sector_size = self.bpb.sector_size() as u64;
+
+        // FAT12 and FAT16 keep their root directory in a fixed region
+        if dir == 0 {
+            return Ok((sector < self.bpb.root_sectors() as u64)
+                .then(|| self.bpb.cluster_physical_loc(0) + sector * sector_size));
+        }
+
+        match self.cluster_of_offset(dir, sector * sector_size) {
+            Ok((cluster, offset)) => Ok(Some(self.bpb.cluster_physical_loc(cluster) + offset)),
+            Err(FsError::EndOfFile) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Find the entry named `name` in the directory starting at `dir`, by its long file
+    /// name or its 8.3 name.
+    fn find_in_dir(&mut self, dir: ClusterId, name: &str) -> Result<DirSlot> {
+        let mut sector_data = [0u8; 512];
+
+        // Max string size for FAT is 20 LFN entries of 13-chars
+        let mut filename_str = [0u8; LFN_MAX_CHARS];
+        let mut filename_len = 0;
+        let mut lfn_start = None;
+
+        for sector in 0.. {
+            let Some(sector_loc) = self.dir_sector_loc(dir, sector)? else {
+                break;
             };
+            self.disk.seek(SeekFrom::Start(sector_loc))?;
+            self.disk.read_exact(&mut sector_data)?;
 
-            // Max string size for FAT is 256-chars
-            let mut filename_str = [0u8; 256];
-            let mut filename_len = 0;
+            for (entry_index, raw) in sector_data.chunks(DIR_ENTRY_SIZE).enumerate() {
+                let index = sector * DIR_ENTRIES_PER_SECTOR + entry_index as u64;
 
-            self.disk.seek(SeekFrom::Start(
-                self.bpb.cluster_physical_loc(inode_cluster),
-            ))?;
-            self.disk.read(&mut data)?;
+                // No entries are used after the first empty one
+                if raw[0] == 0 {
+                    return Err(FsError::NotFound);
+                }
 
-            for inode in data
-                .chunks(size_of::<DirectoryEntry>())
-                .map(|slice| slice.try_into())
-                .filter_map(|entry: Result<Inode>| entry.ok())
-            {
-                let filename = core::str::from_utf8(&filename_str[..filename_len])
-                    .unwrap_or("")
-                    .trim();
+                let inode = match Inode::try_from(raw) {
+                    Ok(inode) => inode,
+                    // A deleted entry, which also orphans the LFN entries before it
+                    Err(_) => {
+                        filename_len = 0;
+                        lfn_start = None;
+                        continue;
+                    }
+                };
 
-                match inode {
+                let entry = match inode {
                     Inode::LongFileName(lfn) => {
-                        let ordering_number = (lfn.ordering - 1) & (u8::MAX ^ 0x40);
-                        let offset = (ordering_number * 13) as usize;
+                        // The last LFN entry is marked with 0x40, and it comes first
+                        if lfn.ordering & 0x40 != 0 {
+                            filename_str = [0u8; LFN_MAX_CHARS];
+                            filename_len = 0;
+                            lfn_start = Some(index);
+                        }
 
-                        filename_str[offset..(offset + 13)]
+                        let ordering_number = (lfn.ordering & !0x40) as usize;
+                        if !(1..=LFN_MAX_ENTRIES).contains(&ordering_number) {
+                            return Err(FsError::Corrupt);
+                        }
+                        let offset = (ordering_number - 1) * 13;
+
+                        let written = filename_str[offset..(offset + 13)]
                             .iter_mut()
                             .zip(
                                 inode
                                     .name_iter()
                                     .filter(|lfn_c| lfn_c.is_ascii() && *lfn_c != '\0'),
                             )
-                            .for_each(|(filename_c, inode_c)| {
-                                *filename_c = inode_c as u8;
-                                filename_len += 1;
-                            });
-                    }
-                    Inode::Dir(entry) => {
-                        if path_part.trim().eq_ignore_ascii_case(filename) {
-                            // more todo
-                            if path.peek().is_some() {
-                                inode_cluster = entry.cluster_id();
-                                continue 'outer;
-                            }
-
-                            return Ok(entry);
-                        }
+                            .map(|(filename_c, inode_c)| *filename_c = inode_c as u8)
+                            .count();
 
-                        filename_str = [0u8; 256];
-                        filename_len = 0;
+                        filename_len = filename_len.max(offset + written);
                         continue;
                     }
-                    Inode::File(file) => {
-                        // Files cannot have other files after it in the path:
-                        // So, we must not be the one.
-                        if path.peek().is_some() {
-                            filename_str = [0u8; 256];
-                            filename_len = 0;
-                            continue;
-                        }
+                    Inode::Dir(entry) | Inode::File(entry) => entry,
+                };
 
-                        if path_part.trim().eq_ignore_ascii_case(filename) {
-                            return Ok(file);
-                        }
+                let long_name = core::str::from_utf8(&filename_str[..filename_len])
+                    .unwrap_or("")
+                    .trim();
+                let (short_name, short_len) = entry.short_name();
+                let short_name = core::str::from_utf8(&short_name[..short_len]).unwrap_or("");
 
-                        filename_str = [0u8; 256];
-                        filename_len = 0;
-                    }
+                if !entry.is_volume_label()
+                    && (name.eq_ignore_ascii_case(long_name)
+                        || name.eq_ignore_ascii_case(short_name))
+                {
+                    return Ok(DirSlot {
+                        dir,
+                        first_index: lfn_start.unwrap_or(index),
+                        index,
+                        entry,
+                    });
                 }
-            }
 
-            return Err(FsError::NotFound);
+                filename_len = 0;
+                lfn_start = None;
+            }
         }
+
+        Err(FsError::NotFound)
+    }
+}
+
+impl<Part: ReadSeek + Write> Fat<Part> {
+    /// # Format
+    /// Write a new empty `kind` volume to `disk`, and mount it.
+    ///
+    /// The volume gets its BPB, both FATs, an empty root directory, and for FAT32 an
+    /// FSInfo sector and a backup of the boot sector. Fails with `InvalidInput` if the
+    /// disk is too small or too large for `kind` with the clusters `options` asks for.
+    pub fn format(mut disk: Part, kind: FatKind, options: FormatOptions) -> Result<Self> {
+        format::format(&mut disk, kind, &options)?;
+        Self::new(disk)
     }
 }
 
@@ -376,8 +607,519 @@ impl<Part: ReadSeek> Debug for Fat<Part> {
 
 #[cfg(test)]
 mod test {
+    use super::*;
+    use crate::fixtures::{self, Fixture, FAT12, FAT16, FAT32};
+    use crate::std_io::StdReadSeek;
+    use std::{io::Cursor, vec, vec::Vec};
+
     #[test]
     fn test() {
         assert!(true, "True Should Be True!");
     }
+
+    fn read_file<Part: ReadSeek>(fat: &mut Fat<Part>, path: &str) -> Vec<u8> {
+        let mut file = fat.open(path).unwrap();
+        let mut contents = vec![0; file.filesize()];
+        file.read(&mut contents).unwrap();
+
+        contents
+    }
+
+    fn check_volume(fixture: &'static Fixture) {
+        let mut fat = Fat::new(fixture.disk()).unwrap();
+
+        assert_eq!(fat.volume_label().trim(), fixture.label);
+        assert!(fat.is_volume_clean().unwrap());
+    }
+
+    fn check_directories(fixture: &'static Fixture) {
+        let mut fat = Fat::new(fixture.disk()).unwrap();
+
+        assert_eq!(
+            read_file(&mut fat, fixtures::HELLO_TXT),
+            fixture.hello_contents()
+        );
+        assert_eq!(
+            read_file(&mut fat, fixtures::README_MD),
+            fixtures::README_CONTENTS
+        );
+        assert!(matches!(
+            fat.entry_of("missing.txt"),
+            Err(FsError::NotFound)
+        ));
+        assert!(matches!(
+            fat.entry_of("docs/missing.txt"),
+            Err(FsError::NotFound)
+        ));
+    }
+
+    fn check_long_file_names(fixture: &'static Fixture) {
+        let mut fat = Fat::new(fixture.disk()).unwrap();
+
+        assert_eq!(
+            read_file(&mut fat, fixtures::LONG_NAME_TXT),
+            fixtures::LONG_NAME_CONTENTS
+        );
+        assert_eq!(
+            read_file(&mut fat, &fixtures::LONG_NAME_TXT.to_ascii_uppercase()),
+            fixtures::LONG_NAME_CONTENTS,
+            "File names should not be case sensitive"
+        );
+    }
+
+    fn check_cluster_chains(fixture: &'static Fixture) {
+        let mut fat = Fat::new(fixture.disk()).unwrap();
+        let big = read_file(&mut fat, fixtures::BIG_BIN);
+
+        assert_eq!(big.len(), fixtures::BIG_BIN_LEN);
+        for (index, &byte) in big.iter().enumerate() {
+            assert_eq!(byte, fixtures::big_bin_byte(index), "Byte {index} is wrong");
+        }
+
+        // Seeking into the middle of the chain must land in the right cluster
+        let mut file = fat.open(fixtures::BIG_BIN).unwrap();
+        let mut middle = [0; 100];
+        file.seek(SeekFrom::Start(3000)).unwrap();
+        file.read(&mut middle).unwrap();
+
+        assert_eq!(
+            middle,
+            core::array::from_fn(|i| fixtures::big_bin_byte(3000 + i))
+        );
+    }
+
+    // FIXME: FAT12 cannot be read past a file's first cluster yet, so its chains and
+    //        clean bit are not checked.
+    #[test]
+    fn test_fat12_volume() {
+        let fat = Fat::new(FAT12.disk()).unwrap();
+        assert!(matches!(fat.bpb.kind(), FatKind::Fat12));
+        assert_eq!(fat.volume_label().trim(), FAT12.label);
+    }
+
+    #[test]
+    fn test_fat12_directories() {
+        check_directories(&FAT12);
+    }
+
+    #[test]
+    fn test_fat12_long_file_names() {
+        check_long_file_names(&FAT12);
+    }
+
+    #[test]
+    fn test_fat16_volume() {
+        assert!(matches!(
+            Fat::new(FAT16.disk()).unwrap().bpb.kind(),
+            FatKind::Fat16
+        ));
+        check_volume(&FAT16);
+    }
+
+    #[test]
+    fn test_fat16_directories() {
+        check_directories(&FAT16);
+    }
+
+    #[test]
+    fn test_fat16_long_file_names() {
+        check_long_file_names(&FAT16);
+    }
+
+    #[test]
+    fn test_fat16_cluster_chains() {
+        check_cluster_chains(&FAT16);
+    }
+
+    #[test]
+    fn test_fat32_volume() {
+        assert!(matches!(
+            Fat::new(FAT32.disk()).unwrap().bpb.kind(),
+            FatKind::Fat32
+        ));
+        check_volume(&FAT32);
+    }
+
+    #[test]
+    fn test_fat32_directories() {
+        check_directories(&FAT32);
+    }
+
+    #[test]
+    fn test_fat32_long_file_names() {
+        check_long_file_names(&FAT32);
+    }
+
+    #[test]
+    fn test_fat32_cluster_chains() {
+        check_cluster_chains(&FAT32);
+    }
+
+    /// Counts the reads made of the disk it wraps
+    struct CountingDisk<Part: ReadSeek> {
+        inner: Part,
+        reads: usize,
+    }
+
+    impl<Part: ReadSeek> Read for CountingDisk<Part> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            self.reads += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    impl<Part: ReadSeek> Seek for CountingDisk<Part> {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+            self.inner.seek(pos)
+        }
+
+        fn stream_position(&mut self) -> u64 {
+            self.inner.stream_position()
+        }
+    }
+
+    #[test]
+    fn test_contiguous_clusters_are_read_together() {
+        let disk = CountingDisk {
+            inner: FAT16.disk(),
+            reads: 0,
+        };
+        let mut fat = Fat::new(disk).unwrap();
+        let mut file = fat.open(fixtures::BIG_BIN).unwrap();
+        let mut contents = vec![0; file.filesize()];
+
+        file.fatfs.disk.reads = 0;
+        file.read(&mut contents).unwrap();
+
+        // `big.bin` is clusters 4, 8, 9, 10, and 11. So one read of the FAT, one read of
+        // cluster 4, and one read of clusters 8 through 11.
+        assert_eq!(file.fatfs.disk.reads, 3);
+        for (index, &byte) in contents.iter().enumerate() {
+            assert_eq!(byte, fixtures::big_bin_byte(index), "Byte {index} is wrong");
+        }
+    }
+
+    #[test]
+    fn test_vectored_reads_cross_clusters() {
+        let mut fat = Fat::new(FAT16.disk()).unwrap();
+        let mut file = fat.open(fixtures::BIG_BIN).unwrap();
+
+        let mut head = [0; 100];
+        let mut middle = vec![0; file.filesize() - 150];
+        let mut tail = [0; 50];
+        let mut bufs = [&mut head[..], &mut middle[..], &mut tail[..]];
+
+        let read = file.read_vectored(&mut bufs).unwrap();
+        assert_eq!(read, file.filesize());
+
+        let contents: Vec<u8> = head.iter().chain(&middle).chain(&tail).copied().collect();
+        for (index, &byte) in contents.iter().enumerate() {
+            assert_eq!(byte, fixtures::big_bin_byte(index), "Byte {index} is wrong");
+        }
+    }
+
+    /// A disk of `sectors` that starts out full of junk, so formatting must clear it
+    fn junk_disk(sectors: usize) -> StdReadSeek<Cursor<Vec<u8>>> {
+        StdReadSeek::new(Cursor::new(vec![0xA5; sectors * 512]))
+    }
+
+    fn check_format(kind: FatKind, sectors: usize) {
+        let options = FormatOptions::new()
+            .with_cluster_sectors(2)
+            .with_label("Aloe Vera")
+            .with_volume_id(0x1234_5678);
+        let mut fat = Fat::format(junk_disk(sectors), kind, options).unwrap();
+
+        assert_eq!(fat.bpb.kind(), kind);
+        assert_eq!(fat.bpb.total_sectors(), sectors);
+        assert_eq!(fat.volume_label(), "ALOE VERA  ");
+        assert!(fat.is_volume_clean().unwrap());
+        assert!(matches!(
+            fat.entry_of("missing.txt"),
+            Err(FsError::NotFound)
+        ));
+
+        // Another implementation must be able to use the volume too
+        let image = fat.disk.into_inner().into_inner();
+        let host =
+            host_fatfs::FileSystem::new(Cursor::new(image), host_fatfs::FsOptions::new()).unwrap();
+        let expected = match kind {
+            FatKind::Fat12 => host_fatfs::FatType::Fat12,
+            FatKind::Fat16 => host_fatfs::FatType::Fat16,
+            FatKind::Fat32 => host_fatfs::FatType::Fat32,
+        };
+
+        assert_eq!(host.fat_type(), expected);
+        assert_eq!(host.volume_id(), 0x1234_5678);
+        assert_eq!(host.root_dir().iter().count(), 0);
+
+        let stats = host.stats().unwrap();
+        assert_eq!(
+            stats.free_clusters(),
+            stats.total_clusters() - (kind == FatKind::Fat32) as u32
+        );
+
+        let mut file = host.root_dir().create_file("hello.txt").unwrap();
+        std::io::Write::write_all(&mut file, b"Hello, Aloe!").unwrap();
+    }
+
+    #[test]
+    fn test_format_fat12() {
+        check_format(FatKind::Fat12, 4 * 2048);
+    }
+
+    #[test]
+    fn test_format_fat16() {
+        check_format(FatKind::Fat16, 16 * 2048);
+    }
+
+    #[test]
+    fn test_format_fat32() {
+        check_format(FatKind::Fat32, 68 * 2048);
+    }
+
+    #[test]
+    fn test_format_picks_cluster_size() {
+        let fat = Fat::format(junk_disk(96 * 2048), FatKind::Fat16, FormatOptions::new()).unwrap();
+
+        assert_eq!(fat.bpb.kind(), FatKind::Fat16);
+        assert_eq!(fat.bpb.cluster_sectors(), 4);
+    }
+
+    #[test]
+    fn test_format_wrong_size() {
+        // Too few clusters for FAT32, and too many for FAT12 with tiny clusters
+        assert!(matches!(
+            Fat::format(junk_disk(2048), FatKind::Fat32, FormatOptions::new()),
+            Err(FsError::InvalidInput)
+        ));
+        assert!(matches!(
+            Fat::format(
+                junk_disk(16 * 2048),
+                FatKind::Fat12,
+                FormatOptions::new().with_cluster_sectors(1)
+            ),
+            Err(FsError::InvalidInput)
+        ));
+    }
+
+    /// Byte offsets into the FAT16 fixture
+    const FAT16_RESERVED_SECTORS: usize = 14;
+    const FAT16_FAT: usize = 512;
+    const FAT16_ROOT: usize = 41 * 512;
+
+    /// The FAT16 fixture as a disk, with `corrupt` applied to it
+    fn corrupt_fat16(corrupt: impl FnOnce(&mut [u8])) -> StdReadSeek<Cursor<Vec<u8>>> {
+        let mut image = FAT16.image();
+        corrupt(&mut image);
+
+        StdReadSeek::new(Cursor::new(image))
+    }
+
+    #[test]
+    fn test_corrupt_bpb() {
+        let disk = corrupt_fat16(|image| {
+            image[FAT16_RESERVED_SECTORS..FAT16_RESERVED_SECTORS + 2]
+                .copy_from_slice(&u16::MAX.to_le_bytes())
+        });
+
+        assert!(matches!(Fat::new(disk), Err(FsError::Corrupt)));
+    }
+
+    #[test]
+    fn test_corrupt_cluster_chain() {
+        // `big.bin`'s second cluster is 8, make it point to a free cluster and then past
+        // the end of the volume.
+        for next in [0u16, 0x7000] {
+            let disk = corrupt_fat16(|image| {
+                image[FAT16_FAT + 8 * 2..FAT16_FAT + 8 * 2 + 2].copy_from_slice(&next.to_le_bytes())
+            });
+            let mut fat = Fat::new(disk).unwrap();
+            let mut file = fat.open(fixtures::BIG_BIN).unwrap();
+            let mut contents = vec![0; file.filesize()];
+
+            assert!(matches!(file.read(&mut contents), Err(FsError::Corrupt)));
+        }
+    }
+
+    #[test]
+    fn test_corrupt_long_file_name() {
+        // The root starts with the volume label, followed by the LFN of `hello.txt`
+        let disk = corrupt_fat16(|image| image[FAT16_ROOT + 32] = 0x7F);
+        let mut fat = Fat::new(disk).unwrap();
+
+        assert!(matches!(
+            fat.entry_of(fixtures::HELLO_TXT),
+            Err(FsError::Corrupt)
+        ));
+    }
+
+    /// The volume on `image`, as another implementation sees it
+    fn host_volume(image: Vec<u8>) -> host_fatfs::FileSystem<Cursor<Vec<u8>>> {
+        host_fatfs::FileSystem::new(Cursor::new(image), host_fatfs::FsOptions::new()).unwrap()
+    }
+
+    fn host_read(host: &host_fatfs::FileSystem<Cursor<Vec<u8>>>, path: &str) -> Vec<u8> {
+        let mut contents = Vec::new();
+        let mut file = host.root_dir().open_file(path).unwrap();
+        std::io::Read::read_to_end(&mut file, &mut contents).unwrap();
+
+        contents
+    }
+
+    /// A copy of `fixture`'s image that the host implementation can mount
+    fn host_ready_image(fixture: &'static Fixture) -> Vec<u8> {
+        let mut image = fixture.image();
+
+        // The FAT32 fixture was made without an FSInfo sector, which the host requires
+        if fixture.bits == 32 {
+            let fs_info = &mut image[512..1024];
+            fs_info[0..4].copy_from_slice(&0x4161_5252u32.to_le_bytes());
+            fs_info[484..488].copy_from_slice(&0x6141_7272u32.to_le_bytes());
+            fs_info[488..496].fill(0xFF);
+            fs_info[508..512].copy_from_slice(&0xAA55_0000u32.to_le_bytes());
+        }
+
+        image
+    }
+
+    fn check_writes(fixture: &'static Fixture) {
+        let image = host_ready_image(fixture);
+        let free_before = host_volume(image.clone()).stats().unwrap().free_clusters();
+        let mut fat = Fat::new(StdReadSeek::new(Cursor::new(image))).unwrap();
+        let notes: Vec<u8> = (0..3000).map(|i| (i % 251) as u8).collect();
+
+        let mut file = fat.create_file("notes.txt").unwrap();
+        file.write_all(&notes[..1000]).unwrap();
+        file.write_all(&notes[1000..]).unwrap();
+        assert_eq!(read_file(&mut fat, "notes.txt"), notes);
+
+        let mut file = fat.create_file("parts.txt").unwrap();
+        file.write_vectored_all(&mut [&notes[..700], &notes[700..701], &notes[701..]])
+            .unwrap();
+        assert_eq!(read_file(&mut fat, "parts.txt"), notes);
+        assert!(matches!(
+            fat.create_file("NOTES.TXT"),
+            Err(FsError::AlreadyExists)
+        ));
+
+        // Writing past the end must fill the hole with zeros
+        let mut file = fat.open("notes.txt").unwrap();
+        file.seek(SeekFrom::Start(4000)).unwrap();
+        file.write_all(b"end").unwrap();
+
+        fat.create_dir("var").unwrap();
+        fat.create_dir("var/log").unwrap();
+        let mut file = fat.create_file("var/log/kernel.log").unwrap();
+        file.write_all(b"first\n").unwrap();
+        let mut file = fat.create_file("var/log/kernel.1").unwrap();
+        file.write_all(b"older\n").unwrap();
+
+        fat.rename("var/log/kernel.log", "var/log/kernel.1")
+            .unwrap();
+        assert!(matches!(
+            fat.entry_of("var/log/kernel.log"),
+            Err(FsError::NotFound)
+        ));
+        assert_eq!(read_file(&mut fat, "var/log/kernel.1"), b"first\n");
+
+        fat.open(fixtures::BIG_BIN).unwrap().set_len(1500).unwrap();
+        fat.remove(fixtures::HELLO_TXT).unwrap();
+        assert!(matches!(fat.remove("var"), Err(FsError::InvalidInput)));
+
+        assert!(!fat.is_volume_clean().unwrap());
+        fat.sync().unwrap();
+        assert!(fat.is_volume_clean().unwrap());
+
+        let host = host_volume(fat.disk.into_inner().into_inner());
+        let mut expected_notes = notes.clone();
+        expected_notes.resize(4000, 0);
+        expected_notes.extend_from_slice(b"end");
+
+        assert_eq!(host_read(&host, "notes.txt"), expected_notes);
+        assert_eq!(host_read(&host, "parts.txt"), notes);
+        assert_eq!(host_read(&host, "var/log/kernel.1"), b"first\n");
+        assert_eq!(
+            host_read(&host, fixtures::BIG_BIN),
+            (0..1500).map(fixtures::big_bin_byte).collect::<Vec<_>>()
+        );
+        assert!(host.root_dir().open_file(fixtures::HELLO_TXT).is_err());
+
+        let names: Vec<_> = host
+            .root_dir()
+            .open_dir("var/log")
+            .unwrap()
+            .iter()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, [".", "..", "kernel.1"]);
+
+        // notes.txt, parts.txt, var, var/log, and both logs took 11 clusters, while
+        // replacing the old kernel.1, shrinking big.bin, and removing hello.txt gave back 5
+        assert_eq!(host.stats().unwrap().free_clusters(), free_before - 6);
+    }
+
+    #[test]
+    fn test_fat16_writes() {
+        check_writes(&FAT16);
+    }
+
+    #[test]
+    fn test_fat32_writes() {
+        check_writes(&FAT32);
+    }
+
+    #[test]
+    fn test_fat12_writes_not_supported() {
+        let mut fat = Fat::new(FAT12.writable_disk()).unwrap();
+
+        assert!(matches!(
+            fat.create_file("new.txt"),
+            Err(FsError::NotSupported)
+        ));
+    }
+
+    #[test]
+    fn test_directories_grow() {
+        let mut fat = Fat::new(FAT16.writable_disk()).unwrap();
+        fat.create_dir("many").unwrap();
+
+        // A cluster only holds 32 entries
+        for index in 0..40 {
+            let mut file = fat.create_file(&std::format!("many/{index}.txt")).unwrap();
+            file.write_all(&[index as u8]).unwrap();
+        }
+
+        for index in 0..40 {
+            assert_eq!(
+                read_file(&mut fat, &std::format!("many/{index}.txt")),
+                [index as u8]
+            );
+        }
+
+        fat.sync().unwrap();
+        let host = host_volume(fat.disk.into_inner().into_inner());
+        assert_eq!(host.root_dir().open_dir("many").unwrap().iter().count(), 42);
+    }
+
+    #[test]
+    fn test_new_names_must_be_short() {
+        let mut fat = Fat::new(FAT16.writable_disk()).unwrap();
+
+        for name in [
+            "a long name.txt",
+            "toolongname.txt",
+            "name.long",
+            "MiXed.txt",
+        ] {
+            assert!(
+                matches!(fat.create_file(name), Err(FsError::InvalidInput)),
+                "'{name}' should not fit in 8.3"
+            );
+        }
+
+        // Names that are all upper or lower case fit, and are found either way
+        fat.create_file("README.md").unwrap();
+        assert!(fat.entry_of("readme.MD").is_ok());
+    }
 }
@@ -0,0 +1,709 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Changing a volume: allocating clusters, growing and shrinking files, and adding,
+//! renaming, and removing directory entries.
+//!
+//! Writes are ordered so a crash can only leak clusters, never leave an entry pointing at
+//! clusters that are free or hold another file's data. New clusters are marked used
+//! before anything links to them, file data reaches the disk before the entry's size
+//! grows to cover it, and entries stop using clusters before they are freed.
+
+use super::{
+    format::{FS_INFO_FREE_CLUSTERS, FS_INFO_LEAD_SIGNATURE},
+    inode::{short_name_of, DirectoryEntry, ATTR_ARCHIVE, ATTR_DIRECTORY},
+    ClusterId, DirSlot, Fat, FatEntry, FatFile, FatKind, ReadSeek, DIR_ENTRIES_PER_SECTOR,
+    DIR_ENTRY_SIZE,
+};
+use crate::{
+    error::{FsError, Result},
+    io::{take_io_vecs, SeekFrom, Write, MAX_IO_VECS},
+};
+
+/// The first byte of a deleted directory entry
+const DELETED_ENTRY: u8 = 0xE5;
+
+/// Holes left by writing past the end of a file are filled with this many zeros at a time
+const ZEROS: [u8; 512] = [0; 512];
+
+impl<Part: ReadSeek + Write> Fat<Part> {
+    /// Set the FAT entry of `id` to `raw`, in every copy of the FAT.
+    fn write_raw_fat(&mut self, id: ClusterId, raw: u32) -> Result<()> {
+        let entry_bytes = match self.bpb.kind() {
+            FatKind::Fat12 => return Err(FsError::NotSupported),
+            FatKind::Fat16 => 2,
+            FatKind::Fat32 => 4,
+        };
+        let sector_size = self.bpb.sector_size() as u64;
+        let fat_start = *self.bpb.fat_range().start();
+        let entry_offset = id as u64 * entry_bytes as u64;
+
+        if entry_offset / sector_size >= self.bpb.fat_sectors() as u64 {
+            return Err(FsError::InvalidInput);
+        }
+
+        let bytes = raw.to_le_bytes();
+        for fat in 0..self.bpb.fat_count() as u64 {
+            let fat_loc = (fat_start + fat * self.bpb.fat_sectors() as u64) * sector_size;
+            self.disk.seek(SeekFrom::Start(fat_loc + entry_offset))?;
+            self.disk.write_all(&bytes[..entry_bytes])?;
+        }
+
+        // Keep the cache in step with the first FAT, which is the one it holds
+        if let Some((first_sector, ref mut cache)) = self.fat_cache {
+            let cache_offset = (fat_start + entry_offset / sector_size)
+                .checked_sub(first_sector)
+                .map(|sector| sector * sector_size + entry_offset % sector_size)
+                .filter(|&offset| offset < cache.len() as u64);
+
+            if let Some(offset) = cache_offset {
+                cache[offset as usize..][..entry_bytes].copy_from_slice(&bytes[..entry_bytes]);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_fat(&mut self, id: ClusterId, entry: FatEntry) -> Result<()> {
+        let raw = match (self.bpb.kind(), entry) {
+            (_, FatEntry::Free) => FatEntry::FREE_CLUSTER,
+            (_, FatEntry::Next(next)) => next,
+            (FatKind::Fat16, FatEntry::EOF) => FatEntry::FAT16_EOF,
+            (FatKind::Fat32, FatEntry::EOF) => FatEntry::FAT32_EOF,
+            _ => return Err(FsError::InvalidInput),
+        };
+
+        // The top 4 bits of FAT32 entries are reserved, and must be kept as they are
+        let raw = match self.bpb.kind() {
+            FatKind::Fat32 => (self.read_raw_fat(id)? & !FatEntry::FAT32_MASK) | raw,
+            _ => raw,
+        };
+
+        self.write_raw_fat(id, raw)
+    }
+
+    /// Set or clear the 'clean shutdown' bit, see [`Fat::is_volume_clean`].
+    fn set_volume_clean(&mut self, clean: bool) -> Result<()> {
+        let clean_bit = match self.bpb.kind() {
+            FatKind::Fat16 => 1 << 15,
+            FatKind::Fat32 => 1 << 27,
+            FatKind::Fat12 => return Ok(()),
+        };
+
+        let raw = self.read_raw_fat(1)?;
+        self.write_raw_fat(
+            1,
+            if clean {
+                raw | clean_bit
+            } else {
+                raw & !clean_bit
+            },
+        )
+    }
+
+    /// Mark the volume as being changed, before the first write since it was mounted or
+    /// last synced.
+    fn mark_dirty(&mut self) -> Result<()> {
+        if self.dirty {
+            return Ok(());
+        }
+
+        // FIXME: FAT12 entries are packed 12 bits at a time, which we can't write yet
+        if self.bpb.kind() == FatKind::Fat12 {
+            return Err(FsError::NotSupported);
+        }
+
+        self.set_volume_clean(false)?;
+
+        // The free cluster count is not kept up to date, so it must be marked as unknown
+        if let Some(fs_info) = self.bpb.fs_info_sector() {
+            let fs_info_loc = fs_info * self.bpb.sector_size() as u64;
+            let mut lead_signature = [0; 4];
+            self.disk.seek(SeekFrom::Start(
+                fs_info_loc + FS_INFO_LEAD_SIGNATURE.0 as u64,
+            ))?;
+            self.disk.read_exact(&mut lead_signature)?;
+
+            if u32::from_le_bytes(lead_signature) == FS_INFO_LEAD_SIGNATURE.1 {
+                self.disk
+                    .seek(SeekFrom::Start(fs_info_loc + FS_INFO_FREE_CLUSTERS as u64))?;
+                self.disk.write_all(&u32::MAX.to_le_bytes())?;
+            }
+        }
+
+        self.disk.flush()?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// # Flush
+    /// Make sure everything written has reached the disk, without marking the volume as
+    /// clean.
+    ///
+    /// This is for when the caller can't be sure its writes were left complete, like
+    /// after a panic, so the next mount still sees the volume was not cleanly unmounted.
+    pub fn flush(&mut self) -> Result<()> {
+        self.disk.flush()
+    }
+
+    /// # Sync
+    /// Make sure everything written has reached the disk, and mark the volume as clean
+    /// so it is known nothing was left half written.
+    pub fn sync(&mut self) -> Result<()> {
+        self.disk.flush()?;
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.set_volume_clean(true)?;
+        self.disk.flush()?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Find a free cluster and mark it as the end of a chain.
+    ///
+    /// Nothing links to the cluster yet, the caller must do that once it is ready.
+    fn allocate_cluster(&mut self) -> Result<ClusterId> {
+        let clusters = self.bpb.clusters() as u32;
+        let first = FatEntry::ALLOCATED_CLUSTER_BEGIN;
+
+        for offset in 0..clusters {
+            let cluster = first + (self.next_free - first + offset) % clusters;
+
+            if let FatEntry::Free = self.read_fat(cluster)? {
+                self.write_fat(cluster, FatEntry::EOF)?;
+                self.next_free = first + (cluster - first + 1) % clusters;
+                return Ok(cluster);
+            }
+        }
+
+        Err(FsError::NoSpace)
+    }
+
+    /// Free every cluster of the chain starting at `cluster`.
+    fn free_chain(&mut self, mut cluster: ClusterId) -> Result<()> {
+        // A chain can never be longer than the volume, so a longer one must loop
+        for _ in 0..=self.bpb.clusters() {
+            if !self.bpb.is_valid_cluster(cluster) {
+                return Err(FsError::Corrupt);
+            }
+
+            let next = self.read_fat(cluster)?;
+            self.write_fat(cluster, FatEntry::Free)?;
+
+            match next {
+                FatEntry::Next(next) => cluster = next,
+                FatEntry::EOF => return Ok(()),
+                _ => return Err(FsError::Corrupt),
+            }
+        }
+
+        Err(FsError::Corrupt)
+    }
+
+    /// The last cluster of the chain starting at `cluster`, and how many clusters it has.
+    fn chain_end(&mut self, mut cluster: ClusterId) -> Result<(ClusterId, u64)> {
+        for length in 1..=self.bpb.clusters() as u64 {
+            if !self.bpb.is_valid_cluster(cluster) {
+                return Err(FsError::Corrupt);
+            }
+
+            match self.read_fat(cluster)? {
+                FatEntry::Next(next) => cluster = next,
+                FatEntry::EOF => return Ok((cluster, length)),
+                _ => return Err(FsError::Corrupt),
+            }
+        }
+
+        Err(FsError::Corrupt)
+    }
+
+    fn zero_cluster(&mut self, cluster: ClusterId) -> Result<()> {
+        self.disk
+            .seek(SeekFrom::Start(self.bpb.cluster_physical_loc(cluster)))?;
+
+        for _ in 0..self.bpb.cluster_sectors() {
+            self.disk.write_all(&ZEROS[..self.bpb.sector_size()])?;
+        }
+
+        Ok(())
+    }
+
+    /// Where the `index`th entry of the directory starting at `dir` is on the disk.
+    fn entry_loc(&mut self, dir: ClusterId, index: u64) -> Result<u64> {
+        let sector_loc = self
+            .dir_sector_loc(dir, index / DIR_ENTRIES_PER_SECTOR)?
+            .ok_or(FsError::Corrupt)?;
+
+        Ok(sector_loc + (index % DIR_ENTRIES_PER_SECTOR) * DIR_ENTRY_SIZE as u64)
+    }
+
+    fn write_entry(&mut self, dir: ClusterId, index: u64, entry: &DirectoryEntry) -> Result<()> {
+        let loc = self.entry_loc(dir, index)?;
+        self.disk.seek(SeekFrom::Start(loc))?;
+        self.disk.write_all(entry.as_bytes())
+    }
+
+    /// Mark the entries `first..=last` of the directory starting at `dir` as deleted.
+    fn delete_entries(&mut self, dir: ClusterId, first: u64, last: u64) -> Result<()> {
+        for index in first..=last {
+            let loc = self.entry_loc(dir, index)?;
+            self.disk.seek(SeekFrom::Start(loc))?;
+            self.disk.write_all(&[DELETED_ENTRY])?;
+        }
+
+        Ok(())
+    }
+
+    /// Store `entry` in the first unused entry of the directory starting at `dir`,
+    /// growing the directory if every entry is used, and return its index.
+    fn add_entry(&mut self, dir: ClusterId, entry: &DirectoryEntry) -> Result<u64> {
+        let mut sector_data = [0u8; 512];
+
+        for sector in 0.. {
+            let sector_loc = match self.dir_sector_loc(dir, sector)? {
+                Some(sector_loc) => sector_loc,
+                // FAT12 and FAT16 roots have a fixed size
+                None if dir == 0 => return Err(FsError::NoSpace),
+                None => {
+                    let (last, _) = self.chain_end(dir)?;
+                    let cluster = self.allocate_cluster()?;
+                    self.zero_cluster(cluster)?;
+                    self.write_fat(last, FatEntry::Next(cluster))?;
+
+                    self.bpb.cluster_physical_loc(cluster)
+                }
+            };
+
+            self.disk.seek(SeekFrom::Start(sector_loc))?;
+            self.disk.read_exact(&mut sector_data)?;
+
+            let unused = sector_data
+                .chunks(DIR_ENTRY_SIZE)
+                .position(|raw| raw[0] == 0 || raw[0] == DELETED_ENTRY);
+
+            if let Some(entry_index) = unused {
+                let index = sector * DIR_ENTRIES_PER_SECTOR + entry_index as u64;
+                self.write_entry(dir, index, entry)?;
+                return Ok(index);
+            }
+        }
+
+        unreachable!("A directory can't have more sectors than a u64 can count")
+    }
+
+    /// The first cluster of the directory `path` is in, and the name of `path` in it.
+    fn parent_of<'b>(&mut self, path: &'b str) -> Result<(ClusterId, &'b str)> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(FsError::InvalidInput);
+        }
+
+        if parent.split('/').all(|part| part.is_empty()) {
+            return Ok((self.bpb.root_cluster(), name));
+        }
+
+        let parent = self.lookup(parent)?;
+        if !parent.entry.is_dir() {
+            return Err(FsError::NotFound);
+        }
+
+        Ok((self.dir_cluster_of(&parent.entry)?, name))
+    }
+
+    /// Make sure `name` is not already used in the directory starting at `dir`.
+    fn check_unused(&mut self, dir: ClusterId, name: &str) -> Result<()> {
+        match self.find_in_dir(dir, name) {
+            Ok(_) => Err(FsError::AlreadyExists),
+            Err(FsError::NotFound) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// # Create File
+    /// Create an empty file at `path`, and open it.
+    ///
+    /// Fails with `AlreadyExists` if something is already at `path`, and with
+    /// `InvalidInput` if its name does not fit in 8.3.
+    ///
+    /// FIXME: New files only get an 8.3 name, writing long file names is not supported.
+    pub fn create_file<'a>(&'a mut self, path: &str) -> Result<FatFile<'a, Part>> {
+        let (dir, name) = self.parent_of(path)?;
+        let (short_name, case) = short_name_of(name).ok_or(FsError::InvalidInput)?;
+        self.check_unused(dir, name)?;
+        self.mark_dirty()?;
+
+        let entry = DirectoryEntry::new(short_name, case, ATTR_ARCHIVE);
+        let index = self.add_entry(dir, &entry)?;
+        self.disk.flush()?;
+
+        Ok(FatFile {
+            filesize: 0,
+            start_cluster: 0,
+            slot: DirSlot {
+                dir,
+                first_index: index,
+                index,
+                entry,
+            },
+            fatfs: self,
+            seek: 0,
+            last_cluster: None,
+        })
+    }
+
+    /// # Create Dir
+    /// Create an empty directory at `path`.
+    ///
+    /// Like [`Fat::create_file`], its name must fit in 8.3.
+    pub fn create_dir(&mut self, path: &str) -> Result<()> {
+        let (dir, name) = self.parent_of(path)?;
+        let (short_name, case) = short_name_of(name).ok_or(FsError::InvalidInput)?;
+        self.check_unused(dir, name)?;
+        self.mark_dirty()?;
+
+        let cluster = self.allocate_cluster()?;
+        self.zero_cluster(cluster)?;
+
+        let mut dot = DirectoryEntry::new(*b".          ", 0, ATTR_DIRECTORY);
+        dot.set_cluster_id(cluster);
+        self.write_entry(cluster, 0, &dot)?;
+
+        // '..' uses cluster 0 when the parent is the root, even on FAT32
+        let mut dot_dot = DirectoryEntry::new(*b"..         ", 0, ATTR_DIRECTORY);
+        if dir != self.bpb.root_cluster() {
+            dot_dot.set_cluster_id(dir);
+        }
+        self.write_entry(cluster, 1, &dot_dot)?;
+
+        let mut entry = DirectoryEntry::new(short_name, case, ATTR_DIRECTORY);
+        entry.set_cluster_id(cluster);
+        self.disk.flush()?;
+        self.add_entry(dir, &entry)?;
+
+        self.disk.flush()
+    }
+
+    /// # Rename
+    /// Move the file or directory at `from` to `to`, replacing the file at `to` if there
+    /// is one.
+    ///
+    /// Replacing is done by rewriting `to`'s entry, so after a crash `to` names either
+    /// the old file or the new one. Directories can't replace anything, and can only be
+    /// renamed within the directory they are in.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+        let from = self.lookup(from)?;
+        let (dir, name) = self.parent_of(to)?;
+        let (short_name, case) = short_name_of(name).ok_or(FsError::InvalidInput)?;
+
+        // Moving a directory would mean fixing its '..' entry too
+        if from.entry.is_dir() && dir != from.dir {
+            return Err(FsError::NotSupported);
+        }
+
+        let replacing = match self.find_in_dir(dir, name) {
+            Ok(existing) => Some(existing),
+            Err(FsError::NotFound) => None,
+            Err(err) => return Err(err),
+        };
+        self.mark_dirty()?;
+
+        let mut entry = from.entry;
+        entry.set_short_name(short_name, case);
+
+        match replacing {
+            // Only the name changes, like `readme.md` to `README.MD`
+            Some(existing) if existing.dir == from.dir && existing.index == from.index => {
+                self.write_entry(dir, from.index, &entry)?;
+                if from.first_index < from.index {
+                    self.delete_entries(dir, from.first_index, from.index - 1)?;
+                }
+            }
+            Some(existing) if existing.entry.is_dir() || entry.is_dir() => {
+                return Err(FsError::AlreadyExists);
+            }
+            Some(existing) => {
+                self.write_entry(dir, existing.index, &entry)?;
+                if existing.first_index < existing.index {
+                    self.delete_entries(dir, existing.first_index, existing.index - 1)?;
+                }
+                self.delete_entries(from.dir, from.first_index, from.index)?;
+                self.disk.flush()?;
+
+                if existing.entry.cluster_id() != 0 {
+                    self.free_chain(existing.entry.cluster_id())?;
+                }
+            }
+            None => {
+                self.add_entry(dir, &entry)?;
+                self.disk.flush()?;
+                self.delete_entries(from.dir, from.first_index, from.index)?;
+            }
+        }
+
+        self.disk.flush()
+    }
+
+    /// # Remove
+    /// Delete the file or empty directory at `path`, freeing its clusters.
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        let slot = self.lookup(path)?;
+
+        if slot.entry.is_dir() && !self.is_dir_empty(self.dir_cluster_of(&slot.entry)?)? {
+            return Err(FsError::InvalidInput);
+        }
+
+        self.mark_dirty()?;
+        self.delete_entries(slot.dir, slot.first_index, slot.index)?;
+        self.disk.flush()?;
+
+        if slot.entry.cluster_id() != 0 {
+            self.free_chain(slot.entry.cluster_id())?;
+        }
+
+        self.disk.flush()
+    }
+
+    /// If the directory starting at `dir` has nothing in it besides '.' and '..'
+    fn is_dir_empty(&mut self, dir: ClusterId) -> Result<bool> {
+        let mut sector_data = [0u8; 512];
+
+        for sector in 0.. {
+            let Some(sector_loc) = self.dir_sector_loc(dir, sector)? else {
+                return Ok(true);
+            };
+            self.disk.seek(SeekFrom::Start(sector_loc))?;
+            self.disk.read_exact(&mut sector_data)?;
+
+            for raw in sector_data.chunks(DIR_ENTRY_SIZE) {
+                match raw[0] {
+                    0 => return Ok(true),
+                    DELETED_ENTRY | b'.' => continue,
+                    _ => return Ok(false),
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<'a, Part: ReadSeek + Write> FatFile<'a, Part> {
+    fn cluster_bytes(&self) -> u64 {
+        (self.fatfs.bpb.cluster_sectors() * self.fatfs.bpb.sector_size()) as u64
+    }
+
+    /// Store the file's first cluster and size in its directory entry.
+    fn write_entry(&mut self) -> Result<()> {
+        self.slot.entry.set_cluster_id(self.start_cluster);
+        self.slot.entry.file_size = self.filesize as u32;
+
+        self.fatfs
+            .write_entry(self.slot.dir, self.slot.index, &self.slot.entry)
+    }
+
+    /// Allocate clusters until the file's chain can hold `len` bytes.
+    fn grow_chain(&mut self, len: u64) -> Result<()> {
+        let wanted = len.div_ceil(self.cluster_bytes());
+        if wanted == 0 {
+            return Ok(());
+        }
+
+        let (mut last, mut clusters) = match self.start_cluster {
+            0 => {
+                self.start_cluster = self.fatfs.allocate_cluster()?;
+                (self.start_cluster, 1)
+            }
+            start => self.fatfs.chain_end(start)?,
+        };
+
+        while clusters < wanted {
+            let cluster = self.fatfs.allocate_cluster()?;
+            self.fatfs.write_fat(last, FatEntry::Next(cluster))?;
+
+            last = cluster;
+            clusters += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Write all of `buf` at `offset`, which must not be past the end of the file.
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
+        self.write_at_vectored(offset, &[buf])
+    }
+
+    /// Write all of `bufs` one after another at `offset`, which must not be past the end
+    /// of the file.
+    fn write_at_vectored(&mut self, offset: u64, bufs: &[&[u8]]) -> Result<()> {
+        let len: usize = bufs.iter().map(|buf| buf.len()).sum();
+        let end = offset + len as u64;
+        let cluster_bytes = self.cluster_bytes();
+        self.grow_chain(end)?;
+
+        let mut written = 0;
+        for chunk in bufs.chunks(MAX_IO_VECS) {
+            let mut pending: [&[u8]; MAX_IO_VECS] = Default::default();
+            pending[..chunk.len()].copy_from_slice(chunk);
+            let chunk_end = written + chunk.iter().map(|buf| buf.len()).sum::<usize>();
+
+            while written < chunk_end {
+                let (cluster, cluster_offset) = self
+                    .fatfs
+                    .cluster_of_offset(self.start_cluster, offset + written as u64)?;
+                let bytes_remaining = (chunk_end - written) as u64;
+
+                // Clusters that follow each other on disk are written together
+                let clusters_wanted = (cluster_offset + bytes_remaining).div_ceil(cluster_bytes);
+                let (run_clusters, _) = self.fatfs.contiguous_run(cluster, clusters_wanted)?;
+                let run_bytes =
+                    (run_clusters * cluster_bytes - cluster_offset).min(bytes_remaining);
+
+                let mut batch: [&[u8]; MAX_IO_VECS] = Default::default();
+                let taken = take_io_vecs(&mut pending, run_bytes as usize, &mut batch);
+
+                let disk_loc = self.fatfs.bpb.cluster_physical_loc(cluster) + cluster_offset;
+                self.fatfs.disk.seek(SeekFrom::Start(disk_loc))?;
+                self.fatfs.disk.write_vectored_all(&mut batch[..taken])?;
+
+                written += run_bytes as usize;
+            }
+        }
+
+        // The data must be on the disk before the entry says it is part of the file
+        if end > self.filesize as u64 || self.slot.entry.cluster_id() != self.start_cluster {
+            self.fatfs.disk.flush()?;
+            self.filesize = self.filesize.max(end as usize);
+            self.write_entry()?;
+        }
+
+        Ok(())
+    }
+
+    /// Grow the file to `len` bytes by adding zeros to its end.
+    fn fill_zeros(&mut self, len: u64) -> Result<()> {
+        while (self.filesize as u64) < len {
+            let hole = (len - self.filesize as u64).min(ZEROS.len() as u64) as usize;
+            self.write_at(self.filesize as u64, &ZEROS[..hole])?;
+        }
+
+        Ok(())
+    }
+
+    /// # Set Len
+    /// Shrink the file to `len` bytes freeing the clusters it no longer needs, or grow it
+    /// to `len` bytes by adding zeros.
+    pub fn set_len(&mut self, len: u64) -> Result<()> {
+        if len > u32::MAX as u64 {
+            return Err(FsError::NoSpace);
+        }
+
+        self.fatfs.mark_dirty()?;
+        if len >= self.filesize as u64 {
+            return self.fill_zeros(len);
+        }
+
+        let keep_clusters = len.div_ceil(self.cluster_bytes());
+        let old_start = self.start_cluster;
+        if keep_clusters == 0 {
+            self.start_cluster = 0;
+        }
+
+        // The entry is shrunk first, so it never covers clusters that were freed
+        self.filesize = len as usize;
+        self.last_cluster = None;
+        self.write_entry()?;
+        self.fatfs.disk.flush()?;
+
+        if keep_clusters == 0 {
+            if old_start != 0 {
+                self.fatfs.free_chain(old_start)?;
+            }
+        } else {
+            let (last, _) = self
+                .fatfs
+                .cluster_of_offset(old_start, (keep_clusters - 1) * self.cluster_bytes())?;
+            let next = self.fatfs.read_fat(last)?;
+            self.fatfs.write_fat(last, FatEntry::EOF)?;
+
+            if let FatEntry::Next(next) = next {
+                self.fatfs.free_chain(next)?;
+            }
+        }
+
+        self.fatfs.disk.flush()
+    }
+}
+
+impl<'a, Part: ReadSeek + Write> Write for FatFile<'a, Part> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // File sizes are stored in 32 bits
+        let end = self.seek + buf.len() as u64;
+        if end > u32::MAX as u64 {
+            return Err(FsError::NoSpace);
+        }
+
+        self.fatfs.mark_dirty()?;
+
+        // Writing past the end leaves a hole, which must read back as zeros
+        self.fill_zeros(self.seek)?;
+        self.write_at(self.seek, buf)?;
+        self.seek = end;
+
+        Ok(buf.len())
+    }
+
+    /// Each run of clusters is written with one vectored write of the disk, no matter how
+    /// many of `bufs` it covers.
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<usize> {
+        let len: usize = bufs.iter().map(|buf| buf.len()).sum();
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let end = self.seek + len as u64;
+        if end > u32::MAX as u64 {
+            return Err(FsError::NoSpace);
+        }
+
+        self.fatfs.mark_dirty()?;
+        self.fill_zeros(self.seek)?;
+        self.write_at_vectored(self.seek, bufs)?;
+        self.seek = end;
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.fatfs.disk.flush()
+    }
+}
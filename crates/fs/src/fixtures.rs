@@ -0,0 +1,116 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Small FAT images for testing, stored compressed as they are mostly empty.
+//!
+//! Every image has the same files, each with a long file name:
+//! - `hello.txt`, containing `Hello from a FAT<bits> volume!\n`
+//! - `a file with a long name.txt`, whose name takes up more than one entry
+//! - `big.bin`, 5000 bytes of `big_bin_byte` over 5 clusters, where the chain skips
+//!   clusters after its first so it must be followed through the FAT
+//! - `docs/readme.md`, in a subdirectory
+//!
+//! Clusters are two 512 byte sectors, and each image has just enough clusters to be the
+//! FAT type it is named after.
+
+use crate::std_io::StdReadSeek;
+use std::{io::Cursor, sync::OnceLock, vec, vec::Vec};
+
+pub const HELLO_TXT: &str = "hello.txt";
+pub const LONG_NAME_TXT: &str = "a file with a long name.txt";
+pub const LONG_NAME_CONTENTS: &[u8] = b"Long file names span more than one entry.\n";
+pub const BIG_BIN: &str = "big.bin";
+pub const BIG_BIN_LEN: usize = 5000;
+pub const README_MD: &str = "docs/readme.md";
+pub const README_CONTENTS: &[u8] = b"# Nested\nThis file is in a directory.\n";
+
+pub struct Fixture {
+    pub label: &'static str,
+    pub bits: usize,
+    compressed: &'static [u8],
+    len: usize,
+    image: OnceLock<Vec<u8>>,
+}
+
+pub static FAT12: Fixture = Fixture {
+    label: "FLOPPY",
+    bits: 12,
+    compressed: include_bytes!("../fixtures/fat12.img.z"),
+    len: 2880 * 512,
+    image: OnceLock::new(),
+};
+
+pub static FAT16: Fixture = Fixture {
+    label: "SMALL16",
+    bits: 16,
+    compressed: include_bytes!("../fixtures/fat16.img.z"),
+    len: 10240 * 512,
+    image: OnceLock::new(),
+};
+
+pub static FAT32: Fixture = Fixture {
+    label: "LARGE32",
+    bits: 32,
+    compressed: include_bytes!("../fixtures/fat32.img.z"),
+    len: 133120 * 512,
+    image: OnceLock::new(),
+};
+
+impl Fixture {
+    /// The image, decompressing it the first time it is used.
+    fn decompressed(&'static self) -> &'static [u8] {
+        self.image.get_or_init(|| {
+            let mut image = vec![0; self.len];
+            let len = inflate::zlib_decompress(self.compressed, &mut image).unwrap();
+            assert_eq!(len, self.len, "Fixture image is not the size it should be");
+
+            image
+        })
+    }
+
+    /// The image as a disk.
+    pub fn disk(&'static self) -> StdReadSeek<Cursor<&'static [u8]>> {
+        StdReadSeek::new(Cursor::new(self.decompressed()))
+    }
+
+    /// A copy of the image, for tests that need to corrupt it.
+    pub fn image(&'static self) -> Vec<u8> {
+        self.decompressed().to_vec()
+    }
+
+    /// A copy of the image as a disk, for tests that write to it.
+    pub fn writable_disk(&'static self) -> StdReadSeek<Cursor<Vec<u8>>> {
+        StdReadSeek::new(Cursor::new(self.image()))
+    }
+
+    pub fn hello_contents(&self) -> Vec<u8> {
+        std::format!("Hello from a FAT{} volume!\n", self.bits).into_bytes()
+    }
+}
+
+/// The byte at `index` of `big.bin`, which repeats with a period that is not a power of two
+pub fn big_bin_byte(index: usize) -> u8 {
+    ((index * 7 + index / 251) & 0xFF) as u8
+}
@@ -23,7 +23,13 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use crate::error::Result;
+use crate::error::{FsError, Result};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// The most buffers a vectored request is split into at once
+pub const MAX_IO_VECS: usize = 16;
 
 pub enum SeekFrom {
     Start(u64),
@@ -39,4 +45,580 @@ pub trait Seek {
 
 pub trait Read {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// # Read Exact
+    /// Keep reading until `buf` is full.
+    ///
+    /// If the end is reached first, `FsError::EndOfFile` is returned and the contents of
+    /// `buf` are unspecified.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(FsError::EndOfFile),
+                read => buf = &mut buf[read..],
+            }
+        }
+
+        Ok(())
+    }
+
+    /// # Read Vectored
+    /// Read into each of `bufs` in order as if they were one buffer, returning how many
+    /// bytes were read.
+    ///
+    /// The default reads each buffer in turn until one isn't filled. Readers that can fill
+    /// many buffers with one request should override this.
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        let mut total = 0;
+
+        for buf in bufs.iter_mut().filter(|buf| !buf.is_empty()) {
+            match self.read(buf) {
+                Ok(read) => {
+                    total += read;
+                    if read < buf.len() {
+                        break;
+                    }
+                }
+                Err(FsError::EndOfFile) if total != 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// # Read Vectored Exact
+    /// Keep reading until every buffer in `bufs` is full.
+    ///
+    /// The buffers in `bufs` are shrunk as they are filled. If the end is reached first,
+    /// `FsError::EndOfFile` is returned.
+    fn read_vectored_exact(&mut self, mut bufs: &mut [&mut [u8]]) -> Result<()> {
+        loop {
+            let filled = bufs.iter().take_while(|buf| buf.is_empty()).count();
+            bufs = &mut core::mem::take(&mut bufs)[filled..];
+            if bufs.is_empty() {
+                return Ok(());
+            }
+
+            match self.read_vectored(bufs)? {
+                0 => return Err(FsError::EndOfFile),
+                read => advance_io_vecs(bufs, read),
+            }
+        }
+    }
+
+    /// # Read To End
+    /// Read everything left into `buf`, returning how many bytes were appended.
+    #[cfg(feature = "alloc")]
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        const CHUNK_SIZE: usize = 512;
+        let start_len = buf.len();
+
+        loop {
+            let filled = buf.len();
+            buf.resize(filled + CHUNK_SIZE, 0);
+
+            match self.read(&mut buf[filled..]) {
+                Ok(0) | Err(FsError::EndOfFile) => {
+                    buf.truncate(filled);
+                    return Ok(filled - start_len);
+                }
+                Ok(read) => buf.truncate(filled + read),
+                Err(err) => {
+                    buf.truncate(filled);
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.is_empty() && !buf.is_empty() {
+            return Err(FsError::EndOfFile);
+        }
+
+        let len = buf.len().min(self.len());
+        let (read, rest) = self.split_at(len);
+        buf[..len].copy_from_slice(read);
+        *self = rest;
+
+        Ok(len)
+    }
+}
+
+pub trait Write {
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Make sure everything written so far has reached its destination.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Write All
+    /// Keep writing until all of `buf` was written.
+    ///
+    /// If the writer stops accepting bytes first, `FsError::EndOfFile` is returned.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(FsError::EndOfFile),
+                written => buf = &buf[written..],
+            }
+        }
+
+        Ok(())
+    }
+
+    /// # Write Vectored
+    /// Write each of `bufs` in order as if they were one buffer, returning how many bytes
+    /// were written.
+    ///
+    /// The default writes each buffer in turn until one isn't written whole. Writers that
+    /// can take many buffers with one request should override this.
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<usize> {
+        let mut total = 0;
+
+        for buf in bufs.iter().filter(|buf| !buf.is_empty()) {
+            match self.write(buf) {
+                Ok(written) => {
+                    total += written;
+                    if written < buf.len() {
+                        break;
+                    }
+                }
+                Err(FsError::EndOfFile) if total != 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// # Write Vectored All
+    /// Keep writing until all of `bufs` were written.
+    ///
+    /// The buffers in `bufs` are shrunk as they are written. If the writer stops accepting
+    /// bytes first, `FsError::EndOfFile` is returned.
+    fn write_vectored_all(&mut self, mut bufs: &mut [&[u8]]) -> Result<()> {
+        loop {
+            let written = bufs.iter().take_while(|buf| buf.is_empty()).count();
+            bufs = &mut core::mem::take(&mut bufs)[written..];
+            if bufs.is_empty() {
+                return Ok(());
+            }
+
+            match self.write_vectored(bufs)? {
+                0 => return Err(FsError::EndOfFile),
+                written => advance_io_vecs(bufs, written),
+            }
+        }
+    }
+}
+
+/// One buffer of a vectored request, which can be split in two
+pub(crate) trait IoVec: Default {
+    fn len(&self) -> usize;
+    fn split(self, at: usize) -> (Self, Self);
+}
+
+impl IoVec for &mut [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn split(self, at: usize) -> (Self, Self) {
+        self.split_at_mut(at)
+    }
+}
+
+impl IoVec for &[u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn split(self, at: usize) -> (Self, Self) {
+        self.split_at(at)
+    }
+}
+
+/// Drop the first `amount` bytes from the front of `bufs`, leaving the buffers that were
+/// used up empty.
+pub(crate) fn advance_io_vecs<V: IoVec>(bufs: &mut [V], mut amount: usize) {
+    for buf in bufs.iter_mut() {
+        if amount == 0 {
+            break;
+        }
+
+        let used = buf.len().min(amount);
+        *buf = core::mem::take(buf).split(used).1;
+        amount -= used;
+    }
+}
+
+/// Move buffers holding up to `len` bytes from the front of `bufs` into `batch`, splitting
+/// the last one if it holds more than is left. Returns how many entries of `batch` were
+/// filled.
+///
+/// The buffers that were moved are left empty in `bufs`.
+pub(crate) fn take_io_vecs<V: IoVec>(bufs: &mut [V], mut len: usize, batch: &mut [V]) -> usize {
+    let mut taken = 0;
+
+    for buf in bufs.iter_mut().filter(|buf| buf.len() != 0) {
+        if len == 0 || taken == batch.len() {
+            break;
+        }
+
+        let at = len.min(buf.len());
+        let (head, tail) = core::mem::take(buf).split(at);
+        len -= head.len();
+        *buf = tail;
+        batch[taken] = head;
+        taken += 1;
+    }
+
+    taken
+}
+
+/// # Buf Reader
+/// Reads `SIZE` bytes at a time from `inner`, so many small reads become a few large ones.
+pub struct BufReader<R: Read, const SIZE: usize = 512> {
+    inner: R,
+    buffer: [u8; SIZE],
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: Read, const SIZE: usize> BufReader<R, SIZE> {
+    pub const fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: [0; SIZE],
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// # Fill Buf
+    /// Get the buffered bytes, reading more from `inner` if none are left.
+    ///
+    /// An empty slice means `inner` has nothing left to read.
+    pub fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.pos >= self.filled {
+            self.filled = match self.inner.read(&mut self.buffer) {
+                Err(FsError::EndOfFile) => 0,
+                read => read?,
+            };
+            self.pos = 0;
+        }
+
+        Ok(&self.buffer[self.pos..self.filled])
+    }
+
+    /// Mark `amount` of the bytes from `fill_buf` as used.
+    pub fn consume(&mut self, amount: usize) {
+        self.pos = (self.pos + amount).min(self.filled);
+    }
+
+    /// # Read Until
+    /// Call `f` with each byte until `delimiter` (which is included) or the end is
+    /// reached, returning how many bytes were given to `f`.
+    pub fn read_until(&mut self, delimiter: u8, mut f: impl FnMut(u8)) -> Result<usize> {
+        let mut read = 0;
+
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                return Ok(read);
+            }
+
+            let (used, done) = match available.iter().position(|&byte| byte == delimiter) {
+                Some(index) => (index + 1, true),
+                None => (available.len(), false),
+            };
+            available[..used].iter().for_each(|&byte| f(byte));
+            self.consume(used);
+            read += used;
+
+            if done {
+                return Ok(read);
+            }
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Stop buffering, any bytes still in the buffer are lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, const SIZE: usize> Read for BufReader<R, SIZE> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // Large reads gain nothing from going through the buffer
+        if self.pos >= self.filled && buf.len() >= SIZE {
+            return self.inner.read(buf);
+        }
+
+        let available = self.fill_buf()?;
+        if available.is_empty() && !buf.is_empty() {
+            return Err(FsError::EndOfFile);
+        }
+
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+
+        Ok(len)
+    }
+}
+
+impl<R: Read + Seek, const SIZE: usize> Seek for BufReader<R, SIZE> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        // `inner` is ahead of us by however much is still buffered
+        let pos = match pos {
+            SeekFrom::Current(current) => {
+                SeekFrom::Current(current - (self.filled - self.pos) as i64)
+            }
+            pos => pos,
+        };
+
+        self.pos = 0;
+        self.filled = 0;
+        self.inner.seek(pos)
+    }
+
+    fn stream_position(&mut self) -> u64 {
+        self.inner.stream_position() - (self.filled - self.pos) as u64
+    }
+}
+
+/// # Buf Writer
+/// Collects writes into `SIZE` byte chunks before giving them to `inner`.
+///
+/// Bytes still buffered when this is dropped are lost, so call `flush` or `into_inner`
+/// once done writing.
+pub struct BufWriter<W: Write, const SIZE: usize = 512> {
+    inner: W,
+    buffer: [u8; SIZE],
+    filled: usize,
+}
+
+impl<W: Write, const SIZE: usize> BufWriter<W, SIZE> {
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buffer: [0; SIZE],
+            filled: 0,
+        }
+    }
+
+    /// Write out the buffer, without flushing `inner`.
+    fn flush_buf(&mut self) -> Result<()> {
+        let filled = core::mem::take(&mut self.filled);
+        self.inner.write_all(&self.buffer[..filled])
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Flush the buffer and give back `inner`.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush_buf()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, const SIZE: usize> Write for BufWriter<W, SIZE> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.filled + buf.len() > SIZE {
+            self.flush_buf()?;
+        }
+
+        // Large writes gain nothing from going through the buffer
+        if buf.len() >= SIZE {
+            return self.inner.write(buf);
+        }
+
+        self.buffer[self.filled..self.filled + buf.len()].copy_from_slice(buf);
+        self.filled += buf.len();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec::Vec;
+
+    /// Gives back at most `chunk` bytes per read, like a disk that reads a sector at a time
+    struct SlowReader<'a> {
+        data: &'a [u8],
+        chunk: usize,
+        reads: usize,
+    }
+
+    impl Read for SlowReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            if self.data.is_empty() {
+                return Err(FsError::EndOfFile);
+            }
+
+            self.reads += 1;
+            let len = buf.len().min(self.chunk).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+
+            Ok(len)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn test_read_exact() {
+        let data: [u8; 20] = core::array::from_fn(|i| i as u8);
+        let mut reader = SlowReader {
+            data: &data,
+            chunk: 3,
+            reads: 0,
+        };
+
+        let mut buf = [0; 16];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[..16]);
+        assert!(matches!(
+            reader.read_exact(&mut buf),
+            Err(FsError::EndOfFile)
+        ));
+    }
+
+    #[test]
+    fn test_read_slice() {
+        let data: [u8; 10] = core::array::from_fn(|i| i as u8);
+        let mut reader = &data[..];
+
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[..4]);
+        assert_eq!(reader.read(&mut [0; 16]).unwrap(), 6);
+        assert!(matches!(reader.read(&mut buf), Err(FsError::EndOfFile)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_read_to_end() {
+        let data: [u8; 1300] = core::array::from_fn(|i| i as u8);
+        let mut reader = SlowReader {
+            data: &data,
+            chunk: 700,
+            reads: 0,
+        };
+
+        let mut buf = Vec::from([0xAA]);
+        assert_eq!(reader.read_to_end(&mut buf).unwrap(), data.len());
+        assert_eq!(buf[0], 0xAA);
+        assert_eq!(buf[1..], data);
+    }
+
+    #[test]
+    fn test_read_vectored() {
+        let data: [u8; 20] = core::array::from_fn(|i| i as u8);
+        let mut reader = SlowReader {
+            data: &data,
+            chunk: 4,
+            reads: 0,
+        };
+
+        // A short read of the second buffer ends the call, leaving the third untouched
+        let (mut first, mut second, mut third) = ([0; 4], [0; 6], [0xFF; 2]);
+        let mut bufs: [&mut [u8]; 4] = [&mut first, &mut [], &mut second, &mut third];
+        assert_eq!(reader.read_vectored(&mut bufs).unwrap(), 8);
+        assert_eq!(first, data[..4]);
+        assert_eq!(second[..4], data[4..8]);
+        assert_eq!(third, [0xFF; 2]);
+
+        let (mut first, mut second) = ([0; 5], [0; 10]);
+        let mut bufs: [&mut [u8]; 2] = [&mut first, &mut second];
+        assert!(matches!(
+            reader.read_vectored_exact(&mut bufs),
+            Err(FsError::EndOfFile)
+        ));
+        assert_eq!(first, data[8..13]);
+        assert_eq!(second[..7], data[13..]);
+    }
+
+    #[test]
+    fn test_write_vectored_all() {
+        let mut writer = Vec::new();
+        let mut bufs: [&[u8]; 4] = [b"kernel", b"", b"=", b"kernel.elf"];
+
+        writer.write_vectored_all(&mut bufs).unwrap();
+        assert_eq!(writer, b"kernel=kernel.elf");
+    }
+
+    #[test]
+    fn test_take_io_vecs() {
+        let data = [1, 2, 3, 4, 5, 6];
+        let mut pending: [&[u8]; 3] = [&data[..2], &data[2..5], &data[5..]];
+        let mut batch: [&[u8]; 3] = Default::default();
+
+        assert_eq!(take_io_vecs(&mut pending, 3, &mut batch), 2);
+        assert_eq!(batch[..2], [&data[..2], &data[2..3]]);
+        assert_eq!(pending, [&[][..], &data[3..5], &data[5..]]);
+
+        assert_eq!(take_io_vecs(&mut pending, 10, &mut batch), 2);
+        assert_eq!(batch[..2], [&data[3..5], &data[5..]]);
+    }
+
+    #[test]
+    fn test_buf_reader() {
+        let data = b"bootloader32=stage32.bin\nkernel=kernel.elf\n";
+        let mut reader = BufReader::<_, 16>::new(SlowReader {
+            data,
+            chunk: 16,
+            reads: 0,
+        });
+
+        let mut line = Vec::new();
+        reader.read_until(b'\n', |byte| line.push(byte)).unwrap();
+        assert_eq!(line, b"bootloader32=stage32.bin\n");
+
+        let mut rest = [0; 18];
+        reader.read_exact(&mut rest).unwrap();
+        assert_eq!(&rest, b"kernel=kernel.elf\n");
+        assert_eq!(reader.read_until(b'\n', |_| ()).unwrap(), 0);
+        assert_eq!(reader.get_ref().reads, 3);
+    }
+
+    #[test]
+    fn test_buf_writer() {
+        let mut writer = BufWriter::<_, 8>::new(Vec::new());
+
+        writer.write_all(b"abc").unwrap();
+        writer.write_all(b"def").unwrap();
+        assert!(writer.get_ref().is_empty());
+
+        writer.write_all(b"ghi").unwrap();
+        assert_eq!(writer.get_ref(), b"abcdef");
+
+        writer.write_all(b"0123456789").unwrap();
+        assert_eq!(writer.into_inner().unwrap(), b"abcdefghi0123456789");
+    }
 }
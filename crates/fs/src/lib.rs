@@ -24,11 +24,26 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 */
 
 #![no_std]
-#![feature(sync_unsafe_cell)]
+
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 #[cfg(feature = "fatfs")]
 pub mod fatfs;
 
+pub mod block_queue;
 pub mod error;
 pub mod io;
+pub mod loopback;
+pub mod partition;
+pub mod ram_disk;
 pub mod read_block;
+
+#[cfg(any(test, feature = "std"))]
+pub mod std_io;
+
+#[cfg(test)]
+mod fixtures;
@@ -0,0 +1,114 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    error::{FsError, Result},
+    io::{Read, Seek, SeekFrom},
+    read_block::BlockDevice,
+};
+
+/// # Loopback
+/// A block device backed by a file, so disk images stored inside another filesystem can
+/// be mounted.
+///
+/// If the file does not end on a block boundary, its last block is padded with zeros.
+pub struct Loopback<File: Read + Seek, const BLOCK_SIZE: usize = 512> {
+    file: File,
+    block: [u8; BLOCK_SIZE],
+}
+
+impl<File: Read + Seek, const BLOCK_SIZE: usize> Loopback<File, BLOCK_SIZE> {
+    /// Create a disk over the image stored in `file`.
+    pub fn new(file: File) -> Self {
+        Self {
+            file,
+            block: [0; BLOCK_SIZE],
+        }
+    }
+
+    /// Stop using the file as a disk.
+    pub fn into_inner(self) -> File {
+        self.file
+    }
+}
+
+impl<File: Read + Seek, const BLOCK_SIZE: usize> BlockDevice for Loopback<File, BLOCK_SIZE> {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+
+    fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+        let start = block_offset
+            .checked_mul(BLOCK_SIZE as u64)
+            .ok_or(FsError::EndOfFile)?;
+
+        // Files refuse to seek past their end, which is where this block would start
+        self.file
+            .seek(SeekFrom::Start(start))
+            .map_err(|_| FsError::EndOfFile)?;
+
+        self.block = [0; BLOCK_SIZE];
+        let mut filled = 0;
+
+        // Files can return less than we ask for, so keep reading until the block is full
+        while filled < BLOCK_SIZE {
+            match self.file.read(&mut self.block[filled..]) {
+                Ok(0) | Err(FsError::EndOfFile) => break,
+                Ok(read) => filled += read,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if filled == 0 {
+            return Err(FsError::EndOfFile);
+        }
+
+        Ok(&self.block)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{partition::Partition, ram_disk::RamDisk};
+
+    #[test]
+    fn test_loopback_over_partition() {
+        let data: [u8; 64] = core::array::from_fn(|i| i as u8);
+        let file = Partition::new(
+            RamDisk::<16>::new(&data),
+            crate::partition::PartitionKind::Whole,
+            8,
+            30,
+        );
+        let mut loopback = Loopback::<_, 8>::new(file);
+
+        assert_eq!(loopback.read_block(0).unwrap(), &data[8..16]);
+        assert_eq!(loopback.read_block(2).unwrap(), &data[24..32]);
+        assert_eq!(
+            loopback.read_block(3).unwrap(),
+            &[32, 33, 34, 35, 36, 37, 0, 0]
+        );
+        assert!(matches!(loopback.read_block(4), Err(FsError::EndOfFile)));
+    }
+}
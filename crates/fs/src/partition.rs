@@ -0,0 +1,537 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use core::fmt::Debug;
+
+use crate::{
+    error::{FsError, Result},
+    io::{Read, Seek, SeekFrom, Write},
+    read_block::{
+        read_smooth_from_block_device, read_smooth_vectored_from_block_device,
+        write_smooth_to_block_device, write_smooth_vectored_to_block_device, BlockDevice,
+    },
+};
+
+/// MBR and GPT both address the disk in 512 byte logical sectors.
+const LOGICAL_SECTOR_SIZE: u64 = 512;
+
+/// # Partition Kind
+/// Where a partition came from, and the type the partition table gave it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionKind {
+    /// The partition covers the whole disk.
+    Whole,
+    /// An entry of a Master Boot Record.
+    Mbr { bootable: bool, kind: u8 },
+    /// An entry of a GUID Partition Table.
+    Gpt {
+        type_guid: [u8; 16],
+        unique_guid: [u8; 16],
+    },
+}
+
+/// # Partition
+/// An owned window of `length` bytes starting at `offset` on a disk.
+///
+/// Partitions own their disk, so they can be handed to a filesystem that outlives the
+/// code that found them. To open more than one partition on the same disk, pass a
+/// `&mut` reference to the disk instead.
+pub struct Partition<D: BlockDevice> {
+    disk: D,
+    kind: PartitionKind,
+    offset: u64,
+    length: u64,
+    seek: u64,
+}
+
+impl<D: BlockDevice> Partition<D> {
+    /// Create a partition of `length` bytes starting at `offset` bytes into `disk`.
+    pub fn new(disk: D, kind: PartitionKind, offset: u64, length: u64) -> Self {
+        Self {
+            disk,
+            kind,
+            offset,
+            length,
+            seek: 0,
+        }
+    }
+
+    /// Open the `index`th primary partition from the MBR of `disk`.
+    pub fn from_mbr(mut disk: D, index: usize) -> Result<Self> {
+        if index >= 4 {
+            return Err(FsError::InvalidInput);
+        }
+
+        let mut sector = [0; 512];
+        read_smooth_from_block_device(&mut disk, 0, &mut sector)?;
+
+        if sector[510..512] != [0x55, 0xAA] {
+            return Err(FsError::InvalidInput);
+        }
+
+        let entry = &sector[446 + index * 16..][..16];
+        let lba_start = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as u64;
+        let lba_count = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as u64;
+
+        if lba_start == 0 || lba_count == 0 {
+            return Err(FsError::NotFound);
+        }
+
+        Ok(Self::new(
+            disk,
+            PartitionKind::Mbr {
+                bootable: entry[0] == 0x80,
+                kind: entry[4],
+            },
+            lba_start * LOGICAL_SECTOR_SIZE,
+            lba_count * LOGICAL_SECTOR_SIZE,
+        ))
+    }
+
+    /// Open the `index`th entry of the GUID Partition Table on `disk`.
+    pub fn from_gpt(mut disk: D, index: usize) -> Result<Self> {
+        let mut header = [0; 92];
+        read_smooth_from_block_device(&mut disk, LOGICAL_SECTOR_SIZE, &mut header)?;
+
+        if &header[0..8] != b"EFI PART" {
+            return Err(FsError::InvalidInput);
+        }
+
+        let entries_lba = u64::from_le_bytes(header[72..80].try_into().unwrap());
+        let entries = u32::from_le_bytes(header[80..84].try_into().unwrap()) as usize;
+        let entry_size = u32::from_le_bytes(header[84..88].try_into().unwrap()) as u64;
+
+        if index >= entries {
+            return Err(FsError::NotFound);
+        }
+
+        if entry_size < 56 {
+            return Err(FsError::InvalidInput);
+        }
+
+        let entry_offset = entries_lba
+            .checked_mul(LOGICAL_SECTOR_SIZE)
+            .and_then(|table| table.checked_add(index as u64 * entry_size))
+            .ok_or(FsError::InvalidInput)?;
+
+        let mut entry = [0; 56];
+        read_smooth_from_block_device(&mut disk, entry_offset, &mut entry)?;
+
+        let type_guid: [u8; 16] = entry[0..16].try_into().unwrap();
+        let first_lba = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+        let last_lba = u64::from_le_bytes(entry[40..48].try_into().unwrap());
+
+        if type_guid == [0; 16] {
+            return Err(FsError::NotFound);
+        }
+
+        if last_lba < first_lba {
+            return Err(FsError::InvalidInput);
+        }
+
+        // The partition has to be addressable in bytes, all the way to its end
+        let offset = first_lba.checked_mul(LOGICAL_SECTOR_SIZE);
+        let end = last_lba
+            .checked_add(1)
+            .and_then(|end| end.checked_mul(LOGICAL_SECTOR_SIZE));
+        let (Some(offset), Some(end)) = (offset, end) else {
+            return Err(FsError::InvalidInput);
+        };
+
+        Ok(Self::new(
+            disk,
+            PartitionKind::Gpt {
+                type_guid,
+                unique_guid: entry[16..32].try_into().unwrap(),
+            },
+            offset,
+            end - offset,
+        ))
+    }
+
+    pub fn kind(&self) -> PartitionKind {
+        self.kind
+    }
+
+    /// The offset in bytes of this partition from the start of the disk.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The length in bytes of this partition.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Give back the disk this partition was opened on.
+    pub fn into_inner(self) -> D {
+        self.disk
+    }
+
+    /// How many of the leading buffers with `lens` fit before the end of the partition.
+    fn fitting_bufs(&self, lens: impl Iterator<Item = usize>) -> usize {
+        let mut remaining = self.length.saturating_sub(self.seek);
+
+        lens.take_while(|&len| {
+            let fits = len as u64 <= remaining;
+            remaining = remaining.saturating_sub(len as u64);
+            fits
+        })
+        .count()
+    }
+}
+
+impl<D: BlockDevice> Read for Partition<D> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let remaining = self.length.saturating_sub(self.seek);
+        let len = (buf.len() as u64).min(remaining) as usize;
+
+        if len == 0 && !buf.is_empty() {
+            return Err(FsError::EndOfFile);
+        }
+
+        let read = read_smooth_from_block_device(
+            &mut self.disk,
+            self.offset + self.seek,
+            &mut buf[..len],
+        )?;
+        self.seek += read as u64;
+
+        Ok(read)
+    }
+
+    /// The buffers that fit before the end of the partition are read at once, and the
+    /// buffer crossing the end is left for a later call.
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        let fitting = self.fitting_bufs(bufs.iter().map(|buf| buf.len()));
+        if fitting == 0 {
+            return match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+                Some(buf) => self.read(buf),
+                None => Ok(0),
+            };
+        }
+
+        let read = read_smooth_vectored_from_block_device(
+            &mut self.disk,
+            self.offset + self.seek,
+            &mut bufs[..fitting],
+        )?;
+        self.seek += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<D: BlockDevice> Write for Partition<D> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let remaining = self.length.saturating_sub(self.seek);
+        let len = (buf.len() as u64).min(remaining) as usize;
+
+        if len == 0 && !buf.is_empty() {
+            return Err(FsError::EndOfFile);
+        }
+
+        let written =
+            write_smooth_to_block_device(&mut self.disk, self.offset + self.seek, &buf[..len])?;
+        self.seek += written as u64;
+
+        Ok(written)
+    }
+
+    /// The buffers that fit before the end of the partition are written at once, and the
+    /// buffer crossing the end is left for a later call.
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<usize> {
+        let fitting = self.fitting_bufs(bufs.iter().map(|buf| buf.len()));
+        if fitting == 0 {
+            return match bufs.iter().find(|buf| !buf.is_empty()) {
+                Some(buf) => self.write(buf),
+                None => Ok(0),
+            };
+        }
+
+        let written = write_smooth_vectored_to_block_device(
+            &mut self.disk,
+            self.offset + self.seek,
+            &bufs[..fitting],
+        )?;
+        self.seek += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.disk.flush()
+    }
+}
+
+impl<D: BlockDevice> Seek for Partition<D> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let new_seek = match pos {
+            SeekFrom::Start(start) => Some(start),
+            SeekFrom::Current(current) => self.seek.checked_add_signed(current),
+            SeekFrom::End(end) => self.length.checked_add_signed(end),
+        };
+
+        self.seek = new_seek
+            .filter(|&seek| seek <= self.length)
+            .ok_or(FsError::InvalidInput)?;
+
+        Ok(self.seek)
+    }
+
+    fn stream_position(&mut self) -> u64 {
+        self.seek
+    }
+}
+
+impl<D: BlockDevice> Debug for Partition<D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Partition")
+            .field("kind", &self.kind)
+            .field("offset", &self.offset)
+            .field("length", &self.length)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Disk {
+        data: [u8; 512 * 8],
+    }
+
+    impl BlockDevice for Disk {
+        const BLOCK_SIZE: usize = 512;
+
+        fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+            let start = block_offset as usize * 512;
+            self.data.get(start..start + 512).ok_or(FsError::EndOfFile)
+        }
+
+        fn write_blocks(&mut self, block_offset: u64, buf: &[u8]) -> Result<()> {
+            let start = block_offset as usize * 512;
+            self.data
+                .get_mut(start..start + buf.len())
+                .ok_or(FsError::EndOfFile)?
+                .copy_from_slice(buf);
+
+            Ok(())
+        }
+    }
+
+    fn disk_with_data() -> Disk {
+        let mut disk = Disk { data: [0; 512 * 8] };
+
+        for (sector, chunk) in disk.data.chunks_exact_mut(512).enumerate() {
+            chunk.fill(sector as u8);
+        }
+
+        disk
+    }
+
+    #[test]
+    fn test_mbr_partition() {
+        let mut disk = disk_with_data();
+        disk.data[..512].fill(0);
+
+        let entry = &mut disk.data[446 + 16..][..16];
+        entry[0] = 0x80;
+        entry[4] = 0x0C;
+        entry[8..12].copy_from_slice(&2u32.to_le_bytes());
+        entry[12..16].copy_from_slice(&3u32.to_le_bytes());
+        disk.data[510] = 0x55;
+        disk.data[511] = 0xAA;
+
+        assert!(matches!(
+            Partition::from_mbr(&mut disk, 0),
+            Err(FsError::NotFound)
+        ));
+
+        let mut part = Partition::from_mbr(&mut disk, 1).unwrap();
+        assert_eq!(
+            part.kind(),
+            PartitionKind::Mbr {
+                bootable: true,
+                kind: 0x0C
+            }
+        );
+        assert_eq!(part.offset(), 1024);
+        assert_eq!(part.len(), 1536);
+
+        let mut buf = [0; 4];
+        part.seek(SeekFrom::Start(510)).unwrap();
+        assert_eq!(part.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [2, 2, 3, 3]);
+
+        // Reads must not escape the end of the partition
+        part.seek(SeekFrom::End(-2)).unwrap();
+        assert_eq!(part.read(&mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [4, 4]);
+        assert!(part.read(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_vectored_partition_reads() {
+        let mut disk = disk_with_data();
+        let mut part = Partition::new(&mut disk, PartitionKind::Whole, 1024, 1536);
+
+        let mut first = [0; 512];
+        let mut second = [0; 2];
+        let mut third = [0; 1024];
+        let mut bufs: [&mut [u8]; 3] = [&mut first, &mut second, &mut third];
+
+        // The last buffer would cross the end, so it is left for the next read
+        assert_eq!(part.read_vectored(&mut bufs).unwrap(), 514);
+        assert_eq!(*bufs[0], [2; 512]);
+        assert_eq!(*bufs[1], [3; 2]);
+
+        assert_eq!(part.read_vectored(&mut bufs[2..]).unwrap(), 1022);
+        assert_eq!(bufs[2][..510], [3; 510]);
+        assert_eq!(bufs[2][510..1022], [4; 512]);
+        assert!(part.read_vectored(&mut bufs[2..]).is_err());
+    }
+
+    #[test]
+    fn test_vectored_partition_writes() {
+        let mut disk = disk_with_data();
+        let mut part = Partition::new(&mut disk, PartitionKind::Whole, 512, 1024);
+
+        let bufs: [&[u8]; 3] = [&[0xAA; 512], &[0xBB; 256], &[0xCC; 256]];
+        part.write_vectored_all(&mut bufs.clone()).unwrap();
+
+        assert_eq!(disk.data[..512], [0; 512]);
+        assert_eq!(disk.data[512..1024], [0xAA; 512]);
+        assert_eq!(disk.data[1024..1280], [0xBB; 256]);
+        assert_eq!(disk.data[1280..1536], [0xCC; 256]);
+        assert_eq!(disk.data[1536..2048], [3; 512]);
+    }
+
+    #[test]
+    fn test_gpt_partition() {
+        let mut disk = disk_with_data();
+        disk.data[512..1536].fill(0);
+
+        let header = &mut disk.data[512..1024];
+        header[0..8].copy_from_slice(b"EFI PART");
+        header[72..80].copy_from_slice(&2u64.to_le_bytes());
+        header[80..84].copy_from_slice(&4u32.to_le_bytes());
+        header[84..88].copy_from_slice(&128u32.to_le_bytes());
+
+        let entry = &mut disk.data[1024 + 128..][..128];
+        entry[0..16].fill(0xAB);
+        entry[16..32].fill(0xCD);
+        entry[32..40].copy_from_slice(&4u64.to_le_bytes());
+        entry[40..48].copy_from_slice(&6u64.to_le_bytes());
+
+        assert!(matches!(
+            Partition::from_gpt(&mut disk, 0),
+            Err(FsError::NotFound)
+        ));
+        assert!(matches!(
+            Partition::from_gpt(&mut disk, 4),
+            Err(FsError::NotFound)
+        ));
+
+        let mut part = Partition::from_gpt(disk, 1).unwrap();
+        assert_eq!(
+            part.kind(),
+            PartitionKind::Gpt {
+                type_guid: [0xAB; 16],
+                unique_guid: [0xCD; 16]
+            }
+        );
+        assert_eq!(part.len(), 3 * 512);
+
+        let mut buf = [0; 2];
+        part.read(&mut buf).unwrap();
+        assert_eq!(buf, [4, 4]);
+    }
+
+    #[test]
+    fn test_invalid_gpt_entries() {
+        let mut disk = disk_with_data();
+        disk.data[512..1536].fill(0);
+
+        let header = &mut disk.data[512..1024];
+        header[0..8].copy_from_slice(b"EFI PART");
+        header[72..80].copy_from_slice(&2u64.to_le_bytes());
+        header[80..84].copy_from_slice(&4u32.to_le_bytes());
+        header[84..88].copy_from_slice(&128u32.to_le_bytes());
+
+        // Ends before it starts
+        let entry = &mut disk.data[1024..][..128];
+        entry[0..16].fill(0xAB);
+        entry[32..40].copy_from_slice(&6u64.to_le_bytes());
+        entry[40..48].copy_from_slice(&4u64.to_le_bytes());
+
+        // Too large to address in bytes
+        let entry = &mut disk.data[1024 + 128..][..128];
+        entry[0..16].fill(0xAB);
+        entry[32..40].copy_from_slice(&4u64.to_le_bytes());
+        entry[40..48].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        assert!(matches!(
+            Partition::from_gpt(&mut disk, 0),
+            Err(FsError::InvalidInput)
+        ));
+        assert!(matches!(
+            Partition::from_gpt(&mut disk, 1),
+            Err(FsError::InvalidInput)
+        ));
+
+        // A table that can't be addressed either
+        disk.data[512 + 72..512 + 80].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            Partition::from_gpt(&mut disk, 1),
+            Err(FsError::InvalidInput)
+        ));
+    }
+
+    #[test]
+    fn test_writing_partition() {
+        let mut disk = disk_with_data();
+        let mut part = Partition::new(&mut disk, PartitionKind::Whole, 1024, 1024);
+
+        part.seek(SeekFrom::Start(510)).unwrap();
+        part.write_all(&[0xEE; 4]).unwrap();
+
+        // Writes must not escape the end of the partition either
+        part.seek(SeekFrom::End(-2)).unwrap();
+        assert_eq!(part.write(&[0xDD; 4]).unwrap(), 2);
+        assert!(part.write(&[0xDD; 4]).is_err());
+
+        assert_eq!(
+            disk.data[1024 + 508..1024 + 516],
+            [2, 2, 0xEE, 0xEE, 0xEE, 0xEE, 3, 3]
+        );
+        assert_eq!(disk.data[2046..2050], [0xDD, 0xDD, 4, 4]);
+    }
+}
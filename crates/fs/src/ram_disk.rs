@@ -0,0 +1,119 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    block_queue::SchedulerKind,
+    error::{FsError, Result},
+    read_block::BlockDevice,
+};
+
+/// # Ram Disk
+/// A block device backed by bytes already in memory, like a disk image loaded from the
+/// initfs.
+///
+/// If the image does not end on a block boundary, its last block is padded with zeros.
+pub struct RamDisk<'a, const BLOCK_SIZE: usize = 512> {
+    data: &'a [u8],
+    tail: [u8; BLOCK_SIZE],
+}
+
+impl<'a, const BLOCK_SIZE: usize> RamDisk<'a, BLOCK_SIZE> {
+    /// Create a disk over the image `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            tail: [0; BLOCK_SIZE],
+        }
+    }
+
+    /// The amount of blocks on this disk, including a padded last block.
+    pub fn blocks(&self) -> u64 {
+        self.data.len().div_ceil(BLOCK_SIZE) as u64
+    }
+}
+
+impl<'a, const BLOCK_SIZE: usize> BlockDevice for RamDisk<'a, BLOCK_SIZE> {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    // Nothing to seek, so reordering would only cost time
+    const SCHEDULER: SchedulerKind = SchedulerKind::Noop;
+
+    fn read_block<'b>(&'b mut self, block_offset: u64) -> Result<&'b [u8]> {
+        let start = usize::try_from(block_offset)
+            .ok()
+            .and_then(|block| block.checked_mul(BLOCK_SIZE))
+            .filter(|&start| start < self.data.len())
+            .ok_or(FsError::EndOfFile)?;
+
+        match self.data.get(start..start + BLOCK_SIZE) {
+            Some(block) => Ok(block),
+            None => {
+                let rest = &self.data[start..];
+                self.tail = [0; BLOCK_SIZE];
+                self.tail[..rest.len()].copy_from_slice(rest);
+
+                Ok(&self.tail)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::read_block::read_smooth_from_block_device;
+
+    #[test]
+    fn test_reading_blocks() {
+        let data: [u8; 40] = core::array::from_fn(|i| i as u8);
+        let mut disk = RamDisk::<10>::new(&data);
+
+        assert_eq!(disk.blocks(), 4);
+        assert_eq!(disk.read_block(0).unwrap(), &data[0..10]);
+        assert_eq!(disk.read_block(3).unwrap(), &data[30..40]);
+        assert!(matches!(disk.read_block(4), Err(FsError::EndOfFile)));
+    }
+
+    #[test]
+    fn test_last_block_is_padded() {
+        let data = [0xAA; 15];
+        let mut disk = RamDisk::<10>::new(&data);
+
+        assert_eq!(disk.blocks(), 2);
+        assert_eq!(
+            disk.read_block(1).unwrap(),
+            &[0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_smooth_reading() {
+        let data: [u8; 1024] = core::array::from_fn(|i| (i / 512) as u8);
+        let mut disk = RamDisk::<512>::new(&data);
+
+        let mut target = [255; 4];
+        read_smooth_from_block_device(&mut disk, 510, &mut target).unwrap();
+        assert_eq!(target, [0, 0, 1, 1]);
+    }
+}
@@ -23,7 +23,14 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use crate::error::Result;
+use crate::{
+    block_queue::SchedulerKind,
+    error::{FsError, Result},
+};
+
+/// The largest block size partial block writes are supported for, as the block being
+/// changed is read onto the stack first.
+const MAX_PARTIAL_BLOCK_SIZE: usize = 4096;
 
 /// # Block Device
 /// A device that can only read 'blocks' of bytes at a time.
@@ -39,6 +46,12 @@ pub trait BlockDevice {
     /// The size of each of the blocks this media can read.
     const BLOCK_SIZE: usize;
 
+    /// # Scheduler
+    /// The order a `RequestQueue` should send this device's requests in.
+    ///
+    /// Defaults to the elevator, which suits anything that behaves like a spinning disk.
+    const SCHEDULER: SchedulerKind = SchedulerKind::Elevator;
+
     /// # Read Device
     /// Read one block from the device given the block's offset.
     ///
@@ -47,6 +60,98 @@ pub trait BlockDevice {
     /// it must be up to the programmer to keep track of providing
     /// the bytes this block device had read.
     fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]>;
+
+    /// # Read Many Blocks
+    /// Read whole blocks starting at `block_offset` into `buf`, whose length must be a
+    /// multiple of the block size.
+    ///
+    /// Devices that can read many blocks with one command should override this, the
+    /// default reads them one at a time.
+    fn read_blocks(&mut self, block_offset: u64, buf: &mut [u8]) -> Result<()> {
+        for (index, chunk) in buf.chunks_mut(Self::BLOCK_SIZE).enumerate() {
+            let block = self.read_block(block_offset + index as u64)?;
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+
+        Ok(())
+    }
+
+    /// # Write Many Blocks
+    /// Write whole blocks from `buf`, whose length must be a multiple of the block size,
+    /// starting at `block_offset`.
+    ///
+    /// Devices are read-only unless they override this.
+    fn write_blocks(&mut self, block_offset: u64, buf: &[u8]) -> Result<()> {
+        let _ = (block_offset, buf);
+        Err(FsError::NotSupported)
+    }
+
+    /// # Read Blocks Vectored
+    /// Read whole blocks starting at `block_offset` into each of `bufs` in turn, whose
+    /// lengths must all be multiples of the block size.
+    ///
+    /// Devices that can scatter one command across many buffers should override this, the
+    /// default reads each buffer with `read_blocks`.
+    fn read_blocks_vectored(&mut self, block_offset: u64, bufs: &mut [&mut [u8]]) -> Result<()> {
+        let mut block = block_offset;
+        for buf in bufs.iter_mut() {
+            self.read_blocks(block, buf)?;
+            block += (buf.len() / Self::BLOCK_SIZE) as u64;
+        }
+
+        Ok(())
+    }
+
+    /// # Write Blocks Vectored
+    /// Write whole blocks from each of `bufs` in turn, whose lengths must all be multiples
+    /// of the block size, starting at `block_offset`.
+    ///
+    /// The default writes each buffer with `write_blocks`.
+    fn write_blocks_vectored(&mut self, block_offset: u64, bufs: &[&[u8]]) -> Result<()> {
+        let mut block = block_offset;
+        for buf in bufs {
+            self.write_blocks(block, buf)?;
+            block += (buf.len() / Self::BLOCK_SIZE) as u64;
+        }
+
+        Ok(())
+    }
+
+    /// # Flush
+    /// Make sure every block written so far is stored on the media, and not just in the
+    /// device's write cache.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: BlockDevice> BlockDevice for &mut T {
+    const BLOCK_SIZE: usize = T::BLOCK_SIZE;
+    const SCHEDULER: SchedulerKind = T::SCHEDULER;
+
+    fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+        (**self).read_block(block_offset)
+    }
+
+    fn read_blocks(&mut self, block_offset: u64, buf: &mut [u8]) -> Result<()> {
+        (**self).read_blocks(block_offset, buf)
+    }
+
+    fn write_blocks(&mut self, block_offset: u64, buf: &[u8]) -> Result<()> {
+        (**self).write_blocks(block_offset, buf)
+    }
+
+    fn read_blocks_vectored(&mut self, block_offset: u64, bufs: &mut [&mut [u8]]) -> Result<()> {
+        (**self).read_blocks_vectored(block_offset, bufs)
+    }
+
+    fn write_blocks_vectored(&mut self, block_offset: u64, bufs: &[&[u8]]) -> Result<()> {
+        (**self).write_blocks_vectored(block_offset, bufs)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
 }
 
 pub fn read_smooth_from_block_device<Device: BlockDevice>(
@@ -56,13 +161,24 @@ pub fn read_smooth_from_block_device<Device: BlockDevice>(
 ) -> Result<usize> {
     let mut data_copied = 0;
 
-    // FIXME: We should try to read bytes into our 'data' buffer directly as much as possible
-    //        Instead of a copy from the 'read_block' method.
     loop {
         let block_index = (offset_bytes + data_copied as u64) / Device::BLOCK_SIZE as u64;
         let block_offset =
             ((offset_bytes + data_copied as u64) % Device::BLOCK_SIZE as u64) as usize;
 
+        // Whole blocks can go straight into `data` with one read of the device
+        let whole_blocks = (data.len() - data_copied) / Device::BLOCK_SIZE;
+        if block_offset == 0 && whole_blocks > 1 {
+            let reading_bytes = whole_blocks * Device::BLOCK_SIZE;
+            device.read_blocks(
+                block_index,
+                &mut data[data_copied..data_copied + reading_bytes],
+            )?;
+
+            data_copied += reading_bytes;
+            continue;
+        }
+
         let index_begin = data_copied;
         let index_end =
             (Device::BLOCK_SIZE - block_offset).min(data.len() - data_copied) + index_begin;
@@ -89,9 +205,131 @@ pub fn read_smooth_from_block_device<Device: BlockDevice>(
     Ok(data.len())
 }
 
+/// Write `data` to `device` starting `offset_bytes` into it.
+///
+/// Whole blocks are written as they are, the blocks `data` only covers part of are read
+/// first so the rest of them is kept.
+pub fn write_smooth_to_block_device<Device: BlockDevice>(
+    device: &mut Device,
+    offset_bytes: u64,
+    data: &[u8],
+) -> Result<usize> {
+    assert!(
+        Device::BLOCK_SIZE <= MAX_PARTIAL_BLOCK_SIZE,
+        "Blocks of {} bytes are too large to be partially written!",
+        Device::BLOCK_SIZE
+    );
+    let mut data_written = 0;
+
+    while data_written < data.len() {
+        let block_index = (offset_bytes + data_written as u64) / Device::BLOCK_SIZE as u64;
+        let block_offset =
+            ((offset_bytes + data_written as u64) % Device::BLOCK_SIZE as u64) as usize;
+
+        let whole_blocks = (data.len() - data_written) / Device::BLOCK_SIZE;
+        if block_offset == 0 && whole_blocks > 0 {
+            let writing_bytes = whole_blocks * Device::BLOCK_SIZE;
+            device.write_blocks(
+                block_index,
+                &data[data_written..data_written + writing_bytes],
+            )?;
+
+            data_written += writing_bytes;
+            continue;
+        }
+
+        let writing_bytes = (Device::BLOCK_SIZE - block_offset).min(data.len() - data_written);
+        let mut block = [0; MAX_PARTIAL_BLOCK_SIZE];
+        let block = &mut block[..Device::BLOCK_SIZE];
+
+        block.copy_from_slice(device.read_block(block_index)?);
+        block[block_offset..block_offset + writing_bytes]
+            .copy_from_slice(&data[data_written..data_written + writing_bytes]);
+        device.write_blocks(block_index, block)?;
+
+        data_written += writing_bytes;
+    }
+
+    Ok(data.len())
+}
+
+/// How many of `lens` can be handed to the device as one vectored command, which is only
+/// possible for buffers made of whole blocks.
+fn whole_block_run<Device: BlockDevice>(lens: impl Iterator<Item = usize>) -> usize {
+    lens.take_while(|&len| len % Device::BLOCK_SIZE == 0)
+        .count()
+}
+
+/// Read `bufs` one after another from `device` starting `offset_bytes` into it.
+///
+/// Once the position is on a block boundary, every following buffer made of whole blocks
+/// is read with one vectored read of the device.
+pub fn read_smooth_vectored_from_block_device<Device: BlockDevice>(
+    device: &mut Device,
+    offset_bytes: u64,
+    bufs: &mut [&mut [u8]],
+) -> Result<usize> {
+    let mut position = offset_bytes;
+    let mut index = 0;
+
+    while index < bufs.len() {
+        if position.is_multiple_of(Device::BLOCK_SIZE as u64) {
+            let run = whole_block_run::<Device>(bufs[index..].iter().map(|buf| buf.len()));
+            if run > 1 {
+                let run_bufs = &mut bufs[index..index + run];
+                device.read_blocks_vectored(position / Device::BLOCK_SIZE as u64, run_bufs)?;
+
+                position += run_bufs.iter().map(|buf| buf.len() as u64).sum::<u64>();
+                index += run;
+                continue;
+            }
+        }
+
+        position += read_smooth_from_block_device(device, position, bufs[index])? as u64;
+        index += 1;
+    }
+
+    Ok((position - offset_bytes) as usize)
+}
+
+/// Write `bufs` one after another to `device` starting `offset_bytes` into it.
+///
+/// Once the position is on a block boundary, every following buffer made of whole blocks
+/// is written with one vectored write of the device.
+pub fn write_smooth_vectored_to_block_device<Device: BlockDevice>(
+    device: &mut Device,
+    offset_bytes: u64,
+    bufs: &[&[u8]],
+) -> Result<usize> {
+    let mut position = offset_bytes;
+    let mut index = 0;
+
+    while index < bufs.len() {
+        if position.is_multiple_of(Device::BLOCK_SIZE as u64) {
+            let run = whole_block_run::<Device>(bufs[index..].iter().map(|buf| buf.len()));
+            if run > 1 {
+                let run_bufs = &bufs[index..index + run];
+                device.write_blocks_vectored(position / Device::BLOCK_SIZE as u64, run_bufs)?;
+
+                position += run_bufs.iter().map(|buf| buf.len() as u64).sum::<u64>();
+                index += run;
+                continue;
+            }
+        }
+
+        position += write_smooth_to_block_device(device, position, bufs[index])? as u64;
+        index += 1;
+    }
+
+    Ok((position - offset_bytes) as usize)
+}
+
 #[cfg(test)]
 mod test {
-    use super::{read_smooth_from_block_device, BlockDevice};
+    use super::{
+        read_smooth_from_block_device, read_smooth_vectored_from_block_device,
+        write_smooth_to_block_device, write_smooth_vectored_to_block_device, BlockDevice,
+    };
 
     struct Dummy {
         buf: [u8; 10],
@@ -211,4 +449,112 @@ mod test {
             "Expected bytes to switch when reading different sector"
         );
     }
+
+    /// Ten blocks of ten bytes that can be written, counting every write command
+    struct Writable {
+        data: [u8; 100],
+        writes: usize,
+        /// How many buffers each vectored write command was given
+        gathered: [usize; 4],
+    }
+
+    impl BlockDevice for Writable {
+        const BLOCK_SIZE: usize = 10;
+
+        fn read_block<'a>(&'a mut self, block_offset: u64) -> crate::error::Result<&'a [u8]> {
+            let start = block_offset as usize * 10;
+            Ok(&self.data[start..start + 10])
+        }
+
+        fn write_blocks(&mut self, block_offset: u64, buf: &[u8]) -> crate::error::Result<()> {
+            let start = block_offset as usize * 10;
+            self.data[start..start + buf.len()].copy_from_slice(buf);
+            self.writes += 1;
+
+            Ok(())
+        }
+
+        fn write_blocks_vectored(
+            &mut self,
+            block_offset: u64,
+            bufs: &[&[u8]],
+        ) -> crate::error::Result<()> {
+            let mut start = block_offset as usize * 10;
+            for buf in bufs {
+                self.data[start..start + buf.len()].copy_from_slice(buf);
+                start += buf.len();
+            }
+            self.gathered[self.writes] = bufs.len();
+            self.writes += 1;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_read_only_by_default() {
+        let mut dummy = Dummy::new();
+        assert!(write_smooth_to_block_device(&mut dummy, 0, &[1; 10]).is_err());
+    }
+
+    #[test]
+    fn test_smooth_writing_keeps_partial_blocks() {
+        let mut disk = Writable {
+            data: core::array::from_fn(|i| i as u8),
+            writes: 0,
+            gathered: [0; 4],
+        };
+
+        write_smooth_to_block_device(&mut disk, 5, &[0xFF; 30]).unwrap();
+
+        // One partial block on each end, and the two whole blocks between them at once
+        assert_eq!(disk.writes, 3);
+        assert_eq!(disk.data[..5], [0, 1, 2, 3, 4]);
+        assert_eq!(disk.data[5..35], [0xFF; 30]);
+        assert_eq!(disk.data[35..40], [35, 36, 37, 38, 39]);
+    }
+
+    #[test]
+    fn test_smooth_vectored_reading() {
+        let mut dummy = Dummy::new();
+
+        let mut first = [255; 5];
+        let mut second = [255; 10];
+        let mut third = [255; 20];
+        let mut bufs: [&mut [u8]; 3] = [&mut first, &mut second, &mut third];
+
+        let read = read_smooth_vectored_from_block_device(&mut dummy, 5, &mut bufs).unwrap();
+
+        assert_eq!(read, 35);
+        assert_eq!(first, [0; 5]);
+        assert_eq!(second, [1; 10]);
+        assert_eq!(
+            third,
+            [2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3]
+        );
+    }
+
+    #[test]
+    fn test_smooth_vectored_writing_gathers_whole_blocks() {
+        let mut disk = Writable {
+            data: core::array::from_fn(|i| i as u8),
+            writes: 0,
+            gathered: [0; 4],
+        };
+
+        let bufs: [&[u8]; 4] = [&[0xAA; 5], &[0xBB; 10], &[0xCC; 20], &[0xDD; 3]];
+        let written = write_smooth_vectored_to_block_device(&mut disk, 5, &bufs).unwrap();
+
+        // The partial block first, then both whole-block buffers in one command, then the
+        // tail
+        assert_eq!(written, 38);
+        assert_eq!(disk.writes, 3);
+        assert_eq!(disk.gathered, [0, 2, 0, 0]);
+        assert_eq!(disk.data[..5], [0, 1, 2, 3, 4]);
+        assert_eq!(disk.data[5..10], [0xAA; 5]);
+        assert_eq!(disk.data[10..20], [0xBB; 10]);
+        assert_eq!(disk.data[20..40], [0xCC; 20]);
+        assert_eq!(disk.data[40..43], [0xDD; 3]);
+        assert_eq!(disk.data[43..45], [43, 44]);
+    }
 }
@@ -0,0 +1,145 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    error::{FsError, Result},
+    io::{Read, Seek, SeekFrom, Write},
+};
+use std::{fs::File, io, path::Path};
+
+/// # Std Read Seek
+/// Adapts anything std can read and seek, like a `std::fs::File`, so disk images on the
+/// host can be used anywhere this crate reads from a disk.
+pub struct StdReadSeek<T: io::Read + io::Seek> {
+    inner: T,
+    position: u64,
+}
+
+impl StdReadSeek<File> {
+    /// Open the disk image at `path`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(File::open(path)?))
+    }
+}
+
+impl<T: io::Read + io::Seek> StdReadSeek<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner, position: 0 }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+fn fs_error(err: io::Error) -> FsError {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => FsError::EndOfFile,
+        io::ErrorKind::NotFound => FsError::NotFound,
+        io::ErrorKind::InvalidInput => FsError::InvalidInput,
+        io::ErrorKind::Unsupported => FsError::NotSupported,
+        _ => FsError::ReadError,
+    }
+}
+
+impl<T: io::Read + io::Seek> Read for StdReadSeek<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut read = 0;
+
+        // Callers expect the whole buffer to be filled unless the end was reached
+        while read < buf.len() {
+            match self.inner.read(&mut buf[read..]) {
+                Ok(0) => break,
+                Ok(len) => read += len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(fs_error(err)),
+            }
+        }
+        self.position += read as u64;
+
+        if read == 0 && !buf.is_empty() {
+            return Err(FsError::EndOfFile);
+        }
+
+        Ok(read)
+    }
+}
+
+impl<T: io::Read + io::Seek + io::Write> Write for StdReadSeek<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.write(buf).map_err(fs_error)?;
+        self.position += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush().map_err(fs_error)
+    }
+}
+
+impl<T: io::Read + io::Seek> Seek for StdReadSeek<T> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(start) => io::SeekFrom::Start(start),
+            SeekFrom::End(end) => io::SeekFrom::End(end),
+            SeekFrom::Current(current) => io::SeekFrom::Current(current),
+        };
+
+        self.position = self.inner.seek(pos).map_err(fs_error)?;
+        Ok(self.position)
+    }
+
+    fn stream_position(&mut self) -> u64 {
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{io::Write, vec::Vec};
+
+    #[test]
+    fn test_reading_file() {
+        let path = std::env::temp_dir().join(std::format!("fs-std-io-{}", std::process::id()));
+        let data = (0..=255).collect::<Vec<u8>>();
+        File::create(&path).unwrap().write_all(&data).unwrap();
+
+        let mut file = StdReadSeek::open(&path).unwrap();
+        let mut buf = [0; 16];
+
+        assert_eq!(file.seek(SeekFrom::Start(100)).unwrap(), 100);
+        assert_eq!(file.read(&mut buf).unwrap(), 16);
+        assert_eq!(buf, data[100..116]);
+        assert_eq!(file.stream_position(), 116);
+
+        file.seek(SeekFrom::End(-4)).unwrap();
+        assert_eq!(file.read(&mut buf).unwrap(), 4);
+        assert!(matches!(file.read(&mut buf), Err(FsError::EndOfFile)));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
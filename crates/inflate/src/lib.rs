@@ -0,0 +1,532 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! # Inflate
+//! A small DEFLATE (RFC 1951) decompressor, with support for the zlib (RFC 1950)
+//! wrapper used by PNG images.
+//!
+//! Everything is decompressed into a caller provided buffer, so no allocation is needed.
+//! This favors being small over being fast, codes are decoded one bit at a time.
+
+#![no_std]
+
+use core::fmt::Display;
+
+/// The most literal/length codes a block can have.
+const MAX_LITERAL_CODES: usize = 288;
+/// The most distance codes a block can have.
+const MAX_DISTANCE_CODES: usize = 30;
+/// The longest a Huffman code can be.
+const MAX_CODE_BITS: usize = 15;
+
+/// The smallest length of each length code, starting from code `257`.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+/// How many extra bits follow each length code.
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// The smallest distance of each distance code.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// How many extra bits follow each distance code.
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order code length code lengths are stored in a dynamic block.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InflateError {
+    /// The input ended in the middle of the stream.
+    Truncated,
+    /// The output buffer cannot fit the decompressed data.
+    OutputTooSmall,
+    /// A block uses the reserved block type.
+    InvalidBlockType,
+    /// A stored block's length does not match its complement.
+    InvalidStoredLength,
+    /// The code lengths do not describe a valid Huffman code, or a symbol is unused.
+    InvalidCode,
+    /// A match refers to data before the start of the output.
+    InvalidDistance,
+    /// The zlib header is malformed, or asks for a preset dictionary.
+    InvalidHeader,
+    /// The Adler-32 checksum does not match the decompressed data.
+    ChecksumMismatch,
+}
+
+impl core::error::Error for InflateError {}
+impl Display for InflateError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InflateError::Truncated => write!(f, "compressed stream is truncated"),
+            InflateError::OutputTooSmall => write!(f, "output buffer is too small"),
+            InflateError::InvalidBlockType => write!(f, "invalid deflate block type"),
+            InflateError::InvalidStoredLength => write!(f, "stored block length is corrupt"),
+            InflateError::InvalidCode => write!(f, "invalid huffman code"),
+            InflateError::InvalidDistance => write!(f, "match refers to data out of range"),
+            InflateError::InvalidHeader => write!(f, "invalid zlib header"),
+            InflateError::ChecksumMismatch => write!(f, "adler-32 checksum does not match"),
+        }
+    }
+}
+
+/// Reads the input LSB first, as DEFLATE packs its bits.
+struct BitReader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    const fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, n: u32) -> Result<u32, InflateError> {
+        while self.bit_count < n {
+            let byte = *self.input.get(self.pos).ok_or(InflateError::Truncated)?;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.pos += 1;
+            self.bit_count += 8;
+        }
+
+        let value = self.bit_buf & ((1 << n) - 1);
+        self.bit_buf >>= n;
+        self.bit_count -= n;
+
+        Ok(value)
+    }
+
+    /// Drop the rest of the current byte.
+    ///
+    /// Bytes are only read when needed, so there are never more than 7 bits buffered
+    /// between reads.
+    fn align_to_byte(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], InflateError> {
+        let bytes = self
+            .input
+            .get(self.pos..self.pos + len)
+            .ok_or(InflateError::Truncated)?;
+        self.pos += len;
+
+        Ok(bytes)
+    }
+}
+
+/// The decompressed data written so far.
+struct Output<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl Output<'_> {
+    fn push(&mut self, byte: u8) -> Result<(), InflateError> {
+        *self
+            .buffer
+            .get_mut(self.len)
+            .ok_or(InflateError::OutputTooSmall)? = byte;
+        self.len += 1;
+
+        Ok(())
+    }
+
+    fn copy_match(&mut self, distance: usize, length: usize) -> Result<(), InflateError> {
+        if distance > self.len {
+            return Err(InflateError::InvalidDistance);
+        }
+
+        // Matches can overlap what they are writing, so this must go a byte at a time
+        for _ in 0..length {
+            self.push(self.buffer[self.len - distance])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A canonical Huffman code, stored as how many codes there are of each length and the
+/// symbols sorted by their code.
+struct Huffman<const N: usize> {
+    counts: [u16; MAX_CODE_BITS + 1],
+    symbols: [u16; N],
+}
+
+impl<const N: usize> Huffman<N> {
+    fn new(lengths: &[u8]) -> Result<Self, InflateError> {
+        let mut counts = [0; MAX_CODE_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+
+        // Make sure there are not more codes of a length than can exist
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(InflateError::InvalidCode);
+            }
+        }
+
+        let mut offsets = [0; MAX_CODE_BITS + 1];
+        for length in 1..MAX_CODE_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = [0; N];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<usize, InflateError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize] as usize);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(InflateError::InvalidCode)
+    }
+}
+
+/// Decompress raw DEFLATE data from `input` into `output`.
+///
+/// Returns the amount of bytes written to `output`.
+pub fn inflate(input: &[u8], output: &mut [u8]) -> Result<usize, InflateError> {
+    let mut reader = BitReader::new(input);
+    inflate_from(&mut reader, output)
+}
+
+/// Decompress zlib wrapped DEFLATE data from `input` into `output`, checking the
+/// Adler-32 checksum at the end of the stream.
+///
+/// Returns the amount of bytes written to `output`.
+pub fn zlib_decompress(input: &[u8], output: &mut [u8]) -> Result<usize, InflateError> {
+    let [cmf, flg, ..] = *input else {
+        return Err(InflateError::Truncated);
+    };
+
+    let compression_method = cmf & 0xF;
+    let window_bits = (cmf >> 4) + 8;
+    let preset_dictionary = flg & (1 << 5) != 0;
+
+    if compression_method != 8
+        || window_bits > 15
+        || preset_dictionary
+        || u16::from_be_bytes([cmf, flg]) % 31 != 0
+    {
+        return Err(InflateError::InvalidHeader);
+    }
+
+    let mut reader = BitReader::new(&input[2..]);
+    let len = inflate_from(&mut reader, output)?;
+
+    reader.align_to_byte();
+    let checksum = reader.bytes(size_of::<u32>())?;
+    if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]])
+        != adler32(&output[..len])
+    {
+        return Err(InflateError::ChecksumMismatch);
+    }
+
+    Ok(len)
+}
+
+/// The Adler-32 checksum of `bytes`, as used by zlib.
+pub fn adler32(bytes: &[u8]) -> u32 {
+    const MODULO: u32 = 65521;
+    // The most bytes that can be summed before `b` could overflow
+    const CHUNK_LEN: usize = 5552;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(CHUNK_LEN) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+
+        a %= MODULO;
+        b %= MODULO;
+    }
+
+    (b << 16) | a
+}
+
+fn inflate_from(reader: &mut BitReader, output: &mut [u8]) -> Result<usize, InflateError> {
+    let mut output = Output {
+        buffer: output,
+        len: 0,
+    };
+
+    loop {
+        let last_block = reader.bits(1)? == 1;
+
+        match reader.bits(2)? {
+            0 => stored_block(reader, &mut output)?,
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                compressed_block(reader, &mut output, &literals, &distances)?
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(reader)?;
+                compressed_block(reader, &mut output, &literals, &distances)?
+            }
+            _ => return Err(InflateError::InvalidBlockType),
+        }
+
+        if last_block {
+            return Ok(output.len);
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, output: &mut Output) -> Result<(), InflateError> {
+    reader.align_to_byte();
+
+    let header = reader.bytes(4)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+
+    if len != !complement {
+        return Err(InflateError::InvalidStoredLength);
+    }
+
+    for &byte in reader.bytes(len as usize)? {
+        output.push(byte)?;
+    }
+
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman<MAX_LITERAL_CODES>, Huffman<MAX_DISTANCE_CODES>), InflateError>
+{
+    let mut lengths = [0; MAX_LITERAL_CODES];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    Ok((
+        Huffman::new(&lengths)?,
+        Huffman::new(&[5; MAX_DISTANCE_CODES])?,
+    ))
+}
+
+fn dynamic_codes(
+    reader: &mut BitReader,
+) -> Result<(Huffman<MAX_LITERAL_CODES>, Huffman<MAX_DISTANCE_CODES>), InflateError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    if literal_count > MAX_LITERAL_CODES || distance_count > MAX_DISTANCE_CODES {
+        return Err(InflateError::InvalidCode);
+    }
+
+    let mut code_lengths = [0; CODE_LENGTH_ORDER.len()];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::<{ CODE_LENGTH_ORDER.len() }>::new(&code_lengths)?;
+
+    let mut lengths = [0; MAX_LITERAL_CODES + MAX_DISTANCE_CODES];
+    let total = literal_count + distance_count;
+    let mut index = 0;
+
+    while index < total {
+        let (value, repeat) = match code_length_code.decode(reader)? {
+            length @ 0..16 => (length as u8, 1),
+            16 => {
+                let previous = *index
+                    .checked_sub(1)
+                    .and_then(|previous| lengths.get(previous))
+                    .ok_or(InflateError::InvalidCode)?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+
+        lengths
+            .get_mut(index..index + repeat)
+            .filter(|_| index + repeat <= total)
+            .ok_or(InflateError::InvalidCode)?
+            .fill(value);
+        index += repeat;
+    }
+
+    // Without an end of block code, the block could never end
+    if lengths[256] == 0 {
+        return Err(InflateError::InvalidCode);
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..total])?,
+    ))
+}
+
+fn compressed_block(
+    reader: &mut BitReader,
+    output: &mut Output,
+    literals: &Huffman<MAX_LITERAL_CODES>,
+    distances: &Huffman<MAX_DISTANCE_CODES>,
+) -> Result<(), InflateError> {
+    loop {
+        let symbol = literals.decode(reader)?;
+
+        match symbol {
+            0..256 => output.push(symbol as u8)?,
+            256 => return Ok(()),
+            _ => {
+                let length_code = symbol - 257;
+                let length = *LENGTH_BASE
+                    .get(length_code)
+                    .ok_or(InflateError::InvalidCode)? as usize
+                    + reader.bits(LENGTH_EXTRA[length_code] as u32)? as usize;
+
+                let distance_code = distances.decode(reader)?;
+                let distance = *DISTANCE_BASE
+                    .get(distance_code)
+                    .ok_or(InflateError::InvalidCode)? as usize
+                    + reader.bits(DISTANCE_EXTRA[distance_code] as u32)? as usize;
+
+                output.copy_match(distance, length)?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stored_block() {
+        let compressed = [
+            0x78, 0x01, 0x01, 0x0b, 0x00, 0xf4, 0xff, b'h', b'e', b'l', b'l', b'o', b' ', b'h',
+            b'e', b'l', b'l', b'o', 0x19, 0x91, 0x04, 0x49,
+        ];
+        let mut output = [0; 16];
+
+        assert_eq!(zlib_decompress(&compressed, &mut output), Ok(11));
+        assert_eq!(&output[..11], b"hello hello");
+    }
+
+    #[test]
+    fn test_fixed_block() {
+        let compressed = [
+            0x78, 0xda, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e,
+            0x06, 0x7d,
+        ];
+        let mut output = [0; 17];
+
+        assert_eq!(zlib_decompress(&compressed, &mut output), Ok(17));
+        assert_eq!(&output, b"hello hello hello");
+
+        let mut small_output = [0; 8];
+        assert_eq!(
+            zlib_decompress(&compressed, &mut small_output),
+            Err(InflateError::OutputTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_dynamic_block() {
+        // "0,1,4,9,16,..." the squares of 0 to 199 modulo 97
+        let compressed = [
+            0x78, 0xda, 0xed, 0x90, 0xc9, 0x8d, 0x05, 0x21, 0x0c, 0x05, 0x13, 0xaa, 0x43, 0xdb,
+            0x06, 0x03, 0xf9, 0x27, 0x36, 0x05, 0x09, 0xfc, 0x04, 0x46, 0x42, 0x88, 0xe5, 0xad,
+            0xfe, 0x08, 0x06, 0x87, 0x68, 0x72, 0x52, 0xcd, 0x38, 0xf4, 0x60, 0x07, 0x45, 0x0e,
+            0xc6, 0x62, 0x25, 0x49, 0x05, 0x9d, 0x1c, 0x11, 0xc5, 0xfa, 0x88, 0x64, 0x16, 0x47,
+            0xb4, 0x5c, 0x05, 0x3c, 0x0f, 0xe6, 0xc7, 0xa6, 0x27, 0xb9, 0xd8, 0x9b, 0x39, 0xc8,
+            0x64, 0xab, 0xa6, 0xd4, 0x24, 0x82, 0xdd, 0xb4, 0x8c, 0x4d, 0x25, 0x21, 0x92, 0x53,
+            0xec, 0xc9, 0x3a, 0x2c, 0xf7, 0x7a, 0xeb, 0x5d, 0x7d, 0xf4, 0xab, 0x2f, 0x48, 0xa8,
+            0x04, 0x69, 0x92, 0xe3, 0x09, 0x29, 0xa7, 0xa8, 0xd2, 0x1a, 0x68, 0xa3, 0x99, 0x96,
+            0xfb, 0x9a, 0x1b, 0xe1, 0x06, 0x89, 0x17, 0xaa, 0x6f, 0x40, 0x63, 0x1a, 0xd6, 0xc8,
+            0x06, 0xef, 0x57, 0x22, 0x6f, 0x1d, 0x4b, 0x59, 0xad, 0x6e, 0x49, 0xab, 0x5a, 0xb8,
+            0x5e, 0x79, 0x47, 0x70, 0x9c, 0x45, 0xf0, 0xfd, 0xcf, 0xe4, 0xc7, 0x4c, 0xfe, 0x00,
+            0x90, 0x13, 0x6d, 0xad,
+        ];
+        let mut output = [0; 1024];
+
+        // The checksum makes sure the whole output is correct
+        assert_eq!(zlib_decompress(&compressed, &mut output), Ok(566));
+        assert!(output.starts_with(b"0,1,4,9,16,25,36,49,64,8"));
+
+        let mut corrupt = compressed;
+        corrupt[40] ^= 0x10;
+        assert!(zlib_decompress(&corrupt, &mut output).is_err());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let mut output = [0; 4];
+
+        assert_eq!(
+            zlib_decompress(&[0x78, 0x00, 0x03, 0x00], &mut output),
+            Err(InflateError::InvalidHeader)
+        );
+        assert_eq!(
+            inflate(&[0x07], &mut output),
+            Err(InflateError::InvalidBlockType)
+        );
+    }
+}
@@ -0,0 +1,523 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Keyboard layouts, turning the keys that were pressed into the text they type.
+//!
+//! Keys are named by their USB HID usage id no matter which keyboard they came from, and
+//! each key types one [`Symbol`] for each combination of Shift and AltGr. The US layout
+//! is built in, other layouts are text files in the initfs that change keys of it:
+//!
+//! ```text
+//! # Lines starting with '#' are comments
+//! key <usage> <plain> [<shift> [<altgr> [<shift+altgr>]]]
+//! compose <dead> <base> <result>
+//! ```
+//!
+//! A symbol is a single character, `U+` and its hex code point (for spaces, or the
+//! characters that are hard to type), `dead:` and a character for a dead key, or `none`.
+//! A dead key types nothing, its accent is combined with the next key using the layout's
+//! `compose` lines, then a table of common accents built into the kernel.
+//!
+//! Holding Ctrl types the control character of a key instead, so Ctrl+C types `0x03`
+//! whichever key the layout puts `c` on.
+//!
+//! The same parser is used by the kernel when switching layouts and by the imager when
+//! building, so a broken layout fails the build instead of the `keymap_set` call.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Display;
+
+/// One more than the largest usage id a layout can change, the 102nd key on ISO keyboards
+pub const KEYS: usize = 0x65;
+/// Plain, Shift, AltGr, and Shift+AltGr
+const LEVELS: usize = 4;
+
+/// The usage ids of the keys in [`US_PLAIN`] and [`US_SHIFT`], from `a` to `/`
+const US_FIRST_USAGE: usize = 0x04;
+/// The usage id of the 102nd key on ISO keyboards, between left Shift and `z`
+const USAGE_NON_US_BACKSLASH: usize = 0x64;
+
+/// What each key of the US layout types, from [`US_FIRST_USAGE`] onwards
+const US_PLAIN: &[u8] = b"abcdefghijklmnopqrstuvwxyz1234567890\n\x1b\x08\t -=[]\\\\;'`,./";
+/// What each key of the US layout types with Shift held
+const US_SHIFT: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ!@#$%^&*()\n\x1b\x08\t _+{}||:\"~<>?";
+
+/// Accents dead keys can put on letters, and the letters they make. The straight quotes
+/// are there for layouts that use them as dead keys for acute and diaeresis.
+const BUILTIN_COMPOSE: &[(char, &str, &str)] = &[
+    ('`', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ('´', "aeiouycAEIOUYC", "áéíóúýćÁÉÍÓÚÝĆ"),
+    ('\'', "aeiouycAEIOUYC", "áéíóúýćÁÉÍÓÚÝĆ"),
+    ('^', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+    ('~', "anoANO", "ãñõÃÑÕ"),
+    ('¨', "aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+    ('"', "aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+    ('¸', "cC", "çÇ"),
+];
+
+/// What a key types
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    None,
+    Char(char),
+    /// A dead key, which puts its accent on the next key typed
+    Dead(char),
+}
+
+/// The keys held, or locked, that change what a key types
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub altgr: bool,
+    pub caps_lock: bool,
+    pub ctrl: bool,
+}
+
+/// Why a layout file could not be read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line the error is on, starting from one
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// A keyboard layout
+pub struct Keymap {
+    name: String,
+    keys: [[Symbol; LEVELS]; KEYS],
+    /// `(dead, base, result)`, looked at before [`BUILTIN_COMPOSE`]
+    compose: Vec<(char, char, char)>,
+}
+
+const _: () = assert!(US_PLAIN.len() == US_SHIFT.len());
+
+const fn us_keys() -> [[Symbol; LEVELS]; KEYS] {
+    let mut keys = [[Symbol::None; LEVELS]; KEYS];
+
+    let mut index = 0;
+    while index < US_PLAIN.len() {
+        keys[US_FIRST_USAGE + index][0] = Symbol::Char(US_PLAIN[index] as char);
+        keys[US_FIRST_USAGE + index][1] = Symbol::Char(US_SHIFT[index] as char);
+        index += 1;
+    }
+
+    keys[USAGE_NON_US_BACKSLASH][0] = Symbol::Char('\\');
+    keys[USAGE_NON_US_BACKSLASH][1] = Symbol::Char('|');
+    keys
+}
+
+impl Keymap {
+    /// The US layout, the only one built into the kernel
+    pub fn us() -> Self {
+        Self {
+            name: String::from("us"),
+            keys: us_keys(),
+            compose: Vec::new(),
+        }
+    }
+
+    /// Read the layout file `text`, as changes to the US layout
+    pub fn parse(name: &str, text: &str) -> Result<Self, ParseError> {
+        let mut keymap = Self::us();
+        keymap.name = String::from(name);
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |reason| ParseError {
+                line: index + 1,
+                reason,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("key") => {
+                    let usage = words
+                        .next()
+                        .and_then(parse_usage)
+                        .ok_or(error("expected a usage id below 0x65"))?;
+
+                    let mut levels = [Symbol::None; LEVELS];
+                    for level in levels.iter_mut() {
+                        let Some(word) = words.next() else {
+                            break;
+                        };
+                        *level = parse_symbol(word).ok_or(error("invalid symbol"))?;
+                    }
+
+                    if levels[0] == Symbol::None {
+                        return Err(error("expected what the key types"));
+                    }
+
+                    keymap.keys[usage] = levels;
+                }
+                Some("compose") => {
+                    let mut chars = [' '; 3];
+                    for ch in chars.iter_mut() {
+                        *ch = words
+                            .next()
+                            .and_then(parse_char)
+                            .ok_or(error("expected a dead key, base, and result"))?;
+                    }
+
+                    keymap.compose.push((chars[0], chars[1], chars[2]));
+                }
+                _ => return Err(error("lines start with `key` or `compose`")),
+            }
+
+            if words.next().is_some() {
+                return Err(error("too many symbols"));
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What the key `usage` types with `modifiers`
+    pub fn symbol(&self, usage: u8, modifiers: Modifiers) -> Symbol {
+        let Some(levels) = self.keys.get(usage as usize) else {
+            return Symbol::None;
+        };
+
+        let altgr = if modifiers.altgr { 2 } else { 0 };
+
+        // Caps Lock only shifts letters
+        let letter = matches!(levels[altgr], Symbol::Char(ch) if ch.is_alphabetic());
+        let shift = modifiers.shift != (modifiers.caps_lock && letter);
+
+        levels[altgr + shift as usize]
+    }
+
+    /// The character the accent of the dead key `dead` makes on `base`
+    pub fn compose(&self, dead: char, base: char) -> Option<char> {
+        if let Some(&(_, _, result)) = self
+            .compose
+            .iter()
+            .find(|&&(other_dead, other_base, _)| other_dead == dead && other_base == base)
+        {
+            return Some(result);
+        }
+
+        let &(_, bases, results) = BUILTIN_COMPOSE
+            .iter()
+            .find(|&&(other_dead, _, _)| other_dead == dead)?;
+        let index = bases.chars().position(|ch| ch == base)?;
+        results.chars().nth(index)
+    }
+
+    /// Type the key `usage`, giving each character it types to `emit`
+    ///
+    /// `dead_key` is the dead key waiting for the next key, a dead key that can't be
+    /// combined with the key after it types its accent on its own first.
+    pub fn type_key(
+        &self,
+        usage: u8,
+        modifiers: Modifiers,
+        dead_key: &mut Option<char>,
+        mut emit: impl FnMut(char),
+    ) {
+        if modifiers.ctrl {
+            let control = match self.symbol(usage, modifiers) {
+                Symbol::Char(ch) => control_char(ch),
+                _ => None,
+            };
+            if let Some(control) = control {
+                *dead_key = None;
+                emit(control);
+            }
+            return;
+        }
+
+        match (self.symbol(usage, modifiers), dead_key.take()) {
+            // Keys that type nothing leave the dead key waiting
+            (Symbol::None, dead) => *dead_key = dead,
+            (Symbol::Dead(accent), None) => *dead_key = Some(accent),
+            // Pressing a dead key twice types its accent
+            (Symbol::Dead(accent), Some(dead)) if accent == dead => emit(accent),
+            (Symbol::Dead(accent), Some(dead)) => {
+                emit(dead);
+                *dead_key = Some(accent);
+            }
+            (Symbol::Char(ch), None) => emit(ch),
+            (Symbol::Char(' '), Some(dead)) => emit(dead),
+            (Symbol::Char(ch), Some(dead)) => match self.compose(dead, ch) {
+                Some(composed) => emit(composed),
+                None => {
+                    emit(dead);
+                    emit(ch);
+                }
+            },
+        }
+    }
+}
+
+/// The control character Ctrl and `ch` types, for letters and the symbols around them
+fn control_char(ch: char) -> Option<char> {
+    match ch.to_ascii_uppercase() {
+        upper @ '@'..='_' => Some((upper as u8 & 0x1F) as char),
+        _ => None,
+    }
+}
+
+/// A usage id written in hex with `0x`, or in decimal
+fn parse_usage(word: &str) -> Option<usize> {
+    let usage = match word.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok()?,
+        None => word.parse().ok()?,
+    };
+
+    (usage < KEYS).then_some(usage)
+}
+
+fn parse_symbol(word: &str) -> Option<Symbol> {
+    if word == "none" {
+        return Some(Symbol::None);
+    }
+
+    match word.strip_prefix("dead:") {
+        Some(accent) => parse_char(accent).map(Symbol::Dead),
+        None => parse_char(word).map(Symbol::Char),
+    }
+}
+
+/// A single character, or `U+` and its code point in hex
+fn parse_char(word: &str) -> Option<char> {
+    if let Some(hex) = word.strip_prefix("U+") {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+    }
+
+    let mut chars = word.chars();
+    let ch = chars.next()?;
+    chars.next().is_none().then_some(ch)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    const USAGE_A: u8 = 0x04;
+    const USAGE_C: u8 = 0x06;
+    const USAGE_E: u8 = 0x08;
+    const USAGE_Y: u8 = 0x1C;
+    const USAGE_Z: u8 = 0x1D;
+    const USAGE_1: u8 = 0x1E;
+    const USAGE_SPACE: u8 = 0x2C;
+    const USAGE_ACUTE: u8 = 0x2E;
+
+    const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        altgr: false,
+        caps_lock: false,
+        ctrl: false,
+    };
+    const ALTGR: Modifiers = Modifiers {
+        shift: false,
+        altgr: true,
+        caps_lock: false,
+        ctrl: false,
+    };
+    const CAPS_LOCK: Modifiers = Modifiers {
+        shift: false,
+        altgr: false,
+        caps_lock: true,
+        ctrl: false,
+    };
+    const CTRL: Modifiers = Modifiers {
+        shift: false,
+        altgr: false,
+        caps_lock: false,
+        ctrl: true,
+    };
+
+    fn german() -> Keymap {
+        Keymap::parse("de", include_str!("../../../kernel/keymaps/de.keymap")).unwrap()
+    }
+
+    /// Type each of `keys` in turn, returning the text they made
+    fn type_keys(keymap: &Keymap, keys: &[(u8, Modifiers)]) -> Vec<char> {
+        let mut typed = Vec::new();
+        let mut dead_key = None;
+
+        for &(usage, modifiers) in keys {
+            keymap.type_key(usage, modifiers, &mut dead_key, |ch| typed.push(ch));
+        }
+
+        typed
+    }
+
+    #[test]
+    fn test_us_layout() {
+        let us = Keymap::us();
+        let plain = Modifiers::default();
+
+        assert_eq!(us.symbol(USAGE_A, plain), Symbol::Char('a'));
+        assert_eq!(us.symbol(USAGE_A, SHIFT), Symbol::Char('A'));
+        assert_eq!(us.symbol(USAGE_1, SHIFT), Symbol::Char('!'));
+        assert_eq!(us.symbol(USAGE_SPACE, plain), Symbol::Char(' '));
+        assert_eq!(us.symbol(USAGE_A, ALTGR), Symbol::None);
+        assert_eq!(us.symbol(0xFF, plain), Symbol::None);
+    }
+
+    #[test]
+    fn test_caps_lock_only_shifts_letters() {
+        let us = Keymap::us();
+
+        assert_eq!(us.symbol(USAGE_A, CAPS_LOCK), Symbol::Char('A'));
+        assert_eq!(us.symbol(USAGE_1, CAPS_LOCK), Symbol::Char('1'));
+        assert_eq!(
+            us.symbol(
+                USAGE_A,
+                Modifiers {
+                    shift: true,
+                    ..CAPS_LOCK
+                }
+            ),
+            Symbol::Char('a')
+        );
+    }
+
+    #[test]
+    fn test_german_layout() {
+        let de = german();
+
+        assert_eq!(de.name(), "de");
+        assert_eq!(de.symbol(USAGE_Y, Modifiers::default()), Symbol::Char('z'));
+        assert_eq!(de.symbol(USAGE_Z, SHIFT), Symbol::Char('Y'));
+        assert_eq!(de.symbol(USAGE_E, ALTGR), Symbol::Char('€'));
+        assert_eq!(de.symbol(USAGE_A, Modifiers::default()), Symbol::Char('a'));
+    }
+
+    #[test]
+    fn test_dead_keys() {
+        let de = german();
+        let plain = Modifiers::default();
+
+        // The accent goes on the next letter
+        assert_eq!(
+            type_keys(&de, &[(USAGE_ACUTE, plain), (USAGE_E, plain)]),
+            vec!['é']
+        );
+        assert_eq!(
+            type_keys(&de, &[(USAGE_ACUTE, SHIFT), (USAGE_A, SHIFT)]),
+            vec!['À']
+        );
+        // Twice, or before a space, types the accent on its own
+        assert_eq!(
+            type_keys(&de, &[(USAGE_ACUTE, plain), (USAGE_ACUTE, plain)]),
+            vec!['´']
+        );
+        assert_eq!(
+            type_keys(&de, &[(USAGE_ACUTE, plain), (USAGE_SPACE, plain)]),
+            vec!['´']
+        );
+        // Letters the accent can't go on are typed after it
+        assert_eq!(
+            type_keys(&de, &[(USAGE_ACUTE, plain), (USAGE_1, plain)]),
+            vec!['´', '1']
+        );
+    }
+
+    #[test]
+    fn test_compose_lines_come_first() {
+        let keymap = Keymap::parse("test", "key 0x04 dead:~\ncompose ~ e ẽ").unwrap();
+
+        assert_eq!(keymap.compose('~', 'e'), Some('ẽ'));
+        assert_eq!(keymap.compose('~', 'n'), Some('ñ'));
+        assert_eq!(keymap.compose('~', 'x'), None);
+    }
+
+    #[test]
+    fn test_ctrl_types_control_characters() {
+        let us = Keymap::us();
+        let de = german();
+
+        assert_eq!(type_keys(&us, &[(USAGE_C, CTRL)]), vec!['\x03']);
+        assert_eq!(
+            type_keys(
+                &us,
+                &[(
+                    USAGE_C,
+                    Modifiers {
+                        shift: true,
+                        ..CTRL
+                    }
+                )]
+            ),
+            vec!['\x03']
+        );
+        // The control character follows the layout, and keys without one type nothing
+        assert_eq!(type_keys(&de, &[(USAGE_Y, CTRL)]), vec!['\x1A']);
+        assert_eq!(type_keys(&us, &[(USAGE_1, CTRL)]), vec![]);
+        // A waiting dead key is dropped
+        assert_eq!(
+            type_keys(
+                &de,
+                &[
+                    (USAGE_ACUTE, Modifiers::default()),
+                    (USAGE_C, CTRL),
+                    (USAGE_E, Modifiers::default())
+                ]
+            ),
+            vec!['\x03', 'e']
+        );
+    }
+
+    #[test]
+    fn test_broken_layouts() {
+        let error = |text| Keymap::parse("broken", text).err().unwrap();
+
+        assert_eq!(error("key 0x65 a").line, 1);
+        assert_eq!(error("# comment\n\nkey 0x04").line, 3);
+        assert_eq!(error("key 0x04 ab").reason, "invalid symbol");
+        assert_eq!(error("key 0x04 a A b B c").reason, "too many symbols");
+        assert_eq!(error("key 0x04 U+D800").reason, "invalid symbol");
+        assert_eq!(
+            error("compose ~ n").reason,
+            "expected a dead key, base, and result"
+        );
+        assert_eq!(
+            error("keys 0x04 a").reason,
+            "lines start with `key` or `compose`"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+extern crate alloc;
+use crate::spin::mutex::SpinMutex;
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    marker::PhantomData,
+    ops::Deref,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// # Rcu Cell
+/// A read-mostly value that can be read without taking a lock, and replaced by writers.
+///
+/// Replaced values are not freed right away, as readers could still be looking at them.
+/// Instead they are retired with the epoch they were replaced in, and only freed once the
+/// epoch has advanced twice, which can only happen after every reader that could have seen
+/// them has finished.
+///
+/// Readers never wait, writers are serialized with each other but never wait on readers.
+pub struct RcuCell<T> {
+    current: AtomicPtr<T>,
+    epoch: AtomicUsize,
+    /// Readers pinned in an even, and odd, epoch
+    readers: [AtomicUsize; 2],
+    /// Values retired in an even, and odd, epoch
+    retired: SpinMutex<[Vec<Box<T>>; 2]>,
+    ph: PhantomData<Box<T>>,
+}
+
+unsafe impl<T: Send + Sync> Send for RcuCell<T> {}
+unsafe impl<T: Send + Sync> Sync for RcuCell<T> {}
+
+/// A reader of a [`RcuCell`], the value it points to will not be freed until it is dropped.
+pub struct RcuReadGuard<'a, T> {
+    readers: &'a AtomicUsize,
+    value: &'a T,
+}
+
+impl<T> RcuCell<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            current: AtomicPtr::new(Box::into_raw(Box::new(value))),
+            epoch: AtomicUsize::new(0),
+            readers: [const { AtomicUsize::new(0) }; 2],
+            retired: SpinMutex::new([Vec::new(), Vec::new()]),
+            ph: PhantomData,
+        }
+    }
+
+    /// # Read
+    /// Get the current value, without taking any locks.
+    ///
+    /// Writers can replace the value while this guard is held, but the value this guard
+    /// points to lives until it is dropped.
+    pub fn read<'a>(&'a self) -> RcuReadGuard<'a, T> {
+        let readers = loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let readers = &self.readers[epoch % 2];
+            readers.fetch_add(1, Ordering::SeqCst);
+
+            // If the epoch advanced before we were counted, a writer could have missed us
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                break readers;
+            }
+
+            readers.fetch_sub(1, Ordering::SeqCst);
+        };
+
+        RcuReadGuard {
+            readers,
+            value: unsafe { &*self.current.load(Ordering::Acquire) },
+        }
+    }
+
+    /// # Replace
+    /// Swap in a new value, retiring the old value so it is freed once no reader can see it.
+    pub fn replace(&self, value: T) {
+        let mut retired = self.retired.lock();
+        let old = self
+            .current
+            .swap(Box::into_raw(Box::new(value)), Ordering::AcqRel);
+
+        retired[self.epoch.load(Ordering::SeqCst) % 2].push(unsafe { Box::from_raw(old) });
+        Self::reclaim_locked(&self.epoch, &self.readers, &mut retired);
+    }
+
+    /// # Update
+    /// Replace the value with one made from the current value.
+    ///
+    /// Writers are serialized, so no other writer can replace the value while `f` runs.
+    pub fn update(&self, f: impl FnOnce(&T) -> T) {
+        let mut retired = self.retired.lock();
+        let current = self.current.load(Ordering::Acquire);
+        let new = Box::into_raw(Box::new(f(unsafe { &*current })));
+        self.current.store(new, Ordering::Release);
+
+        retired[self.epoch.load(Ordering::SeqCst) % 2].push(unsafe { Box::from_raw(current) });
+        Self::reclaim_locked(&self.epoch, &self.readers, &mut retired);
+    }
+
+    /// # Reclaim
+    /// Free every retired value no reader can still see, returning how many are left
+    /// waiting on readers.
+    ///
+    /// Writers already do this, but a structure that is rarely written can call it to free
+    /// its old values sooner.
+    pub fn reclaim(&self) -> usize {
+        let mut retired = self.retired.lock();
+        Self::reclaim_locked(&self.epoch, &self.readers, &mut retired);
+
+        retired.iter().map(|bag| bag.len()).sum()
+    }
+
+    fn reclaim_locked(
+        epoch: &AtomicUsize,
+        readers: &[AtomicUsize; 2],
+        retired: &mut [Vec<Box<T>>; 2],
+    ) {
+        // Values retired in this epoch can be seen by readers of this epoch, so we need to
+        // advance twice before they can be freed.
+        for _ in 0..2 {
+            let current = epoch.load(Ordering::SeqCst);
+
+            // The other counter holds the readers pinned in the previous epoch
+            if readers[(current + 1) % 2].load(Ordering::SeqCst) != 0 {
+                break;
+            }
+
+            epoch.store(current + 1, Ordering::SeqCst);
+
+            // Anything retired in the previous epoch was unlinked before any reader still
+            // pinned could have started.
+            retired[(current + 1) % 2].clear();
+        }
+    }
+}
+
+impl<T> Drop for RcuCell<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(*self.current.get_mut()) });
+    }
+}
+
+impl<'a, T> Deref for RcuReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<'a, T> Drop for RcuReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.readers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use alloc::sync::Arc;
+
+    /// Counts how many of itself are still alive
+    struct Counted(Arc<AtomicUsize>, usize);
+
+    impl Counted {
+        fn new(alive: &Arc<AtomicUsize>, value: usize) -> Self {
+            alive.fetch_add(1, Ordering::SeqCst);
+            Self(alive.clone(), value)
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_retired_values_outlive_readers() {
+        let alive = Arc::new(AtomicUsize::new(0));
+        let cell = RcuCell::new(Counted::new(&alive, 1));
+
+        let reader = cell.read();
+        cell.replace(Counted::new(&alive, 2));
+        cell.update(|old| Counted::new(&alive, old.1 + 1));
+
+        assert_eq!(reader.1, 1);
+        assert_eq!(cell.read().1, 3);
+        assert_eq!(alive.load(Ordering::SeqCst), 3);
+        assert_eq!(cell.reclaim(), 2);
+
+        drop(reader);
+        assert_eq!(cell.reclaim(), 0);
+        assert_eq!(alive.load(Ordering::SeqCst), 1);
+
+        drop(cell);
+        assert_eq!(alive.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_multi_threaded() {
+        let alive = Arc::new(AtomicUsize::new(0));
+        let cell = Arc::new(RcuCell::new(Counted::new(&alive, 0)));
+
+        let mut spawned_threads = std::vec::Vec::new();
+        for thread_number in 0..8 {
+            let cell = cell.clone();
+            let alive = alive.clone();
+            spawned_threads.push(std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    if thread_number % 4 == 0 {
+                        cell.update(|old| Counted::new(&alive, old.1 + 1));
+                    } else {
+                        let reader = cell.read();
+                        let seen = reader.1;
+                        std::thread::yield_now();
+                        assert_eq!(reader.1, seen);
+                    }
+                }
+            }));
+        }
+
+        for thread in spawned_threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(cell.read().1, 2000);
+        assert_eq!(cell.reclaim(), 0);
+        assert_eq!(alive.load(Ordering::SeqCst), 1);
+    }
+}
@@ -28,6 +28,7 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 pub mod atomic_arc;
 pub mod atomic_list;
 pub mod atomic_option;
+pub mod epoch;
 pub mod linkedlist;
 pub mod spin;
 pub mod sync;
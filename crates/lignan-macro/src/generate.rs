@@ -153,26 +153,40 @@ fn is_type_option(debug_type: &Type) -> bool {
 pub fn generate_init_function(macro_input: &DebugMacroInput) -> proc_macro2::TokenStream {
     // FIXME: We should only do one call to `static_stream_var_name` per stream, however, this
     // is eaiser for now.
-    let stream_outputs: Vec<proc_macro2::TokenStream> = macro_input
-        .streams
-        .iter()
-        .enumerate()
-        .map(|(count, stream)| {
-            let stream_name = Ident::new(&static_stream_var_name(count, stream), Span::call_site());
-            let is_option = is_type_option(&stream.debug_type);
-
-            generate_print_each(&stream_name, is_option)
-        })
-        .collect();
+    let (stream_outputs, stream_print_fns): (Vec<proc_macro2::TokenStream>, Vec<Ident>) =
+        macro_input
+            .streams
+            .iter()
+            .enumerate()
+            .map(|(count, stream)| {
+                let stream_name =
+                    Ident::new(&static_stream_var_name(count, stream), Span::call_site());
+                let print_fn =
+                    format_ident!("{}_print", stream_name.to_string().to_ascii_lowercase());
+                let is_option = is_type_option(&stream.debug_type);
+                let print_each = generate_print_each(&stream_name, is_option);
+
+                (
+                    quote! {
+                        fn #print_fn(args: ::core::fmt::Arguments) {
+                            use ::core::fmt::Write;
+                            #print_each
+                        }
+                    },
+                    print_fn,
+                )
+            })
+            .unzip();
 
     quote_spanned! {Span::call_site()=>
-        fn all_print(args: ::core::fmt::Arguments) {
-            use ::core::fmt::Write;
-            #(#stream_outputs)*
-        }
+        #(#stream_outputs)*
 
         pub(crate) fn debug_macro_init() {
-            ::lignan::set_global_debug_fn(all_print);
+            #(
+                ::lignan::stream::add_stream_connection(
+                    ::lignan::stream::StreamConnection::new(#stream_print_fns)
+                );
+            )*
         }
     }
 }
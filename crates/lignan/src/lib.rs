@@ -32,39 +32,48 @@ use core::sync::atomic::Ordering;
 // Re-export the macro
 pub use lignan_macro::debug_ready;
 pub use lignan_macro::make_debug;
-use lock::DebugMutex;
 use lock::DEBUG_LOCKS;
 use lock::UNLOCK_OVERRIDE;
+use stream::StreamConnection;
 
 pub mod color;
 pub mod hexdump;
 pub mod lock;
+pub mod ring;
+pub mod stream;
 
+/// The level of a message, ordered from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogKind {
     Log,
     Warn,
     Error,
 }
 
+impl LogKind {
+    /// The style and marker printed at the start of each line
+    fn marker(&self) -> (&'static str, char) {
+        match self {
+            LogKind::Log => (color::LOG_STYLE, '+'),
+            LogKind::Warn => (color::WARN_STYLE, '-'),
+            LogKind::Error => (color::ERR_STYLE, 'X'),
+        }
+    }
+}
+
 pub type OutputFn = fn(core::fmt::Arguments);
 
 static REQUIRES_HEADER_PRINT: AtomicBool = AtomicBool::new(true);
-static GLOBAL_PRINT_FN: DebugMutex<Option<OutputFn>> = DebugMutex::new(None);
-
-fn raw_print(args: core::fmt::Arguments) {
-    let Some(ptr) = GLOBAL_PRINT_FN.try_lock() else {
-        return;
-    };
-
-    if let Some(inner) = *ptr {
-        inner(args);
-    }
-}
 
+/// Set the primary output for debug messages.
+///
+/// This replaces the connection in the first stream slot, see [`stream`] for attaching
+/// more than one output.
 pub fn set_global_debug_fn(function: OutputFn) {
-    *GLOBAL_PRINT_FN
-        .try_lock()
-        .expect("Unable to lock when setting function") = Some(function);
+    assert!(
+        stream::set_primary_connection(StreamConnection::new(function)),
+        "Unable to lock when setting function"
+    );
 }
 
 /// Forces all `DebugMutex`'s to unlock, allowing to provide debug output again. This
@@ -98,27 +107,10 @@ impl core::fmt::Write for PrettyOutput<'_> {
             c => {
                 if REQUIRES_HEADER_PRINT.load(Ordering::Relaxed) {
                     REQUIRES_HEADER_PRINT.store(false, Ordering::Relaxed);
-                    match self.kind {
-                        LogKind::Log => {
-                            raw_print(format_args!("\n{}+{}", color::LOG_STYLE, color::RESET))
-                        }
-                        LogKind::Warn => {
-                            raw_print(format_args!("\n{}-{}", color::WARN_STYLE, color::RESET))
-                        }
-                        LogKind::Error => {
-                            raw_print(format_args!("\n{}X{}", color::ERR_STYLE, color::RESET))
-                        }
-                    }
-
-                    raw_print(format_args!(
-                        "{}{:<30}{} : ",
-                        color::DIM_STYLE,
-                        self.crate_name,
-                        color::RESET
-                    ));
+                    stream::stream_print_header(self.kind, self.crate_name);
                 }
 
-                raw_print(format_args!("{}", c));
+                stream::stream_print(self.kind, format_args!("{}", c));
             }
         }
 
@@ -193,8 +185,8 @@ macro_rules! testing_stdout {
         {
             fn all_print(args: ::core::fmt::Arguments) {
                 extern crate std;
-                use std::io::stdout;
                 use std::io::Write;
+                use std::io::stdout;
                 let _ = stdout().write_fmt(args);
             }
 
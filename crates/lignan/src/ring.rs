@@ -0,0 +1,328 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! A ring of log bytes in shared memory.
+//!
+//! The kernel writes its log into a `LogRing`, and a logger process maps the same memory
+//! to read it back without making a syscall for every line. There is one writer and one
+//! reader, so neither side needs a lock: the writer only moves `head`, and the reader only
+//! moves `tail`.
+//!
+//! The reader can write anything into the shared header, so the writer keeps its own copy
+//! of `capacity` and `head`, and only trusts `tail` once it is clamped to what could have
+//! been read.
+//!
+//! The writer bumps the ring's futex word after each write, so the reader can sleep on it
+//! while the ring is empty.
+
+use core::{
+    mem::offset_of,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+};
+
+/// Marks memory that was set up with [`LogRing::init`]
+pub const LOG_RING_MAGIC: u32 = u32::from_le_bytes(*b"KLOG");
+
+/// The start of a log ring's memory, the ring's bytes follow right after it
+#[repr(C)]
+#[derive(Debug)]
+struct LogRingHeader {
+    magic: u32,
+    /// The number of bytes in the ring
+    capacity: u32,
+    /// How many bytes were ever written, only the writer moves this
+    head: AtomicU64,
+    /// How many bytes were ever read, only the reader moves this
+    tail: AtomicU64,
+    /// How many bytes were thrown away because the ring was full
+    dropped: AtomicU64,
+    /// Bumped after every write
+    futex: AtomicU32,
+}
+
+/// A view of a log ring living in (possibly shared) memory
+#[derive(Debug)]
+pub struct LogRing {
+    header: *const LogRingHeader,
+    data: *mut u8,
+    capacity: usize,
+    /// How many bytes were written, the writer's own copy of the header's `head`
+    head: AtomicU64,
+}
+
+unsafe impl Send for LogRing {}
+unsafe impl Sync for LogRing {}
+
+impl LogRing {
+    /// The number of bytes before the ring's data starts
+    pub const HEADER_SIZE: usize = size_of::<LogRingHeader>().next_multiple_of(64);
+    /// The offset of the futex word from the start of the ring's memory
+    pub const FUTEX_OFFSET: usize = offset_of!(LogRingHeader, futex);
+
+    /// Set up an empty ring in the `len` bytes at `ptr`.
+    ///
+    /// Returns `None` if `len` is too small to hold any bytes. The writer must keep using
+    /// the ring returned from here, as a ring from [`LogRing::from_raw`] believes the header.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for `len` bytes, aligned to 8 bytes, and nothing else can use it
+    /// while it is being set up.
+    pub unsafe fn init(ptr: *mut u8, len: usize) -> Option<Self> {
+        let capacity = len.checked_sub(Self::HEADER_SIZE)?.min(u32::MAX as usize);
+        if capacity == 0 {
+            return None;
+        }
+
+        unsafe {
+            ptr.cast::<LogRingHeader>().write(LogRingHeader {
+                magic: LOG_RING_MAGIC,
+                capacity: capacity as u32,
+                head: AtomicU64::new(0),
+                tail: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+                futex: AtomicU32::new(0),
+            });
+        }
+
+        Some(Self {
+            header: ptr.cast::<LogRingHeader>().cast_const(),
+            data: unsafe { ptr.add(Self::HEADER_SIZE) },
+            capacity,
+            head: AtomicU64::new(0),
+        })
+    }
+
+    /// Use a ring that was set up with [`LogRing::init`] in the `len` bytes at `ptr`.
+    ///
+    /// Returns `None` if the memory does not hold a ring.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for `len` bytes and aligned to 8 bytes for as long as the ring
+    /// is used.
+    pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> Option<Self> {
+        if len <= Self::HEADER_SIZE {
+            return None;
+        }
+
+        let header = ptr.cast::<LogRingHeader>().cast_const();
+        let (magic, capacity) = unsafe { ((*header).magic, (*header).capacity as usize) };
+        if magic != LOG_RING_MAGIC || capacity > len - Self::HEADER_SIZE {
+            return None;
+        }
+
+        Some(Self {
+            header,
+            data: unsafe { ptr.add(Self::HEADER_SIZE) },
+            capacity,
+            head: AtomicU64::new(unsafe { (*header).head.load(Ordering::Acquire) }),
+        })
+    }
+
+    fn header(&self) -> &LogRingHeader {
+        unsafe { &*self.header }
+    }
+
+    /// Append all of `bytes` to the ring.
+    ///
+    /// If they don't fit, nothing is written and they are counted as dropped instead.
+    /// Only one writer may use a ring.
+    pub fn write(&self, bytes: &[u8]) -> bool {
+        let header = self.header();
+        let head = self.head.load(Ordering::Relaxed);
+
+        // The reader can only have read what was written, and can't be further behind than
+        // the ring holds
+        let tail = header
+            .tail
+            .load(Ordering::Acquire)
+            .clamp(head.saturating_sub(self.capacity as u64), head);
+
+        let free = self.capacity - (head - tail) as usize;
+        if bytes.len() > free {
+            header
+                .dropped
+                .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+            return false;
+        }
+
+        let start = (head % self.capacity as u64) as usize;
+        let first_len = bytes.len().min(self.capacity - start);
+        unsafe {
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), self.data.add(start), first_len);
+            core::ptr::copy_nonoverlapping(
+                bytes[first_len..].as_ptr(),
+                self.data,
+                bytes.len() - first_len,
+            );
+        }
+
+        self.head
+            .store(head + bytes.len() as u64, Ordering::Relaxed);
+        header
+            .head
+            .store(head + bytes.len() as u64, Ordering::Release);
+        header.futex.fetch_add(1, Ordering::Release);
+        true
+    }
+
+    /// Move as many unread bytes as fit into `buf`, returning how many were read.
+    ///
+    /// Only one reader may use a ring.
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        let header = self.header();
+        let tail = header.tail.load(Ordering::Relaxed);
+        let head = header.head.load(Ordering::Acquire);
+
+        let len = ((head - tail) as usize).min(buf.len());
+        let start = (tail % self.capacity as u64) as usize;
+        let first_len = len.min(self.capacity - start);
+        unsafe {
+            core::ptr::copy_nonoverlapping(self.data.add(start), buf.as_mut_ptr(), first_len);
+            core::ptr::copy_nonoverlapping(
+                self.data,
+                buf[first_len..].as_mut_ptr(),
+                len - first_len,
+            );
+        }
+
+        header.tail.store(tail + len as u64, Ordering::Release);
+        len
+    }
+
+    /// Are there no bytes waiting to be read?
+    pub fn is_empty(&self) -> bool {
+        let header = self.header();
+        header.head.load(Ordering::Acquire) == header.tail.load(Ordering::Acquire)
+    }
+
+    /// The number of bytes the ring can hold
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// How many bytes were thrown away because the reader fell behind
+    pub fn dropped(&self) -> u64 {
+        self.header().dropped.load(Ordering::Relaxed)
+    }
+
+    /// The word the reader can wait on with a futex, it changes after every write
+    pub fn futex(&self) -> &AtomicU32 {
+        &self.header().futex
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use std::vec;
+
+    fn ring_memory(capacity: usize) -> std::vec::Vec<u64> {
+        vec![0; (LogRing::HEADER_SIZE + capacity).div_ceil(8)]
+    }
+
+    #[test]
+    fn write_then_read() {
+        let mut memory = ring_memory(16);
+        let ring = unsafe { LogRing::init(memory.as_mut_ptr().cast(), memory.len() * 8) }.unwrap();
+
+        assert!(ring.is_empty());
+        assert!(ring.write(b"hello"));
+
+        let mut buf = [0; 8];
+        assert_eq!(ring.read(&mut buf), 5);
+        assert_eq!(&buf[..5], b"hello");
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut memory = ring_memory(16);
+        let ring = unsafe { LogRing::init(memory.as_mut_ptr().cast(), memory.len() * 8) }.unwrap();
+        let mut buf = [0; 16];
+
+        for round in 0..10u8 {
+            let bytes = [round; 7];
+            assert!(ring.write(&bytes));
+            assert_eq!(ring.read(&mut buf), 7);
+            assert_eq!(&buf[..7], &bytes);
+        }
+    }
+
+    #[test]
+    fn drops_when_full() {
+        let mut memory = ring_memory(16);
+        let ring = unsafe { LogRing::init(memory.as_mut_ptr().cast(), memory.len() * 8) }.unwrap();
+        let capacity = ring.capacity();
+
+        assert!(ring.write(&vec![1; capacity]));
+        assert!(!ring.write(b"more"));
+        assert_eq!(ring.dropped(), 4);
+
+        let mut buf = vec![0; capacity];
+        assert_eq!(ring.read(&mut buf), capacity);
+        assert!(ring.write(b"more"));
+    }
+
+    #[test]
+    fn futex_changes_on_write() {
+        let mut memory = ring_memory(16);
+        let ring = unsafe { LogRing::init(memory.as_mut_ptr().cast(), memory.len() * 8) }.unwrap();
+
+        let before = ring.futex().load(Ordering::Relaxed);
+        ring.write(b"x");
+        assert_ne!(ring.futex().load(Ordering::Relaxed), before);
+    }
+
+    #[test]
+    fn ignores_a_broken_header() {
+        let mut memory = ring_memory(16);
+        let ring = unsafe { LogRing::init(memory.as_mut_ptr().cast(), memory.len() * 8) }.unwrap();
+        let capacity = ring.capacity();
+
+        // The reader claims the ring is huge and that it read more than was ever written
+        let header = memory.as_mut_ptr().cast::<LogRingHeader>();
+        unsafe {
+            (*header).capacity = u32::MAX;
+            (*header).head.store(0, Ordering::Relaxed);
+            (*header).tail.store(u64::MAX, Ordering::Relaxed);
+        }
+
+        // Reading past `head` counts as having read everything, but never more
+        assert!(ring.write(&vec![1; capacity]));
+        assert!(ring.write(&vec![2; capacity]));
+        assert!(!ring.write(&vec![3; capacity + 1]));
+        assert_eq!(ring.capacity(), capacity);
+    }
+
+    #[test]
+    fn rejects_other_memory() {
+        let mut memory = ring_memory(16);
+        assert!(
+            unsafe { LogRing::from_raw(memory.as_mut_ptr().cast(), memory.len() * 8) }.is_none()
+        );
+    }
+}
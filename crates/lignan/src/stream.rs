@@ -0,0 +1,392 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{LogKind, OutputFn, lock::DebugMutex};
+use core::{fmt::Write, time::Duration};
+
+/// The max number of stream connections that can be attached at once.
+pub const MAX_STREAM_CONNECTIONS: usize = 8;
+/// The number of bytes kept from before any connection was attached.
+pub const EARLY_LOG_LEN: usize = 4096;
+
+/// A single output sink for debug messages.
+#[derive(Clone, Copy)]
+pub struct StreamConnection {
+    output: OutputFn,
+    min_level: LogKind,
+    timestamps: bool,
+    wall_time: bool,
+    deltas: bool,
+    cpu_id: bool,
+}
+
+/// The slot a `StreamConnection` was attached to.
+///
+/// Slots never move, so an id stays valid until its connection is removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamId(usize);
+
+static STREAM_CONNECTIONS: DebugMutex<[Option<StreamConnection>; MAX_STREAM_CONNECTIONS]> =
+    DebugMutex::new([None; MAX_STREAM_CONNECTIONS]);
+static EARLY_LOG: DebugMutex<EarlyLog> = DebugMutex::new(EarlyLog::new());
+static TIMESTAMP_FN: DebugMutex<Option<fn() -> Duration>> = DebugMutex::new(None);
+static WALL_CLOCK_FN: DebugMutex<Option<fn() -> u64>> = DebugMutex::new(None);
+static LAST_TIMESTAMP: DebugMutex<Option<Duration>> = DebugMutex::new(None);
+static CPU_ID_FN: DebugMutex<Option<fn() -> usize>> = DebugMutex::new(None);
+
+impl StreamConnection {
+    /// Make a new connection that prints all messages, without any prefix.
+    pub const fn new(output: OutputFn) -> Self {
+        Self {
+            output,
+            min_level: LogKind::Log,
+            timestamps: false,
+            wall_time: false,
+            deltas: false,
+            cpu_id: false,
+        }
+    }
+
+    /// Only print messages at or above this level.
+    pub const fn with_min_level(mut self, level: LogKind) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    /// Prefix each message with the time from the provider set by [`set_timestamp_fn`].
+    pub const fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Prefix each message with the time of day from the clock set by [`set_wall_clock_fn`].
+    pub const fn with_wall_time(mut self, enabled: bool) -> Self {
+        self.wall_time = enabled;
+        self
+    }
+
+    /// Prefix each message with the time since the previous message.
+    pub const fn with_deltas(mut self, enabled: bool) -> Self {
+        self.deltas = enabled;
+        self
+    }
+
+    /// Prefix each message with the cpu id from the provider set by [`set_cpu_id_fn`].
+    pub const fn with_cpu_id(mut self, enabled: bool) -> Self {
+        self.cpu_id = enabled;
+        self
+    }
+}
+
+/// A ring buffer of messages printed before any connection was attached.
+///
+/// Once full, the oldest bytes are overwritten.
+struct EarlyLog {
+    buffer: [u8; EARLY_LOG_LEN],
+    start: usize,
+    len: usize,
+}
+
+impl EarlyLog {
+    const fn new() -> Self {
+        Self {
+            buffer: [0; EARLY_LOG_LEN],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// The buffered bytes, oldest first
+    fn slices(&self) -> (&[u8], &[u8]) {
+        let end = self.start + self.len;
+        if end <= EARLY_LOG_LEN {
+            (&self.buffer[self.start..end], &[])
+        } else {
+            (
+                &self.buffer[self.start..],
+                &self.buffer[..end - EARLY_LOG_LEN],
+            )
+        }
+    }
+
+    /// Print the buffered messages into `output`
+    fn replay(&self, output: OutputFn) {
+        let (first, second) = self.slices();
+        for chunk in first.utf8_chunks().chain(second.utf8_chunks()) {
+            output(format_args!("{}", chunk.valid()));
+        }
+    }
+}
+
+impl core::fmt::Write for EarlyLog {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for &byte in s.as_bytes() {
+            if self.len == EARLY_LOG_LEN {
+                self.buffer[self.start] = byte;
+                self.start = (self.start + 1) % EARLY_LOG_LEN;
+            } else {
+                self.buffer[(self.start + self.len) % EARLY_LOG_LEN] = byte;
+                self.len += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Attach a new connection to the first free slot.
+///
+/// Anything printed before the first connection was attached gets replayed into the new
+/// connection. Returns `None` if all slots are taken.
+pub fn add_stream_connection(connection: StreamConnection) -> Option<StreamId> {
+    let mut connections = STREAM_CONNECTIONS.try_lock()?;
+    let (index, slot) = connections
+        .iter_mut()
+        .enumerate()
+        .find(|(_, slot)| slot.is_none())?;
+
+    *slot = Some(connection);
+
+    if let Some(early_log) = EARLY_LOG.try_lock() {
+        early_log.replay(connection.output);
+    }
+
+    Some(StreamId(index))
+}
+
+/// Detach a connection, returning it if it existed.
+pub fn remove_stream_connection(id: StreamId) -> Option<StreamConnection> {
+    STREAM_CONNECTIONS.try_lock()?.get_mut(id.0)?.take()
+}
+
+/// Replace the options of an attached connection.
+pub fn update_stream_connection(
+    id: StreamId,
+    f: impl FnOnce(StreamConnection) -> StreamConnection,
+) -> bool {
+    let Some(mut connections) = STREAM_CONNECTIONS.try_lock() else {
+        return false;
+    };
+
+    match connections.get_mut(id.0) {
+        Some(Some(connection)) => {
+            *connection = f(*connection);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Replace the options of every attached connection.
+pub fn update_all_stream_connections(f: impl Fn(StreamConnection) -> StreamConnection) {
+    let Some(mut connections) = STREAM_CONNECTIONS.try_lock() else {
+        return;
+    };
+
+    connections
+        .iter_mut()
+        .flatten()
+        .for_each(|connection| *connection = f(*connection));
+}
+
+/// Replace the connection in the first slot.
+pub(crate) fn set_primary_connection(connection: StreamConnection) -> bool {
+    let Some(mut connections) = STREAM_CONNECTIONS.try_lock() else {
+        return false;
+    };
+
+    if connections[0].is_none()
+        && let Some(early_log) = EARLY_LOG.try_lock()
+    {
+        early_log.replay(connection.output);
+    }
+
+    connections[0] = Some(connection);
+    true
+}
+
+/// Buffer this message if there are no connections to print to.
+///
+/// Returns `true` if the message was buffered.
+fn early_print(
+    connections: &[Option<StreamConnection>; MAX_STREAM_CONNECTIONS],
+    args: core::fmt::Arguments,
+) -> bool {
+    if connections.iter().any(|connection| connection.is_some()) {
+        return false;
+    }
+
+    if let Some(mut early_log) = EARLY_LOG.try_lock() {
+        let _ = early_log.write_fmt(args);
+    }
+
+    true
+}
+
+/// Set the monotonic clock used for timestamps.
+pub fn set_timestamp_fn(function: fn() -> Duration) {
+    if let Some(mut timestamp_fn) = TIMESTAMP_FN.try_lock() {
+        *timestamp_fn = Some(function);
+    }
+}
+
+/// Set the wall clock used for the time of day, in seconds since the unix epoch.
+///
+/// This is usually set once the RTC has been read.
+pub fn set_wall_clock_fn(function: fn() -> u64) {
+    if let Some(mut wall_clock_fn) = WALL_CLOCK_FN.try_lock() {
+        *wall_clock_fn = Some(function);
+    }
+}
+
+/// Set the function used to get the id of the current cpu.
+pub fn set_cpu_id_fn(function: fn() -> usize) {
+    if let Some(mut cpu_id_fn) = CPU_ID_FN.try_lock() {
+        *cpu_id_fn = Some(function);
+    }
+}
+
+/// Print to each connection that accepts messages of `kind`.
+pub(crate) fn stream_print(kind: LogKind, args: core::fmt::Arguments) {
+    let Some(connections) = STREAM_CONNECTIONS.try_lock() else {
+        return;
+    };
+
+    if early_print(&connections, args) {
+        return;
+    }
+
+    connections
+        .iter()
+        .flatten()
+        .filter(|connection| kind >= connection.min_level)
+        .for_each(|connection| (connection.output)(args));
+}
+
+/// Print the start of a new line to each connection that accepts messages of `kind`.
+///
+/// Each connection gets its own prefix depending on if it wants timestamps or the cpu id.
+pub(crate) fn stream_print_header(kind: LogKind, crate_name: &str) {
+    let Some(connections) = STREAM_CONNECTIONS.try_lock() else {
+        return;
+    };
+
+    let timestamp = TIMESTAMP_FN
+        .try_lock()
+        .and_then(|timestamp_fn| *timestamp_fn)
+        .map(|timestamp_fn| timestamp_fn());
+    let wall_time = WALL_CLOCK_FN
+        .try_lock()
+        .and_then(|wall_clock_fn| *wall_clock_fn)
+        .map(|wall_clock_fn| wall_clock_fn());
+    let delta = timestamp
+        .zip(LAST_TIMESTAMP.try_lock())
+        .map(|(timestamp, mut last)| {
+            let delta = timestamp.saturating_sub(last.unwrap_or(timestamp));
+            *last = Some(timestamp);
+            delta
+        });
+    let cpu_id = CPU_ID_FN
+        .try_lock()
+        .and_then(|cpu_id_fn| *cpu_id_fn)
+        .map(|cpu_id_fn| cpu_id_fn());
+
+    let (marker_style, marker) = kind.marker();
+    if early_print(
+        &connections,
+        format_args!(
+            "\n{}{}{}{}{:<30}{} : ",
+            marker_style,
+            marker,
+            crate::color::RESET,
+            crate::color::DIM_STYLE,
+            crate_name,
+            crate::color::RESET
+        ),
+    ) {
+        return;
+    }
+
+    for connection in connections
+        .iter()
+        .flatten()
+        .filter(|connection| kind >= connection.min_level)
+    {
+        (connection.output)(format_args!(
+            "\n{}{}{}",
+            marker_style,
+            marker,
+            crate::color::RESET
+        ));
+
+        if let (true, Some(timestamp)) = (connection.timestamps, timestamp) {
+            (connection.output)(format_args!(
+                "{}[{:>5}.{:03}]{} ",
+                crate::color::DIM_STYLE,
+                timestamp.as_secs(),
+                timestamp.subsec_millis(),
+                crate::color::RESET
+            ));
+        }
+
+        if let (true, Some(wall_time)) = (connection.wall_time, wall_time) {
+            let time_of_day = wall_time % 86400;
+            (connection.output)(format_args!(
+                "{}{:02}:{:02}:{:02}{} ",
+                crate::color::DIM_STYLE,
+                time_of_day / 3600,
+                (time_of_day / 60) % 60,
+                time_of_day % 60,
+                crate::color::RESET
+            ));
+        }
+
+        if let (true, Some(delta)) = (connection.deltas, delta) {
+            (connection.output)(format_args!(
+                "{}(+{:>3}.{:03}){} ",
+                crate::color::DIM_STYLE,
+                delta.as_secs(),
+                delta.subsec_millis(),
+                crate::color::RESET
+            ));
+        }
+
+        if let (true, Some(cpu_id)) = (connection.cpu_id, cpu_id) {
+            (connection.output)(format_args!(
+                "{}cpu{:<2}{} ",
+                crate::color::DIM_STYLE,
+                cpu_id,
+                crate::color::RESET
+            ));
+        }
+
+        (connection.output)(format_args!(
+            "{}{:<30}{} : ",
+            crate::color::DIM_STYLE,
+            crate_name,
+            crate::color::RESET
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+
+use crate::{HEADER_LEN, MAGIC, MAX_MATCH, MIN_MATCH, WINDOW_SIZE};
+
+/// The amount of previous positions checked for each match.
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: usize = 14;
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+    (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Compress `input` into the lzss format.
+///
+/// # Panics
+/// `input` must be smaller than 4Gib.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let input_len: u32 = input.len().try_into().expect("Input too large to compress");
+
+    let mut output = Vec::with_capacity(HEADER_LEN + input.len() + input.len() / 8 + 1);
+    output.extend_from_slice(&MAGIC);
+    output.extend_from_slice(&input_len.to_le_bytes());
+
+    // Hash chains of previous positions with the same 3 byte prefix
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; input.len()];
+
+    let mut pos = 0;
+    let mut flag_index = 0;
+    let mut item = 8;
+
+    while pos < input.len() {
+        if item == 8 {
+            flag_index = output.len();
+            output.push(0);
+            item = 0;
+        }
+
+        let (best_len, best_distance) = find_match(input, pos, &head, &prev);
+
+        if best_len >= MIN_MATCH {
+            let encoded = (((best_distance - 1) << 4) | (best_len - MIN_MATCH)) as u16;
+            output.extend_from_slice(&encoded.to_le_bytes());
+
+            for i in pos..(pos + best_len) {
+                insert(input, i, &mut head, &mut prev);
+            }
+            pos += best_len;
+        } else {
+            output[flag_index] |= 1 << item;
+            output.push(input[pos]);
+
+            insert(input, pos, &mut head, &mut prev);
+            pos += 1;
+        }
+
+        item += 1;
+    }
+
+    output
+}
+
+/// Add `pos` to the hash chains.
+fn insert(input: &[u8], pos: usize, head: &mut [usize], prev: &mut [usize]) {
+    if pos + MIN_MATCH <= input.len() {
+        let h = hash(&input[pos..]);
+        prev[pos] = head[h];
+        head[h] = pos;
+    }
+}
+
+/// Find the longest match for `pos` in the window, returning its `(length, distance)`.
+fn find_match(input: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if pos + MIN_MATCH > input.len() {
+        return (0, 0);
+    }
+
+    let max_len = MAX_MATCH.min(input.len() - pos);
+    let mut candidate = head[hash(&input[pos..])];
+    let mut best = (0, 0);
+
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || pos - candidate > WINDOW_SIZE {
+            break;
+        }
+
+        let len = input[candidate..]
+            .iter()
+            .zip(&input[pos..pos + max_len])
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if len > best.0 {
+            best = (len, pos - candidate);
+
+            if len == max_len {
+                break;
+            }
+        }
+
+        candidate = prev[candidate];
+    }
+
+    best
+}
@@ -0,0 +1,231 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! # LZSS
+//! A tiny LZSS compression format used to shrink boot artifacts.
+//!
+//! # Format
+//! Compressed data starts with a [`MAGIC`] followed by the decompressed length as a
+//! little-endian `u32`. After the header, every flag byte describes the next 8 items
+//! (LSB first). A set bit is a literal byte, and a clear bit is a 2-byte little-endian
+//! match of `(distance - 1) << 4 | (length - MIN_MATCH)`.
+
+#![no_std]
+
+use core::fmt::Display;
+
+#[cfg(any(test, feature = "alloc"))]
+mod compress;
+#[cfg(any(test, feature = "alloc"))]
+pub use compress::compress;
+
+/// The magic bytes at the start of compressed data.
+pub const MAGIC: [u8; 4] = *b"QLZS";
+/// The size of the header before the compressed stream.
+pub const HEADER_LEN: usize = MAGIC.len() + size_of::<u32>();
+/// The furthest back a match can refer to.
+pub const WINDOW_SIZE: usize = 1 << 12;
+/// The shortest match that will be encoded.
+pub const MIN_MATCH: usize = 3;
+/// The longest match that can be encoded.
+pub const MAX_MATCH: usize = MIN_MATCH + 0xF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LzssError {
+    /// The input does not start with a valid header.
+    InvalidHeader,
+    /// The output buffer cannot fit the decompressed data.
+    OutputTooSmall,
+    /// The input ended in the middle of the stream.
+    Truncated,
+    /// A match refers to data before the start of the output.
+    InvalidDistance,
+}
+
+impl core::error::Error for LzssError {}
+impl Display for LzssError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LzssError::InvalidHeader => write!(f, "invalid lzss header"),
+            LzssError::OutputTooSmall => write!(f, "output buffer is too small"),
+            LzssError::Truncated => write!(f, "compressed stream is truncated"),
+            LzssError::InvalidDistance => write!(f, "match refers to data out of range"),
+        }
+    }
+}
+
+/// Check if `input` starts with a compressed header.
+pub fn is_compressed(input: &[u8]) -> bool {
+    input.starts_with(&MAGIC) && input.len() >= HEADER_LEN
+}
+
+/// Get the length `input` will be once decompressed.
+pub fn decompressed_len(input: &[u8]) -> Result<usize, LzssError> {
+    if !is_compressed(input) {
+        return Err(LzssError::InvalidHeader);
+    }
+
+    let mut len = [0; 4];
+    len.copy_from_slice(&input[MAGIC.len()..HEADER_LEN]);
+
+    Ok(u32::from_le_bytes(len) as usize)
+}
+
+/// Decompress `input` into `output`, returning the amount of bytes written.
+pub fn decompress(input: &[u8], output: &mut [u8]) -> Result<usize, LzssError> {
+    let expected_len = decompressed_len(input)?;
+    if expected_len > output.len() {
+        return Err(LzssError::OutputTooSmall);
+    }
+
+    let mut input = input[HEADER_LEN..].iter().copied();
+    let mut written = 0;
+
+    while written < expected_len {
+        let flags = input.next().ok_or(LzssError::Truncated)?;
+
+        for bit in 0..8 {
+            if written >= expected_len {
+                break;
+            }
+
+            if flags & (1 << bit) != 0 {
+                output[written] = input.next().ok_or(LzssError::Truncated)?;
+                written += 1;
+                continue;
+            }
+
+            let low = input.next().ok_or(LzssError::Truncated)?;
+            let high = input.next().ok_or(LzssError::Truncated)?;
+            let encoded = u16::from_le_bytes([low, high]) as usize;
+
+            let distance = (encoded >> 4) + 1;
+            let len = (encoded & 0xF) + MIN_MATCH;
+
+            if distance > written {
+                return Err(LzssError::InvalidDistance);
+            }
+            if written + len > expected_len {
+                return Err(LzssError::OutputTooSmall);
+            }
+
+            // Matches can overlap with the bytes they produce, so copy byte by byte
+            for i in written..(written + len) {
+                output[i] = output[i - distance];
+            }
+            written += len;
+        }
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate alloc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn round_trip(data: &[u8]) {
+        let compressed = compress(data);
+        assert_eq!(decompressed_len(&compressed), Ok(data.len()));
+
+        let mut output = vec![0; data.len()];
+        assert_eq!(decompress(&compressed, &mut output), Ok(data.len()));
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn test_empty() {
+        round_trip(&[]);
+    }
+
+    #[test]
+    fn test_repeating() {
+        round_trip(&[0xAA; 10_000]);
+        round_trip(b"abcabcabcabcabcabcabcabcabcabcabcabcabc");
+
+        assert!(compress(&[0; 10_000]).len() < 2_000);
+    }
+
+    #[test]
+    fn test_pseudo_random() {
+        let mut state = 0x1234_5678_u32;
+        let data: Vec<u8> = (0..50_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                // Keep a small alphabet so there are matches to find
+                (state % 7) as u8
+            })
+            .collect();
+
+        round_trip(&data);
+    }
+
+    #[test]
+    fn test_output_too_small() {
+        let compressed = compress(b"hello world, hello world");
+        let mut output = [0; 4];
+
+        assert_eq!(
+            decompress(&compressed, &mut output),
+            Err(LzssError::OutputTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_corrupt_input() {
+        let data = b"hello world, hello world, hello world";
+        let compressed = compress(data);
+        let mut output = [0; 64];
+
+        assert_eq!(
+            decompress(&data[..], &mut output),
+            Err(LzssError::InvalidHeader)
+        );
+        assert_eq!(
+            decompress(&compressed[..compressed.len() - 1], &mut output),
+            Err(LzssError::Truncated)
+        );
+
+        // A match at the start of the stream has nothing to refer to
+        let mut bad_distance = compressed.clone();
+        bad_distance[HEADER_LEN] = 0;
+        assert_eq!(
+            decompress(&bad_distance, &mut output),
+            Err(LzssError::InvalidDistance)
+        );
+
+        // Corrupting any byte should never panic
+        for i in 0..compressed.len() {
+            let mut corrupt = compressed.clone();
+            corrupt[i] ^= 0xFF;
+            let _ = decompress(&corrupt, &mut output);
+        }
+    }
+}
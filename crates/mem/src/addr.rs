@@ -27,7 +27,7 @@ use core::marker::PhantomData;
 
 /// The kernel start Virtual address
 #[cfg(target_pointer_width = "64")]
-pub const KERNEL_ADDR_START: VirtAddr = VirtAddr::new(0xffffffff80000000);
+pub const KERNEL_ADDR_START: VirtAddr = VirtAddr::new(memory_layout::KERNEL_IMAGE.start);
 
 /// A trait to enfore structs to be aligned
 pub trait AlignmentTo: Clone + Copy {}
@@ -51,6 +51,56 @@ impl<const ALIGNMENT: usize> core::fmt::Debug for AlignmentError<ALIGNMENT> {
     }
 }
 
+/// An error from address arithmetic, or from converting a raw value into an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddrError {
+    /// The result went past the end of the address space
+    Overflow,
+    /// The result went below zero
+    Underflow,
+    /// The value is not a canonical virtual address (bits 48..64 must all equal bit 47)
+    NonCanonical(usize),
+}
+
+impl core::fmt::Display for AddrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AddrError::Overflow => write!(f, "address overflowed"),
+            AddrError::Underflow => write!(f, "address underflowed"),
+            AddrError::NonCanonical(addr) => write!(f, "{addr:#018x} is not canonical"),
+        }
+    }
+}
+
+impl core::error::Error for AddrError {}
+
+/// Check that `addr` is sign-extended from bit 47, the only form the cpu accepts.
+#[cfg(target_pointer_width = "64")]
+pub const fn is_canonical(addr: usize) -> bool {
+    let upper = addr >> 47;
+    upper == 0 || upper == (1 << 17) - 1
+}
+
+/// Every address is canonical in 32bit mode
+#[cfg(not(target_pointer_width = "64"))]
+pub const fn is_canonical(_addr: usize) -> bool {
+    true
+}
+
+/// Physical addresses have no canonical form
+const fn check_phys(addr: usize) -> Result<usize, AddrError> {
+    Ok(addr)
+}
+
+/// Virtual addresses must stay canonical
+const fn check_virt(addr: usize) -> Result<usize, AddrError> {
+    if is_canonical(addr) {
+        Ok(addr)
+    } else {
+        Err(AddrError::NonCanonical(addr))
+    }
+}
+
 /// If this ptr is above 48bits, it truncates the ptr to -1
 #[cfg(target_pointer_width = "64")]
 pub const fn fix_ptr_higher(ptr: usize) -> usize {
@@ -70,7 +120,7 @@ pub const fn fix_ptr_higher(ptr: usize) -> usize {
 macro_rules! make_addr {
     (
         $(#[$attr:meta])*
-        $ident:ident
+        $ident:ident, $check:ident
     ) => {
         $(#[$attr])*
         #[derive(Clone, Copy)]
@@ -246,6 +296,8 @@ macro_rules! make_addr {
             }
 
             /// Align the addr by bumping up its value until it reaches a valid alignment.
+            ///
+            /// In debug builds, this panics if the aligned addr is not valid.
             pub const fn align_up_to(mut self, alignment: usize) -> Self {
                 let rmd = self.addr % alignment;
 
@@ -255,6 +307,7 @@ macro_rules! make_addr {
                     self.addr
                 };
 
+                debug_assert!($check(self.addr).is_ok(), "Aligned address is not valid");
                 self
             }
 
@@ -283,6 +336,32 @@ macro_rules! make_addr {
                 }
             }
 
+            /// Align the addr down to `alignment`, which must be a power of 2.
+            pub const fn align_down(self, alignment: usize) -> Self {
+                assert!(alignment.is_power_of_two(), "Alignment should be a power of 2!");
+
+                Self {
+                    addr: self.addr & !(alignment - 1),
+                    _ph: PhantomData,
+                }
+            }
+
+            /// Align the addr up to `alignment`, which must be a power of 2.
+            ///
+            /// Fails instead of wrapping if the aligned addr would not fit.
+            pub const fn align_up(self, alignment: usize) -> Result<Self, AddrError> {
+                assert!(alignment.is_power_of_two(), "Alignment should be a power of 2!");
+
+                let Some(bumped) = self.addr.checked_add(alignment - 1) else {
+                    return Err(AddrError::Overflow);
+                };
+
+                match $check(bumped & !(alignment - 1)) {
+                    Ok(addr) => Ok(Self { addr, _ph: PhantomData }),
+                    Err(err) => Err(err),
+                }
+            }
+
             /// Check if this ptr is null.
             pub const fn is_null(&self) -> bool {
                 self.addr == 0
@@ -345,8 +424,11 @@ macro_rules! make_addr {
             }
 
             /// Make a new address, asserts if address is not aligned.
+            ///
+            /// In debug builds, this also panics if `addr` is not valid.
             pub const fn try_new(addr: usize) -> Self {
                 assert!(addr & (ALIGNMENT - 1) == 0, "Address not aligned");
+                debug_assert!($check(addr).is_ok(), "Address is not valid");
                 Self {
                     addr: fix_ptr_higher(addr),
                     _ph: PhantomData
@@ -357,13 +439,42 @@ macro_rules! make_addr {
 
         impl $ident<NotAligned> {
             /// Make a new address.
+            ///
+            /// In debug builds, this panics if `addr` is not valid, use `new_checked` for
+            /// addresses that can't be trusted. Since `offset`, `sub_offset` and `extend_by`
+            /// go through here, they panic too if they leave the valid range.
             pub const fn new(addr: usize) -> Self {
+                debug_assert!($check(addr).is_ok(), "Address is not valid");
                 Self {
                     addr: fix_ptr_higher(addr),
                     _ph: PhantomData
                 }
             }
 
+            /// Make a new address, failing instead of fixing up an invalid `addr`.
+            pub const fn new_checked(addr: usize) -> Result<Self, AddrError> {
+                match $check(addr) {
+                    Ok(addr) => Ok(Self { addr, _ph: PhantomData }),
+                    Err(err) => Err(err),
+                }
+            }
+
+            /// Add `bytes` to this addr, failing if the result is not a valid address.
+            pub const fn checked_add(self, bytes: usize) -> Result<Self, AddrError> {
+                match self.addr.checked_add(bytes) {
+                    Some(addr) => Self::new_checked(addr),
+                    None => Err(AddrError::Overflow),
+                }
+            }
+
+            /// Subtract `bytes` from this addr, failing if the result is not a valid address.
+            pub const fn checked_sub(self, bytes: usize) -> Result<Self, AddrError> {
+                match self.addr.checked_sub(bytes) {
+                    Some(addr) => Self::new_checked(addr),
+                    None => Err(AddrError::Underflow),
+                }
+            }
+
             /// Offset this addr by `offset`.
             pub const fn offset(self, offset: usize) -> Self {
                 Self::new(self.addr + offset)
@@ -402,20 +513,25 @@ impl<Align: AlignmentTo> VirtAddr<Align> {
     pub const fn is_kernel_addr(&self) -> bool {
         self.addr() >= KERNEL_ADDR_START.addr()
     }
+
+    /// Check if this virtual address is in canonical form
+    pub const fn is_canonical(&self) -> bool {
+        is_canonical(self.addr())
+    }
 }
 
 make_addr! {
     /// A structure safely repr a ptr to physical memory.
     ///
     /// Can be aligned, or non aligned though generic.
-    PhysAddr
+    PhysAddr, check_phys
 }
 
 make_addr! {
     /// A structure safely repr a ptr to Virtual memory.
     ///
     /// Can be aligned, or non aligned though generic.
-    VirtAddr
+    VirtAddr, check_virt
 }
 
 #[cfg(test)]
@@ -447,6 +563,71 @@ mod test {
         needs_aligned(addr.align_into());
     }
 
+    #[test]
+    fn test_align_up_down() {
+        let addr = PhysAddr::from(0x1234);
+
+        assert_eq!(addr.align_down(0x1000), PhysAddr::from(0x1000));
+        assert_eq!(addr.align_up(0x1000), Ok(PhysAddr::from(0x2000)));
+        assert_eq!(
+            PhysAddr::from(0x2000).align_up(0x1000),
+            Ok(PhysAddr::from(0x2000))
+        );
+        assert_eq!(
+            PhysAddr::from(usize::MAX - 5).align_up(16),
+            Err(AddrError::Overflow)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_align_up_not_power_of_two() {
+        let _ = PhysAddr::from(5).align_up(12);
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let addr = PhysAddr::from(0x1000);
+
+        assert_eq!(addr.checked_add(0x10), Ok(PhysAddr::from(0x1010)));
+        assert_eq!(addr.checked_sub(0x1000), Ok(PhysAddr::from(0)));
+        assert_eq!(addr.checked_sub(0x1001), Err(AddrError::Underflow));
+        assert_eq!(addr.checked_add(usize::MAX), Err(AddrError::Overflow));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_canonical_virt_addr() {
+        assert!(VirtAddr::new_checked(0x0000_7fff_ffff_ffff).is_ok());
+        assert!(VirtAddr::new_checked(0xffff_8000_0000_0000).is_ok());
+        assert_eq!(
+            VirtAddr::new_checked(0x0000_8000_0000_0000),
+            Err(AddrError::NonCanonical(0x0000_8000_0000_0000))
+        );
+        assert_eq!(
+            VirtAddr::new(0x0000_7fff_ffff_f000).checked_add(0x1000),
+            Err(AddrError::NonCanonical(0x0000_8000_0000_0000))
+        );
+        assert_eq!(
+            VirtAddr::new(0x1000).align_up(0x1000_0000_0000_0000),
+            Err(AddrError::NonCanonical(0x1000_0000_0000_0000))
+        );
+    }
+
+    #[test]
+    #[cfg(all(target_pointer_width = "64", debug_assertions))]
+    #[should_panic]
+    fn test_new_non_canonical_virt_addr() {
+        let _ = VirtAddr::new(0x0000_8000_0000_0000);
+    }
+
+    #[test]
+    #[cfg(all(target_pointer_width = "64", debug_assertions))]
+    #[should_panic]
+    fn test_offset_past_lower_half() {
+        let _ = VirtAddr::new(0x0000_7fff_ffff_f000).offset(0x1000);
+    }
+
     #[test]
     #[should_panic]
     fn test_fail_alignment() {
@@ -31,10 +31,72 @@ use core::{
 };
 use util::is_align_to;
 
+#[cfg(feature = "alloc-redzones")]
+mod redzone;
+
+#[cfg(feature = "alloc-redzones")]
+pub use redzone::RedzoneCorruption;
+
 #[derive(Debug, PartialEq, Eq)]
 enum BuddyState {
     Free,
-    Used { layout: Layout },
+    Used {
+        layout: Layout,
+        #[cfg(feature = "alloc-tracking")]
+        info: AllocInfo,
+    },
+}
+
+/// The number of return addresses recorded for each tracked allocation.
+#[cfg(feature = "alloc-tracking")]
+pub const CALL_SITE_DEPTH: usize = 8;
+
+/// Incremented on every allocation, so allocations made after some point can be found.
+#[cfg(feature = "alloc-tracking")]
+static ALLOC_GENERATION: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+
+/// # Alloc Info
+/// What the allocator recorded about a live allocation while tracking.
+#[cfg(feature = "alloc-tracking")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocInfo {
+    /// The requested size of this allocation
+    pub size: usize,
+    /// The value of the generation counter when this allocation was made
+    pub generation: u64,
+    /// The return addresses of the frames that made this allocation, innermost first.
+    ///
+    /// Unused entries are zero.
+    pub call_site: [u64; CALL_SITE_DEPTH],
+}
+
+#[cfg(feature = "alloc-tracking")]
+impl AllocInfo {
+    /// Record an allocation of `size` bytes made by the caller.
+    ///
+    /// This walks the frame pointer chain, so the kernel must be built with frame pointers.
+    /// The walk never leaves the running stack, so without them it only records less.
+    #[inline(always)]
+    fn capture(size: usize) -> Self {
+        let mut call_site = [0; CALL_SITE_DEPTH];
+        arch::stack_walk::capture_frames(&mut call_site);
+
+        Self {
+            size,
+            generation: ALLOC_GENERATION.fetch_add(1, core::sync::atomic::Ordering::Relaxed),
+            call_site,
+        }
+    }
+}
+
+/// The layout carved out of the heap for an allocation of `layout`
+#[inline]
+fn carved_layout(layout: Layout) -> Layout {
+    #[cfg(feature = "alloc-redzones")]
+    return redzone::outer_layout(layout);
+
+    #[cfg(not(feature = "alloc-redzones"))]
+    layout
 }
 
 #[derive(Debug)]
@@ -115,6 +177,7 @@ impl BuddyAllocator {
             return self.region_start.as_ptr();
         }
 
+        let carved = carved_layout(layout);
         let mut cursor = self.head();
 
         loop {
@@ -130,8 +193,8 @@ impl BuddyAllocator {
             let post_header_size = cursor_read.size;
             let end_region_ptr = unsafe { post_header_ptr.byte_add(post_header_size) };
 
-            let type_alignment_cost = post_header_ptr.cast::<u8>().align_offset(layout.align());
-            let type_size = type_alignment_cost + layout.size();
+            let type_alignment_cost = post_header_ptr.cast::<u8>().align_offset(carved.align());
+            let type_size = type_alignment_cost + carved.size();
 
             // Check if this buddy can fit the allocation
             if post_header_size < type_size {
@@ -140,7 +203,7 @@ impl BuddyAllocator {
                 } else {
                     panic!(
                         "Reached end of allocation region, no region fits desired allocation = {:?}",
-                        layout
+                        carved
                     );
                 }
                 continue;
@@ -193,7 +256,11 @@ impl BuddyAllocator {
             // Update buddy's status
             unsafe {
                 let cursor_mut = cursor.as_mut();
-                cursor_mut.state = BuddyState::Used { layout };
+                cursor_mut.state = BuddyState::Used {
+                    layout,
+                    #[cfg(feature = "alloc-tracking")]
+                    info: AllocInfo::capture(layout.size()),
+                };
             }
 
             let ret_ptr: *mut u8 = unsafe { post_header_ptr.byte_add(type_alignment_cost) }
@@ -201,7 +268,10 @@ impl BuddyAllocator {
                 .as_ptr();
 
             debug_assert!(is_align_to(ret_ptr.addr() as u64, layout.align()));
-            unsafe { ret_ptr.write_bytes(0, layout.size()) };
+            unsafe { ret_ptr.write_bytes(0, carved.size()) };
+
+            #[cfg(feature = "alloc-redzones")]
+            let ret_ptr = unsafe { redzone::surround(ret_ptr, layout) };
 
             return ret_ptr;
         }
@@ -299,6 +369,7 @@ impl BuddyAllocator {
                 ),
                 BuddyState::Used {
                     layout: state_layout,
+                    ..
                 } if state_layout != layout => {
                     panic!(
                         "Layout does not match previous state! prev={:?} new={:?}",
@@ -308,6 +379,18 @@ impl BuddyAllocator {
                 _ => (),
             }
 
+            #[cfg(feature = "alloc-redzones")]
+            if let BuddyState::Used { info, .. } = cursor_read.state {
+                if let Err(corruption) = unsafe { redzone::check(ptr, layout) } {
+                    panic!(
+                        "Heap corruption: {corruption}\nptr={:?}\nallocated at={:#x?}",
+                        ptr, info.call_site
+                    );
+                }
+
+                unsafe { redzone::poison(ptr, layout) };
+            }
+
             unsafe { cursor.as_mut().state = BuddyState::Free };
             self.combine(cursor);
 
@@ -316,6 +399,23 @@ impl BuddyAllocator {
     }
 }
 
+#[cfg(feature = "alloc-tracking")]
+impl BuddyAllocator {
+    /// Call `f` with the info of each live allocation.
+    fn for_each_used(&mut self, mut f: impl FnMut(AllocInfo)) {
+        let mut cursor = Some(self.head());
+
+        while let Some(buddy) = cursor {
+            let buddy_read = self.safety_check_buddy(buddy);
+            if let BuddyState::Used { info, .. } = buddy_read.state {
+                f(info);
+            }
+
+            cursor = buddy_read.next;
+        }
+    }
+}
+
 impl Debug for BuddyAllocator {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         struct Fields {
@@ -389,6 +489,31 @@ pub fn dump_allocator() {
     lignan::logln!("{:#?}", inner);
 }
 
+/// The current value of the allocation generation counter.
+#[cfg(feature = "alloc-tracking")]
+pub fn alloc_generation() -> u64 {
+    ALLOC_GENERATION.load(core::sync::atomic::Ordering::Relaxed)
+}
+
+/// The number of allocations currently live in the kernel's heap.
+#[cfg(feature = "alloc-tracking")]
+pub fn live_allocation_count() -> usize {
+    let mut count = 0;
+    for_each_live_allocation(|_| count += 1);
+    count
+}
+
+/// Call `f` with the info of each allocation currently live in the kernel's heap.
+///
+/// The allocator is locked while this runs, so `f` must not allocate.
+#[cfg(feature = "alloc-tracking")]
+pub fn for_each_live_allocation(f: impl FnMut(AllocInfo)) {
+    let mut inner = INNER_ALLOC.lock();
+    if let Some(init_alloc) = inner.init_alloc.as_mut() {
+        init_alloc.for_each_used(f);
+    }
+}
+
 pub struct KernelAllocator {}
 
 impl KernelAllocator {
@@ -468,4 +593,60 @@ mod test {
 
         unsafe { std::alloc::dealloc(mem_region, layout) };
     }
+
+    #[test]
+    #[cfg(feature = "alloc-tracking")]
+    fn test_alloc_tracking() {
+        lignan::testing_stdout!();
+        let len = 10 * util::consts::KIB;
+        let layout = Layout::from_size_align(len, 1).unwrap();
+        let mem_region = unsafe { std::alloc::alloc_zeroed(layout) };
+
+        let mut alloc = BuddyAllocator::new(NonNull::new(mem_region).unwrap(), len);
+        let first = unsafe { alloc.alloc(Layout::new::<u64>()) };
+        let second = unsafe { alloc.alloc(Layout::new::<[u8; 100]>()) };
+        unsafe { alloc.dealloc(first, Layout::new::<u64>()) };
+
+        let mut live = std::vec::Vec::new();
+        alloc.for_each_used(|info| live.push(info));
+
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].size, 100);
+        assert!(live[0].generation < alloc_generation());
+
+        unsafe { alloc.dealloc(second, Layout::new::<[u8; 100]>()) };
+        unsafe { std::alloc::dealloc(mem_region, layout) };
+    }
+
+    #[test]
+    #[cfg(feature = "alloc-redzones")]
+    fn test_redzones() {
+        lignan::testing_stdout!();
+        let len = 10 * util::consts::KIB;
+        let layout = Layout::from_size_align(len, 1).unwrap();
+        let mem_region = unsafe { std::alloc::alloc_zeroed(layout) };
+
+        let mut alloc = BuddyAllocator::new(NonNull::new(mem_region).unwrap(), len);
+        let array = Layout::new::<[u8; 100]>();
+        let ptr = unsafe { alloc.alloc(array) };
+
+        unsafe { ptr.write_bytes(0xAA, 100) };
+        assert_eq!(unsafe { redzone::check(ptr, array) }, Ok(()));
+
+        unsafe { ptr.add(101).write(0) };
+        assert_eq!(
+            unsafe { redzone::check(ptr, array) },
+            Err(RedzoneCorruption {
+                offset: 101,
+                found: 0,
+                size: 100
+            })
+        );
+
+        unsafe { ptr.add(101).write(redzone::REDZONE_BYTE) };
+        unsafe { alloc.dealloc(ptr, array) };
+        assert_eq!(unsafe { ptr.read() }, redzone::POISON_BYTE);
+
+        unsafe { std::alloc::dealloc(mem_region, layout) };
+    }
 }
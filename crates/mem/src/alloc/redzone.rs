@@ -0,0 +1,150 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Heap redzones and use-after-free poisoning.
+//!
+//! Each allocation is surrounded by bytes of [`REDZONE_BYTE`], which are checked when it
+//! is freed, so writes past either end of an allocation are caught instead of silently
+//! corrupting its neighbours. Freed memory is filled with [`POISON_BYTE`], so pointers read
+//! out of it after the free are non-canonical and fault on use.
+
+use core::{alloc::Layout, fmt::Display};
+
+/// The number of bytes guarded after each allocation, and at least this many before it
+pub const REDZONE_LEN: usize = 16;
+/// The byte redzones are filled with
+pub const REDZONE_BYTE: u8 = 0xFD;
+/// The byte freed memory is filled with
+pub const POISON_BYTE: u8 = 0x6B;
+
+/// The number of redzone bytes before an allocation of `layout`, this keeps its alignment
+#[inline]
+const fn front_len(layout: Layout) -> usize {
+    if layout.align() > REDZONE_LEN {
+        layout.align()
+    } else {
+        REDZONE_LEN
+    }
+}
+
+/// The layout to carve out of the heap for `layout` and both of its redzones
+pub fn outer_layout(layout: Layout) -> Layout {
+    Layout::from_size_align(
+        front_len(layout) + layout.size() + REDZONE_LEN,
+        layout.align(),
+    )
+    .expect("Allocation is too large to fit its redzones")
+}
+
+/// Fill the redzones of the `outer_layout(layout)` allocation at `outer`, returning the
+/// pointer to hand out.
+///
+/// # Safety
+/// `outer` must point to an allocation of `outer_layout(layout)`.
+pub unsafe fn surround(outer: *mut u8, layout: Layout) -> *mut u8 {
+    let front = front_len(layout);
+
+    unsafe {
+        outer.write_bytes(REDZONE_BYTE, front);
+        outer
+            .add(front + layout.size())
+            .write_bytes(REDZONE_BYTE, REDZONE_LEN);
+        outer.add(front)
+    }
+}
+
+/// A redzone byte that was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedzoneCorruption {
+    /// Where the byte is, relative to the start of the allocation
+    pub offset: isize,
+    /// The value it was overwritten with
+    pub found: u8,
+    /// The size of the allocation
+    pub size: usize,
+}
+
+impl Display for RedzoneCorruption {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.offset < 0 {
+            write!(
+                f,
+                "{:#04x} written {} byte(s) before the start of a {} byte allocation",
+                self.found, -self.offset, self.size
+            )
+        } else {
+            write!(
+                f,
+                "{:#04x} written {} byte(s) past the end of a {} byte allocation",
+                self.found,
+                self.offset as usize - self.size + 1,
+                self.size
+            )
+        }
+    }
+}
+
+/// Check both redzones of the allocation at `ptr`.
+///
+/// The corrupt byte closest to the allocation is reported, as that is where an overrun
+/// starts from.
+///
+/// # Safety
+/// `ptr` must have been returned by [`surround`] for `layout`.
+pub unsafe fn check(ptr: *const u8, layout: Layout) -> Result<(), RedzoneCorruption> {
+    let size = layout.size();
+
+    for offset in 1..=front_len(layout) {
+        let found = unsafe { ptr.sub(offset).read() };
+        if found != REDZONE_BYTE {
+            return Err(RedzoneCorruption {
+                offset: -(offset as isize),
+                found,
+                size,
+            });
+        }
+    }
+
+    for offset in size..size + REDZONE_LEN {
+        let found = unsafe { ptr.add(offset).read() };
+        if found != REDZONE_BYTE {
+            return Err(RedzoneCorruption {
+                offset: offset as isize,
+                found,
+                size,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Fill the freed allocation at `ptr` with [`POISON_BYTE`].
+///
+/// # Safety
+/// `ptr` must point to `layout.size()` writable bytes.
+pub unsafe fn poison(ptr: *mut u8, layout: Layout) {
+    unsafe { ptr.write_bytes(POISON_BYTE, layout.size()) };
+}
@@ -29,19 +29,21 @@ use core::ops::{Add, AddAssign, BitOr, BitOrAssign};
 use crate::{
     addr::{PhysAddr, VirtAddr},
     virt2phys::PhysPtrTranslationError,
+    vm::VmRegion,
 };
 use crate::{
-    page::{PhysPage, VirtPage},
+    page::{Page1G, Page2M, PhysPage, VirtPage},
     virt2phys::ObtainPhysAddr,
 };
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 use arch::{
     paging64::{
         PageEntry1G, PageEntry2M, PageEntry4K, PageEntryLvl2, PageEntryLvl3, PageEntryLvl4,
         PageMapLvl1, PageMapLvl2, PageMapLvl3, PageMapLvl4,
     },
-    registers::cr3,
+    registers::{cr3, ia32_pat},
 };
+use util::consts::{PAGE_1G, PAGE_2M, PAGE_4K};
 
 /// The top-most page table
 pub struct Virt2PhysMapping {
@@ -98,7 +100,8 @@ pub unsafe fn bootloader_convert_phys(virt: u64) -> Option<u64> {
     // FIXME: This is just hardcoded for now, but should be populated from the bootloader!!!!
     //
     // Since we know the bootloader is identity mapped, the physical PTRs are valid virtual PTRs!
-    let (lvl4_idx, lvl3_idx, lvl2_idx, lvl1_idx) = table_indexes_for(VirtAddr::new(virt as usize));
+    let (lvl4_idx, lvl3_idx, lvl2_idx, lvl1_idx) =
+        table_indexes_for(VirtAddr::new_checked(virt as usize).ok()?);
 
     let lvl4_table_ptr =
         arch::registers::cr3::get_page_directory_base_register() as *const PageMapLvl4;
@@ -379,18 +382,26 @@ impl Virt2PhysMapping {
         };
 
         let (lvl4_index, lvl3_index, lvl2_index, lvl1_index) = table_indexes_for(page.addr());
+        let vaddr = page.addr().addr() as u64;
 
         let Some(phys_addr) = inner.lower.get(lvl4_index).and_then(|lvl3| {
-            let entry = lvl3
-                .as_ref()?
-                .lower
-                .get(lvl3_index)?
-                .as_ref()?
-                .lower
-                .get(lvl2_index)?
-                .as_ref()?
-                .table
-                .get(lvl1_index);
+            let lvl3 = lvl3.as_ref()?;
+            let lvl3_entry = lvl3.table.get(lvl3_index);
+            if lvl3_entry.is_present_set() {
+                if let Some(huge) = PageEntry1G::convert_entry(lvl3_entry) {
+                    return Some(huge.get_phy_address() + vaddr % PageMapLvl3::SIZE_PER_INDEX);
+                }
+            }
+
+            let lvl2 = lvl3.lower.get(lvl3_index)?.as_ref()?;
+            let lvl2_entry = lvl2.table.get(lvl2_index);
+            if lvl2_entry.is_present_set() {
+                if let Some(huge) = PageEntry2M::convert_entry(lvl2_entry) {
+                    return Some(huge.get_phy_address() + vaddr % PageMapLvl2::SIZE_PER_INDEX);
+                }
+            }
+
+            let entry = lvl2.lower.get(lvl2_index)?.as_ref()?.table.get(lvl1_index);
 
             if entry.is_present_set() {
                 Some(entry.get_phy_address())
@@ -414,6 +425,10 @@ impl Virt2PhysMapping {
     ) -> Result<Option<PhysPage>, PageCorrelationError> {
         let (lvl4_index, lvl3_index, lvl2_index, lvl1_index) = table_indexes_for(vpage.addr());
 
+        if self.is_inside_huge_page(vpage.addr()) {
+            self.split_huge_page(vpage.addr(), PAGE_4K)?;
+        }
+
         fn check_perms(
             present: bool,
             prev_perm: VmPermissions,
@@ -762,10 +777,102 @@ impl Virt2PhysMapping {
     field(RW, 5, pub only_commit_permissions),
     /// Force set permissions, regardless if they are higher or lower for the bottom most table only
     field(RW, 6, pub force_permissions_on_page),
+    /// Set the `PWT` bit on the page entry (see [`CacheMode`])
+    field(RW, 7, pub pat_write_through),
+    /// Set the `PCD` bit on the page entry (see [`CacheMode`])
+    field(RW, 8, pub pat_cache_disable),
+    /// Set the `PAT` bit on the page entry (see [`CacheMode`])
+    field(RW, 9, pub pat_select),
 )]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct VmOptions(usize);
 
+/// The caching type of a page mapping
+///
+/// These rely on the PAT being programmed with [`CacheMode::PAT_LAYOUT`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CacheMode {
+    /// Normal memory, fully cached
+    WriteBack,
+    /// Writes go straight to memory, reads are cached
+    WriteThrough,
+    /// Writes are buffered and combined, good for framebuffers
+    WriteCombining,
+    /// No caching at all, required for device MMIO
+    Uncached,
+}
+
+impl CacheMode {
+    /// The PAT layout the kernel programs at boot.
+    ///
+    /// Entries 0-3 keep their power-on defaults (WB, WT, UC-, UC) so any mappings made
+    /// before the PAT was programmed keep their meaning. Entry 4 is swapped for WC.
+    pub const PAT_LAYOUT: u64 = ia32_pat::from_entries([
+        ia32_pat::WRITE_BACK,
+        ia32_pat::WRITE_THROUGH,
+        ia32_pat::UNCACHED_MINUS,
+        ia32_pat::UNCACHEABLE,
+        ia32_pat::WRITE_COMBINING,
+        ia32_pat::WRITE_THROUGH,
+        ia32_pat::UNCACHED_MINUS,
+        ia32_pat::UNCACHEABLE,
+    ]);
+
+    /// The PAT index (`PAT`, `PCD`, `PWT` bits) this mode uses in [`CacheMode::PAT_LAYOUT`]
+    pub const fn pat_index(&self) -> u8 {
+        match self {
+            CacheMode::WriteBack => 0,
+            CacheMode::WriteThrough => 1,
+            CacheMode::Uncached => 3,
+            CacheMode::WriteCombining => 4,
+        }
+    }
+
+    /// The mode a page's `PAT`, `PCD`, and `PWT` bits select in [`CacheMode::PAT_LAYOUT`]
+    pub const fn from_pat_bits(pat: bool, cache_disable: bool, write_through: bool) -> Self {
+        Self::from_pat_index((pat as u8) << 2 | (cache_disable as u8) << 1 | write_through as u8)
+    }
+
+    /// The mode a PAT index selects in [`CacheMode::PAT_LAYOUT`]
+    ///
+    /// UC- is reported as [`CacheMode::Uncached`], as the kernel never asks for it.
+    pub const fn from_pat_index(index: u8) -> Self {
+        match index & 0b111 {
+            0 => CacheMode::WriteBack,
+            1 | 5 => CacheMode::WriteThrough,
+            4 => CacheMode::WriteCombining,
+            _ => CacheMode::Uncached,
+        }
+    }
+}
+
+/// A present page found by [`Virt2PhysMapping::walk`]
+#[derive(Debug, Clone, Copy)]
+pub struct PageMapping {
+    /// The first virtual address of the page
+    pub virt: VirtAddr,
+    /// Where the page points to in physical memory
+    pub phys: PhysAddr,
+    /// The size of the page in bytes
+    pub size: usize,
+    /// Writes are allowed, which needs `W` at every level of the walk
+    pub writable: bool,
+    /// Execution is blocked, which `NX` at any level of the walk does
+    pub no_exec: bool,
+    /// Userspace can access the page, which needs `U` at every level of the walk
+    pub user: bool,
+    /// The page stays in the TLB across CR3 switches
+    pub global: bool,
+    pub cache: CacheMode,
+}
+
+impl PageMapping {
+    /// The physical address `virt` maps to, `virt` must be inside this page.
+    pub const fn translate(&self, virt: VirtAddr) -> PhysAddr {
+        PhysAddr::new(self.phys.addr() + (virt.addr() - self.virt.addr()))
+    }
+}
+
 /// Permissions for mapping a page
 #[bits::bits(
     /// Make execute is possible
@@ -785,6 +892,15 @@ impl VmOptions {
     pub const fn none() -> Self {
         Self(0)
     }
+
+    /// Map pages with this cache mode
+    pub const fn set_cache_mode(mut self, mode: CacheMode) -> Self {
+        let pat_index = mode.pat_index();
+
+        self.set_pat_write_through_flag(pat_index & 0b001 != 0)
+            .set_pat_cache_disable_flag(pat_index & 0b010 != 0)
+            .set_pat_select_flag(pat_index & 0b100 != 0)
+    }
 }
 
 impl Add for VmPermissions {
@@ -863,6 +979,11 @@ impl VmPermissions {
     pub const fn none() -> Self {
         Self(0)
     }
+
+    /// Does this include all of `other`'s permissions
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
 }
 
 impl BitOr for VmPermissions {
@@ -1074,6 +1195,50 @@ impl SafePageMapLvl3 {
     }
 }
 
+impl SafePageMapLvl3 {
+    /// Map a 1Gib page at `index`, dropping the table that was there.
+    ///
+    /// Returns if something was mapped here before.
+    fn store_huge(
+        &mut self,
+        index: usize,
+        entry: PageEntry1G,
+        options: VmOptions,
+    ) -> Result<bool, PageCorrelationError> {
+        let was_present = self.table.get(index).is_present_set();
+        if was_present && !options.is_overwrite_set() {
+            return Err(PageCorrelationError::PageAlreadyMapped);
+        }
+
+        self.lower[index] = None;
+        self.table.store(entry, index);
+
+        Ok(was_present)
+    }
+}
+
+impl SafePageMapLvl2 {
+    /// Map a 2Mib page at `index`, dropping the table that was there.
+    ///
+    /// Returns if something was mapped here before.
+    fn store_huge(
+        &mut self,
+        index: usize,
+        entry: PageEntry2M,
+        options: VmOptions,
+    ) -> Result<bool, PageCorrelationError> {
+        let was_present = self.table.get(index).is_present_set();
+        if was_present && !options.is_overwrite_set() {
+            return Err(PageCorrelationError::PageAlreadyMapped);
+        }
+
+        self.lower[index] = None;
+        self.table.store(entry, index);
+
+        Ok(was_present)
+    }
+}
+
 impl SafePageMapLvl2 {
     /// Make an empty page table mapping
     const fn empty() -> Self {
@@ -1187,6 +1352,10 @@ impl SafePageMapLvl1 {
         if !options.is_only_commit_permissions_set() {
             // do the actual linking of vpage -> ppage
             entry.set_phy_address(ppage.addr().addr() as u64);
+
+            entry.set_write_though_flag(options.is_pat_write_through_set());
+            entry.set_cache_disable_flag(options.is_pat_cache_disable_set());
+            entry.set_page_attribute_table_flag(options.is_pat_select_set());
         }
 
         self.table.store(entry, local_table_index);
@@ -1203,6 +1372,624 @@ impl SafePageMapLvl1 {
     }
 }
 
+/// Find the first mapping of every run in `mappings` that could be replaced by a single page
+/// of `huge_size`.
+fn aligned_runs(mappings: &[PageMapping], huge_size: usize) -> Vec<PageMapping> {
+    let mut runs = Vec::new();
+    let mut run: Option<(PageMapping, usize)> = None;
+
+    for mapping in mappings {
+        let continues = run.is_some_and(|(first, bytes)| {
+            mapping.virt.addr() == first.virt.addr() + bytes
+                && mapping.phys.addr() == first.phys.addr() + bytes
+                && mapping.writable == first.writable
+                && mapping.no_exec == first.no_exec
+                && mapping.user == first.user
+                && mapping.global == first.global
+                && mapping.cache == first.cache
+        });
+
+        run = match run {
+            Some((first, bytes)) if continues => Some((first, bytes + mapping.size)),
+            _ if mapping.virt.is_aligned_to(huge_size) && mapping.phys.is_aligned_to(huge_size) => {
+                Some((*mapping, mapping.size))
+            }
+            _ => None,
+        };
+
+        if let Some((first, bytes)) = run {
+            if bytes == huge_size {
+                runs.push(first);
+                run = None;
+            }
+        }
+    }
+
+    runs
+}
+
+/// The permissions of a walk so far, combined from each level above the page.
+#[derive(Clone, Copy)]
+struct WalkFlags {
+    writable: bool,
+    no_exec: bool,
+    user: bool,
+}
+
+impl WalkFlags {
+    const fn combine(self, writable: bool, no_exec: bool, user: bool) -> Self {
+        Self {
+            writable: self.writable && writable,
+            no_exec: self.no_exec || no_exec,
+            user: self.user && user,
+        }
+    }
+}
+
+impl Virt2PhysMapping {
+    /// Call `f` with every present page that overlaps `region`, in address order.
+    ///
+    /// Huge pages are reported once with their full size, even if only part of them
+    /// is inside `region`.
+    pub fn walk(&self, region: VmRegion, mut f: impl FnMut(PageMapping)) {
+        let Some(lvl4) = self.mapping.as_ref() else {
+            return;
+        };
+
+        let first = region.start.addr().addr();
+        let last = region.end.addr().addr() + (PageMapLvl1::SIZE_PER_INDEX as usize - 1);
+
+        // The tables only hold 48 bits, the upper half is found by sign extending them
+        let entry_base = |raw: usize| {
+            if raw & (1 << 47) != 0 {
+                raw | !((1 << 48) - 1)
+            } else {
+                raw
+            }
+        };
+        let overlaps = |base: usize, size: u64| {
+            base <= last && base.saturating_add(size as usize - 1) >= first
+        };
+
+        let top = WalkFlags {
+            writable: true,
+            no_exec: false,
+            user: true,
+        };
+
+        for lvl4_index in 0..512 {
+            let lvl4_entry = lvl4.table.get(lvl4_index);
+            let lvl4_base = entry_base(lvl4_index * PageMapLvl4::SIZE_PER_INDEX as usize);
+            let Some(lvl3) = lvl4.lower[lvl4_index].as_ref() else {
+                continue;
+            };
+
+            if !lvl4_entry.is_present_set() || !overlaps(lvl4_base, PageMapLvl4::SIZE_PER_INDEX) {
+                continue;
+            }
+
+            let lvl4_flags = top.combine(
+                lvl4_entry.is_read_write_set(),
+                lvl4_entry.is_execute_disable_set(),
+                lvl4_entry.is_user_access_set(),
+            );
+
+            for lvl3_index in 0..512 {
+                let lvl3_entry = lvl3.table.get(lvl3_index);
+                let lvl3_base = lvl4_base + lvl3_index * PageMapLvl3::SIZE_PER_INDEX as usize;
+
+                if !lvl3_entry.is_present_set() || !overlaps(lvl3_base, PageMapLvl3::SIZE_PER_INDEX)
+                {
+                    continue;
+                }
+
+                let lvl3_flags = lvl4_flags.combine(
+                    lvl3_entry.is_read_write_set(),
+                    lvl3_entry.is_execute_disable_set(),
+                    lvl3_entry.is_user_access_set(),
+                );
+
+                if let Some(huge) = PageEntry1G::convert_entry(lvl3_entry) {
+                    f(PageMapping {
+                        virt: VirtAddr::new(lvl3_base),
+                        phys: PhysAddr::new(huge.get_phy_address() as usize),
+                        size: PageMapLvl3::SIZE_PER_INDEX as usize,
+                        writable: lvl3_flags.writable,
+                        no_exec: lvl3_flags.no_exec,
+                        user: lvl3_flags.user,
+                        global: huge.is_global_set(),
+                        cache: CacheMode::from_pat_bits(
+                            huge.is_page_attribute_table_set(),
+                            huge.is_cache_disable_set(),
+                            huge.is_write_though_set(),
+                        ),
+                    });
+                    continue;
+                }
+
+                let Some(lvl2) = lvl3.lower[lvl3_index].as_ref() else {
+                    continue;
+                };
+
+                for lvl2_index in 0..512 {
+                    let lvl2_entry = lvl2.table.get(lvl2_index);
+                    let lvl2_base = lvl3_base + lvl2_index * PageMapLvl2::SIZE_PER_INDEX as usize;
+
+                    if !lvl2_entry.is_present_set()
+                        || !overlaps(lvl2_base, PageMapLvl2::SIZE_PER_INDEX)
+                    {
+                        continue;
+                    }
+
+                    let lvl2_flags = lvl3_flags.combine(
+                        lvl2_entry.is_read_write_set(),
+                        lvl2_entry.is_execute_disable_set(),
+                        lvl2_entry.is_user_access_set(),
+                    );
+
+                    if let Some(huge) = PageEntry2M::convert_entry(lvl2_entry) {
+                        f(PageMapping {
+                            virt: VirtAddr::new(lvl2_base),
+                            phys: PhysAddr::new(huge.get_phy_address() as usize),
+                            size: PageMapLvl2::SIZE_PER_INDEX as usize,
+                            writable: lvl2_flags.writable,
+                            no_exec: lvl2_flags.no_exec,
+                            user: lvl2_flags.user,
+                            global: huge.is_global_set(),
+                            cache: CacheMode::from_pat_bits(
+                                huge.is_page_attribute_table_set(),
+                                huge.is_cache_disable_set(),
+                                huge.is_write_though_set(),
+                            ),
+                        });
+                        continue;
+                    }
+
+                    let Some(lvl1) = lvl2.lower[lvl2_index].as_ref() else {
+                        continue;
+                    };
+
+                    for lvl1_index in 0..512 {
+                        let page = lvl1.table.get(lvl1_index);
+                        let page_base =
+                            lvl2_base + lvl1_index * PageMapLvl1::SIZE_PER_INDEX as usize;
+
+                        if !page.is_present_set()
+                            || !overlaps(page_base, PageMapLvl1::SIZE_PER_INDEX)
+                        {
+                            continue;
+                        }
+
+                        let flags = lvl2_flags.combine(
+                            page.is_read_write_set(),
+                            page.is_execute_disable_set(),
+                            page.is_user_access_set(),
+                        );
+
+                        f(PageMapping {
+                            virt: VirtAddr::new(page_base),
+                            phys: PhysAddr::new(page.get_phy_address() as usize),
+                            size: PageMapLvl1::SIZE_PER_INDEX as usize,
+                            writable: flags.writable,
+                            no_exec: flags.no_exec,
+                            user: flags.user,
+                            global: page.is_global_set(),
+                            cache: CacheMode::from_pat_bits(
+                                page.is_page_attribute_table_set(),
+                                page.is_cache_disable_set(),
+                                page.is_write_though_set(),
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Find the page `virt` is in, if it is mapped.
+    pub fn translate(&self, virt: VirtAddr) -> Option<PageMapping> {
+        let mut found = None;
+        let page = VirtPage::containing_addr(virt);
+        self.walk(VmRegion::new(page, page), |mapping| found = Some(mapping));
+
+        found
+    }
+
+    /// Is `vaddr` inside a 2Mib or 1Gib page?
+    fn is_inside_huge_page(&self, vaddr: VirtAddr) -> bool {
+        let (lvl4_index, lvl3_index, lvl2_index, _) = table_indexes_for(vaddr);
+        let is_huge = |present: bool, page_size: bool| present && page_size;
+
+        self.mapping
+            .as_ref()
+            .and_then(|lvl4| {
+                lvl4.ref_at(lvl4_index, |_, lvl3| {
+                    let lvl3_entry = lvl3.table.get(lvl3_index);
+                    is_huge(lvl3_entry.is_present_set(), lvl3_entry.is_page_size_set())
+                        || lvl3
+                            .ref_at(lvl3_index, |_, lvl2| {
+                                let lvl2_entry = lvl2.table.get(lvl2_index);
+                                is_huge(lvl2_entry.is_present_set(), lvl2_entry.is_page_size_set())
+                            })
+                            .unwrap_or(false)
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Map the 2Mib page `vpage` to `ppage`.
+    ///
+    /// The tables above are given at least `permissions`. If `options` allows overwriting,
+    /// any 4Kib pages mapped within `vpage` are dropped.
+    pub fn correlate_2m_page(
+        &mut self,
+        vpage: VirtPage<Page2M>,
+        ppage: PhysPage<Page2M>,
+        options: VmOptions,
+        permissions: VmPermissions,
+    ) -> Result<(), PageCorrelationError> {
+        let (lvl4_index, lvl3_index, lvl2_index, _) = table_indexes_for(vpage.addr());
+
+        let mut entry = PageEntry2M::new();
+        entry.add_permissions_from(permissions);
+        entry.set_phy_address(ppage.addr().addr() as u64);
+        entry.set_write_though_flag(options.is_pat_write_through_set());
+        entry.set_cache_disable_flag(options.is_pat_cache_disable_set());
+        entry.set_page_attribute_table_flag(options.is_pat_select_set());
+
+        // A 1Gib page here is split so the rest of it stays mapped
+        self.split_huge_page(vpage.addr(), PAGE_2M)?;
+
+        let mut new_tables = [None; 2];
+        let replaced = self
+            .mapping
+            .get_or_insert_with(|| Box::new(SafePageMapLvl4::empty()))
+            .ensured_mut_at(lvl4_index, |lvl4_entry, lvl3| {
+                if !lvl4_entry.is_present_set() {
+                    new_tables[0] = Some(VirtAddr::new(lvl3.table.table_ptr() as usize));
+                }
+                lvl4_entry.add_permissions_from(permissions);
+
+                lvl3.ensured_mut_at(lvl3_index, |lvl3_entry, lvl2| {
+                    if !lvl3_entry.is_present_set() {
+                        new_tables[1] = Some(VirtAddr::new(lvl2.table.table_ptr() as usize));
+                    }
+                    lvl3_entry.add_permissions_from(permissions);
+
+                    lvl2.store_huge(lvl2_index, entry, options)
+                })
+            })?;
+
+        self.link_new_tables(lvl4_index, lvl3_index, new_tables)?;
+        self.flush_huge(vpage.addr(), replaced, options);
+
+        Ok(())
+    }
+
+    /// Map the 1Gib page `vpage` to `ppage`.
+    ///
+    /// The CPU must support 1Gib pages. Like [`Self::correlate_2m_page`], any smaller pages
+    /// within `vpage` are dropped if `options` allows overwriting.
+    pub fn correlate_1g_page(
+        &mut self,
+        vpage: VirtPage<Page1G>,
+        ppage: PhysPage<Page1G>,
+        options: VmOptions,
+        permissions: VmPermissions,
+    ) -> Result<(), PageCorrelationError> {
+        let (lvl4_index, lvl3_index, _, _) = table_indexes_for(vpage.addr());
+
+        let mut entry = PageEntry1G::new();
+        entry.add_permissions_from(permissions);
+        entry.set_phy_address(ppage.addr().addr() as u64);
+        entry.set_write_though_flag(options.is_pat_write_through_set());
+        entry.set_cache_disable_flag(options.is_pat_cache_disable_set());
+        entry.set_page_attribute_table_flag(options.is_pat_select_set());
+
+        let mut new_tables = [None; 2];
+        let replaced = self
+            .mapping
+            .get_or_insert_with(|| Box::new(SafePageMapLvl4::empty()))
+            .ensured_mut_at(lvl4_index, |lvl4_entry, lvl3| {
+                if !lvl4_entry.is_present_set() {
+                    new_tables[0] = Some(VirtAddr::new(lvl3.table.table_ptr() as usize));
+                }
+                lvl4_entry.add_permissions_from(permissions);
+
+                lvl3.store_huge(lvl3_index, entry, options)
+            })?;
+
+        self.link_new_tables(lvl4_index, lvl3_index, new_tables)?;
+        self.flush_huge(vpage.addr(), replaced, options);
+
+        Ok(())
+    }
+
+    /// Point the entries above a huge page at the tables that were made for it.
+    ///
+    /// This is done once the tables are in place, for the same reason `correlate_page` waits.
+    fn link_new_tables(
+        &mut self,
+        lvl4_index: usize,
+        lvl3_index: usize,
+        new_tables: [Option<VirtAddr>; 2],
+    ) -> Result<(), PageCorrelationError> {
+        let mut phys_tables = [None; 2];
+        for (phys, virt) in phys_tables.iter_mut().zip(new_tables) {
+            if let Some(virt) = virt {
+                *phys = Some(
+                    virt.phys_addr()
+                        .map_err(|perr| PageCorrelationError::PhysTranslationErr(perr))?
+                        .addr() as u64,
+                );
+            }
+        }
+
+        let Some(lvl4) = self.mapping.as_mut() else {
+            return Ok(());
+        };
+        lvl4.ensured_mut_at(lvl4_index, |lvl4_entry, lvl3| {
+            if let Some(phys) = phys_tables[0] {
+                lvl4_entry.set_next_entry_phy_address(phys);
+            }
+
+            if let Some(phys) = phys_tables[1] {
+                lvl3.ensured_mut_at(lvl3_index, |lvl3_entry, _| {
+                    lvl3_entry.set_next_entry_phy_address(phys);
+                });
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Flush a huge page from the TLB, if these tables are loaded.
+    ///
+    /// `invlpg` only flushes the smaller page containing an address, so if `replaced` pages
+    /// were mapped here before the whole TLB is flushed.
+    fn flush_huge(&self, vaddr: VirtAddr, replaced: bool, options: VmOptions) {
+        if options.is_no_tlb_flush_set() || !self.is_loaded() {
+            return;
+        }
+
+        if replaced {
+            unsafe { cr3::write(cr3::read()) };
+        } else {
+            unsafe { flush_tlb(VirtPage::containing_addr(vaddr)) };
+        }
+    }
+
+    /// Split the huge page containing `vaddr` into pages of the next size down, until
+    /// `vaddr` is in a page no larger than `size`.
+    ///
+    /// The smaller pages map the same memory with the same flags, so nothing using the
+    /// page can tell it was split. Does nothing if `vaddr` is not inside a larger page.
+    pub fn split_huge_page(
+        &mut self,
+        vaddr: VirtAddr,
+        size: usize,
+    ) -> Result<(), PageCorrelationError> {
+        while let Some(huge) = self.translate(vaddr).filter(|mapping| mapping.size > size) {
+            self.split_one(huge)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the huge page `huge` with a full table of pages of the next size down.
+    fn split_one(&mut self, huge: PageMapping) -> Result<(), PageCorrelationError> {
+        let (lvl4_index, lvl3_index, lvl2_index, _) = table_indexes_for(huge.virt);
+        let loaded = self.is_loaded();
+        let Some(lvl4) = self.mapping.as_mut() else {
+            return Ok(());
+        };
+
+        macro_rules! copy_page_flags {
+            ($from:expr, $to:expr) => {{
+                $to.set_present_flag(true);
+                $to.set_read_write_flag($from.is_read_write_set());
+                $to.set_user_access_flag($from.is_user_access_set());
+                $to.set_execute_disable_flag($from.is_execute_disable_set());
+                $to.set_write_though_flag($from.is_write_though_set());
+                $to.set_cache_disable_flag($from.is_cache_disable_set());
+                $to.set_page_attribute_table_flag($from.is_page_attribute_table_set());
+                $to.set_global_flag($from.is_global_set());
+            }};
+        }
+
+        let table_phys = |table_ptr: u64| {
+            VirtAddr::new(table_ptr as usize)
+                .phys_addr()
+                .map(|phys| phys.addr() as u64)
+                .map_err(|perr| PageCorrelationError::PhysTranslationErr(perr))
+        };
+
+        // The new table is filled in before the huge entry is swapped for it, so the memory
+        // never goes unmapped.
+        if huge.size == PAGE_1G {
+            let Some(huge_entry) = lvl4
+                .ref_at(lvl4_index, |_, lvl3| {
+                    PageEntry1G::convert_entry(lvl3.table.get(lvl3_index))
+                })
+                .flatten()
+            else {
+                return Ok(());
+            };
+
+            let mut lower = Box::new(SafePageMapLvl2::empty());
+            for index in 0..512 {
+                let mut entry = PageEntry2M::new();
+                copy_page_flags!(huge_entry, entry);
+                entry.set_phy_address(huge_entry.get_phy_address() + (index * PAGE_2M) as u64);
+                lower.table.store(entry, index);
+            }
+
+            let mut entry = PageEntryLvl3::zero();
+            entry.add_permissions_from(huge_entry.get_permissions());
+            entry.set_next_entry_phy_address(table_phys(lower.table.table_ptr())?);
+
+            lvl4.ensured_mut_at(lvl4_index, |_, lvl3| {
+                lvl3.lower[lvl3_index] = Some(lower);
+                lvl3.table.store(entry, lvl3_index);
+            });
+        } else {
+            let Some(huge_entry) = lvl4
+                .ref_at(lvl4_index, |_, lvl3| {
+                    lvl3.ref_at(lvl3_index, |_, lvl2| {
+                        PageEntry2M::convert_entry(lvl2.table.get(lvl2_index))
+                    })
+                })
+                .flatten()
+                .flatten()
+            else {
+                return Ok(());
+            };
+
+            let mut lower = Box::new(SafePageMapLvl1::empty());
+            for index in 0..512 {
+                let mut entry = PageEntry4K::new();
+                copy_page_flags!(huge_entry, entry);
+                entry.set_phy_address(huge_entry.get_phy_address() + (index * PAGE_4K) as u64);
+                lower.table.store(entry, index);
+            }
+
+            let mut entry = PageEntryLvl2::zero();
+            entry.add_permissions_from(huge_entry.get_permissions());
+            entry.set_next_entry_phy_address(table_phys(lower.table.table_ptr())?);
+
+            lvl4.ensured_mut_at(lvl4_index, |_, lvl3| {
+                lvl3.ensured_mut_at(lvl3_index, |_, lvl2| {
+                    lvl2.lower[lvl2_index] = Some(lower);
+                    lvl2.table.store(entry, lvl2_index);
+                });
+            });
+        }
+
+        // `invlpg` drops the whole entry for a huge page, even a global one
+        if loaded {
+            unsafe { flush_tlb(VirtPage::containing_addr(huge.virt)) };
+        }
+
+        Ok(())
+    }
+
+    /// Replace the 4Kib pages in `region` with 2Mib pages wherever 512 of them line up, then
+    /// do the same for 1Gib pages if `allow_1g` is set. Returns how many huge pages were made.
+    ///
+    /// Pages line up when they map contiguous, naturally aligned physical memory with the
+    /// same flags.
+    pub fn promote_huge_pages(&mut self, region: VmRegion, allow_1g: bool) -> usize {
+        let mut promoted = 0;
+
+        for (size, huge_size) in [(PAGE_4K, PAGE_2M), (PAGE_2M, PAGE_1G)] {
+            if huge_size == PAGE_1G && !allow_1g {
+                break;
+            }
+
+            let mut mappings = Vec::new();
+            self.walk(region, |mapping| {
+                if mapping.size == size {
+                    mappings.push(mapping);
+                }
+            });
+
+            for first in aligned_runs(&mappings, huge_size) {
+                let permissions = VmPermissions::none()
+                    .set_read_flag(true)
+                    .set_write_flag(first.writable)
+                    .set_exec_flag(!first.no_exec)
+                    .set_user_flag(first.user);
+                let options = VmOptions::none()
+                    .set_overwrite_flag(true)
+                    .set_cache_mode(first.cache);
+
+                let result = if huge_size == PAGE_2M {
+                    self.correlate_2m_page(
+                        VirtPage::containing_addr(first.virt),
+                        PhysPage::containing_addr(first.phys),
+                        options,
+                        permissions,
+                    )
+                } else {
+                    self.correlate_1g_page(
+                        VirtPage::containing_addr(first.virt),
+                        PhysPage::containing_addr(first.phys),
+                        options,
+                        permissions,
+                    )
+                };
+
+                if result.is_ok() {
+                    promoted += 1;
+                }
+            }
+        }
+
+        promoted
+    }
+
+    /// Unmap every page in `region`, returning the pages that were mapped there.
+    ///
+    /// The physical pages are not freed, that is up to whoever mapped them. Huge pages
+    /// hanging over either end of `region` are split first, so only the part inside
+    /// `region` is unmapped.
+    pub fn unmap_region(
+        &mut self,
+        region: VmRegion,
+    ) -> Result<Vec<PageMapping>, PageCorrelationError> {
+        let first = region.start.addr().addr();
+        let last = region.end.addr().addr() + (PAGE_4K - 1);
+        for edge in [region.start.addr(), region.end.addr()] {
+            let crosses = self.translate(edge).is_some_and(|mapping| {
+                mapping.virt.addr() < first || mapping.virt.addr() + (mapping.size - 1) > last
+            });
+
+            if crosses {
+                self.split_huge_page(edge, PAGE_4K)?;
+            }
+        }
+
+        let mut mappings = Vec::new();
+        self.walk(region, |mapping| mappings.push(mapping));
+
+        let loaded = self.is_loaded();
+        let Some(lvl4) = self.mapping.as_mut() else {
+            return Ok(mappings);
+        };
+
+        for mapping in mappings.iter() {
+            let (lvl4_index, lvl3_index, lvl2_index, lvl1_index) = table_indexes_for(mapping.virt);
+
+            lvl4.ensured_mut_at(lvl4_index, |_, lvl3| {
+                if mapping.size == PAGE_1G {
+                    lvl3.lower[lvl3_index] = None;
+                    lvl3.table.store(PageEntryLvl3::zero(), lvl3_index);
+                    return;
+                }
+
+                lvl3.ensured_mut_at(lvl3_index, |_, lvl2| {
+                    if mapping.size == PAGE_2M {
+                        lvl2.lower[lvl2_index] = None;
+                        lvl2.table.store(PageEntryLvl2::zero(), lvl2_index);
+                        return;
+                    }
+
+                    lvl2.ensured_mut_at(lvl2_index, |_, lvl1| {
+                        lvl1.table.store(PageEntry4K::zero(), lvl1_index);
+                    });
+                });
+            });
+
+            // `invlpg` drops the whole entry for a huge page too
+            if loaded {
+                unsafe { flush_tlb(VirtPage::containing_addr(mapping.virt)) };
+            }
+        }
+
+        Ok(mappings)
+    }
+}
+
 impl core::fmt::Debug for Virt2PhysMapping {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         if self.mapping.is_none() {
@@ -41,6 +41,7 @@ pub enum PhysMemoryKind {
     InitFs,
     Bootloader,
     PageTables,
+    Pstore,
     Broken,
 }
 
@@ -193,7 +194,8 @@ impl<const N: usize> PhysMemoryMap<N> {
                 | PhysMemoryKind::KernelElf
                 | PhysMemoryKind::KernelHeap
                 | PhysMemoryKind::InitFs
-                | PhysMemoryKind::PageTables => true,
+                | PhysMemoryKind::PageTables
+                | PhysMemoryKind::Pstore => true,
                 _ => false,
             })
             .for_each(|region| bytes += region.start.distance_to(region.end));
@@ -211,13 +213,14 @@ impl<const N: usize> PhysMemoryMap<N> {
         self.iter()
             .filter(|region| region.kind == from_kind)
             .find_map(|region| {
-                let new_start = region.start.max(min_address).align_up_to(alignment);
+                let new_start = region.start.max(min_address).align_up(alignment).ok()?;
+                let new_end = new_start.checked_add(bytes).ok()?;
 
-                if region.end > new_start && new_start.distance_to(region.end) >= bytes {
+                if region.end > new_start && new_end <= region.end {
                     Some(PhysMemoryEntry {
                         kind: region.kind,
                         start: new_start,
-                        end: new_start.offset(bytes),
+                        end: new_end,
                     })
                 } else {
                     None
@@ -225,6 +228,34 @@ impl<const N: usize> PhysMemoryMap<N> {
             })
     }
 
+    /// Like [`PhysMemoryMap::find_continuous_of`], but finds the highest region that ends at
+    /// or below `max_address` instead of the lowest.
+    pub fn find_last_continuous_of(
+        &mut self,
+        from_kind: PhysMemoryKind,
+        bytes: usize,
+        alignment: usize,
+        max_address: PhysAddr,
+    ) -> Option<PhysMemoryEntry> {
+        self.iter()
+            .filter(|region| region.kind == from_kind)
+            .filter_map(|region| {
+                let new_end = region.end.min(max_address).align_down(alignment);
+                let new_start = new_end.checked_sub(bytes).ok()?.align_down(alignment);
+
+                if new_start >= region.start && new_start < new_end {
+                    Some(PhysMemoryEntry {
+                        kind: region.kind,
+                        start: new_start,
+                        end: new_start.checked_add(bytes).ok()?,
+                    })
+                } else {
+                    None
+                }
+            })
+            .last()
+    }
+
     // FIXME: This function should be remade, it was made quickly and I just wanted it to work.
     //        I think at one point it failed to deoverlap some regions, so that could be possible.
     pub fn add_region(&mut self, region: impl MemoryDesc) -> Result<(), crate::MemoryError> {
@@ -1087,6 +1118,42 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_find_last_cont_of() {
+        let mut mm = PhysMemoryMap::<10>::new();
+
+        mm.add_region(PhysMemoryEntry {
+            kind: PhysMemoryKind::Free,
+            start: 1.into(),
+            end: 64.into(),
+        })
+        .unwrap();
+
+        mm.add_region(PhysMemoryEntry {
+            kind: PhysMemoryKind::Reserved,
+            start: 32.into(),
+            end: 40.into(),
+        })
+        .unwrap();
+
+        assert_eq!(
+            mm.find_last_continuous_of(PhysMemoryKind::Free, 16, 8, 128.into()),
+            Some(PhysMemoryEntry {
+                kind: PhysMemoryKind::Free,
+                start: 48.into(),
+                end: 64.into()
+            })
+        );
+        assert_eq!(
+            mm.find_last_continuous_of(PhysMemoryKind::Free, 16, 8, 50.into()),
+            Some(PhysMemoryEntry {
+                kind: PhysMemoryKind::Free,
+                start: 16.into(),
+                end: 32.into()
+            })
+        );
+    }
+
     #[test]
     fn test_real_add_ss_regions_to_mm() {
         let mut mm = PhysMemoryMap::<16>::new();
@@ -25,12 +25,12 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 use alloc::{boxed::Box, sync::Arc};
 use arch::locks::InterruptMutex;
-use core::ops::Deref;
+use core::ops::{Deref, Range};
 use util::consts::PAGE_4K;
 
 use crate::{
     MemoryError,
-    page::PhysPage,
+    page::{PagingStructureSize, PhysPage},
     phys::{PhysMemoryKind, PhysMemoryMap},
 };
 
@@ -70,6 +70,10 @@ where
 
 pub struct Pmm {
     table: Box<backing::MemoryTable<backing::TableFlat>>,
+    /// The page numbers of the first and last page this PMM can hand out
+    pages: Range<usize>,
+    /// How many pages of real memory are within `pages`
+    usable_pages: usize,
 }
 
 impl Pmm {
@@ -85,6 +89,8 @@ impl Pmm {
         }
 
         let mut table = Box::new(backing::MemoryTable::new(opt_table));
+        let mut pages = usize::MAX..0;
+        let mut usable_pages = 0;
 
         memory_map
             .iter()
@@ -92,15 +98,26 @@ impl Pmm {
                 entry.kind == PhysMemoryKind::Free && entry.start.addr() >= (1 * util::consts::MIB)
             })
             .try_for_each(|entry| {
+                pages.start = pages.start.min(entry.start.addr().div_ceil(PAGE_4K));
+                pages.end = pages.end.max(entry.end.addr() / PAGE_4K);
+
+                let start = entry
+                    .start
+                    .align_up(PAGE_4K)
+                    .map_err(|_| MemoryError::InvalidSize)?;
+                let end = entry.end.align_down(PAGE_4K);
+                usable_pages += end.addr().saturating_sub(start.addr()) / PAGE_4K;
+
                 table
-                    .populate_with(
-                        entry.start.align_up_to(PAGE_4K).try_into().unwrap(),
-                        entry.end.align_down_to(PAGE_4K).try_into().unwrap(),
-                    )
+                    .populate_with(start.try_into().unwrap(), end.try_into().unwrap())
                     .map(|_| ())
             })?;
 
-        Ok(Self { table })
+        Ok(Self {
+            table,
+            pages,
+            usable_pages,
+        })
     }
 
     pub fn allocate_page(&mut self) -> Result<PhysPage, MemoryError> {
@@ -114,6 +131,92 @@ impl Pmm {
     pub fn pages_free(&self) -> Result<usize, MemoryError> {
         self.table.pages_free()
     }
+
+    /// The page numbers that this PMM could ever hand out.
+    ///
+    /// Not every page within this range is real memory, reserved holes are never free.
+    pub fn managed_pages(&self) -> Range<usize> {
+        self.pages.clone()
+    }
+
+    /// How many pages of real memory this PMM manages, used or not.
+    ///
+    /// Unlike [`Pmm::managed_pages`], this does not count the reserved holes.
+    pub fn usable_pages(&self) -> usize {
+        self.usable_pages
+    }
+
+    /// Is `page` real memory that is not allocated?
+    pub fn is_page_free(&self, page: PhysPage) -> bool {
+        self.pages.contains(&page.page()) && self.table.is_page_free(page)
+    }
+
+    /// Allocate `page` itself, instead of letting the PMM choose one.
+    pub fn claim_page(&mut self, page: PhysPage) -> Result<(), MemoryError> {
+        if !self.pages.contains(&page.page()) {
+            return Err(MemoryError::NotPhysicalPage);
+        }
+
+        self.table.claim_page(page)
+    }
+
+    /// Allocate `count` pages that follow on from each other in physical memory, returning
+    /// the first of them.
+    ///
+    /// This looks for the lowest free run that is long enough, so it does not move anything
+    /// to make room. If memory is too fragmented this fails with `OutOfAllocMemory`.
+    pub fn allocate_contiguous(&mut self, count: usize) -> Result<PhysPage, MemoryError> {
+        self.allocate_run(count, 1).map(PhysPage::new)
+    }
+
+    /// Allocate a naturally aligned huge page, made of `S::N_PAGES` contiguous pages.
+    pub fn allocate_huge<S: PagingStructureSize>(&mut self) -> Result<PhysPage<S>, MemoryError> {
+        self.allocate_run(S::N_PAGES, S::N_PAGES)
+            .map(|first| PhysPage::new(first / S::N_PAGES))
+    }
+
+    /// Free a huge page allocated with `allocate_huge`.
+    pub fn free_huge<S: PagingStructureSize>(
+        &mut self,
+        page: PhysPage<S>,
+    ) -> Result<(), MemoryError> {
+        self.free_contiguous(PhysPage::new(page.page() * S::N_PAGES), S::N_PAGES)
+    }
+
+    /// Allocate the lowest run of `count` free pages that starts on a multiple of `align`
+    /// pages, returning its first page number.
+    fn allocate_run(&mut self, count: usize, align: usize) -> Result<usize, MemoryError> {
+        if count == 0 || align == 0 {
+            return Err(MemoryError::InvalidSize);
+        }
+
+        let mut run_start = self.pages.start.next_multiple_of(align);
+        let mut page = run_start;
+        while page < self.pages.end {
+            if !self.table.is_page_free(PhysPage::new(page)) {
+                run_start = (page + 1).next_multiple_of(align);
+                page = run_start;
+                continue;
+            }
+
+            page += 1;
+            if page - run_start == count {
+                for page in run_start..page {
+                    self.table.claim_page(PhysPage::new(page))?;
+                }
+
+                return Ok(run_start);
+            }
+        }
+
+        Err(MemoryError::OutOfAllocMemory)
+    }
+
+    /// Free `count` pages starting at `first`, allocated with `allocate_contiguous`.
+    pub fn free_contiguous(&mut self, first: PhysPage, count: usize) -> Result<(), MemoryError> {
+        (first.page()..(first.page() + count))
+            .try_for_each(|page| self.table.free_page(PhysPage::new(page)))
+    }
 }
 
 /// This physical page was allocated by the PMM and when dropped it
@@ -163,7 +266,12 @@ impl SharedPhysPage {
 
 #[cfg(test)]
 mod test {
-    use crate::{addr::PhysAddr, phys::PhysMemoryEntry, pmm::backing::TABLE_SIZE};
+    use crate::{
+        addr::PhysAddr,
+        page::{Page1G, Page2M},
+        phys::PhysMemoryEntry,
+        pmm::backing::TABLE_SIZE,
+    };
 
     use super::*;
     extern crate std;
@@ -206,6 +314,134 @@ mod test {
         },
     ];
 
+    fn small_pmm() -> Pmm {
+        const MEM_MAP: [PhysMemoryEntry; 1] = [PhysMemoryEntry {
+            kind: PhysMemoryKind::Free,
+            start: PhysAddr::new(util::consts::MIB),
+            end: PhysAddr::new(util::consts::MIB + 4096 * TABLE_SIZE * 4),
+        }];
+
+        let mut mm = Box::new(PhysMemoryMap::<20>::new());
+        for entry in MEM_MAP.iter() {
+            mm.add_region(entry.clone()).unwrap();
+        }
+
+        Pmm::new(&mm).unwrap()
+    }
+
+    #[test]
+    fn test_allocate_contiguous_skips_fragments() {
+        let mut pmm = small_pmm();
+        let pages = pmm.managed_pages();
+        assert_eq!(pages.len(), TABLE_SIZE * 4);
+
+        let allocated = (0..pages.len())
+            .map(|_| pmm.allocate_page().unwrap())
+            .collect::<std::vec::Vec<_>>();
+        assert_eq!(
+            pmm.allocate_contiguous(1),
+            Err(MemoryError::OutOfAllocMemory)
+        );
+
+        // Free every other page, so no two free pages are next to each other
+        for page in allocated.iter().step_by(2) {
+            pmm.free_page(*page).unwrap();
+        }
+        assert_eq!(pmm.pages_free().unwrap(), pages.len() / 2);
+        assert_eq!(
+            pmm.allocate_contiguous(2),
+            Err(MemoryError::OutOfAllocMemory)
+        );
+
+        let hole = pages.start + 1001;
+        for page in hole..(hole + 3) {
+            if !pmm.is_page_free(PhysPage::new(page)) {
+                pmm.free_page(PhysPage::new(page)).unwrap();
+            }
+        }
+
+        let first = pmm.allocate_contiguous(4).unwrap();
+        assert_eq!(first.page(), hole - 1);
+        assert!(
+            (first.page()..(first.page() + 4)).all(|page| !pmm.is_page_free(PhysPage::new(page)))
+        );
+
+        pmm.free_contiguous(first, 4).unwrap();
+        assert_eq!(pmm.allocate_contiguous(4).unwrap(), first);
+    }
+
+    #[test]
+    fn test_allocate_huge_is_aligned() {
+        let mut pmm = small_pmm();
+        let start = pmm.managed_pages().start;
+
+        // Knock out a page in the first 2Mib page, so the huge page has to skip it
+        let first_huge = start.next_multiple_of(Page2M::N_PAGES);
+        pmm.claim_page(PhysPage::new(first_huge + 3)).unwrap();
+
+        let huge = pmm.allocate_huge::<Page2M>().unwrap();
+        assert_eq!(huge.page(), first_huge / Page2M::N_PAGES + 1);
+        assert!(!pmm.is_page_free(PhysPage::new(huge.page() * Page2M::N_PAGES + 511)));
+
+        // There is not enough memory for a 1Gib page
+        assert_eq!(
+            pmm.allocate_huge::<Page1G>().map(|page| page.page()),
+            Err(MemoryError::OutOfAllocMemory)
+        );
+
+        pmm.free_huge(huge).unwrap();
+        assert!(pmm.is_page_free(PhysPage::new(huge.page() * Page2M::N_PAGES)));
+    }
+
+    #[test]
+    fn test_usable_pages_skips_holes() {
+        const MEM_MAP: [PhysMemoryEntry; 3] = [
+            PhysMemoryEntry {
+                kind: PhysMemoryKind::Free,
+                start: PhysAddr::new(util::consts::MIB),
+                end: PhysAddr::new(util::consts::MIB + 4096 * 100),
+            },
+            PhysMemoryEntry {
+                kind: PhysMemoryKind::Reserved,
+                start: PhysAddr::new(util::consts::MIB + 4096 * 100),
+                end: PhysAddr::new(util::consts::MIB + 4096 * 150),
+            },
+            PhysMemoryEntry {
+                kind: PhysMemoryKind::Free,
+                start: PhysAddr::new(util::consts::MIB + 4096 * 150),
+                end: PhysAddr::new(util::consts::MIB + 4096 * 400),
+            },
+        ];
+
+        let mut mm = Box::new(PhysMemoryMap::<20>::new());
+        for entry in MEM_MAP.iter() {
+            mm.add_region(entry.clone()).unwrap();
+        }
+
+        let pmm = Pmm::new(&mm).unwrap();
+        assert_eq!(pmm.managed_pages().len(), 400);
+        assert_eq!(pmm.usable_pages(), 350);
+        assert_eq!(small_pmm().usable_pages(), TABLE_SIZE * 4);
+    }
+
+    #[test]
+    fn test_claim_page() {
+        let mut pmm = small_pmm();
+        let page = PhysPage::new(pmm.managed_pages().start + 700);
+
+        assert!(pmm.is_page_free(page));
+        pmm.claim_page(page).unwrap();
+        assert!(!pmm.is_page_free(page));
+        assert_eq!(pmm.claim_page(page), Err(MemoryError::AlreadyUsed));
+        assert_eq!(
+            pmm.claim_page(PhysPage::new(0)),
+            Err(MemoryError::NotPhysicalPage)
+        );
+
+        pmm.free_page(page).unwrap();
+        assert!(pmm.is_page_free(page));
+    }
+
     #[test]
     fn ensure_pmm_doesnt_run_out_of_memory() {
         const BYTES: usize = 4096 * TABLE_SIZE * 4;
@@ -112,6 +112,16 @@ pub trait TableImpl: Sized {
     ) -> Result<AllocationResult, MemoryError>;
 
     fn pages_free(&self, el_size: usize) -> Result<usize, MemoryError>;
+
+    // Is this exact page free to be allocated
+    fn is_page_free(&self, page: PhysPage, el_size: usize) -> bool;
+
+    // Allocate this exact page
+    fn claim_page(
+        &mut self,
+        page: PhysPage,
+        el_size: usize,
+    ) -> Result<AllocationResult, MemoryError>;
 }
 
 #[derive(Clone)]
@@ -164,6 +174,21 @@ impl<Table: TableImpl> MemoryTable<Table> {
     pub fn pages_free(&self) -> Result<usize, MemoryError> {
         self.table.pages_free(self.element_size)
     }
+
+    #[inline]
+    pub fn is_page_free(&self, page: PhysPage) -> bool {
+        self.table.is_page_free(page, self.element_size)
+    }
+
+    #[inline]
+    pub fn claim_page(&mut self, page: PhysPage) -> Result<(), MemoryError> {
+        self.table.claim_page(page, self.element_size).map(|_| ())
+    }
+
+    #[inline]
+    fn claim_page_from_higher(&mut self, page: PhysPage) -> Result<AllocationResult, MemoryError> {
+        self.table.claim_page(page, self.element_size)
+    }
 }
 
 impl TableImpl for TableFlat {
@@ -403,6 +428,94 @@ impl TableImpl for TableFlat {
                 })
         })
     }
+
+    fn is_page_free(&self, page: PhysPage, el_size: usize) -> bool {
+        let table_index = page.addr().addr() / el_size;
+        let inner_page = PhysPage::new(page.addr().realative_offset(el_size).addr() / PAGE_4K);
+
+        match self.table.get(table_index) {
+            None | Some(TableElementKind::NotAllocated) => false,
+            Some(TableElementKind::Present) => true,
+            Some(TableElementKind::TableFlat { ptr, .. }) => {
+                unsafe { ptr.as_ref() }.is_page_free(inner_page)
+            }
+            Some(TableElementKind::TableBits { ptr, .. }) => {
+                unsafe { ptr.as_ref() }.is_page_free(inner_page)
+            }
+        }
+    }
+
+    fn claim_page(
+        &mut self,
+        page: PhysPage,
+        el_size: usize,
+    ) -> Result<AllocationResult, MemoryError> {
+        let el_size_as_ptr: PhysAddr<AlignedTo<PAGE_4K>> = el_size
+            .try_into()
+            .map_err(|_| MemoryError::NotPageAligned)?;
+
+        let table_index = page.addr().addr() / el_size;
+        let inner_page = PhysPage::new(page.addr().realative_offset(el_size).addr() / PAGE_4K);
+
+        let atom = self
+            .table
+            .get_mut(table_index)
+            .ok_or(MemoryError::NotPhysicalPage)?;
+
+        // Whole elements have no table yet, so we split them the same way `request_page` does
+        let alloc_result = match atom {
+            TableElementKind::NotAllocated => return Err(MemoryError::NotPhysicalPage),
+            TableElementKind::Present if el_size <= LVL1_TABLE => {
+                let bref = Box::leak(Box::new(MemoryTable::new(el_size / TABLE_SIZE)));
+                bref.populate_with(PhysAddr::try_new(0), el_size_as_ptr)?;
+                *atom = TableElementKind::TableBits {
+                    ptr: bref.into(),
+                    atom: TABLE_SIZE,
+                };
+
+                self.healthy_tables -= 1;
+                self.dirty_tables += 1;
+
+                bref.claim_page_from_higher(inner_page)
+            }
+            TableElementKind::Present => {
+                let bref = Box::leak(Box::new(MemoryTable::new(el_size / TABLE_SIZE)));
+                bref.populate_with(PhysAddr::try_new(0), el_size_as_ptr)?;
+                *atom = TableElementKind::TableFlat {
+                    ptr: bref.into(),
+                    atom: TABLE_SIZE,
+                };
+
+                self.healthy_tables -= 1;
+                self.dirty_tables += 1;
+
+                bref.claim_page_from_higher(inner_page)
+            }
+            TableElementKind::TableFlat { ptr, .. } => {
+                unsafe { ptr.as_mut() }.claim_page_from_higher(inner_page)
+            }
+            TableElementKind::TableBits { ptr, .. } => {
+                unsafe { ptr.as_mut() }.claim_page_from_higher(inner_page)
+            }
+        }?;
+
+        match atom {
+            TableElementKind::TableFlat { atom, .. } | TableElementKind::TableBits { atom, .. } => {
+                *atom = alloc_result.new_size;
+            }
+            _ => unreachable!(),
+        }
+
+        if alloc_result.new_size == 0 {
+            self.dirty_tables -= 1;
+            self.available.set(table_index, false);
+        }
+
+        Ok(AllocationResult {
+            page,
+            new_size: self.healthy_tables.max(self.dirty_tables.min(1)),
+        })
+    }
 }
 
 impl TableImpl for TableBits {
@@ -484,6 +597,32 @@ impl TableImpl for TableBits {
     fn pages_free(&self, el_size: usize) -> Result<usize, MemoryError> {
         Ok(self.atom_size * (el_size / PAGE_4K))
     }
+
+    fn is_page_free(&self, page: PhysPage, _el_size: usize) -> bool {
+        self.real_pages.get(page.page()) && self.table.get(page.page())
+    }
+
+    fn claim_page(
+        &mut self,
+        page: PhysPage,
+        _el_size: usize,
+    ) -> Result<AllocationResult, MemoryError> {
+        if !self.real_pages.get(page.page()) {
+            return Err(MemoryError::NotPhysicalPage);
+        }
+
+        if !self.table.get(page.page()) {
+            return Err(MemoryError::AlreadyUsed);
+        }
+
+        self.table.set(page.page(), false);
+        self.atom_size -= 1;
+
+        Ok(AllocationResult {
+            page,
+            new_size: self.atom_size,
+        })
+    }
 }
 
 #[cfg(test)]
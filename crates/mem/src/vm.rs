@@ -30,7 +30,7 @@ use crate::{
     MemoryError,
     addr::{AlignedTo, KERNEL_ADDR_START, VirtAddr},
     page::{PhysPage, VirtPage},
-    paging::{PageCorrelationError, Virt2PhysMapping, VmOptions, VmPermissions},
+    paging::{CacheMode, PageCorrelationError, Virt2PhysMapping, VmOptions, VmPermissions},
     pmm::use_pmm_mut,
 };
 use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec::Vec};
@@ -332,6 +332,8 @@ pub enum VmObjectMappingError {
     },
     /// There was a problem populating this entry
     InjectError(Box<dyn Error>),
+    /// This page is not anonymous memory owned by this process, so it cannot be moved
+    PageNotMovable(VirtPage),
 }
 
 impl Error for VmObjectMappingError {}
@@ -391,6 +393,7 @@ impl VmObject {
         region: VmRegion,
         mappings: BTreeMap<VirtPage, PhysPage>,
         permissions: VmPermissions,
+        cache_mode: CacheMode,
     ) -> Result<Arc<RwLock<Self>>, NewVmObjectError> {
         for (vpage, ppage) in mappings.iter() {
             vm_process
@@ -403,7 +406,8 @@ impl VmObject {
                         .set_reduce_perm_from_tables_flag(true)
                         .set_increase_perm_flag(true)
                         .set_force_permissions_on_page_flag(true)
-                        .set_overwrite_flag(true),
+                        .set_overwrite_flag(true)
+                        .set_cache_mode(cache_mode),
                     permissions,
                 )
                 .map_err(|err| {
@@ -499,6 +503,14 @@ impl VmObject {
         Ok(())
     }
 
+    /// Is every page of this object anonymous memory, that only this object maps?
+    ///
+    /// The backing of these pages can be swapped for another physical page, as long as
+    /// its contents are copied over first.
+    pub fn is_movable(&self) -> bool {
+        matches!(*self.fill_action.read(), VmFillAction::Scrub(_))
+    }
+
     /// The page fault handler for this VmObject
     pub fn page_fault_handler(
         &self,
@@ -565,6 +577,15 @@ pub enum InsertVmObjectError {
     VmObjectError(NewVmObjectError),
 }
 
+/// Why a VmObject could not be removed from a VmProcess
+#[derive(Debug)]
+pub enum RemoveVmObjectError {
+    /// No object starts at this page
+    NotMapped(VirtPage),
+    /// The object's pages could not be unmapped
+    UnmapError(PageCorrelationError),
+}
+
 /// The result from checking an addr within the region
 #[derive(Debug)]
 pub enum CheckAddrResult {
@@ -630,6 +651,34 @@ impl VmProcess {
         }
     }
 
+    /// Check that every page of `region` is mapped with at least `perms`
+    pub fn check_region_perms(&self, region: &VmRegion, perms: VmPermissions) -> CheckAddrResult {
+        let object_lock = self.objects.read();
+        let mut page = region.start;
+
+        // Walk each object the region passes through, since a region can span many objects
+        while page <= region.end {
+            let Some(object) = object_lock
+                .iter()
+                .map(|object| object.read())
+                .find(|object| object.region.does_contain_page(page))
+            else {
+                return CheckAddrResult::NotMapped;
+            };
+
+            if !object.permissions.contains(perms) {
+                return CheckAddrResult::MappedInvalidPerms {
+                    expected: perms,
+                    found: object.permissions,
+                };
+            }
+
+            page = VirtPage::new(object.region.end.page() + 1);
+        }
+
+        CheckAddrResult::MappedAndValidPerms
+    }
+
     /// Does this VmRegion overlap with any of the VmObjects in this Process?
     ///
     /// If it returns the region that is overlapping.
@@ -699,6 +748,29 @@ impl VmProcess {
         Ok(())
     }
 
+    /// Remove the object that starts at `start`, unmapping all of its pages.
+    ///
+    /// The object is returned so the caller decides what happens to its memory, once it
+    /// is dropped its fill action is dropped with it.
+    pub fn remove_vm_object(
+        &self,
+        start: VirtPage,
+    ) -> Result<Arc<RwLock<VmObject>>, RemoveVmObjectError> {
+        let mut objects = self.objects.write();
+        let index = objects
+            .iter()
+            .position(|object| object.read().region.start == start)
+            .ok_or(RemoveVmObjectError::NotMapped(start))?;
+
+        let region = objects[index].read().region;
+        self.page_tables
+            .write()
+            .unmap_region(region)
+            .map_err(RemoveVmObjectError::UnmapError)?;
+
+        Ok(objects.remove(index))
+    }
+
     /// Make a new vm object from this process. This will both insert the object
     /// and return a new Arc<..> ptr to it.
     pub fn inplace_new_vmobject(
@@ -742,6 +814,7 @@ impl VmProcess {
         region: VmRegion,
         permissions: VmPermissions,
         mappings: BTreeMap<VirtPage, PhysPage>,
+        cache_mode: CacheMode,
     ) -> Result<Arc<RwLock<VmObject>>, InsertVmObjectError> {
         // If there is already a region that exists on that virtual address
         //
@@ -756,7 +829,7 @@ impl VmProcess {
         }
 
         // Construct the object
-        let obj = VmObject::manual_new(self, region, mappings, permissions)
+        let obj = VmObject::manual_new(self, region, mappings, permissions, cache_mode)
             .map_err(|obj_err| InsertVmObjectError::VmObjectError(obj_err))?;
 
         // Insert the object
@@ -765,6 +838,69 @@ impl VmProcess {
         Ok(obj)
     }
 
+    /// Call `f` with every mapped page of the movable objects in this process.
+    pub fn movable_pages(&self, mut f: impl FnMut(VirtPage, PhysPage)) {
+        let page_tables = self.page_tables.read();
+
+        for object in self.objects.read().iter() {
+            let object = object.read();
+            if !object.is_movable() {
+                continue;
+            }
+
+            page_tables.walk(object.region, |mapping| {
+                if mapping.size == PAGE_4K {
+                    f(
+                        VirtPage::containing_addr(mapping.virt),
+                        PhysPage::containing_addr(mapping.phys),
+                    );
+                }
+            });
+        }
+    }
+
+    /// Point `vpage` at `new_page` with the permissions of its object, returning the page
+    /// that used to back it.
+    ///
+    /// This does not copy the contents of the page, the caller must have already done so.
+    pub fn remap_movable_page(
+        &self,
+        vpage: VirtPage,
+        new_page: PhysPage,
+    ) -> Result<PhysPage, VmObjectMappingError> {
+        let objects = self.objects.read();
+        let Some(object) = objects
+            .iter()
+            .find(|object| object.read().region.does_contain_page(vpage))
+        else {
+            return Err(VmObjectMappingError::PageNotMovable(vpage));
+        };
+
+        let object = object.read();
+        if !object.is_movable() {
+            return Err(VmObjectMappingError::PageNotMovable(vpage));
+        }
+
+        let mut page_tables = self.page_tables.write();
+        if page_tables.vpage_to_ppage_lookup(vpage).is_err() {
+            return Err(VmObjectMappingError::PageNotMovable(vpage));
+        }
+
+        page_tables
+            .correlate_page(
+                vpage,
+                new_page,
+                VmOptions::none()
+                    .set_reduce_perm_from_tables_flag(true)
+                    .set_increase_perm_flag(true)
+                    .set_force_permissions_on_page_flag(true)
+                    .set_overwrite_flag(true),
+                object.permissions,
+            )
+            .map_err(|err| VmObjectMappingError::MappingError(err))?
+            .ok_or(VmObjectMappingError::PageNotMovable(vpage))
+    }
+
     /// The page fault handler for this VmProcess
     pub fn page_fault_handler(&self, info: PageFaultInfo) -> PageFaultReponse {
         let lock = self.objects.read();
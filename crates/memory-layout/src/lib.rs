@@ -0,0 +1,226 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! # Memory Layout
+//! The virtual address space map shared by the bootloader and the kernel.
+//!
+//! Every fixed virtual region lives here, so the bootloader's page tables, the kernel's
+//! VMM, and its heap all agree on where things go. The regions are checked against each
+//! other at compile time, so moving one into another fails the build instead of the boot.
+//!
+//! ```text
+//! 0x0000_0000_0000_0000 ┬ IDENTITY_MAP   (bootloader only)
+//! 0x0000_0000_4000_0000 ┼ USER_MMAP
+//! 0x0000_7fff_0000_0000 ┼ USER_STACKS
+//! 0x0000_8000_0000_0000 ┴ (non-canonical)
+//! 0xffff_8000_0000_0000 ┬ KERNEL_SPACE
+//! 0xffff_ff00_0000_0000 ┼ MMIO_WINDOW
+//! 0xffff_ffff_8000_0000 ┴ KERNEL_IMAGE   (exe, stack, init heap, initfs)
+//! ```
+
+#![no_std]
+#![cfg(target_pointer_width = "64")]
+
+use util::consts::{GIB, KIB, MIB, PAGE_2M};
+
+/// A named range of virtual memory, from `start` up to and including `last`.
+///
+/// Regions store their last address instead of their end, so regions that reach the top
+/// of the address space don't overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtRegion {
+    pub name: &'static str,
+    pub start: usize,
+    pub last: usize,
+}
+
+impl VirtRegion {
+    /// A region of `len` bytes starting at `start`
+    pub const fn new(name: &'static str, start: usize, len: usize) -> Self {
+        assert!(len != 0, "Regions cannot be empty");
+        let Some(last) = start.checked_add(len - 1) else {
+            panic!("Region goes past the end of the address space");
+        };
+
+        Self { name, start, last }
+    }
+
+    /// A region from `start` until the end of the address space
+    pub const fn until_end(name: &'static str, start: usize) -> Self {
+        Self {
+            name,
+            start,
+            last: usize::MAX,
+        }
+    }
+
+    /// The size of this region in bytes, saturating if it spans the whole address space
+    pub const fn len(&self) -> usize {
+        (self.last - self.start).saturating_add(1)
+    }
+
+    /// The first address past the end of this region, if there is one
+    pub const fn end(&self) -> Option<usize> {
+        self.last.checked_add(1)
+    }
+
+    /// Is `addr` inside this region?
+    pub const fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr <= self.last
+    }
+
+    /// Are all `len` bytes starting at `start` inside this region?
+    pub const fn contains_range(&self, start: usize, len: usize) -> bool {
+        if len == 0 {
+            return self.contains(start);
+        }
+
+        match start.checked_add(len - 1) {
+            Some(last) => self.contains(start) && last <= self.last,
+            None => false,
+        }
+    }
+
+    /// Is all of `other` inside this region?
+    pub const fn contains_region(&self, other: &VirtRegion) -> bool {
+        other.start >= self.start && other.last <= self.last
+    }
+
+    /// Do this region and `other` share any addresses?
+    pub const fn overlaps(&self, other: &VirtRegion) -> bool {
+        self.start <= other.last && other.start <= self.last
+    }
+}
+
+/// The canonical lower half, where processes live
+pub const USER_SPACE: VirtRegion = VirtRegion::new("user space", 0, 0x0000_8000_0000_0000);
+
+/// The canonical higher half, which is the same in every process
+pub const KERNEL_SPACE: VirtRegion = VirtRegion::until_end("kernel space", 0xffff_8000_0000_0000);
+
+/// Low physical memory, identity mapped by the bootloader.
+///
+/// The kernel does not inherit this mapping, so it only exists until the kernel builds its
+/// own page tables.
+pub const IDENTITY_MAP: VirtRegion = VirtRegion::new("identity map", 0, 1 * GIB);
+
+/// Where the kernel looks for free virtual memory to map into a process
+pub const USER_MMAP: VirtRegion =
+    VirtRegion::new("user mmap", 1 * GIB, USER_STACKS.start - 1 * GIB);
+
+/// Thread stacks, with each thread's stack placed above the last.
+pub const USER_STACKS: VirtRegion = VirtRegion::new(
+    "user stacks",
+    0x0000_7fff_0000_0000,
+    USER_SPACE.len() - 0x0000_7fff_0000_0000,
+);
+
+/// The kernel's window for device memory mapped with `vmm::map_mmio`
+pub const MMIO_WINDOW: VirtRegion = VirtRegion::new("mmio", 0xffff_ff00_0000_0000, 64 * GIB);
+
+/// The kernel's executable, followed by its stack, init heap, and initfs.
+///
+/// The bootloader maps all of these with a single table of 2Mib pages, so together they
+/// must fit within 1Gib. The kernel's linker script must place the kernel at `start`.
+pub const KERNEL_IMAGE: VirtRegion =
+    VirtRegion::new("kernel image", 0xffff_ffff_8000_0000, 1 * GIB);
+
+/// The size of the heap the bootloader hands to the kernel, before the kernel has a
+/// physical memory manager of its own.
+pub const KERNEL_INIT_HEAP_SIZE: usize = 16 * MIB;
+
+/// The size of the physical region the bootloader reserves for the kernel's pstore, which
+/// keeps the log of a panic across a warm reboot.
+///
+/// The bootloader places it at the top of the identity map, since that stays at the same
+/// address from boot to boot.
+pub const PSTORE_SIZE: usize = 64 * KIB;
+
+/// Every fixed region, for printing the layout
+pub const REGIONS: &[VirtRegion] = &[
+    IDENTITY_MAP,
+    USER_MMAP,
+    USER_STACKS,
+    MMIO_WINDOW,
+    KERNEL_IMAGE,
+];
+
+const _: () = {
+    assert!(!USER_SPACE.overlaps(&KERNEL_SPACE));
+
+    assert!(USER_SPACE.contains_region(&IDENTITY_MAP));
+    assert!(USER_SPACE.contains_region(&USER_MMAP));
+    assert!(USER_SPACE.contains_region(&USER_STACKS));
+    assert!(KERNEL_SPACE.contains_region(&MMIO_WINDOW));
+    assert!(KERNEL_SPACE.contains_region(&KERNEL_IMAGE));
+
+    // The identity map is gone by the time processes exist, so it may share addresses
+    // with them, but nothing else can share addresses with anything.
+    let mut i = 1;
+    while i < REGIONS.len() {
+        let mut j = i + 1;
+        while j < REGIONS.len() {
+            assert!(!REGIONS[i].overlaps(&REGIONS[j]), "Regions overlap");
+            j += 1;
+        }
+        i += 1;
+    }
+
+    assert!(KERNEL_IMAGE.start % PAGE_2M == 0);
+    assert!(KERNEL_INIT_HEAP_SIZE % PAGE_2M == 0);
+    assert!(KERNEL_INIT_HEAP_SIZE < KERNEL_IMAGE.len());
+};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_region_bounds() {
+        let region = VirtRegion::new("test", 0x1000, 0x1000);
+
+        assert_eq!(region.last, 0x1fff);
+        assert_eq!(region.end(), Some(0x2000));
+        assert!(region.contains(0x1000));
+        assert!(region.contains(0x1fff));
+        assert!(!region.contains(0x2000));
+        assert!(region.contains_range(0x1800, 0x800));
+        assert!(!region.contains_range(0x1800, 0x801));
+        assert!(!region.contains_range(usize::MAX, 2));
+    }
+
+    #[test]
+    fn test_region_overlaps() {
+        let a = VirtRegion::new("a", 0x1000, 0x1000);
+        let b = VirtRegion::new("b", 0x2000, 0x1000);
+        let c = VirtRegion::new("c", 0x1fff, 2);
+
+        assert!(!a.overlaps(&b));
+        assert!(a.overlaps(&c));
+        assert!(b.overlaps(&c));
+        assert_eq!(KERNEL_SPACE.end(), None);
+        assert!(KERNEL_SPACE.overlaps(&KERNEL_IMAGE));
+    }
+}
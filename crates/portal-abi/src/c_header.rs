@@ -0,0 +1,518 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Renders a portal into a C header.
+//!
+//! Every type is rendered with the same layout Rust gives it, so C code can fill in a
+//! syscall's input and read its output directly. The header checks each struct's size and
+//! field offsets against [`Portal::layout`] when it is compiled.
+
+use crate::{AbiError, Field, Layout, Portal, Type, TypeDef, TypeDefKind};
+use portal::syscall::{SYSCALL_BAD_RESP, SYSCALL_CALLER_ID, SYSCALL_OKAY_RESP};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// C keywords which are valid Rust identifiers, and need renaming
+const C_KEYWORDS: &[&str] = &[
+    "auto", "bool", "case", "char", "default", "double", "float", "goto", "inline", "int", "long",
+    "register", "restrict", "short", "signed", "sizeof", "switch", "typedef", "union", "unsigned",
+    "void", "volatile",
+];
+
+/// Render the portal's C header
+pub fn render(portal: &Portal) -> Result<String, AbiError> {
+    CHeader::new(portal).render()
+}
+
+struct CHeader<'a> {
+    portal: &'a Portal,
+    /// The prefix of every item in the header (`vera_portal`)
+    prefix: String,
+    /// The prefix of every macro in the header (`VERA_PORTAL`)
+    upper_prefix: String,
+}
+
+impl<'a> CHeader<'a> {
+    fn new(portal: &'a Portal) -> Self {
+        Self {
+            portal,
+            prefix: portal.service.clone(),
+            upper_prefix: portal.service.to_uppercase(),
+        }
+    }
+
+    fn render(&self) -> Result<String, AbiError> {
+        let mut h = String::new();
+        let prefix = &self.prefix;
+        let upper = &self.upper_prefix;
+        let version = self.portal.version;
+
+        writeln!(
+            h,
+            "/*\n * Generated by portal-abi from the {prefix} IDL, do not edit.\n *\n * \
+            {} syscall ABI, version {version}.\n */",
+            self.portal.name
+        )
+        .unwrap();
+        writeln!(h, "#ifndef {upper}_H\n#define {upper}_H\n").unwrap();
+        writeln!(
+            h,
+            "#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n"
+        )
+        .unwrap();
+
+        writeln!(h, "#define {upper}_ABI_VERSION {version}").unwrap();
+        writeln!(h, "#define {upper}_SYSCALL_KIND {SYSCALL_CALLER_ID}").unwrap();
+        writeln!(h, "#define {upper}_SYSCALL_OKAY {SYSCALL_OKAY_RESP}").unwrap();
+        writeln!(h, "#define {upper}_SYSCALL_BAD {SYSCALL_BAD_RESP}\n").unwrap();
+
+        for endpoint in self.portal.endpoints.iter() {
+            writeln!(
+                h,
+                "#define {upper}_EVENT_{} {}",
+                endpoint.name.to_uppercase(),
+                endpoint.id
+            )
+            .unwrap();
+        }
+        writeln!(h).unwrap();
+
+        // Forward declare every type, so they can be pointed to before they are defined
+        for type_def in self.portal.types.iter() {
+            writeln!(h, "struct {};", self.type_name(&type_def.name)).unwrap();
+        }
+        writeln!(h).unwrap();
+
+        for type_def in self.types_in_dependency_order()? {
+            self.render_type(&mut h, type_def)?;
+        }
+
+        // Each endpoint's arguments are a struct in the input, while the return value is
+        // written directly into the output.
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for endpoint in self.portal.endpoints.iter() {
+            let member = member_name(&endpoint.name);
+
+            inputs.extend(self.fields_struct(&endpoint.args, &member)?);
+            outputs.extend(self.decl(&endpoint.returns, &member)?);
+        }
+        render_event_struct(&mut h, &format!("{prefix}_input"), &inputs);
+        render_event_struct(&mut h, &format!("{prefix}_output"), &outputs);
+        self.render_layout_checks(&mut h)?;
+
+        writeln!(
+            h,
+            "/*\n * Call an endpoint, returning {upper}_SYSCALL_OKAY if the call succeeded.\n \
+            *\n * The input's event must be set to the endpoint being called.\n */"
+        )
+        .unwrap();
+        writeln!(
+            h,
+            "static inline uint64_t {prefix}_syscall(const struct {prefix}_input *input,\n    \
+            struct {prefix}_output *output)\n{{"
+        )
+        .unwrap();
+        writeln!(h, "    uint64_t result = {upper}_SYSCALL_KIND;").unwrap();
+        writeln!(
+            h,
+            "    uint64_t packed_len = ((uint64_t)sizeof(*input) << 32) | sizeof(*output);"
+        )
+        .unwrap();
+        writeln!(
+            h,
+            "    register uint64_t packed_version __asm__(\"r8\") =\n        \
+            ((uint64_t){upper}_ABI_VERSION << 32) | {upper}_ABI_VERSION;\n"
+        )
+        .unwrap();
+        writeln!(
+            h,
+            "    __asm__ volatile(\"syscall\"\n        \
+            : \"+a\"(result), \"+D\"(input), \"+S\"(output), \"+d\"(packed_len), \
+            \"+r\"(packed_version)\n        \
+            :\n        \
+            : \"rcx\", \"r9\", \"r10\", \"r11\", \"memory\");\n"
+        )
+        .unwrap();
+        writeln!(h, "    return result;\n}}\n").unwrap();
+
+        writeln!(h, "#endif /* {upper}_H */").unwrap();
+
+        Ok(h)
+    }
+
+    /// Check the size of every struct, and the offset of every field, matches Rust
+    fn render_layout_checks(&self, h: &mut String) -> Result<(), AbiError> {
+        let prefix = &self.prefix;
+        writeln!(
+            h,
+            "/* The layouts Rust gives these types, so a stale header fails to build */"
+        )
+        .unwrap();
+
+        for type_def in self.portal.types.iter() {
+            let ty = Type::Named(type_def.name.clone());
+            if self.is_zero_sized(&ty)? {
+                continue;
+            }
+
+            let name = format!("struct {}", self.type_name(&type_def.name));
+            push_static_assert(h, &format!("sizeof({name})"), self.portal.layout(&ty)?.size);
+
+            if let TypeDefKind::Struct(fields) = &type_def.kind {
+                let (_, offsets) = self.portal.fields_layout(fields)?;
+
+                for (field, offset) in fields.iter().zip(offsets) {
+                    if !self.is_zero_sized(&field.ty)? {
+                        let member = member_name(&field.name);
+                        push_static_assert(h, &format!("offsetof({name}, {member})"), offset);
+                    }
+                }
+            }
+        }
+
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for endpoint in self.portal.endpoints.iter() {
+            inputs.push(self.portal.fields_layout(&endpoint.args)?.0);
+            outputs.push(self.portal.layout(&endpoint.returns)?);
+        }
+
+        let event = Layout::new(8, 8);
+        push_static_assert(
+            h,
+            &format!("sizeof(struct {prefix}_input)"),
+            Layout::of_tagged(event, inputs).size,
+        );
+        push_static_assert(
+            h,
+            &format!("sizeof(struct {prefix}_output)"),
+            Layout::of_tagged(event, outputs).size,
+        );
+        writeln!(h).unwrap();
+
+        Ok(())
+    }
+
+    fn render_type(&self, h: &mut String, type_def: &TypeDef) -> Result<(), AbiError> {
+        let name = self.type_name(&type_def.name);
+
+        match &type_def.kind {
+            TypeDefKind::Struct(fields) => {
+                // Structs without any fields take up no space, so they are only declared
+                if self.is_zero_sized(&Type::Named(type_def.name.clone()))? {
+                    return Ok(());
+                }
+
+                push_comment(h, &type_def.docs, 0);
+                writeln!(h, "struct {name} {{").unwrap();
+                for field in fields.iter() {
+                    if let Some(decl) = self.decl(&field.ty, &member_name(&field.name))? {
+                        push_comment(h, &field.docs, 1);
+                        writeln!(h, "    {decl};").unwrap();
+                    }
+                }
+                writeln!(h, "}};\n").unwrap();
+            }
+            TypeDefKind::Enum(variants) => {
+                // Enums without any variants can never be made, so they are only declared
+                if variants.is_empty() {
+                    return Ok(());
+                }
+
+                push_comment(h, &type_def.docs, 0);
+                writeln!(h, "enum {name}_tag {{").unwrap();
+                for (tag, variant) in variants.iter().enumerate() {
+                    push_comment(h, &variant.docs, 1);
+                    writeln!(
+                        h,
+                        "    {}_{}_{} = {tag},",
+                        self.upper_prefix,
+                        snake_case(&type_def.name).to_uppercase(),
+                        snake_case(&variant.name).to_uppercase()
+                    )
+                    .unwrap();
+                }
+                writeln!(h, "}};\n").unwrap();
+
+                let mut members = Vec::new();
+                for variant in variants.iter() {
+                    let member = member_name(&snake_case(&variant.name));
+
+                    if let Some(member) = self.fields_struct(&variant.fields, &member)? {
+                        members.push(member);
+                    }
+                }
+
+                writeln!(h, "struct {name} {{").unwrap();
+                writeln!(h, "    uint32_t tag;").unwrap();
+                push_union(h, &members, 1);
+                writeln!(h, "}};\n").unwrap();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// An anonymous struct of `fields` named `name`, or `None` if it would be empty
+    fn fields_struct(&self, fields: &[Field], name: &str) -> Result<Option<String>, AbiError> {
+        let mut decls = Vec::new();
+        for field in fields.iter() {
+            if let Some(decl) = self.decl(&field.ty, &member_name(&field.name))? {
+                decls.push(decl);
+            }
+        }
+
+        if decls.is_empty() {
+            return Ok(None);
+        }
+
+        // Always a member of a union, so it is indented to match
+        Ok(Some(format!(
+            "struct {{\n{}        }} {name}",
+            decls
+                .iter()
+                .map(|decl| format!("            {decl};\n"))
+                .collect::<String>()
+        )))
+    }
+
+    /// The C name of a type the portal defined
+    fn type_name(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix, snake_case(name))
+    }
+
+    /// If this type takes up no space, and so is left out of C structs
+    fn is_zero_sized(&self, ty: &Type) -> Result<bool, AbiError> {
+        Ok(match ty {
+            Type::Unit | Type::Never => true,
+            Type::Array(len, to) => *len == 0 || self.is_zero_sized(to)?,
+            Type::Named(name) => match &self.portal.type_def(name)?.kind {
+                TypeDefKind::Struct(fields) => {
+                    for field in fields.iter() {
+                        if !self.is_zero_sized(&field.ty)? {
+                            return Ok(false);
+                        }
+                    }
+
+                    true
+                }
+                TypeDefKind::Enum(variants) => variants.is_empty(),
+            },
+            _ => false,
+        })
+    }
+
+    /// The C declaration of `name` with type `ty`, or `None` if `ty` takes up no space
+    fn decl(&self, ty: &Type, name: &str) -> Result<Option<String>, AbiError> {
+        if self.is_zero_sized(ty)? {
+            return Ok(None);
+        }
+
+        Ok(Some(match ty {
+            Type::Bool => format!("bool {name}"),
+            Type::Int { signed, bits } => {
+                format!("{}int{bits}_t {name}", if *signed { "" } else { "u" })
+            }
+            Type::Usize => format!("uint64_t {name}"),
+            Type::Named(type_name) => format!("struct {} {name}", self.type_name(type_name)),
+            Type::Array(len, to) => return self.decl(to, &format!("{name}[{len}]")),
+            Type::Ref { is_mut, to } | Type::Ptr { is_mut, to } => {
+                let constness = if *is_mut { "" } else { "const " };
+
+                match to.as_ref() {
+                    Type::Str => {
+                        format!("struct {{ {constness}uint8_t *ptr; uint64_t len; }} {name}")
+                    }
+                    Type::Slice(to) => {
+                        let ptr = self.decl(to, "*ptr")?.unwrap_or_else(|| "void *ptr".into());
+
+                        format!("struct {{ {constness}{ptr}; uint64_t len; }} {name}")
+                    }
+                    Type::Array(..) => {
+                        let pointee = self.decl(to, &format!("(*{name})"))?;
+                        format!("{constness}{}", pointee.unwrap_or(format!("void *{name}")))
+                    }
+                    to => {
+                        let pointee = self.decl(to, &format!("*{name}"))?;
+                        format!("{constness}{}", pointee.unwrap_or(format!("void *{name}")))
+                    }
+                }
+            }
+            Type::Result(ok, err) => {
+                let members: Vec<_> = [self.decl(ok, "ok")?, self.decl(err, "err")?]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+
+                if members.is_empty() {
+                    format!("struct {{ uint32_t tag; }} {name}")
+                } else {
+                    format!(
+                        "struct {{ uint32_t tag; union {{ {}; }}; }} {name}",
+                        members.join("; ")
+                    )
+                }
+            }
+            unsupported => return Err(AbiError::Unsupported(unsupported.to_string())),
+        }))
+    }
+
+    /// The portal's types, ordered so each type is defined before it is used by value
+    fn types_in_dependency_order(&self) -> Result<Vec<&'a TypeDef>, AbiError> {
+        let mut ordered = Vec::new();
+        let mut visited = HashSet::new();
+
+        for type_def in self.portal.types.iter() {
+            self.visit_type(type_def, &mut visited, &mut ordered)?;
+        }
+
+        Ok(ordered)
+    }
+
+    fn visit_type(
+        &self,
+        type_def: &'a TypeDef,
+        visited: &mut HashSet<&'a str>,
+        ordered: &mut Vec<&'a TypeDef>,
+    ) -> Result<(), AbiError> {
+        if !visited.insert(&type_def.name) {
+            return Ok(());
+        }
+
+        let fields: Vec<&Field> = match &type_def.kind {
+            TypeDefKind::Struct(fields) => fields.iter().collect(),
+            TypeDefKind::Enum(variants) => variants.iter().flat_map(|v| v.fields.iter()).collect(),
+        };
+
+        for field in fields {
+            let mut dependencies = Vec::new();
+            by_value_names(&field.ty, &mut dependencies);
+
+            for dependency in dependencies {
+                let dependency = self.portal.type_def(dependency)?;
+                self.visit_type(dependency, visited, ordered)?;
+            }
+        }
+
+        ordered.push(type_def);
+        Ok(())
+    }
+}
+
+/// The names of every type `ty` contains by value (and not behind a pointer)
+fn by_value_names<'a>(ty: &'a Type, names: &mut Vec<&'a str>) {
+    match ty {
+        Type::Named(name) => names.push(name),
+        Type::Array(_, to) => by_value_names(to, names),
+        Type::Result(ok, err) => {
+            by_value_names(ok, names);
+            by_value_names(err, names);
+        }
+        _ => (),
+    }
+}
+
+/// Render a struct tagged by an endpoint's event, with a union of each endpoint's members
+fn render_event_struct(h: &mut String, name: &str, members: &[String]) {
+    writeln!(h, "struct {name} {{").unwrap();
+    writeln!(h, "    uint64_t event;").unwrap();
+    push_union(h, members, 1);
+    writeln!(h, "}};\n").unwrap();
+}
+
+/// Push a check that `expr` is `value`, which fails when the header is compiled otherwise
+fn push_static_assert(h: &mut String, expr: &str, value: usize) {
+    writeln!(
+        h,
+        "_Static_assert({expr} == {value}, \"{expr} does not match Rust\");"
+    )
+    .unwrap();
+}
+
+/// Push an anonymous union of `members`, if there are any
+fn push_union(h: &mut String, members: &[String], indent: usize) {
+    if members.is_empty() {
+        return;
+    }
+
+    let indent = "    ".repeat(indent);
+    writeln!(h, "{indent}union {{").unwrap();
+    for member in members.iter() {
+        writeln!(h, "{indent}    {member};").unwrap();
+    }
+    writeln!(h, "{indent}}};").unwrap();
+}
+
+/// Push doc lines as a C comment
+fn push_comment(h: &mut String, docs: &[String], indent: usize) {
+    let indent = "    ".repeat(indent);
+    let docs: Vec<_> = docs.iter().map(|line| line.replace("*/", "* /")).collect();
+
+    match docs.as_slice() {
+        [] => (),
+        [line] => writeln!(h, "{indent}/* {line} */").unwrap(),
+        lines => {
+            writeln!(h, "{indent}/*").unwrap();
+            for line in lines {
+                if line.is_empty() {
+                    writeln!(h, "{indent} *").unwrap();
+                } else {
+                    writeln!(h, "{indent} * {line}").unwrap();
+                }
+            }
+            writeln!(h, "{indent} */").unwrap();
+        }
+    }
+}
+
+/// A field or endpoint name that is safe to use in C
+fn member_name(name: &str) -> String {
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else if C_KEYWORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.into()
+    }
+}
+
+/// Convert a `CamelCase` name into `snake_case`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if !snake.is_empty() {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+
+    snake
+}
@@ -0,0 +1,707 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Generates a human readable ABI reference, and C headers, from a portal's IDL export.
+//!
+//! Syscall portals export their ABI as line based IDL text (for example
+//! `vera_portal::VERA_PORTAL_IDL`), which this crate parses into a [`Portal`] and renders
+//! with [`markdown::render`] and [`c_header::render`].
+
+use std::fmt::Display;
+
+pub mod c_header;
+pub mod markdown;
+
+/// An error from reading a portal's IDL, or rendering it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiError {
+    /// The IDL on `line` could not be parsed
+    Parse { line: usize, message: String },
+    /// This type has no stable layout, so it cannot be described to other languages
+    Unsupported(String),
+    /// A type was referenced that the portal never defined
+    UnknownType(String),
+}
+
+impl Display for AbiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbiError::Parse { line, message } => write!(f, "IDL line {line}: {message}"),
+            AbiError::Unsupported(ty) => write!(f, "'{ty}' has no stable ABI"),
+            AbiError::UnknownType(name) => write!(f, "'{name}' was never defined"),
+        }
+    }
+}
+
+impl std::error::Error for AbiError {}
+
+/// A syscall portal, read from its IDL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Portal {
+    /// The portal's trait name (`VeraPortal`)
+    pub name: String,
+    /// The portal's service name (`vera_portal`)
+    pub service: String,
+    /// The syscall ABI version both sides of a syscall must agree on
+    pub version: u32,
+    pub docs: Vec<String>,
+    pub endpoints: Vec<Endpoint>,
+    pub types: Vec<TypeDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    /// The event id, which is also the endpoint's tag in the syscall's input and output
+    pub id: u64,
+    pub name: String,
+    pub is_unsafe: bool,
+    pub docs: Vec<String>,
+    pub args: Vec<Field>,
+    pub returns: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// The field's name, or its index for tuple fields
+    pub name: String,
+    pub ty: Type,
+    pub docs: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDefKind {
+    Struct(Vec<Field>),
+    Enum(Vec<Variant>),
+}
+
+/// A type the portal defines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDef {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub kind: TypeDefKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Result(Box<Type>, Box<Type>),
+    Never,
+    Unit,
+    Bool,
+    Int { signed: bool, bits: u8 },
+    Usize,
+    Str,
+    String,
+    Slice(Box<Type>),
+    Array(usize, Box<Type>),
+    Vec(Box<Type>),
+    Ref { is_mut: bool, to: Box<Type> },
+    Ptr { is_mut: bool, to: Box<Type> },
+    Named(String),
+    External(String),
+    Unknown(String),
+}
+
+impl Type {
+    /// Parse a type from the front of `tokens`
+    fn parse<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let Some(token) = tokens.next() else {
+            return Err("Expected a type".into());
+        };
+
+        let mut name = || {
+            tokens
+                .next()
+                .map(|name| name.to_string())
+                .ok_or_else(|| format!("Expected a name after '{token}'"))
+        };
+
+        Ok(match token {
+            "result" => Self::Result(
+                Box::new(Self::parse(tokens)?),
+                Box::new(Self::parse(tokens)?),
+            ),
+            "never" => Self::Never,
+            "unit" => Self::Unit,
+            "bool" => Self::Bool,
+            "usize" => Self::Usize,
+            "str" => Self::Str,
+            "string" => Self::String,
+            "slice" => Self::Slice(Box::new(Self::parse(tokens)?)),
+            "array" => {
+                let len = name()?
+                    .parse()
+                    .map_err(|_| "Expected an array length".to_string())?;
+
+                Self::Array(len, Box::new(Self::parse(tokens)?))
+            }
+            "vec" => Self::Vec(Box::new(Self::parse(tokens)?)),
+            "ref" | "refmut" => Self::Ref {
+                is_mut: token == "refmut",
+                to: Box::new(Self::parse(tokens)?),
+            },
+            "ptr" | "ptrmut" => Self::Ptr {
+                is_mut: token == "ptrmut",
+                to: Box::new(Self::parse(tokens)?),
+            },
+            "named" => Self::Named(name()?),
+            "external" => Self::External(name()?),
+            "unknown" => Self::Unknown(name()?),
+            int => {
+                let (signed, bits) = int.split_at(1);
+
+                match (signed, bits.parse()) {
+                    ("i", Ok(bits @ (8 | 16 | 32 | 64))) => Self::Int { signed: true, bits },
+                    ("u", Ok(bits @ (8 | 16 | 32 | 64))) => Self::Int {
+                        signed: false,
+                        bits,
+                    },
+                    _ => return Err(format!("Unknown type '{int}'")),
+                }
+            }
+        })
+    }
+
+    /// If this type takes up no space (like `()` or `!`)
+    pub fn is_zero_sized(&self) -> bool {
+        matches!(self, Type::Unit | Type::Never)
+    }
+}
+
+/// Types are displayed as they are written in Rust
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Result(ok, err) => write!(f, "Result<{ok}, {err}>"),
+            Type::Never => write!(f, "!"),
+            Type::Unit => write!(f, "()"),
+            Type::Bool => write!(f, "bool"),
+            Type::Int { signed, bits } => write!(f, "{}{bits}", if *signed { "i" } else { "u" }),
+            Type::Usize => write!(f, "usize"),
+            Type::Str => write!(f, "str"),
+            Type::String => write!(f, "String"),
+            Type::Slice(to) => write!(f, "[{to}]"),
+            Type::Array(len, to) => write!(f, "[{to}; {len}]"),
+            Type::Vec(to) => write!(f, "Vec<{to}>"),
+            Type::Ref { is_mut, to } => write!(f, "&{}{to}", if *is_mut { "mut " } else { "" }),
+            Type::Ptr { is_mut, to } => {
+                write!(f, "*{} {to}", if *is_mut { "mut" } else { "const" })
+            }
+            Type::Named(name) | Type::External(name) | Type::Unknown(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// The size and alignment of a type, as both Rust and C lay it out on x86_64
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub size: usize,
+    pub align: usize,
+}
+
+impl Layout {
+    pub const fn new(size: usize, align: usize) -> Self {
+        Self { size, align }
+    }
+
+    /// Lay `members` out one after another like a `repr(C)` struct, returning the struct's
+    /// layout and the offset of each member.
+    pub fn of_struct(members: impl IntoIterator<Item = Layout>) -> (Layout, Vec<usize>) {
+        let mut size = 0usize;
+        let mut align = 1;
+        let mut offsets = Vec::new();
+
+        for member in members {
+            size = size.next_multiple_of(member.align);
+            offsets.push(size);
+            size += member.size;
+            align = align.max(member.align);
+        }
+
+        (Layout::new(size.next_multiple_of(align), align), offsets)
+    }
+
+    /// Lay `members` over each other like a `repr(C)` union
+    pub fn of_union(members: impl IntoIterator<Item = Layout>) -> Layout {
+        let (size, align) = members
+            .into_iter()
+            .fold((0usize, 1usize), |(size, align), member| {
+                (size.max(member.size), align.max(member.align))
+            });
+
+        Layout::new(size.next_multiple_of(align), align)
+    }
+
+    /// A `tag` followed by a union of `variants`, like a `repr(C, u32)` enum
+    pub fn of_tagged(tag: Layout, variants: impl IntoIterator<Item = Layout>) -> Layout {
+        Self::of_struct([tag, Self::of_union(variants)]).0
+    }
+}
+
+/// Where `doc` lines are currently being attached
+enum DocTarget {
+    Portal,
+    Endpoint,
+    Type,
+    Variant,
+    Field,
+}
+
+impl Portal {
+    /// Parse a portal from its IDL text
+    pub fn parse(idl: &str) -> Result<Self, AbiError> {
+        let mut portal: Option<Portal> = None;
+        let mut doc_target = DocTarget::Portal;
+        let mut in_type = false;
+
+        for (line_index, line) in idl.lines().enumerate() {
+            let line_number = line_index + 1;
+            let error = |message: String| AbiError::Parse {
+                line: line_number,
+                message,
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let mut tokens = rest.split(' ').filter(|token| !token.is_empty());
+
+            if keyword == "portal" {
+                if portal.is_some() {
+                    return Err(error("Only one portal can be defined".into()));
+                }
+
+                let (Some(name), Some(service), Some(version)) =
+                    (tokens.next(), tokens.next(), tokens.next())
+                else {
+                    return Err(error("Expected 'portal <name> <service> <version>'".into()));
+                };
+
+                portal = Some(Portal {
+                    name: name.into(),
+                    service: service.into(),
+                    version: version
+                        .parse()
+                        .map_err(|_| error(format!("Invalid version '{version}'")))?,
+                    docs: Vec::new(),
+                    endpoints: Vec::new(),
+                    types: Vec::new(),
+                });
+                continue;
+            }
+
+            let Some(portal) = portal.as_mut() else {
+                return Err(error("Expected a 'portal' line first".into()));
+            };
+
+            match keyword {
+                "doc" => {
+                    let docs = match doc_target {
+                        DocTarget::Portal => Some(&mut portal.docs),
+                        DocTarget::Endpoint => portal.endpoints.last_mut().map(|e| &mut e.docs),
+                        DocTarget::Type => portal.types.last_mut().map(|t| &mut t.docs),
+                        DocTarget::Variant => match portal.types.last_mut().map(|t| &mut t.kind) {
+                            Some(TypeDefKind::Enum(variants)) => {
+                                variants.last_mut().map(|v| &mut v.docs)
+                            }
+                            _ => None,
+                        },
+                        DocTarget::Field => last_fields(portal)
+                            .and_then(|fields| fields.last_mut())
+                            .map(|f| &mut f.docs),
+                    };
+
+                    docs.ok_or_else(|| error("Nothing to document".into()))?
+                        .push(rest.into());
+                }
+                "endpoint" => {
+                    if in_type {
+                        return Err(error("Expected 'end' before this endpoint".into()));
+                    }
+
+                    let (Some(id), Some(name), Some(_kind)) =
+                        (tokens.next(), tokens.next(), tokens.next())
+                    else {
+                        return Err(error("Expected 'endpoint <id> <name> <kind>'".into()));
+                    };
+
+                    portal.endpoints.push(Endpoint {
+                        id: id
+                            .parse()
+                            .map_err(|_| error(format!("Invalid endpoint id '{id}'")))?,
+                        name: name.into(),
+                        is_unsafe: tokens.next() == Some("unsafe"),
+                        docs: Vec::new(),
+                        args: Vec::new(),
+                        // Replaced by the endpoint's 'returns' line
+                        returns: Type::Unit,
+                    });
+                    doc_target = DocTarget::Endpoint;
+                }
+                "arg" | "returns" => {
+                    let Some(endpoint) = portal.endpoints.last_mut().filter(|_| !in_type) else {
+                        return Err(error(format!("'{keyword}' must follow an endpoint")));
+                    };
+
+                    if keyword == "returns" {
+                        endpoint.returns = Type::parse(&mut tokens).map_err(error)?;
+                    } else {
+                        let name = tokens
+                            .next()
+                            .ok_or_else(|| error("Expected an argument name".into()))?;
+
+                        endpoint.args.push(Field {
+                            name: name.into(),
+                            ty: Type::parse(&mut tokens).map_err(error)?,
+                            docs: Vec::new(),
+                        });
+                    }
+                }
+                "struct" | "enum" => {
+                    if in_type {
+                        return Err(error("Expected 'end' before this type".into()));
+                    }
+
+                    let name = tokens
+                        .next()
+                        .ok_or_else(|| error("Expected a type name".into()))?;
+
+                    portal.types.push(TypeDef {
+                        name: name.into(),
+                        docs: Vec::new(),
+                        kind: if keyword == "struct" {
+                            TypeDefKind::Struct(Vec::new())
+                        } else {
+                            TypeDefKind::Enum(Vec::new())
+                        },
+                    });
+                    doc_target = DocTarget::Type;
+                    in_type = true;
+                }
+                "variant" => {
+                    let Some(TypeDefKind::Enum(variants)) = portal
+                        .types
+                        .last_mut()
+                        .filter(|_| in_type)
+                        .map(|t| &mut t.kind)
+                    else {
+                        return Err(error("'variant' must be inside an enum".into()));
+                    };
+
+                    let name = tokens
+                        .next()
+                        .ok_or_else(|| error("Expected a variant name".into()))?;
+                    variants.push(Variant {
+                        name: name.into(),
+                        docs: Vec::new(),
+                        fields: Vec::new(),
+                    });
+                    doc_target = DocTarget::Variant;
+                }
+                "field" => {
+                    let name = tokens
+                        .next()
+                        .ok_or_else(|| error("Expected a field name".into()))?;
+                    let ty = Type::parse(&mut tokens).map_err(error)?;
+
+                    let Some(fields) = last_fields(portal).filter(|_| in_type) else {
+                        return Err(error("'field' must be inside a struct or variant".into()));
+                    };
+
+                    fields.push(Field {
+                        name: name.into(),
+                        ty,
+                        docs: Vec::new(),
+                    });
+                    doc_target = DocTarget::Field;
+                }
+                "end" => {
+                    if !in_type {
+                        return Err(error("'end' without a struct or enum".into()));
+                    }
+
+                    in_type = false;
+                }
+                unknown => return Err(error(format!("Unknown keyword '{unknown}'"))),
+            }
+
+            if let Some(extra) = tokens.next().filter(|_| keyword != "doc") {
+                return Err(error(format!("Unexpected '{extra}'")));
+            }
+        }
+
+        if in_type {
+            return Err(AbiError::Parse {
+                line: idl.lines().count(),
+                message: "Expected 'end' before the end of the IDL".into(),
+            });
+        }
+
+        portal.ok_or(AbiError::Parse {
+            line: 0,
+            message: "Expected a 'portal' line".into(),
+        })
+    }
+
+    /// The layout Rust gives `ty`, which the C header matches
+    pub fn layout(&self, ty: &Type) -> Result<Layout, AbiError> {
+        Ok(match ty {
+            Type::Unit | Type::Never => Layout::new(0, 1),
+            Type::Bool => Layout::new(1, 1),
+            Type::Int { bits, .. } => Layout::new(*bits as usize / 8, *bits as usize / 8),
+            Type::Usize => Layout::new(8, 8),
+            Type::Array(len, to) => {
+                let to = self.layout(to)?;
+                Layout::new(to.size * len, to.align)
+            }
+            // Pointers to slices and strings carry their length after the pointer
+            Type::Ref { to, .. } | Type::Ptr { to, .. } => match to.as_ref() {
+                Type::Str | Type::Slice(_) => Layout::new(16, 8),
+                _ => Layout::new(8, 8),
+            },
+            Type::Result(ok, err) => {
+                Layout::of_tagged(Layout::new(4, 4), [self.layout(ok)?, self.layout(err)?])
+            }
+            Type::Named(name) => match &self.type_def(name)?.kind {
+                TypeDefKind::Struct(fields) => self.fields_layout(fields)?.0,
+                TypeDefKind::Enum(variants) if variants.is_empty() => Layout::new(0, 1),
+                TypeDefKind::Enum(variants) => {
+                    let mut members = Vec::new();
+                    for variant in variants.iter() {
+                        members.push(self.fields_layout(&variant.fields)?.0);
+                    }
+
+                    Layout::of_tagged(Layout::new(4, 4), members)
+                }
+            },
+            unsupported => return Err(AbiError::Unsupported(unsupported.to_string())),
+        })
+    }
+
+    /// The layout of `fields` as a `repr(C)` struct, and the offset of each field
+    pub fn fields_layout(&self, fields: &[Field]) -> Result<(Layout, Vec<usize>), AbiError> {
+        let mut layouts = Vec::new();
+        for field in fields.iter() {
+            layouts.push(self.layout(&field.ty)?);
+        }
+
+        Ok(Layout::of_struct(layouts))
+    }
+
+    /// Get a type the portal defined
+    pub fn type_def(&self, name: &str) -> Result<&TypeDef, AbiError> {
+        self.types
+            .iter()
+            .find(|type_def| type_def.name == name)
+            .ok_or_else(|| AbiError::UnknownType(name.into()))
+    }
+}
+
+/// The fields being defined, from either the last struct or the last enum variant
+fn last_fields(portal: &mut Portal) -> Option<&mut Vec<Field>> {
+    match &mut portal.types.last_mut()?.kind {
+        TypeDefKind::Struct(fields) => Some(fields),
+        TypeDefKind::Enum(variants) => variants.last_mut().map(|v| &mut v.fields),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE_IDL: &str = "\
+portal ExamplePortal example_portal 2
+doc An example portal
+endpoint 0 read event
+doc Read into `buf`
+arg handle u64
+arg buf refmut slice u8
+returns result usize named ReadError
+endpoint 3 status event unsafe
+returns named Status
+struct Status
+field flags array 4 u8
+doc Every flag
+field error ptr named ReadError
+field last_error named ReadError
+end
+enum ReadError
+variant Closed
+doc The handle was closed
+variant TooBig
+field 0 usize
+end
+";
+
+    #[test]
+    fn test_parse_example() {
+        let portal = Portal::parse(EXAMPLE_IDL).unwrap();
+
+        assert_eq!(portal.name, "ExamplePortal");
+        assert_eq!(portal.service, "example_portal");
+        assert_eq!(portal.version, 2);
+        assert_eq!(portal.docs, ["An example portal"]);
+
+        assert_eq!(portal.endpoints.len(), 2);
+        assert_eq!(portal.endpoints[0].docs, ["Read into `buf`"]);
+        assert_eq!(
+            portal.endpoints[0].args[1].ty,
+            Type::Ref {
+                is_mut: true,
+                to: Box::new(Type::Slice(Box::new(Type::Int {
+                    signed: false,
+                    bits: 8
+                })))
+            }
+        );
+        assert_eq!(
+            portal.endpoints[0].returns.to_string(),
+            "Result<usize, ReadError>"
+        );
+        assert_eq!(portal.endpoints[1].id, 3);
+        assert!(portal.endpoints[1].is_unsafe);
+
+        let TypeDefKind::Enum(variants) = &portal.type_def("ReadError").unwrap().kind else {
+            panic!("ReadError should be an enum");
+        };
+        assert_eq!(variants[0].docs, ["The handle was closed"]);
+        assert_eq!(variants[1].fields[0].ty, Type::Usize);
+
+        let TypeDefKind::Struct(fields) = &portal.type_def("Status").unwrap().kind else {
+            panic!("Status should be a struct");
+        };
+        assert_eq!(fields[0].docs, ["Every flag"]);
+        assert_eq!(fields[1].ty.to_string(), "*const ReadError");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Portal::parse("endpoint 0 read event"),
+            Err(AbiError::Parse {
+                line: 1,
+                message: "Expected a 'portal' line first".into()
+            })
+        );
+        assert!(matches!(
+            Portal::parse("portal A a 2\nendpoint 0 read event\nreturns u7"),
+            Err(AbiError::Parse { line: 3, .. })
+        ));
+        assert!(matches!(
+            Portal::parse("portal A a 2\nstruct B\nfield c u8"),
+            Err(AbiError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_render_example_header() {
+        let portal = Portal::parse(EXAMPLE_IDL).unwrap();
+        let header = c_header::render(&portal).unwrap();
+
+        assert!(header.contains("#define EXAMPLE_PORTAL_EVENT_STATUS 3"));
+        assert!(header.contains("struct { uint8_t *ptr; uint64_t len; } buf;"));
+        assert!(header.contains(
+            "struct { uint32_t tag; union { uint64_t ok; struct example_portal_read_error err; }; } read;"
+        ));
+        assert!(header.contains("uint8_t flags[4];"));
+        assert!(header.contains("const struct example_portal_read_error *error;"));
+        assert!(header.contains("struct example_portal_read_error last_error;"));
+
+        assert!(header.contains("_Static_assert(sizeof(struct example_portal_read_error) == 16,"));
+        assert!(header.contains("_Static_assert(sizeof(struct example_portal_status) == 32,"));
+        assert!(
+            header.contains(
+                "_Static_assert(offsetof(struct example_portal_status, last_error) == 16,"
+            )
+        );
+
+        // `Status` contains a `ReadError`, which has to be defined first
+        let read_error = header.find("struct example_portal_read_error {").unwrap();
+        let status = header.find("struct example_portal_status {").unwrap();
+        assert!(read_error < status);
+    }
+
+    #[test]
+    fn test_layout_matches_rust() {
+        use core::mem::{offset_of, size_of};
+        use portal::syscall::AbiResult;
+        use vera_portal::{FramebufferError, FramebufferInfo, MemoryInfo, TaskInfo, TaskState};
+
+        let portal = Portal::parse(vera_portal::VERA_PORTAL_IDL).unwrap();
+        let size = |name: &str| portal.layout(&Type::Named(name.into())).unwrap().size;
+
+        assert_eq!(size("FramebufferInfo"), size_of::<FramebufferInfo>());
+        assert_eq!(size("FramebufferError"), size_of::<FramebufferError>());
+        assert_eq!(size("MemoryInfo"), size_of::<MemoryInfo>());
+        assert_eq!(size("TaskInfo"), size_of::<TaskInfo>());
+        assert_eq!(size("TaskState"), size_of::<TaskState>());
+
+        let framebuffer_map = portal
+            .endpoints
+            .iter()
+            .find(|endpoint| endpoint.name == "framebuffer_map")
+            .unwrap();
+        assert_eq!(
+            portal.layout(&framebuffer_map.returns).unwrap().size,
+            size_of::<AbiResult<FramebufferInfo, FramebufferError>>()
+        );
+
+        let TypeDefKind::Struct(fields) = &portal.type_def("TaskInfo").unwrap().kind else {
+            panic!("TaskInfo should be a struct");
+        };
+        let (_, offsets) = portal.fields_layout(fields).unwrap();
+        assert_eq!(
+            offsets,
+            [
+                offset_of!(TaskInfo, pid),
+                offset_of!(TaskInfo, tid),
+                offset_of!(TaskInfo, name),
+                offset_of!(TaskInfo, state),
+                offset_of!(TaskInfo, nice),
+                offset_of!(TaskInfo, cpu_ns),
+                offset_of!(TaskInfo, stack_used),
+                offset_of!(TaskInfo, stack_len),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_vera_portal() {
+        let portal = Portal::parse(vera_portal::VERA_PORTAL_IDL).unwrap();
+
+        assert!(!portal.endpoints.is_empty());
+        assert!(c_header::render(&portal).is_ok());
+        assert!(markdown::render(&portal).contains("## Endpoints"));
+    }
+}
@@ -0,0 +1,65 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use anyhow::Context;
+use clap::Parser;
+use portal_abi::Portal;
+use std::path::PathBuf;
+
+/// Generate the syscall ABI reference and C headers from the portal definitions
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Where to write the generated files
+    #[arg(short, long, default_value = "target/abi")]
+    out: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    std::fs::create_dir_all(&args.out)
+        .with_context(|| format!("Could not create '{}'", args.out.display()))?;
+
+    let portal = Portal::parse(vera_portal::VERA_PORTAL_IDL)?;
+
+    let reference_path = args.out.join(format!("{}.md", portal.service));
+    let header_path = args.out.join(format!("{}.h", portal.service));
+
+    std::fs::write(&reference_path, portal_abi::markdown::render(&portal))
+        .with_context(|| format!("Could not write '{}'", reference_path.display()))?;
+    std::fs::write(&header_path, portal_abi::c_header::render(&portal)?)
+        .with_context(|| format!("Could not write '{}'", header_path.display()))?;
+
+    println!(
+        "{} (ABI version {}): {}, {}",
+        portal.name,
+        portal.version,
+        reference_path.display(),
+        header_path.display()
+    );
+
+    Ok(())
+}
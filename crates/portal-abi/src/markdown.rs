@@ -0,0 +1,232 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Renders a portal into a human readable ABI reference (Markdown).
+
+use crate::{Endpoint, Portal, Type, TypeDef, TypeDefKind};
+use portal::syscall::{SYSCALL_BAD_RESP, SYSCALL_CALLER_ID, SYSCALL_OKAY_RESP};
+use std::fmt::Write;
+
+/// Render the portal's ABI reference
+pub fn render(portal: &Portal) -> String {
+    let mut md = String::new();
+    let version = portal.version;
+
+    writeln!(md, "# {} syscall ABI (version {version})\n", portal.name).unwrap();
+    writeln!(
+        md,
+        "<!-- Generated by portal-abi from the {} IDL, do not edit. -->\n",
+        portal.service
+    )
+    .unwrap();
+    push_docs(&mut md, &portal.docs);
+
+    writeln!(md, "## Calling convention\n").unwrap();
+    writeln!(
+        md,
+        "Every endpoint is called with the `syscall` instruction, passing its arguments in \
+        an input, and receiving its return value in an output. Both are laid out like the \
+        `struct {0}_input` and `struct {0}_output` in `{0}.h`.\n",
+        portal.service
+    )
+    .unwrap();
+    writeln!(md, "| Register | Value |").unwrap();
+    writeln!(md, "|----------|-------|").unwrap();
+    writeln!(md, "| `rax` | `{SYSCALL_CALLER_ID}` |").unwrap();
+    writeln!(md, "| `rdi` | A pointer to the input |").unwrap();
+    writeln!(md, "| `rsi` | A pointer to the output |").unwrap();
+    writeln!(md, "| `rdx` | `(sizeof(input) << 32) \\| sizeof(output)` |").unwrap();
+    writeln!(
+        md,
+        "| `r8` | `({version} << 32) \\| {version}`, the input and output's ABI version |\n"
+    )
+    .unwrap();
+    writeln!(
+        md,
+        "The kernel returns `{SYSCALL_OKAY_RESP}` in `rax` if the call succeeded, \
+        `{SYSCALL_BAD_RESP}` if it did not understand the call (for example, if a size or \
+        pointer was wrong), or its own packed ABI versions if they did not match `r8`. \
+        Every other caller saved register (including `rcx` and `r11`) may be clobbered.\n"
+    )
+    .unwrap();
+
+    writeln!(md, "### Layout\n").unwrap();
+    writeln!(
+        md,
+        "- The input and output start with a `u64` tag, which is the endpoint's event, \
+        followed by a union of every endpoint's arguments (as a C struct, in the order they \
+        are declared) or return value."
+    )
+    .unwrap();
+    writeln!(
+        md,
+        "- `Result<T, E>` is returned as a `u32` tag (`0` for `Ok` and `1` for `Err`), \
+        followed by a union of `T` and `E`."
+    )
+    .unwrap();
+    writeln!(
+        md,
+        "- Enums with fields are a `u32` tag (the variant's index) followed by a union of \
+        each variant's fields, and enums without fields are just the `u32` tag."
+    )
+    .unwrap();
+    writeln!(
+        md,
+        "- `&str` and `&[T]` are passed as a pointer followed by a `u64` length."
+    )
+    .unwrap();
+    writeln!(
+        md,
+        "- `usize` is a `u64`, `bool` is a single byte, and `()` and `!` take up no space.\n"
+    )
+    .unwrap();
+
+    writeln!(md, "## Endpoints\n").unwrap();
+    writeln!(md, "| Event | Endpoint | Returns |").unwrap();
+    writeln!(md, "|-------|----------|---------|").unwrap();
+    for endpoint in portal.endpoints.iter() {
+        writeln!(
+            md,
+            "| {} | [`{}`](#{}) | `{}` |",
+            endpoint.id, endpoint.name, endpoint.name, endpoint.returns
+        )
+        .unwrap();
+    }
+    writeln!(md).unwrap();
+
+    for endpoint in portal.endpoints.iter() {
+        render_endpoint(&mut md, endpoint);
+    }
+
+    writeln!(md, "## Types\n").unwrap();
+    for type_def in portal.types.iter() {
+        render_type(&mut md, type_def);
+    }
+
+    md
+}
+
+fn render_endpoint(md: &mut String, endpoint: &Endpoint) {
+    writeln!(md, "### `{}`\n", endpoint.name).unwrap();
+
+    let args = endpoint
+        .args
+        .iter()
+        .map(|arg| format!("{}: {}", arg.name, arg.ty))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let returns = match endpoint.returns {
+        Type::Unit => String::new(),
+        ref returns => format!(" -> {returns}"),
+    };
+    let is_unsafe = if endpoint.is_unsafe { "unsafe " } else { "" };
+
+    writeln!(md, "```rust").unwrap();
+    writeln!(md, "{is_unsafe}fn {}({args}){returns};", endpoint.name).unwrap();
+    writeln!(md, "```\n").unwrap();
+    push_docs(md, &endpoint.docs);
+
+    writeln!(md, "Event `{}`.\n", endpoint.id).unwrap();
+}
+
+fn render_type(md: &mut String, type_def: &TypeDef) {
+    writeln!(md, "### `{}`\n", type_def.name).unwrap();
+    push_docs(md, &type_def.docs);
+
+    match &type_def.kind {
+        TypeDefKind::Struct(fields) => {
+            if fields.is_empty() {
+                writeln!(md, "A struct without any fields.\n").unwrap();
+                return;
+            }
+
+            writeln!(md, "| Field | Type | Description |").unwrap();
+            writeln!(md, "|-------|------|-------------|").unwrap();
+            for field in fields.iter() {
+                writeln!(
+                    md,
+                    "| `{}` | `{}` | {} |",
+                    field.name,
+                    field.ty,
+                    table_docs(&field.docs)
+                )
+                .unwrap();
+            }
+        }
+        TypeDefKind::Enum(variants) => {
+            if variants.is_empty() {
+                writeln!(md, "An enum without any variants.\n").unwrap();
+                return;
+            }
+
+            writeln!(md, "| Tag | Variant | Fields | Description |").unwrap();
+            writeln!(md, "|-----|---------|--------|-------------|").unwrap();
+            for (tag, variant) in variants.iter().enumerate() {
+                let fields = variant
+                    .fields
+                    .iter()
+                    .map(|field| format!("`{}: {}`", field.name, field.ty))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                writeln!(
+                    md,
+                    "| {tag} | `{}` | {fields} | {} |",
+                    variant.name,
+                    table_docs(&variant.docs)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    writeln!(md).unwrap();
+}
+
+/// Push doc lines as their own paragraphs, demoting their headings below ours
+fn push_docs(md: &mut String, docs: &[String]) {
+    if docs.is_empty() {
+        return;
+    }
+
+    for line in docs.iter() {
+        if line.starts_with('#') {
+            writeln!(md, "###{line}").unwrap();
+        } else {
+            writeln!(md, "{line}").unwrap();
+        }
+    }
+    writeln!(md).unwrap();
+}
+
+/// Docs squashed onto a single line, so they fit in a table
+fn table_docs(docs: &[String]) -> String {
+    docs.iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
@@ -25,7 +25,7 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 use proc_macro2::Span;
 use quote::format_ident;
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, rc::Rc};
 use syn::{Attribute, Ident, Visibility};
 
 #[cfg(any(feature = "ipc-client", feature = "ipc-server"))]
@@ -54,6 +54,11 @@ pub struct PortalMacroArgs {
     pub is_global: bool,
 }
 
+/// The version of the syscall ABI, which both sides of a syscall must agree on.
+///
+/// This should be bumped whenever the layout of the syscall enums changes.
+pub const SYSCALL_ABI_VERSION: u32 = 2;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProtocolKind {
     Syscall,
@@ -96,6 +101,14 @@ pub enum ProtocolVarType {
     Unsigned64(Span),
     UnsignedSize(Span),
     Unknown(Ident),
+    /// A type from another crate, written as a path (`some_crate::SomeType`).
+    ///
+    /// The portal cannot see how external types are laid out, so they must implement
+    /// `PortalConvert` themselves.
+    External {
+        span: Span,
+        path: syn::Path,
+    },
     UserDefined {
         span: Span,
         to: ProtocolDefine,
@@ -185,7 +198,8 @@ pub struct ProtocolEnumVarient {
 pub enum ProtocolEnumFields {
     None,
     Unnamed(Vec<ProtocolVarType>),
-    Named(HashMap<Ident, ProtocolVarType>),
+    /// Named fields, in the order they were declared
+    Named(Vec<(Ident, ProtocolVarType)>),
 }
 
 impl ProtocolVarType {
@@ -229,6 +243,7 @@ impl ProtocolVarType {
                 span: _,
                 is_mut: _,
             } => to.search(f),
+            ProtocolVarType::IpcVec { span: _, to } => to.search(f),
             _ => None,
         } {
             return Some(value);
@@ -277,6 +292,7 @@ impl ProtocolVarType {
                 span: _,
                 is_mut: _,
             } => to.search_mut(f),
+            ProtocolVarType::IpcVec { span: _, to } => to.search_mut(f),
             _ => None,
         } {
             return Some(value);
@@ -325,8 +341,8 @@ impl ProtocolVarType {
                                     var.check_allowed(portal_type)?;
                                 }
                             }
-                            ProtocolEnumFields::Named(hash_map) => {
-                                for var in hash_map.values() {
+                            ProtocolEnumFields::Named(fields) => {
+                                for (_, var) in fields {
                                     var.check_allowed(portal_type)?;
                                 }
                             }
@@ -344,6 +360,7 @@ impl ProtocolVarType {
                 }
             },
             (ProtocolKind::Ipc, ProtocolVarType::IpcString(_)) => Ok(()),
+            (ProtocolKind::Ipc, ProtocolVarType::External { .. }) => Ok(()),
             (ProtocolKind::Ipc, ProtocolVarType::IpcVec { span: _, to }) => {
                 to.check_allowed(portal_type)
             }
@@ -374,14 +391,40 @@ impl ProtocolVarType {
                 },
             ) => to.check_allowed(portal_type),
 
-            (protocol, var) => Err(syn::Error::new(
-                var.span(),
-                format!(
-                    "Cannot use '{}' with {:?} protocol!",
-                    var.span().source_text().as_deref().unwrap_or("??"),
-                    protocol
-                ),
-            )),
+            (protocol, var) => {
+                let help = match (protocol, var) {
+                    (ProtocolKind::Syscall, ProtocolVarType::IpcString(_)) => {
+                        ", use `&str` instead"
+                    }
+                    (ProtocolKind::Syscall, ProtocolVarType::IpcVec { .. }) => {
+                        ", use a slice `&[T]` instead"
+                    }
+                    (ProtocolKind::Syscall, ProtocolVarType::External { .. }) => {
+                        ", define the type in the endpoint's body instead"
+                    }
+                    (
+                        ProtocolKind::Ipc,
+                        ProtocolVarType::Str(_) | ProtocolVarType::RefTo { .. },
+                    ) => {
+                        ", references cannot be sent to another process, use an owned type like `String` instead"
+                    }
+                    (ProtocolKind::Ipc, ProtocolVarType::Array { .. }) => ", use `Vec<T>` instead",
+                    (ProtocolKind::Ipc, ProtocolVarType::PtrTo { .. }) => {
+                        ", pointers cannot be sent to another process"
+                    }
+                    _ => "",
+                };
+
+                Err(syn::Error::new(
+                    var.span(),
+                    format!(
+                        "Cannot use '{}' with {:?} protocol{}",
+                        var.span().source_text().as_deref().unwrap_or("??"),
+                        protocol,
+                        help
+                    ),
+                ))
+            }
         }
     }
 
@@ -416,6 +459,7 @@ impl ProtocolVarType {
             ProtocolVarType::Array { span, .. } => span.clone(),
             ProtocolVarType::Bool(span) => span.clone(),
             ProtocolVarType::IpcString(span) => span.clone(),
+            ProtocolVarType::External { span, .. } => span.clone(),
             ProtocolVarType::IpcVec { span, to: _ } => span.clone(),
         }
     }
@@ -467,7 +511,7 @@ impl ProtocolEnumFields {
                     .unwrap_or(false)
                 })
             }
-            ProtocolEnumFields::Named(hash_map) => hash_map.values().any(|var| {
+            ProtocolEnumFields::Named(fields) => fields.iter().any(|(_, var)| {
                 var.search(&|ty| match ty {
                     ProtocolVarType::RefTo { .. } => Some(true),
                     _ => None,
@@ -606,7 +650,7 @@ impl PortalMacro {
                                     search_var_type(var_type, &protocol_defines)
                                 });
                             }
-                            ProtocolEnumFields::Named(hash_map) => hash_map
+                            ProtocolEnumFields::Named(fields) => fields
                                 .iter_mut()
                                 .map(|(_, value)| value)
                                 .for_each(|var_type| search_var_type(var_type, &protocol_defines)),
@@ -639,7 +683,6 @@ impl PortalMacro {
         Ident::new(&new_str, self.trait_ident.span())
     }
 
-    #[cfg(any(feature = "ipc-client", feature = "ipc-server"))]
     pub fn get_service_name(&self) -> String {
         let mut new_str = String::new();
         for old_char in self.trait_ident.to_string().chars() {
@@ -670,6 +713,12 @@ impl PortalMacro {
         }
     }
 
+    /// The client request enum whose bulk data borrows from the received message
+    #[cfg(feature = "ipc-server")]
+    pub fn get_borrowed_input_enum_ident(&self) -> Ident {
+        format_ident!("{}ClientRequestRef", self.trait_ident)
+    }
+
     pub fn get_output_enum_ident(&self) -> Ident {
         if matches!(self.args.as_ref().unwrap().protocol_kind, ProtocolKind::Ipc) {
             Ident::new(
@@ -0,0 +1,240 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::ast;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
+use quote::TokenStreamExt;
+use quote::format_ident;
+use quote::quote;
+use syn::Attribute;
+
+/// A generator for the portal's IDL export
+///
+/// The IDL is a line based description of the portal's syscall ABI, which host tools
+/// (like `portal-abi`) read to describe the ABI to other languages. Each line starts with
+/// a keyword:
+///
+/// - `portal <Trait> <service> <abi version>`
+/// - `endpoint <id> <name> <event|handle> [unsafe]`, followed by its `arg <name> <type>`
+///   lines and a `returns <type>` line.
+/// - `struct <Name>` or `enum <Name>`, followed by their `variant <Name>` and
+///   `field <name> <type>` lines, and closed with `end`.
+/// - `doc <text>`, which documents the line before it.
+///
+/// Types are written in prefix form, for example `ref slice u8` for `&[u8]` or
+/// `result usize named RecvHandleError` for `Result<usize, RecvHandleError>`.
+pub struct PortalIdl<'a> {
+    portal: &'a ast::PortalMacro,
+}
+
+impl<'a> PortalIdl<'a> {
+    pub fn new(portal: &'a ast::PortalMacro) -> Self {
+        Self { portal }
+    }
+
+    /// Render the portal into its IDL text
+    pub fn render(&self) -> String {
+        let mut idl = String::new();
+
+        idl.push_str(&format!(
+            "portal {} {} {}\n",
+            self.portal.trait_ident,
+            self.portal.get_service_name(),
+            ast::SYSCALL_ABI_VERSION
+        ));
+        push_docs(&mut idl, &self.portal.doc_attributes);
+
+        for endpoint in self.portal.endpoints.iter() {
+            let kind = match endpoint.kind {
+                ast::ProtocolEndpointKind::Event => "event",
+                ast::ProtocolEndpointKind::Handle => "handle",
+            };
+            let is_unsafe = if endpoint.is_unsafe { " unsafe" } else { "" };
+
+            idl.push_str(&format!(
+                "endpoint {} {} {}{}\n",
+                endpoint.portal_id.0, endpoint.fn_ident, kind, is_unsafe
+            ));
+            push_docs(&mut idl, &endpoint.doc_attributes);
+
+            for input_arg in endpoint.input_args.iter() {
+                idl.push_str(&format!(
+                    "arg {} {}\n",
+                    input_arg.argument_ident,
+                    idl_type(&input_arg.ty)
+                ));
+            }
+
+            idl.push_str(&format!("returns {}\n", idl_type(&endpoint.output_arg.0)));
+        }
+
+        let user_defined_types = self
+            .portal
+            .endpoints
+            .iter()
+            .flat_map(|endpoint| endpoint.body.iter());
+
+        for user_defined in user_defined_types {
+            match user_defined {
+                ast::ProtocolDefine::DefinedEnum(ref_cell) => {
+                    let enum_def = ref_cell.borrow();
+
+                    idl.push_str(&format!("enum {}\n", enum_def.ident));
+                    push_docs(&mut idl, &enum_def.docs);
+
+                    for varient in enum_def.varients.iter() {
+                        idl.push_str(&format!("variant {}\n", varient.ident));
+                        push_docs(&mut idl, &varient.docs);
+
+                        match &varient.fields {
+                            ast::ProtocolEnumFields::None => (),
+                            ast::ProtocolEnumFields::Unnamed(fields) => {
+                                for (index, ty) in fields.iter().enumerate() {
+                                    idl.push_str(&format!("field {} {}\n", index, idl_type(ty)));
+                                }
+                            }
+                            ast::ProtocolEnumFields::Named(fields) => {
+                                for (name, ty) in fields.iter() {
+                                    idl.push_str(&format!("field {} {}\n", name, idl_type(ty)));
+                                }
+                            }
+                        }
+                    }
+
+                    idl.push_str("end\n");
+                }
+                ast::ProtocolDefine::DefinedStruct(ref_cell) => {
+                    let struct_def = ref_cell.borrow();
+
+                    idl.push_str(&format!("struct {}\n", struct_def.ident));
+                    push_docs(&mut idl, &struct_def.docs);
+
+                    for (index, item) in struct_def.items.iter().enumerate() {
+                        match &item.name {
+                            Some(name) => {
+                                idl.push_str(&format!("field {} {}\n", name, idl_type(&item.ty)))
+                            }
+                            None => {
+                                idl.push_str(&format!("field {} {}\n", index, idl_type(&item.ty)))
+                            }
+                        }
+                        push_docs(&mut idl, &item.docs);
+                    }
+
+                    idl.push_str("end\n");
+                }
+            }
+        }
+
+        idl
+    }
+}
+
+impl<'a> ToTokens for PortalIdl<'a> {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let const_ident = format_ident!("{}_IDL", self.portal.get_service_name().to_uppercase());
+        let idl = self.render();
+
+        tokens.append_all(quote! {
+            /// The IDL export of this portal's syscall ABI, see `portal-abi`.
+            pub const #const_ident: &str = #idl;
+        });
+    }
+}
+
+/// Push a `doc` line for each line of the given doc attributes
+fn push_docs(idl: &mut String, docs: &[Attribute]) {
+    for doc in docs.iter() {
+        let syn::Meta::NameValue(name_value) = &doc.meta else {
+            continue;
+        };
+        let syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit_str),
+            ..
+        }) = &name_value.value
+        else {
+            continue;
+        };
+
+        for line in lit_str.value().lines() {
+            let line = line.strip_prefix(' ').unwrap_or(line).trim_end();
+
+            if line.is_empty() {
+                idl.push_str("doc\n");
+            } else {
+                idl.push_str(&format!("doc {}\n", line));
+            }
+        }
+    }
+}
+
+/// Render a type in the IDL's prefix form
+fn idl_type(ty: &ast::ProtocolVarType) -> String {
+    match ty {
+        ast::ProtocolVarType::ResultKind { ok_ty, err_ty, .. } => {
+            format!("result {} {}", idl_type(ok_ty), idl_type(err_ty))
+        }
+        ast::ProtocolVarType::Never(_) => "never".into(),
+        ast::ProtocolVarType::Unit(_) => "unit".into(),
+        ast::ProtocolVarType::Bool(_) => "bool".into(),
+        ast::ProtocolVarType::Signed8(_) => "i8".into(),
+        ast::ProtocolVarType::Signed16(_) => "i16".into(),
+        ast::ProtocolVarType::Signed32(_) => "i32".into(),
+        ast::ProtocolVarType::Signed64(_) => "i64".into(),
+        ast::ProtocolVarType::Unsigned8(_) => "u8".into(),
+        ast::ProtocolVarType::Unsigned16(_) => "u16".into(),
+        ast::ProtocolVarType::Unsigned32(_) => "u32".into(),
+        ast::ProtocolVarType::Unsigned64(_) => "u64".into(),
+        ast::ProtocolVarType::UnsignedSize(_) => "usize".into(),
+        ast::ProtocolVarType::Unknown(ident) => format!("unknown {}", ident),
+        ast::ProtocolVarType::External { path, .. } => {
+            let path = path.to_token_stream().to_string().replace(' ', "");
+            format!("external {}", path)
+        }
+        ast::ProtocolVarType::UserDefined { to, .. } => format!("named {}", to.var_ident()),
+        ast::ProtocolVarType::IpcString(_) => "string".into(),
+        ast::ProtocolVarType::IpcVec { to, .. } => format!("vec {}", idl_type(to)),
+        ast::ProtocolVarType::Str(_) => "str".into(),
+        ast::ProtocolVarType::Array {
+            to, len: Some(len), ..
+        } => format!("array {} {}", len, idl_type(to)),
+        ast::ProtocolVarType::Array { to, len: None, .. } => format!("slice {}", idl_type(to)),
+        ast::ProtocolVarType::RefTo { is_mut, to, .. } => {
+            format!(
+                "{} {}",
+                if *is_mut { "refmut" } else { "ref" },
+                idl_type(to)
+            )
+        }
+        ast::ProtocolVarType::PtrTo { is_mut, to, .. } => {
+            format!(
+                "{} {}",
+                if *is_mut { "ptrmut" } else { "ptr" },
+                idl_type(to)
+            )
+        }
+    }
+}
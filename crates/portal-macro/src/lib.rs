@@ -27,6 +27,7 @@ use proc_macro::TokenStream;
 use syn::parse_macro_input;
 
 mod ast;
+mod idl_builder;
 mod parse;
 mod rust_builder;
 
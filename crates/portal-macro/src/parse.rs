@@ -23,7 +23,7 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, rc::Rc};
 
 use crate::ast;
 use proc_macro2::Span;
@@ -32,17 +32,99 @@ use syn::{
     Token, TraitItemFn, parse::Parse, spanned::Spanned,
 };
 
+/// The endpoint kinds, as they are written in an endpoint's attribute.
+const ENDPOINT_KINDS: &[&str] = &["event", "handle"];
+
+/// The protocols a portal can use.
+const PROTOCOL_KINDS: &[&str] = &["ipc", "syscall"];
+
+/// The options that can be given to `#[portal(...)]`.
+const PORTAL_OPTIONS: &[&str] = &["global", "protocol"];
+
+/// Every type a portal knows how to convert.
+const SUPPORTED_TYPES: &str = "`bool`, `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `usize`, \
+    `()`, `!`, `Result<T, E>`, `String` and `Vec<T>` (ipc), `str`, arrays, references and pointers (syscall), \
+    and `enum`s or `struct`s defined in an endpoint's body";
+
+/// Primitives that look like they should work, but a portal cannot convert.
+const UNSUPPORTED_PRIMITIVES: &[&str] = &["isize", "i128", "u128", "f32", "f64", "char"];
+
+/// Find the candidate closest to `found`, if any of them are close enough to be a typo.
+fn closest_match<'a>(found: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    fn distance(a: &str, b: &str) -> usize {
+        let b: Vec<char> = b.chars().collect();
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+
+        for (i, a_char) in a.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+
+            for (j, &b_char) in b.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = (diagonal + (a_char != b_char) as usize)
+                    .min(row[j] + 1)
+                    .min(above + 1);
+                diagonal = above;
+            }
+        }
+
+        row[b.len()]
+    }
+
+    let found = found.to_ascii_lowercase();
+    candidates
+        .iter()
+        .map(|candidate| (distance(&found, candidate), *candidate))
+        .filter(|&(distance, candidate)| distance <= (candidate.len() / 3).max(1))
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Format `candidates` as a list of options for an error message.
+fn one_of(candidates: &[&str], format_candidate: impl Fn(&str) -> String) -> String {
+    let mut list = String::new();
+
+    for (index, candidate) in candidates.iter().enumerate() {
+        if index != 0 {
+            list.push_str(if index + 1 == candidates.len() {
+                " or "
+            } else {
+                ", "
+            });
+        }
+
+        list.push_str(&format_candidate(candidate));
+    }
+
+    list
+}
+
+/// A 'did you mean' suggestion for `found`, or nothing if no candidate is close.
+fn suggestion(
+    found: &str,
+    candidates: &[&str],
+    format_candidate: impl Fn(&str) -> String,
+) -> String {
+    closest_match(found, candidates)
+        .map(|candidate| format!(", did you mean {}?", format_candidate(candidate)))
+        .unwrap_or_default()
+}
+
 impl Parse for ast::ProtocolKind {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let string: LitStr = input.parse()?;
-        let str_value = string.value();
-
-        if &str_value == "syscall" {
-            Ok(Self::Syscall)
-        } else if &str_value == "ipc" {
-            Ok(Self::Ipc)
-        } else {
-            Ok(Self::Invalid)
+
+        match string.value().as_str() {
+            "syscall" => Ok(Self::Syscall),
+            "ipc" => Ok(Self::Ipc),
+            unknown => Err(syn::Error::new(
+                string.span(),
+                format!(
+                    "Unknown protocol \"{unknown}\", expected {}{}",
+                    one_of(PROTOCOL_KINDS, |kind| format!("\"{kind}\"")),
+                    suggestion(unknown, PROTOCOL_KINDS, |kind| format!("\"{kind}\""))
+                ),
+            )),
         }
     }
 }
@@ -73,6 +155,18 @@ impl Parse for ast::PortalMacroArgs {
                 input.parse::<portal_keywords::protocol>()?;
                 input.parse::<Token![=]>()?;
                 protocol = Some(input.parse()?);
+            } else if input.peek(Ident) {
+                let option: Ident = input.parse()?;
+                let option_name = option.to_string();
+
+                return Err(syn::Error::new(
+                    option.span(),
+                    format!(
+                        "Unknown portal option `{option_name}`, expected {}{}",
+                        one_of(PORTAL_OPTIONS, |option| format!("`{option}`")),
+                        suggestion(&option_name, PORTAL_OPTIONS, |option| format!("`{option}`"))
+                    ),
+                ));
             } else {
                 return Err(lookahead.error());
             }
@@ -109,15 +203,7 @@ impl Parse for ast::PortalMacro {
                 inner.parse::<Token![;]>()?;
             }
 
-            let item_fn: ast::ProtocolEndpoint = match inner.parse() {
-                Ok(v) => v,
-                Err(err) => {
-                    return Err(syn::Error::new(
-                        err.span(),
-                        format!("Cannot parse endpoint function: {}", err),
-                    ));
-                }
-            };
+            let item_fn: ast::ProtocolEndpoint = inner.parse()?;
 
             endpoints.push(item_fn);
         }
@@ -131,18 +217,24 @@ impl Parse for ast::PortalMacro {
         };
 
         // Check for duplicate IDs
-        for (duplicate_id, _duplicate_source, duplicate_use) in
-            portal_macro.all_non_unique_portal_ids()
+        if let Some((duplicate_id, duplicate_source, duplicate_use)) =
+            portal_macro.all_non_unique_portal_ids().next()
         {
-            let _new_id = portal_macro.highest_id() + 1;
+            let new_id = portal_macro.highest_id() + 1;
 
-            return Err(syn::Error::new(
+            let mut error = syn::Error::new(
                 duplicate_use,
                 format!(
-                    "Cannot have two endpoint functions with the same ID {}",
-                    duplicate_id
+                    "Cannot have two endpoint functions with the same ID {}, the next free ID is {}",
+                    duplicate_id, new_id
                 ),
+            );
+            error.combine(syn::Error::new(
+                duplicate_source,
+                format!("ID {} is first used here", duplicate_id),
             ));
+
+            return Err(error);
         }
 
         Ok(portal_macro)
@@ -152,44 +244,50 @@ impl Parse for ast::PortalMacro {
 fn convert_attribute_to_id_kind(
     attribute: Attribute,
 ) -> syn::Result<(usize, Span, ast::ProtocolEndpointKind)> {
-    if attribute.path().is_ident("event") {
-        let name_value = attribute.meta.require_name_value()?;
-        match &name_value.value {
-            syn::Expr::Lit(syn::ExprLit {
-                lit: syn::Lit::Int(expr_lit),
-                ..
-            }) => {
-                let id = expr_lit.base10_parse()?;
-                Ok((id, expr_lit.span(), ast::ProtocolEndpointKind::Event))
-            }
-            _ => Err(syn::Error::new(
-                attribute.span(),
-                "Only integer literals are supported 'event' IDs",
-            )),
-        }
+    let (kind_name, kind) = if attribute.path().is_ident("event") {
+        ("event", ast::ProtocolEndpointKind::Event)
     } else if attribute.path().is_ident("handle") {
-        let name_value = attribute.meta.require_name_value()?;
-        match &name_value.value {
-            syn::Expr::Lit(syn::ExprLit {
-                lit: syn::Lit::Int(expr_lit),
-                ..
-            }) => {
-                let id = expr_lit.base10_parse()?;
-                Ok((id, expr_lit.span(), ast::ProtocolEndpointKind::Handle))
-            }
-            _ => Err(syn::Error::new(
-                attribute.span(),
-                "Only integer literals are supported 'handle' IDs",
-            )),
-        }
+        ("handle", ast::ProtocolEndpointKind::Handle)
     } else {
-        Err(syn::Error::new(
+        let path = attribute.path();
+        let attribute_name = path
+            .get_ident()
+            .map(|ident| ident.to_string())
+            .unwrap_or_else(|| path.span().source_text().unwrap_or_default());
+
+        return Err(syn::Error::new(
+            path.span(),
+            format!(
+                "Unknown endpoint kind `#[{attribute_name}]`, expected {}{}",
+                one_of(ENDPOINT_KINDS, |kind| format!("`#[{kind} = <id>]`")),
+                suggestion(&attribute_name, ENDPOINT_KINDS, |kind| format!(
+                    "`#[{kind} = <id>]`"
+                ))
+            ),
+        ));
+    };
+
+    let name_value = attribute.meta.require_name_value().map_err(|_| {
+        syn::Error::new(
             attribute.span(),
+            format!("Endpoint kind needs an ID, like `#[{kind_name} = 0]`"),
+        )
+    })?;
+
+    match &name_value.value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(expr_lit),
+            ..
+        }) => {
+            let id = expr_lit.base10_parse()?;
+            Ok((id, expr_lit.span(), kind))
+        }
+        value => Err(syn::Error::new(
+            value.span(),
             format!(
-                "Attribute '{}' not supported.",
-                attribute.span().source_text().as_deref().unwrap_or("??")
+                "Only integer literals are supported as '{kind_name}' IDs, like `#[{kind_name} = 0]`"
             ),
-        ))
+        )),
     }
 }
 
@@ -209,12 +307,21 @@ impl Parse for ast::ProtocolEndpoint {
         let (id, span, kind) = remaining
             .pop()
             .map(convert_attribute_to_id_kind)
-            .ok_or(syn::Error::new(input.span(), "Must define endpoint kind"))??;
+            .ok_or_else(|| {
+                syn::Error::new(
+                    sig.ident.span(),
+                    format!(
+                        "Must define endpoint kind for `{}`, add {} to it",
+                        sig.ident,
+                        one_of(ENDPOINT_KINDS, |kind| format!("`#[{kind} = <id>]`"))
+                    ),
+                )
+            })??;
 
-        if !remaining.is_empty() {
+        if let Some(extra) = remaining.first() {
             return Err(syn::Error::new(
-                span,
-                "Only one endpoint specifier is allowed",
+                extra.span(),
+                "Only one endpoint specifier is allowed, remove this one",
             ));
         }
 
@@ -242,7 +349,7 @@ impl Parse for ast::ProtocolEndpoint {
                     stmt => {
                         return Err(syn::Error::new(
                             stmt.span(),
-                            "Only `enum` definitions are currently supported",
+                            "Only `enum` and `struct` definitions are currently supported in an endpoint's body",
                         ));
                     }
                 }
@@ -330,7 +437,7 @@ impl TryFrom<&syn::Type> for ast::ProtocolVarType {
                                 _ => Err(syn::Error::new(
                                     type_path.span(),
                                     format!(
-                                        "Result '{}' only supports 2 generic arguments",
+                                        "Result '{}' needs an ok and an error type, like `Result<T, E>`",
                                         type_path.span().source_text().as_deref().unwrap_or("??")
                                     ),
                                 )),
@@ -383,6 +490,18 @@ impl TryFrom<&syn::Type> for ast::ProtocolVarType {
                     "usize" => Ok(Self::UnsignedSize(path.span())),
                     "str" => Ok(Self::Str(path.span())),
                     "String" => Ok(Self::IpcString(path.span())),
+                    unsupported if UNSUPPORTED_PRIMITIVES.contains(&unsupported) => {
+                        Err(syn::Error::new(
+                            type_path.span(),
+                            format!(
+                                "Type `{unsupported}` is not currently supported by portal, supported types are {SUPPORTED_TYPES}"
+                            ),
+                        ))
+                    }
+                    _ if type_path.path.segments.len() > 1 => Ok(Self::External {
+                        span: type_path.span(),
+                        path: type_path.path.clone(),
+                    }),
                     user_defined => Ok(Self::Unknown(Ident::new(user_defined, type_path.span()))),
                 }
             }
@@ -432,7 +551,7 @@ impl TryFrom<&syn::Type> for ast::ProtocolVarType {
                     Err(syn::Error::new(
                         type_tuple.span(),
                         format!(
-                            "Type '{}' is not currently supported by portal",
+                            "Type '{}' is not currently supported by portal, define a `struct` in the endpoint's body instead",
                             type_tuple.span().source_text().as_deref().unwrap_or("??")
                         ),
                     ))
@@ -441,7 +560,7 @@ impl TryFrom<&syn::Type> for ast::ProtocolVarType {
             _ => Err(syn::Error::new(
                 value.span(),
                 format!(
-                    "Type '{}' is not currently supported by portal",
+                    "Type '{}' is not currently supported by portal, supported types are {SUPPORTED_TYPES}",
                     value.span().source_text().as_deref().unwrap_or("??")
                 ),
             )),
@@ -470,7 +589,7 @@ impl TryFrom<&ItemStruct> for ast::ProtocolStructDef {
             } else {
                 return Err(syn::Error::new(
                     attr.span(),
-                    "Attribute not supported for portal defined enum",
+                    "Attribute not supported for portal defined struct, only doc comments are allowed",
                 ));
             }
         }
@@ -478,7 +597,7 @@ impl TryFrom<&ItemStruct> for ast::ProtocolStructDef {
         if !generics.params.is_empty() {
             return Err(syn::Error::new(
                 generics.span(),
-                "Portal defined enum cannot have any generics",
+                "Portal defined struct cannot have any generics",
             ));
         }
 
@@ -515,7 +634,7 @@ impl TryFrom<&Field> for ast::ProtocolStructItem {
             } else {
                 return Err(syn::Error::new(
                     attr.span(),
-                    "Attribute not supported for portal defined enum",
+                    "Attribute not supported for portal defined struct, only doc comments are allowed",
                 ));
             }
         }
@@ -549,7 +668,7 @@ impl TryFrom<&ItemEnum> for ast::ProtocolEnumDef {
             } else {
                 return Err(syn::Error::new(
                     attr.span(),
-                    "Attribute not supported for portal defined enum",
+                    "Attribute not supported for portal defined enum, only doc comments are allowed",
                 ));
             }
         }
@@ -599,18 +718,18 @@ impl TryFrom<&Fields> for ast::ProtocolEnumFields {
     fn try_from(value: &syn::Fields) -> Result<Self, Self::Error> {
         match value {
             Fields::Named(fields_named) => {
-                let mut map = HashMap::new();
+                let mut fields = Vec::new();
                 for field in fields_named.named.iter() {
-                    map.insert(
+                    fields.push((
                         field.ident.clone().ok_or(syn::Error::new(
                             fields_named.span(),
                             "Expected named field to have an ident",
                         ))?,
                         (&field.ty).try_into()?,
-                    );
+                    ));
                 }
 
-                Ok(Self::Named(map))
+                Ok(Self::Named(fields))
             }
             Fields::Unnamed(fields_unnamed) => {
                 let mut vec = Vec::new();
@@ -24,6 +24,7 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 */
 
 use crate::ast;
+use crate::idl_builder::PortalIdl;
 use proc_macro2::Span;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::ToTokens;
@@ -98,6 +99,23 @@ impl<'a> LifetimedProtocolVarType<'a> {
     }
 }
 
+/// A generator for the type an IPC server borrows an argument as
+///
+/// `Vec<u8>` and `String` arguments are borrowed from the received message as
+/// `&[u8]` and `&str`, every other type is the same as its owned version.
+#[cfg(feature = "ipc-server")]
+pub struct BorrowedProtocolVarType<'a> {
+    lifetime_ident: &'a Lifetime,
+    ty: &'a ast::ProtocolVarType,
+}
+
+#[cfg(feature = "ipc-server")]
+impl<'a> BorrowedProtocolVarType<'a> {
+    pub fn new(lifetime_ident: &'a Lifetime, ty: &'a ast::ProtocolVarType) -> Self {
+        Self { lifetime_ident, ty }
+    }
+}
+
 /// A generator for all the functions if they are intended to be global
 #[cfg(any(feature = "syscall-client", feature = "syscall-server"))]
 pub struct GlobalSyscallFunctionImpl<'a> {
@@ -174,6 +192,19 @@ impl<'a> PortalInfoStruct<'a> {
     }
 }
 
+/// A generator for the IPC conversions of the portal's user defined types
+#[cfg(any(feature = "ipc-client", feature = "ipc-server"))]
+pub struct PortalUserConvert<'a> {
+    portal: &'a ast::PortalMacro,
+}
+
+#[cfg(any(feature = "ipc-client", feature = "ipc-server"))]
+impl<'a> PortalUserConvert<'a> {
+    pub fn new(portal: &'a ast::PortalMacro) -> Self {
+        Self { portal }
+    }
+}
+
 /// Generate the Rust portal output tokens
 pub fn generate_rust_portal(portal: &ast::PortalMacro) -> TokenStream2 {
     portal.to_token_stream()
@@ -191,6 +222,8 @@ impl ToTokens for ast::PortalMacro {
         });
 
         if self.is_syscall_kind() {
+            PortalIdl::new(self).to_tokens(tokens);
+
             #[cfg(any(feature = "syscall-client", feature = "syscall-server"))]
             {
                 let input = PortalTranslationInputType::new(self);
@@ -231,8 +264,10 @@ impl ToTokens for ast::PortalMacro {
             #[cfg(any(feature = "ipc-client", feature = "ipc-server"))]
             {
                 let info_trait = PortalInfoStruct::new(self);
+                let user_convert = PortalUserConvert::new(self);
 
                 info_trait.to_tokens(tokens);
+                user_convert.to_tokens(tokens);
             }
             #[cfg(feature = "ipc-client")]
             {
@@ -271,6 +306,154 @@ impl<'a> ToTokens for PortalInfoStruct<'a> {
     }
 }
 
+#[cfg(any(feature = "ipc-client", feature = "ipc-server"))]
+impl<'a> ToTokens for PortalUserConvert<'a> {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let user_defined_types = self
+            .portal
+            .endpoints
+            .iter()
+            .flat_map(|endpoint| endpoint.body.iter());
+
+        for user_defined in user_defined_types {
+            match user_defined {
+                ast::ProtocolDefine::DefinedEnum(ref_cell) => {
+                    let enum_def = ref_cell.borrow();
+
+                    // Borrowed enums cannot be sent over IPC
+                    if enum_def.requires_lifetime {
+                        continue;
+                    }
+
+                    let ident = &enum_def.ident;
+                    let (serialize_arms, deserialize_arms): (Vec<_>, Vec<_>) = enum_def
+                        .varients
+                        .iter()
+                        .enumerate()
+                        .map(|(tag, varient)| {
+                            let tag = tag as u32;
+                            let varient_ident = &varient.ident;
+
+                            match &varient.fields {
+                                ast::ProtocolEnumFields::None => (
+                                    quote! {
+                                        Self::#varient_ident => tag_len(#tag, send)
+                                    },
+                                    quote! {
+                                        #tag => Ok(Self::#varient_ident)
+                                    },
+                                ),
+                                ast::ProtocolEnumFields::Unnamed(fields) => {
+                                    let names: Vec<_> = (0..fields.len())
+                                        .map(|i| format_ident!("field{}", i))
+                                        .collect();
+
+                                    (
+                                        quote! {
+                                            Self::#varient_ident(#(#names),*) => {
+                                                Ok(tag_len(#tag, send)? #(+ ::portal::ipc::PortalConvert::serialize(#names, send)?)*)
+                                            }
+                                        },
+                                        quote! {
+                                            #tag => Ok(Self::#varient_ident(#(<#fields as ::portal::ipc::PortalConvert>::deserialize(recv)?),*))
+                                        },
+                                    )
+                                }
+                                ast::ProtocolEnumFields::Named(fields) => {
+                                    // Fields are sent sorted by name, rather than in the order
+                                    // they were declared.
+                                    let mut fields: Vec<_> = fields.iter().collect();
+                                    fields.sort_by_key(|(name, _)| name.to_string());
+
+                                    let names: Vec<_> = fields.iter().map(|(name, _)| name).collect();
+                                    let tys = fields.iter().map(|(_, ty)| ty);
+
+                                    (
+                                        quote! {
+                                            Self::#varient_ident { #(#names),* } => {
+                                                Ok(tag_len(#tag, send)? #(+ ::portal::ipc::PortalConvert::serialize(#names, send)?)*)
+                                            }
+                                        },
+                                        quote! {
+                                            #tag => Ok(Self::#varient_ident { #(#names: <#tys as ::portal::ipc::PortalConvert>::deserialize(recv)?),* })
+                                        },
+                                    )
+                                }
+                            }
+                        })
+                        .unzip();
+
+                    tokens.append_all(quote! {
+                        impl ::portal::ipc::PortalConvert for #ident {
+                            #[allow(unused_variables)]
+                            fn serialize(&self, send: &mut impl ::portal::ipc::Sender) -> ::core::result::Result<usize, ::portal::ipc::IpcError> {
+                                let tag_len = |tag: u32, send: &mut _| -> ::core::result::Result<usize, ::portal::ipc::IpcError> {
+                                    ::portal::ipc::PortalConvert::serialize(&tag, send)
+                                };
+
+                                match self {
+                                    #(#serialize_arms),*
+                                }
+                            }
+
+                            fn deserialize(recv: &mut impl ::portal::ipc::Receiver) -> ::core::result::Result<Self, ::portal::ipc::IpcError> {
+                                match <u32 as ::portal::ipc::PortalConvert>::deserialize(recv)? {
+                                    #(#deserialize_arms,)*
+                                    _ => Err(::portal::ipc::IpcError::InvalidTypeConvert),
+                                }
+                            }
+                        }
+                    });
+                }
+                ast::ProtocolDefine::DefinedStruct(ref_cell) => {
+                    let struct_def = ref_cell.borrow();
+
+                    let ident = &struct_def.ident;
+                    let tys = struct_def.items.iter().map(|item| &item.ty);
+
+                    let (serialize_fields, construct) = if struct_def
+                        .items
+                        .iter()
+                        .any(|struct_field| struct_field.name.is_some())
+                    {
+                        let names: Vec<_> = struct_def
+                            .items
+                            .iter()
+                            .filter_map(|item| item.name.as_ref())
+                            .collect();
+
+                        (
+                            quote! { #(+ ::portal::ipc::PortalConvert::serialize(&self.#names, send)?)* },
+                            quote! { Self { #(#names: <#tys as ::portal::ipc::PortalConvert>::deserialize(recv)?),* } },
+                        )
+                    } else {
+                        let indices = (0..struct_def.items.len()).map(syn::Index::from);
+
+                        (
+                            quote! { #(+ ::portal::ipc::PortalConvert::serialize(&self.#indices, send)?)* },
+                            quote! { Self(#(<#tys as ::portal::ipc::PortalConvert>::deserialize(recv)?),*) },
+                        )
+                    };
+
+                    tokens.append_all(quote! {
+                        impl ::portal::ipc::PortalConvert for #ident {
+                            #[allow(unused_variables)]
+                            fn serialize(&self, send: &mut impl ::portal::ipc::Sender) -> ::core::result::Result<usize, ::portal::ipc::IpcError> {
+                                Ok(0 #serialize_fields)
+                            }
+
+                            #[allow(unused_variables)]
+                            fn deserialize(recv: &mut impl ::portal::ipc::Receiver) -> ::core::result::Result<Self, ::portal::ipc::IpcError> {
+                                Ok(#construct)
+                            }
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
 #[cfg(feature = "ipc-client")]
 impl<'a> ToTokens for PortalServerRequestEnum<'a> {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
@@ -329,14 +512,60 @@ impl<'a> ToTokens for PortalClientRequestEnum<'a> {
                 let name = event.get_enum_ident();
                 let target_id = event.portal_id.0 as u64;
 
+                let arguments = &event.input_args;
+                let type_body = if !event.is_async {
+                    let output_type = &event.output_arg.0;
+
+                    quote! {
+                        {
+                            #(#arguments,)*
+                            sender: ::portal::ipc::IpcResponder<'sender, Glue, #info_struct, #output_type, #target_id>
+                        }
+                    }
+                } else if !arguments.is_empty() {
+                    quote! {
+                        { #(#arguments),* }
+                    }
+                } else {
+                    quote! {}
+                };
+
+                quote! {
+                    #name #type_body
+                }
+            });
+
+        let borrowed_enum = self.portal.get_borrowed_input_enum_ident();
+        let lifetime = Lifetime::new("'sender", Span::call_site());
+
+        let borrowed_requests = self
+            .portal
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.kind == ast::ProtocolEndpointKind::Event)
+            .map(|event| {
+                let name = event.get_enum_ident();
+                let target_id = event.portal_id.0 as u64;
+
+                let argument_names = event.input_args.iter().map(|arg| &arg.argument_ident);
+                let argument_tys = event
+                    .input_args
+                    .iter()
+                    .map(|arg| BorrowedProtocolVarType::new(&lifetime, &arg.ty));
+
                 let type_body = if !event.is_async {
                     let output_type = &event.output_arg.0;
 
                     quote! {
                         {
+                            #(#argument_names: #argument_tys,)*
                             sender: ::portal::ipc::IpcResponder<'sender, Glue, #info_struct, #output_type, #target_id>
                         }
                     }
+                } else if !event.input_args.is_empty() {
+                    quote! {
+                        { #(#argument_names: #argument_tys),* }
+                    }
                 } else {
                     quote! {}
                 };
@@ -353,6 +582,15 @@ impl<'a> ToTokens for PortalClientRequestEnum<'a> {
                 _Unused(::core::marker::PhantomData<&'sender Glue>),
                 #(#client_requests),*
             }
+
+            /// A client request whose `Vec<u8>` and `String` arguments borrow from the
+            /// received message instead of being copied out of it.
+            #[non_exhaustive]
+            pub enum #borrowed_enum<'sender, Glue: ::portal::ipc::IpcGlue> {
+                #[doc(hidden)]
+                _Unused(::core::marker::PhantomData<&'sender Glue>),
+                #(#borrowed_requests),*
+            }
         });
     }
 }
@@ -396,7 +634,7 @@ impl<'a> ToTokens for PortalTrait<'a> {
                         }
                     } else {
                         quote!{
-                            #target_id => return Ok(#client_enum::#enum_name { sender: ::portal::ipc::IpcResponder::new(&mut self.0)}),
+                            #target_id => return Ok(#client_enum::#enum_name { sender: ::portal::ipc::IpcResponder::new(&mut self.0, ipc_msg.request_id)}),
                         }
                     }
                 });
@@ -409,6 +647,34 @@ impl<'a> ToTokens for PortalTrait<'a> {
                         Self(::portal::ipc::IpcService::new(glue, false))
                     }
 
+                    /// Reconnect to `service` with `policy` when the connection is lost.
+                    pub fn with_reconnect(mut self, service: &str, policy: ::portal::ipc::ReconnectPolicy) -> Self {
+                        self.0.set_reconnect(service, policy);
+                        self
+                    }
+
+                    /// Give up on a blocking call after `timeout` without a response.
+                    pub fn with_timeout(mut self, timeout: ::core::time::Duration) -> Self {
+                        self.0.set_timeout(Some(timeout.as_nanos().min(u64::MAX as u128) as u64));
+                        self
+                    }
+
+                    /// Call `hook` every time the connection state changes.
+                    pub fn on_state_change(mut self, hook: impl FnMut(::portal::ipc::ConnectionState) + 'static) -> Self {
+                        self.0.set_state_hook(hook);
+                        self
+                    }
+
+                    /// The current state of the connection.
+                    pub fn state(&self) -> ::portal::ipc::ConnectionState {
+                        self.0.state()
+                    }
+
+                    /// The glue this client is connected with.
+                    pub fn glue(&self) -> &Glue {
+                        self.0.glue()
+                    }
+
                     #(#endpoints)*
                     pub fn incoming<'a>(&'a mut self) -> ::portal::ipc::IpcResult<#client_enum<'a, Glue>> {
                         self.0.drive_rx()?;
@@ -444,24 +710,110 @@ impl<'a> ToTokens for PortalTrait<'a> {
                 .map(|endpoint| {
                     let target_id = endpoint.portal_id.0 as u64;
                     let enum_name = endpoint.get_enum_ident();
+                    let argument_names: Vec<_> = endpoint.input_args.iter().map(|arg| &arg.argument_ident).collect();
+                    let argument_tys = endpoint.input_args.iter().map(|arg| &arg.ty);
+
+                    let parse_arguments = quote! {
+                        let (#(#argument_names,)*) = ipc_msg.try_parse::<(#(#argument_tys,)*)>()?;
+                    };
 
                     if endpoint.is_async {
                         quote!{
-                            #target_id => return Ok(#server_enum::#enum_name),
+                            #target_id => {
+                                #parse_arguments
+                                return Ok(#server_enum::#enum_name { #(#argument_names),* });
+                            }
                         }
                     } else {
                         quote!{
-                            #target_id => return Ok(#server_enum::#enum_name { sender: ::portal::ipc::IpcResponder::new(&mut self.0)}),
+                            #target_id => {
+                                #parse_arguments
+                                return Ok(#server_enum::#enum_name { #(#argument_names,)* sender: ::portal::ipc::IpcResponder::new(&mut self.0, ipc_msg.request_id)});
+                            }
+                        }
+                    }
+                });
+
+            let borrowed_enum = self.portal.get_borrowed_input_enum_ident();
+            let lifetime = Lifetime::new("'sender", Span::call_site());
+
+            let borrowed_target_tokens = self.portal.endpoints
+                .iter()
+                .filter(|endpoint| endpoint.kind == ast::ProtocolEndpointKind::Event)
+                .map(|endpoint| {
+                    let target_id = endpoint.portal_id.0 as u64;
+                    let enum_name = endpoint.get_enum_ident();
+                    let argument_names: Vec<_> = endpoint.input_args.iter().map(|arg| &arg.argument_ident).collect();
+                    let argument_tys = endpoint.input_args.iter().map(|arg| BorrowedProtocolVarType::new(&lifetime, &arg.ty));
+
+                    let parse_arguments = if argument_names.is_empty() {
+                        quote! {}
+                    } else {
+                        quote! {
+                            let mut data: &'sender [u8] = &ipc_msg.data;
+                            #(let #argument_names = <#argument_tys as ::portal::ipc::PortalBorrow<'sender>>::deserialize_borrowed(&mut data)?;)*
+                        }
+                    };
+
+                    if endpoint.is_async {
+                        quote!{
+                            #target_id => {
+                                #parse_arguments
+                                return Ok(#borrowed_enum::#enum_name { #(#argument_names),* });
+                            }
+                        }
+                    } else {
+                        quote!{
+                            #target_id => {
+                                #parse_arguments
+                                return Ok(#borrowed_enum::#enum_name { #(#argument_names,)* sender: ::portal::ipc::IpcResponder::new(service, ipc_msg.request_id)});
+                            }
                         }
                     }
                 });
 
             tokens.append_all(quote! {
-                pub struct #server_trait<Glue: ::portal::ipc::IpcGlue>(::portal::ipc::IpcService<Glue, #info_struct>);
+                pub struct #server_trait<Glue: ::portal::ipc::IpcGlue>(
+                    ::portal::ipc::IpcService<Glue, #info_struct>,
+                    /// The last message returned by `incoming_borrowed`, which its request borrows from
+                    ::core::option::Option<::portal::ipc::IpcMessage>,
+                );
 
                 impl<Glue: ::portal::ipc::IpcGlue> #server_trait<Glue> {
                     pub fn new(glue: Glue) -> Self {
-                        Self(::portal::ipc::IpcService::new(glue, true))
+                        Self(::portal::ipc::IpcService::new(glue, true), None)
+                    }
+
+                    /// The glue this server is connected with.
+                    pub fn glue(&self) -> &Glue {
+                        self.0.glue()
+                    }
+
+                    /// Get the next request, borrowing its bulk data from the received message
+                    ///
+                    /// Unlike `incoming`, `Vec<u8>` and `String` arguments are not copied out of
+                    /// the message, so large payloads can be handled without an allocation.
+                    pub fn incoming_borrowed<'sender>(&'sender mut self) -> ::portal::ipc::IpcResult<#borrowed_enum<'sender, Glue>> {
+                        let Self(service, last_message) = self;
+                        service.drive_rx()?;
+
+                        let Some(ipc_msg) = service.pop_rx() else {
+                            return Err(::portal::ipc::IpcError::NotReady);
+                        };
+                        let ipc_msg: &'sender ::portal::ipc::IpcMessage = last_message.insert(ipc_msg);
+
+                        match ipc_msg.target_id {
+                            #(#borrowed_target_tokens)*
+                            _ => return Err(::portal::ipc::IpcError::InvalidTypeConvert),
+                        }
+                    }
+
+                    /// Turn a response put off with `IpcResponder::defer` back into a responder
+                    pub fn resume<T: ::portal::ipc::PortalConvert, const TARGET_ID: u64>(
+                        &mut self,
+                        deferred: ::portal::ipc::IpcDeferred<#info_struct, T, TARGET_ID>,
+                    ) -> ::portal::ipc::IpcResponder<'_, Glue, #info_struct, T, TARGET_ID> {
+                        ::portal::ipc::IpcResponder::resume(&mut self.0, deferred)
                     }
 
                     #(#endpoints)*
@@ -507,24 +859,43 @@ impl ClientServerTokens for ast::ProtocolEndpoint {
                 };
 
                 let target_id = self.portal_id.0 as u64;
-                let blocking_tokens = if !self.is_async {
-                    quote! {
-                        self.0.blocking_rx(#target_id)
-                    }
+                let call_ident = if self.is_async {
+                    format_ident!("notify")
                 } else {
-                    quote! {
-                        Ok(())
-                    }
+                    format_ident!("call")
                 };
 
+                let arguments = &self.input_args;
+                let argument_names = self.input_args.iter().map(|arg| &arg.argument_ident);
+
+                if self.is_async {
+                    return quote! {
+                        #(#docs)*
+                        pub fn #fn_name(&mut self, #(#arguments),*) -> ::portal::ipc::PortalResult<#output_ty> {
+                            const TARGET_ID: u64 = #target_id;
+
+                            self.0.#call_ident(TARGET_ID, &(#(#argument_names,)*))
+                        }
+                    };
+                }
+
+                let fn_with_name = format_ident!("{}_with", fn_name);
+                let argument_names: Vec<_> = argument_names.collect();
                 quote! {
                     #(#docs)*
-                    pub fn #fn_name(&mut self) -> ::portal::ipc::IpcResult<#output_ty> {
+                    pub fn #fn_name(&mut self, #(#arguments),*) -> ::portal::ipc::PortalResult<#output_ty> {
                         const TARGET_ID: u64 = #target_id;
 
-                        self.0.tx_msg(TARGET_ID, false, ())?;
-                        self.0.flush_tx()?;
-                        #blocking_tokens
+                        self.0.#call_ident(TARGET_ID, &(#(#argument_names,)*))
+                    }
+
+                    #(#docs)*
+                    ///
+                    /// Waits on the response following `options` instead of the client's own.
+                    pub fn #fn_with_name(&mut self, #(#arguments,)* options: &::portal::ipc::CallOptions) -> ::portal::ipc::PortalResult<#output_ty> {
+                        const TARGET_ID: u64 = #target_id;
+
+                        self.0.call_with(TARGET_ID, &(#(#argument_names,)*), options)
                     }
                 }
             }
@@ -627,8 +998,22 @@ impl ToTokens for ast::ProtocolDefine {
                     quote! {}
                 };
 
+                // Enums are laid out as a `u32` tag followed by a union of their variants, so
+                // they can be described to other languages.
+                let repr = if varients.is_empty() {
+                    quote! {}
+                } else if varients
+                    .iter()
+                    .all(|varient| matches!(varient.fields, ast::ProtocolEnumFields::None))
+                {
+                    quote! { #[repr(u32)] }
+                } else {
+                    quote! { #[repr(C, u32)] }
+                };
+
                 tokens.append_all(quote! {
                     #(#docs)*
+                    #repr
                     #[derive(Debug, Clone)]
                     pub enum #ident #lifetime {
                         #(#varients),*
@@ -708,8 +1093,8 @@ impl ToTokens for ast::ProtocolEnumFields {
             ast::ProtocolEnumFields::Unnamed(protocol_var_types) => {
                 tokens.append_all(quote! {(#(#protocol_var_types),*)});
             }
-            ast::ProtocolEnumFields::Named(hash_map) => {
-                let var_defs = hash_map.iter().map(|(name, ty)| quote! { #name : #ty });
+            ast::ProtocolEnumFields::Named(fields) => {
+                let var_defs = fields.iter().map(|(name, ty)| quote! { #name : #ty });
 
                 tokens.append_all(quote! {
                     { #(#var_defs),* }
@@ -735,6 +1120,7 @@ impl<'a> ToTokens for PortalTranslationInputType<'a> {
                 }
             });
             let endpoint_enum_name = format_ident!("{}Endpoint", endpoint.get_enum_ident());
+            let endpoint_id = endpoint.portal_id.0 as u64;
 
             let fields = if !endpoint.input_args.is_empty() {
                 quote! { { #(#named_var),* } }
@@ -743,22 +1129,24 @@ impl<'a> ToTokens for PortalTranslationInputType<'a> {
             };
 
             quote! {
-                #endpoint_enum_name #fields,
+                #endpoint_enum_name #fields = #endpoint_id,
             }
         });
+        let version_id = ast::SYSCALL_ABI_VERSION;
 
         // TODO: We should try and not emit this field in the future, and look to see if we
         // actually need to use the lifetime.
         tokens.append_all(quote! {
+            #[repr(C, u64)]
             pub enum #translation_ident<#lifetime> {
                 #(#varients)*
-                _UnusedPhantomData(core::marker::PhantomData<&#lifetime ()>)
+                _UnusedPhantomData(core::marker::PhantomData<&#lifetime ()>) = u64::MAX,
             }
         });
         tokens.append_all(quote! {
             unsafe impl<'input_lifetime> ::portal::syscall::SyscallInput for #translation_ident<'input_lifetime> {
                 fn version_id() -> u32 {
-                    1
+                    #version_id
                 }
             }
         });
@@ -772,21 +1160,25 @@ impl<'a> ToTokens for PortalTranslationOutputType<'a> {
         let varients = self.portal.endpoints.iter().map(|endpoint| {
             let var_output = &endpoint.output_arg.0;
             let endpoint_enum_name = format_ident!("{}Endpoint", endpoint.get_enum_ident());
+            let endpoint_id = endpoint.portal_id.0 as u64;
 
-            let fields = if !matches!(var_output, ast::ProtocolVarType::Unit(_))
-                && !matches!(var_output, ast::ProtocolVarType::Never(_))
-            {
-                quote! { ( #var_output ) }
-            } else {
-                quote! {}
+            let fields = match var_output {
+                ast::ProtocolVarType::Unit(_) | ast::ProtocolVarType::Never(_) => quote! {},
+                // `Result` has no stable layout, so it is returned as an `AbiResult` instead
+                ast::ProtocolVarType::ResultKind { ok_ty, err_ty, .. } => {
+                    quote! { ( ::portal::syscall::AbiResult<#ok_ty, #err_ty> ) }
+                }
+                _ => quote! { ( #var_output ) },
             };
 
             quote! {
-                #endpoint_enum_name #fields,
+                #endpoint_enum_name #fields = #endpoint_id,
             }
         });
+        let version_id = ast::SYSCALL_ABI_VERSION;
 
         tokens.append_all(quote! {
+            #[repr(C, u64)]
             pub enum #translation_ident {
                 #(#varients)*
             }
@@ -794,7 +1186,7 @@ impl<'a> ToTokens for PortalTranslationOutputType<'a> {
         tokens.append_all(quote! {
             unsafe impl ::portal::syscall::SyscallOutput for #translation_ident {
                 fn version_id() -> u32 {
-                    1
+                    #version_id
                 }
             }
         });
@@ -879,6 +1271,9 @@ impl ToTokens for ast::ProtocolVarType {
                     tokens.append_all(quote! {[#to]});
                 }
             }
+            ast::ProtocolVarType::External { span, path } => {
+                tokens.append_all(quote_spanned! {span.clone()=>#path})
+            }
             ast::ProtocolVarType::IpcString(span) => {
                 tokens.append_all(quote_spanned! {span.clone()=> ::portal::ipc::IpcString });
             }
@@ -890,6 +1285,25 @@ impl ToTokens for ast::ProtocolVarType {
     }
 }
 
+#[cfg(feature = "ipc-server")]
+impl<'a> ToTokens for BorrowedProtocolVarType<'a> {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let lifetime = self.lifetime_ident;
+
+        match self.ty {
+            ast::ProtocolVarType::IpcString(span) => {
+                tokens.append_all(quote_spanned! {*span=>& #lifetime str});
+            }
+            ast::ProtocolVarType::IpcVec { span, to }
+                if matches!(to.as_ref(), ast::ProtocolVarType::Unsigned8(_)) =>
+            {
+                tokens.append_all(quote_spanned! {*span=>& #lifetime [u8]});
+            }
+            ty => ty.to_tokens(tokens),
+        }
+    }
+}
+
 impl<'a> ToTokens for LifetimedProtocolVarType<'a> {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         match self.ty {
@@ -982,6 +1396,17 @@ impl<'a> ToTokens for IntoSyscallPortalImpl<'a> {
                         }
                     }
                 }
+                ast::ProtocolVarType::ResultKind { .. } => {
+                    let fmt_string = format!("Portal Endpoint '{}': '{}::call_syscall' was supposed to return '{}::{}'", fn_ident, ident, output_enum, enum_part);
+                    quote! {
+                        {
+                            #output_enum::#enum_part (output_val) => { output_val.into() }
+                            _ => {
+                                unreachable!(#fmt_string)
+                            }
+                        }
+                    }
+                }
                 _ => {
                     let fmt_string = format!("Portal Endpoint '{}': '{}::call_syscall' was supposed to return '{}::{}'", fn_ident, ident, output_enum, enum_part);
                     quote! {
@@ -1089,16 +1514,38 @@ impl<'a> ToTokens for OutSyscallPortalImpl<'a> {
                 (quote!{}, quote!{ () })
             };
 
-            let output = if !matches!(endpoint.output_arg.0, ast::ProtocolVarType::Never(_)) && !matches!(endpoint.output_arg.0, ast::ProtocolVarType::Unit(_)) {
-                quote!{{
-                   super::#output_enum::#enum_part (<Self as #trait_ident>::#fn_ident #function_args)
-                }}
-            } else {
-                quote! {{
-                   <Self as #trait_ident>::#fn_ident #function_args;
-                   super::#output_enum::#enum_part
-                }}
+            let endpoint_name = fn_ident.to_string();
+            let (trace_format, trace_values) = trace_arguments(&endpoint.input_args);
+            let call = quote! { <Self as #trait_ident>::#fn_ident #function_args };
+
+            let output = match endpoint.output_arg.0 {
+                // Endpoints that never return can't report their output, so they are
+                // finished tracing before they run.
+                ast::ProtocolVarType::Never(_) => quote! {
+                    <Self as #output_ident>::trace_exit(#endpoint_name, None);
+                    #call;
+                    super::#output_enum::#enum_part
+                },
+                ast::ProtocolVarType::Unit(_) => quote! {
+                    #call;
+                    <Self as #output_ident>::trace_exit(#endpoint_name, Some(&()));
+                    super::#output_enum::#enum_part
+                },
+                ast::ProtocolVarType::ResultKind { .. } => quote! {
+                    let output = #call;
+                    <Self as #output_ident>::trace_exit(#endpoint_name, Some(&output));
+                    super::#output_enum::#enum_part(output.into())
+                },
+                _ => quote! {
+                    let output = #call;
+                    <Self as #output_ident>::trace_exit(#endpoint_name, Some(&output));
+                    super::#output_enum::#enum_part(output)
+                },
             };
+            let output = quote! {{
+                <Self as #output_ident>::trace_enter(#endpoint_name, format_args!(#trace_format #(, #trace_values)*));
+                #output
+            }};
 
            quote!{
                super::#input_enum::#enum_part #enum_args => #output
@@ -1120,7 +1567,15 @@ impl<'a> ToTokens for OutSyscallPortalImpl<'a> {
                     }
 
                     unsafe {
-                        ::portal::syscall::server::adapt_syscall(kind, syscall_input_ptr, syscall_output_ptr, syscall_packed_len, syscall_packed_id, |input| {
+                        ::portal::syscall::server::adapt_syscall(
+                            kind,
+                            syscall_input_ptr,
+                            syscall_output_ptr,
+                            syscall_packed_len,
+                            syscall_packed_id,
+                            |ptr| unsafe { <Self as #output_ident>::read_user_input(ptr) },
+                            |ptr, output| unsafe { <Self as #output_ident>::write_user_output(ptr, output) },
+                            |input| {
                             match input {
                                 #(#endpoints)*
                                 _ => unreachable!("Should never get here?"),
@@ -1131,7 +1586,104 @@ impl<'a> ToTokens for OutSyscallPortalImpl<'a> {
 
                 /// Check that the user's ptr is correct
                 fn verify_user_ptr<T: Sized>(ptr: *const T) -> bool;
+
+                /// Copy a `T` out of the user's memory at `ptr`, or `None` if it can't be read
+                ///
+                /// # Safety
+                /// `ptr` must have passed `verify_user_ptr`, and any bytes read must be a valid `T`.
+                unsafe fn read_user_input<T: Sized>(ptr: *const T) -> Option<T>;
+
+                /// Copy `value` into the user's memory at `ptr`, returning if it could be written
+                ///
+                /// # Safety
+                /// `ptr` must have passed `verify_user_ptr`.
+                unsafe fn write_user_output<T: Sized>(ptr: *mut T, value: T) -> bool;
+
+                /// Called before each endpoint runs, with a summary of its arguments
+                ///
+                /// References into the caller's memory are summarized by their address, so
+                /// the summary is safe to format without checking them.
+                #[inline]
+                fn trace_enter(endpoint: &'static str, arguments: ::core::fmt::Arguments) {
+                    let _ = (endpoint, arguments);
+                }
+
+                /// Called after each endpoint returns with its output, or with `None` right
+                /// before running an endpoint that never returns
+                #[inline]
+                fn trace_exit(endpoint: &'static str, output: ::core::option::Option<&dyn ::core::fmt::Debug>) {
+                    let _ = (endpoint, output);
+                }
             }
         });
     }
 }
+
+/// Build a format string and its values that summarize `input_args` for tracing.
+///
+/// Values are only formatted when doing so can't read through a reference into the
+/// caller's memory, references themselves are shown by their address.
+#[cfg(feature = "syscall-server")]
+fn trace_arguments(input_args: &[ast::ProtocolInputArg]) -> (String, Vec<TokenStream2>) {
+    fn holds_reference(ty: &ast::ProtocolVarType) -> bool {
+        ty.search(&|ty| match ty {
+            ast::ProtocolVarType::RefTo { .. } => Some(true),
+            ast::ProtocolVarType::UserDefined {
+                to: ast::ProtocolDefine::DefinedEnum(enum_def),
+                ..
+            } if enum_def.borrow().requires_lifetime => Some(true),
+            ast::ProtocolVarType::UserDefined {
+                to: ast::ProtocolDefine::DefinedStruct(struct_def),
+                ..
+            } if struct_def
+                .borrow()
+                .items
+                .iter()
+                .any(|item| holds_reference(&item.ty)) =>
+            {
+                Some(true)
+            }
+            _ => None,
+        })
+        .unwrap_or(false)
+    }
+
+    let mut format = String::new();
+    let mut values = Vec::new();
+
+    for (index, input_arg) in input_args.iter().enumerate() {
+        let name = &input_arg.argument_ident;
+        if index != 0 {
+            format.push_str(", ");
+        }
+        format.push_str(&format!("{name}="));
+
+        match &input_arg.ty {
+            ast::ProtocolVarType::RefTo { to, .. }
+                if matches!(
+                    **to,
+                    ast::ProtocolVarType::Str(_) | ast::ProtocolVarType::Array { len: None, .. }
+                ) =>
+            {
+                format.push_str("{:p}[{}]");
+                values.push(quote! { #name });
+                values.push(quote! { #name.len() });
+            }
+            ast::ProtocolVarType::RefTo { .. } | ast::ProtocolVarType::PtrTo { .. } => {
+                format.push_str("{:p}");
+                values.push(quote! { #name });
+            }
+            ast::ProtocolVarType::External { .. }
+            | ast::ProtocolVarType::Unknown(_)
+            | ast::ProtocolVarType::IpcString(_)
+            | ast::ProtocolVarType::IpcVec { .. } => format.push_str(".."),
+            ty if holds_reference(ty) => format.push_str(".."),
+            _ => {
+                format.push_str("{:?}");
+                values.push(quote! { #name });
+            }
+        }
+    }
+
+    (format, values)
+}
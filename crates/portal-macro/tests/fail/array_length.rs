@@ -0,0 +1,11 @@
+use portal_macro::portal;
+
+const LEN: usize = 4;
+
+#[portal(protocol = "syscall")]
+pub trait Example {
+    #[event = 0]
+    fn write(bytes: &[u8; LEN]);
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[event = 0]
+    fn ping() {
+        let x = 0;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[event = 0]
+    fn first();
+
+    #[event = 1]
+    fn second();
+
+    #[event = 0]
+    fn third();
+}
+
+fn main() {}
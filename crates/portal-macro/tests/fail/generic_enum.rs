@@ -0,0 +1,13 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[event = 0]
+    fn ping() -> Reply<u8> {
+        enum Reply<T> {
+            Pong(T),
+        }
+    }
+}
+
+fn main() {}
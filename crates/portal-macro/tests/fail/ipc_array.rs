@@ -0,0 +1,9 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[event = 0]
+    fn write(bytes: [u8; 16]);
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[handle]
+    fn no_id();
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    fn missing_kind();
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use portal_macro::portal;
+
+const ID: usize = 0;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[handle = ID]
+    fn constant_id();
+}
+
+fn main() {}
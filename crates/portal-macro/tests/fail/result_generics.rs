@@ -0,0 +1,9 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[event = 0]
+    fn read() -> Result<u8>;
+}
+
+fn main() {}
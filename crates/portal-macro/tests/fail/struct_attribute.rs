@@ -0,0 +1,14 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[event = 0]
+    fn ping() -> Reply {
+        #[derive(Clone)]
+        struct Reply {
+            id: u8,
+        }
+    }
+}
+
+fn main() {}
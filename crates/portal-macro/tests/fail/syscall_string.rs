@@ -0,0 +1,9 @@
+use portal_macro::portal;
+
+#[portal(protocol = "syscall")]
+pub trait Example {
+    #[event = 0]
+    fn debug_msg(msg: String);
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[event = 0]
+    #[handle = 1]
+    fn both();
+}
+
+fn main() {}
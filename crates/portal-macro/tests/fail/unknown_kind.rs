@@ -0,0 +1,9 @@
+use portal_macro::portal;
+
+#[portal(protocol = "ipc")]
+pub trait Example {
+    #[evnt = 0]
+    fn typo();
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use portal_macro::portal;
+
+#[portal(protocol = "sycall")]
+pub trait Example {
+    #[event = 0]
+    fn ping();
+}
+
+fn main() {}
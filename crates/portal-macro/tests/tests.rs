@@ -0,0 +1,7 @@
+mod test {
+    #[test]
+    fn compile_fail_cases() {
+        let t = trybuild::TestCases::new();
+        t.compile_fail("tests/fail/*.rs");
+    }
+}
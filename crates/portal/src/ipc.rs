@@ -24,15 +24,21 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 */
 
 extern crate alloc;
-use alloc::collections::VecDeque;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use convert::{
     MESSAGE_CLIENT_REQ_START, MESSAGE_CLIENT_RSP_START, MESSAGE_END, MESSAGE_SERVER_REQ_START,
     MESSAGE_SERVER_RSP_START,
 };
 use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
 
 pub mod convert;
+pub mod frame;
 
 pub type IpcString = alloc::string::String;
 pub type IpcVec<T> = Vec<T>;
@@ -40,7 +46,10 @@ pub type IpcResult<T> = ::core::result::Result<T, IpcError>;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum IpcError {
-    InvalidMagic { given: u8, expected: u8 },
+    InvalidMagic {
+        given: u8,
+        expected: u8,
+    },
     GlueError,
     BufferInvalidSize,
     Utf8ConvertError,
@@ -48,7 +57,153 @@ pub enum IpcError {
     NotReady,
     AlreadyUsed,
     InvalidMessage(Vec<u8>),
-    InvalidHash { given: u64, expected: u64 },
+    InvalidHash {
+        given: u64,
+        expected: u64,
+    },
+    /// The other side of the connection has gone away
+    Disconnected,
+    /// No response arrived before the timeout
+    Timeout,
+}
+
+pub type PortalResult<T> = ::core::result::Result<T, PortalError>;
+
+/// The error returned by every call of a generated client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortalError {
+    /// The connection to the service was lost, and could not be made again
+    Disconnected,
+    /// The service did not respond in time
+    Timeout,
+    /// The service's response could not be decoded
+    DecodeError(IpcError),
+}
+
+impl From<IpcError> for PortalError {
+    fn from(value: IpcError) -> Self {
+        match value {
+            IpcError::Disconnected | IpcError::GlueError => Self::Disconnected,
+            IpcError::Timeout | IpcError::NotReady => Self::Timeout,
+            other => Self::DecodeError(other),
+        }
+    }
+}
+
+/// The state of a client's connection to its service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// The connection was lost, and is not being made again
+    Disconnected,
+    /// The connection was lost, and this is the `attempt`th try at making it again
+    Reconnecting {
+        attempt: u32,
+    },
+}
+
+/// How a client should reconnect to its service after losing its connection
+///
+/// The delay between attempts starts at `initial_backoff_ns`, and doubles after every
+/// failed attempt up to `max_backoff_ns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// How many times to try reconnecting before giving up
+    pub max_attempts: u32,
+    /// How long to wait before the second attempt, the first is made right away
+    pub initial_backoff_ns: u64,
+    /// The longest to ever wait between two attempts
+    pub max_backoff_ns: u64,
+}
+
+impl ReconnectPolicy {
+    /// Never reconnect, the default for new clients
+    pub const NEVER: Self = Self::new(0, 0, 0);
+
+    pub const fn new(max_attempts: u32, initial_backoff_ns: u64, max_backoff_ns: u64) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff_ns,
+            max_backoff_ns,
+        }
+    }
+
+    /// How long to wait before reconnect attempt `attempt`, starting from 1
+    pub fn backoff_ns(&self, attempt: u32) -> u64 {
+        match attempt {
+            0 | 1 => 0,
+            attempt => self
+                .initial_backoff_ns
+                .checked_shl(attempt - 2)
+                .filter(|&backoff| backoff >> (attempt - 2) == self.initial_backoff_ns)
+                .unwrap_or(u64::MAX)
+                .min(self.max_backoff_ns),
+        }
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::NEVER
+    }
+}
+
+/// How often a blocking call checks its `CancelToken` while waiting on the glue
+pub const CANCEL_POLL_NS: u64 = 10_000_000;
+
+/// A flag that makes the blocking calls watching it give up
+///
+/// Clones share the same flag, so one can be handed to whoever should be able to cancel
+/// the call. A cancelled call fails with `PortalError::Timeout`, as if its deadline had
+/// passed.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make every call watching this token give up
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Let this token be used for new calls again
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Options for a single blocking call, overriding the client's own
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    /// Give up after this long without a response, `None` uses the client's timeout
+    pub timeout_ns: Option<u64>,
+    /// Give up once this token is cancelled
+    pub cancel: Option<CancelToken>,
+}
+
+impl CallOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give up after `timeout` without a response
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ns = Some(timeout.as_nanos().min(u64::MAX as u128) as u64);
+        self
+    }
+
+    /// Give up once `token` is cancelled
+    pub fn cancel_on(mut self, token: &CancelToken) -> Self {
+        self.cancel = Some(token.clone());
+        self
+    }
 }
 
 /// Ipc Sender (TX)
@@ -82,25 +237,55 @@ pub struct IpcResponder<
     const TARGET_ID: u64,
 > {
     connection: &'a mut IpcService<Glue, Info>,
+    request_id: u64,
     ty: PhantomData<T>,
 }
 
 impl<'a, Glue: IpcGlue, Info: IpcServiceInfo, T: PortalConvert, const TARGET_ID: u64>
     IpcResponder<'a, Glue, Info, T, TARGET_ID>
 {
-    pub fn new(connection: &'a mut IpcService<Glue, Info>) -> Self {
+    /// Respond to the request `request_id` on `connection`
+    pub fn new(connection: &'a mut IpcService<Glue, Info>, request_id: u64) -> Self {
         Self {
             connection,
+            request_id,
             ty: PhantomData,
         }
     }
 
+    /// Respond to a request that was put off with `defer`
+    pub fn resume(
+        connection: &'a mut IpcService<Glue, Info>,
+        deferred: IpcDeferred<Info, T, TARGET_ID>,
+    ) -> Self {
+        Self::new(connection, deferred.request_id)
+    }
+
     pub fn respond_with(self, value: T) -> IpcResult<()> {
-        let tx = self.connection.tx_msg(TARGET_ID, true, value)?;
+        let tx = self
+            .connection
+            .tx_msg(TARGET_ID, self.request_id, true, &value)?;
         self.connection.flush_tx()?;
 
         Ok(tx)
     }
+
+    /// Put off responding, so the connection can be released until the response is ready
+    ///
+    /// The returned token has to be resumed on the same connection it was deferred from.
+    pub fn defer(self) -> IpcDeferred<Info, T, TARGET_ID> {
+        IpcDeferred {
+            request_id: self.request_id,
+            ty: PhantomData,
+        }
+    }
+}
+
+/// A response put off with [`IpcResponder::defer`]
+#[must_use = "the client is waiting on this response"]
+pub struct IpcDeferred<Info: IpcServiceInfo, T: PortalConvert, const TARGET_ID: u64> {
+    request_id: u64,
+    ty: PhantomData<fn() -> (Info, T)>,
 }
 
 /// Conversion from/to IPC Sockets
@@ -117,6 +302,41 @@ pub trait PortalConvert: Sized {
     fn serialize(&self, send: &mut impl Sender) -> Result<usize, IpcError>;
     /// Deserialize the transfered bytes into `Self`
     fn deserialize(recv: &mut impl Receiver) -> Result<Self, IpcError>;
+
+    /// Serialize the items of a `Vec<Self>`
+    ///
+    /// By default each item is serialized on its own, types can override this to
+    /// send all of their items packed together.
+    fn serialize_slice(items: &[Self], send: &mut impl Sender) -> Result<usize, IpcError> {
+        let mut bytes = 0;
+
+        for item in items {
+            bytes += item.serialize(send)?;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Deserialize `len` items sent with `serialize_slice`
+    fn deserialize_vec(len: usize, recv: &mut impl Receiver) -> Result<Vec<Self>, IpcError> {
+        let mut vec = Vec::with_capacity(len);
+
+        for _ in 0..len {
+            vec.push(Self::deserialize(recv)?);
+        }
+
+        Ok(vec)
+    }
+}
+
+/// Borrowing conversion from IPC messages
+///
+/// This is the zero-copy version of `PortalConvert::deserialize`. Byte buffers and
+/// strings are returned as views into the received message instead of being copied
+/// into a new allocation, every other type is deserialized as normal.
+pub trait PortalBorrow<'a>: Sized {
+    /// Deserialize `Self` from the front of `recv`, borrowing from it if possible
+    fn deserialize_borrowed(recv: &mut &'a [u8]) -> Result<Self, IpcError>;
 }
 
 /// A header-valid IPC message
@@ -129,6 +349,8 @@ pub struct IpcMessage {
     pub start_byte: u8,
     pub endpoint_hash: u64,
     pub target_id: u64,
+    /// The request this message is, or responds to
+    pub request_id: u64,
     pub data: Vec<u8>,
     pub end_byte: u8,
 }
@@ -183,13 +405,18 @@ impl RawIpcBuffer {
         u64::deserialize(&mut target_slice)
     }
 
+    pub fn get_request_id(&self) -> IpcResult<u64> {
+        let mut request_slice = self.0.get(19..28).ok_or(IpcError::NotReady)?;
+        u64::deserialize(&mut request_slice)
+    }
+
     pub fn get_data_len(&self) -> IpcResult<usize> {
-        let mut len_slice = self.0.get(19..28).ok_or(IpcError::NotReady)?;
+        let mut len_slice = self.0.get(28..37).ok_or(IpcError::NotReady)?;
         Ok(u64::deserialize(&mut len_slice)? as usize)
     }
 
     pub fn get_data(&self) -> IpcResult<Vec<u8>> {
-        let data_start = 28;
+        let data_start = 37;
         let data_end = data_start + self.get_data_len()?;
 
         Ok(self
@@ -201,7 +428,7 @@ impl RawIpcBuffer {
 
     pub fn get_end_byte(&self) -> IpcResult<u8> {
         let data_len = self.get_data_len()?;
-        let end_index = 28 + data_len;
+        let end_index = 37 + data_len;
 
         self.0
             .get(end_index)
@@ -217,6 +444,7 @@ impl RawIpcBuffer {
             start_byte: self.get_start_byte()?,
             endpoint_hash: self.get_endpoint_hash()?,
             target_id: self.get_target_id()?,
+            request_id: self.get_request_id()?,
             data: self.get_data()?,
             end_byte: self.get_end_byte()?,
         })
@@ -235,7 +463,7 @@ impl RawIpcBuffer {
         match self.populate_ipc_message() {
             Err(IpcError::NotReady) => Err(IpcError::NotReady),
             Ok(valid) => {
-                self.0.drain(0..valid.data.len() + 29);
+                self.0.drain(0..valid.data.len() + 38);
                 Ok(valid)
             }
             Err(invalid) => {
@@ -281,6 +509,32 @@ pub trait IpcGlue: Sender + Receiver {
     /// call this method to block until the socket has woken up.
     fn socket_wait(&self) {}
 
+    /// Block until the socket has woken up, or `timeout_ns` has passed
+    ///
+    /// Glue that can't wait with a timeout falls back to `socket_wait`, and is only
+    /// checked for timeouts between wakes.
+    fn socket_wait_for(&self, _timeout_ns: Option<u64>) -> IpcResult<()> {
+        self.socket_wait();
+        Ok(())
+    }
+
+    /// Replace this connection with a new one to the service named `service`
+    ///
+    /// Glue that cannot reconnect returns `IpcError::Disconnected`.
+    fn reconnect(&mut self, _service: &str) -> IpcResult<()> {
+        Err(IpcError::Disconnected)
+    }
+
+    /// The current time of a monotonic clock in nanoseconds
+    ///
+    /// Timeouts are only supported by glue that has a clock.
+    fn now_ns(&self) -> Option<u64> {
+        None
+    }
+
+    /// Sleep for `ns` nanoseconds, used to back off between reconnect attempts
+    fn sleep_ns(&self, _ns: u64) {}
+
     // /// Make a connection to the server provided with the service info
     // fn connect<Info: IpcServiceInfo>(&mut self) -> IpcResult<()>;
 
@@ -295,6 +549,15 @@ pub struct IpcService<Glue: IpcGlue, Info: IpcServiceInfo> {
     rx_queue: VecDeque<IpcMessage>,
     tx_queue: VecDeque<IpcMessage>,
     rx_buf: RawIpcBuffer,
+    /// The service to reconnect to, and how
+    reconnect: Option<(String, ReconnectPolicy)>,
+    timeout_ns: Option<u64>,
+    /// The id given to the next request sent
+    next_request_id: u64,
+    /// Requests that gave up waiting, whose responses are dropped when they arrive
+    abandoned: BTreeSet<u64>,
+    state: ConnectionState,
+    state_hook: Option<Box<dyn FnMut(ConnectionState)>>,
 }
 
 impl<Glue: IpcGlue, Info: IpcServiceInfo> IpcService<Glue, Info> {
@@ -306,9 +569,136 @@ impl<Glue: IpcGlue, Info: IpcServiceInfo> IpcService<Glue, Info> {
             tx_queue: VecDeque::new(),
             rx_buf: RawIpcBuffer::new(),
             is_server,
+            reconnect: None,
+            timeout_ns: None,
+            next_request_id: 0,
+            abandoned: BTreeSet::new(),
+            state: ConnectionState::Connected,
+            state_hook: None,
         }
     }
 
+    /// Reconnect to `service` following `policy` when the connection is lost
+    pub fn set_reconnect(&mut self, service: &str, policy: ReconnectPolicy) {
+        self.reconnect = Some((String::from(service), policy));
+    }
+
+    /// Give up waiting on a response after `timeout_ns`, or wait forever with `None`
+    pub fn set_timeout(&mut self, timeout_ns: Option<u64>) {
+        self.timeout_ns = timeout_ns;
+    }
+
+    /// Call `hook` every time the state of the connection changes
+    pub fn set_state_hook(&mut self, hook: impl FnMut(ConnectionState) + 'static) {
+        self.state_hook = Some(Box::new(hook));
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// The glue this service is connected with
+    pub fn glue(&self) -> &Glue {
+        &self.glue
+    }
+
+    fn set_state(&mut self, state: ConnectionState) {
+        if self.state == state {
+            return;
+        }
+
+        self.state = state;
+        if let Some(hook) = self.state_hook.as_mut() {
+            hook(state);
+        }
+    }
+
+    /// Make a new connection with the reconnect policy, dropping everything that was
+    /// queued on the old one.
+    fn reconnect(&mut self) -> PortalResult<()> {
+        let Some((service, policy)) = self.reconnect.clone() else {
+            self.set_state(ConnectionState::Disconnected);
+            return Err(PortalError::Disconnected);
+        };
+
+        for attempt in 1..=policy.max_attempts {
+            self.set_state(ConnectionState::Reconnecting { attempt });
+            self.glue.sleep_ns(policy.backoff_ns(attempt));
+
+            if self.glue.reconnect(&service).is_ok() {
+                self.rx_queue.clear();
+                self.tx_queue.clear();
+                self.rx_buf = RawIpcBuffer::new();
+                self.abandoned.clear();
+                self.set_state(ConnectionState::Connected);
+
+                return Ok(());
+            }
+        }
+
+        self.set_state(ConnectionState::Disconnected);
+        Err(PortalError::Disconnected)
+    }
+
+    /// Run `request`, reconnecting and running it once more if the connection was lost
+    ///
+    /// # Note
+    /// A request that was lost with the connection might have already been handled by
+    /// the service, so it could be handled twice.
+    fn with_reconnect<T>(
+        &mut self,
+        mut request: impl FnMut(&mut Self) -> IpcResult<T>,
+    ) -> PortalResult<T> {
+        for _ in 0..2 {
+            if self.state != ConnectionState::Connected {
+                self.reconnect()?;
+            }
+
+            match request(self) {
+                Ok(value) => return Ok(value),
+                Err(IpcError::Disconnected | IpcError::GlueError) => {
+                    self.set_state(ConnectionState::Disconnected);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Err(PortalError::Disconnected)
+    }
+
+    /// Send a request to `target_id`, and wait for its response
+    pub fn call<A: PortalConvert, R: PortalConvert>(
+        &mut self,
+        target_id: u64,
+        args: &A,
+    ) -> PortalResult<R> {
+        self.call_with(target_id, args, &CallOptions::default())
+    }
+
+    /// Send a request to `target_id`, and wait for its response following `options`
+    pub fn call_with<A: PortalConvert, R: PortalConvert>(
+        &mut self,
+        target_id: u64,
+        args: &A,
+        options: &CallOptions,
+    ) -> PortalResult<R> {
+        self.with_reconnect(|service| {
+            let request_id = service.new_request_id();
+            service.tx_msg(target_id, request_id, false, args)?;
+            service.flush_tx()?;
+            service.blocking_rx_with(request_id, options)
+        })
+    }
+
+    /// Send a request to `target_id` without waiting for a response
+    pub fn notify<A: PortalConvert>(&mut self, target_id: u64, args: &A) -> PortalResult<()> {
+        self.with_reconnect(|service| {
+            let request_id = service.new_request_id();
+            service.tx_msg(target_id, request_id, false, args)?;
+            service.flush_tx()
+        })
+    }
+
     /// Try to read data into the data queue and parse it into `IpcMessage`'s
     pub fn drive_rx(&mut self) -> IpcResult<()> {
         // read into the data queue
@@ -343,29 +733,75 @@ impl<Glue: IpcGlue, Info: IpcServiceInfo> IpcService<Glue, Info> {
         }
     }
 
+    /// Get an id for a new request, that no other request on this connection has
+    pub fn new_request_id(&mut self) -> u64 {
+        let request_id = self.next_request_id;
+        self.next_request_id = self.next_request_id.wrapping_add(1);
+
+        request_id
+    }
+
     /// A blocking RX and deserialization call to the service
-    pub fn blocking_rx<T: PortalConvert>(&mut self, target_id: u64) -> IpcResult<T> {
+    pub fn blocking_rx<T: PortalConvert>(&mut self, request_id: u64) -> IpcResult<T> {
+        self.blocking_rx_with(request_id, &CallOptions::default())
+    }
+
+    /// A blocking RX and deserialization call to the service, following `options`
+    ///
+    /// If this gives up before the response arrives, the response is dropped when it
+    /// does arrive instead of being taken as the response to a later call.
+    pub fn blocking_rx_with<T: PortalConvert>(
+        &mut self,
+        request_id: u64,
+        options: &CallOptions,
+    ) -> IpcResult<T> {
         let is_server = self.is_server;
+        let response_start = if is_server {
+            MESSAGE_CLIENT_RSP_START
+        } else {
+            MESSAGE_SERVER_RSP_START
+        };
+        let deadline = options
+            .timeout_ns
+            .or(self.timeout_ns)
+            .zip(self.glue.now_ns())
+            .map(|(timeout, now)| now.saturating_add(timeout));
 
         loop {
             self.drive_rx()?;
 
-            if self.rx_queue.is_empty() {
-                self.glue.socket_wait();
-                continue;
-            }
+            // Drop the responses to requests that gave up waiting on them
+            let abandoned = &mut self.abandoned;
+            self.rx_queue.retain(|message| {
+                message.start_byte != response_start || !abandoned.remove(&message.request_id)
+            });
 
-            if let Some(reponse) = self.pop_rx_if(|messages| {
-                messages.target_id == target_id
-                    && messages.start_byte
-                        == if is_server {
-                            MESSAGE_CLIENT_RSP_START
-                        } else {
-                            MESSAGE_SERVER_RSP_START
-                        }
+            if let Some(reponse) = self.pop_rx_if(|message| {
+                message.request_id == request_id && message.start_byte == response_start
             }) {
                 return T::deserialize(&mut reponse.data.as_slice());
             }
+
+            let cancelled = options
+                .cancel
+                .as_ref()
+                .is_some_and(|token| token.is_cancelled());
+            let now = self.glue.now_ns();
+            let remaining = deadline
+                .zip(now)
+                .map(|(deadline, now)| deadline.saturating_sub(now));
+
+            if cancelled || remaining == Some(0) {
+                self.abandoned.insert(request_id);
+                return Err(IpcError::Timeout);
+            }
+
+            // Wake up every so often to notice the token being cancelled
+            let wait = match options.cancel {
+                Some(_) => Some(remaining.unwrap_or(CANCEL_POLL_NS).min(CANCEL_POLL_NS)),
+                None => remaining,
+            };
+            self.glue.socket_wait_for(wait)?;
         }
     }
 
@@ -373,8 +809,9 @@ impl<Glue: IpcGlue, Info: IpcServiceInfo> IpcService<Glue, Info> {
     pub fn tx_msg<T: PortalConvert>(
         &mut self,
         target_id: u64,
+        request_id: u64,
         is_response: bool,
-        data: T,
+        data: &T,
     ) -> IpcResult<()> {
         let start_byte = match () {
             _ if self.is_server && is_response => MESSAGE_SERVER_RSP_START,
@@ -390,6 +827,7 @@ impl<Glue: IpcGlue, Info: IpcServiceInfo> IpcService<Glue, Info> {
             start_byte,
             endpoint_hash: Info::ENDPOINT_HASH,
             target_id,
+            request_id,
             data: data_vec,
             end_byte: MESSAGE_END,
         });
@@ -435,3 +873,226 @@ impl<Glue: IpcGlue, Info: IpcServiceInfo> IpcService<Glue, Info> {
         self.rx_queue.remove(index)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+    use alloc::vec;
+    use core::cell::{Cell, RefCell};
+
+    struct TestInfo;
+
+    impl IpcServiceInfo for TestInfo {
+        const ENDPOINT_NAME: &'static str = "test";
+        const ENDPOINT_HASH: u64 = 0x1234;
+    }
+
+    /// A connection that answers every request with `response`, and is lost after
+    /// `lose_after` sends.
+    struct TestGlue {
+        connected: bool,
+        lose_after: Option<usize>,
+        sends: usize,
+        response: Cell<Option<u64>>,
+        /// Keep responses in `held` instead of delivering them
+        hold: Cell<bool>,
+        held: Vec<u8>,
+        rx: Vec<u8>,
+        clock: Cell<u64>,
+    }
+
+    impl TestGlue {
+        fn new(response: Option<u64>) -> Self {
+            Self {
+                connected: true,
+                lose_after: None,
+                sends: 0,
+                response: Cell::new(response),
+                hold: Cell::new(false),
+                held: Vec::new(),
+                rx: Vec::new(),
+                clock: Cell::new(0),
+            }
+        }
+    }
+
+    impl Sender for TestGlue {
+        fn send(&mut self, bytes: &[u8]) -> IpcResult<()> {
+            if self.lose_after == Some(self.sends) {
+                self.connected = false;
+            }
+            if !self.connected {
+                return Err(IpcError::Disconnected);
+            }
+            self.sends += 1;
+
+            // Responses that were held arrive before this one
+            if !self.hold.get() {
+                self.rx.append(&mut self.held);
+            }
+
+            // Respond to the request we were just sent
+            let request = RawIpcBuffer(bytes.into()).populate_ipc_message()?;
+            if let Some(response) = self.response.get() {
+                let mut data = Vec::new();
+                response.serialize(&mut data)?;
+
+                IpcMessage {
+                    start_byte: MESSAGE_SERVER_RSP_START,
+                    endpoint_hash: TestInfo::ENDPOINT_HASH,
+                    target_id: request.target_id,
+                    request_id: request.request_id,
+                    data,
+                    end_byte: MESSAGE_END,
+                }
+                .serialize(if self.hold.get() {
+                    &mut self.held
+                } else {
+                    &mut self.rx
+                })?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Receiver for TestGlue {
+        fn recv(&mut self, bytes: &mut [u8]) -> IpcResult<usize> {
+            let len = bytes.len().min(self.rx.len());
+            bytes[..len].copy_from_slice(&self.rx[..len]);
+            self.rx.drain(..len);
+
+            Ok(len)
+        }
+    }
+
+    impl IpcGlue for TestGlue {
+        fn disconnect(&mut self) {
+            self.connected = false;
+        }
+
+        fn reconnect(&mut self, service: &str) -> IpcResult<()> {
+            assert_eq!(service, "test-service");
+            self.connected = true;
+            self.lose_after = None;
+            Ok(())
+        }
+
+        fn now_ns(&self) -> Option<u64> {
+            self.clock.set(self.clock.get() + 100);
+            Some(self.clock.get())
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = ReconnectPolicy::new(5, 10, 50);
+
+        assert_eq!(policy.backoff_ns(1), 0);
+        assert_eq!(policy.backoff_ns(2), 10);
+        assert_eq!(policy.backoff_ns(3), 20);
+        assert_eq!(policy.backoff_ns(4), 40);
+        assert_eq!(policy.backoff_ns(5), 50);
+        assert_eq!(policy.backoff_ns(100), 50);
+    }
+
+    #[test]
+    fn test_call() {
+        let mut service = IpcService::<_, TestInfo>::new(TestGlue::new(Some(42)), false);
+
+        assert_eq!(service.call::<_, u64>(1, &(7_u8,)), Ok(42));
+        assert_eq!(service.state(), ConnectionState::Connected);
+    }
+
+    #[test]
+    fn test_disconnected_without_reconnect() {
+        let mut glue = TestGlue::new(Some(42));
+        glue.lose_after = Some(0);
+        let mut service = IpcService::<_, TestInfo>::new(glue, false);
+
+        assert_eq!(
+            service.call::<_, u64>(1, &()),
+            Err(PortalError::Disconnected)
+        );
+        assert_eq!(service.state(), ConnectionState::Disconnected);
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let mut glue = TestGlue::new(Some(42));
+        glue.lose_after = Some(1);
+        let mut service = IpcService::<_, TestInfo>::new(glue, false);
+
+        let states = Rc::new(RefCell::new(Vec::new()));
+        let hook_states = states.clone();
+        service.set_state_hook(move |state| hook_states.borrow_mut().push(state));
+        service.set_reconnect("test-service", ReconnectPolicy::new(3, 10, 100));
+
+        assert_eq!(service.call::<_, u64>(1, &()), Ok(42));
+        assert_eq!(service.call::<_, u64>(1, &()), Ok(42));
+        assert_eq!(
+            *states.borrow(),
+            vec![
+                ConnectionState::Disconnected,
+                ConnectionState::Reconnecting { attempt: 1 },
+                ConnectionState::Connected
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut service = IpcService::<_, TestInfo>::new(TestGlue::new(None), false);
+        service.set_timeout(Some(1000));
+
+        assert_eq!(service.call::<_, u64>(1, &()), Err(PortalError::Timeout));
+    }
+
+    #[test]
+    fn test_late_response_dropped() {
+        let glue = TestGlue::new(Some(42));
+        glue.hold.set(true);
+        let mut service = IpcService::<_, TestInfo>::new(glue, false);
+
+        let options = CallOptions::new().timeout(Duration::from_nanos(1000));
+        assert_eq!(
+            service.call_with::<_, u64>(1, &(), &options),
+            Err(PortalError::Timeout)
+        );
+
+        // The response to the call that timed out arrives before the next one's
+        service.glue().hold.set(false);
+        service.glue().response.set(Some(7));
+        assert_eq!(service.call::<_, u64>(1, &()), Ok(7));
+    }
+
+    #[test]
+    fn test_unanswered_call_keeps_next_response() {
+        let mut service = IpcService::<_, TestInfo>::new(TestGlue::new(None), false);
+        service.set_timeout(Some(1000));
+
+        // The service never responds to the call that timed out
+        assert_eq!(service.call::<_, u64>(1, &()), Err(PortalError::Timeout));
+
+        service.glue().response.set(Some(7));
+        assert_eq!(service.call::<_, u64>(1, &()), Ok(7));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut service = IpcService::<_, TestInfo>::new(TestGlue::new(None), false);
+        let token = CancelToken::new();
+        token.cancel();
+
+        let options = CallOptions::new().cancel_on(&token);
+        assert_eq!(
+            service.call_with::<_, u64>(1, &(), &options),
+            Err(PortalError::Timeout)
+        );
+
+        token.reset();
+        service.glue().response.set(Some(42));
+        assert_eq!(service.call_with::<_, u64>(2, &(), &options), Ok(42));
+    }
+}
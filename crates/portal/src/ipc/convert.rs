@@ -26,7 +26,7 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 extern crate alloc;
 use alloc::vec::Vec;
 
-use super::{IpcError, IpcMessage, PortalConvert, Receiver, Sender};
+use super::{IpcError, IpcMessage, PortalBorrow, PortalConvert, Receiver, Sender};
 
 impl Sender for Vec<u8> {
     fn send(&mut self, bytes: &[u8]) -> Result<(), IpcError> {
@@ -49,6 +49,7 @@ impl Receiver for &[u8] {
     fn recv(&mut self, bytes: &mut [u8]) -> super::IpcResult<usize> {
         let min_len = bytes.len().min(self.len());
         bytes[..min_len].copy_from_slice(&self[..min_len]);
+        *self = &self[min_len..];
 
         Ok(min_len)
     }
@@ -121,6 +122,19 @@ impl PortalConvert for u8 {
 
         Ok(recv_array[1])
     }
+
+    /// Bytes are sent packed together, so they can be borrowed from the message.
+    fn serialize_slice(items: &[Self], send: &mut impl Sender) -> Result<usize, IpcError> {
+        send.send(items)?;
+        Ok(items.len())
+    }
+
+    fn deserialize_vec(len: usize, recv: &mut impl Receiver) -> Result<Vec<Self>, IpcError> {
+        let mut vec = alloc::vec![0; len];
+        recv.recv_exact(&mut vec)?;
+
+        Ok(vec)
+    }
 }
 
 impl PortalConvert for u16 {
@@ -455,13 +469,7 @@ where
         send.send(&[CONVERT_VEC])?;
         send.send(&(self.len() as u64).to_ne_bytes())?;
 
-        let mut bytes = 1 + (usize::BITS as usize / 8);
-
-        for item in self {
-            bytes += item.serialize(send)?;
-        }
-
-        Ok(bytes)
+        Ok(1 + (usize::BITS as usize / 8) + T::serialize_slice(self, send)?)
     }
 
     fn deserialize(recv: &mut impl Receiver) -> Result<Self, IpcError> {
@@ -484,15 +492,81 @@ where
                 .map_err(|_| IpcError::BufferInvalidSize)?,
         );
 
-        let mut vec = Vec::with_capacity(vec_len);
-        for _ in 0..vec_len {
-            vec.push(T::deserialize(recv)?);
-        }
+        T::deserialize_vec(vec_len, recv)
+    }
+}
 
-        Ok(vec)
+/// Split the bytes of a length prefixed `magic` value off of the front of `recv`.
+fn borrow_len_prefixed<'a>(recv: &mut &'a [u8], magic: u8) -> Result<&'a [u8], IpcError> {
+    let mut magic_len = [0; 1 + size_of::<u64>()];
+    recv.recv_exact(&mut magic_len)?;
+
+    if magic_len[0] != magic {
+        return Err(IpcError::InvalidMagic {
+            given: magic_len[0],
+            expected: magic,
+        });
+    }
+
+    let len = u64::from_ne_bytes(
+        magic_len[1..]
+            .try_into()
+            .map_err(|_| IpcError::BufferInvalidSize)?,
+    );
+    let (bytes, rest) = usize::try_from(len)
+        .ok()
+        .and_then(|len| recv.split_at_checked(len))
+        .ok_or(IpcError::BufferInvalidSize)?;
+
+    *recv = rest;
+    Ok(bytes)
+}
+
+impl<'a, T: PortalConvert> PortalBorrow<'a> for T {
+    fn deserialize_borrowed(recv: &mut &'a [u8]) -> Result<Self, IpcError> {
+        T::deserialize(recv)
     }
 }
 
+impl<'a> PortalBorrow<'a> for &'a [u8] {
+    fn deserialize_borrowed(recv: &mut &'a [u8]) -> Result<Self, IpcError> {
+        borrow_len_prefixed(recv, CONVERT_VEC)
+    }
+}
+
+impl<'a> PortalBorrow<'a> for &'a str {
+    fn deserialize_borrowed(recv: &mut &'a [u8]) -> Result<Self, IpcError> {
+        core::str::from_utf8(borrow_len_prefixed(recv, CONVERT_STR)?)
+            .map_err(|_| IpcError::Utf8ConvertError)
+    }
+}
+
+macro_rules! tuple_convert {
+    ($($name:ident),+) => {
+        impl<$($name),+> PortalConvert for ($($name,)+)
+        where
+            $($name: PortalConvert),+
+        {
+            #[allow(non_snake_case)]
+            fn serialize(&self, send: &mut impl Sender) -> Result<usize, IpcError> {
+                let ($($name,)+) = self;
+                Ok(0 $(+ $name.serialize(send)?)+)
+            }
+
+            fn deserialize(recv: &mut impl Receiver) -> Result<Self, IpcError> {
+                Ok(($($name::deserialize(recv)?,)+))
+            }
+        }
+    };
+}
+
+tuple_convert!(A);
+tuple_convert!(A, B);
+tuple_convert!(A, B, C);
+tuple_convert!(A, B, C, D);
+tuple_convert!(A, B, C, D, E);
+tuple_convert!(A, B, C, D, E, F);
+
 impl PortalConvert for IpcMessage {
     fn serialize(&self, send: &mut impl Sender) -> Result<usize, IpcError> {
         let mut bytes = 1;
@@ -500,6 +574,7 @@ impl PortalConvert for IpcMessage {
 
         bytes += self.endpoint_hash.serialize(send)?;
         bytes += self.target_id.serialize(send)?;
+        bytes += self.request_id.serialize(send)?;
         bytes += (self.data.len() as u64).serialize(send)?;
         bytes += self.data.len();
 
@@ -593,4 +668,57 @@ mod test {
             my_result_err
         );
     }
+
+    #[test]
+    fn test_tuple_from_slice() {
+        let mut bytes = Vec::new();
+
+        let args = (String::from("hello"), 10_u64, Err::<u8, bool>(true));
+        args.serialize(&mut bytes).unwrap();
+
+        let mut slice = bytes.as_slice();
+        assert_eq!(
+            <(String, u64, Result<u8, bool>)>::deserialize(&mut slice),
+            Ok(args)
+        );
+        assert!(slice.is_empty());
+    }
+
+    #[test]
+    fn test_bytes_are_packed() {
+        let mut bytes = Vec::new();
+
+        let data = vec![1_u8, 2, 3, 4];
+        assert_eq!(data.serialize(&mut bytes), Ok(bytes.len()));
+        assert_eq!(bytes.len(), 1 + size_of::<u64>() + data.len());
+
+        assert_eq!(Vec::<u8>::deserialize(&mut bytes.as_slice()), Ok(data));
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let mut bytes = Vec::new();
+
+        let args = (vec![0xAA_u8; 300], String::from("hello"), 10_u64);
+        args.serialize(&mut bytes).unwrap();
+
+        let mut slice = bytes.as_slice();
+        let data = <&[u8]>::deserialize_borrowed(&mut slice).unwrap();
+        let text = <&str>::deserialize_borrowed(&mut slice).unwrap();
+        let number = u64::deserialize_borrowed(&mut slice).unwrap();
+
+        assert_eq!((data, text, number), (args.0.as_slice(), "hello", 10));
+        assert!(slice.is_empty());
+
+        // The views must point into the message, not at a copy of it
+        assert!(bytes.as_ptr_range().contains(&data.as_ptr()));
+        assert!(bytes.as_ptr_range().contains(&text.as_ptr()));
+
+        // Data that was cut short must not be borrowed past its end
+        let mut short = &bytes[..20];
+        assert_eq!(
+            <&[u8]>::deserialize_borrowed(&mut short),
+            Err(IpcError::BufferInvalidSize)
+        );
+    }
 }
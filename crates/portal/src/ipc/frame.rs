@@ -0,0 +1,250 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Framing for IPC byte streams
+//!
+//! The portal message format can only detect corruption once it has already been
+//! parsed, so glue that moves bytes over an unreliable or untrusted channel wraps
+//! them in frames first:
+//!
+//! ```text
+//! | magic (4) | length (u32 LE) | crc32 (u32 LE) | payload (length bytes) |
+//! ```
+//!
+//! The crc covers both the length and the payload. When a frame fails to validate the
+//! decoder skips ahead to the next magic and tries again, so garbage on the channel
+//! never reaches the message parser, and never grows the receive buffer past one
+//! maximum sized frame.
+
+extern crate alloc;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use util::crc32::{crc32, crc32_continue};
+
+/// The bytes that start every frame
+pub const FRAME_MAGIC: [u8; 4] = *b"QIPC";
+/// The size of the frame header before the payload
+pub const FRAME_HEADER_SIZE: usize = 12;
+/// The default largest payload a frame can carry
+pub const MAX_FRAME_SIZE: usize = 16 * 1024;
+
+fn frame_crc(len: u32, payload: &[u8]) -> u32 {
+    crc32_continue(crc32(&len.to_le_bytes()), payload)
+}
+
+/// Append `payload` to `out` as frames of at most `max_frame_size` bytes each.
+pub fn encode_frames(payload: &[u8], max_frame_size: usize, out: &mut Vec<u8>) {
+    for chunk in payload.chunks(max_frame_size.max(1)) {
+        let len = chunk.len() as u32;
+
+        out.extend_from_slice(&FRAME_MAGIC);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&frame_crc(len, chunk).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+}
+
+/// Turns a stream of received bytes back into the payloads of valid frames
+pub struct FrameDecoder {
+    max_frame_size: usize,
+    raw: Vec<u8>,
+    payload: VecDeque<u8>,
+    dropped_bytes: usize,
+}
+
+impl FrameDecoder {
+    /// Create a decoder that accepts frames up to `MAX_FRAME_SIZE`
+    pub const fn new() -> Self {
+        Self::with_max_frame_size(MAX_FRAME_SIZE)
+    }
+
+    /// Create a decoder that rejects any frame larger than `max_frame_size`
+    pub const fn with_max_frame_size(max_frame_size: usize) -> Self {
+        Self {
+            max_frame_size,
+            raw: Vec::new(),
+            payload: VecDeque::new(),
+            dropped_bytes: 0,
+        }
+    }
+
+    /// The number of received bytes that were thrown away because they were not part
+    /// of a valid frame.
+    ///
+    /// Services can use this to drop a connection that keeps sending garbage.
+    pub fn dropped_bytes(&self) -> usize {
+        self.dropped_bytes
+    }
+
+    /// The number of payload bytes ready to be read
+    pub fn ready(&self) -> usize {
+        self.payload.len()
+    }
+
+    /// Add received bytes to the decoder
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.raw.extend_from_slice(bytes);
+        self.decode();
+    }
+
+    /// Read decoded payload bytes into `bytes`, returning how many were read
+    pub fn read(&mut self, bytes: &mut [u8]) -> usize {
+        let len = bytes.len().min(self.payload.len());
+
+        for (dest, src) in bytes.iter_mut().zip(self.payload.drain(..len)) {
+            *dest = src;
+        }
+
+        len
+    }
+
+    fn drop_raw(&mut self, len: usize) {
+        self.raw.drain(..len);
+        self.dropped_bytes += len;
+    }
+
+    fn decode(&mut self) {
+        loop {
+            // Skip to the next frame, keeping anything that could be the start of a
+            // magic split across two reads.
+            let Some(start) = self
+                .raw
+                .windows(FRAME_MAGIC.len())
+                .position(|window| window == FRAME_MAGIC)
+            else {
+                let keep = self.raw.len().min(FRAME_MAGIC.len() - 1);
+                self.drop_raw(self.raw.len() - keep);
+                return;
+            };
+            self.drop_raw(start);
+
+            let Some(header) = self.raw.get(..FRAME_HEADER_SIZE) else {
+                return;
+            };
+
+            let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            let crc = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+
+            // A length this large can only be corruption, skip the magic so we don't
+            // wait on bytes that will never come.
+            if len as usize > self.max_frame_size {
+                self.drop_raw(1);
+                continue;
+            }
+
+            let frame_end = FRAME_HEADER_SIZE + len as usize;
+            let Some(payload) = self.raw.get(FRAME_HEADER_SIZE..frame_end) else {
+                return;
+            };
+
+            if frame_crc(len, payload) != crc {
+                self.drop_raw(1);
+                continue;
+            }
+
+            self.payload.extend(payload);
+            self.raw.drain(..frame_end);
+        }
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    fn decode_all(decoder: &mut FrameDecoder) -> Vec<u8> {
+        let mut out = vec![0; decoder.ready()];
+        decoder.read(&mut out);
+        out
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut wire = Vec::new();
+        encode_frames(b"hello world", 4, &mut wire);
+        assert_eq!(wire.len(), 11 + 3 * FRAME_HEADER_SIZE);
+
+        // Feed the decoder one byte at a time
+        let mut decoder = FrameDecoder::new();
+        for byte in wire {
+            decoder.push(&[byte]);
+        }
+
+        assert_eq!(decode_all(&mut decoder), b"hello world");
+        assert_eq!(decoder.dropped_bytes(), 0);
+    }
+
+    #[test]
+    fn test_resync() {
+        let mut wire = b"garbage QI".to_vec();
+        encode_frames(b"first", MAX_FRAME_SIZE, &mut wire);
+
+        // A frame with a bad crc, which is also a truncated frame
+        let corrupt_start = wire.len();
+        encode_frames(b"corrupt", MAX_FRAME_SIZE, &mut wire);
+        wire[corrupt_start + FRAME_HEADER_SIZE] ^= 0xFF;
+        wire.truncate(wire.len() - 2);
+
+        encode_frames(b"second", MAX_FRAME_SIZE, &mut wire);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&wire);
+
+        assert_eq!(decode_all(&mut decoder), b"firstsecond");
+        assert_eq!(decoder.dropped_bytes(), 10 + FRAME_HEADER_SIZE + 5);
+    }
+
+    #[test]
+    fn test_max_frame_size() {
+        let mut wire = Vec::new();
+        encode_frames(&[0xAA; 64], 64, &mut wire);
+        encode_frames(b"ok", 64, &mut wire);
+
+        let mut decoder = FrameDecoder::with_max_frame_size(32);
+        decoder.push(&wire);
+
+        assert_eq!(decode_all(&mut decoder), b"ok");
+        assert_eq!(decoder.dropped_bytes(), 64 + FRAME_HEADER_SIZE);
+    }
+
+    #[test]
+    fn test_garbage_is_bounded() {
+        let mut decoder = FrameDecoder::new();
+
+        for _ in 0..1024 {
+            decoder.push(&[0x55; 64]);
+        }
+
+        assert_eq!(decoder.ready(), 0);
+        assert!(decoder.raw.len() < FRAME_MAGIC.len());
+    }
+}
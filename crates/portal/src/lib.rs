@@ -30,5 +30,4 @@ pub use portal_macro::*;
 #[cfg(any(feature = "ipc-client", feature = "ipc-server"))]
 pub mod ipc;
 
-#[cfg(any(feature = "syscall-client", feature = "syscall-server"))]
 pub mod syscall;
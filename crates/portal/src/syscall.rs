@@ -30,6 +30,56 @@ pub const SYSCALL_OKAY_RESP: u64 = 0;
 /// Syscall's inputs where not known by the kernel
 pub const SYSCALL_BAD_RESP: u64 = 1;
 
+/// A `Result` with a stable layout, which is how syscalls return results.
+///
+/// This is laid out as a `u32` tag (`0` for `Ok` and `1` for `Err`) followed by a union of
+/// both values.
+#[repr(C, u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbiResult<T, E> {
+    Ok(T),
+    Err(E),
+}
+
+impl<T, E> From<Result<T, E>> for AbiResult<T, E> {
+    fn from(value: Result<T, E>) -> Self {
+        match value {
+            Ok(ok) => Self::Ok(ok),
+            Err(err) => Self::Err(err),
+        }
+    }
+}
+
+impl<T, E> From<AbiResult<T, E>> for Result<T, E> {
+    fn from(value: AbiResult<T, E>) -> Self {
+        match value {
+            AbiResult::Ok(ok) => Ok(ok),
+            AbiResult::Err(err) => Err(err),
+        }
+    }
+}
+
+/// How `&[T]` and `&str` arguments are read on the other side of a syscall, a pointer to the
+/// first element followed by the length.
+///
+/// Rust does not promise this layout, so it is checked whenever this crate is built.
+#[repr(C)]
+struct RawSlice {
+    ptr: *const u8,
+    len: usize,
+}
+
+const _: () = {
+    assert!(size_of::<&[u8]>() == size_of::<RawSlice>());
+    assert!(size_of::<&str>() == size_of::<RawSlice>());
+
+    let slice: RawSlice = unsafe { core::mem::transmute::<&[u8], _>(&[1, 2, 3]) };
+    assert!(!slice.ptr.is_null() && slice.len == 3, "&[T] is not laid out as (ptr, len)");
+
+    let str: RawSlice = unsafe { core::mem::transmute::<&str, _>("four") };
+    assert!(!str.ptr.is_null() && str.len == 4, "&str is not laid out as (ptr, len)");
+};
+
 pub unsafe trait SyscallInput: Sized {
     /// Version ID of this syscall argument, any new release should increment the syscall number.
     fn version_id() -> u32;
@@ -103,19 +153,27 @@ pub mod server {
 
     /// Convert out of the syscall interface, and back into 'SyscallInput' and 'SyscallOutput'.
     ///
+    /// The input is copied out of the caller with `read_input`, and the output copied back
+    /// with `write_output`, so the server decides how the caller's memory is accessed.
+    ///
     /// # Safety
-    /// The caller must ensure that the `syscall_input_ptr` is valid, and that the `syscall_output_ptr` is valid.
+    /// `read_input` and `write_output` must only succeed if they could copy a whole `I` out of
+    /// `syscall_input_ptr`, and a whole `O` into `syscall_output_ptr`.
     #[inline]
-    pub unsafe fn adapt_syscall<F, I: super::SyscallInput, O: super::SyscallOutput>(
+    pub unsafe fn adapt_syscall<F, R, W, I: super::SyscallInput, O: super::SyscallOutput>(
         kind: u64,
         syscall_input_ptr: *const I,
         syscall_output_ptr: *mut O,
         syscall_packed_len: u64,
         syscall_packed_id: u64,
+        read_input: R,
+        write_output: W,
         callable: F,
     ) -> u64
     where
         F: FnOnce(I) -> O,
+        R: FnOnce(*const I) -> Option<I>,
+        W: FnOnce(*mut O, O) -> bool,
     {
         // If the kind is not reconized
         if kind != super::SYSCALL_CALLER_ID {
@@ -148,8 +206,15 @@ pub mod server {
             return super::SYSCALL_BAD_RESP;
         }
 
-        let input = unsafe { core::ptr::read(syscall_input_ptr) };
-        unsafe { *syscall_output_ptr = callable(input) };
+        let Some(input) = read_input(syscall_input_ptr) else {
+            warnln!("Could not read input");
+            return super::SYSCALL_BAD_RESP;
+        };
+
+        if !write_output(syscall_output_ptr, callable(input)) {
+            warnln!("Could not write output");
+            return super::SYSCALL_BAD_RESP;
+        }
 
         super::SYSCALL_OKAY_RESP
     }
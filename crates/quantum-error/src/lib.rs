@@ -0,0 +1,358 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! # Quantum Error
+//! One error type that every part of QuantumOS can turn its errors into.
+//!
+//! Subsystem errors like `FsError` and `MemoryError` are great inside their own crate,
+//! but they mean nothing to a process on the other side of a syscall or portal. Each
+//! [`QuantumError`] has a stable numeric code, so it can cross those boundaries and still
+//! tell the receiver *why* something failed.
+//!
+//! The conversions from those errors are behind the `fs` and `mem` features, so only the
+//! crates that convert them pull in those subsystems.
+
+#![no_std]
+
+use core::fmt::Display;
+
+#[cfg(feature = "fs")]
+use fs::error::{DiskError, FsError};
+#[cfg(feature = "mem")]
+use mem::MemoryError;
+
+/// # Quantum Error
+/// The reason an operation failed.
+///
+/// The discriminant of each variant is its wire code. Codes are part of the ABI, so
+/// existing codes must never be renumbered or reused; new errors get new codes. Code `0`
+/// is reserved to mean success.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum QuantumError {
+    /// The error does not fit any other kind.
+    Unknown = 1,
+    NotFound = 2,
+    PermissionDenied = 3,
+    AlreadyExists = 4,
+    /// The caller passed an argument that does not make sense.
+    InvalidInput = 5,
+    /// Data that was read back is malformed.
+    InvalidData = 6,
+    NotSupported = 7,
+    OutOfMemory = 8,
+    /// The operation would have to block, try again later.
+    WouldBlock = 9,
+    TimedOut = 10,
+    EndOfFile = 11,
+    /// A device failed to do what was asked.
+    Io = 12,
+    /// The other side of a connection went away.
+    Disconnected = 13,
+    InvalidHandle = 14,
+    /// The resource is being used by someone else.
+    Busy = 15,
+    /// A pointer does not point to accessible memory.
+    BadAddress = 16,
+    /// A removable device has no media in it.
+    NoMedia = 17,
+    /// The filesystem or device was mounted read-only.
+    ReadOnly = 18,
+    /// The filesystem or device has no space left.
+    NoSpace = 19,
+}
+
+impl QuantumError {
+    const ALL: [QuantumError; 19] = [
+        Self::Unknown,
+        Self::NotFound,
+        Self::PermissionDenied,
+        Self::AlreadyExists,
+        Self::InvalidInput,
+        Self::InvalidData,
+        Self::NotSupported,
+        Self::OutOfMemory,
+        Self::WouldBlock,
+        Self::TimedOut,
+        Self::EndOfFile,
+        Self::Io,
+        Self::Disconnected,
+        Self::InvalidHandle,
+        Self::Busy,
+        Self::BadAddress,
+        Self::NoMedia,
+        Self::ReadOnly,
+        Self::NoSpace,
+    ];
+
+    /// The stable wire code of this error.
+    pub const fn code(self) -> u16 {
+        self as u16
+    }
+
+    /// Get the error for a wire code.
+    ///
+    /// Returns `None` for `0` (success), and for codes this build does not know about.
+    pub const fn from_code(code: u16) -> Option<Self> {
+        let mut index = 0;
+        while index < Self::ALL.len() {
+            if Self::ALL[index] as u16 == code {
+                return Some(Self::ALL[index]);
+            }
+            index += 1;
+        }
+
+        None
+    }
+
+    /// Get the error for a wire code, treating codes from a newer build as
+    /// [`QuantumError::Unknown`].
+    pub const fn from_code_lossy(code: u16) -> Self {
+        match Self::from_code(code) {
+            Some(error) => error,
+            None => Self::Unknown,
+        }
+    }
+
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown error",
+            Self::NotFound => "not found",
+            Self::PermissionDenied => "permission denied",
+            Self::AlreadyExists => "already exists",
+            Self::InvalidInput => "invalid input",
+            Self::InvalidData => "invalid data",
+            Self::NotSupported => "not supported",
+            Self::OutOfMemory => "out of memory",
+            Self::WouldBlock => "operation would block",
+            Self::TimedOut => "timed out",
+            Self::EndOfFile => "unexpected end of file",
+            Self::Io => "i/o error",
+            Self::Disconnected => "disconnected",
+            Self::InvalidHandle => "invalid handle",
+            Self::Busy => "resource busy",
+            Self::BadAddress => "bad address",
+            Self::NoMedia => "no media",
+            Self::ReadOnly => "read-only filesystem",
+            Self::NoSpace => "no space left on device",
+        }
+    }
+}
+
+impl Display for QuantumError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} (E{})", self.description(), self.code())
+    }
+}
+
+#[cfg(feature = "fs")]
+impl From<FsError> for QuantumError {
+    fn from(value: FsError) -> Self {
+        match value {
+            FsError::EndOfFile => Self::EndOfFile,
+            FsError::ReadError => Self::Io,
+            FsError::InvalidInput => Self::InvalidInput,
+            FsError::NotFound => Self::NotFound,
+            FsError::NotSupported => Self::NotSupported,
+            FsError::Corrupt => Self::InvalidData,
+            FsError::ReadOnly => Self::ReadOnly,
+            FsError::AlreadyExists => Self::AlreadyExists,
+            FsError::NoSpace => Self::NoSpace,
+            FsError::DiskError(DiskError::NoDevice) => Self::NotFound,
+            FsError::DiskError(DiskError::NoMedia) => Self::NoMedia,
+            FsError::DiskError(DiskError::Timeout) => Self::TimedOut,
+            FsError::DiskError(_) => Self::Io,
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl From<QuantumError> for FsError {
+    fn from(value: QuantumError) -> Self {
+        match value {
+            QuantumError::EndOfFile => Self::EndOfFile,
+            QuantumError::InvalidInput => Self::InvalidInput,
+            QuantumError::NotFound => Self::NotFound,
+            QuantumError::NotSupported => Self::NotSupported,
+            QuantumError::InvalidData => Self::Corrupt,
+            QuantumError::ReadOnly => Self::ReadOnly,
+            QuantumError::AlreadyExists => Self::AlreadyExists,
+            QuantumError::NoSpace => Self::NoSpace,
+            QuantumError::NoMedia => Self::DiskError(DiskError::NoMedia),
+            QuantumError::TimedOut => Self::DiskError(DiskError::Timeout),
+            _ => Self::ReadError,
+        }
+    }
+}
+
+#[cfg(feature = "mem")]
+impl From<MemoryError> for QuantumError {
+    fn from(value: MemoryError) -> Self {
+        match value {
+            MemoryError::OutOfAllocMemory => Self::OutOfMemory,
+            MemoryError::NotFound => Self::NotFound,
+            MemoryError::AlreadyUsed => Self::AlreadyExists,
+            MemoryError::TableNotSupported | MemoryError::NotSupported => Self::NotSupported,
+            MemoryError::PtrWasNull | MemoryError::DidNotHandleException => Self::BadAddress,
+            MemoryError::ParentDropped => Self::Disconnected,
+            MemoryError::InvalidPageTable => Self::InvalidData,
+            MemoryError::ArrayTooSmall
+            | MemoryError::EmptySegment
+            | MemoryError::InvalidSize
+            | MemoryError::EntrySizeIsNegative
+            | MemoryError::NotPageAligned
+            | MemoryError::NotPhysicalPage
+            | MemoryError::DoubleFree => Self::InvalidInput,
+        }
+    }
+}
+
+#[cfg(feature = "mem")]
+impl From<QuantumError> for MemoryError {
+    fn from(value: QuantumError) -> Self {
+        match value {
+            QuantumError::OutOfMemory => Self::OutOfAllocMemory,
+            QuantumError::NotFound => Self::NotFound,
+            QuantumError::AlreadyExists | QuantumError::Busy => Self::AlreadyUsed,
+            QuantumError::BadAddress => Self::PtrWasNull,
+            QuantumError::Disconnected => Self::ParentDropped,
+            QuantumError::InvalidData => Self::InvalidPageTable,
+            QuantumError::InvalidInput => Self::InvalidSize,
+            _ => Self::NotSupported,
+        }
+    }
+}
+
+#[cfg(feature = "ipc")]
+mod ipc {
+    use super::QuantumError;
+    use portal::ipc::{IpcError, PortalConvert, PortalError, Receiver, Sender};
+
+    impl From<IpcError> for QuantumError {
+        fn from(value: IpcError) -> Self {
+            match value {
+                IpcError::NotReady => Self::WouldBlock,
+                IpcError::AlreadyUsed => Self::Busy,
+                IpcError::GlueError | IpcError::Disconnected => Self::Disconnected,
+                IpcError::Timeout => Self::TimedOut,
+                IpcError::InvalidMagic { .. }
+                | IpcError::BufferInvalidSize
+                | IpcError::Utf8ConvertError
+                | IpcError::InvalidTypeConvert
+                | IpcError::InvalidMessage(_)
+                | IpcError::InvalidHash { .. } => Self::InvalidData,
+            }
+        }
+    }
+
+    impl From<PortalError> for QuantumError {
+        fn from(value: PortalError) -> Self {
+            match value {
+                PortalError::Disconnected => Self::Disconnected,
+                PortalError::Timeout => Self::TimedOut,
+                PortalError::DecodeError(ipc_error) => ipc_error.into(),
+            }
+        }
+    }
+
+    impl From<QuantumError> for IpcError {
+        fn from(value: QuantumError) -> Self {
+            match value {
+                QuantumError::WouldBlock => Self::NotReady,
+                QuantumError::Busy => Self::AlreadyUsed,
+                QuantumError::InvalidData => Self::InvalidTypeConvert,
+                QuantumError::Disconnected => Self::Disconnected,
+                QuantumError::TimedOut => Self::Timeout,
+                _ => Self::GlueError,
+            }
+        }
+    }
+
+    /// Errors are sent as their wire code, so a peer built with newer error codes can
+    /// still talk to us.
+    impl PortalConvert for QuantumError {
+        fn serialize(&self, send: &mut impl Sender) -> Result<usize, IpcError> {
+            self.code().serialize(send)
+        }
+
+        fn deserialize(recv: &mut impl Receiver) -> Result<Self, IpcError> {
+            u16::deserialize(recv).map(Self::from_code_lossy)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_codes_round_trip() {
+        for error in QuantumError::ALL {
+            assert_eq!(QuantumError::from_code(error.code()), Some(error));
+        }
+
+        assert_eq!(QuantumError::from_code(0), None);
+        assert_eq!(QuantumError::from_code_lossy(0xBEEF), QuantumError::Unknown);
+    }
+
+    #[test]
+    fn test_codes_are_stable() {
+        assert_eq!(QuantumError::NotFound.code(), 2);
+        assert_eq!(QuantumError::PermissionDenied.code(), 3);
+        assert_eq!(QuantumError::NoMedia.code(), 17);
+        assert_eq!(QuantumError::ReadOnly.code(), 18);
+        assert_eq!(QuantumError::NoSpace.code(), 19);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_fs_error_conversion() {
+        assert_eq!(
+            QuantumError::from(FsError::NotFound),
+            QuantumError::NotFound
+        );
+        assert_eq!(
+            QuantumError::from(FsError::DiskError(DiskError::NoMedia)),
+            QuantumError::NoMedia
+        );
+        assert!(matches!(
+            FsError::from(QuantumError::EndOfFile),
+            FsError::EndOfFile
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "mem")]
+    fn test_mem_error_conversion() {
+        assert_eq!(
+            QuantumError::from(MemoryError::OutOfAllocMemory),
+            QuantumError::OutOfMemory
+        );
+        assert!(matches!(
+            MemoryError::from(QuantumError::OutOfMemory),
+            MemoryError::OutOfAllocMemory
+        ));
+    }
+}
@@ -25,7 +25,7 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 /// # Serial Baud
 /// Set a supported serial baud rate for serial comms.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SerialBaud {
     Baud115200,
     Baud57600,
@@ -57,7 +57,32 @@ impl SerialBaud {
             Self::Baud2400 => 48,
             Self::Baud1200 => 96,
             Self::Baud600 => 192,
-            Self::Baud300 => 348,
+            Self::Baud300 => 384,
         }
     }
+
+    /// # Rate
+    /// The number of bits per second this baud rate sends.
+    pub const fn rate(self) -> u32 {
+        115200 / self.get_divisor() as u32
+    }
+
+    /// # From Rate
+    /// Get the baud rate that sends `rate` bits per second, if it is supported.
+    pub const fn from_rate(rate: u32) -> Option<Self> {
+        Some(match rate {
+            115200 => Self::Baud115200,
+            57600 => Self::Baud57600,
+            38400 => Self::Baud38400,
+            19200 => Self::Baud19200,
+            14400 => Self::Baud14400,
+            9600 => Self::Baud9600,
+            4800 => Self::Baud4800,
+            2400 => Self::Baud2400,
+            1200 => Self::Baud1200,
+            600 => Self::Baud600,
+            300 => Self::Baud300,
+            _ => return None,
+        })
+    }
 }
@@ -0,0 +1,157 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::baud::SerialBaud;
+
+/// # Parity
+/// The parity bit sent after each character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    /// The parity bit is always set
+    Mark,
+    /// The parity bit is always clear
+    Space,
+}
+
+/// # Stop Bits
+/// The number of stop bits sent after each character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+/// # Serial Config
+/// The line settings for a serial port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerialConfig {
+    pub baud: SerialBaud,
+    /// Between 5 and 8 bits per character
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+impl SerialConfig {
+    /// # New
+    /// 8 data bits, no parity, and one stop bit at `baud`.
+    pub const fn new(baud: SerialBaud) -> Self {
+        Self {
+            baud,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+        }
+    }
+
+    /// # With Mode
+    /// Set the data bits, parity, and stop bits from a mode like `8N1` or `7e2`.
+    pub fn with_mode(self, mode: &str) -> Option<Self> {
+        let &[data_bits, parity, stop_bits] = mode.as_bytes() else {
+            return None;
+        };
+
+        let data_bits = match data_bits {
+            b'5'..=b'8' => data_bits - b'0',
+            _ => return None,
+        };
+        let parity = match parity.to_ascii_lowercase() {
+            b'n' => Parity::None,
+            b'o' => Parity::Odd,
+            b'e' => Parity::Even,
+            b'm' => Parity::Mark,
+            b's' => Parity::Space,
+            _ => return None,
+        };
+        let stop_bits = match stop_bits {
+            b'1' => StopBits::One,
+            b'2' => StopBits::Two,
+            _ => return None,
+        };
+
+        Some(Self {
+            data_bits,
+            parity,
+            stop_bits,
+            ..self
+        })
+    }
+
+    /// # Line Control
+    /// The value of the line control register for these settings, with DLAB clear.
+    pub const fn line_control(&self) -> u8 {
+        let data_bits = self.data_bits.saturating_sub(5) & 0b11;
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 0,
+            StopBits::Two => 1 << 2,
+        };
+        let parity = match self.parity {
+            Parity::None => 0b000,
+            Parity::Odd => 0b001,
+            Parity::Even => 0b011,
+            Parity::Mark => 0b101,
+            Parity::Space => 0b111,
+        };
+
+        data_bits | stop_bits | (parity << 3)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_is_8n1() {
+        assert_eq!(
+            SerialConfig::new(SerialBaud::Baud115200).line_control(),
+            0x03
+        );
+    }
+
+    #[test]
+    fn test_with_mode() {
+        let config = SerialConfig::new(SerialBaud::Baud9600)
+            .with_mode("7E2")
+            .unwrap();
+
+        assert_eq!(config.data_bits, 7);
+        assert_eq!(config.parity, Parity::Even);
+        assert_eq!(config.stop_bits, StopBits::Two);
+        assert_eq!(config.line_control(), 0b0001_1110);
+
+        assert_eq!(
+            SerialConfig::new(SerialBaud::Baud9600).with_mode("9n1"),
+            None
+        );
+        assert_eq!(
+            SerialConfig::new(SerialBaud::Baud9600).with_mode("8n"),
+            None
+        );
+    }
+}
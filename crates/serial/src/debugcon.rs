@@ -0,0 +1,70 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2024 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use arch::io::IOPort;
+
+/// # Debugcon
+/// The Bochs/QEMU debug console, every byte written to port `0xE9` is forwarded
+/// by the emulator (QEMU needs `-debugcon`) without any setup.
+pub struct DebugCon {
+    port: IOPort,
+}
+
+impl DebugCon {
+    pub const PORT: IOPort = IOPort::new(0xE9);
+
+    /// # New
+    /// Writes are harmless on real hardware, they are just dropped.
+    pub const fn new() -> Self {
+        Self { port: Self::PORT }
+    }
+
+    /// # Probe
+    /// Both Bochs and QEMU read `0xE9` back from the port when debugcon is attached.
+    pub fn probe() -> Option<Self> {
+        if unsafe { Self::PORT.read_byte() } == 0xE9 {
+            Some(Self::new())
+        } else {
+            None
+        }
+    }
+
+    /// # Transmit Byte
+    /// Send a byte to the debug console.
+    #[inline]
+    pub fn transmit_byte(&self, byte: u8) {
+        unsafe { self.port.write_byte(byte) };
+    }
+}
+
+impl core::fmt::Write for DebugCon {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            self.transmit_byte(byte);
+        }
+
+        Ok(())
+    }
+}
@@ -26,27 +26,106 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 #![no_std]
 
 use arch::io::IOPort;
+use config::SerialConfig;
 
 pub mod baud;
+pub mod config;
+pub mod debugcon;
 mod registers;
+pub mod tx_ring;
+
+/// # Com Port
+/// The four standard PC serial ports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComPort {
+    Com1,
+    Com2,
+    Com3,
+    Com4,
+}
+
+impl ComPort {
+    pub const ALL: [ComPort; 4] = [Self::Com1, Self::Com2, Self::Com3, Self::Com4];
+
+    /// # IO Port
+    /// The base of this port's registers.
+    pub const fn io_port(self) -> IOPort {
+        match self {
+            Self::Com1 => registers::ports::COM1,
+            Self::Com2 => registers::ports::COM2,
+            Self::Com3 => registers::ports::COM3,
+            Self::Com4 => registers::ports::COM4,
+        }
+    }
+
+    /// # IRQ
+    /// The ISA IRQ this port raises, COM3 and COM4 share theirs with COM1 and COM2.
+    pub const fn irq(self) -> u8 {
+        match self {
+            Self::Com1 | Self::Com3 => 4,
+            Self::Com2 | Self::Com4 => 3,
+        }
+    }
+
+    /// # From Name
+    /// Get the port named `name`, like `com2`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|port| port.name().eq_ignore_ascii_case(name))
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Com1 => "com1",
+            Self::Com2 => "com2",
+            Self::Com3 => "com3",
+            Self::Com4 => "com4",
+        }
+    }
+}
+
+/// # Uart Kind
+/// Which UART the port is, found by how it answers to turning on its FIFO.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UartKind {
+    /// No FIFO, only one byte can be sent at a time
+    Uart8250,
+    /// Has a FIFO, but it is too broken to use
+    Uart16550,
+    /// Has a working 16 byte FIFO
+    Uart16550A,
+}
+
+impl UartKind {
+    /// # Fifo Len
+    /// How many bytes can be written each time the transmitter is empty.
+    pub const fn fifo_len(self) -> usize {
+        match self {
+            Self::Uart8250 | Self::Uart16550 => 1,
+            Self::Uart16550A => 16,
+        }
+    }
+}
 
 pub struct Serial {
-    baud: baud::SerialBaud,
+    config: SerialConfig,
     port: IOPort,
+    uart: UartKind,
 }
 
 /// # Init Serial Device
 /// Probe and init a serial device.
-unsafe fn init_serial_device(baud: baud::SerialBaud, port: IOPort) -> bool {
+unsafe fn init_serial_device(config: SerialConfig, port: IOPort) -> Option<UartKind> {
     // Disable interrupts and enable DLAB bit
     registers::write_interrupt_enable(port, 0x00);
     registers::write_line_control(port, 0x80);
 
     // Setup devisor and loopback
-    let divisor = baud.get_divisor();
+    let divisor = config.baud.get_divisor();
     registers::write_dlab_lsb(port, divisor as u8);
     registers::write_dlab_msb(port, (divisor >> 8) as u8);
-    registers::write_line_control(port, 0x03); /* 8-bit char, no parity mode, and one stop bit */
+    registers::write_line_control(port, config.line_control());
     registers::write_fifo_control(port, 0xC7);
     registers::write_modem_control(port, 0x0B);
     registers::write_modem_control(port, 0x1E);
@@ -54,23 +133,28 @@ unsafe fn init_serial_device(baud: baud::SerialBaud, port: IOPort) -> bool {
     // Preform 3 tests to check if loopback is working
     registers::write_transmit_buffer(port, 0xFF);
     if registers::read_receive_buffer(port) != 0xFF {
-        return false;
+        return None;
     }
 
     registers::write_transmit_buffer(port, 0xAB);
     if registers::read_receive_buffer(port) != 0xAB {
-        return false;
+        return None;
     }
 
     registers::write_transmit_buffer(port, 0x00);
     if registers::read_receive_buffer(port) != 0x00 {
-        return false;
+        return None;
     }
 
     // Finally turn off loopback and go into normal mode
     registers::write_modem_control(port, 0x0F);
 
-    true
+    // The top two bits of the IIR say if the FIFO we asked for above was turned on
+    Some(match registers::read_interrupt_identification(port) >> 6 {
+        0b11 => UartKind::Uart16550A,
+        0b10 => UartKind::Uart16550,
+        _ => UartKind::Uart8250,
+    })
 }
 
 impl Serial {
@@ -80,10 +164,12 @@ impl Serial {
     /// (When using an Emulator this is the best option to find which
     ///  serial port the emulator is connected to.)
     pub fn probe_first(baud: baud::SerialBaud) -> Option<Self> {
+        let config = SerialConfig::new(baud);
+
         for _ in 0..5 {
             for port in registers::ports::COMMS_ARRAY {
-                if unsafe { init_serial_device(baud, port) } {
-                    return Some(Self { baud, port });
+                if let Some(uart) = unsafe { init_serial_device(config, port) } {
+                    return Some(Self { config, port, uart });
                 }
             }
         }
@@ -91,20 +177,122 @@ impl Serial {
         None
     }
 
+    /// # Open
+    /// Init `com` with `config`, if the port exists.
+    pub fn open(com: ComPort, config: SerialConfig) -> Option<Self> {
+        let port = com.io_port();
+        let uart = unsafe { init_serial_device(config, port) }?;
+
+        Some(Self { config, port, uart })
+    }
+
+    /// # Reconfigure
+    /// Change the line settings of this port.
+    ///
+    /// This also turns off the port's interrupts.
+    pub fn reconfigure(&mut self, config: SerialConfig) -> bool {
+        match unsafe { init_serial_device(config, self.port) } {
+            Some(uart) => {
+                self.config = config;
+                self.uart = uart;
+                true
+            }
+            None => false,
+        }
+    }
+
     /// # Transmit Byte
     /// This will send a byte over serial.
     #[inline]
     pub fn transmit_byte(&self, byte: u8) {
+        unsafe { Self::transmit_byte_on(self.port, byte) };
+    }
+
+    /// # Transmit Byte On
+    /// Send a byte on the port at `port`, waiting for room, without needing its `Serial`.
+    ///
+    /// # Safety
+    /// `port` must be a serial port that has already been set up.
+    pub unsafe fn transmit_byte_on(port: IOPort, byte: u8) {
         unsafe {
-            while registers::read_line_status(self.port) & 0x20 == 0 {}
-            registers::write_transmit_buffer(self.port, byte);
+            while registers::read_line_status(port) & 0x20 == 0 {}
+            registers::write_transmit_buffer(port, byte);
+        }
+    }
+
+    /// # Transmit Ready
+    /// Check if the transmitter (and its FIFO) is empty.
+    #[inline]
+    pub fn transmit_ready(&self) -> bool {
+        unsafe { registers::read_line_status(self.port) & 0x20 != 0 }
+    }
+
+    /// # Fill Fifo
+    /// If the transmitter is empty, fill it with bytes from `next` without waiting.
+    ///
+    /// Returns the number of bytes written.
+    pub fn fill_fifo(&self, mut next: impl FnMut() -> Option<u8>) -> usize {
+        if !self.transmit_ready() {
+            return 0;
+        }
+
+        let mut written = 0;
+        while written < self.uart.fifo_len() {
+            let Some(byte) = next() else {
+                break;
+            };
+
+            unsafe { registers::write_transmit_buffer(self.port, byte) };
+            written += 1;
         }
+
+        written
+    }
+
+    /// # Set Transmit Interrupt
+    /// Raise an interrupt each time the transmitter becomes empty.
+    pub fn set_transmit_interrupt(&self, enabled: bool) {
+        unsafe { registers::write_interrupt_enable(self.port, if enabled { 0x02 } else { 0x00 }) };
+    }
+
+    /// # Acknowledge Interrupt
+    /// Read the port's pending interrupt, which clears a transmitter empty interrupt.
+    ///
+    /// Returns `false` if the port has no interrupt pending.
+    pub fn acknowledge_interrupt(&self) -> bool {
+        unsafe { registers::read_interrupt_identification(self.port) & 0x01 == 0 }
     }
 
     /// # Get Baud
     /// Get the currently set baud rate.
     pub fn get_baud(&self) -> baud::SerialBaud {
-        self.baud
+        self.config.baud
+    }
+
+    /// # Get Config
+    /// Get the current line settings.
+    pub fn get_config(&self) -> SerialConfig {
+        self.config
+    }
+
+    /// # IO Port
+    /// The first of the port's registers.
+    pub fn io_port(&self) -> IOPort {
+        self.port
+    }
+
+    /// # Com Port
+    /// Which of the standard ports this is, if it is one of them.
+    pub fn com_port(&self) -> Option<ComPort> {
+        ComPort::ALL
+            .into_iter()
+            .find(|com| com.io_port() == self.port)
+    }
+
+    /// # Get Uart
+    /// Get which UART this port is.
+    pub fn get_uart(&self) -> UartKind {
+        self.uart
     }
 }
 
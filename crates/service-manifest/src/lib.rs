@@ -0,0 +1,476 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! # Service Manifest
+//! The list of services `init` starts, and what each of them needs.
+//!
+//! The manifest is a small INI style file. Each service starts with a `[name]` header,
+//! followed by `key = value` pairs, and lines starting with `#` are comments:
+//!
+//! ```text
+//! [logd]
+//! binary = logd
+//! requires = fs-server
+//! restart = on-failure
+//! capabilities = log-ring
+//! ```
+//!
+//! `binary` is the initfs program the service runs and defaults to its name, `requires`
+//! lists the services started before it, `restart` is `always`, `on-failure` (the default)
+//! or `never`, and `capabilities` lists the privileged things the service uses.
+//!
+//! The same parser is used by `init` when booting and by the imager when building, so a
+//! broken manifest fails the build instead of the boot.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Display;
+
+/// When a service is started again after it exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    Always,
+    /// Only if it failed or faulted
+    OnFailure,
+    Never,
+}
+
+impl Restart {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(Self::Always),
+            "on-failure" => Some(Self::OnFailure),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Something privileged a service asks to use
+///
+/// `init` spawns each service with the kernel capability of the same name, and the kernel
+/// refuses the syscalls behind a capability to any service that did not ask for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Claiming IO ports to drive hardware
+    IoPorts,
+    /// Owning the framebuffer
+    Framebuffer,
+    /// Reading the kernel's log ring
+    LogRing,
+    /// Looking inside the system, like taking screenshots or reading other processes' memory
+    Debug,
+    /// Reading the disks the kernel drives itself, like USB sticks
+    Disks,
+    /// Changing system wide settings, like the keyboard layout
+    System,
+}
+
+impl Capability {
+    /// Every capability a service can ask for
+    pub const ALL: [Self; 6] = [
+        Self::IoPorts,
+        Self::Framebuffer,
+        Self::LogRing,
+        Self::Debug,
+        Self::Disks,
+        Self::System,
+    ];
+
+    /// The name of this capability in a manifest
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::IoPorts => "io-ports",
+            Self::Framebuffer => "framebuffer",
+            Self::LogRing => "log-ring",
+            Self::Debug => "debug",
+            Self::Disks => "disks",
+            Self::System => "system",
+        }
+    }
+
+    /// Find the capability called `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cap| cap.name() == name)
+    }
+}
+
+/// A service `init` starts and looks after
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceDef {
+    pub name: String,
+    /// The initfs program the service runs
+    pub binary: String,
+    /// The services that are started before this one
+    pub requires: Vec<String>,
+    pub restart: Restart,
+    pub capabilities: Vec<Capability>,
+}
+
+/// Why a manifest could not be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    /// A line is not a comment, a `[service]` header or a `key = value` pair
+    Syntax {
+        line: usize,
+    },
+    /// A `key = value` pair came before the first `[service]` header
+    NoService {
+        line: usize,
+    },
+    UnknownKey {
+        line: usize,
+    },
+    UnknownRestart {
+        line: usize,
+    },
+    UnknownCapability {
+        line: usize,
+    },
+    Duplicate {
+        service: String,
+    },
+    /// A service requires a service the manifest does not have
+    MissingDependency {
+        service: String,
+        requires: String,
+    },
+    /// A service ends up requiring itself
+    Cycle {
+        service: String,
+    },
+    /// A service runs a program that does not exist
+    MissingBinary {
+        service: String,
+        binary: String,
+    },
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Syntax { line } => write!(f, "line {line} is not a header or `key = value`"),
+            Self::NoService { line } => write!(f, "line {line} is not inside a `[service]`"),
+            Self::UnknownKey { line } => write!(f, "line {line} has an unknown key"),
+            Self::UnknownRestart { line } => write!(f, "line {line} has an unknown restart policy"),
+            Self::UnknownCapability { line } => write!(f, "line {line} has an unknown capability"),
+            Self::Duplicate { service } => write!(f, "'{service}' is listed more than once"),
+            Self::MissingDependency { service, requires } => {
+                write!(f, "'{service}' requires '{requires}', which is not listed")
+            }
+            Self::Cycle { service } => write!(f, "'{service}' ends up requiring itself"),
+            Self::MissingBinary { service, binary } => {
+                write!(f, "'{service}' runs '{binary}', which does not exist")
+            }
+        }
+    }
+}
+
+/// # Manifest
+/// A parsed list of services, checked to have every dependency and no cycles.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    services: Vec<ServiceDef>,
+    /// Indexes of `services`, each after every service it requires
+    order: Vec<usize>,
+}
+
+impl Manifest {
+    /// # Parse
+    /// Parse a manifest, and check that its services can all be started.
+    pub fn parse(text: &str) -> Result<Self, ManifestError> {
+        let services = parse_services(text)?;
+        let order = start_order(&services)?;
+
+        Ok(Self { services, order })
+    }
+
+    /// The services, in the order they are listed
+    pub fn services(&self) -> &[ServiceDef] {
+        &self.services
+    }
+
+    /// Indexes of [`Self::services`], in an order where each service comes after every
+    /// service it requires
+    pub fn start_order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Check that every service's binary exists
+    pub fn check_binaries(&self, exists: impl Fn(&str) -> bool) -> Result<(), ManifestError> {
+        match self
+            .services
+            .iter()
+            .find(|service| !exists(&service.binary))
+        {
+            Some(service) => Err(ManifestError::MissingBinary {
+                service: service.name.clone(),
+                binary: service.binary.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Take the services and their start order
+    pub fn into_parts(self) -> (Vec<ServiceDef>, Vec<usize>) {
+        (self.services, self.order)
+    }
+}
+
+fn parse_services(text: &str) -> Result<Vec<ServiceDef>, ManifestError> {
+    let mut services: Vec<ServiceDef> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(ManifestError::Syntax { line: line_number });
+            }
+            if services.iter().any(|service| service.name == name) {
+                return Err(ManifestError::Duplicate {
+                    service: name.into(),
+                });
+            }
+
+            services.push(ServiceDef {
+                name: name.into(),
+                binary: name.into(),
+                requires: Vec::new(),
+                restart: Restart::OnFailure,
+                capabilities: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(ManifestError::Syntax { line: line_number });
+        };
+        let Some(service) = services.last_mut() else {
+            return Err(ManifestError::NoService { line: line_number });
+        };
+
+        let value = value.trim();
+        match key.trim() {
+            "binary" if !value.is_empty() => service.binary = value.into(),
+            "binary" => return Err(ManifestError::Syntax { line: line_number }),
+            "requires" => service
+                .requires
+                .extend(value.split_whitespace().map(String::from)),
+            "restart" => {
+                service.restart = Restart::from_name(value)
+                    .ok_or(ManifestError::UnknownRestart { line: line_number })?
+            }
+            "capabilities" => {
+                for name in value.split_whitespace() {
+                    let cap = Capability::from_name(name)
+                        .ok_or(ManifestError::UnknownCapability { line: line_number })?;
+                    if !service.capabilities.contains(&cap) {
+                        service.capabilities.push(cap);
+                    }
+                }
+            }
+            _ => return Err(ManifestError::UnknownKey { line: line_number }),
+        }
+    }
+
+    Ok(services)
+}
+
+fn start_order(services: &[ServiceDef]) -> Result<Vec<usize>, ManifestError> {
+    let index_of = |name: &str| services.iter().position(|service| service.name == name);
+
+    for service in services {
+        if let Some(requires) = service
+            .requires
+            .iter()
+            .find(|name| index_of(name).is_none())
+        {
+            return Err(ManifestError::MissingDependency {
+                service: service.name.clone(),
+                requires: requires.clone(),
+            });
+        }
+    }
+
+    let mut order = Vec::with_capacity(services.len());
+    while order.len() < services.len() {
+        let ready = (0..services.len()).find(|index| {
+            !order.contains(index)
+                && services[*index]
+                    .requires
+                    .iter()
+                    .all(|name| order.contains(&index_of(name).unwrap()))
+        });
+
+        match ready {
+            Some(index) => order.push(index),
+            None => {
+                let stuck = (0..services.len())
+                    .find(|index| !order.contains(index))
+                    .unwrap();
+
+                return Err(ManifestError::Cycle {
+                    service: services[stuck].name.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    const EXAMPLE: &str = "
+        # The disk comes first
+        [fs-server]
+        capabilities = io-ports
+
+        [logd]
+        requires = fs-server
+        restart = always
+        capabilities = log-ring
+
+        [shell]
+        binary = debug-shell
+        requires = logd fs-server
+        restart = never
+    ";
+
+    fn names(manifest: &Manifest) -> Vec<&str> {
+        manifest
+            .start_order()
+            .iter()
+            .map(|&index| manifest.services()[index].name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let manifest = Manifest::parse(EXAMPLE).unwrap();
+        let services = manifest.services();
+
+        assert_eq!(services.len(), 3);
+        assert_eq!(services[0].binary, "fs-server");
+        assert_eq!(services[0].restart, Restart::OnFailure);
+        assert_eq!(services[0].capabilities, vec![Capability::IoPorts]);
+        assert_eq!(services[1].restart, Restart::Always);
+        assert_eq!(services[2].binary, "debug-shell");
+        assert_eq!(services[2].requires, vec!["logd", "fs-server"]);
+        assert_eq!(services[2].restart, Restart::Never);
+    }
+
+    #[test]
+    fn test_dependencies_start_first() {
+        let manifest =
+            Manifest::parse("[shell]\nrequires = logd\n[logd]\nrequires = fs\n[fs]\n[clock]\n")
+                .unwrap();
+
+        assert_eq!(names(&manifest), vec!["fs", "logd", "shell", "clock"]);
+    }
+
+    #[test]
+    fn test_broken_manifests() {
+        let error = |text| Manifest::parse(text).unwrap_err();
+
+        assert_eq!(error("[a]\nnonsense"), ManifestError::Syntax { line: 2 });
+        assert_eq!(
+            error("restart = never"),
+            ManifestError::NoService { line: 1 }
+        );
+        assert_eq!(
+            error("[a]\ncolour = red"),
+            ManifestError::UnknownKey { line: 2 }
+        );
+        assert_eq!(
+            error("[a]\nrestart = sometimes"),
+            ManifestError::UnknownRestart { line: 2 }
+        );
+        assert_eq!(
+            error("[a]\ncapabilities = root"),
+            ManifestError::UnknownCapability { line: 2 }
+        );
+        assert_eq!(
+            error("[a]\n[a]"),
+            ManifestError::Duplicate {
+                service: "a".into()
+            }
+        );
+        assert_eq!(
+            error("[a]\nrequires = b"),
+            ManifestError::MissingDependency {
+                service: "a".into(),
+                requires: "b".into()
+            }
+        );
+        assert_eq!(
+            error("[a]\nrequires = b\n[b]\nrequires = a\n[c]"),
+            ManifestError::Cycle {
+                service: "a".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_binaries() {
+        let manifest = Manifest::parse(EXAMPLE).unwrap();
+
+        assert_eq!(manifest.check_binaries(|_| true), Ok(()));
+        assert_eq!(
+            manifest.check_binaries(|binary| binary != "debug-shell"),
+            Err(ManifestError::MissingBinary {
+                service: "shell".into(),
+                binary: "debug-shell".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_init_manifest_parses() {
+        let manifest = Manifest::parse(include_str!("../../../user/init/services.conf")).unwrap();
+
+        assert!(
+            manifest
+                .services()
+                .iter()
+                .any(|service| service.name == "fs-server")
+        );
+    }
+}
@@ -0,0 +1,241 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! # TTY
+//! A terminal line discipline that sits between raw input bytes and a reader.
+//!
+//! In canonical mode input is collected into lines, and the line can be edited with
+//! backspace and kill-line before it is handed to the reader. In raw mode every byte
+//! is handed to the reader as soon as it arrives.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{collections::vec_deque::VecDeque, vec::Vec};
+
+/// ASCII backspace
+pub const BACKSPACE: u8 = 0x08;
+/// ASCII delete, which most terminals send for the backspace key
+pub const DELETE: u8 = 0x7F;
+/// Ctrl-U, erases the whole line
+pub const KILL_LINE: u8 = 0x15;
+
+/// How input is processed before it is handed to the reader
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TtyMode {
+    /// Collect input into lines, and allow editing the line before it is read
+    pub canonical: bool,
+    /// Write input back to the terminal as it is typed
+    pub echo: bool,
+}
+
+impl TtyMode {
+    /// Line buffered input with echo, what a shell wants
+    pub const CANONICAL: Self = Self {
+        canonical: true,
+        echo: true,
+    };
+    /// Unprocessed input without echo, what an editor wants
+    pub const RAW: Self = Self {
+        canonical: false,
+        echo: false,
+    };
+}
+
+impl Default for TtyMode {
+    fn default() -> Self {
+        Self::CANONICAL
+    }
+}
+
+/// The line discipline state of a terminal
+#[derive(Debug, Default)]
+pub struct LineDiscipline {
+    mode: TtyMode,
+    /// The line currently being edited
+    line: Vec<u8>,
+    /// Bytes that are ready to be read
+    ready: VecDeque<u8>,
+}
+
+impl LineDiscipline {
+    /// Create a new line discipline in canonical mode
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current input mode
+    pub const fn mode(&self) -> TtyMode {
+        self.mode
+    }
+
+    /// Change the input mode
+    ///
+    /// Leaving canonical mode hands the partially edited line to the reader.
+    pub fn set_mode(&mut self, mode: TtyMode) {
+        if self.mode.canonical && !mode.canonical {
+            self.ready.extend(self.line.drain(..));
+        }
+
+        self.mode = mode;
+    }
+
+    /// Process one byte of input, calling `echo` with anything that should be
+    /// written back to the terminal.
+    pub fn input(&mut self, byte: u8, mut echo: impl FnMut(&[u8])) {
+        if !self.mode.canonical {
+            self.ready.push_back(byte);
+            if self.mode.echo {
+                echo(&[byte]);
+            }
+
+            return;
+        }
+
+        match byte {
+            b'\r' | b'\n' => {
+                self.line.push(b'\n');
+                self.ready.extend(self.line.drain(..));
+
+                if self.mode.echo {
+                    echo(b"\r\n");
+                }
+            }
+            BACKSPACE | DELETE => {
+                if self.line.pop().is_some() && self.mode.echo {
+                    echo(b"\x08 \x08");
+                }
+            }
+            KILL_LINE => {
+                for _ in self.line.drain(..) {
+                    if self.mode.echo {
+                        echo(b"\x08 \x08");
+                    }
+                }
+            }
+            byte => {
+                self.line.push(byte);
+                if self.mode.echo {
+                    echo(&[byte]);
+                }
+            }
+        }
+    }
+
+    /// Is there anything for the reader to read?
+    pub fn can_read(&self) -> bool {
+        !self.ready.is_empty()
+    }
+
+    /// Read input into `buf`, returning how many bytes were read
+    ///
+    /// In canonical mode, a read never returns more than one line.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut bytes = 0;
+
+        for entry_mut in buf.iter_mut() {
+            let Some(byte) = self.ready.pop_front() else {
+                break;
+            };
+
+            *entry_mut = byte;
+            bytes += 1;
+
+            if self.mode.canonical && byte == b'\n' {
+                break;
+            }
+        }
+
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+    use std::vec::Vec;
+
+    fn feed(tty: &mut LineDiscipline, input: &[u8]) -> Vec<u8> {
+        let mut echoed = Vec::new();
+        for byte in input {
+            tty.input(*byte, |echo| echoed.extend_from_slice(echo));
+        }
+
+        echoed
+    }
+
+    fn read_all(tty: &mut LineDiscipline) -> Vec<u8> {
+        let mut buf = [0; 64];
+        let len = tty.read(&mut buf);
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn test_canonical_lines() {
+        let mut tty = LineDiscipline::new();
+
+        assert_eq!(feed(&mut tty, b"ls"), b"ls");
+        assert!(!tty.can_read());
+
+        assert_eq!(feed(&mut tty, b"\rcd /\r"), b"\r\ncd /\r\n");
+        assert_eq!(read_all(&mut tty), b"ls\n");
+        assert_eq!(read_all(&mut tty), b"cd /\n");
+        assert!(!tty.can_read());
+    }
+
+    #[test]
+    fn test_line_editing() {
+        let mut tty = LineDiscipline::new();
+
+        assert_eq!(feed(&mut tty, b"lx\x7F"), b"lx\x08 \x08");
+        feed(&mut tty, b"s");
+        assert_eq!(read_all(&mut tty), b"");
+
+        assert_eq!(feed(&mut tty, &[KILL_LINE]), b"\x08 \x08\x08 \x08");
+        // Backspace on an empty line does nothing
+        assert_eq!(feed(&mut tty, &[BACKSPACE]), b"");
+
+        feed(&mut tty, b"pwd\n");
+        assert_eq!(read_all(&mut tty), b"pwd\n");
+    }
+
+    #[test]
+    fn test_raw_and_echo() {
+        let mut tty = LineDiscipline::new();
+        feed(&mut tty, b"vi");
+
+        tty.set_mode(TtyMode::RAW);
+        assert_eq!(feed(&mut tty, b"\x7Fq"), b"");
+        assert_eq!(read_all(&mut tty), b"vi\x7Fq");
+
+        tty.set_mode(TtyMode {
+            canonical: false,
+            echo: true,
+        });
+        assert_eq!(feed(&mut tty, b"a"), b"a");
+    }
+}
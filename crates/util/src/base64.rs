@@ -0,0 +1,185 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The number of characters on each line before wrapping, as used by MIME.
+pub const LINE_WIDTH: usize = 76;
+
+/// A streaming base64 encoder.
+///
+/// Bytes can be given to [`Base64Encoder::write`] in any sized pieces, and the encoded
+/// text is passed to `out` wrapped into lines of [`LINE_WIDTH`] characters. Nothing needs
+/// to be allocated, so large buffers (like the framebuffer) can be encoded directly.
+pub struct Base64Encoder<F: FnMut(&[u8])> {
+    out: F,
+    pending: [u8; 3],
+    pending_len: usize,
+    line_len: usize,
+}
+
+impl<F: FnMut(&[u8])> Base64Encoder<F> {
+    /// Make a new encoder that outputs into `out`.
+    pub const fn new(out: F) -> Self {
+        Self {
+            out,
+            pending: [0; 3],
+            pending_len: 0,
+            line_len: 0,
+        }
+    }
+
+    /// Encode more `bytes`.
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.pending[self.pending_len] = byte;
+            self.pending_len += 1;
+
+            if self.pending_len == self.pending.len() {
+                self.emit_pending();
+            }
+        }
+    }
+
+    /// Encode any remaining bytes with padding, and end the last line.
+    pub fn finish(mut self) {
+        if self.pending_len != 0 {
+            self.emit_pending();
+        }
+
+        if self.line_len != 0 {
+            (self.out)(b"\n");
+        }
+    }
+
+    fn emit_pending(&mut self) {
+        let [a, b, c] = self.pending;
+        let chunk = ((a as u32) << 16) | ((b as u32) << 8) | c as u32;
+
+        let mut encoded = [b'='; 4];
+        for (i, encoded_char) in encoded.iter_mut().enumerate().take(self.pending_len + 1) {
+            *encoded_char = ALPHABET[((chunk >> (18 - i * 6)) & 0x3F) as usize];
+        }
+
+        (self.out)(&encoded);
+        self.line_len += encoded.len();
+        if self.line_len >= LINE_WIDTH {
+            (self.out)(b"\n");
+            self.line_len = 0;
+        }
+
+        self.pending = [0; 3];
+        self.pending_len = 0;
+    }
+}
+
+/// The input of [`decode`] was not valid base64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidBase64 {
+    /// The offset of the first invalid character.
+    pub offset: usize,
+}
+
+/// Decode base64 `input`, passing each decoded byte to `out`.
+///
+/// Whitespace (including line breaks) is skipped, and decoding stops at the first `=`.
+pub fn decode(input: &[u8], mut out: impl FnMut(u8)) -> Result<(), InvalidBase64> {
+    let mut chunk = 0u32;
+    let mut chunk_bits = 0;
+
+    for (offset, &char) in input.iter().enumerate() {
+        let value = match char {
+            b'=' => break,
+            char if char.is_ascii_whitespace() => continue,
+            char => ALPHABET
+                .iter()
+                .position(|&alphabet_char| alphabet_char == char)
+                .ok_or(InvalidBase64 { offset })?,
+        };
+
+        chunk = (chunk << 6) | value as u32;
+        chunk_bits += 6;
+
+        if chunk_bits >= 8 {
+            chunk_bits -= 8;
+            out((chunk >> chunk_bits) as u8);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use std::vec::Vec;
+
+    fn encode(bytes: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let mut encoder = Base64Encoder::new(|encoded: &[u8]| output.extend_from_slice(encoded));
+
+        // Split the input to make sure chunks carry over between writes
+        let (first, second) = bytes.split_at(bytes.len() / 2);
+        encoder.write(first);
+        encoder.write(second);
+        encoder.finish();
+
+        output
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(encode(b""), b"");
+        assert_eq!(encode(b"M"), b"TQ==\n");
+        assert_eq!(encode(b"Ma"), b"TWE=\n");
+        assert_eq!(encode(b"Man"), b"TWFu\n");
+        assert_eq!(encode(b"hello world"), b"aGVsbG8gd29ybGQ=\n");
+    }
+
+    #[test]
+    fn test_base64_decode() {
+        let mut decoded = Vec::new();
+        decode(&encode(b"hello world"), |byte| decoded.push(byte)).unwrap();
+        assert_eq!(decoded, b"hello world");
+
+        let mut decoded = Vec::new();
+        decode(&encode(&[0xAB; 100]), |byte| decoded.push(byte)).unwrap();
+        assert_eq!(decoded, [0xAB; 100]);
+
+        assert_eq!(decode(b"TQ*=", |_| ()), Err(InvalidBase64 { offset: 2 }));
+    }
+
+    #[test]
+    fn test_base64_line_wrap() {
+        let output = encode(&[0; 60]);
+        let lines: Vec<&[u8]> = output.split(|&b| b == b'\n').collect();
+
+        assert_eq!(lines[0].len(), LINE_WIDTH);
+        assert_eq!(lines[1].len(), 80 - LINE_WIDTH);
+        assert_eq!(lines[2].len(), 0);
+    }
+}
@@ -0,0 +1,205 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+/// The line written before the encoded dump.
+pub const BEGIN_MARKER: &str = "-----BEGIN CRASH DUMP-----";
+/// The line written after the encoded dump.
+pub const END_MARKER: &str = "-----END CRASH DUMP-----";
+
+/// The magic at the start of every crash dump.
+pub const DUMP_MAGIC: [u8; 8] = *b"VERADUMP";
+/// The version of the dump layout, bumped on any change to it.
+pub const DUMP_VERSION: u32 = 1;
+
+/// The registers stored in a [`RecordKind::Registers`] record, in order.
+pub const REGISTER_NAMES: [&str; 8] = ["rsp", "rbp", "rflags", "cr0", "cr2", "cr3", "cr4", "efer"];
+
+/// What the data of a record contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RecordKind {
+    /// The panic message, as text.
+    Message = 1,
+    /// Each register of [`REGISTER_NAMES`], as little endian `u64`s.
+    Registers = 2,
+    /// The little endian `u64` address of the first byte, followed by stack memory.
+    Stack = 3,
+    /// The symbolized backtrace, as text.
+    Backtrace = 4,
+    /// The physical memory map, as text.
+    MemoryMap = 5,
+    /// The most recent log messages, as text.
+    Log = 6,
+}
+
+impl RecordKind {
+    pub const fn from_u8(kind: u8) -> Option<Self> {
+        Some(match kind {
+            1 => Self::Message,
+            2 => Self::Registers,
+            3 => Self::Stack,
+            4 => Self::Backtrace,
+            5 => Self::MemoryMap,
+            6 => Self::Log,
+            _ => return None,
+        })
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Message => "Message",
+            Self::Registers => "Registers",
+            Self::Stack => "Stack",
+            Self::Backtrace => "Backtrace",
+            Self::MemoryMap => "Memory Map",
+            Self::Log => "Log",
+        }
+    }
+}
+
+/// The bytes that start every dump.
+pub fn dump_header() -> [u8; 12] {
+    let mut header = [0; 12];
+    header[..8].copy_from_slice(&DUMP_MAGIC);
+    header[8..].copy_from_slice(&DUMP_VERSION.to_le_bytes());
+    header
+}
+
+/// The bytes that start a record of `kind` with `len` bytes of data.
+pub fn record_header(kind: RecordKind, len: u32) -> [u8; 5] {
+    let [a, b, c, d] = len.to_le_bytes();
+    [kind as u8, a, b, c, d]
+}
+
+/// Reasons a dump could not be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpError {
+    /// The dump did not start with [`DUMP_MAGIC`].
+    BadMagic,
+    /// The dump was made with a different layout.
+    UnsupportedVersion(u32),
+    /// A record claimed more data than the dump contained.
+    Truncated,
+}
+
+/// A single record of a dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// The raw kind, see [`Record::kind`].
+    pub raw_kind: u8,
+    pub data: &'a [u8],
+}
+
+impl Record<'_> {
+    /// The kind of this record, or `None` if it is from a newer kernel.
+    pub const fn kind(&self) -> Option<RecordKind> {
+        RecordKind::from_u8(self.raw_kind)
+    }
+}
+
+/// Iterator over the records of a dump, see [`parse`].
+pub struct Records<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>, DumpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (header, rest) = self.remaining.split_first_chunk::<5>()?;
+        let [raw_kind, a, b, c, d] = *header;
+        let len = u32::from_le_bytes([a, b, c, d]) as usize;
+
+        if rest.len() < len {
+            self.remaining = &[];
+            return Some(Err(DumpError::Truncated));
+        }
+
+        let (data, rest) = rest.split_at(len);
+        self.remaining = rest;
+
+        Some(Ok(Record { raw_kind, data }))
+    }
+}
+
+/// # Parse
+/// Check the dump header, and iterate over each of its records.
+///
+/// A dump is [`DUMP_MAGIC`] and [`DUMP_VERSION`] followed by records. Each record is a
+/// kind byte, a little endian `u32` length, and `length` bytes of data. When sent over
+/// serial, the dump is base64 encoded between [`BEGIN_MARKER`] and [`END_MARKER`].
+pub fn parse(dump: &[u8]) -> Result<Records<'_>, DumpError> {
+    let (header, remaining) = dump.split_first_chunk::<12>().ok_or(DumpError::BadMagic)?;
+
+    if header[..8] != DUMP_MAGIC {
+        return Err(DumpError::BadMagic);
+    }
+
+    let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    if version != DUMP_VERSION {
+        return Err(DumpError::UnsupportedVersion(version));
+    }
+
+    Ok(Records { remaining })
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_dump_round_trip() {
+        let mut dump = Vec::new();
+        dump.extend_from_slice(&dump_header());
+        dump.extend_from_slice(&record_header(RecordKind::Message, 5));
+        dump.extend_from_slice(b"oops!");
+        dump.extend_from_slice(&record_header(RecordKind::Log, 0));
+
+        let records: Vec<_> = parse(&dump).unwrap().collect();
+        assert_eq!(records.len(), 2);
+
+        let message = records[0].unwrap();
+        assert_eq!(message.kind(), Some(RecordKind::Message));
+        assert_eq!(message.data, b"oops!");
+        assert_eq!(records[1].unwrap().kind(), Some(RecordKind::Log));
+    }
+
+    #[test]
+    fn test_dump_errors() {
+        assert!(matches!(parse(b"NOTADUMP1234"), Err(DumpError::BadMagic)));
+
+        let mut dump = Vec::new();
+        dump.extend_from_slice(&dump_header());
+        dump.extend_from_slice(&record_header(RecordKind::Stack, 100));
+        dump.extend_from_slice(&[0; 10]);
+
+        let mut records = parse(&dump).unwrap();
+        assert_eq!(records.next(), Some(Err(DumpError::Truncated)));
+        assert_eq!(records.next(), None);
+    }
+}
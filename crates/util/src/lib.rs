@@ -25,8 +25,12 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 #![no_std]
 
+pub mod base64;
 pub mod bytes;
 pub mod consts;
+pub mod crashdump;
+pub mod crc32;
+pub mod utf8;
 
 /// Align `addr` to `alignment`
 ///
@@ -0,0 +1,124 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+/// A streaming UTF-8 decoder.
+///
+/// Bytes are given to [`Utf8Decoder::push`] one at a time, so a character split between
+/// two writes is still decoded. Invalid, overlong, and interrupted sequences decode to
+/// [`char::REPLACEMENT_CHARACTER`] instead of being dropped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Utf8Decoder {
+    code_point: u32,
+    remaining: u8,
+    min: u32,
+}
+
+impl Utf8Decoder {
+    pub const fn new() -> Self {
+        Self {
+            code_point: 0,
+            remaining: 0,
+            min: 0,
+        }
+    }
+
+    /// Decode another `byte`, passing each finished character to `out`.
+    ///
+    /// A byte that interrupts a sequence produces a replacement character, and is then
+    /// decoded on its own, so this can output up to two characters.
+    pub fn push(&mut self, byte: u8, mut out: impl FnMut(char)) {
+        if self.remaining != 0 {
+            if byte & 0xC0 == 0x80 {
+                self.code_point = (self.code_point << 6) | (byte & 0x3F) as u32;
+                self.remaining -= 1;
+
+                if self.remaining == 0 {
+                    let c = (self.code_point >= self.min)
+                        .then(|| char::from_u32(self.code_point))
+                        .flatten();
+                    out(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                return;
+            }
+
+            self.remaining = 0;
+            out(char::REPLACEMENT_CHARACTER);
+        }
+
+        match byte {
+            0x00..=0x7F => out(byte as char),
+            0xC2..=0xDF => self.start(byte & 0x1F, 1, 0x80),
+            0xE0..=0xEF => self.start(byte & 0x0F, 2, 0x800),
+            0xF0..=0xF4 => self.start(byte & 0x07, 3, 0x10000),
+            _ => out(char::REPLACEMENT_CHARACTER),
+        }
+    }
+
+    /// Is a character partly decoded?
+    pub const fn is_pending(&self) -> bool {
+        self.remaining != 0
+    }
+
+    fn start(&mut self, bits: u8, remaining: u8, min: u32) {
+        self.code_point = bits as u32;
+        self.remaining = remaining;
+        self.min = min;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    extern crate std;
+    use std::string::String;
+
+    fn decode(bytes: &[u8]) -> String {
+        let mut decoder = Utf8Decoder::new();
+        let mut decoded = String::new();
+
+        for &byte in bytes {
+            decoder.push(byte, |c| decoded.push(c));
+        }
+
+        decoded
+    }
+
+    #[test]
+    fn test_decode_valid() {
+        let text = "±5 µs, 20°C 🦀";
+        assert_eq!(decode(text.as_bytes()), text);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        // A lone continuation byte, an overlong '/', and a sequence cut off by ascii
+        assert_eq!(decode(b"a\x80b"), "a\u{FFFD}b");
+        assert_eq!(decode(b"\xC0\xAF"), "\u{FFFD}\u{FFFD}");
+        assert_eq!(decode(b"\xE2\x82x"), "\u{FFFD}x");
+        // An encoded surrogate
+        assert_eq!(decode(b"\xED\xA0\x80"), "\u{FFFD}");
+    }
+}
@@ -0,0 +1,467 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Just enough ACPI to notice the power and sleep buttons, and to turn the machine off.
+//!
+//! The FADT tells us where the PM1 registers are and which interrupt the SCI is on. The
+//! buttons raise fixed events in PM1, which are [`published`](events::publish) on the
+//! event bus. Pressing the power button starts a clean shutdown: every process is asked
+//! to exit, which gives the fs server the chance to sync, and once they have (or after a
+//! few seconds) the machine is put into S5.
+//!
+//! There is no AML interpreter, the S5 sleep type is picked out of the DSDT's bytes.
+
+use crate::{
+    events::{self, SystemEvent},
+    int::attach_irq_handler,
+    locks::ScheduleLock,
+    process::scheduler::Scheduler,
+    qemu,
+    resources::{self, ClaimError, Resource, Sharing},
+    timer::{self, NS_PER_TICK},
+    vmm,
+};
+use arch::{idt64::InterruptInfo, io::IOPort, pic8259::pic_unmask_irq};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use lignan::{logln, warnln};
+use mem::{addr::PhysAddr, paging::CacheMode};
+use vera_portal::WaitSignal;
+
+/// Where the BIOS keeps the segment of the extended BIOS data area
+const EBDA_SEGMENT_PTR: usize = 0x40E;
+/// The BIOS's read only memory, where the RSDP is when it is not in the EBDA
+const BIOS_ROM: (usize, usize) = (0xE0000, 0x20000);
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+/// The size of the header every system description table starts with
+const TABLE_HEADER_LEN: usize = 36;
+
+/// Offsets into the FADT
+const FADT_DSDT: usize = 40;
+const FADT_SCI_INT: usize = 46;
+const FADT_SMI_CMD: usize = 48;
+const FADT_ACPI_ENABLE: usize = 52;
+const FADT_PM1A_EVT_BLK: usize = 56;
+const FADT_PM1B_EVT_BLK: usize = 60;
+const FADT_PM1A_CNT_BLK: usize = 64;
+const FADT_PM1B_CNT_BLK: usize = 68;
+const FADT_PM1_EVT_LEN: usize = 88;
+const FADT_PM1_CNT_LEN: usize = 89;
+const FADT_FLAGS: usize = 112;
+const FADT_X_DSDT: usize = 140;
+
+/// Set in the FADT's flags when a button is a control method device instead of a fixed
+/// event, which we can't handle without AML
+const FLAG_PWR_BUTTON: u32 = 1 << 4;
+const FLAG_SLP_BUTTON: u32 = 1 << 5;
+
+/// Bits in the PM1 status and enable registers
+const PM1_PWRBTN: u16 = 1 << 8;
+const PM1_SLPBTN: u16 = 1 << 9;
+/// Bits in the PM1 control register
+const PM1_SCI_EN: u16 = 1 << 0;
+const PM1_SLP_TYP_SHIFT: u16 = 10;
+const PM1_SLP_EN: u16 = 1 << 13;
+
+/// How many times to check if the firmware switched to ACPI mode before giving up
+const ENABLE_POLLS: usize = 1_000_000;
+/// How long processes get to exit before the machine is turned off anyway
+const SHUTDOWN_GRACE_NS: u64 = 5_000_000_000;
+
+static PM1: ScheduleLock<Option<Pm1>> = ScheduleLock::new(None);
+/// Set once the power button started a shutdown
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+/// The tick the machine is turned off at, even if processes are still running
+static SHUTDOWN_DEADLINE: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// The PM1 register blocks, there can be two of each that must be used together
+#[derive(Clone, Copy)]
+struct Pm1 {
+    status: [Option<IOPort>; 2],
+    enable: [Option<IOPort>; 2],
+    control: [Option<IOPort>; 2],
+    /// The values of `SLP_TYPa` and `SLP_TYPb` for S5
+    s5: Option<(u8, u8)>,
+}
+
+impl Pm1 {
+    fn read_status(&self) -> u16 {
+        self.status
+            .iter()
+            .flatten()
+            .fold(0, |status, port| status | unsafe { port.read_word() })
+    }
+
+    /// Clear the status bits in `bits`, they are cleared by writing a one to them
+    fn clear_status(&self, bits: u16) {
+        for port in self.status.iter().flatten() {
+            unsafe { port.write_word(bits) };
+        }
+    }
+
+    fn enable(&self, bits: u16) {
+        for port in self.enable.iter().flatten() {
+            unsafe { port.write_word(port.read_word() | bits) };
+        }
+    }
+}
+
+/// Why ACPI could not be set up
+#[derive(Debug)]
+enum AcpiError {
+    NoRsdp,
+    NoFadt,
+    /// A table could not be mapped, or its checksum is wrong
+    BadTable(PhysAddr),
+    Claim(ClaimError),
+    /// Firmware never handed the hardware over to us
+    EnableTimeout,
+}
+
+/// Find the FADT, take over the PM1 registers and start listening for the buttons
+pub fn init() {
+    match init_pm1() {
+        Ok(()) => events::subscribe(on_event),
+        Err(err) => {
+            warnln!("ACPI: Unable to use the power button ({err:?})");
+            resources::release("acpi");
+        }
+    }
+}
+
+fn init_pm1() -> Result<(), AcpiError> {
+    let rsdp = find_rsdp().ok_or(AcpiError::NoRsdp)?;
+    let fadt = find_table(rsdp, b"FACP")?.ok_or(AcpiError::NoFadt)?;
+
+    let dsdt = match read_u64(fadt, FADT_X_DSDT) {
+        0 => read_u32(fadt, FADT_DSDT) as u64,
+        x_dsdt => x_dsdt,
+    };
+    let s5 = map_table(PhysAddr::new(dsdt as usize))
+        .ok()
+        .and_then(find_s5);
+    if s5.is_none() {
+        warnln!("ACPI: No _S5 in the DSDT, the machine can't be turned off");
+    }
+
+    let evt_len = fadt[FADT_PM1_EVT_LEN] as u16;
+    let cnt_len = fadt[FADT_PM1_CNT_LEN] as u16;
+    let block = |offset: usize| match read_u32(fadt, offset) {
+        0 => None,
+        port => Some(port as u16),
+    };
+    let (evt_a, evt_b) = (block(FADT_PM1A_EVT_BLK), block(FADT_PM1B_EVT_BLK));
+    let (cnt_a, cnt_b) = (block(FADT_PM1A_CNT_BLK), block(FADT_PM1B_CNT_BLK));
+    let sci = read_u16(fadt, FADT_SCI_INT) as u8;
+
+    let mut claims = alloc::vec![(Resource::Irq(sci), Sharing::Shared)];
+    for (base, len) in [
+        (evt_a, evt_len),
+        (evt_b, evt_len),
+        (cnt_a, cnt_len),
+        (cnt_b, cnt_len),
+    ] {
+        if let Some(base) = base {
+            claims.push((Resource::IoPorts { base, len }, Sharing::Exclusive));
+        }
+    }
+    resources::claim("acpi", &claims).map_err(AcpiError::Claim)?;
+
+    // The enable registers are the second half of each event block
+    let pm1 = Pm1 {
+        status: [evt_a.map(IOPort::new), evt_b.map(IOPort::new)],
+        enable: [
+            evt_a.map(|base| IOPort::new(base + evt_len / 2)),
+            evt_b.map(|base| IOPort::new(base + evt_len / 2)),
+        ],
+        control: [cnt_a.map(IOPort::new), cnt_b.map(IOPort::new)],
+        s5,
+    };
+    enable_acpi(fadt, &pm1)?;
+
+    let flags = read_u32(fadt, FADT_FLAGS);
+    let mut buttons = 0;
+    if flags & FLAG_PWR_BUTTON == 0 {
+        buttons |= PM1_PWRBTN;
+    }
+    if flags & FLAG_SLP_BUTTON == 0 {
+        buttons |= PM1_SLPBTN;
+    }
+
+    pm1.clear_status(buttons);
+    pm1.enable(buttons);
+    *PM1.lock() = Some(pm1);
+
+    attach_irq_handler(sci_interrupt_handler, sci);
+    unsafe {
+        if sci >= 8 {
+            // The second PIC is chained through the first PIC's IRQ 2
+            pic_unmask_irq(2);
+        }
+        pic_unmask_irq(sci);
+    }
+
+    logln!(
+        "ACPI: Listening for the{}{} button on IRQ {sci}",
+        if buttons & PM1_PWRBTN != 0 {
+            " power"
+        } else {
+            ""
+        },
+        if buttons & PM1_SLPBTN != 0 {
+            " sleep"
+        } else {
+            ""
+        },
+    );
+    Ok(())
+}
+
+/// Switch the firmware into ACPI mode, so the buttons raise the SCI instead of an SMI
+fn enable_acpi(fadt: &[u8], pm1: &Pm1) -> Result<(), AcpiError> {
+    let Some(control) = pm1.control[0] else {
+        return Ok(());
+    };
+    let smi_cmd = read_u32(fadt, FADT_SMI_CMD) as u16;
+    let acpi_enable = fadt[FADT_ACPI_ENABLE];
+
+    // Some firmware starts in ACPI mode, and then has no way to switch to it
+    if unsafe { control.read_word() } & PM1_SCI_EN != 0 || smi_cmd == 0 || acpi_enable == 0 {
+        return Ok(());
+    }
+
+    // The timer isn't running yet, so this counts port reads, which take about a
+    // microsecond each
+    unsafe { IOPort::new(smi_cmd).write_byte(acpi_enable) };
+    (0..ENABLE_POLLS)
+        .find(|_| unsafe { control.read_word() } & PM1_SCI_EN != 0)
+        .map(|_| ())
+        .ok_or(AcpiError::EnableTimeout)
+}
+
+fn sci_interrupt_handler(_args: &InterruptInfo) {
+    // Nothing holds this for long, and never once the SCI is attached
+    let Some(pm1) = PM1.try_lock().and_then(|pm1| *pm1) else {
+        return;
+    };
+
+    let status = pm1.read_status();
+    pm1.clear_status(status & (PM1_PWRBTN | PM1_SLPBTN));
+
+    if status & PM1_PWRBTN != 0 {
+        events::publish(SystemEvent::PowerButton);
+    }
+    if status & PM1_SLPBTN != 0 {
+        events::publish(SystemEvent::SleepButton);
+    }
+}
+
+fn on_event(event: &SystemEvent) {
+    match event {
+        SystemEvent::PowerButton => shutdown(),
+        // FIXME: There is no way to put the machine to sleep yet
+        SystemEvent::SleepButton => logln!("ACPI: Sleep button pressed, sleep isn't supported"),
+        _ => (),
+    }
+}
+
+/// Ask every process to exit, the machine is turned off once they have, or once they
+/// had a few seconds to
+fn shutdown() {
+    if SHUTTING_DOWN.swap(true, Ordering::AcqRel) {
+        return;
+    }
+
+    logln!("ACPI: Power button pressed, shutting down");
+    SHUTDOWN_DEADLINE.store(
+        timer::kernel_ticks() + SHUTDOWN_GRACE_NS / NS_PER_TICK,
+        Ordering::Release,
+    );
+
+    for process in Scheduler::get().processes() {
+        process.push_signal(WaitSignal::TerminationRequest);
+    }
+}
+
+/// If the power button started a shutdown
+pub fn shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::Acquire)
+}
+
+/// Turn the machine off if processes took too long to exit after the power button.
+///
+/// This is called from the scheduler's tick.
+pub fn tick(now: u64) {
+    if now >= SHUTDOWN_DEADLINE.load(Ordering::Acquire) {
+        warnln!("ACPI: Processes did not exit in time, turning off anyway");
+        power_off();
+    }
+}
+
+/// Put the machine into S5, falling back to closing QEMU if that didn't work
+pub fn power_off() -> ! {
+    logln!("ACPI: Turning off");
+
+    let pm1 = PM1.try_lock().and_then(|pm1| *pm1);
+    if let Some((pm1, (slp_typ_a, slp_typ_b))) = pm1.and_then(|pm1| Some((pm1, pm1.s5?))) {
+        for (port, slp_typ) in pm1.control.iter().zip([slp_typ_a, slp_typ_b]) {
+            let Some(port) = port else {
+                continue;
+            };
+
+            unsafe {
+                let control = port.read_word() & !(0b111 << PM1_SLP_TYP_SHIFT);
+                port.write_word(control | (slp_typ as u16) << PM1_SLP_TYP_SHIFT | PM1_SLP_EN);
+            }
+        }
+    }
+
+    qemu::exit_emulator(qemu::QemuExitStatus::Success)
+}
+
+/// Look for the RSDP in the first KiB of the EBDA, then in the BIOS's ROM
+fn find_rsdp() -> Option<&'static [u8]> {
+    let ebda = map_physical(PhysAddr::new(EBDA_SEGMENT_PTR), 2)
+        .map(|segment| (read_u16(segment, 0) as usize) << 4)
+        .filter(|&ebda| ebda != 0);
+
+    ebda.map(|ebda| (ebda, 1024))
+        .into_iter()
+        .chain([BIOS_ROM])
+        .filter_map(|(start, len)| map_physical(PhysAddr::new(start), len))
+        .find_map(|area| {
+            // The RSDP is always on a 16 byte boundary
+            (0..area.len().saturating_sub(20))
+                .step_by(16)
+                .map(|offset| &area[offset..])
+                .find(|rsdp| rsdp.starts_with(RSDP_SIGNATURE) && checksum(&rsdp[..20]))
+        })
+}
+
+/// Find the table with `signature` through the XSDT, or the RSDT on ACPI 1.0
+fn find_table(rsdp: &[u8], signature: &[u8; 4]) -> Result<Option<&'static [u8]>, AcpiError> {
+    let revision = rsdp[15];
+    let xsdt = if revision >= 2 && rsdp.len() >= 32 {
+        read_u64(rsdp, 24)
+    } else {
+        0
+    };
+
+    let (root, entry_len) = match xsdt {
+        0 => (read_u32(rsdp, 16) as u64, 4),
+        xsdt => (xsdt, 8),
+    };
+    let root = map_table(PhysAddr::new(root as usize))?;
+
+    for entry in root[TABLE_HEADER_LEN..].chunks_exact(entry_len) {
+        let addr = match entry_len {
+            4 => read_u32(entry, 0) as u64,
+            _ => read_u64(entry, 0),
+        };
+
+        let table = map_table(PhysAddr::new(addr as usize))?;
+        if &table[..4] == signature {
+            return Ok(Some(table));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Map the system description table at `phys`, and check it is intact
+fn map_table(phys: PhysAddr) -> Result<&'static [u8], AcpiError> {
+    let header = map_physical(phys, TABLE_HEADER_LEN).ok_or(AcpiError::BadTable(phys))?;
+    let len = read_u32(header, 4) as usize;
+    if len < TABLE_HEADER_LEN {
+        return Err(AcpiError::BadTable(phys));
+    }
+
+    map_physical(phys, len)
+        .filter(|table| checksum(table))
+        .ok_or(AcpiError::BadTable(phys))
+}
+
+/// Map `len` bytes of firmware memory at `phys` into the kernel
+///
+/// FIXME: The kernel can't unmap memory, so every table mapped stays mapped.
+fn map_physical(phys: PhysAddr, len: usize) -> Option<&'static [u8]> {
+    let virt = vmm::map_mmio(phys, len, CacheMode::WriteBack).ok()?;
+    Some(unsafe { core::slice::from_raw_parts(virt.as_ptr(), len) })
+}
+
+/// Get the sleep types of S5 from the `_S5_` package in the DSDT.
+///
+/// This looks for `Name (_S5, Package () { SLP_TYPa, SLP_TYPb, ... })` in the AML, which is
+/// how every firmware we've seen writes it.
+fn find_s5(dsdt: &[u8]) -> Option<(u8, u8)> {
+    const NAME_OP: u8 = 0x08;
+    const PACKAGE_OP: u8 = 0x12;
+    const BYTE_PREFIX: u8 = 0x0A;
+
+    // `_S5_` can also show up where it is used, only its definition is preceded by a
+    // NameOp, sometimes with a root prefix in between
+    let name = (1..dsdt.len().saturating_sub(4)).find(|&name| {
+        &dsdt[name..name + 4] == b"_S5_"
+            && (dsdt[name - 1] == NAME_OP
+                || (name >= 2 && dsdt[name - 1] == b'\\' && dsdt[name - 2] == NAME_OP))
+    })?;
+
+    let package = dsdt.get(name + 4..)?;
+    if *package.first()? != PACKAGE_OP {
+        return None;
+    }
+
+    // Skip the PkgLength, its top two bits are how many more bytes it has, and the
+    // number of elements
+    let length_bytes = (*package.get(1)? >> 6) as usize + 1;
+    let mut elements = package.get(1 + length_bytes + 1..)?.iter().copied();
+    let mut element = || match elements.next()? {
+        BYTE_PREFIX => elements.next(),
+        constant => Some(constant),
+    };
+
+    Some((element()?, element()?))
+}
+
+fn checksum(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    bytes
+        .get(offset..offset + 2)
+        .map_or(0, |bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    bytes
+        .get(offset..offset + 4)
+        .map_or(0, |bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    bytes
+        .get(offset..offset + 8)
+        .map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+}
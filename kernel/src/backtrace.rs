@@ -0,0 +1,80 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::symbols::SymbolizedAddr;
+use core::fmt::{Debug, Display};
+
+/// The max number of frames recorded in a [`Backtrace`]
+pub const BACKTRACE_DEPTH: usize = 16;
+
+/// The return addresses of the kernel's current call stack
+///
+/// This walks the frame pointer chain, which is only kept by kernels built with one of the
+/// debugging features (see `meta`). Otherwise the walk stops at the first frame that falls
+/// outside of the running stack.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Backtrace {
+    frames: [u64; BACKTRACE_DEPTH],
+}
+
+impl Backtrace {
+    /// Record the call stack of the caller
+    #[inline(always)]
+    pub fn capture() -> Self {
+        let mut frames = [0; BACKTRACE_DEPTH];
+        arch::stack_walk::capture_frames(&mut frames);
+
+        Self { frames }
+    }
+
+    /// The return addresses, innermost first
+    pub fn frames(&self) -> impl Iterator<Item = u64> + '_ {
+        self.frames.iter().copied().take_while(|&addr| addr != 0)
+    }
+}
+
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, addr) in self.frames().enumerate() {
+            writeln!(f, "  #{i:<2} {}", SymbolizedAddr(addr))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for Backtrace {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        struct Frame(u64);
+
+        impl Debug for Frame {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "{:#018x}", self.0)
+            }
+        }
+
+        f.debug_list().entries(self.frames().map(Frame)).finish()
+    }
+}
@@ -0,0 +1,306 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! The kernel's serial console.
+//!
+//! Until [`init`] the log is written to the port one byte at a time, waiting on the port
+//! for each. After it, bytes are queued in a ring and the port's transmitter empty interrupt
+//! refills its FIFO from the ring, so logging only waits on the port once the ring is full.
+//!
+//! The port and its settings can be chosen on the command line with `serial.port=com2`,
+//! `serial.baud=57600`, and `serial.mode=8N1`.
+
+use crate::{
+    int::attach_irq_handler,
+    resources::{self, Resource, Sharing},
+};
+use alloc::format;
+use arch::{idt64::InterruptInfo, io::IOPort, pic8259::pic_unmask_irq};
+use bootloader::cmdline::KernelCmdline;
+use core::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use lignan::{lock::DebugMutex, logln, warnln};
+use serial::{ComPort, Serial, baud::SerialBaud, config::SerialConfig, tx_ring::TxRing};
+
+/// How many IO ports a UART's registers take up
+const REGISTERS_LEN: u16 = 8;
+/// How many bytes can be waiting to be sent
+const TX_RING_LEN: usize = 4096;
+
+static PORT: DebugMutex<Option<SerialTx>> = DebugMutex::new(None);
+/// Set when the port interrupted while `PORT` was locked, for whoever holds it to handle
+static IRQ_PENDING: AtomicBool = AtomicBool::new(false);
+/// The first register of the port in `PORT`, or 0 if there is none
+///
+/// This lets the panic handler write to the port even if `PORT` is stuck locked.
+static RAW_PORT: AtomicU16 = AtomicU16::new(0);
+
+struct SerialTx {
+    serial: Serial,
+    /// The IRQ the port interrupts on, once interrupts are being used
+    irq: Option<u8>,
+    ring: TxRing<TX_RING_LEN>,
+}
+
+impl SerialTx {
+    fn new(serial: Serial) -> Self {
+        RAW_PORT.store(serial.io_port().port(), Ordering::Release);
+
+        Self {
+            serial,
+            irq: None,
+            ring: TxRing::new(),
+        }
+    }
+
+    /// Queue `bytes` to be sent, or send them right away before interrupts are used
+    fn write(&mut self, bytes: &[u8]) {
+        if self.irq.is_none() {
+            bytes
+                .iter()
+                .for_each(|&byte| self.serial.transmit_byte(byte));
+            return;
+        }
+
+        for &byte in bytes {
+            while !self.ring.push(byte) {
+                self.refill();
+            }
+        }
+
+        self.refill();
+    }
+
+    /// Move bytes from the ring into the port's FIFO, if it has room
+    fn refill(&mut self) {
+        let ring = &mut self.ring;
+        self.serial.fill_fifo(|| ring.pop());
+    }
+
+    /// Send everything in the ring, waiting on the port
+    fn flush(&mut self) {
+        while let Some(byte) = self.ring.pop() {
+            self.serial.transmit_byte(byte);
+        }
+    }
+}
+
+/// The log's handle to the serial port, see [`SerialLog::probe`]
+pub struct SerialLog;
+
+impl SerialLog {
+    /// Find the first serial port at 115200 baud, and make it the log's port
+    pub fn probe() -> Option<Self> {
+        let serial = Serial::probe_first(SerialBaud::Baud115200)?;
+        *PORT.try_lock()? = Some(SerialTx::new(serial));
+
+        Some(Self)
+    }
+}
+
+impl core::fmt::Write for SerialLog {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Apply the command line's port settings, and start sending from the port's interrupt
+///
+/// This must happen after interrupts have been attached.
+pub fn init(cmdline: &KernelCmdline) {
+    let baud = cmdline
+        .value_of("serial.baud")
+        .map(|baud| baud.parse().ok().and_then(SerialBaud::from_rate));
+    let mut config = SerialConfig::new(baud.flatten().unwrap_or(SerialBaud::Baud115200));
+    if let Some(mode) = cmdline.value_of("serial.mode") {
+        match config.with_mode(mode) {
+            Some(with_mode) => config = with_mode,
+            None => warnln!("Serial mode {mode:?} is not like `8N1`, ignoring it"),
+        }
+    }
+    if baud.is_some_and(|baud| baud.is_none()) {
+        warnln!("Unsupported serial baud rate, using {}", config.baud.rate());
+    }
+
+    let com = match cmdline.value_of("serial.port") {
+        Some(name) => match ComPort::from_name(name) {
+            Some(com) => Some(com),
+            None => {
+                warnln!("There is no serial port {name:?}, try `com1` to `com4`");
+                None
+            }
+        },
+        None => None,
+    };
+
+    let Some(mut port) = PORT.try_lock() else {
+        return;
+    };
+
+    // The port is reset by either of these, so anything waiting must go out first
+    if let Some(tx) = port.as_mut() {
+        tx.flush();
+    }
+
+    match com {
+        Some(com) => match Serial::open(com, config) {
+            Some(serial) => *port = Some(SerialTx::new(serial)),
+            None => {
+                drop(port);
+                warnln!("Unable to open serial port {}", com.name());
+                return;
+            }
+        },
+        None => {
+            let Some(tx) = port.as_mut() else {
+                return;
+            };
+
+            if tx.serial.get_config() != config && !tx.serial.reconfigure(config) {
+                drop(port);
+                warnln!("Unable to reconfigure the serial port");
+                return;
+            }
+        }
+    }
+
+    let Some(tx) = port.as_mut() else {
+        return;
+    };
+    // Ports past COM4 have no standard IRQ, so they are left waiting on the port
+    let Some(com) = tx.serial.com_port() else {
+        return;
+    };
+
+    // The console server reads input from the port, while the kernel writes the log to it
+    let claims = [
+        (
+            Resource::IoPorts {
+                base: com.io_port().port(),
+                len: REGISTERS_LEN,
+            },
+            Sharing::Shared,
+        ),
+        (Resource::Irq(com.irq()), Sharing::Exclusive),
+    ];
+    if let Err(err) = resources::claim(&format!("serial {}", com.name()), &claims) {
+        drop(port);
+        warnln!("Serial on {} will not use interrupts, {err}", com.name());
+        return;
+    }
+
+    tx.irq = Some(com.irq());
+    attach_irq_handler(serial_interrupt_handler, com.irq());
+    unsafe { pic_unmask_irq(com.irq()) };
+    tx.serial.set_transmit_interrupt(true);
+
+    let uart = tx.serial.get_uart();
+    let config = tx.serial.get_config();
+    drop(port);
+
+    logln!(
+        "Serial on {} at {} baud ({:?}, {} byte FIFO)",
+        com.name(),
+        config.baud.rate(),
+        uart,
+        uart.fifo_len()
+    );
+}
+
+/// Run `f` on the log's serial port, then handle any interrupt that came in while it was
+/// locked
+///
+/// Returns `None` if there is no serial port, or it is locked.
+fn with_port<R>(f: impl FnOnce(&mut SerialTx) -> R) -> Option<R> {
+    let mut port = PORT.try_lock()?;
+    let result = f(port.as_mut()?);
+    drop(port);
+
+    handle_pending_irq();
+    Some(result)
+}
+
+/// Acknowledge the port's interrupt and refill its FIFO, if it interrupted while `PORT` was
+/// locked
+///
+/// An unacknowledged interrupt is never raised again, so it can't be dropped.
+fn handle_pending_irq() {
+    while IRQ_PENDING.swap(false, Ordering::AcqRel) {
+        let Some(mut port) = PORT.try_lock() else {
+            // Whoever holds the port now will see the flag again once they let go of it
+            IRQ_PENDING.store(true, Ordering::Release);
+            return;
+        };
+
+        if let Some(tx) = port.as_mut() {
+            tx.serial.acknowledge_interrupt();
+            tx.refill();
+        }
+
+        // The flag is checked again after unlocking, in case the port interrupted while it
+        // was held here
+    }
+}
+
+/// Send `bytes` on the log's serial port
+///
+/// Returns `false` if there is no serial port, or it is locked.
+pub fn write_bytes(bytes: &[u8]) -> bool {
+    with_port(|tx| tx.write(bytes)).is_some()
+}
+
+/// Send `bytes` on the log's serial port, writing straight to the port if it is locked
+///
+/// This is for output that must not be lost, like crash dumps, where whoever holds the
+/// port may never let go of it. Bytes written around the lock can end up in the middle of
+/// the holder's output. Returns `false` if there is no serial port.
+pub fn force_write_bytes(bytes: &[u8]) -> bool {
+    if write_bytes(bytes) {
+        return true;
+    }
+
+    let port = RAW_PORT.load(Ordering::Acquire);
+    if port == 0 {
+        return false;
+    }
+
+    bytes
+        .iter()
+        .for_each(|&byte| unsafe { Serial::transmit_byte_on(IOPort::new(port), byte) });
+    true
+}
+
+/// Send everything waiting in the ring, without waiting for interrupts
+///
+/// This is for when interrupts are off for good, like in the panic handler.
+pub fn flush() {
+    with_port(|tx| tx.flush());
+}
+
+fn serial_interrupt_handler(_args: &InterruptInfo) {
+    // If the port is locked, whoever holds it handles the interrupt before letting go
+    IRQ_PENDING.store(true, Ordering::Release);
+    handle_pending_irq();
+}
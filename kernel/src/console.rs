@@ -0,0 +1,298 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{locks::ScheduleLock, process::shared::SharedMemory};
+use bootgfx::{Framebuffer, glyph_cache::GlyphCache, terminal::Terminal};
+use bootloader::video::VideoInformation;
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+use lignan::{
+    lock::DebugMutex,
+    logln,
+    stream::{StreamConnection, add_stream_connection},
+    warnln,
+};
+use mem::addr::VirtAddr;
+use util::consts::PAGE_4K;
+
+/// The number of virtual consoles, switched between with Alt+F1..F4
+pub const VIRTUAL_CONSOLES: usize = 4;
+/// The console the kernel log is written to
+pub const KERNEL_LOG_CONSOLE: usize = 0;
+/// The console userspace debug messages are written to
+pub const USER_CONSOLE: usize = 1;
+
+/// Set when a switch was requested while the consoles were locked
+const NO_SWITCH: usize = usize::MAX;
+
+struct Consoles {
+    framebuffer: Option<Framebuffer>,
+    terminals: [Terminal; VIRTUAL_CONSOLES],
+    glyphs: GlyphCache,
+    active: usize,
+    /// Set while a process owns the framebuffer, the consoles keep their text but are not
+    /// drawn
+    suspended: bool,
+}
+
+// This is a `DebugMutex` so consoles can be written to from the log, interrupts, and
+// while panicking. Anything written while it is locked is dropped.
+static CONSOLES: DebugMutex<Consoles> = DebugMutex::new(Consoles {
+    framebuffer: None,
+    terminals: [const { Terminal::new(0, 0) }; VIRTUAL_CONSOLES],
+    glyphs: GlyphCache::new(),
+    active: KERNEL_LOG_CONSOLE,
+    suspended: false,
+});
+static PENDING_SWITCH: AtomicUsize = AtomicUsize::new(NO_SWITCH);
+/// The console that was last switched to, even if it isn't drawn yet
+static FOCUSED: AtomicUsize = AtomicUsize::new(KERNEL_LOG_CONSOLE);
+/// Set while a process owns the framebuffer, and none of the consoles are on screen
+static SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+impl Consoles {
+    /// Draw the active console, after handling any pending switch
+    fn render(&mut self) {
+        let pending = PENDING_SWITCH.swap(NO_SWITCH, Ordering::AcqRel);
+        if pending < VIRTUAL_CONSOLES && pending != self.active {
+            self.active = pending;
+            self.terminals[pending].invalidate();
+        }
+
+        if self.suspended {
+            return;
+        }
+
+        if let Some(framebuffer) = self.framebuffer.as_mut() {
+            let damaged = self.terminals[self.active].render(framebuffer, &mut self.glyphs);
+
+            // Framebuffers from virtio-gpu are only shown once flushed
+            #[cfg(feature = "virtio-gpu")]
+            if let Some(damaged) = damaged {
+                crate::virtio::gpu::try_flush(crate::virtio::gpu::Rect {
+                    x: damaged.x as u32,
+                    y: damaged.y as u32,
+                    width: damaged.width.get() as u32,
+                    height: damaged.height.get() as u32,
+                });
+            }
+            #[cfg(not(feature = "virtio-gpu"))]
+            let _ = damaged;
+        }
+    }
+}
+
+/// Normal memory the consoles' framebuffer keeps a copy of the screen in, and its length
+///
+/// Kernel mappings are never taken down, so it is kept for every later framebuffer that
+/// fits in it.
+static SHADOW: ScheduleLock<Option<(VirtAddr, usize)>> = ScheduleLock::new(None);
+
+/// Get a shadow for a framebuffer of `len` bytes, see [`Framebuffer::with_shadow`]
+fn shadow_for(len: usize) -> Option<VirtAddr> {
+    let mut shadow = SHADOW.lock();
+    if let Some((virt, shadow_len)) = *shadow {
+        if shadow_len >= len {
+            return Some(virt);
+        }
+    }
+
+    let memory = SharedMemory::create(len.div_ceil(PAGE_4K)).ok()?;
+    let virt = memory.map_into_kernel().ok()?;
+    // The pages are mapped into the kernel for good now, so they can't be freed
+    core::mem::forget(memory);
+
+    *shadow = Some((virt, len));
+    Some(virt)
+}
+
+/// Make a framebuffer for `video`
+///
+/// Scrolling reads back the whole screen, which is very slow from write-combining
+/// framebuffer memory. So the framebuffer is given a shadow in normal memory if there is
+/// enough memory for one.
+///
+/// # Safety
+/// The framebuffer must be mapped at `video.virt_addr`.
+unsafe fn framebuffer_for(video: &VideoInformation) -> Framebuffer {
+    let framebuffer = unsafe {
+        Framebuffer::new(
+            video.virt_addr as *mut u8,
+            video.width as usize,
+            video.height as usize,
+            video.pitch as usize,
+            video.format,
+        )
+    };
+
+    match shadow_for(framebuffer.pitch() * framebuffer.height()) {
+        Some(shadow) => unsafe { framebuffer.with_shadow(shadow.as_mut_ptr()) },
+        None => {
+            warnln!("No memory for a console shadow, scrolling will be slow");
+            framebuffer
+        }
+    }
+}
+
+/// Take over the framebuffer described by `video` for the virtual consoles
+///
+/// From now on the kernel log is also written to [`KERNEL_LOG_CONSOLE`].
+///
+/// # Safety
+/// The framebuffer must be mapped at `video.virt_addr`, and nothing else can draw into it.
+pub unsafe fn init(video: &VideoInformation) {
+    let framebuffer = unsafe { framebuffer_for(video) };
+
+    {
+        let Some(mut consoles) = CONSOLES.try_lock() else {
+            return;
+        };
+
+        consoles
+            .terminals
+            .iter_mut()
+            .for_each(|terminal| terminal.fit_to(&framebuffer));
+        consoles.framebuffer = Some(framebuffer);
+        consoles.render();
+    }
+
+    add_stream_connection(StreamConnection::new(|args| {
+        write(KERNEL_LOG_CONSOLE, args)
+    }));
+
+    let (columns, rows) = CONSOLES
+        .try_lock()
+        .map(|consoles| {
+            (
+                consoles.terminals[0].columns(),
+                consoles.terminals[0].rows(),
+            )
+        })
+        .unwrap_or_default();
+    logln!(
+        "{VIRTUAL_CONSOLES} virtual consoles of {columns}x{rows}, switch with Alt+F1..F{VIRTUAL_CONSOLES}"
+    );
+}
+
+/// Lock the consoles, waiting for the log if it is holding them
+#[cfg(feature = "gfx")]
+fn lock_consoles() -> lignan::lock::DebugMutexGuard<'static, Consoles> {
+    // The log can be holding the consoles, but it only does so briefly
+    loop {
+        if let Some(consoles) = CONSOLES.try_lock() {
+            break consoles;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Move the consoles onto the framebuffer described by `video`, after its mode changed
+///
+/// Each console keeps as much of its text as still fits.
+///
+/// # Safety
+/// The framebuffer must be mapped at `video.virt_addr`, and nothing else can draw into it.
+#[cfg(feature = "virtio-gpu")]
+pub unsafe fn resize(video: &VideoInformation) {
+    let framebuffer = unsafe { framebuffer_for(video) };
+
+    let mut consoles = lock_consoles();
+    consoles
+        .terminals
+        .iter_mut()
+        .for_each(|terminal| terminal.refit_to(&framebuffer));
+    consoles.framebuffer = Some(framebuffer);
+    consoles.render();
+}
+
+/// Stop drawing the consoles, while a process owns the framebuffer
+///
+/// Anything written to them is still kept, and shown once they are resumed.
+#[cfg(feature = "gfx")]
+pub fn suspend() {
+    lock_consoles().suspended = true;
+    SUSPENDED.store(true, Ordering::Release);
+}
+
+/// Draw the consoles again from scratch, after a process gave back the framebuffer
+#[cfg(feature = "gfx")]
+pub fn resume() {
+    let mut consoles = lock_consoles();
+    consoles.suspended = false;
+    SUSPENDED.store(false, Ordering::Release);
+
+    // The terminal only covers whole cells, the edges of the screen are cleared here
+    if let Some(framebuffer) = consoles.framebuffer.as_mut() {
+        if let Some(bounds) = framebuffer.bounds() {
+            framebuffer.fill_rect(bounds, bootgfx::Color::QUANTUM_BACKGROUND);
+        }
+    }
+    let active = consoles.active;
+    consoles.terminals[active].invalidate();
+    consoles.render();
+}
+
+/// Write to a console, drawing it if it is the active console
+pub fn write(console: usize, args: core::fmt::Arguments) {
+    let Some(mut consoles) = CONSOLES.try_lock() else {
+        return;
+    };
+
+    if consoles.framebuffer.is_none() {
+        return;
+    }
+
+    if let Some(terminal) = consoles.terminals.get_mut(console) {
+        let _ = terminal.write_fmt(args);
+    }
+
+    consoles.render();
+}
+
+/// Show `console` on the screen
+pub fn switch_to(console: usize) {
+    if console >= VIRTUAL_CONSOLES {
+        return;
+    }
+
+    PENDING_SWITCH.store(console, Ordering::Release);
+    FOCUSED.store(console, Ordering::Release);
+    if let Some(mut consoles) = CONSOLES.try_lock() {
+        consoles.render();
+    }
+}
+
+/// The console on screen, which keys are typed into
+///
+/// This is `None` while a process owns the framebuffer.
+pub fn focused() -> Option<usize> {
+    if SUSPENDED.load(Ordering::Acquire) {
+        return None;
+    }
+
+    Some(FOCUSED.load(Ordering::Acquire))
+}
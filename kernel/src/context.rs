@@ -36,6 +36,7 @@ pub unsafe extern "C" fn kernel_entry() {
             #  -- Save User's stack ptr, and restore our own
 
             cli
+            swapgs                         # Userspace owns `GS`, take back ours
             mov [{userspace_rsp_ptr}], rsp
             mov rsp, [{kernel_rsp_ptr}]
             push [{userspace_rsp_ptr}]
@@ -97,6 +98,8 @@ pub unsafe extern "C" fn kernel_entry() {
 
             #  -- Return back to Userspace
 
+            cli
+            swapgs                         # Hand `GS` back to userspace
             pop rsp
             sysretq
         ",
@@ -0,0 +1,233 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{backtrace::Backtrace, com};
+use arch::registers::{cr0, cr2, cr3, cr4, eflags, ia32_efer};
+use bootloader::{KernelBootHeader, MEMORY_REGIONS};
+use core::{
+    fmt::Write,
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use lignan::{
+    lock::DebugMutex,
+    stream::{StreamConnection, add_stream_connection},
+};
+use mem::phys::PhysMemoryMap;
+use util::{
+    base64::Base64Encoder,
+    crashdump::{self, RecordKind},
+};
+
+/// The number of recent log bytes kept for crash dumps
+const CRASH_LOG_LEN: usize = 16 * 1024;
+/// The number of bytes above the panic handler's `rsp` included in crash dumps
+const STACK_DUMP_LEN: usize = 512;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static CRASH_LOG: DebugMutex<CrashLog> = DebugMutex::new(CrashLog::new());
+static MEMORY_MAP: DebugMutex<PhysMemoryMap<MEMORY_REGIONS>> =
+    DebugMutex::new(PhysMemoryMap::new());
+
+/// Start recording what is needed for crash dumps, if `crashdump` is on the command line
+///
+/// The bootloader's memory map is copied, since its memory can be reused after boot.
+pub fn init(kbh: &KernelBootHeader) {
+    if !kbh.cmdline.has_flag("crashdump") {
+        return;
+    }
+
+    if let Some(mut memory_map) = MEMORY_MAP.try_lock() {
+        *memory_map = *kbh.phys_mem_map;
+    }
+
+    add_stream_connection(StreamConnection::new(|args| {
+        if let Some(mut crash_log) = CRASH_LOG.try_lock() {
+            let _ = crash_log.write_fmt(args);
+        }
+    }));
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Write a crash dump over serial
+///
+/// The dump is base64 encoded between `BEGIN CRASH DUMP` and `END CRASH DUMP` marker
+/// lines, and can be decoded with `meta decode-crash-dump <serial log>`. See
+/// `util::crashdump` for its layout.
+pub fn write_crash_dump(info: &PanicInfo) {
+    if !ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+
+    if !com::force_write_bytes(b"\n") {
+        return;
+    }
+
+    let transmit = |bytes: &[u8]| {
+        com::force_write_bytes(bytes);
+    };
+    transmit(crashdump::BEGIN_MARKER.as_bytes());
+    transmit(b"\n");
+
+    let mut dump = DumpWriter(Base64Encoder::new(transmit));
+    dump.0.write(&crashdump::dump_header());
+    dump.text_record(RecordKind::Message, format_args!("{info}"));
+    dump.record(RecordKind::Registers, &[&registers()]);
+
+    let rsp: u64;
+    unsafe { core::arch::asm!("mov {}, rsp", out(reg) rsp) };
+    let stack = unsafe { core::slice::from_raw_parts(rsp as *const u8, STACK_DUMP_LEN) };
+    dump.record(RecordKind::Stack, &[&rsp.to_le_bytes(), stack]);
+
+    dump.text_record(
+        RecordKind::Backtrace,
+        format_args!("{}", Backtrace::capture()),
+    );
+
+    if let Some(memory_map) = MEMORY_MAP.try_lock() {
+        dump.text_record(RecordKind::MemoryMap, format_args!("{}", *memory_map));
+    }
+
+    if let Some(crash_log) = CRASH_LOG.try_lock() {
+        let (first, second) = crash_log.slices();
+        dump.record(RecordKind::Log, &[first, second]);
+    }
+
+    dump.0.finish();
+    transmit(crashdump::END_MARKER.as_bytes());
+    transmit(b"\n");
+    com::flush();
+}
+
+/// The registers listed in `util::crashdump::REGISTER_NAMES`, as little endian bytes
+fn registers() -> [u8; 64] {
+    let (rsp, rbp): (u64, u64);
+    unsafe { core::arch::asm!("mov {}, rsp", "mov {}, rbp", out(reg) rsp, out(reg) rbp) };
+
+    let values = [
+        rsp,
+        rbp,
+        eflags::read() as u64,
+        cr0::read(),
+        cr2::read(),
+        cr3::read(),
+        cr4::read(),
+        ia32_efer::read(),
+    ];
+
+    let mut bytes = [0; 64];
+    for (chunk, value) in bytes.chunks_exact_mut(8).zip(values) {
+        chunk.copy_from_slice(&value.to_le_bytes());
+    }
+
+    bytes
+}
+
+struct DumpWriter<F: FnMut(&[u8])>(Base64Encoder<F>);
+
+impl<F: FnMut(&[u8])> DumpWriter<F> {
+    /// Write a record made up of each of `parts`
+    fn record(&mut self, kind: RecordKind, parts: &[&[u8]]) {
+        let len = parts.iter().map(|part| part.len()).sum::<usize>();
+        self.0.write(&crashdump::record_header(kind, len as u32));
+        parts.iter().for_each(|part| self.0.write(part));
+    }
+
+    /// Write a record of formatted text
+    ///
+    /// The text is formatted twice, once to find its length and once to write it.
+    fn text_record(&mut self, kind: RecordKind, args: core::fmt::Arguments) {
+        struct Counter(usize);
+
+        impl Write for Counter {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.0 += s.len();
+                Ok(())
+            }
+        }
+
+        let mut counter = Counter(0);
+        let _ = counter.write_fmt(args);
+
+        self.0
+            .write(&crashdump::record_header(kind, counter.0 as u32));
+        let _ = self.write_fmt(args);
+    }
+}
+
+impl<F: FnMut(&[u8])> Write for DumpWriter<F> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// A ring buffer of the most recent log messages
+///
+/// Once full, the oldest bytes are overwritten.
+struct CrashLog {
+    buffer: [u8; CRASH_LOG_LEN],
+    start: usize,
+    len: usize,
+}
+
+impl CrashLog {
+    const fn new() -> Self {
+        Self {
+            buffer: [0; CRASH_LOG_LEN],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// The buffered bytes, oldest first
+    fn slices(&self) -> (&[u8], &[u8]) {
+        let end = self.start + self.len;
+        if end <= CRASH_LOG_LEN {
+            (&self.buffer[self.start..end], &[])
+        } else {
+            (
+                &self.buffer[self.start..],
+                &self.buffer[..end - CRASH_LOG_LEN],
+            )
+        }
+    }
+}
+
+impl Write for CrashLog {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for &byte in s.as_bytes() {
+            if self.len == CRASH_LOG_LEN {
+                self.buffer[self.start] = byte;
+                self.start = (self.start + 1) % CRASH_LOG_LEN;
+            } else {
+                self.buffer[(self.start + self.len) % CRASH_LOG_LEN] = byte;
+                self.len += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
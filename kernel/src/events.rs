@@ -0,0 +1,190 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! A publish/subscribe bus for things that happen to the system as a whole.
+//!
+//! Drivers and the rest of the kernel [`publish`] events like a device being added, or
+//! memory running low, and anything interested [`subscribe`]s a callback instead of
+//! polling the hardware inventory. Publishing only queues the event, so it is safe from
+//! interrupt handlers. Events are delivered from the scheduler's tick, once no schedule
+//! locks are held, so callbacks must be quick and must not block.
+//!
+//! Processes can ask for events too with the `system_events` syscall, which forwards them
+//! as a [`WaitSignal::SystemEvent`].
+
+use crate::{
+    locks::ScheduleLock,
+    process::{RefProcess, WeakProcess},
+};
+use alloc::{collections::vec_deque::VecDeque, string::String, sync::Arc, vec::Vec};
+use arch::locks::InterruptMutex;
+use core::sync::atomic::{AtomicBool, Ordering};
+use lignan::{logln, warnln};
+use mem::pmm::use_pmm_ref;
+use util::consts::PAGE_4K;
+use vera_portal::{SystemEventKind, WaitSignal};
+
+/// The most events that can be waiting to be delivered, newer events are dropped after
+const MAX_PENDING: usize = 64;
+/// How often, in ticks, free memory is checked
+const MEMORY_CHECK_TICKS: u64 = 1000;
+/// Below this many free pages memory is low
+const LOW_MEMORY_PAGES: usize = (4 * 1024 * 1024) / PAGE_4K;
+
+/// Events waiting to be delivered
+static PENDING: InterruptMutex<VecDeque<SystemEvent>> = InterruptMutex::new(VecDeque::new());
+/// Set when `PENDING` has events, so the tick doesn't need to lock it to find out
+static EVENTS_PENDING: AtomicBool = AtomicBool::new(false);
+/// Set after `LowMemory` is published, until memory recovers
+static MEMORY_LOW: AtomicBool = AtomicBool::new(false);
+/// Kernel callbacks given every event
+static SUBSCRIBERS: ScheduleLock<Vec<Subscriber>> = ScheduleLock::new(Vec::new());
+/// Processes that asked for events with the `system_events` syscall
+static PROCESSES: ScheduleLock<Vec<WeakProcess>> = ScheduleLock::new(Vec::new());
+
+/// Something that happened to the system
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SystemEvent {
+    /// A driver claimed the hardware of a device, `owner` is the name it claimed it as
+    DeviceAdded { owner: String },
+    /// A driver gave up the hardware of a device it claimed as `owner`
+    DeviceRemoved { owner: String },
+    /// Free memory dropped below a few megabytes, this is sent once until it recovers
+    LowMemory { free_pages: usize },
+    /// The power button was pressed
+    PowerButton,
+    /// The sleep button was pressed
+    SleepButton,
+    /// A key was typed, after turning it into text with the keyboard layout
+    KeyTyped { ch: char },
+}
+
+impl SystemEvent {
+    /// This event as processes see it
+    fn kind(&self) -> SystemEventKind {
+        match *self {
+            SystemEvent::DeviceAdded { .. } => SystemEventKind::DeviceAdded,
+            SystemEvent::DeviceRemoved { .. } => SystemEventKind::DeviceRemoved,
+            SystemEvent::LowMemory { free_pages } => SystemEventKind::LowMemory { free_pages },
+            SystemEvent::PowerButton => SystemEventKind::PowerButton,
+            SystemEvent::SleepButton => SystemEventKind::SleepButton,
+            SystemEvent::KeyTyped { ch } => SystemEventKind::KeyTyped {
+                codepoint: ch as u32,
+            },
+        }
+    }
+}
+
+/// A callback that is given every published event
+pub type Subscriber = fn(&SystemEvent);
+
+/// Start forwarding events to processes that ask for them
+pub fn init() {
+    subscribe(forward_to_processes);
+}
+
+/// Call `subscriber` with every event published from now on
+pub fn subscribe(subscriber: Subscriber) {
+    SUBSCRIBERS.lock().push(subscriber);
+}
+
+/// Queue `event` to be given to every subscriber.
+///
+/// This can be called from anywhere, including interrupt handlers.
+pub fn publish(event: SystemEvent) {
+    let mut pending = PENDING.lock();
+    if pending.len() >= MAX_PENDING {
+        drop(pending);
+        warnln!("Dropping system event {event:?}, too many events are pending");
+        return;
+    }
+
+    pending.push_back(event);
+    EVENTS_PENDING.store(true, Ordering::Release);
+}
+
+/// Deliver pending events, and check if memory is running low.
+///
+/// This is called from the scheduler's tick, where no schedule locks are held.
+pub fn tick(now: u64) {
+    if now % MEMORY_CHECK_TICKS == 0 {
+        check_memory();
+    }
+
+    if !EVENTS_PENDING.swap(false, Ordering::Acquire) {
+        return;
+    }
+
+    loop {
+        // The queue can't be locked while calling subscribers, as they may publish too
+        let Some(event) = PENDING.lock().pop_front() else {
+            break;
+        };
+
+        for subscriber in SUBSCRIBERS.lock().iter() {
+            subscriber(&event);
+        }
+    }
+}
+
+/// Publish `LowMemory` when free memory drops too low, once until it recovers
+fn check_memory() {
+    let Ok(free_pages) = use_pmm_ref(|pmm| pmm.pages_free()) else {
+        return;
+    };
+
+    // Memory has to recover well past the limit before it can be low again, so hovering
+    // around it doesn't flood subscribers
+    if free_pages < LOW_MEMORY_PAGES {
+        if !MEMORY_LOW.swap(true, Ordering::Relaxed) {
+            publish(SystemEvent::LowMemory { free_pages });
+        }
+    } else if free_pages > LOW_MEMORY_PAGES * 2 {
+        MEMORY_LOW.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Start or stop sending system events to `process`
+pub fn set_process_subscribed(process: &RefProcess, subscribed: bool) {
+    let mut processes = PROCESSES.lock();
+    processes.retain(|other| other.upgrade().is_some_and(|other| other.id != process.id));
+
+    if subscribed {
+        processes.push(Arc::downgrade(process));
+        logln!("Process {} subscribed to system events", process.id);
+    }
+}
+
+fn forward_to_processes(event: &SystemEvent) {
+    for process in PROCESSES
+        .lock()
+        .iter()
+        .filter_map(|process| process.upgrade())
+    {
+        process.push_signal(WaitSignal::SystemEvent {
+            event: event.kind(),
+        });
+    }
+}
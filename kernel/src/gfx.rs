@@ -0,0 +1,453 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    com,
+    locks::ScheduleLock,
+    process::{Process, ProcessId, scheduler::Scheduler},
+};
+use alloc::vec::Vec;
+use bootgfx::image::Image;
+use bootloader::{
+    progress::{BootMode, BootScreen, BootStage},
+    video::VideoInformation,
+};
+use core::sync::atomic::{AtomicBool, Ordering};
+use lignan::{logln, warnln};
+use mem::{
+    addr::PhysAddr,
+    page::{PhysPage, VirtPage},
+    paging::CacheMode,
+};
+use util::base64::Base64Encoder;
+use vera_portal::{
+    FramebufferError, FramebufferInfo, ScreenshotError, VideoModeError, capabilities,
+};
+
+/// The names the splash image can have within the initfs, in the order they are tried
+const SPLASH_FILENAMES: [&str; 3] = ["splash.png", "splash.bmp", "splash.ppm"];
+
+/// The largest screenshot that will be taken, enough for a 4K screen
+pub const MAX_SCREENSHOT_LEN: usize = 3840 * 2160 * 3 + 32;
+
+/// The boot screen, if the bootloader gave us a framebuffer
+static BOOT_SCREEN: ScheduleLock<Option<BootScreen>> = ScheduleLock::new(None);
+/// The framebuffer the boot screen is drawing into
+static VIDEO: ScheduleLock<Option<VideoInformation>> = ScheduleLock::new(None);
+/// The pages of the framebuffer in `VIDEO`, once it is no longer the contiguous one the
+/// bootloader set up
+static VIDEO_PAGES: ScheduleLock<Option<Vec<PhysPage>>> = ScheduleLock::new(None);
+/// Set once a process has taken over the framebuffer, after which we stop drawing to it
+static FRAMEBUFFER_TAKEN: AtomicBool = AtomicBool::new(false);
+/// The process that took over the framebuffer
+static FRAMEBUFFER_OWNER: ScheduleLock<Option<ProcessId>> = ScheduleLock::new(None);
+/// Where the framebuffer is mapped into its owner
+static FRAMEBUFFER_MAPPING: ScheduleLock<Option<VirtPage>> = ScheduleLock::new(None);
+/// Set when the kernel's consoles are drawing into the framebuffer instead of the boot screen
+static CONSOLES_ACTIVE: AtomicBool = AtomicBool::new(false);
+/// The last stage shown, so the boot screen can be drawn again after a mode switch
+static LAST_STAGE: ScheduleLock<BootStage> = ScheduleLock::new(BootStage::KernelEntry);
+
+/// Start reporting boot progress onto the framebuffer described by `video`.
+///
+/// # Safety
+/// The framebuffer must be mapped at `video.virt_addr`.
+pub unsafe fn init(video: &VideoInformation, mode: BootMode) {
+    let boot_screen = unsafe { BootScreen::new(video.virt_addr as *mut u8, video, mode) };
+    *BOOT_SCREEN.lock() = Some(boot_screen);
+    *VIDEO.lock() = Some(*video);
+
+    report(BootStage::KernelEntry);
+}
+
+/// Remember that the kernel's consoles are drawing into the framebuffer described by
+/// `video`, so they can be redrawn when its mode changes.
+pub fn init_consoles(video: &VideoInformation) {
+    *VIDEO.lock() = Some(*video);
+    CONSOLES_ACTIVE.store(true, Ordering::Release);
+}
+
+/// Show that boot has reached `stage`.
+pub fn report(stage: BootStage) {
+    *LAST_STAGE.lock() = stage;
+    if FRAMEBUFFER_TAKEN.load(Ordering::Acquire) {
+        return;
+    }
+
+    if let Some(boot_screen) = BOOT_SCREEN.lock().as_mut() {
+        boot_screen.report(stage);
+    }
+    flush_screen();
+}
+
+/// Show what the kernel drew, framebuffers from virtio-gpu only show what was flushed
+fn flush_screen() {
+    #[cfg(feature = "virtio-gpu")]
+    crate::virtio::gpu::try_flush_all();
+}
+
+/// Draw the splash image from the initfs, if we are in splash mode.
+pub fn show_splash() {
+    let Some((splash_filename, splash_file)) = SPLASH_FILENAMES
+        .iter()
+        .find_map(|&filename| Some((filename, Scheduler::get().initfs_file(filename)?)))
+    else {
+        warnln!("No splash image found in the initfs");
+        return;
+    };
+
+    let mut boot_screen = BOOT_SCREEN.lock();
+    let Some(boot_screen) = boot_screen
+        .as_mut()
+        .filter(|boot_screen| boot_screen.mode() == BootMode::Splash)
+        .filter(|_| !FRAMEBUFFER_TAKEN.load(Ordering::Acquire))
+    else {
+        return;
+    };
+
+    match Image::parse(splash_file) {
+        Ok(image) => boot_screen.show_splash(&image),
+        Err(err) => warnln!("Unable to parse '{splash_filename}': {err:?}"),
+    }
+    flush_screen();
+}
+
+/// Copy what is currently on the screen into a PPM image.
+///
+/// The screen is only locked while its pixels are copied, so whatever the image is sent
+/// to afterwards does not stop anything else from drawing.
+pub fn snapshot_framebuffer() -> Result<Vec<u8>, ScreenshotError> {
+    let boot_screen = BOOT_SCREEN.lock();
+    let framebuffer = boot_screen
+        .as_ref()
+        .ok_or(ScreenshotError::NoFramebuffer)?
+        .framebuffer();
+
+    // The pixels, and room for the header
+    let len = framebuffer.width() * framebuffer.height() * 3 + 32;
+    if len > MAX_SCREENSHOT_LEN {
+        return Err(ScreenshotError::OutOfMemory);
+    }
+
+    let mut image = Vec::new();
+    image
+        .try_reserve_exact(len)
+        .map_err(|_| ScreenshotError::OutOfMemory)?;
+    framebuffer.snapshot_ppm(|bytes| image.extend_from_slice(bytes));
+
+    Ok(image)
+}
+
+/// Write what is currently on the screen over serial as a base64 encoded PPM image.
+///
+/// The image is written between `BEGIN FRAMEBUFFER PPM` and `END FRAMEBUFFER PPM` marker
+/// lines, so it can be cut out of a serial log and decoded with `base64 -d`. The screen is
+/// copied first, at 115200 baud sending it can take a few minutes for large framebuffers.
+pub fn dump_framebuffer() -> Result<(), ScreenshotError> {
+    let image = snapshot_framebuffer()?;
+    if !com::force_write_bytes(b"\n") {
+        return Err(ScreenshotError::NoSerialDevice);
+    }
+
+    logln!("Dumping framebuffer ({} bytes)...", image.len());
+
+    let transmit = |bytes: &[u8]| {
+        com::force_write_bytes(bytes);
+    };
+    transmit(b"\n-----BEGIN FRAMEBUFFER PPM-----\n");
+
+    let mut encoder = Base64Encoder::new(transmit);
+    encoder.write(&image);
+    encoder.finish();
+
+    transmit(b"-----END FRAMEBUFFER PPM-----\n");
+    Ok(())
+}
+
+/// Can `process` take the framebuffer?
+///
+/// `init` only gives [`capabilities::FRAMEBUFFER`] to the services that ask for it.
+fn may_take_framebuffer(process: &Process) -> bool {
+    process.has_capability(capabilities::FRAMEBUFFER)
+}
+
+/// Map the framebuffer into `process`, after which the kernel stops drawing into it.
+pub fn take_framebuffer(process: &Process) -> Result<FramebufferInfo, FramebufferError> {
+    let video = VIDEO.lock().ok_or(FramebufferError::NoFramebuffer)?;
+    if !may_take_framebuffer(process) {
+        return Err(FramebufferError::PermissionDenied);
+    }
+
+    if FRAMEBUFFER_TAKEN.swap(true, Ordering::AcqRel) {
+        return Err(FramebufferError::AlreadyTaken);
+    }
+
+    let info = map_framebuffer(process, &video).ok_or_else(|| {
+        FRAMEBUFFER_TAKEN.store(false, Ordering::Release);
+        FramebufferError::MappingMemoryError
+    })?;
+
+    *FRAMEBUFFER_OWNER.lock() = Some(process.id);
+    if CONSOLES_ACTIVE.load(Ordering::Acquire) {
+        crate::console::suspend();
+    }
+
+    logln!("Process {} took the framebuffer", process.id);
+    Ok(info)
+}
+
+/// Give the framebuffer back to the kernel if `process` owns it, unmapping it from
+/// `process` before drawing the consoles or boot screen again.
+pub fn release_framebuffer(process: &Process) -> Result<(), FramebufferError> {
+    if *FRAMEBUFFER_OWNER.lock() != Some(process.id) {
+        return Err(FramebufferError::NotOwner);
+    }
+
+    unmap_framebuffer(process);
+    give_back_framebuffer(process);
+    Ok(())
+}
+
+/// Give the framebuffer back to the kernel if `process`, which is exiting, owns it.
+///
+/// The mapping goes away with the rest of the process, so it is not unmapped here. That
+/// also keeps this safe to call while the process is being torn down.
+pub fn release_framebuffer_on_exit(process: &Process) {
+    if *FRAMEBUFFER_OWNER.lock() != Some(process.id) {
+        return;
+    }
+
+    FRAMEBUFFER_MAPPING.lock().take();
+    give_back_framebuffer(process);
+}
+
+/// Hand the framebuffer from `process` back to the kernel, which draws into it again.
+fn give_back_framebuffer(process: &Process) {
+    *FRAMEBUFFER_OWNER.lock() = None;
+    FRAMEBUFFER_TAKEN.store(false, Ordering::Release);
+    logln!("Process {} gave back the framebuffer", process.id);
+
+    if CONSOLES_ACTIVE.load(Ordering::Acquire) {
+        crate::console::resume();
+    } else if let Some(video) = *VIDEO.lock() {
+        redraw_boot_screen(&video);
+    }
+}
+
+/// Map the framebuffer described by `video` into `process`, which owns it.
+fn map_framebuffer(process: &Process, video: &VideoInformation) -> Option<FramebufferInfo> {
+    let page = match VIDEO_PAGES.lock().as_ref() {
+        Some(pages) => process.map_physical_pages(pages.clone(), CacheMode::WriteCombining),
+        None => process.map_physical(
+            PhysAddr::new(video.phys_addr as usize),
+            video.size(),
+            CacheMode::WriteCombining,
+        ),
+    }
+    .ok()?;
+    *FRAMEBUFFER_MAPPING.lock() = Some(page);
+
+    Some(FramebufferInfo {
+        ptr: page.addr().as_mut_ptr(),
+        width: video.width,
+        height: video.height,
+        pitch: video.pitch,
+        bits_per_pixel: video.format.bits_per_pixel,
+        red_position: video.format.red.position,
+        green_position: video.format.green.position,
+        blue_position: video.format.blue.position,
+    })
+}
+
+/// Unmap the framebuffer from `process`, which owns it.
+fn unmap_framebuffer(process: &Process) {
+    if let Some(page) = FRAMEBUFFER_MAPPING.lock().take() {
+        if let Err(err) = process.unmap_physical_pages(page) {
+            warnln!(
+                "Unable to unmap the framebuffer from process {} ({err:?})",
+                process.id
+            );
+        }
+    }
+}
+
+/// Switch the framebuffer to `width` x `height` at `bits_per_pixel`, for `process`, which
+/// must own it.
+///
+/// See [`set_mode`], the old mapping is replaced by one of the new framebuffer.
+pub fn set_mode_for(
+    process: &Process,
+    width: u32,
+    height: u32,
+    bits_per_pixel: u8,
+) -> Result<FramebufferInfo, VideoModeError> {
+    if *FRAMEBUFFER_OWNER.lock() != Some(process.id) {
+        return Err(VideoModeError::NotOwner);
+    }
+
+    let video = set_mode(width, height, bits_per_pixel)?;
+    unmap_framebuffer(process);
+    map_framebuffer(process, &video).ok_or(VideoModeError::MappingMemoryError)
+}
+
+/// Show `width` x `height` of the framebuffer starting at `x`, `y`, for `process`.
+pub fn flush_for(
+    process: &Process,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), VideoModeError> {
+    if *FRAMEBUFFER_OWNER.lock() != Some(process.id) {
+        return Err(VideoModeError::NotOwner);
+    }
+
+    // The bootloader's framebuffer is always shown as it is
+    if VIDEO_PAGES.lock().is_none() {
+        return Ok(());
+    }
+
+    #[cfg(feature = "virtio-gpu")]
+    return crate::virtio::gpu::flush(crate::virtio::gpu::Rect {
+        x,
+        y,
+        width,
+        height,
+    })
+    .map_err(mode_error);
+
+    #[cfg(not(feature = "virtio-gpu"))]
+    {
+        let _ = (x, y, width, height);
+        Ok(())
+    }
+}
+
+/// Switch the framebuffer to `width` x `height` at `bits_per_pixel`, redrawing the
+/// consoles or boot screen to fit. Zero for `width` and `height` picks the size the
+/// display would like to be.
+///
+/// Only virtio-gpu can change modes after boot. If the switch fails the previous mode is
+/// kept, the mode the bootloader set up can't be gone back to once the GPU has taken over.
+pub fn set_mode(
+    width: u32,
+    height: u32,
+    bits_per_pixel: u8,
+) -> Result<VideoInformation, VideoModeError> {
+    #[cfg(feature = "virtio-gpu")]
+    {
+        use crate::virtio::gpu;
+
+        let old = VIDEO.lock().ok_or(VideoModeError::NotSupported)?;
+        if bits_per_pixel != 32 {
+            return Err(VideoModeError::InvalidMode);
+        }
+
+        let (width, height) = if width == 0 || height == 0 {
+            let display = gpu::displays()
+                .map_err(mode_error)?
+                .into_iter()
+                .find(|display| display.enabled)
+                .ok_or(VideoModeError::InvalidMode)?;
+            (display.preferred.width, display.preferred.height)
+        } else {
+            (width, height)
+        };
+
+        let framebuffer = gpu::set_resolution(width, height).map_err(mode_error)?;
+        let video = VideoInformation {
+            phys_addr: framebuffer.pages[0].addr().addr() as u64,
+            virt_addr: framebuffer.virt.addr() as u64,
+            width,
+            height,
+            pitch: framebuffer.pitch,
+            format: bootgfx::PixelFormat::XRGB8888,
+            mode_id: old.mode_id,
+        };
+
+        // The memory still has the last mode's pixels in it, laid out for the wrong pitch
+        unsafe { core::ptr::write_bytes(framebuffer.virt.as_mut_ptr::<u8>(), 0, video.size()) };
+        *VIDEO.lock() = Some(video);
+        *VIDEO_PAGES.lock() = Some(framebuffer.pages);
+        logln!("Switched the framebuffer to {width}x{height}");
+
+        redraw_kernel_screen(&video);
+        Ok(video)
+    }
+
+    #[cfg(not(feature = "virtio-gpu"))]
+    {
+        // Switching VBE modes needs a real mode trampoline, which the kernel does not have
+        let _ = (width, height, bits_per_pixel);
+        Err(VideoModeError::NotSupported)
+    }
+}
+
+/// What a failed request to virtio-gpu means for the process that made it
+#[cfg(feature = "virtio-gpu")]
+fn mode_error(err: crate::virtio::gpu::GpuError) -> VideoModeError {
+    use crate::virtio::gpu::GpuError;
+
+    match err {
+        GpuError::NoDevice => VideoModeError::NotSupported,
+        GpuError::InvalidResolution | GpuError::InvalidRect => VideoModeError::InvalidMode,
+        GpuError::Virtio(_) | GpuError::Device(_) => VideoModeError::DeviceError,
+    }
+}
+
+/// Redraw whatever the kernel shows on the framebuffer onto its new mode, `video`.
+#[cfg(feature = "virtio-gpu")]
+fn redraw_kernel_screen(video: &VideoInformation) {
+    if CONSOLES_ACTIVE.load(Ordering::Acquire) {
+        unsafe { crate::console::resize(video) };
+        return;
+    }
+
+    // The owner of the framebuffer redraws it itself
+    if FRAMEBUFFER_TAKEN.load(Ordering::Acquire) {
+        crate::virtio::gpu::try_flush_all();
+        return;
+    }
+
+    redraw_boot_screen(video);
+}
+
+/// Draw the boot screen onto `video` from scratch
+fn redraw_boot_screen(video: &VideoInformation) {
+    {
+        let mut boot_screen = BOOT_SCREEN.lock();
+        let Some(mode) = boot_screen.as_ref().map(|boot_screen| boot_screen.mode()) else {
+            return;
+        };
+
+        let mut new_screen = unsafe { BootScreen::new(video.virt_addr as *mut u8, video, mode) };
+        new_screen.clear();
+        *boot_screen = Some(new_screen);
+    }
+
+    show_splash();
+    let stage = *LAST_STAGE.lock();
+    report(stage);
+}
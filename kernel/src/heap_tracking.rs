@@ -0,0 +1,117 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use vera_portal::HeapDumpError;
+
+/// Without `heap-tracking` there is nothing recorded to dump
+#[cfg(not(feature = "heap-tracking"))]
+pub fn dump(_since_generation: u64) -> Result<u64, HeapDumpError> {
+    Err(HeapDumpError::TrackingDisabled)
+}
+
+/// Print the live kernel heap allocations made since `since_generation`, grouped by the
+/// function that made them.
+///
+/// Returns the current generation, so a later dump can show only what was allocated (and
+/// not yet freed) after this one. Calling this twice around some work shows what it leaked.
+#[cfg(feature = "heap-tracking")]
+pub fn dump(since_generation: u64) -> Result<u64, HeapDumpError> {
+    use crate::symbols::{KernelSymbol, KernelSymbols};
+    use alloc::{collections::btree_map::BTreeMap, vec::Vec};
+    use lignan::logln;
+    use mem::alloc::{
+        AllocInfo, CALL_SITE_DEPTH, alloc_generation, for_each_live_allocation,
+        live_allocation_count,
+    };
+
+    /// The number of call sites printed
+    const DUMP_TOP_SITES: usize = 32;
+    /// Frames within these are part of allocating, and are skipped to find the call site
+    const ALLOCATOR_FRAMES: &[&str] = &[
+        "__rust_",
+        "__rg_",
+        "alloc::",
+        "<alloc::",
+        "mem::alloc::",
+        "<mem::alloc::",
+    ];
+
+    let current_generation = alloc_generation();
+
+    // We cannot allocate while the allocator is locked, so reserve room for every
+    // allocation ahead of time (plus a few for any made before we lock it again).
+    let mut allocations: Vec<AllocInfo> = Vec::with_capacity(live_allocation_count() + 16);
+    for_each_live_allocation(|info| {
+        if info.generation >= since_generation
+            && info.generation < current_generation
+            && allocations.len() < allocations.capacity()
+        {
+            allocations.push(info);
+        }
+    });
+
+    let mut sites: BTreeMap<[u64; CALL_SITE_DEPTH], (usize, usize)> = BTreeMap::new();
+    for info in allocations.iter() {
+        let (count, bytes) = sites.entry(info.call_site).or_default();
+        *count += 1;
+        *bytes += info.size;
+    }
+
+    let mut sites: Vec<_> = sites.into_iter().collect();
+    sites.sort_unstable_by(|(_, (_, lhs)), (_, (_, rhs))| rhs.cmp(lhs));
+
+    let symbols = KernelSymbols::get();
+    let symbolize = |addr: u64| symbols.and_then(|symbols| symbols.lookup(addr));
+    let is_allocator_frame = |symbol: &KernelSymbol| {
+        ALLOCATOR_FRAMES
+            .iter()
+            .any(|prefix| symbol.name.starts_with(prefix))
+    };
+
+    logln!(
+        "Live heap allocations since generation {since_generation} ({} allocations, {} bytes):",
+        allocations.len(),
+        allocations.iter().map(|info| info.size).sum::<usize>()
+    );
+    for (call_site, (count, bytes)) in sites.into_iter().take(DUMP_TOP_SITES) {
+        let site = call_site
+            .iter()
+            .copied()
+            .filter(|&addr| addr != 0)
+            .find(|&addr| symbolize(addr).is_none_or(|symbol| !is_allocator_frame(&symbol)))
+            .unwrap_or(call_site[0]);
+
+        match symbolize(site) {
+            Some(symbol) => logln!(
+                "  {bytes:>8} bytes {count:>6} allocs  {}+{:#x}",
+                symbol.name,
+                site - symbol.start
+            ),
+            None => logln!("  {bytes:>8} bytes {count:>6} allocs  {site:#018x}"),
+        }
+    }
+
+    Ok(current_generation)
+}
@@ -0,0 +1,110 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Puts the cpu to sleep when there is nothing to run.
+//!
+//! The idle thread calls [`wait_for_work`] whenever the picking queue is empty. This uses
+//! `mwait` with a C-state hint when the cpu supports it, and `hlt` otherwise, so an idle
+//! system leaves the host cpu alone instead of spinning on the scheduler.
+
+use arch::{
+    idle::{enable_interrupts_and_halt, monitor, mwait, mwait_hint},
+    interrupts::{are_interrupts_enabled, disable_interrupts, enable_interrupts},
+    supports::{CpuFeature, does_cpu_support, mwait_sub_states},
+};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use lignan::logln;
+
+/// The deepest C-state we ask for, deeper states take longer to wake from than a tick
+const MAX_IDLE_CSTATE: u8 = 2;
+/// Stored in `MWAIT_HINT` when mwait should not be used
+const USE_HLT: u32 = u32::MAX;
+
+static MWAIT_HINT: AtomicU32 = AtomicU32::new(USE_HLT);
+
+/// The flag `mwait` watches, it gets its own cache line so unrelated writes don't wake us
+#[repr(align(64))]
+struct WakeLine(AtomicBool);
+
+static WAKE_PENDING: WakeLine = WakeLine(AtomicBool::new(false));
+
+/// Pick how this cpu should idle
+pub fn init() {
+    if !does_cpu_support(CpuFeature::SupportsMonitor) {
+        logln!("Idle: using hlt");
+        return;
+    }
+
+    let Some(sub_states) = mwait_sub_states() else {
+        logln!("Idle: using hlt (mwait C-states are not enumerated)");
+        return;
+    };
+
+    let Some(cstate) = (1..=MAX_IDLE_CSTATE)
+        .rev()
+        .find(|&cstate| sub_states[cstate as usize] != 0)
+    else {
+        logln!("Idle: using hlt (no usable mwait C-states)");
+        return;
+    };
+
+    MWAIT_HINT.store(mwait_hint(cstate, 0), Ordering::Relaxed);
+    logln!("Idle: using mwait with C{cstate}");
+}
+
+/// Tell the idle thread there is new work, waking it if it is waiting in `mwait`
+pub fn kick() {
+    WAKE_PENDING.0.store(true, Ordering::Release);
+}
+
+/// Sleep the cpu until there might be more work to do.
+///
+/// Returns right away if `has_work` says there is already something to do, or if
+/// interrupts are disabled (as nothing could wake us up).
+pub fn wait_for_work(has_work: impl Fn() -> bool) {
+    if !are_interrupts_enabled() {
+        return;
+    }
+
+    // Interrupts stay disabled from checking for work until we sleep, otherwise a wakeup
+    // could land in between and we would sleep through it.
+    unsafe { disable_interrupts() };
+
+    if WAKE_PENDING.0.swap(false, Ordering::Acquire) || has_work() {
+        unsafe { enable_interrupts() };
+        return;
+    }
+
+    match MWAIT_HINT.load(Ordering::Relaxed) {
+        USE_HLT => unsafe { enable_interrupts_and_halt() },
+        hint => unsafe {
+            monitor(WAKE_PENDING.0.as_ptr().cast());
+            if !WAKE_PENDING.0.load(Ordering::Acquire) {
+                mwait(hint);
+            }
+            enable_interrupts();
+        },
+    }
+}
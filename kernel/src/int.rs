@@ -23,7 +23,7 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use crate::process::scheduler::Scheduler;
+use crate::{process::scheduler::Scheduler, usercopy};
 use arch::{
     CpuPrivilege, attach_irq, critcal_section,
     idt64::{
@@ -38,6 +38,7 @@ use mem::{
     addr::VirtAddr,
     vm::{PageFaultInfo, call_page_fault_handler},
 };
+use vera_portal::FaultKind;
 
 static INTERRUPT_TABLE: InterruptMutex<InterruptDescTable> =
     InterruptMutex::new(InterruptDescTable::new());
@@ -78,6 +79,8 @@ fn exception_handler(args: &InterruptInfo) {
             match call_page_fault_handler(info) {
                 // If this page fault was handled, we dont need to do anything!
                 mem::vm::PageFaultReponse::Handled => (),
+                // A checked user copy faulted, it will report the fault to its caller
+                _ if usercopy::fixup_user_copy(args) => (),
                 // Crash the process
                 mem::vm::PageFaultReponse::NoAccess {
                     page_perm,
@@ -91,6 +94,9 @@ fn exception_handler(args: &InterruptInfo) {
                         addr,
                         args
                     );
+                    if is_user_fault(args) {
+                        fault_current(fault_kind(&args.flags));
+                    }
                     Scheduler::crash_current();
                 }
                 // panic
@@ -98,6 +104,12 @@ fn exception_handler(args: &InterruptInfo) {
                     panic!("PageFault critical fault: {error}");
                 }
                 // panic
+                mem::vm::PageFaultReponse::NotAttachedHandler if is_user_fault(args) => {
+                    errorln!("PageFault without attached handler!\n{:#016x?}", info);
+                    fault_current(fault_kind(&args.flags));
+                    Scheduler::crash_current();
+                }
+                // panic
                 mem::vm::PageFaultReponse::NotAttachedHandler => {
                     panic!(
                         "PageFault without attached handler!\n{:#016x?}\n{:#016x?}",
@@ -111,12 +123,49 @@ fn exception_handler(args: &InterruptInfo) {
         }
         exception => {
             errorln!("UNHANDLED FAULT\n{:#016x?}", args);
+            if is_user_fault(args) {
+                fault_current(fault_kind(&exception));
+            }
             Scheduler::crash_current();
         }
         _ => (),
     }
 }
 
+/// Did this exception happen while running userspace code?
+fn is_user_fault(args: &InterruptInfo) -> bool {
+    Segment(args.context.cs as u16).cpu_privl() == CpuPrivilege::Ring3
+}
+
+/// Convert a cpu exception into a fault we can send to userspace
+fn fault_kind(flags: &InterruptFlags) -> FaultKind {
+    match *flags {
+        InterruptFlags::PageFault {
+            write,
+            instruction_fetch,
+            virt_addr,
+            ..
+        } => FaultKind::PageFault {
+            addr: virt_addr,
+            write,
+            execute: instruction_fetch,
+        },
+        InterruptFlags::DivisionError => FaultKind::DivisionError,
+        InterruptFlags::InvalidOpcode => FaultKind::InvalidOpcode,
+        InterruptFlags::GeneralProtectionFault => FaultKind::GeneralProtection,
+        _ => FaultKind::Other,
+    }
+}
+
+/// Record the fault on the current process, the caller is expected to crash the thread after
+fn fault_current(fault: FaultKind) {
+    let Some(current_thread) = Scheduler::get().current_thread().upgrade() else {
+        return;
+    };
+
+    current_thread.process.fault(fault);
+}
+
 fn call_attached_irq(irq_id: u8, args: &InterruptInfo) {
     let irq_handler = IRQ_HANDLERS.lock();
 
@@ -0,0 +1,100 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! The subsystems this kernel was built with, chosen with cargo features, and the
+//! command line it was booted with.
+//!
+//! See the `[features]` of the kernel's `Cargo.toml` for what each one does, and the
+//! profiles that pick them.
+
+use crate::locks::ScheduleLock;
+use bootloader::cmdline::KernelCmdline;
+use core::fmt::Display;
+use lignan::logln;
+
+/// The command line from the bootloader, kept so userspace can read it
+static CMDLINE: ScheduleLock<KernelCmdline> = ScheduleLock::new(KernelCmdline::empty());
+
+/// Every optional subsystem, and if it was built into this kernel
+pub const FEATURES: &[(&str, bool)] = &[
+    ("usb", cfg!(feature = "usb")),
+    ("sound", cfg!(feature = "sound")),
+    ("gfx", cfg!(feature = "gfx")),
+    ("virtio-gpu", cfg!(feature = "virtio-gpu")),
+    ("virtio-balloon", cfg!(feature = "virtio-balloon")),
+    ("acpi", cfg!(feature = "acpi")),
+    ("profile", cfg!(feature = "profile")),
+    ("log-ring", cfg!(feature = "log-ring")),
+    ("syscall-trace", cfg!(feature = "syscall-trace")),
+    ("heap-tracking", cfg!(feature = "heap-tracking")),
+    ("heap-redzones", cfg!(feature = "heap-redzones")),
+    ("lock-debug", cfg!(feature = "lock-debug")),
+];
+
+/// The profile this kernel was built with, if it was built with one
+pub const PROFILE: Option<&str> = if cfg!(feature = "test") {
+    Some("test")
+} else if cfg!(feature = "desktop") {
+    Some("desktop")
+} else if cfg!(feature = "minimal-boot") {
+    Some("minimal-boot")
+} else {
+    None
+};
+
+/// Log which subsystems were built into this kernel
+pub fn print() {
+    logln!(
+        "Kernel config ({}): {}",
+        PROFILE.unwrap_or("custom"),
+        FeatureList
+    );
+}
+
+/// Remember the command line the kernel was booted with
+pub fn set_cmdline(cmdline: &KernelCmdline) {
+    *CMDLINE.lock() = *cmdline;
+}
+
+/// The command line the kernel was booted with
+pub fn cmdline() -> KernelCmdline {
+    *CMDLINE.lock()
+}
+
+/// Formats [`FEATURES`] as `+enabled -disabled`
+struct FeatureList;
+
+impl Display for FeatureList {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (index, &(name, enabled)) in FEATURES.iter().enumerate() {
+            if index != 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}{name}", if enabled { '+' } else { '-' })?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    console,
+    events::{self, SystemEvent},
+    int::attach_irq_handler,
+    process::scheduler::Scheduler,
+    resources::{self, Resource, Sharing},
+};
+use alloc::format;
+use arch::{idt64::InterruptInfo, io::IOPort, locks::InterruptMutex, pic8259::pic_unmask_irq};
+use core::sync::atomic::{AtomicBool, Ordering};
+use keymap::{Keymap, Modifiers};
+use lignan::{logln, warnln};
+use vera_portal::KeymapError;
+
+/// The PS/2 controller's data port
+const PS2_DATA: IOPort = IOPort::new(0x60);
+/// The IRQ raised by the PS/2 keyboard
+const KEYBOARD_IRQ: u8 = 1;
+
+/// Scancode set 1 codes for the keys we handle, the right Ctrl and Alt keys are extended
+const CTRL_PRESSED: u8 = 0x1D;
+const LEFT_SHIFT_PRESSED: u8 = 0x2A;
+const RIGHT_SHIFT_PRESSED: u8 = 0x36;
+const ALT_PRESSED: u8 = 0x38;
+const CAPS_LOCK_PRESSED: u8 = 0x3A;
+const F1_PRESSED: u8 = 0x3B;
+const F10_PRESSED: u8 = 0x44;
+/// Set in a scancode when the key was released
+const RELEASED_BIT: u8 = 0x80;
+/// Sent before the scancodes of keys that were added after the original keyboard
+const EXTENDED_PREFIX: u8 = 0xE0;
+
+/// The layout built into the kernel, the only one that isn't read from the initfs
+const BUILTIN_KEYMAP: &str = "us";
+/// Added to a layout's name to get the initfs file it is read from
+const KEYMAP_EXTENSION: &str = ".keymap";
+
+static ALT_HELD: AtomicBool = AtomicBool::new(false);
+static ALTGR_HELD: AtomicBool = AtomicBool::new(false);
+static LEFT_SHIFT_HELD: AtomicBool = AtomicBool::new(false);
+static RIGHT_SHIFT_HELD: AtomicBool = AtomicBool::new(false);
+static LEFT_CTRL_HELD: AtomicBool = AtomicBool::new(false);
+static RIGHT_CTRL_HELD: AtomicBool = AtomicBool::new(false);
+static CAPS_LOCK: AtomicBool = AtomicBool::new(false);
+/// Set after the PS/2 keyboard sent [`EXTENDED_PREFIX`]
+static PS2_EXTENDED: AtomicBool = AtomicBool::new(false);
+
+/// The layout keys are typed with, and the dead key waiting for the next key
+static LAYOUT: InterruptMutex<Option<Layout>> = InterruptMutex::new(None);
+
+struct Layout {
+    keymap: Keymap,
+    dead_key: Option<char>,
+}
+
+/// A key on the keyboard, the same no matter which keyboard it came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Alt,
+    /// The right Alt key, which types the third symbol of a key in many layouts
+    AltGr,
+    LeftShift,
+    RightShift,
+    LeftCtrl,
+    RightCtrl,
+    CapsLock,
+    /// A function key, where `Function(1)` is F1
+    Function(u8),
+    /// A key that types something, by its USB HID usage id
+    Usage(u8),
+    Unknown,
+}
+
+/// Start handling PS/2 keyboard interrupts
+pub fn init() {
+    *LAYOUT.lock() = Some(Layout {
+        keymap: Keymap::us(),
+        dead_key: None,
+    });
+
+    let claims = [
+        (
+            Resource::IoPorts {
+                base: PS2_DATA.port(),
+                len: 1,
+            },
+            Sharing::Exclusive,
+        ),
+        (Resource::Irq(KEYBOARD_IRQ), Sharing::Exclusive),
+    ];
+    if let Err(err) = resources::claim("ps2-keyboard", &claims) {
+        warnln!("PS/2 keyboard: {err}");
+        return;
+    }
+
+    attach_irq_handler(keyboard_interrupt_handler, KEYBOARD_IRQ);
+    unsafe { pic_unmask_irq(KEYBOARD_IRQ) };
+}
+
+/// Switch every keyboard to the layout `name`
+///
+/// Layouts other than the built-in US layout are read from `<name>.keymap` in the initfs.
+pub fn set_keymap(name: &str) -> Result<(), KeymapError> {
+    let keymap = if name == BUILTIN_KEYMAP {
+        Keymap::us()
+    } else {
+        let file = Scheduler::get()
+            .initfs_file(&format!("{name}{KEYMAP_EXTENSION}"))
+            .ok_or(KeymapError::NotFound)?;
+        let text = core::str::from_utf8(file).map_err(|err| KeymapError::Malformed {
+            line: file[..err.valid_up_to()]
+                .iter()
+                .filter(|&&byte| byte == b'\n')
+                .count()
+                + 1,
+        })?;
+
+        Keymap::parse(name, text).map_err(|err| {
+            warnln!("Keymap '{name}' line {}: {}", err.line, err.reason);
+            KeymapError::Malformed { line: err.line }
+        })?
+    };
+
+    logln!("Switched to the '{}' keyboard layout", keymap.name());
+    *LAYOUT.lock() = Some(Layout {
+        keymap,
+        dead_key: None,
+    });
+
+    Ok(())
+}
+
+/// Handle a key being pressed or released on any keyboard
+///
+/// Keys that type something are turned into text with the current layout, and published
+/// as [`SystemEvent::KeyTyped`] while the user console is on screen.
+pub fn key_event(key: Key, pressed: bool) {
+    let alt_held = ALT_HELD.load(Ordering::Relaxed) || ALTGR_HELD.load(Ordering::Relaxed);
+
+    match key {
+        Key::Alt => ALT_HELD.store(pressed, Ordering::Relaxed),
+        Key::AltGr => ALTGR_HELD.store(pressed, Ordering::Relaxed),
+        Key::LeftShift => LEFT_SHIFT_HELD.store(pressed, Ordering::Relaxed),
+        Key::RightShift => RIGHT_SHIFT_HELD.store(pressed, Ordering::Relaxed),
+        Key::LeftCtrl => LEFT_CTRL_HELD.store(pressed, Ordering::Relaxed),
+        Key::RightCtrl => RIGHT_CTRL_HELD.store(pressed, Ordering::Relaxed),
+        Key::CapsLock if pressed => {
+            CAPS_LOCK.fetch_xor(true, Ordering::Relaxed);
+        }
+        Key::Function(number)
+            if pressed && alt_held && (1..=console::VIRTUAL_CONSOLES as u8).contains(&number) =>
+        {
+            console::switch_to((number - 1) as usize)
+        }
+        // Alt and a key is a shortcut, not text
+        Key::Usage(usage) if pressed && !ALT_HELD.load(Ordering::Relaxed) => type_key(usage),
+        _ => (),
+    }
+}
+
+fn type_key(usage: u8) {
+    // Only the console on screen is typed into, and nothing reads the kernel's own
+    // consoles, so keys typed while they are shown go nowhere
+    if console::focused() != Some(console::USER_CONSOLE) {
+        return;
+    }
+
+    let modifiers = Modifiers {
+        shift: LEFT_SHIFT_HELD.load(Ordering::Relaxed) || RIGHT_SHIFT_HELD.load(Ordering::Relaxed),
+        altgr: ALTGR_HELD.load(Ordering::Relaxed),
+        caps_lock: CAPS_LOCK.load(Ordering::Relaxed),
+        ctrl: LEFT_CTRL_HELD.load(Ordering::Relaxed) || RIGHT_CTRL_HELD.load(Ordering::Relaxed),
+    };
+
+    let mut layout = LAYOUT.lock();
+    let Some(Layout { keymap, dead_key }) = layout.as_mut() else {
+        return;
+    };
+
+    keymap.type_key(usage, modifiers, dead_key, |ch| {
+        events::publish(SystemEvent::KeyTyped { ch })
+    });
+}
+
+/// The USB HID usage id of a key that types something, from its scancode set 1 code
+fn set1_to_usage(scancode: u8) -> Option<u8> {
+    Some(match scancode {
+        0x01 => 0x29,
+        // The number row, from 1 through 0
+        0x02..=0x0B => scancode - 0x02 + 0x1E,
+        0x0C => 0x2D,
+        0x0D => 0x2E,
+        0x0E => 0x2A,
+        0x0F => 0x2B,
+        0x10 => 0x14,
+        0x11 => 0x1A,
+        0x12 => 0x08,
+        0x13 => 0x15,
+        0x14 => 0x17,
+        0x15 => 0x1C,
+        0x16 => 0x18,
+        0x17 => 0x0C,
+        0x18 => 0x12,
+        0x19 => 0x13,
+        0x1A => 0x2F,
+        0x1B => 0x30,
+        0x1C => 0x28,
+        0x1E => 0x04,
+        0x1F => 0x16,
+        0x20 => 0x07,
+        0x21 => 0x09,
+        0x22 => 0x0A,
+        0x23 => 0x0B,
+        0x24 => 0x0D,
+        0x25 => 0x0E,
+        0x26 => 0x0F,
+        0x27 => 0x33,
+        0x28 => 0x34,
+        0x29 => 0x35,
+        0x2B => 0x31,
+        0x2C => 0x1D,
+        0x2D => 0x1B,
+        0x2E => 0x06,
+        0x2F => 0x19,
+        0x30 => 0x05,
+        0x31 => 0x11,
+        0x32 => 0x10,
+        0x33 => 0x36,
+        0x34 => 0x37,
+        0x35 => 0x38,
+        0x39 => 0x2C,
+        0x56 => 0x64,
+        _ => return None,
+    })
+}
+
+fn keyboard_interrupt_handler(_args: &InterruptInfo) {
+    let scancode = unsafe { PS2_DATA.read_byte() };
+    if scancode == EXTENDED_PREFIX {
+        PS2_EXTENDED.store(true, Ordering::Relaxed);
+        return;
+    }
+
+    let extended = PS2_EXTENDED.swap(false, Ordering::Relaxed);
+    let pressed = scancode & RELEASED_BIT == 0;
+
+    let key = match (extended, scancode & !RELEASED_BIT) {
+        (true, ALT_PRESSED) => Key::AltGr,
+        (true, CTRL_PRESSED) => Key::RightCtrl,
+        // Extended keys are the arrows and the keys above them, which type nothing
+        (true, _) => Key::Unknown,
+        (false, ALT_PRESSED) => Key::Alt,
+        (false, CTRL_PRESSED) => Key::LeftCtrl,
+        (false, LEFT_SHIFT_PRESSED) => Key::LeftShift,
+        (false, RIGHT_SHIFT_PRESSED) => Key::RightShift,
+        (false, CAPS_LOCK_PRESSED) => Key::CapsLock,
+        (false, function_key @ F1_PRESSED..=F10_PRESSED) => {
+            Key::Function(function_key - F1_PRESSED + 1)
+        }
+        (false, code) => set1_to_usage(code).map_or(Key::Unknown, Key::Usage),
+    };
+
+    key_event(key, pressed);
+}
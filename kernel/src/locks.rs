@@ -31,6 +31,8 @@ pub use yield_lock::*;
 mod critical_lock;
 mod schedule_lock;
 mod thread_cell;
+#[cfg(feature = "lock-debug")]
+pub mod watchdog;
 mod yield_lock;
 
 use core::fmt::Debug;
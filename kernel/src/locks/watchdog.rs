@@ -0,0 +1,99 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Warns about latency bugs, where scheduling or interrupts are kept disabled for too long.
+//!
+//! Scheduling is checked by counting the timer ticks that arrive while a `ScheduleLock` is
+//! held. Interrupts cannot be checked that way, since the timer cannot interrupt while they
+//! are disabled, so instead the TSC is compared between timer ticks to find ticks that
+//! arrived late.
+
+use super::current_scheduler_locks;
+use crate::symbols::SymbolizedAddr;
+use core::{
+    arch::x86_64::_rdtsc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use lignan::{current_debug_locks, warnln};
+
+/// Warn when scheduling has been disabled for this many ticks
+const SCHEDULE_LOCK_WARN_TICKS: u64 = 50;
+/// Warn when a timer tick arrives this many ticks late
+const IRQ_DELAY_WARN_TICKS: u64 = 10;
+/// The number of ticks used to measure the TSC's rate
+const CALIBRATION_TICKS: u64 = 100;
+
+static TICKS_SEEN: AtomicU64 = AtomicU64::new(0);
+static CALIBRATION_START_TSC: AtomicU64 = AtomicU64::new(0);
+static LAST_TICK_TSC: AtomicU64 = AtomicU64::new(0);
+/// Zero until the TSC's rate has been measured
+static TSC_PER_TICK: AtomicU64 = AtomicU64::new(0);
+static SCHEDULE_LOCKED_TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Check how long scheduling and interrupts were disabled, called on each timer tick with
+/// the instruction pointer it interrupted.
+pub fn timer_tick(rip: u64) {
+    let now = unsafe { _rdtsc() };
+    let last = LAST_TICK_TSC.swap(now, Ordering::Relaxed);
+
+    match TICKS_SEEN.fetch_add(1, Ordering::Relaxed) {
+        0 => CALIBRATION_START_TSC.store(now, Ordering::Relaxed),
+        CALIBRATION_TICKS => {
+            let start = CALIBRATION_START_TSC.load(Ordering::Relaxed);
+            TSC_PER_TICK.store((now - start) / CALIBRATION_TICKS, Ordering::Relaxed);
+        }
+        _ => (),
+    }
+
+    // We cannot log while another log is being written
+    if current_debug_locks() != 0 {
+        return;
+    }
+
+    let tsc_per_tick = TSC_PER_TICK.load(Ordering::Relaxed);
+    if tsc_per_tick != 0 && last != 0 {
+        let ticks_late = now.saturating_sub(last) / tsc_per_tick;
+
+        if ticks_late >= IRQ_DELAY_WARN_TICKS {
+            warnln!(
+                "Timer tick arrived ~{ticks_late} ticks late, interrupts were disabled for too long (resumed at {})",
+                SymbolizedAddr(rip)
+            );
+        }
+    }
+
+    if current_scheduler_locks() == 0 {
+        SCHEDULE_LOCKED_TICKS.store(0, Ordering::Relaxed);
+        return;
+    }
+
+    let locked_ticks = SCHEDULE_LOCKED_TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    if locked_ticks == SCHEDULE_LOCK_WARN_TICKS {
+        warnln!(
+            "Scheduling has been disabled for {locked_ticks} ticks, currently at {}",
+            SymbolizedAddr(rip)
+        );
+    }
+}
@@ -0,0 +1,172 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Streams the kernel's log to a logger process through a [`LogRing`] in shared memory.
+//!
+//! The ring is attached as a lignan stream connection, so it sees the same text as the
+//! serial console. A single logger process can attach to the ring to read it, and is
+//! woken through the ring's futex word when new bytes arrive.
+
+use crate::{
+    locks::ScheduleLock,
+    process::{RefProcess, WeakProcess, shared::SharedMemory},
+};
+use alloc::{boxed::Box, sync::Arc};
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+use lignan::{
+    ring::LogRing,
+    stream::{StreamConnection, add_stream_connection},
+    warnln,
+};
+use util::consts::PAGE_4K;
+use vera_portal::{LogRingError, LogRingMapping};
+
+/// The size of the ring, including its header
+const LOG_RING_LEN: usize = 16 * PAGE_4K;
+
+/// The ring as the kernel set it up, or null before `init`
+///
+/// This is never read back from the shared header, which the logger can change.
+static RING: AtomicPtr<LogRing> = AtomicPtr::new(core::ptr::null_mut());
+/// The shared memory region holding the ring
+static RING_SHARED: ScheduleLock<Option<Arc<SharedMemory>>> = ScheduleLock::new(None);
+/// Set when bytes were written that the logger hasn't been woken for yet
+static WAKE_PENDING: AtomicBool = AtomicBool::new(false);
+/// The attached logger, and the address of the ring's futex word in its memory
+static LOGGER: ScheduleLock<Option<(WeakProcess, usize)>> = ScheduleLock::new(None);
+
+/// Create the ring, and start streaming the log into it.
+///
+/// The ring is mapped into the kernel, so this must happen before any process is spawned.
+pub fn init() {
+    let shared = match SharedMemory::create(LOG_RING_LEN / PAGE_4K) {
+        Ok(shared) => shared,
+        Err(err) => {
+            warnln!("Unable to allocate the log ring: {err:?}");
+            return;
+        }
+    };
+
+    let virt = match shared.map_into_kernel() {
+        Ok(virt) => virt,
+        Err(err) => {
+            warnln!("Unable to map the log ring: {err}");
+            return;
+        }
+    };
+
+    let Some(ring) = (unsafe { LogRing::init(virt.as_mut_ptr(), LOG_RING_LEN) }) else {
+        return;
+    };
+
+    *RING_SHARED.lock() = Some(shared);
+    RING.store(Box::into_raw(Box::new(ring)), Ordering::Release);
+
+    if add_stream_connection(StreamConnection::new(ring_output)).is_none() {
+        warnln!("No free stream connection for the log ring");
+    }
+}
+
+fn ring() -> Option<&'static LogRing> {
+    // Set once by `init` and never freed
+    unsafe { RING.load(Ordering::Acquire).as_ref() }
+}
+
+fn ring_output(args: core::fmt::Arguments) {
+    struct RingWriter(&'static LogRing);
+
+    impl Write for RingWriter {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            self.0.write(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    let Some(ring) = ring() else {
+        return;
+    };
+
+    let _ = RingWriter(ring).write_fmt(args);
+    WAKE_PENDING.store(true, Ordering::Release);
+}
+
+/// Write straight into the ring, without going to the other log streams.
+#[cfg_attr(not(feature = "syscall-trace"), allow(dead_code))]
+pub fn write(args: core::fmt::Arguments) {
+    ring_output(args);
+}
+
+/// Map the ring into `process`, and make it the logger.
+///
+/// There can only be one logger at a time, since only one reader may use a ring.
+pub fn attach(process: &RefProcess) -> Result<LogRingMapping, LogRingError> {
+    let mut logger = LOGGER.lock();
+    if logger
+        .as_ref()
+        .is_some_and(|(logger, _)| logger.strong_count() > 0)
+    {
+        return Err(LogRingError::AlreadyAttached);
+    }
+
+    let shared = RING_SHARED
+        .lock()
+        .clone()
+        .ok_or(LogRingError::Unavailable)?;
+
+    let start = process
+        .map_shared_memory(shared, true)
+        .map_err(|_| LogRingError::MappingFailed)?
+        .addr();
+    *logger = Some((
+        Arc::downgrade(process),
+        start.addr() + LogRing::FUTEX_OFFSET,
+    ));
+
+    Ok(LogRingMapping {
+        ptr: start.as_mut_ptr(),
+        len: LOG_RING_LEN,
+    })
+}
+
+/// Wake the logger if anything was written since it was last woken.
+///
+/// This is called from the scheduler's tick, as waking threads from inside of a log
+/// message is not safe.
+pub fn wake_logger() {
+    if !WAKE_PENDING.swap(false, Ordering::Acquire) {
+        return;
+    }
+
+    let Some((logger, futex_addr)) = LOGGER.lock().clone() else {
+        return;
+    };
+
+    if let Some(logger) = logger.upgrade() {
+        logger.futexes.wake(futex_addr, usize::MAX);
+    }
+}
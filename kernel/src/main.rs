@@ -32,23 +32,63 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 extern crate alloc;
 
+#[cfg(feature = "acpi")]
+mod acpi;
+mod backtrace;
+mod com;
+mod console;
 mod context;
+mod crashdump;
+mod events;
 mod gdt;
+#[cfg(feature = "gfx")]
+mod gfx;
+mod heap_tracking;
+mod idle;
 mod int;
+mod kconfig;
+mod keyboard;
 mod locks;
+#[cfg(feature = "log-ring")]
+mod log_ring;
+mod mitigations;
 mod panic;
+#[cfg(any(feature = "usb", feature = "virtio-gpu", feature = "virtio-balloon"))]
+mod pci;
 mod process;
 mod processor;
+#[cfg(feature = "profile")]
+mod profile;
+mod pstore;
 mod qemu;
+mod resources;
+#[cfg(feature = "sound")]
+mod sound;
+mod symbols;
 mod syscall_handler;
+#[cfg(feature = "syscall-trace")]
+mod syscall_trace;
 mod timer;
+#[cfg(feature = "usb")]
+mod usb;
+mod usercopy;
+#[cfg(any(feature = "virtio-gpu", feature = "virtio-balloon"))]
+mod virtio;
+mod vmm;
 
 use arch::supports::cpu_vender;
-use bootloader::KernelBootHeader;
+#[cfg(feature = "gfx")]
+use bootloader::progress::BootStage;
+use bootloader::{
+    KernelBootHeader,
+    boot_info::{BOOT_TAG_BOOTLOADER_NAME, BootInfo},
+};
 use core::cell::SyncUnsafeCell;
-use lignan::{debug_ready, logln, make_debug};
+use lignan::{debug_ready, logln, make_debug, warnln};
 use mem::{
+    addr::PhysAddr,
     alloc::{KernelAllocator, provide_init_region},
+    paging::CacheMode,
     pmm::Pmm,
     vm::VmRegion,
 };
@@ -57,14 +97,14 @@ use process::{
     scheduler::{Scheduler, init_virt2phys_provider},
     thread::Thread,
 };
-use serial::{Serial, baud::SerialBaud};
+use serial::debugcon::DebugCon;
 use util::{bytes::HumanBytes, consts::PAGE_4K};
 
 #[global_allocator]
 static ALLOC: KernelAllocator = KernelAllocator::new();
 
 make_debug! {
-    "Serial": Option<Serial> = Serial::probe_first(SerialBaud::Baud115200);
+    "Serial": Option<com::SerialLog> = com::SerialLog::probe();
 }
 
 #[unsafe(no_mangle)]
@@ -76,27 +116,68 @@ extern "C" fn _start(kbh: u64) -> ! {
 
 #[debug_ready]
 fn main(kbh: &KernelBootHeader) {
+    if let Err(err) = kbh.validate() {
+        panic!("Unable to read the boot header from the bootloader: {err}");
+    }
+
+    if kbh.cmdline.has_flag("debugcon") {
+        lignan::stream::add_stream_connection(lignan::stream::StreamConnection::new(
+            debugcon_print,
+        ));
+    }
+    crashdump::init(kbh);
+
     logln!("Welcome to the Vera Kernel!");
+    logln!("Command line: {:?}", kbh.cmdline.as_str());
+    for (kind, payload) in unsafe { kbh.tags() } {
+        if kind == BOOT_TAG_BOOTLOADER_NAME {
+            logln!(
+                "Booted by: {}",
+                core::str::from_utf8(payload).unwrap_or("?")
+            );
+        }
+    }
     logln!(
         "Free Memory : {}",
         HumanBytes::from(kbh.phys_mem_map.bytes_of(mem::phys::PhysMemoryKind::Free))
     );
     logln!("Running on a(n) '{:?}' processor.", cpu_vender());
+    kconfig::print();
+    kconfig::set_cmdline(&kbh.cmdline);
     logln!(
         "Init Heap Region ({})",
         HumanBytes::from(kbh.kernel_init_heap.1)
     );
 
+    assert!(
+        memory_layout::KERNEL_IMAGE
+            .contains_range(kbh.kernel_init_heap.0 as usize, kbh.kernel_init_heap.1),
+        "The bootloader placed our init heap outside of the kernel's virtual region"
+    );
     provide_init_region(unsafe {
         core::slice::from_raw_parts_mut(kbh.kernel_init_heap.0 as *mut u8, kbh.kernel_init_heap.1)
     });
 
     gdt::init_kernel_gdt();
     unsafe { gdt::load_tss() };
+    // Every entry from userspace does a `swapgs`, so our `GS` base stays ours even if
+    // userspace changes its own.
+    unsafe { processor::init_processor_local() };
+    processor::set_kernel_stack(kbh.kernel_stack.0..kbh.kernel_stack.0 + kbh.kernel_stack.1 as u64);
+    arch::stack_walk::set_stack_bounds(processor::kernel_stack);
+    logln!(
+        "Processor local data ready (cpu={})",
+        processor::processor_local().cpu_id
+    );
     int::enable_pic();
     int::attach_interrupts();
     int::attach_syscall();
+    com::init(&kbh.cmdline);
     unsafe { arch::registers::ia32_efer::set_no_execute_flag(true) };
+    usercopy::init_protections();
+    mitigations::init(&kbh.cmdline);
+    #[cfg(feature = "syscall-trace")]
+    syscall_trace::init(&kbh.cmdline);
 
     logln!("Init PhysMemoryManager");
     let pmm = Pmm::new(kbh.phys_mem_map).unwrap();
@@ -124,10 +205,47 @@ fn main(kbh: &KernelBootHeader) {
     }
 
     unsafe { (*INITFS_REGION.get()) = initfs_region };
+    logln!("Kernel page coverage: {}", vmm::kernel_page_coverage());
+
+    vmm::init_mtrr(kbh.phys_mem_map);
+    vmm::init_pat();
+    pstore::init(kbh);
+    if let Some(mut video) = kbh.video {
+        match vmm::map_mmio(
+            PhysAddr::new(video.phys_addr as usize),
+            video.size(),
+            CacheMode::WriteCombining,
+        ) {
+            Ok(vaddr) => {
+                logln!(
+                    "Mapped {}x{} {}bpp framebuffer at {:#018x} (pitch={})",
+                    video.width,
+                    video.height,
+                    video.format.bits_per_pixel,
+                    vaddr.addr(),
+                    video.pitch
+                );
+                video.virt_addr = vaddr.addr() as u64;
+
+                if kbh.cmdline.has_flag("vt") || !cfg!(feature = "gfx") {
+                    unsafe { console::init(&video) };
+                    #[cfg(feature = "gfx")]
+                    gfx::init_consoles(&video);
+                } else {
+                    #[cfg(feature = "gfx")]
+                    {
+                        unsafe { gfx::init(&video, kbh.boot_mode) };
+                        gfx::report(BootStage::Memory);
+                    }
+                }
+            }
+            Err(err) => warnln!("Unable to map framebuffer: {}", err),
+        }
+    }
 
     let kernel_process = Process::new("kernel".into());
     Thread::new_kernel(kernel_process.clone(), init_stage2);
-    Thread::new_kernel(kernel_process.clone(), idle);
+    Thread::new_kernel(kernel_process.clone(), idle_loop);
 
     // This will start the scheduler for the first time
     Scheduler::yield_now();
@@ -138,18 +256,64 @@ static INITFS_REGION: SyncUnsafeCell<VmRegion> = SyncUnsafeCell::new(VmRegion::f
 /// Tasks required after scheduling is setup to be started.
 fn init_stage2() {
     logln!("Starting second-stage init!");
+    events::init();
+    #[cfg(feature = "gfx")]
+    gfx::report(BootStage::Scheduler);
+
+    // This must happen before spawning, so processes can see the memory devices use for DMA
+    #[cfg(feature = "usb")]
+    usb::init();
+    #[cfg(feature = "virtio-gpu")]
+    virtio::gpu::init();
+    #[cfg(feature = "virtio-balloon")]
+    virtio::balloon::init();
+    #[cfg(feature = "acpi")]
+    acpi::init();
+
+    // The log ring is mapped into the kernel, so it must exist before any process does
+    #[cfg(feature = "log-ring")]
+    log_ring::init();
+
     let s = Scheduler::get();
     unsafe { s.spawn_all_initfs(*INITFS_REGION.get()) };
+    symbols::init();
+    #[cfg(feature = "gfx")]
+    {
+        gfx::show_splash();
+        gfx::report(BootStage::Userspace);
+    }
     timer::init_timer();
+    keyboard::init();
+
+    lignan::stream::set_timestamp_fn(timer::kernel_uptime);
+    lignan::stream::set_cpu_id_fn(|| processor::processor_local().cpu_id);
+    lignan::stream::update_all_stream_connections(|connection| {
+        connection
+            .with_timestamps(true)
+            .with_wall_time(true)
+            .with_cpu_id(true)
+    });
 }
 
-fn idle() {
+fn debugcon_print(args: core::fmt::Arguments) {
+    use core::fmt::Write;
+    let _ = DebugCon::new().write_fmt(args);
+}
+
+fn idle_loop() {
+    idle::init();
+
     loop {
         let s = Scheduler::get();
         if s.threads_alive() <= 1 {
             logln!("All threads exited!");
+            #[cfg(feature = "acpi")]
+            if acpi::shutting_down() {
+                acpi::power_off();
+            }
             qemu::exit_emulator(qemu::QemuExitStatus::Success);
         }
         Scheduler::yield_now();
+        idle::wait_for_work(|| Scheduler::get().has_ready_threads());
     }
 }
@@ -0,0 +1,135 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Speculative execution mitigations.
+//!
+//! Each mitigation is on by default when the cpu supports it, and can be controlled
+//! from the kernel command line:
+//! - `mitigations=off` turns everything off unless it is asked for by name.
+//! - `ibrs=on|off` restricts indirect branch speculation in the kernel.
+//! - `ibpb=on|off` flushes branch predictions when switching between processes.
+//! - `ssbd=on|off` disables speculative store bypass.
+//! - `kpti=on|off` would hide the kernel's mappings from user processes, but KPTI is not
+//!   implemented yet so this only changes what is reported.
+//!
+//! Only enhanced IBRS protects the kernel by being set once. Older cpus need IBRS written
+//! again on every entry into the kernel, which the entry paths don't do, so there it is
+//! reported as partial.
+
+use arch::{
+    hardening::{
+        ArchCapabilities, SPEC_CTRL_IBRS, SPEC_CTRL_SSBD, SpeculationControls,
+        indirect_branch_prediction_barrier, update_spec_ctrl,
+    },
+    supports::{CpuVender, cpu_vender},
+};
+use bootloader::cmdline::KernelCmdline;
+use core::sync::atomic::{AtomicBool, Ordering};
+use lignan::{logln, warnln};
+
+static IBPB_ON_SWITCH: AtomicBool = AtomicBool::new(false);
+
+/// Is the mitigation `name` wanted, given what the command line says?
+fn wanted(cmdline: &KernelCmdline, name: &str) -> bool {
+    match cmdline.value_of(name) {
+        Some("on") => true,
+        Some("off") => false,
+        _ => cmdline.value_of("mitigations") != Some("off"),
+    }
+}
+
+/// Turn on the mitigations the command line asks for, and report what was chosen
+pub fn init(cmdline: &KernelCmdline) {
+    let controls = SpeculationControls::detect();
+    let caps = ArchCapabilities::read(&controls).unwrap_or_default();
+    let mut spec_ctrl = 0;
+
+    let ibrs = if !wanted(cmdline, "ibrs") {
+        "off"
+    } else if !controls.ibrs {
+        "unsupported"
+    } else {
+        spec_ctrl |= SPEC_CTRL_IBRS;
+        if caps.ibrs_all {
+            "on (enhanced)"
+        } else {
+            "partial (only set at boot, not on each kernel entry)"
+        }
+    };
+
+    let ssbd = if !wanted(cmdline, "ssbd") {
+        "off"
+    } else if caps.ssb_no {
+        "not affected"
+    } else if !controls.ssbd {
+        "unsupported"
+    } else {
+        spec_ctrl |= SPEC_CTRL_SSBD;
+        "on"
+    };
+
+    if spec_ctrl != 0 {
+        unsafe { update_spec_ctrl(spec_ctrl, 0) };
+    }
+
+    let ibpb = if !wanted(cmdline, "ibpb") {
+        "off"
+    } else if !controls.ibpb {
+        "unsupported"
+    } else {
+        IBPB_ON_SWITCH.store(true, Ordering::Relaxed);
+        "on process switch"
+    };
+
+    // Only Intel cpus speculate loads past a page fault, `RDCL_NO` marks the ones fixed in hardware
+    let meltdown = matches!(cpu_vender(), CpuVender::Intel) && !caps.rdcl_no;
+    // FIXME: Processes copy the kernel's page tables, a separate user copy holding only
+    //        the entry trampolines needs every kernel entry path to switch `cr3` first.
+    let kpti = if !meltdown && cmdline.value_of("kpti") != Some("on") {
+        "not affected"
+    } else if wanted(cmdline, "kpti") {
+        "not implemented"
+    } else {
+        "off"
+    };
+
+    logln!("Mitigations: IBRS {ibrs}, IBPB {ibpb}, SSBD {ssbd}, KPTI {kpti}");
+    if meltdown {
+        warnln!("This cpu is affected by Meltdown, kernel memory is readable from userspace");
+    }
+    if controls.ibrs && !caps.ibrs_all && wanted(cmdline, "ibrs") {
+        warnln!(
+            "This cpu only has legacy IBRS, userspace can still steer the kernel's indirect branches"
+        );
+    }
+}
+
+/// Called when switching to another process's page tables
+#[inline]
+pub fn on_address_space_switch() {
+    if IBPB_ON_SWITCH.load(Ordering::Relaxed) {
+        unsafe { indirect_branch_prediction_barrier() };
+    }
+}
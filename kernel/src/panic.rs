@@ -23,7 +23,8 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use arch::interrupts::disable_interrupts;
+use crate::{backtrace::Backtrace, com, crashdump, pstore};
+use arch::{idle::halt_forever, interrupts::disable_interrupts};
 use core::panic::PanicInfo;
 use lignan::{current_debug_locks, errorln};
 
@@ -34,8 +35,14 @@ fn panic(info: &PanicInfo) -> ! {
         unsafe { lignan::force_unlock_all() };
     }
     errorln!("{}", info);
+    errorln!("Backtrace:\n{}", Backtrace::capture());
+    pstore::seal();
+    crashdump::write_crash_dump(info);
+    com::flush();
+    #[cfg(feature = "sound")]
+    crate::sound::panic_beep();
 
     // Close the emulator on panic
     // exit_emulator(QemuExitStatus::Failure);
-    loop {}
+    halt_forever();
 }
@@ -0,0 +1,287 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::{format, string::String, vec::Vec};
+use arch::io::IOPort;
+use core::fmt::Debug;
+use lignan::logln;
+
+/// Selects which config space register `CONFIG_DATA` accesses
+const CONFIG_ADDRESS: IOPort = IOPort::new(0xCF8);
+/// The selected config space register
+const CONFIG_DATA: IOPort = IOPort::new(0xCFC);
+
+/// Config space offsets shared by every device
+const VENDOR_ID: u8 = 0x00;
+const COMMAND: u8 = 0x04;
+const STATUS: u8 = 0x06;
+const CLASS: u8 = 0x08;
+const HEADER_TYPE: u8 = 0x0E;
+const BAR0: u8 = 0x10;
+const CAPABILITIES_POINTER: u8 = 0x34;
+const INTERRUPT_LINE: u8 = 0x3C;
+
+/// Command register bits
+const COMMAND_IO_SPACE: u16 = 1 << 0;
+const COMMAND_MEMORY_SPACE: u16 = 1 << 1;
+const COMMAND_BUS_MASTER: u16 = 1 << 2;
+
+/// Set in the status register when the device has a capability list
+const STATUS_CAPABILITIES: u16 = 1 << 4;
+
+/// Set in the header type when a device has more than one function
+const MULTI_FUNCTION_BIT: u8 = 1 << 7;
+
+/// A function of a device on the PCI bus
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PciDevice {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+}
+
+/// Where a base address register points
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bar {
+    Io(u16),
+    Memory(u64),
+}
+
+impl PciDevice {
+    fn probe(bus: u8, device: u8, function: u8) -> Option<Self> {
+        let ids = read_config(bus, device, function, VENDOR_ID);
+        if ids & 0xFFFF == 0xFFFF {
+            return None;
+        }
+
+        let class = read_config(bus, device, function, CLASS);
+        Some(Self {
+            bus,
+            device,
+            function,
+            vendor_id: ids as u16,
+            device_id: (ids >> 16) as u16,
+            class: (class >> 24) as u8,
+            subclass: (class >> 16) as u8,
+            prog_if: (class >> 8) as u8,
+        })
+    }
+
+    /// Read the dword config register at `offset`.
+    pub fn read_u32(&self, offset: u8) -> u32 {
+        read_config(self.bus, self.device, self.function, offset)
+    }
+
+    /// Read the word config register at `offset`.
+    pub fn read_u16(&self, offset: u8) -> u16 {
+        (self.read_u32(offset & !0b11) >> ((offset & 0b10) * 8)) as u16
+    }
+
+    /// Read the byte config register at `offset`.
+    pub fn read_u8(&self, offset: u8) -> u8 {
+        (self.read_u32(offset & !0b11) >> ((offset & 0b11) * 8)) as u8
+    }
+
+    /// Write the dword config register at `offset`.
+    pub fn write_u32(&self, offset: u8, value: u32) {
+        write_config(self.bus, self.device, self.function, offset, value);
+    }
+
+    /// Write the word config register at `offset`, keeping the other half of its dword.
+    pub fn write_u16(&self, offset: u8, value: u16) {
+        let shift = (offset & 0b10) * 8;
+        let dword = self.read_u32(offset & !0b11) & !(0xFFFF << shift);
+        self.write_u32(offset & !0b11, dword | (value as u32) << shift);
+    }
+
+    /// Get where base address register `index` points, if it is in use.
+    pub fn bar(&self, index: u8) -> Option<Bar> {
+        let offset = BAR0 + index * 4;
+        let bar = self.read_u32(offset);
+
+        if bar & 1 == 1 {
+            return Some(Bar::Io((bar & 0xFFFC) as u16)).filter(|&bar| bar != Bar::Io(0));
+        }
+
+        // Bits 1-2 say if this is a 64-bit BAR, taking the next register for its high half
+        let high = if (bar >> 1) & 0b11 == 0b10 {
+            self.read_u32(offset + 4) as u64
+        } else {
+            0
+        };
+
+        Some(Bar::Memory(high << 32 | (bar & 0xFFFF_FFF0) as u64))
+            .filter(|&bar| bar != Bar::Memory(0))
+    }
+
+    /// Get how many bytes the memory base address register `index` decodes.
+    ///
+    /// Memory decoding is turned off while the register is probed, and turned back on
+    /// after if it was on before.
+    pub fn bar_size(&self, index: u8) -> Option<u64> {
+        let offset = BAR0 + index * 4;
+        let bar = self.read_u32(offset);
+        if bar & 1 == 1 {
+            return None;
+        }
+
+        let command = self.read_u16(COMMAND);
+        self.write_u16(COMMAND, command & !COMMAND_MEMORY_SPACE);
+
+        // The bits the device can't decode stay zero when every bit is written
+        let probe = |offset: u8| {
+            let original = self.read_u32(offset);
+            self.write_u32(offset, u32::MAX);
+            let mask = self.read_u32(offset);
+            self.write_u32(offset, original);
+            mask
+        };
+        let low = probe(offset) & 0xFFFF_FFF0;
+        let high = if (bar >> 1) & 0b11 == 0b10 {
+            probe(offset + 4)
+        } else {
+            u32::MAX
+        };
+
+        self.write_u16(COMMAND, command);
+
+        let mask = (high as u64) << 32 | low as u64;
+        Some((!mask).wrapping_add(1)).filter(|_| low != 0 || high != u32::MAX)
+    }
+
+    /// Walk the capability list, giving the `(id, offset)` of each capability.
+    pub fn capabilities(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        let first = if self.read_u16(STATUS) & STATUS_CAPABILITIES != 0 {
+            self.read_u8(CAPABILITIES_POINTER) & !0b11
+        } else {
+            0
+        };
+
+        // Every capability is inside the 256 byte config space, so a well formed list
+        // can't be longer than this. Stopping here also guards against lists with loops.
+        core::iter::successors(Some(first).filter(|&offset| offset != 0), |&offset| {
+            Some(self.read_u8(offset + 1) & !0b11).filter(|&next| next != 0)
+        })
+        .take(48)
+        .map(|offset| (self.read_u8(offset), offset))
+    }
+
+    /// The legacy PIC line this device interrupts on, if it has one.
+    pub fn interrupt_line(&self) -> Option<u8> {
+        Some(self.read_u8(INTERRUPT_LINE)).filter(|&line| line < 16)
+    }
+
+    /// The name `driver` claims this device's resources under, like `uhci 00:1d.0`.
+    pub fn owner_name(&self, driver: &str) -> String {
+        format!(
+            "{driver} {:02x}:{:02x}.{}",
+            self.bus, self.device, self.function
+        )
+    }
+
+    /// Let this device respond to IO and memory accesses, and do DMA.
+    pub fn enable(&self) {
+        let command = self.read_u16(COMMAND);
+        self.write_u16(
+            COMMAND,
+            command | COMMAND_IO_SPACE | COMMAND_MEMORY_SPACE | COMMAND_BUS_MASTER,
+        );
+    }
+}
+
+impl Debug for PciDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:02x}:{:02x}.{} [{:04x}:{:04x}] class {:02x}.{:02x}.{:02x}",
+            self.bus,
+            self.device,
+            self.function,
+            self.vendor_id,
+            self.device_id,
+            self.class,
+            self.subclass,
+            self.prog_if
+        )
+    }
+}
+
+fn config_address(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+    1 << 31
+        | (bus as u32) << 16
+        | (device as u32) << 11
+        | (function as u32) << 8
+        | (offset & !0b11) as u32
+}
+
+fn read_config(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+    unsafe {
+        CONFIG_ADDRESS.write_dword(config_address(bus, device, function, offset));
+        CONFIG_DATA.read_dword()
+    }
+}
+
+fn write_config(bus: u8, device: u8, function: u8, offset: u8, value: u32) {
+    unsafe {
+        CONFIG_ADDRESS.write_dword(config_address(bus, device, function, offset));
+        CONFIG_DATA.write_dword(value);
+    }
+}
+
+/// Find every function of every device on the PCI bus.
+///
+/// This uses configuration mechanism #1, and checks every bus instead of following bridges.
+pub fn scan() -> Vec<PciDevice> {
+    let mut devices = Vec::new();
+
+    for bus in 0..=255 {
+        for device in 0..32 {
+            let Some(first) = PciDevice::probe(bus, device, 0) else {
+                continue;
+            };
+
+            let functions = if first.read_u8(HEADER_TYPE) & MULTI_FUNCTION_BIT != 0 {
+                8
+            } else {
+                1
+            };
+
+            devices.extend(
+                (0..functions).filter_map(|function| PciDevice::probe(bus, device, function)),
+            );
+        }
+    }
+
+    for device in &devices {
+        logln!("PCI {device:?}");
+    }
+
+    devices
+}
@@ -23,34 +23,52 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use core::sync::atomic::AtomicBool;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
-use crate::locks::{LockEncouragement, RwCriticalLock, RwYieldLock};
+use crate::{
+    locks::{LockEncouragement, RwCriticalLock, RwYieldLock},
+    resources, timer, vmm,
+};
 use alloc::{
-    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet, vec_deque::VecDeque},
+    format,
     string::String,
     sync::{Arc, Weak},
     vec::Vec,
 };
 use boolvec::BoolVec;
 use elf::elf_owned::ElfOwned;
-use lignan::warnln;
+use futex::FutexTable;
+use lignan::{logln, warnln};
 use mem::{
     addr::VirtAddr,
-    page::VirtPage,
-    paging::VmPermissions,
-    vm::{VmFillAction, VmProcess, VmRegion},
+    page::{PhysPage, VirtPage},
+    paging::{CacheMode, PageMapping, VmOptions, VmPermissions},
+    vm::{CheckAddrResult, VmFillAction, VmObjectMappingError, VmProcess, VmRegion},
 };
-use vera_portal::{HandleUpdateKind, MapMemoryError, WaitSignal};
+use memory_layout::USER_MMAP;
+use pipe::{Pipe, PipeReader, PipeWriter};
+use run_queue::CpuSet;
 use scheduler::Scheduler;
+use shared::SharedMemory;
 use thread::{ThreadId, WeakThread};
-use util::consts::PAGE_1G;
+use tls::TlsTemplate;
+use vera_portal::{
+    ExitReason, FaultKind, HandleUpdateKind, MapMemoryError, WaitSignal, capabilities, rights,
+};
 use vm_elf::VmElfInject;
+use wait::WaitQueue;
 
+pub mod futex;
+pub mod pipe;
+pub mod run_queue;
 pub mod scheduler;
+pub mod shared;
 pub mod task;
 pub mod thread;
+mod tls;
 mod vm_elf;
+pub mod wait;
 
 pub type ProcessEntry = VirtAddr;
 pub type ProcessId = usize;
@@ -82,13 +100,69 @@ pub enum ProcessHandle {
         /// Id on the host
         id: u64,
     },
+    /// The read end of a pipe
+    PipeRead(PipeReader),
+    /// The write end of a pipe
+    PipeWrite(PipeWriter),
+    /// A shared memory region, which can be mapped with `shared_map`
+    SharedMemory(Arc<SharedMemory>),
     Disconnected,
 }
 
+/// Why a process stopped running
+#[derive(Debug, Clone)]
+pub enum ExitStatus {
+    /// The process called `exit`
+    Exited(ExitReason),
+    /// The process was terminated because of a cpu exception
+    Faulted(FaultKind),
+}
+
+/// What a process is allowed to do with one of its handles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandleRights(u64);
+
+impl HandleRights {
+    pub const READ: Self = Self(rights::READ);
+    pub const WRITE: Self = Self(rights::WRITE);
+    pub const MAP: Self = Self(rights::MAP);
+    pub const TRANSFER: Self = Self(rights::TRANSFER);
+    pub const DUPLICATE: Self = Self(rights::DUPLICATE);
+    pub const ALL: Self = Self(rights::ALL);
+
+    /// No rights, for looking at a handle without using it
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Get the rights from a user provided mask, or `None` if it has unknown bits set
+    pub const fn from_bits(bits: u64) -> Option<Self> {
+        if bits & !rights::ALL != 0 {
+            return None;
+        }
+
+        Some(Self(bits))
+    }
+
+    /// Check if every right in `other` is also in `self`
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The rights in either `self` or `other`
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
 #[derive(Debug)]
 pub struct ProcessHandleManager {
     id_alloc: BoolVec,
     handles: BTreeMap<u64, ProcessHandle>,
+    /// The rights of every live handle
+    rights: BTreeMap<u64, HandleRights>,
+    /// The connection each handle sent to this process with `handle_send` came over
+    origins: BTreeMap<u64, u64>,
 }
 
 impl ProcessHandleManager {
@@ -96,6 +170,8 @@ impl ProcessHandleManager {
         Self {
             id_alloc: BoolVec::new(),
             handles: BTreeMap::new(),
+            rights: BTreeMap::new(),
+            origins: BTreeMap::new(),
         }
     }
 
@@ -103,6 +179,7 @@ impl ProcessHandleManager {
     fn alloc_handle_id(&mut self) -> u64 {
         let id = self.id_alloc.find_first_of(false).unwrap_or(0);
         self.id_alloc.set(id, true);
+        self.rights.insert(id as u64, HandleRights::ALL);
 
         id as u64
     }
@@ -114,6 +191,17 @@ impl ProcessHandleManager {
             "Tried to free an invalid handle!"
         );
         self.id_alloc.set(handle as usize, false);
+        self.rights.remove(&handle);
+        self.origins.remove(&handle);
+    }
+
+    /// Check that `handle` exists and has all the `needed` rights
+    fn require_rights(&self, handle: u64, needed: HandleRights) -> Result<(), HandleError> {
+        match self.rights.get(&handle) {
+            Some(rights) if rights.contains(needed) => Ok(()),
+            Some(_) => Err(HandleError::PermissionDenied),
+            None => Err(HandleError::HandleDoesntExist(handle)),
+        }
     }
 
     /// Disconnect handle
@@ -138,6 +226,44 @@ impl ProcessHandleManager {
         id
     }
 
+    /// Insert an existing handle, returning its new id
+    fn insert_handle(&mut self, handle: ProcessHandle) -> u64 {
+        self.insert_handle_with_rights(handle, HandleRights::ALL)
+    }
+
+    /// Insert an existing handle with only `rights`, returning its new id
+    fn insert_handle_with_rights(&mut self, handle: ProcessHandle, rights: HandleRights) -> u64 {
+        let id = self.alloc_handle_id();
+        self.handles.insert(id, handle);
+        self.rights.insert(id, rights);
+
+        id
+    }
+
+    /// Find the process on the other end of the socket `handle`, and the socket's id there
+    fn peer_of(&self, handle: u64) -> Result<(RefProcess, u64), HandleError> {
+        let (peer, id) = match self.handles.get(&handle) {
+            Some(ProcessHandle::HostTwoWay { client, id, .. }) => (client, *id),
+            Some(ProcessHandle::ClientTwoWay { host, id }) => (host, *id),
+            Some(ProcessHandle::Disconnected) | None => {
+                return Err(HandleError::HandleDoesntExist(handle));
+            }
+            Some(_) => return Err(HandleError::InvalidSocketKind),
+        };
+
+        Ok((peer.upgrade().ok_or(HandleError::HostDisconnect)?, id))
+    }
+
+    /// Create a new pipe, returning the ids of its read and write ends
+    pub fn new_pipe_handles(&mut self) -> (u64, u64) {
+        let (reader, writer) = Pipe::create();
+
+        (
+            self.insert_handle(ProcessHandle::PipeRead(reader)),
+            self.insert_handle(ProcessHandle::PipeWrite(writer)),
+        )
+    }
+
     /// Create a new host and client handle pair
     fn new_handle_pair(owner: RefProcess, host_id: u64, client: RefProcess) -> (u64, u64) {
         let mut owner_process = owner.handles.write(LockEncouragement::Strong);
@@ -173,15 +299,12 @@ impl ProcessHandleManager {
             },
         );
 
-        owner
-            .signals
-            .write(LockEncouragement::Moderate)
-            .push_back(WaitSignal::HandleUpdate {
-                handle: host_id,
-                kind: HandleUpdateKind::NewConnection {
-                    new_handle: owner_id,
-                },
-            });
+        owner.push_signal(WaitSignal::HandleUpdate {
+            handle: host_id,
+            kind: HandleUpdateKind::NewConnection {
+                new_handle: owner_id,
+            },
+        });
 
         (owner_id, client_id)
     }
@@ -205,29 +328,81 @@ pub struct Process {
     /// The memory map of this process
     // FIXME: Need to convert `VmProcess` to not use locks
     vm: RwCriticalLock<VmProcess>,
+    /// The first page of every shared memory mapping, the only mappings that can be unmapped
+    shared_mappings: RwYieldLock<BTreeSet<usize>>,
     /// Has this process been killed, or exited?
     pub dead: AtomicBool,
     /// Signals for userspace
     signals: RwYieldLock<VecDeque<WaitSignal>>,
+    /// Threads waiting in `next_signal`
+    signal_waiters: WaitQueue,
+    /// Threads waiting in `handle_wait` for data on one of our connections
+    handle_waiters: WaitQueue,
+    /// The thread local storage image each new userspace thread gets a copy of
+    tls_template: RwYieldLock<Option<TlsTemplate>>,
+    /// The status this process exited with
+    exit_status: RwYieldLock<Option<ExitStatus>>,
+    /// The process to notify if this process faults
+    fault_handler: RwYieldLock<Option<WeakProcess>>,
+    /// The process that spawned this process, or `init` once that process exits
+    parent: RwYieldLock<WeakProcess>,
+    /// Child processes, kept alive until they are reaped with `wait_child`
+    children: RwYieldLock<BTreeMap<ProcessId, RefProcess>>,
+    /// Threads waiting for this process to exit
+    exit_waiters: WaitQueue,
+    /// Threads waiting on futex words in this process
+    pub futexes: FutexTable,
+    /// The privileged things this process can do, as a `vera_portal::capabilities` mask
+    capabilities: AtomicU64,
+    /// Are this process's syscalls being traced into the log ring?
+    #[cfg(feature = "syscall-trace")]
+    tracing: AtomicBool,
 }
 
 impl Process {
-    /// Create a new process
+    /// Create a new process, with every capability
     pub fn new(name: String) -> RefProcess {
-        let s = Scheduler::get();
-        let proc = Arc::new(Self {
-            id: s.alloc_pid(),
-            name,
-            threads: RwYieldLock::new(BTreeMap::new()),
-            thread_id_alloc: RwYieldLock::new(BoolVec::new()),
-            vm: RwCriticalLock::new(s.fork_kernel_vm()),
-            handles: RwYieldLock::new(ProcessHandleManager::new()),
-            dead: AtomicBool::new(false),
-            signals: RwYieldLock::new(VecDeque::new()),
-        });
-        s.register_new_process(proc.clone());
+        Self::new_with_parent(name, WeakProcess::new(), capabilities::ALL)
+    }
+
+    /// Create a new process owned by `parent`, with the `capabilities` it was given
+    pub fn new_child(name: String, parent: &RefProcess, capabilities: u64) -> RefProcess {
+        let child = Self::new_with_parent(name, Arc::downgrade(parent), capabilities);
+        parent
+            .children
+            .write(LockEncouragement::Moderate)
+            .insert(child.id, child.clone());
+
+        child
+    }
 
-        proc
+    fn new_with_parent(name: String, parent: WeakProcess, capabilities: u64) -> RefProcess {
+        let s = Scheduler::get();
+        s.register_new_process(|vm| {
+            Arc::new(Self {
+                id: s.alloc_pid(),
+                #[cfg(feature = "syscall-trace")]
+                tracing: AtomicBool::new(crate::syscall_trace::traced_at_spawn(&name)),
+                name,
+                threads: RwYieldLock::new(BTreeMap::new()),
+                thread_id_alloc: RwYieldLock::new(BoolVec::new()),
+                vm: RwCriticalLock::new(vm),
+                shared_mappings: RwYieldLock::new(BTreeSet::new()),
+                handles: RwYieldLock::new(ProcessHandleManager::new()),
+                dead: AtomicBool::new(false),
+                signals: RwYieldLock::new(VecDeque::new()),
+                signal_waiters: WaitQueue::new(),
+                handle_waiters: WaitQueue::new(),
+                tls_template: RwYieldLock::new(None),
+                exit_status: RwYieldLock::new(None),
+                fault_handler: RwYieldLock::new(None),
+                parent: RwYieldLock::new(parent),
+                children: RwYieldLock::new(BTreeMap::new()),
+                exit_waiters: WaitQueue::new(),
+                futexes: FutexTable::new(),
+                capabilities: AtomicU64::new(capabilities),
+            })
+        })
     }
 
     /// Add an ELF mapping to this process's memory map
@@ -251,9 +426,71 @@ impl Process {
             )
             .unwrap();
 
+        *self.tls_template.write(LockEncouragement::Weak) = TlsTemplate::from_elf(&elf.elf());
+
         elf.elf().entry_point().unwrap().into()
     }
 
+    /// Call `f` with every page mapped in `region` of this process's page tables
+    pub fn walk_mappings(&self, region: VmRegion, f: impl FnMut(PageMapping)) {
+        self.vm
+            .read(LockEncouragement::Weak)
+            .page_tables
+            .read()
+            .walk(region, f);
+    }
+
+    /// Find the page `virt` is mapped to in this process's page tables
+    pub fn translate(&self, virt: VirtAddr) -> Option<PageMapping> {
+        self.vm
+            .read(LockEncouragement::Weak)
+            .page_tables
+            .read()
+            .translate(virt)
+    }
+
+    /// Call `f` with every page of anonymous memory mapped in this process
+    pub fn movable_pages(&self, f: impl FnMut(VirtPage, PhysPage)) {
+        self.vm.read(LockEncouragement::Weak).movable_pages(f);
+    }
+
+    /// Move the anonymous page at `vpage` onto `new_page`, returning the page that used to
+    /// back it.
+    pub fn migrate_page(
+        &self,
+        vpage: VirtPage,
+        new_page: PhysPage,
+    ) -> Result<PhysPage, VmObjectMappingError> {
+        let vm_lock = self.vm.write();
+        vmm::migrate_page(&vm_lock, vpage, new_page)
+    }
+
+    /// Map pages the kernel mapped after this process was created
+    pub fn map_kernel_pages(
+        &self,
+        mappings: &BTreeMap<VirtPage, PhysPage>,
+        permissions: VmPermissions,
+        cache_mode: CacheMode,
+    ) -> Result<(), VmObjectMappingError> {
+        let vm_lock = self.vm.write();
+        let mut page_tables = vm_lock.page_tables.write();
+
+        for (vpage, ppage) in mappings.iter() {
+            page_tables
+                .correlate_page(
+                    *vpage,
+                    *ppage,
+                    VmOptions::none()
+                        .set_overwrite_flag(true)
+                        .set_cache_mode(cache_mode),
+                    permissions,
+                )
+                .map_err(VmObjectMappingError::MappingError)?;
+        }
+
+        Ok(())
+    }
+
     /// Add a new anonymous memory mapping
     pub fn map_anon(&self, region: VmRegion, perm: VmPermissions) {
         let mut vm_lock = self.vm.write();
@@ -263,6 +500,13 @@ impl Process {
             .unwrap();
     }
 
+    /// Check that all of `region` is mapped in this process with at least `perms`
+    pub fn check_region_perms(&self, region: &VmRegion, perms: VmPermissions) -> CheckAddrResult {
+        self.vm
+            .read(LockEncouragement::Weak)
+            .check_region_perms(region, perms)
+    }
+
     /// Add a new anonymous memory mapping for a size
     pub fn map_anon_anywhere(
         &self,
@@ -272,7 +516,10 @@ impl Process {
         let mut vm_lock = self.vm.write();
 
         let region = vm_lock
-            .find_vm_free(VirtPage::containing_addr(VirtAddr::new(PAGE_1G)), n_pages)
+            .find_vm_free(
+                VirtPage::containing_addr(VirtAddr::new(USER_MMAP.start)),
+                n_pages,
+            )
             .ok_or(MapMemoryError::OutOfMemory)?;
 
         vm_lock
@@ -308,12 +555,10 @@ impl Process {
             return;
         }
 
-        host.signals
-            .write(LockEncouragement::Moderate)
-            .push_back(WaitSignal::HandleUpdate {
-                handle,
-                kind: HandleUpdateKind::Disconnected,
-            });
+        host.push_signal(WaitSignal::HandleUpdate {
+            handle,
+            kind: HandleUpdateKind::Disconnected,
+        });
 
         match host
             .handles
@@ -340,10 +585,145 @@ impl Process {
                 }
             }
             ProcessHandle::ClientTwoWay { .. } => (),
+            // Dropping a pipe end closes it
+            ProcessHandle::PipeRead(_) | ProcessHandle::PipeWrite(_) => (),
+            // The region is freed once nothing else refers to it
+            ProcessHandle::SharedMemory(_) => (),
             ProcessHandle::Disconnected => (),
         }
     }
 
+    /// Create a new pipe, returning the ids of its read and write ends
+    pub fn new_pipe(&self) -> (u64, u64) {
+        self.handles
+            .write(LockEncouragement::Moderate)
+            .new_pipe_handles()
+    }
+
+    /// Give this process a handle to the shared memory region `shared`
+    pub fn new_shared_handle(&self, shared: Arc<SharedMemory>) -> u64 {
+        self.handles
+            .write(LockEncouragement::Moderate)
+            .insert_handle(ProcessHandle::SharedMemory(shared))
+    }
+
+    /// Get the shared memory region behind `handle`
+    pub fn shared_memory(
+        &self,
+        handle: u64,
+        needed: HandleRights,
+    ) -> Result<Arc<SharedMemory>, HandleError> {
+        let handle_lock = self.handles.read(LockEncouragement::Weak);
+
+        match handle_lock.handles.get(&handle) {
+            Some(ProcessHandle::SharedMemory(shared)) => {
+                handle_lock.require_rights(handle, needed)?;
+                Ok(shared.clone())
+            }
+            Some(ProcessHandle::Disconnected) | None => Err(HandleError::HandleDoesntExist(handle)),
+            Some(_) => Err(HandleError::InvalidSocketKind),
+        }
+    }
+
+    /// Move `handle` out of this process and into `to`, returning its id in `to`
+    ///
+    /// Only pipe ends and shared memory can be moved, since sockets are tied to the
+    /// process that connected them.
+    pub fn transfer_handle(&self, handle: u64, to: &Process) -> Result<u64, HandleError> {
+        let (moved, rights) = self.take_transferable(handle)?;
+
+        let new_handle = to
+            .handles
+            .write(LockEncouragement::Moderate)
+            .insert_handle_with_rights(moved, rights);
+
+        to.push_signal(WaitSignal::HandleUpdate {
+            handle: new_handle,
+            kind: HandleUpdateKind::Received { from: self.id },
+        });
+
+        Ok(new_handle)
+    }
+
+    /// Move `handle` to the process on the other end of the socket `connection`,
+    /// returning its id there
+    ///
+    /// Unlike [`Self::transfer_handle`] the receiver is not signaled, the sender is
+    /// expected to tell it the new id over `connection`. The receiver can check which
+    /// connection the handle came over with [`Self::handle_origin`].
+    pub fn send_handle(&self, handle: u64, connection: u64) -> Result<u64, HandleError> {
+        let (peer, peer_connection) = self
+            .handles
+            .read(LockEncouragement::Moderate)
+            .peer_of(connection)?;
+        let (moved, rights) = self.take_transferable(handle)?;
+
+        let mut peer_handles = peer.handles.write(LockEncouragement::Moderate);
+        let new_handle = peer_handles.insert_handle_with_rights(moved, rights);
+        peer_handles.origins.insert(new_handle, peer_connection);
+
+        Ok(new_handle)
+    }
+
+    /// The connection `handle` was sent to this process over with [`Self::send_handle`]
+    pub fn handle_origin(&self, handle: u64) -> Result<Option<u64>, HandleError> {
+        let handle_lock = self.handles.read(LockEncouragement::Weak);
+        if !handle_lock.rights.contains_key(&handle) {
+            return Err(HandleError::HandleDoesntExist(handle));
+        }
+
+        Ok(handle_lock.origins.get(&handle).copied())
+    }
+
+    /// Remove `handle` from this process so it can be given to another, if it is
+    /// allowed to move
+    fn take_transferable(&self, handle: u64) -> Result<(ProcessHandle, HandleRights), HandleError> {
+        let mut handle_lock = self.handles.write(LockEncouragement::Moderate);
+
+        match handle_lock.handles.get(&handle) {
+            Some(
+                ProcessHandle::PipeRead(_)
+                | ProcessHandle::PipeWrite(_)
+                | ProcessHandle::SharedMemory(_),
+            ) => (),
+            Some(ProcessHandle::Disconnected) | None => {
+                return Err(HandleError::HandleDoesntExist(handle));
+            }
+            Some(_) => return Err(HandleError::InvalidSocketKind),
+        }
+
+        handle_lock.require_rights(handle, HandleRights::TRANSFER)?;
+        let rights = handle_lock.rights[&handle];
+
+        handle_lock.dealloc_handle_id(handle);
+        Ok((handle_lock.handles.remove(&handle).unwrap(), rights))
+    }
+
+    /// Make a new handle to the same object as `handle`, with only `rights`
+    ///
+    /// The new handle can never have more rights than `handle` has, and only pipe ends
+    /// and shared memory can be duplicated.
+    pub fn duplicate_handle(&self, handle: u64, rights: HandleRights) -> Result<u64, HandleError> {
+        let mut handle_lock = self.handles.write(LockEncouragement::Moderate);
+
+        let duplicate = match handle_lock.handles.get(&handle) {
+            Some(ProcessHandle::PipeRead(reader)) => ProcessHandle::PipeRead(reader.clone()),
+            Some(ProcessHandle::PipeWrite(writer)) => ProcessHandle::PipeWrite(writer.clone()),
+            Some(ProcessHandle::SharedMemory(shared)) => {
+                ProcessHandle::SharedMemory(shared.clone())
+            }
+            Some(ProcessHandle::Disconnected) | None => {
+                return Err(HandleError::HandleDoesntExist(handle));
+            }
+            Some(_) => return Err(HandleError::InvalidSocketKind),
+        };
+
+        handle_lock.require_rights(handle, HandleRights::DUPLICATE)?;
+        handle_lock.require_rights(handle, rights)?;
+
+        Ok(handle_lock.insert_handle_with_rights(duplicate, rights))
+    }
+
     /// Create a new connection handle
     pub fn new_connection_handle(host: RefProcess, name: String) -> Option<u64> {
         let s = Scheduler::get();
@@ -393,6 +773,7 @@ impl Process {
     /// Send data over this socket
     pub fn handle_tx(&self, id: u64, data: &[u8]) -> Result<usize, HandleError> {
         let handle_lock = self.handles.read(LockEncouragement::Weak);
+        handle_lock.require_rights(id, HandleRights::WRITE)?;
 
         let Some(handle_info) = handle_lock.handles.get(&id) else {
             return Err(HandleError::HandleDoesntExist(id));
@@ -402,23 +783,33 @@ impl Process {
             ProcessHandle::HostTwoWay {
                 host_rx: _,
                 host_tx,
-                client: _,
+                client,
                 id: _,
             } => {
-                let mut tx_lock = host_tx.write(LockEncouragement::Moderate);
-                tx_lock.extend(data.iter());
+                host_tx
+                    .write(LockEncouragement::Moderate)
+                    .extend(data.iter());
+
+                if let Some(client) = client.upgrade() {
+                    client.handle_waiters.wake_all();
+                }
                 Ok(data.len())
             }
             ProcessHandle::ClientTwoWay { host, id } => {
                 let host = host.upgrade().ok_or(HandleError::HostDisconnect)?;
-                host.signals.write(LockEncouragement::Moderate).push_back(
-                    WaitSignal::HandleUpdate {
-                        handle: *id,
-                        kind: HandleUpdateKind::ReadReady,
-                    },
-                );
+                host.push_signal(WaitSignal::HandleUpdate {
+                    handle: *id,
+                    kind: HandleUpdateKind::ReadReady,
+                });
                 host.remote_tx(*id, data)
             }
+            ProcessHandle::PipeWrite(writer) => {
+                // Writing can block, so don't hold onto our handles while it does
+                let pipe = writer.pipe();
+                drop(handle_lock);
+
+                pipe.write(data)
+            }
             _ => Err(HandleError::InvalidSocketKind),
         }
     }
@@ -440,12 +831,10 @@ impl Process {
             } => {
                 let mut tx_lock = host_tx.write(LockEncouragement::Moderate);
                 if tx_lock.len() == 0 {
-                    self.signals.write(LockEncouragement::Moderate).push_back(
-                        WaitSignal::HandleUpdate {
-                            handle: id,
-                            kind: HandleUpdateKind::WriteReady,
-                        },
-                    );
+                    self.push_signal(WaitSignal::HandleUpdate {
+                        handle: id,
+                        kind: HandleUpdateKind::WriteReady,
+                    });
                     return Err(HandleError::WouldBlock);
                 }
 
@@ -468,6 +857,7 @@ impl Process {
     /// Recv data from this socket
     pub fn handle_rx(&self, id: u64, data: &mut [u8]) -> Result<usize, HandleError> {
         let handle_lock = self.handles.read(LockEncouragement::Weak);
+        handle_lock.require_rights(id, HandleRights::READ)?;
 
         let Some(handle_info) = handle_lock.handles.get(&id) else {
             return Err(HandleError::HandleDoesntExist(id));
@@ -487,13 +877,10 @@ impl Process {
                         //
                         // We should instead use a bitmap for flags and handles. If we set the `WriteReady`
                         // flag then we don't need to send another...
-                        client_upgrade
-                            .signals
-                            .write(LockEncouragement::Moderate)
-                            .push_back(WaitSignal::HandleUpdate {
-                                handle: *client_id,
-                                kind: HandleUpdateKind::WriteReady,
-                            });
+                        client_upgrade.push_signal(WaitSignal::HandleUpdate {
+                            handle: *client_id,
+                            kind: HandleUpdateKind::WriteReady,
+                        });
                     }
 
                     return Err(HandleError::WouldBlock);
@@ -515,22 +902,290 @@ impl Process {
                 let host = host.upgrade().ok_or(HandleError::HostDisconnect)?;
                 host.remote_rx(*id, data)
             }
+            ProcessHandle::PipeRead(reader) => {
+                // Reading can block, so don't hold onto our handles while it does
+                let pipe = reader.pipe();
+                drop(handle_lock);
+
+                pipe.read(data)
+            }
             _ => Err(HandleError::InvalidSocketKind),
         }
     }
 
-    /// Get the next wait signal for this process
-    pub fn next_signal(&self) -> WaitSignal {
+    /// Is there data to receive on the connection handle `id`?
+    ///
+    /// Once the other side is gone this is an error, after everything it sent was received.
+    fn handle_readable(&self, id: u64) -> Result<bool, HandleError> {
+        let handle_lock = self.handles.read(LockEncouragement::Weak);
+        handle_lock.require_rights(id, HandleRights::READ)?;
+
+        match handle_lock.handles.get(&id) {
+            Some(ProcessHandle::HostTwoWay {
+                host_rx, client, ..
+            }) => {
+                if !host_rx.read(LockEncouragement::Weak).is_empty() {
+                    return Ok(true);
+                }
+
+                match client.upgrade() {
+                    Some(client) if !client.dead.load(Ordering::SeqCst) => Ok(false),
+                    _ => Err(HandleError::BrokenPipe),
+                }
+            }
+            Some(ProcessHandle::ClientTwoWay { host, id }) => {
+                let host = host.upgrade().ok_or(HandleError::HostDisconnect)?;
+                let ready = host.remote_readable(*id)?;
+
+                if !ready && host.dead.load(Ordering::SeqCst) {
+                    return Err(HandleError::HostDisconnect);
+                }
+                Ok(ready)
+            }
+            Some(ProcessHandle::Disconnected) => Err(HandleError::HostDisconnect),
+            Some(_) => Err(HandleError::InvalidSocketKind),
+            None => Err(HandleError::HandleDoesntExist(id)),
+        }
+    }
+
+    /// Is there data the host sent on its handle `id` waiting to be received?
+    fn remote_readable(&self, id: u64) -> Result<bool, HandleError> {
+        match self.handles.read(LockEncouragement::Weak).handles.get(&id) {
+            Some(ProcessHandle::HostTwoWay { host_tx, .. }) => {
+                Ok(!host_tx.read(LockEncouragement::Weak).is_empty())
+            }
+            Some(ProcessHandle::Disconnected) => Err(HandleError::HostDisconnect),
+            Some(_) => Err(HandleError::InvalidSocketKind),
+            None => Err(HandleError::HandleDoesntExist(id)),
+        }
+    }
+
+    /// Block until there is data to receive on the connection handle `id`
+    ///
+    /// Returns false if the kernel reached tick `deadline` first.
+    pub fn handle_wait(&self, id: u64, deadline: Option<u64>) -> Result<bool, HandleError> {
         loop {
-            let len = { self.signals.read(LockEncouragement::Weak).len() };
-            if len == 0 {
-                Scheduler::yield_now();
+            if self.handle_readable(id)? {
+                return Ok(true);
+            }
+
+            let still_waiting = || matches!(self.handle_readable(id), Ok(false));
+            match deadline {
+                Some(deadline) if timer::kernel_ticks() >= deadline => return Ok(false),
+                Some(deadline) => self.handle_waiters.wait_once_until(deadline, still_waiting),
+                None => self.handle_waiters.wait_once(still_waiting),
+            }
+        }
+    }
+
+    /// Wake everything waiting on a connection with this process, so it sees us exit
+    fn wake_peers(&self) {
+        let handle_lock = self.handles.read(LockEncouragement::Weak);
+
+        for handle in handle_lock.handles.values() {
+            let peer = match handle {
+                ProcessHandle::HostTwoWay { client, .. } => client.upgrade(),
+                ProcessHandle::ClientTwoWay { host, .. } => host.upgrade(),
+                _ => None,
+            };
+
+            if let Some(peer) = peer {
+                peer.handle_waiters.wake_all();
+            }
+        }
+    }
+
+    /// Record why this process stopped running
+    ///
+    /// Only the first status is kept, a process can only exit once.
+    pub fn record_exit(&self, status: ExitStatus) {
+        {
+            let mut exit_status = self.exit_status.write(LockEncouragement::Strong);
+            if exit_status.is_some() {
+                return;
             }
 
+            *exit_status = Some(status);
+        }
+
+        self.dead.store(true, Ordering::SeqCst);
+
+        self.wake_peers();
+
+        // A compositor that exits or crashes hands the framebuffer back to the kernel
+        #[cfg(feature = "gfx")]
+        crate::gfx::release_framebuffer_on_exit(self);
+
+        // Nobody is left to wait on our children, so hand them to `init` to reap
+        let orphans = core::mem::take(&mut *self.children.write(LockEncouragement::Moderate));
+        match Scheduler::get().init_process() {
+            Some(init) if init.id != self.id => {
+                for orphan in orphans.into_values() {
+                    init.adopt(orphan);
+                }
+            }
+            // Children that are still running keep themselves alive through their threads
+            _ => drop(orphans),
+        }
+
+        if let Some(parent) = self.parent.read(LockEncouragement::Weak).upgrade() {
+            parent.push_signal(WaitSignal::ChildExit { pid: self.id });
+        }
+
+        self.exit_waiters.wake_all();
+    }
+
+    /// Make `orphan` a child of this process
+    ///
+    /// If `orphan` already exited this process is told right away, so it can be reaped.
+    fn adopt(self: &RefProcess, orphan: RefProcess) {
+        *orphan.parent.write(LockEncouragement::Moderate) = Arc::downgrade(self);
+        let exited = orphan.exit_status().is_some();
+
+        let pid = orphan.id;
+        self.children
+            .write(LockEncouragement::Moderate)
+            .insert(pid, orphan);
+
+        if exited {
+            self.push_signal(WaitSignal::ChildExit { pid });
+        }
+    }
+
+    /// Block until the child `pid` exits, then reap it
+    ///
+    /// Returns `None` if `pid` is not a child of this process.
+    pub fn wait_child(&self, pid: ProcessId) -> Option<ExitStatus> {
+        loop {
+            let child = self
+                .children
+                .read(LockEncouragement::Weak)
+                .get(&pid)
+                .cloned()?;
+
+            if let Some(status) = child.exit_status() {
+                self.children
+                    .write(LockEncouragement::Moderate)
+                    .remove(&pid);
+                return Some(status);
+            }
+
+            child
+                .exit_waiters
+                .wait_once(|| child.exit_status().is_none());
+        }
+    }
+
+    /// The `vera_portal::capabilities` this process has
+    pub fn capabilities(&self) -> u64 {
+        self.capabilities.load(Ordering::Acquire)
+    }
+
+    /// Can this process do the privileged things `capability` allows?
+    pub fn has_capability(&self, capability: u64) -> bool {
+        self.capabilities() & capability == capability
+    }
+
+    /// Get the child process `pid`, if it has not been reaped
+    pub fn child(&self, pid: ProcessId) -> Option<RefProcess> {
+        self.children
+            .read(LockEncouragement::Weak)
+            .get(&pid)
+            .cloned()
+    }
+
+    /// Get the status this process exited with
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.exit_status.read(LockEncouragement::Weak).clone()
+    }
+
+    /// The name this process's hardware resource claims are made under
+    pub fn resource_owner(&self) -> String {
+        format!("{} (pid {})", self.name, self.id)
+    }
+
+    /// Set the process to notify if this process faults
+    pub fn set_fault_handler(&self, handler: WeakProcess) {
+        *self.fault_handler.write(LockEncouragement::Weak) = Some(handler);
+    }
+
+    /// Record a cpu exception for this process, and notify its fault handler if one exists
+    pub fn fault(&self, fault: FaultKind) {
+        logln!("Process '{}' faulted ({:?})", self.name, fault);
+
+        let handler = self
+            .fault_handler
+            .read(LockEncouragement::Weak)
+            .as_ref()
+            .and_then(|handler| handler.upgrade());
+
+        if let Some(handler) = handler {
+            handler.push_signal(WaitSignal::ProcessFault {
+                pid: self.id,
+                fault: fault.clone(),
+            });
+        }
+
+        self.record_exit(ExitStatus::Faulted(fault));
+    }
+
+    /// Set the nice value of every thread in this process
+    pub fn set_nice(&self, nice: i8) {
+        self.threads
+            .read(LockEncouragement::Weak)
+            .values()
+            .filter_map(|thread| thread.upgrade())
+            .for_each(|thread| thread.set_nice(nice));
+    }
+
+    /// Start or stop tracing this process's syscalls into the log ring
+    #[cfg(feature = "syscall-trace")]
+    pub fn set_tracing(&self, enabled: bool) {
+        if enabled {
+            crate::syscall_trace::tracing_enabled();
+        }
+        self.tracing.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Are this process's syscalls being traced?
+    #[cfg(feature = "syscall-trace")]
+    pub fn is_tracing(&self) -> bool {
+        self.tracing.load(Ordering::Relaxed)
+    }
+
+    /// Limit every thread in this process to running on `cpus`
+    pub fn set_affinity(&self, cpus: CpuSet) {
+        self.threads
+            .read(LockEncouragement::Weak)
+            .values()
+            .filter_map(|thread| thread.upgrade())
+            .for_each(|thread| thread.set_affinity(cpus));
+    }
+
+    /// Queue a wait signal for this process, waking a thread waiting for one
+    pub fn push_signal(&self, signal: WaitSignal) {
+        let handle_update = matches!(signal, WaitSignal::HandleUpdate { .. });
+
+        self.signals
+            .write(LockEncouragement::Moderate)
+            .push_back(signal);
+        self.signal_waiters.wake_one();
+
+        if handle_update {
+            self.handle_waiters.wake_all();
+        }
+    }
+
+    /// Get the next wait signal for this process, blocking until there is one
+    pub fn next_signal(&self) -> WaitSignal {
+        loop {
             match self.signals.write(LockEncouragement::Strong).pop_front() {
                 Some(signal) => break signal,
                 None => (),
             }
+
+            self.signal_waiters
+                .wait_once(|| self.signals.read(LockEncouragement::Weak).is_empty());
         }
     }
 }
@@ -541,10 +1196,24 @@ pub enum HandleError {
     InvalidSocketKind,
     HostDisconnect,
     WouldBlock,
+    BrokenPipe,
+    /// The handle does not have the rights needed for this operation
+    PermissionDenied,
 }
 
 impl Drop for Process {
     fn drop(&mut self) {
+        match self.exit_status() {
+            Some(ExitStatus::Exited(reason)) => {
+                logln!("Process '{}' exited ({:?})", self.name, reason)
+            }
+            Some(ExitStatus::Faulted(fault)) => {
+                warnln!("Process '{}' was terminated ({:?})", self.name, fault)
+            }
+            None => (),
+        }
+
+        resources::release(&self.resource_owner());
         let s = Scheduler::get();
         s.remove_process(self);
     }
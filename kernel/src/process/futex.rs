@@ -0,0 +1,80 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::wait::WaitQueue;
+use crate::locks::ScheduleLock;
+use alloc::{collections::btree_map::BTreeMap, sync::Arc};
+
+/// The wait queues for every futex word a process is waiting on
+///
+/// Futexes are keyed by their virtual address, so they only work between threads of the
+/// same process.
+#[derive(Debug)]
+pub struct FutexTable {
+    queues: ScheduleLock<BTreeMap<usize, Arc<WaitQueue>>>,
+}
+
+impl FutexTable {
+    pub const fn new() -> Self {
+        Self {
+            queues: ScheduleLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Block the current thread on the futex at `addr`, unless `still_expected` returns
+    /// false once the thread is queued.
+    pub fn wait(&self, addr: usize, still_expected: impl FnOnce() -> bool) {
+        let queue = self
+            .queues
+            .lock()
+            .entry(addr)
+            .or_insert_with(|| Arc::new(WaitQueue::new()))
+            .clone();
+
+        queue.wait_once(still_expected);
+        self.cleanup(addr);
+    }
+
+    /// Wake up to `count` threads waiting on the futex at `addr`
+    pub fn wake(&self, addr: usize, count: usize) -> usize {
+        let Some(queue) = self.queues.lock().get(&addr).cloned() else {
+            return 0;
+        };
+
+        let woken = queue.wake(count);
+        self.cleanup(addr);
+
+        woken
+    }
+
+    /// Forget the queue for `addr` once nothing is waiting on it
+    fn cleanup(&self, addr: usize) {
+        let mut queues = self.queues.lock();
+
+        if queues.get(&addr).is_some_and(|queue| queue.is_empty()) {
+            queues.remove(&addr);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{HandleError, wait::WaitQueue};
+use crate::locks::ScheduleLock;
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The most bytes a pipe will hold before writers start to block.
+pub const PIPE_CAPACITY: usize = 4096;
+
+/// A bounded byte stream between a set of readers and writers.
+#[derive(Debug)]
+pub struct Pipe {
+    buffer: ScheduleLock<VecDeque<u8>>,
+    /// How many open read handles this pipe has
+    readers: AtomicUsize,
+    /// How many open write handles this pipe has
+    writers: AtomicUsize,
+    /// Readers waiting for bytes, or for every writer to close
+    readable: WaitQueue,
+    /// Writers waiting for space, or for every reader to close
+    writable: WaitQueue,
+}
+
+/// The read end of a pipe, the pipe sees EOF once all of these are dropped.
+#[derive(Debug)]
+pub struct PipeReader(Arc<Pipe>);
+
+/// The write end of a pipe, writes fail once all readers are dropped.
+#[derive(Debug)]
+pub struct PipeWriter(Arc<Pipe>);
+
+impl Pipe {
+    /// Create a new pipe, and get both of its ends.
+    pub fn create() -> (PipeReader, PipeWriter) {
+        let pipe = Arc::new(Self {
+            buffer: ScheduleLock::new(VecDeque::with_capacity(PIPE_CAPACITY)),
+            readers: AtomicUsize::new(1),
+            writers: AtomicUsize::new(1),
+            readable: WaitQueue::new(),
+            writable: WaitQueue::new(),
+        });
+
+        (PipeReader(pipe.clone()), PipeWriter(pipe))
+    }
+
+    /// Block until there are bytes to read, then read as many as fit in `data`.
+    ///
+    /// Returns `Ok(0)` once the pipe is empty and every writer has closed.
+    pub fn read(&self, data: &mut [u8]) -> Result<usize, HandleError> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            // Check for writers before taking the buffer, otherwise a writer could fill
+            // the buffer and close between us seeing it empty and seeing it closed.
+            let closed = self.writers.load(Ordering::Acquire) == 0;
+
+            {
+                let mut buffer = self.buffer.lock();
+                if !buffer.is_empty() {
+                    let bytes = data.len().min(buffer.len());
+                    for (entry_mut, byte) in data.iter_mut().zip(buffer.drain(..bytes)) {
+                        *entry_mut = byte;
+                    }
+
+                    // Pass on what we didn't read to the next reader
+                    let leftover = !buffer.is_empty();
+                    drop(buffer);
+
+                    self.writable.wake_one();
+                    if leftover {
+                        self.readable.wake_one();
+                    }
+
+                    return Ok(bytes);
+                }
+            }
+
+            if closed {
+                return Ok(0);
+            }
+
+            self.readable.wait_once(|| {
+                self.buffer.lock().is_empty() && self.writers.load(Ordering::Acquire) != 0
+            });
+        }
+    }
+
+    /// Block until all of `data` has been written into the pipe.
+    ///
+    /// If every reader closes before everything is written, this returns how many
+    /// bytes made it into the pipe, or `BrokenPipe` if none did.
+    pub fn write(&self, data: &[u8]) -> Result<usize, HandleError> {
+        let mut written = 0;
+
+        while written < data.len() {
+            if self.readers.load(Ordering::Acquire) == 0 {
+                return match written {
+                    0 => Err(HandleError::BrokenPipe),
+                    written => Ok(written),
+                };
+            }
+
+            let bytes = {
+                let mut buffer = self.buffer.lock();
+                let space = PIPE_CAPACITY - buffer.len();
+                let bytes = space.min(data.len() - written);
+
+                buffer.extend(&data[written..written + bytes]);
+                bytes
+            };
+            written += bytes;
+
+            if bytes != 0 {
+                self.readable.wake_one();
+            }
+
+            if written < data.len() {
+                self.writable.wait_once(|| {
+                    self.buffer.lock().len() == PIPE_CAPACITY
+                        && self.readers.load(Ordering::Acquire) != 0
+                });
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+impl PipeReader {
+    /// Get the pipe this end reads from.
+    pub fn pipe(&self) -> Arc<Pipe> {
+        self.0.clone()
+    }
+}
+
+impl PipeWriter {
+    /// Get the pipe this end writes to.
+    pub fn pipe(&self) -> Arc<Pipe> {
+        self.0.clone()
+    }
+}
+
+impl Clone for PipeReader {
+    fn clone(&self) -> Self {
+        self.0.readers.fetch_add(1, Ordering::AcqRel);
+        Self(self.0.clone())
+    }
+}
+
+impl Clone for PipeWriter {
+    fn clone(&self) -> Self {
+        self.0.writers.fetch_add(1, Ordering::AcqRel);
+        Self(self.0.clone())
+    }
+}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        if self.0.readers.fetch_sub(1, Ordering::Release) == 1 {
+            // Every writer needs to see the pipe is broken
+            self.0.writable.wake_all();
+        }
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        if self.0.writers.fetch_sub(1, Ordering::Release) == 1 {
+            // Every reader needs to see the end of the pipe
+            self.0.readable.wake_all();
+        }
+    }
+}
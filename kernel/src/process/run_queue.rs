@@ -0,0 +1,177 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Per-cpu run queues.
+//!
+//! Each cpu picks threads from its own queue, so cpus only touch each other's queues when
+//! one runs dry and steals work, or when the scheduler rebalances them.
+
+use super::thread::{RefThread, WeakThread};
+use crate::locks::ScheduleLock;
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+/// The most cpus threads can be scheduled on, as a [`CpuSet`] has a bit for each of them
+pub const MAX_CPUS: usize = 64;
+
+/// A set of cpus, used to limit which cpus a thread is allowed to run on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuSet(u64);
+
+impl CpuSet {
+    /// Every cpu
+    pub const ALL: Self = Self(u64::MAX);
+
+    /// Make a set where bit `n` is cpu `n`
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Get the bits of this set, where bit `n` is cpu `n`
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Is `cpu` within this set?
+    pub const fn contains(self, cpu: usize) -> bool {
+        cpu < MAX_CPUS && self.0 & (1 << cpu) != 0
+    }
+
+    /// Only keep the first `cpu_count` cpus in this set
+    pub const fn limit(self, cpu_count: usize) -> Self {
+        if cpu_count >= MAX_CPUS {
+            self
+        } else {
+            Self(self.0 & ((1 << cpu_count) - 1))
+        }
+    }
+
+    /// Does this set contain no cpus?
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// A priority queue item with a weak reference to its owned thread
+#[derive(Debug)]
+struct ScheduleItem {
+    priority: isize,
+    thread: WeakThread,
+}
+
+impl Eq for ScheduleItem {}
+impl PartialEq for ScheduleItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl PartialOrd for ScheduleItem {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.priority.cmp(&other.priority))
+    }
+}
+
+impl Ord for ScheduleItem {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.partial_cmp(other).unwrap()
+    }
+}
+
+/// The scheduling state of one cpu
+#[derive(Debug)]
+pub struct RunQueue {
+    /// The cpu this queue feeds
+    pub cpu_id: usize,
+    /// Weak references to threads waiting to run on this cpu
+    queue: ScheduleLock<VecDeque<ScheduleItem>>,
+    /// The thread this cpu is running
+    pub running: ScheduleLock<Option<RefThread>>,
+}
+
+impl RunQueue {
+    pub const fn new(cpu_id: usize) -> Self {
+        Self {
+            cpu_id,
+            queue: ScheduleLock::new(VecDeque::new()),
+            running: ScheduleLock::new(None),
+        }
+    }
+
+    /// Queue `thread` to run on this cpu
+    pub fn push(&self, thread: &RefThread) {
+        self.queue.lock().push_back(ScheduleItem {
+            priority: thread.priority(),
+            thread: Arc::downgrade(thread),
+        });
+    }
+
+    /// Take the next thread that can still run from the front of this queue
+    pub fn pop(&self) -> Option<RefThread> {
+        let mut queue = self.queue.lock();
+
+        while let Some(item) = queue.pop_front() {
+            match item.thread.upgrade() {
+                Some(thread) if !thread.is_zombie() => return Some(thread),
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    /// Take a thread that is allowed to run on `cpu` from the back of this queue.
+    ///
+    /// Threads at the back waited the least, so they are the cheapest to move.
+    pub fn steal(&self, cpu: usize) -> Option<RefThread> {
+        let mut queue = self.queue.lock();
+
+        let index = queue.iter().rposition(|item| {
+            item.thread
+                .upgrade()
+                .is_some_and(|thread| !thread.is_zombie() && thread.affinity().contains(cpu))
+        })?;
+
+        queue.remove(index)?.thread.upgrade()
+    }
+
+    /// Could `cpu` steal a thread from this queue?
+    pub fn can_steal(&self, cpu: usize) -> bool {
+        self.queue.lock().iter().any(|item| {
+            item.thread
+                .upgrade()
+                .is_some_and(|thread| !thread.is_zombie() && thread.affinity().contains(cpu))
+        })
+    }
+
+    /// The number of threads waiting on this queue
+    pub fn len(&self) -> usize {
+        self.queue.lock().len()
+    }
+
+    /// Is no thread waiting on this queue?
+    pub fn is_empty(&self) -> bool {
+        self.queue.lock().is_empty()
+    }
+}
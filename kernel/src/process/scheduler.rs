@@ -30,61 +30,60 @@ use core::{
 
 use super::{
     Process, ProcessId, RefProcess, WeakProcess,
+    run_queue::RunQueue,
     task::Task,
-    thread::{RefThread, WeakThread},
+    thread::{RefThread, ThreadState, WeakThread},
 };
+#[cfg(feature = "lock-debug")]
+use crate::backtrace::Backtrace;
 use crate::{
+    idle,
     locks::{
         AcquiredLock, LockEncouragement, LockId, ScheduleLock, current_scheduler_locks,
         manual_schedule_lock, manual_schedule_unlock,
     },
     process::thread::Thread,
+    processor,
+    timer::kernel_ticks,
 };
 use alloc::{
-    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    collections::btree_map::BTreeMap,
     string::String,
     sync::{Arc, Weak},
     vec::Vec,
 };
+use arch::supports::{CpuFeature, does_cpu_support};
 use boolvec::BoolVec;
 use elf::elf_owned::ElfOwned;
-use lignan::{current_debug_locks, log, logln};
+use lignan::{current_debug_locks, log, logln, warnln};
 use mem::{
     addr::{PhysAddr, VirtAddr},
     page::{PhysPage, VirtPage},
-    paging::{VmPermissions, bootloader_convert_phys},
+    paging::{CacheMode, PageMapping, VmPermissions, bootloader_convert_phys},
     virt2phys::{PhysPtrTranslationError, set_global_lookup_fn, virt2phys},
-    vm::{PageFaultInfo, PageFaultReponse, VmProcess, VmRegion, set_page_fault_handler},
+    vm::{
+        InsertVmObjectError, NewVmObjectError, PageFaultInfo, PageFaultReponse, VmProcess,
+        VmRegion, set_page_fault_handler,
+    },
 };
 use tar::Tar;
 use util::consts::PAGE_4K;
 
 const VERBOSE_LOGING: bool = false;
-
-/// A priority queue item with a weak reference to its owned thread
-#[derive(Debug)]
-struct ScheduleItem {
-    priority: isize,
-    thread: WeakThread,
-}
-
-impl Eq for ScheduleItem {}
-impl PartialEq for ScheduleItem {
-    fn eq(&self, other: &Self) -> bool {
-        self.priority == other.priority
-    }
-}
-
-impl PartialOrd for ScheduleItem {
-    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
-        Some(self.priority.cmp(&other.priority))
-    }
-}
-
-impl Ord for ScheduleItem {
-    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
-    }
+/// How often the run queues are rebalanced
+const BALANCE_INTERVAL_TICKS: u64 = 100;
+/// The initfs program started instead of every program, if the initfs has one
+const INIT_PROGRAM: &str = "init";
+
+/// Why the current thread is being switched out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SwitchReason {
+    /// The thread still wants to run, put it back into the picking queue
+    Yield,
+    /// The thread is not scheduled until the kernel reaches this tick
+    Sleep(u64),
+    /// The thread is not scheduled until something wakes it
+    Block,
 }
 
 type NoDropAquiredLockId = usize;
@@ -103,6 +102,12 @@ pub struct LockHoldings {
     aquired_id_alloc: BoolVec,
     aquired_map: BTreeMap<NoDropAquiredLockId, NoDropLockId>,
     id_map: BTreeMap<NoDropLockId, LockIdInfo>,
+    /// Every `(held, taken)` pair of locks seen, with where `taken` was first locked
+    #[cfg(feature = "lock-debug")]
+    lock_order: BTreeMap<(NoDropLockId, NoDropLockId), Backtrace>,
+    /// Where each held lock was locked
+    #[cfg(feature = "lock-debug")]
+    aquired_at: BTreeMap<NoDropAquiredLockId, Backtrace>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -124,6 +129,10 @@ impl LockHoldings {
             aquired_id_alloc: BoolVec::new(),
             aquired_map: BTreeMap::new(),
             id_map: BTreeMap::new(),
+            #[cfg(feature = "lock-debug")]
+            lock_order: BTreeMap::new(),
+            #[cfg(feature = "lock-debug")]
+            aquired_at: BTreeMap::new(),
         }
     }
 
@@ -181,6 +190,58 @@ impl LockHoldings {
         );
         self.lock_id_alloc.set(lock_id.0, false);
         self.id_map.remove(&lock_id.0);
+
+        // Lock ids are reused, so forget the order this one was taken in
+        #[cfg(feature = "lock-debug")]
+        self.lock_order
+            .retain(|&(held, taken), _| held != lock_id.0 && taken != lock_id.0);
+    }
+
+    /// Panic if taking `lock_id` while holding this thread's other locks would invert the
+    /// order they were taken in before, otherwise remember this order.
+    #[cfg(feature = "lock-debug")]
+    fn check_lock_order(&mut self, current_thread: &WeakThread, lock_id: NoDropLockId) {
+        let backtrace = Backtrace::capture();
+        let held_locks: Vec<NoDropLockId> = self
+            .id_map
+            .iter()
+            .filter(|&(&id, info)| {
+                id != lock_id
+                    && info
+                        .lock_map
+                        .values()
+                        .any(|(_, _, weak_thread)| weak_thread.ptr_eq(current_thread))
+            })
+            .map(|(&id, _)| id)
+            .collect();
+
+        for held_lock in held_locks {
+            if let Some(previous) = self.lock_order.get(&(lock_id, held_lock)) {
+                panic!(
+                    "Lock order inversion! Lock {lock_id} is being taken while holding lock {held_lock}:\n{backtrace}but lock {held_lock} was previously taken while holding lock {lock_id}:\n{previous}"
+                );
+            }
+
+            self.lock_order
+                .entry((held_lock, lock_id))
+                .or_insert(backtrace);
+        }
+    }
+
+    /// Where `current_thread` locked each of its holdings of `lock_id`
+    #[cfg(feature = "lock-debug")]
+    pub fn aquired_backtraces(
+        &self,
+        current_thread: &WeakThread,
+        lock_id: &LockId,
+    ) -> Vec<Backtrace> {
+        self.id_map
+            .get(&lock_id.0)
+            .into_iter()
+            .flat_map(|info| info.lock_map.iter())
+            .filter(|(_, (_, _, weak_thread))| weak_thread.ptr_eq(current_thread))
+            .filter_map(|(aquired_id, _)| self.aquired_at.get(aquired_id).copied())
+            .collect()
     }
 
     pub fn try_lock_exclusive(
@@ -189,6 +250,9 @@ impl LockHoldings {
         lock_id: &LockId,
         encouragement: LockEncouragement,
     ) -> Result<AcquiredLock, LockError> {
+        #[cfg(feature = "lock-debug")]
+        self.check_lock_order(&current_thread, lock_id.0);
+
         let lock_id_info = self
             .id_map
             .get_mut(&lock_id.0)
@@ -204,6 +268,9 @@ impl LockHoldings {
             lock_id_info
                 .lock_map
                 .insert(new_aquired_lock_id, (true, encouragement, current_thread));
+            #[cfg(feature = "lock-debug")]
+            self.aquired_at
+                .insert(new_aquired_lock_id, Backtrace::capture());
 
             return Ok(AcquiredLock(new_aquired_lock_id));
         }
@@ -227,6 +294,9 @@ impl LockHoldings {
         lock_id: &LockId,
         encouragement: LockEncouragement,
     ) -> Result<AcquiredLock, LockError> {
+        #[cfg(feature = "lock-debug")]
+        self.check_lock_order(&current_thread, lock_id.0);
+
         let lock_id_info = self
             .id_map
             .get_mut(&lock_id.0)
@@ -242,6 +312,9 @@ impl LockHoldings {
             lock_id_info
                 .lock_map
                 .insert(new_aquired_lock_id, (false, encouragement, current_thread));
+            #[cfg(feature = "lock-debug")]
+            self.aquired_at
+                .insert(new_aquired_lock_id, Backtrace::capture());
 
             return Ok(AcquiredLock(new_aquired_lock_id));
         }
@@ -287,6 +360,8 @@ impl LockHoldings {
         }
 
         self.aquired_id_alloc.set(lock.0, false);
+        #[cfg(feature = "lock-debug")]
+        self.aquired_at.remove(&lock.0);
     }
 }
 
@@ -307,16 +382,20 @@ pub struct Scheduler {
     thread_list: ScheduleLock<Vec<RefThread>>,
     /// An allocation bitmap of PIDs
     pid_alloc: ScheduleLock<BoolVec>,
-    /// Weak references to queued threads
-    picking_queue: ScheduleLock<VecDeque<ScheduleItem>>,
-    /// The currently running thread
-    running: ScheduleLock<Option<RefThread>>,
+    /// The threads waiting on, and running on, each cpu
+    run_queues: Vec<RunQueue>,
     /// The currently held locks for processes and threads
     held_locks: ScheduleLock<LockHoldings>,
     /// Kernel Memory Map
     kernel_vm: ScheduleLock<VmProcess>,
     /// Handle Servers
     pub serve_sockets: ScheduleLock<BTreeMap<String, (WeakProcess, u64)>>,
+    /// The initfs region processes can be spawned from
+    initfs: ScheduleLock<Option<VmRegion>>,
+    /// The first userspace process, which orphaned processes are handed to
+    init: ScheduleLock<Option<WeakProcess>>,
+    /// Threads that are not scheduled until the kernel reaches their wake tick
+    sleeping: ScheduleLock<Vec<(u64, WeakThread)>>,
 }
 
 impl Scheduler {
@@ -332,13 +411,15 @@ impl Scheduler {
             logln!("Scheduler Init...");
             let new_scheduler = Arc::new(Self {
                 process_list: ScheduleLock::new(BTreeMap::new()),
-                picking_queue: ScheduleLock::new(VecDeque::new()),
-                running: ScheduleLock::new(None),
+                run_queues: (0..processor::cpu_count()).map(RunQueue::new).collect(),
                 held_locks: ScheduleLock::new(LockHoldings::new()),
                 kernel_vm: ScheduleLock::new(VmProcess::new()),
                 pid_alloc: ScheduleLock::new(BoolVec::new()),
                 thread_list: ScheduleLock::new(Vec::new()),
                 serve_sockets: ScheduleLock::new(BTreeMap::new()),
+                initfs: ScheduleLock::new(None),
+                init: ScheduleLock::new(None),
+                sleeping: ScheduleLock::new(Vec::new()),
             });
 
             set_page_fault_handler(page_fault_handler);
@@ -371,7 +452,12 @@ impl Scheduler {
                 );
             }
             kernel_vm
-                .manual_inplace_new_vmobject(region, permissions, kernel_mappings)
+                .manual_inplace_new_vmobject(
+                    region,
+                    permissions,
+                    kernel_mappings,
+                    CacheMode::WriteBack,
+                )
                 .expect("Unable to map kernel exe region");
         };
 
@@ -382,13 +468,48 @@ impl Scheduler {
         map_vm_object(kernel_heap, VmPermissions::SYS_RW);
         map_vm_object(kernel_stack, VmPermissions::SYS_RW);
         map_vm_object(initfs, VmPermissions::SYS_R);
+
+        // The heap is touched constantly, so it's worth fewer TLB entries
+        let huge_pages = kernel_vm
+            .page_tables
+            .write()
+            .promote_huge_pages(kernel_heap, does_cpu_support(CpuFeature::SupportsPage1G));
+
         unsafe { kernel_vm.page_tables.read().load() }.unwrap();
-        logln!("OK ({mapping_counter})");
+        logln!("OK ({mapping_counter}, {huge_pages} huge)");
+    }
+
+    /// Manually map physical pages into the kernel's memory map
+    ///
+    /// Processes copy the kernel's page tables when they are created, so only processes
+    /// created after this call will see this mapping.
+    pub fn map_kernel_region(
+        &self,
+        region: VmRegion,
+        permissions: VmPermissions,
+        mappings: BTreeMap<VirtPage, PhysPage>,
+        cache_mode: CacheMode,
+    ) -> Result<(), InsertVmObjectError> {
+        let mut kernel_vm = self.kernel_vm.lock();
+        kernel_vm.manual_inplace_new_vmobject(region, permissions, mappings.clone(), cache_mode)?;
+
+        // Processes copy the kernel's page tables when they are created, so the ones that
+        // already exist need the new pages too. The kernel's map stays locked, so a process
+        // being registered right now either copied them or is already in the list.
+        for process in self.processes() {
+            process
+                .map_kernel_pages(&mappings, permissions, cache_mode)
+                .map_err(|err| {
+                    InsertVmObjectError::VmObjectError(NewVmObjectError::MappingErr(err))
+                })?;
+        }
+
+        Ok(())
     }
 
-    /// Clone the `VmProcess` instance of the kernel's memory map
-    pub fn fork_kernel_vm(&self) -> VmProcess {
-        VmProcess::inhearit_page_tables(&self.kernel_vm.lock().page_tables.read())
+    /// Call `f` with every page mapped in `region` of the kernel's memory map
+    pub fn walk_kernel_mappings(&self, region: VmRegion, f: impl FnMut(PageMapping)) {
+        self.kernel_vm.lock().page_tables.read().walk(region, f);
     }
 
     /// Create a new PID
@@ -403,8 +524,17 @@ impl Scheduler {
         bit_index
     }
 
-    /// Add a process to the process mapping
-    pub fn register_new_process(&self, p: RefProcess) {
+    /// Create a process with `new`, giving it a copy of the kernel's memory map, and add it
+    /// to the process mapping.
+    ///
+    /// The kernel's memory map is locked until the process is in the mapping, so it can't
+    /// miss pages from [`Self::map_kernel_region`].
+    pub fn register_new_process(&self, new: impl FnOnce(VmProcess) -> RefProcess) -> RefProcess {
+        let kernel_vm = self.kernel_vm.lock();
+        let p = new(VmProcess::inhearit_page_tables(
+            &kernel_vm.page_tables.read(),
+        ));
+
         if VERBOSE_LOGING {
             logln!("Spawn Process '{}' (pid='{}')", p.name, p.id);
         }
@@ -416,6 +546,8 @@ impl Scheduler {
                 );
             }
         }
+
+        p
     }
 
     /// Register a new thread
@@ -431,15 +563,46 @@ impl Scheduler {
         }
 
         self.thread_list.lock().push(t.clone());
-        self.picking_queue.lock().push_back(ScheduleItem {
-            priority: 0,
-            thread: Arc::downgrade(&t),
-        });
+        self.enqueue(&t);
+    }
+
+    /// Get this cpu's run queue
+    fn local_queue(&self) -> &RunQueue {
+        &self.run_queues[processor::processor_local().cpu_id]
+    }
+
+    /// Queue `thread` to run on one of the cpus it is allowed to run on.
+    ///
+    /// Threads go back to the cpu they last ran on while they are allowed to, as its caches
+    /// are likely still warm, otherwise they go to the allowed cpu with the least work.
+    fn enqueue(&self, thread: &RefThread) {
+        let affinity = thread.affinity();
+        let last_cpu = thread.last_cpu();
+
+        let queue = if affinity.contains(last_cpu) && last_cpu < self.run_queues.len() {
+            &self.run_queues[last_cpu]
+        } else {
+            self.run_queues
+                .iter()
+                .filter(|queue| affinity.contains(queue.cpu_id))
+                .min_by_key(|queue| queue.len())
+                .unwrap_or(self.local_queue())
+        };
+
+        queue.push(thread);
+        self.notify_cpu(queue.cpu_id);
+    }
+
+    /// Let `cpu` know it has new work
+    fn notify_cpu(&self, _cpu: usize) {
+        // Cpus idling in `mwait` are woken by this, but ones in `hlt` need an IPI
+        // FIXME: Send `cpu` a reschedule IPI once we support SMP
+        idle::kick();
     }
 
     /// Get the currently running thread
     pub fn current_thread(&self) -> WeakThread {
-        match &*self.running.lock() {
+        match &*self.local_queue().running.lock() {
             Some(thread) => Arc::downgrade(thread),
             None => WeakThread::new(),
         }
@@ -464,19 +627,61 @@ impl Scheduler {
     }
 
     /// Returns the next process that should execute
+    ///
+    /// If this cpu's queue is empty, a thread is stolen from the busiest cpu.
     fn next(&self) -> RefThread {
+        let local = self.local_queue();
+
         loop {
-            match self
-                .picking_queue
-                .lock()
-                .pop_front()
-                .expect("No active threads to schedule")
-                .thread
-                .upgrade()
-            {
-                Some(thread) => break thread,
-                None => (),
+            let thread = local
+                .pop()
+                .or_else(|| self.steal_for(local.cpu_id))
+                .expect("No active threads to schedule");
+
+            // Its affinity changed while it was queued here
+            if !thread.affinity().contains(local.cpu_id) {
+                self.enqueue(&thread);
+                continue;
+            }
+
+            thread.set_state(ThreadState::Running);
+            thread.set_last_cpu(local.cpu_id);
+            break thread;
+        }
+    }
+
+    /// Take a thread that can run on `cpu` from the busiest other cpu
+    fn steal_for(&self, cpu: usize) -> Option<RefThread> {
+        let mut victims: Vec<&RunQueue> = self
+            .run_queues
+            .iter()
+            .filter(|queue| queue.cpu_id != cpu)
+            .collect();
+        victims.sort_unstable_by_key(|queue| core::cmp::Reverse(queue.len()));
+
+        victims.into_iter().find_map(|queue| queue.steal(cpu))
+    }
+
+    /// Move threads from the busiest cpus to the least busy ones, until no cpu has more
+    /// than one thread more waiting than another.
+    fn balance(&self) {
+        loop {
+            let (Some(busiest), Some(idlest)) = (
+                self.run_queues.iter().max_by_key(|queue| queue.len()),
+                self.run_queues.iter().min_by_key(|queue| queue.len()),
+            ) else {
+                return;
+            };
+
+            if busiest.len() <= idlest.len() + 1 {
+                return;
             }
+
+            let Some(thread) = busiest.steal(idlest.cpu_id) else {
+                return;
+            };
+            idlest.push(&thread);
+            self.notify_cpu(idlest.cpu_id);
         }
     }
 
@@ -490,7 +695,20 @@ impl Scheduler {
         }
 
         let s = Scheduler::get();
-        let running_lock = s.running.lock();
+        let now = kernel_ticks();
+        s.wake_sleeping(now);
+        #[cfg(feature = "log-ring")]
+        crate::log_ring::wake_logger();
+        crate::events::tick(now);
+        crate::timer::tick(now);
+        #[cfg(feature = "acpi")]
+        crate::acpi::tick(now);
+
+        if now % BALANCE_INTERVAL_TICKS == 0 {
+            s.balance();
+        }
+
+        let running_lock = s.local_queue().running.lock();
         let skipped_ticks = SKIPPED_TICKS.swap(0, Ordering::SeqCst);
 
         // If we are not running a thread, we don't care to about skipped ticks
@@ -498,7 +716,8 @@ impl Scheduler {
             return;
         };
 
-        if running_thread.thread_tick(skipped_ticks) {
+        // This tick was not skipped, so it counts too
+        if running_thread.thread_tick(skipped_ticks + 1) {
             drop(running_lock);
             drop(s);
 
@@ -506,26 +725,132 @@ impl Scheduler {
         }
     }
 
+    /// Check if any thread is waiting to be scheduled on this cpu
+    pub fn has_ready_threads(&self) -> bool {
+        let local = self.local_queue();
+
+        !local.is_empty()
+            || self
+                .run_queues
+                .iter()
+                .any(|queue| queue.cpu_id != local.cpu_id && queue.can_steal(local.cpu_id))
+    }
+
+    /// Move all sleeping threads that should be awake by `tick` back into the picking queue
+    fn wake_sleeping(&self, tick: u64) {
+        let mut sleeping = self.sleeping.lock();
+        if sleeping.is_empty() {
+            return;
+        }
+
+        sleeping.retain(|(wake_tick, thread)| {
+            if *wake_tick > tick {
+                return true;
+            }
+
+            if let Some(thread) = thread.upgrade() {
+                if thread.transition(ThreadState::Sleeping, ThreadState::Ready) {
+                    self.enqueue(&thread);
+                }
+            }
+            false
+        });
+    }
+
+    /// Make a blocked `thread` ready to run again
+    ///
+    /// Returns false if the thread was not blocked. A thread that is woken before it has
+    /// finished blocking will see that it was woken, and keep running instead.
+    pub fn wake(&self, thread: &RefThread) -> bool {
+        let running_locks: Vec<_> = self
+            .run_queues
+            .iter()
+            .map(|queue| queue.running.lock())
+            .collect();
+        if !thread.transition(ThreadState::Blocked, ThreadState::Ready) {
+            return false;
+        }
+
+        let is_running = running_locks.iter().any(|running| {
+            running
+                .as_ref()
+                .is_some_and(|running| Arc::ptr_eq(running, thread))
+        });
+        if !is_running {
+            self.enqueue(thread);
+        }
+
+        true
+    }
+
+    /// Suspend the current thread until the kernel reaches `wake_tick`
+    ///
+    /// The thread is not scheduled while it sleeps. If there is nothing else to run,
+    /// this will keep yielding until the tick is reached.
+    pub fn sleep_until(wake_tick: u64) {
+        while kernel_ticks() < wake_tick {
+            Self::switch_out(SwitchReason::Sleep(wake_tick));
+        }
+    }
+
     /// Yield the current thread (If possible)
     pub fn yield_now() {
+        Self::switch_out(SwitchReason::Yield);
+    }
+
+    /// Stop scheduling the current thread until it is woken with `wake`
+    ///
+    /// The caller must have set the current thread's state to `Blocked`, and made it
+    /// reachable by whoever will wake it. If the thread was already woken this returns
+    /// right away. If there is nothing else to run this also returns right away, so
+    /// callers must check what they are waiting on again.
+    pub fn block_current() {
+        Self::switch_out(SwitchReason::Block);
+    }
+
+    /// Switch to the next thread, putting the current thread back into the picking
+    /// queue, to sleep, or leaving it blocked depending on `reason`.
+    fn switch_out(reason: SwitchReason) {
         assert_eq!(current_scheduler_locks(), 0);
         assert_eq!(current_debug_locks(), 0);
 
         let s = Scheduler::get();
-        let mut running_lock = s.running.lock();
+        let mut running_lock = s.local_queue().running.lock();
 
-        if s.picking_queue.lock().len() == 0 {
+        if !s.has_ready_threads() {
+            // Nothing else can run, so the current thread keeps the cpu
+            if let Some(running) = running_lock.as_ref() {
+                running.set_state(ThreadState::Running);
+            }
             return;
         }
 
         // Save the current running process
         if let Some(previous_running) = running_lock.clone() {
-            if !*previous_running.crashed.borrow() {
+            // Checked while `previous_running` is still the current thread, as the switch
+            // itself happens after the next thread is picked
+            previous_running
+                .task
+                .borrow_mut()
+                .check_stack(&previous_running);
+
+            if !previous_running.is_zombie() {
                 previous_running.pre_switch_out();
-                s.picking_queue.lock().push_back(ScheduleItem {
-                    priority: 0,
-                    thread: Arc::downgrade(&previous_running),
-                });
+
+                match reason {
+                    SwitchReason::Sleep(wake_tick) => {
+                        previous_running.set_state(ThreadState::Sleeping);
+                        s.sleeping
+                            .lock()
+                            .push((wake_tick, Arc::downgrade(&previous_running)));
+                    }
+                    // Blocked threads are only reachable from what they are waiting on
+                    SwitchReason::Block if previous_running.state() == ThreadState::Blocked => {}
+                    SwitchReason::Yield | SwitchReason::Block => {
+                        previous_running.set_state(ThreadState::Ready);
+                        s.enqueue(&previous_running);
+                    }
+                }
             }
 
             // Pick the next running thread
@@ -549,6 +874,7 @@ impl Scheduler {
 
             let previous_task_ptr = previous_running.task.as_ptr();
             let new_task_ptr = next_running.task.as_ptr();
+            next_running.restore_tls();
 
             unsafe { manual_schedule_lock() };
 
@@ -571,6 +897,7 @@ impl Scheduler {
             }
 
             let new_task_ptr = next_running.task.as_ptr();
+            next_running.restore_tls();
 
             unsafe { manual_schedule_lock() };
 
@@ -581,23 +908,105 @@ impl Scheduler {
         }
     }
 
-    /// Spawn all the processes within the initfs region
+    /// Spawn the first processes from the initfs region
+    ///
+    /// If the initfs has an `init` program only it is spawned, and it starts everything else.
+    /// Otherwise every program in the initfs is spawned.
     ///
     /// # Safety
     /// The caller must ensure that this is the same region that was mapped, and that
     /// this region exists with correct data.
     pub unsafe fn spawn_all_initfs(&self, initfs: VmRegion) {
-        let initfs_slice = unsafe {
-            core::slice::from_raw_parts(initfs.start.addr().as_ptr::<u8>(), initfs.len_bytes())
-        };
+        *self.initfs.lock() = Some(initfs);
+
+        let tar_file = Tar::new(Self::initfs_slice(initfs));
+        let has_init = tar_file.iter().any(|file| {
+            file.filename().is_ok_and(|name| name == INIT_PROGRAM)
+                && file.file().is_ok_and(Self::is_elf)
+        });
 
-        let tar_file = Tar::new(initfs_slice);
         for file in tar_file.iter() {
-            let new_process = Process::new(file.filename().unwrap().into());
-            let file_bytes = Arc::new(ElfOwned::new_from_slice(file.file().unwrap()));
+            // The initfs also carries data files (like the boot splash), only spawn programs
+            if !file.file().is_ok_and(Self::is_elf) {
+                continue;
+            }
+
+            let filename = file.filename().unwrap();
+            if has_init && filename != INIT_PROGRAM {
+                continue;
+            }
+
+            let mut file = file.file().unwrap();
+            let len = file.len();
+            let Ok(elf) = ElfOwned::read_from(&mut file, len) else {
+                warnln!("Could not read '{filename}' from the initfs, not spawning it");
+                continue;
+            };
+
+            let new_process = Process::new(filename.into());
+            let file_bytes = Arc::new(elf);
 
             let entry_ptr = new_process.map_elf(file_bytes);
             Thread::new_user(new_process.clone(), entry_ptr);
+
+            if has_init {
+                *self.init.lock() = Some(Arc::downgrade(&new_process));
+            }
+        }
+    }
+
+    /// The `init` process, if one was spawned and it is still alive
+    pub fn init_process(&self) -> Option<RefProcess> {
+        self.init
+            .lock()
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .filter(|init| !init.dead.load(Ordering::SeqCst))
+    }
+
+    /// Spawn the initfs program `name` as a child of `parent`, with `capabilities`
+    pub fn spawn_initfs_child(
+        &self,
+        name: &str,
+        parent: &RefProcess,
+        capabilities: u64,
+    ) -> Option<RefProcess> {
+        let initfs = (*self.initfs.lock())?;
+
+        let tar_file = Tar::new(Self::initfs_slice(initfs));
+        let file = tar_file
+            .iter()
+            .find(|file| file.filename().is_ok_and(|filename| filename == name))?;
+        let mut file = file.file().ok().filter(|file| Self::is_elf(file))?;
+        let len = file.len();
+        let file_bytes = Arc::new(ElfOwned::read_from(&mut file, len).ok()?);
+
+        let new_process = Process::new_child(name.into(), parent, capabilities);
+
+        let entry_ptr = new_process.map_elf(file_bytes);
+        Thread::new_user(new_process.clone(), entry_ptr);
+
+        Some(new_process)
+    }
+
+    /// Get the contents of the initfs file `name`
+    pub fn initfs_file(&self, name: &str) -> Option<&'static [u8]> {
+        let initfs = (*self.initfs.lock())?;
+
+        Tar::new(Self::initfs_slice(initfs))
+            .iter()
+            .find(|file| file.filename().is_ok_and(|filename| filename == name))?
+            .file()
+            .ok()
+    }
+
+    fn is_elf(file: &[u8]) -> bool {
+        file.starts_with(b"\x7fELF")
+    }
+
+    fn initfs_slice(initfs: VmRegion) -> &'static [u8] {
+        unsafe {
+            core::slice::from_raw_parts(initfs.start.addr().as_ptr::<u8>(), initfs.len_bytes())
         }
     }
 
@@ -621,6 +1030,20 @@ impl Scheduler {
                     Self::yield_now()
                 }
                 Err(LockError::Deadlock) => {
+                    #[cfg(feature = "lock-debug")]
+                    {
+                        let s = Scheduler::get();
+                        let held_at = s
+                            .held_locks
+                            .lock()
+                            .aquired_backtraces(&s.current_thread(), lock_id);
+
+                        lignan::errorln!("Deadlock while locking here:\n{}", Backtrace::capture());
+                        for backtrace in held_at {
+                            lignan::errorln!("This lock is already held from here:\n{backtrace}");
+                        }
+                    }
+
                     panic!(
                         "Aquiring an exclusive lock on this thread will deadlock! {:#?}",
                         Scheduler::get().held_locks.lock()
@@ -639,6 +1062,20 @@ impl Scheduler {
                     Self::yield_now()
                 }
                 Err(LockError::Deadlock) => {
+                    #[cfg(feature = "lock-debug")]
+                    {
+                        let s = Scheduler::get();
+                        let held_at = s
+                            .held_locks
+                            .lock()
+                            .aquired_backtraces(&s.current_thread(), lock_id);
+
+                        lignan::errorln!("Deadlock while locking here:\n{}", Backtrace::capture());
+                        for backtrace in held_at {
+                            lignan::errorln!("This lock is already held from here:\n{backtrace}");
+                        }
+                    }
+
                     panic!(
                         "Aquiring a shared lock on this thread will deadlock! {:#?}",
                         Scheduler::get().held_locks.lock()
@@ -659,7 +1096,7 @@ impl Scheduler {
             encouragement,
         ) {
             Ok(lock) => {
-                let running_lock = self.running.lock();
+                let running_lock = self.local_queue().running.lock();
                 if let Some(running) = &*running_lock {
                     running.stall_additional(encouragement.stall_amount() as isize);
                 }
@@ -681,7 +1118,7 @@ impl Scheduler {
             .try_lock_shared(self.current_thread(), lock_id, encouragement)
         {
             Ok(lock) => {
-                let running_lock = self.running.lock();
+                let running_lock = self.local_queue().running.lock();
                 if let Some(running) = &*running_lock {
                     running.stall_additional(encouragement.stall_amount() as isize);
                 }
@@ -718,7 +1155,7 @@ impl Scheduler {
                 current_thread.id
             );
 
-            current_thread.crashed.replace(true);
+            current_thread.set_state(ThreadState::Zombie);
             current_thread
                 .process
                 .threads
@@ -727,7 +1164,7 @@ impl Scheduler {
                 .remove(&current_thread.id)
                 .expect("Expected to find thread in parent process's array!");
 
-            *s.running.lock() = None;
+            *s.local_queue().running.lock() = None;
             unsafe { manual_schedule_unlock() };
         }
 
@@ -740,10 +1177,32 @@ impl Scheduler {
         self.thread_list
             .lock()
             .iter()
-            .filter(|thread| !*thread.crashed.borrow())
+            .filter(|thread| !thread.is_zombie())
             .count()
     }
 
+    /// Get the thread numbered `index`, counting every thread ever created
+    pub fn thread_at(&self, index: usize) -> Option<RefThread> {
+        self.thread_list.lock().get(index).cloned()
+    }
+
+    /// Get the process with the id `pid`, if it is still alive
+    pub fn process(&self, pid: ProcessId) -> Option<RefProcess> {
+        self.process_list
+            .lock()
+            .get(&pid)
+            .and_then(|process| process.upgrade())
+    }
+
+    /// Get every process that is still alive
+    pub fn processes(&self) -> Vec<RefProcess> {
+        self.process_list
+            .lock()
+            .values()
+            .filter_map(|process| process.upgrade())
+            .collect()
+    }
+
     /// Get the stack owner for this stack ptr
     pub fn stack_owner(&self, rsp: VirtAddr) -> Option<RefThread> {
         let thread_list = self.thread_list.lock();
@@ -775,7 +1234,7 @@ pub fn page_fault_handler(info: PageFaultInfo) -> PageFaultReponse {
 /// Convert a virtual address to a physical address
 pub fn virt_to_phys(virt: VirtAddr) -> Result<PhysAddr, PhysPtrTranslationError> {
     let scheduler = Scheduler::get();
-    let Some(running_thread) = scheduler.running.lock().clone() else {
+    let Some(running_thread) = scheduler.local_queue().running.lock().clone() else {
         return unsafe {
             bootloader_convert_phys(virt.addr() as u64)
                 .ok_or(PhysPtrTranslationError::VirtNotFound(virt))
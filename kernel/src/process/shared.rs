@@ -0,0 +1,303 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::Process;
+use crate::{
+    locks::LockEncouragement,
+    vmm::{self, MapMmioError},
+};
+use alloc::{sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+use mem::{
+    MemoryError,
+    addr::{PhysAddr, VirtAddr},
+    page::{Page2M, PagingStructureSize, PhysPage, VirtPage},
+    paging::{CacheMode, VmPermissions},
+    pmm::use_pmm_mut,
+    vm::{
+        PopulationReponse, VmFillAction, VmInjectFillAction, VmObject, VmProcess, VmRegion,
+        scrub_page,
+    },
+};
+use memory_layout::USER_MMAP;
+use util::consts::PAGE_4K;
+use vera_portal::SharedMemoryError;
+
+/// A set of physical pages that can be mapped into many processes at once.
+///
+/// Processes only reach a region through a handle or a mapping, each holding a reference,
+/// and its pages go back to the physical memory manager once the last one is gone.
+#[derive(Debug)]
+pub struct SharedMemory {
+    pages: Vec<PhysPage>,
+    /// Pages are scrubbed the first time they are mapped, since that is the first time
+    /// we have a virtual address to access them with.
+    scrubbed: Vec<AtomicBool>,
+}
+
+impl SharedMemory {
+    /// Allocate a new shared memory region of `n_pages`.
+    pub fn create(n_pages: usize) -> Result<Arc<Self>, SharedMemoryError> {
+        let pages = allocate_pages(n_pages).map_err(|_| SharedMemoryError::OutOfMemory)?;
+        let scrubbed = (0..n_pages).map(|_| AtomicBool::new(false)).collect();
+
+        Ok(Arc::new(Self { pages, scrubbed }))
+    }
+
+    /// The amount of pages in this region.
+    pub fn n_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Map this region into the kernel, scrubbing it first.
+    ///
+    /// Processes mapping the region afterwards see what the kernel wrote, instead of it
+    /// being scrubbed again.
+    #[cfg_attr(not(feature = "log-ring"), allow(dead_code))]
+    pub fn map_into_kernel(&self) -> Result<VirtAddr, MapMmioError> {
+        let virt = vmm::map_pages(&self.pages, CacheMode::WriteBack)?;
+
+        unsafe { core::ptr::write_bytes(virt.as_mut_ptr::<u8>(), 0, self.pages.len() * PAGE_4K) };
+        self.scrubbed
+            .iter()
+            .for_each(|scrubbed| scrubbed.store(true, Ordering::Release));
+
+        Ok(virt)
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        for page in self.pages.iter() {
+            let _ = use_pmm_mut(|pmm| pmm.free_page(*page));
+        }
+    }
+}
+
+/// Allocate `n_pages` for a shared memory region.
+///
+/// Whole 2Mib runs are allocated where possible, so large regions can be mapped with huge
+/// pages. If memory runs out part way, the pages allocated so far are freed again.
+fn allocate_pages(n_pages: usize) -> Result<Vec<PhysPage>, MemoryError> {
+    let mut pages = Vec::with_capacity(n_pages);
+
+    while pages.len() < n_pages {
+        let huge = if n_pages - pages.len() >= Page2M::N_PAGES {
+            use_pmm_mut(|pmm| pmm.allocate_huge::<Page2M>()).ok()
+        } else {
+            None
+        };
+
+        match huge {
+            Some(huge) => {
+                let first: PhysPage = PhysPage::containing_addr(huge.addr());
+                pages.extend((0..Page2M::N_PAGES).map(|index| PhysPage::new(first.page() + index)));
+            }
+            None => match use_pmm_mut(|pmm| pmm.allocate_page()) {
+                Ok(page) => pages.push(page),
+                Err(err) => {
+                    for page in pages {
+                        let _ = use_pmm_mut(|pmm| pmm.free_page(page));
+                    }
+                    return Err(err);
+                }
+            },
+        }
+    }
+
+    Ok(pages)
+}
+
+/// A `VmObject` backing that maps the pages of a `SharedMemory` region.
+#[derive(Debug)]
+struct VmSharedInject {
+    shared: Arc<SharedMemory>,
+    start: VirtPage,
+}
+
+impl VmInjectFillAction for VmSharedInject {
+    fn populate_page(
+        &mut self,
+        _parent_object: &VmObject,
+        _process: &VmProcess,
+        _relative_index: usize,
+        vpage: VirtPage,
+        _ppage: PhysPage,
+    ) -> PopulationReponse {
+        let index = vpage.page() - self.start.page();
+
+        if !self.shared.scrubbed[index].swap(true, Ordering::AcqRel) {
+            unsafe { scrub_page(vpage, 0) };
+        }
+
+        PopulationReponse::Okay
+    }
+
+    fn alloc_physical_page(&mut self, vpage: VirtPage) -> Result<PhysPage, MemoryError> {
+        Ok(self.shared.pages[vpage.page() - self.start.page()])
+    }
+
+    fn requests_all_pages_filled(&self, _parent_object: &VmObject) -> bool {
+        true
+    }
+}
+
+impl Process {
+    /// Map `len` bytes of device memory starting at `phys` into this process.
+    ///
+    /// `phys` must be page aligned, and the memory must not be owned by the physical
+    /// memory manager.
+    #[cfg_attr(not(feature = "gfx"), allow(dead_code))]
+    pub fn map_physical(
+        &self,
+        phys: PhysAddr,
+        len: usize,
+        cache_mode: CacheMode,
+    ) -> Result<VirtPage, SharedMemoryError> {
+        let first: PhysPage = PhysPage::containing_addr(phys);
+        let pages = (0..len.div_ceil(PAGE_4K))
+            .map(|index| PhysPage::new(first.page() + index))
+            .collect();
+
+        self.map_physical_pages(pages, cache_mode)
+    }
+
+    /// Map `pages` into this process one after another, even if they are not contiguous
+    /// in physical memory.
+    ///
+    /// The pages are never freed by this process, whoever allocated them keeps them.
+    #[cfg_attr(not(feature = "gfx"), allow(dead_code))]
+    pub fn map_physical_pages(
+        &self,
+        pages: Vec<PhysPage>,
+        cache_mode: CacheMode,
+    ) -> Result<VirtPage, SharedMemoryError> {
+        let mut vm_lock = self.vm.write();
+        let region = vm_lock
+            .find_vm_free(
+                VirtPage::containing_addr(VirtAddr::new(USER_MMAP.start)),
+                pages.len(),
+            )
+            .ok_or(SharedMemoryError::OutOfMemory)?;
+
+        let mappings = region.pages_iter().zip(pages).collect();
+        vm_lock
+            .manual_inplace_new_vmobject(region, VmPermissions::USER_RW, mappings, cache_mode)
+            .map_err(|_| SharedMemoryError::MappingMemoryError)?;
+
+        Ok(region.start)
+    }
+
+    /// Unmap the pages mapped at `start` by [`Self::map_physical_pages`].
+    ///
+    /// The pages themselves are left to whoever allocated them.
+    #[cfg_attr(not(feature = "gfx"), allow(dead_code))]
+    pub fn unmap_physical_pages(&self, start: VirtPage) -> Result<(), SharedMemoryError> {
+        self.vm
+            .write()
+            .remove_vm_object(start)
+            .map(|_| ())
+            .map_err(|_| SharedMemoryError::NotMapped)
+    }
+
+    /// Map the shared memory region `shared` into this process.
+    ///
+    /// The mapping keeps the region alive until it is unmapped with
+    /// [`Self::unmap_shared_memory`], or this process goes away. This process's page tables
+    /// must be loaded.
+    pub fn map_shared_memory(
+        &self,
+        shared: Arc<SharedMemory>,
+        writable: bool,
+    ) -> Result<VirtPage, SharedMemoryError> {
+        let perm = if writable {
+            VmPermissions::USER_RW
+        } else {
+            VmPermissions::USER_R
+        };
+
+        let n_pages = shared.n_pages();
+        let wants_huge = n_pages >= Page2M::N_PAGES;
+
+        // Large regions are placed on a 2Mib boundary, so their 2Mib runs line up
+        let mut vm_lock = self.vm.write();
+        let free = vm_lock
+            .find_vm_free(
+                VirtPage::containing_addr(VirtAddr::new(USER_MMAP.start)),
+                if wants_huge {
+                    n_pages + (Page2M::N_PAGES - 1)
+                } else {
+                    n_pages
+                },
+            )
+            .ok_or(SharedMemoryError::OutOfMemory)?;
+        let start = if wants_huge {
+            VirtPage::new(free.start.page().next_multiple_of(Page2M::N_PAGES))
+        } else {
+            free.start
+        };
+        let region = VmRegion::new(start, start.offset_by(n_pages - 1));
+
+        let fill_action = VmFillAction::convert(VmSharedInject {
+            shared,
+            start: region.start,
+        });
+
+        vm_lock
+            .inplace_new_vmobject(region, perm, fill_action, true)
+            .map_err(|_| SharedMemoryError::MappingMemoryError)?;
+
+        if wants_huge {
+            vm_lock
+                .page_tables
+                .write()
+                .promote_huge_pages(region, false);
+        }
+
+        self.shared_mappings
+            .write(LockEncouragement::Moderate)
+            .insert(region.start.page());
+        Ok(region.start)
+    }
+
+    /// Unmap the shared memory mapping starting at `start`.
+    ///
+    /// The region is freed if this was its last mapping, and no handle refers to it.
+    pub fn unmap_shared_memory(&self, start: VirtPage) -> Result<(), SharedMemoryError> {
+        let mut mappings = self.shared_mappings.write(LockEncouragement::Moderate);
+        if !mappings.contains(&start.page()) {
+            return Err(SharedMemoryError::NotMapped);
+        }
+
+        // Dropping the object drops its `VmSharedInject`, and the reference it held
+        self.vm
+            .write()
+            .remove_vm_object(start)
+            .map_err(|_| SharedMemoryError::MappingMemoryError)?;
+        mappings.remove(&start.page());
+
+        Ok(())
+    }
+}
@@ -23,13 +23,13 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use alloc::alloc::{alloc_zeroed, dealloc};
+use alloc::alloc::{alloc, dealloc};
 use core::{
     alloc::Layout,
     arch::{asm, naked_asm},
     cell::UnsafeCell,
 };
-use lignan::logln;
+use lignan::{logln, warnln};
 use mem::addr::{AlignedTo, VirtAddr};
 use util::consts::PAGE_4K;
 
@@ -37,13 +37,20 @@ use crate::{
     context::set_syscall_rsp,
     gdt,
     locks::{LockEncouragement, manual_schedule_unlock},
-    process::scheduler::Scheduler,
+    mitigations,
+    process::{scheduler::Scheduler, thread::Thread},
+    processor, usercopy,
 };
 
 type ArchStackPtr = usize;
 
 /// A task's flags
-#[bits::bits(field(RW, 0, running), field(RW, 1, dead))]
+#[bits::bits(
+    field(RW, 0, running),
+    field(RW, 1, dead),
+    field(RW, 2, user_access),
+    field(RW, 3, stack_warned)
+)]
 #[derive(Debug, Clone, Copy)]
 pub struct TaskFlags(u64);
 
@@ -56,11 +63,26 @@ pub struct TaskStack {
 }
 
 impl TaskStack {
+    /// Every word of a new stack is filled with this, so the deepest the stack has been can
+    /// be found by looking for the lowest word that changed.
+    const FILL: ArchStackPtr = 0x57AC_57AC_57AC_57AC;
+
+    /// The lowest word of every stack, if this changes the stack has overflowed.
+    const CANARY: ArchStackPtr = 0xDEAD_C0DE_CA4A_121E;
+
+    /// The percentage of the stack a task can use before a warning is logged
+    pub const WARN_PERCENT: usize = vera_portal::STACK_WARN_PERCENT as usize;
+
     pub fn new(len: usize) -> Self {
         // FIXME: This should allocate kernel pages instead of using the default alloc
-        let allocation = unsafe {
-            alloc_zeroed(Layout::from_size_align(len, size_of::<ArchStackPtr>()).unwrap())
-        };
+        let allocation =
+            unsafe { alloc(Layout::from_size_align(len, size_of::<ArchStackPtr>()).unwrap()) };
+
+        let words = allocation as *mut ArchStackPtr;
+        for index in 0..(len / size_of::<ArchStackPtr>()) {
+            unsafe { words.add(index).write(Self::FILL) };
+        }
+        unsafe { words.write(Self::CANARY) };
 
         Self {
             stack_bottom: VirtAddr::try_from(allocation).unwrap(),
@@ -68,6 +90,38 @@ impl TaskStack {
             len,
         }
     }
+
+    /// Read the word `index` words up from the bottom of the stack
+    fn word(&self, index: usize) -> ArchStackPtr {
+        // The stack could be in use on another cpu, so this must always be re-read
+        unsafe {
+            self.stack_bottom
+                .as_ptr::<ArchStackPtr>()
+                .add(index)
+                .read_volatile()
+        }
+    }
+
+    /// Check if the canary at the bottom of the stack is still there
+    pub fn canary_intact(&self) -> bool {
+        self.word(0) == Self::CANARY
+    }
+
+    /// The deepest this stack has ever been, in bytes
+    pub fn high_water(&self) -> usize {
+        let words = self.len / size_of::<ArchStackPtr>();
+        let lowest_used = (1..words)
+            .find(|&index| self.word(index) != Self::FILL)
+            .unwrap_or(words);
+
+        (words - lowest_used) * size_of::<ArchStackPtr>()
+    }
+
+    /// Check if this stack has ever gone past [`TaskStack::WARN_PERCENT`] of its length
+    pub fn past_warning(&self) -> bool {
+        let free_words = self.len * (100 - Self::WARN_PERCENT) / 100 / size_of::<ArchStackPtr>();
+        self.word(free_words) != Self::FILL
+    }
 }
 
 impl Drop for TaskStack {
@@ -114,11 +168,16 @@ impl Task {
     fn switch_prelude(&mut self) {
         self.task_flags.set_running_flag(false);
 
+        // `AC` isn't saved across a switch, so the next task would inherit our user access
+        self.task_flags
+            .set_user_access_flag(usercopy::suspend_user_access());
+
         unsafe {
             let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
             let page_tables = current_thread.process.vm.read(LockEncouragement::Strong);
             if !page_tables.page_tables.read().is_loaded() {
                 page_tables.page_tables.read().load().unwrap();
+                mitigations::on_address_space_switch();
             }
         };
     }
@@ -128,11 +187,57 @@ impl Task {
     fn switch_epilogue(&mut self) {
         self.task_flags.set_running_flag(true);
 
+        if self.task_flags.is_user_access_set() {
+            usercopy::resume_user_access();
+        }
+
         let top_of_task_stack = self.stack_top();
+        processor::set_kernel_stack(
+            self.stack.stack_bottom.addr() as u64..top_of_task_stack.addr() as u64,
+        );
         gdt::set_stack_for_privl(top_of_task_stack.as_mut_ptr(), arch::CpuPrivilege::Ring0);
         unsafe { set_syscall_rsp(top_of_task_stack.addr() as u64) };
     }
 
+    /// Panic if this task's stack has overflowed, and warn the first time it gets close to
+    /// overflowing
+    ///
+    /// `thread` is the thread this task belongs to, which is named in the warning. The
+    /// scheduler calls this before it switches away from `thread`.
+    pub fn check_stack(&mut self, thread: &Thread) {
+        if !self.stack.canary_intact() {
+            panic!(
+                "Task stack overflowed! (stack: {:016x}-{:016x})",
+                self.stack.stack_bottom.addr(),
+                self.stack_top().addr()
+            );
+        }
+
+        if !self.task_flags.is_stack_warned_set() && self.stack.past_warning() {
+            self.task_flags.set_stack_warned_flag(true);
+
+            warnln!(
+                "Task stack of '{}' (pid={}, tid={}) is over {}% used ({} of {} bytes)",
+                thread.process.name,
+                thread.process.id,
+                thread.id,
+                TaskStack::WARN_PERCENT,
+                self.stack.high_water(),
+                self.stack.len
+            );
+        }
+    }
+
+    /// The deepest this task's stack has ever been, in bytes
+    pub fn stack_high_water(&self) -> usize {
+        self.stack.high_water()
+    }
+
+    /// How large this task's stack is, in bytes
+    pub fn stack_len(&self) -> usize {
+        self.stack.len
+    }
+
     /// Get the tasks inner stack ptr
     #[inline]
     fn get_task_stack_ptr(&mut self) -> *mut ArchStackPtr {
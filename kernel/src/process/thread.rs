@@ -25,15 +25,23 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 use core::{
     arch::asm,
-    sync::atomic::{AtomicIsize, Ordering},
+    sync::atomic::{AtomicIsize, AtomicU8, AtomicU64, AtomicUsize, Ordering},
 };
 
-use super::{ProcessEntry, RefProcess, scheduler::Scheduler, task::Task};
-use crate::{context::set_syscall_rsp, gdt, locks::ThreadCell};
+use super::{ProcessEntry, RefProcess, run_queue::CpuSet, scheduler::Scheduler, task::Task};
+#[cfg(feature = "syscall-trace")]
+use crate::syscall_trace::PendingTrace;
+use crate::{
+    context::set_syscall_rsp,
+    gdt,
+    locks::{LockEncouragement, ThreadCell},
+    usercopy::copy_to_user,
+};
 use alloc::sync::{Arc, Weak};
-use arch::interrupts;
+use arch::{interrupts, registers::segment_base};
 use lignan::logln;
 use mem::{addr::VirtAddr, paging::VmPermissions, vm::VmRegion};
+use memory_layout::USER_STACKS;
 use util::consts::PAGE_4K;
 
 pub type UserspaceStackTop = VirtAddr;
@@ -47,6 +55,34 @@ pub enum ThreadContextKind {
     Kernel,
 }
 
+/// What a thread is doing as far as the scheduler is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum ThreadState {
+    /// This thread is on the cpu
+    Running,
+    /// This thread is in the picking queue, waiting for its turn
+    Ready,
+    /// This thread is waiting in a `WaitQueue`, and is not scheduled until it is woken
+    Blocked,
+    /// This thread is not scheduled until the kernel reaches its wake tick
+    Sleeping,
+    /// This thread has exited or crashed, and will never run again
+    Zombie,
+}
+
+impl ThreadState {
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Running,
+            1 => Self::Ready,
+            2 => Self::Blocked,
+            3 => Self::Sleeping,
+            _ => Self::Zombie,
+        }
+    }
+}
+
 /// A userspace execution unit, like a [`Task`] but for userspace.
 #[derive(Debug)]
 pub struct Thread {
@@ -70,11 +106,25 @@ pub struct Thread {
     // `userspace_thread_begin`?
     userspace_entry_ptr: Option<ProcessEntry>,
     userspace_rsp_ptr: ThreadCell<Option<UserspaceStackTop>>,
-    pub crashed: ThreadCell<bool>,
+    /// The current `ThreadState` of this thread
+    state: AtomicU8,
+    /// Higher priority threads are woken first from a `WaitQueue`, and run for longer
+    priority: AtomicIsize,
+    /// How many ticks this thread has been running for
+    cpu_ticks: AtomicU64,
+    /// The bits of the `CpuSet` this thread may run on
+    affinity: AtomicU64,
+    /// The cpu this thread last ran on
+    last_cpu: AtomicUsize,
+    /// The `FS` base (thread pointer) of this thread
+    fs_base: AtomicU64,
+    /// The syscall being traced, from entering it until it returns
+    #[cfg(feature = "syscall-trace")]
+    pending_trace: ThreadCell<Option<PendingTrace>>,
 }
 
 impl Thread {
-    pub const DEFAULT_USERSPACE_RSP_TOP: VirtAddr = VirtAddr::new(0x7fff00000000);
+    pub const DEFAULT_USERSPACE_RSP_TOP: VirtAddr = VirtAddr::new(USER_STACKS.start);
     pub const DEFAULT_USERSPACE_RSP_LEN: usize = PAGE_4K * 16;
     pub const QUANTA: usize = 20;
 
@@ -90,9 +140,16 @@ impl Thread {
             process,
             userspace_entry_ptr: Some(entry_point),
             userspace_rsp_ptr: ThreadCell::new(None),
-            crashed: ThreadCell::new(false),
+            state: AtomicU8::new(ThreadState::Ready as u8),
+            priority: AtomicIsize::new(0),
+            cpu_ticks: AtomicU64::new(0),
+            affinity: AtomicU64::new(CpuSet::ALL.bits()),
+            last_cpu: AtomicUsize::new(0),
             quanta: AtomicIsize::new(Self::QUANTA as isize),
             temporary_quanta: AtomicIsize::new(0),
+            fs_base: AtomicU64::new(0),
+            #[cfg(feature = "syscall-trace")]
+            pending_trace: ThreadCell::new(None),
         });
 
         let s = Scheduler::get();
@@ -114,9 +171,16 @@ impl Thread {
             process,
             userspace_entry_ptr: None,
             userspace_rsp_ptr: ThreadCell::new(None),
-            crashed: ThreadCell::new(false),
+            state: AtomicU8::new(ThreadState::Ready as u8),
+            priority: AtomicIsize::new(0),
+            cpu_ticks: AtomicU64::new(0),
+            affinity: AtomicU64::new(CpuSet::ALL.bits()),
+            last_cpu: AtomicUsize::new(0),
             quanta: AtomicIsize::new(Self::QUANTA as isize),
             temporary_quanta: AtomicIsize::new(0),
+            fs_base: AtomicU64::new(0),
+            #[cfg(feature = "syscall-trace")]
+            pending_trace: ThreadCell::new(None),
         });
 
         let s = Scheduler::get();
@@ -125,20 +189,117 @@ impl Thread {
         thread
     }
 
+    /// Get the current state of this thread
+    pub fn state(&self) -> ThreadState {
+        ThreadState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    /// Set the state of this thread
+    ///
+    /// Once a thread is a zombie it stays that way.
+    pub fn set_state(&self, state: ThreadState) {
+        let mut current = self.state.load(Ordering::Acquire);
+
+        while current != ThreadState::Zombie as u8 {
+            match self.state.compare_exchange_weak(
+                current,
+                state as u8,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Move this thread from the `from` state to `to`, returning false if it was not in
+    /// the `from` state.
+    pub fn transition(&self, from: ThreadState, to: ThreadState) -> bool {
+        self.state
+            .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    /// Check if this thread has exited or crashed
+    pub fn is_zombie(&self) -> bool {
+        self.state() == ThreadState::Zombie
+    }
+
+    /// Get the scheduling priority of this thread
+    pub fn priority(&self) -> isize {
+        self.priority.load(Ordering::Relaxed)
+    }
+
+    /// Get the nice value of this thread, which is its priority the other way around
+    pub fn nice(&self) -> i8 {
+        -self.priority() as i8
+    }
+
+    /// Set the nice value of this thread, see `vera_portal::NICE_RANGE`
+    pub fn set_nice(&self, nice: i8) {
+        self.priority.store(-nice as isize, Ordering::Relaxed);
+    }
+
+    /// How many ticks this thread gets to run before it is switched out
+    pub fn time_slice(&self) -> isize {
+        (Self::QUANTA as isize + self.priority()).max(1)
+    }
+
+    /// How many ticks this thread has spent running
+    pub fn cpu_ticks(&self) -> u64 {
+        self.cpu_ticks.load(Ordering::Relaxed)
+    }
+
+    /// Get the cpus this thread may run on
+    pub fn affinity(&self) -> CpuSet {
+        CpuSet::from_bits(self.affinity.load(Ordering::Relaxed))
+    }
+
+    /// Limit which cpus this thread may run on
+    ///
+    /// If it is queued on a cpu that is no longer allowed, it moves once that cpu picks it.
+    pub fn set_affinity(&self, cpus: CpuSet) {
+        self.affinity.store(cpus.bits(), Ordering::Relaxed);
+    }
+
+    /// Remember the syscall this thread is running, until it returns
+    #[cfg(feature = "syscall-trace")]
+    pub fn begin_trace(&self, trace: PendingTrace) {
+        *self.pending_trace.borrow_mut() = Some(trace);
+    }
+
+    /// Take the syscall this thread was running, if it was being traced
+    #[cfg(feature = "syscall-trace")]
+    pub fn take_trace(&self) -> Option<PendingTrace> {
+        self.pending_trace.borrow_mut().take()
+    }
+
+    /// Get the cpu this thread last ran on
+    pub fn last_cpu(&self) -> usize {
+        self.last_cpu.load(Ordering::Relaxed)
+    }
+
+    /// Record that this thread is now running on `cpu`
+    pub fn set_last_cpu(&self, cpu: usize) {
+        self.last_cpu.store(cpu, Ordering::Relaxed);
+    }
+
     /// Called before switching out of this thread
     pub fn pre_switch_out(&self) {
         self.temporary_quanta.store(0, Ordering::SeqCst);
 
-        self.quanta
-            .fetch_add(Self::QUANTA as isize, Ordering::SeqCst);
-        self.quanta
-            .fetch_min(Self::QUANTA as isize, Ordering::SeqCst);
+        self.quanta.fetch_add(self.time_slice(), Ordering::SeqCst);
+        self.quanta.fetch_min(self.time_slice(), Ordering::SeqCst);
     }
 
     /// Tick this thread forward
     ///
     /// Returns true if this thread is ready to switch.
     pub fn thread_tick(&self, elapsed_ticks: usize) -> bool {
+        self.cpu_ticks
+            .fetch_add(elapsed_ticks as u64, Ordering::Relaxed);
+
         let quanta = self
             .quanta
             .fetch_sub(elapsed_ticks as isize, Ordering::SeqCst);
@@ -157,6 +318,40 @@ impl Thread {
         self.temporary_quanta.fetch_sub(quanta, Ordering::AcqRel);
     }
 
+    /// Load this thread's `FS` base into the processor
+    ///
+    /// Must be called before switching into this thread.
+    pub fn restore_tls(&self) {
+        unsafe { segment_base::write_fs_base(self.fs_base.load(Ordering::Relaxed)) };
+    }
+
+    /// Map and init this thread's TLS block from its process's TLS template.
+    ///
+    /// This must be called while this thread's process's page tables are loaded.
+    fn alloc_user_tls(&self) {
+        let template_lock = self.process.tls_template.read(LockEncouragement::Weak);
+        let Some(template) = template_lock.as_ref() else {
+            return;
+        };
+
+        // FIXME: We only ever map page aligned blocks
+        assert!(
+            template.block_align() <= PAGE_4K,
+            "TLS blocks aligned larger than a page are not supported"
+        );
+
+        let n_pages = template.block_len().div_ceil(PAGE_4K);
+        let block = self
+            .process
+            .map_anon_anywhere(n_pages, VmPermissions::USER_RW)
+            .expect("Unable to allocate thread's TLS block");
+
+        let (init_block, thread_ptr) = template.build_block(block.addr().addr() as u64);
+        copy_to_user(block.addr().as_mut_ptr(), &init_block)
+            .expect("Unable to init thread's TLS block");
+        self.fs_base.store(thread_ptr, Ordering::Relaxed);
+    }
+
     /// Create a mapping for the userspace stack
     fn alloc_user_stack(&self) {
         let stack_top = Self::DEFAULT_USERSPACE_RSP_TOP
@@ -201,6 +396,9 @@ fn userspace_thread_begin() {
         .expect("Requires an rsp ptr")
         .addr();
 
+    current_thread.alloc_user_tls();
+    current_thread.restore_tls();
+
     // Here we need a critical section because we need to ensure the ISR stack is set
     unsafe { interrupts::disable_interrupts() };
 
@@ -235,6 +433,7 @@ fn userspace_thread_begin() {
               mov rsi, 0
               mov rdi, 0
 
+              swapgs      # hand `GS` to userspace
               iretq
           ",
           in("rdi") entry,
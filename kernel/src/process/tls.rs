@@ -0,0 +1,91 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::{vec, vec::Vec};
+use elf::Elf;
+use util::align_to;
+
+/// The size of the thread control block that sits at the thread pointer.
+///
+/// The first entry is the self-pointer (`fs:0`), and the second is reserved for a DTV.
+const TCB_LEN: usize = size_of::<u64>() * 2;
+
+/// The thread local storage image for a process.
+///
+/// Uses the x86_64 'Variant II' layout, where the TLS block sits directly below the
+/// thread pointer and the thread control block starts at the thread pointer.
+#[derive(Debug, Clone)]
+pub struct TlsTemplate {
+    /// The initialized data (`.tdata`) to copy into each block
+    init_image: Vec<u8>,
+    /// The total in memory size (`.tdata` + `.tbss`)
+    mem_size: usize,
+    /// The required alignment of the block
+    align: usize,
+}
+
+impl TlsTemplate {
+    /// Get the TLS template from this elf, if it has one
+    pub fn from_elf(elf: &Elf) -> Option<Self> {
+        let header = elf.tls_header().ok()??;
+        let init_image = elf.program_header_slice(&header).ok()?;
+
+        Some(Self {
+            init_image: init_image.into(),
+            mem_size: header.in_mem_size(),
+            align: (header.alignment() as usize).max(size_of::<u64>()),
+        })
+    }
+
+    /// The offset of the thread pointer from the start of the block
+    fn tp_offset(&self) -> usize {
+        align_to(self.mem_size as u64, self.align) as usize
+    }
+
+    /// The total size of a block, including the thread control block
+    pub fn block_len(&self) -> usize {
+        self.tp_offset() + TCB_LEN
+    }
+
+    /// The alignment that a block must be placed at
+    pub fn block_align(&self) -> usize {
+        self.align
+    }
+
+    /// Build the initial contents of a TLS block that will be placed at `block_addr`, returning
+    /// the block and the thread pointer for `FS`.
+    pub fn build_block(&self, block_addr: u64) -> (Vec<u8>, u64) {
+        // The TLS image starts `tp_offset` bytes below the thread pointer, which is the
+        // start of the block.
+        let mut block = vec![0; self.block_len()];
+        block[..self.init_image.len()].copy_from_slice(&self.init_image);
+
+        let tp_offset = self.tp_offset();
+        let thread_ptr = block_addr + tp_offset as u64;
+        block[tp_offset..tp_offset + size_of::<u64>()].copy_from_slice(&thread_ptr.to_ne_bytes());
+
+        (block, thread_ptr)
+    }
+}
@@ -0,0 +1,176 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{
+    scheduler::Scheduler,
+    thread::{RefThread, ThreadState, WeakThread},
+};
+use crate::{
+    locks::ScheduleLock,
+    timer::{self, kernel_ticks},
+};
+use alloc::{sync::Arc, vec::Vec};
+
+/// A thread waiting in a `WaitQueue`
+#[derive(Debug)]
+struct Waiter {
+    priority: isize,
+    /// When this thread started waiting, so equal priorities are woken in order
+    ticket: u64,
+    thread: WeakThread,
+}
+
+/// A set of threads blocked until something happens
+///
+/// Anything a thread can wait on (pipe data, signals, futexes, another process exiting)
+/// owns one of these, and wakes it when that thing happens. Waiters are woken highest
+/// priority first, then in the order they started waiting.
+#[derive(Debug)]
+pub struct WaitQueue {
+    waiters: ScheduleLock<(u64, Vec<Waiter>)>,
+}
+
+impl WaitQueue {
+    /// Create a new empty wait queue
+    pub const fn new() -> Self {
+        Self {
+            waiters: ScheduleLock::new((0, Vec::new())),
+        }
+    }
+
+    /// Block the current thread once, unless `should_wait` returns false
+    ///
+    /// `should_wait` is checked after the thread is in the queue, so a wake between
+    /// checking and blocking is never lost. This can return without being woken, so
+    /// callers must check what they are waiting on again.
+    pub fn wait_once(&self, should_wait: impl FnOnce() -> bool) {
+        let Some(thread) = Scheduler::get().current_thread().upgrade() else {
+            return;
+        };
+
+        thread.set_state(ThreadState::Blocked);
+        self.push(&thread);
+
+        if should_wait() {
+            drop(thread);
+            Scheduler::block_current();
+        } else {
+            thread.set_state(ThreadState::Running);
+            drop(thread);
+        }
+
+        // We could have been woken by something other than this queue
+        self.remove_current();
+    }
+
+    /// Block the current thread once like `wait_once`, but give up once the kernel
+    /// reaches tick `deadline`
+    pub fn wait_once_until(&self, deadline: u64, should_wait: impl FnOnce() -> bool) {
+        let Some(thread) = Scheduler::get().current_thread().upgrade() else {
+            return;
+        };
+
+        timer::add_wake_timer(&thread, deadline);
+        drop(thread);
+
+        self.wait_once(|| kernel_ticks() < deadline && should_wait());
+        timer::remove_wake_timers(&Scheduler::get().current_thread());
+    }
+
+    /// Wake the most important waiting thread, returning false if nothing was waiting
+    pub fn wake_one(&self) -> bool {
+        self.wake(1) == 1
+    }
+
+    /// Wake every waiting thread, returning how many were woken
+    pub fn wake_all(&self) -> usize {
+        self.wake(usize::MAX)
+    }
+
+    /// Wake up to `count` waiting threads, returning how many were woken
+    pub fn wake(&self, count: usize) -> usize {
+        let mut woken = 0;
+
+        while woken < count {
+            let Some(thread) = self.pop() else {
+                break;
+            };
+
+            if Scheduler::get().wake(&thread) {
+                woken += 1;
+            }
+        }
+
+        woken
+    }
+
+    /// Check if no threads are waiting
+    pub fn is_empty(&self) -> bool {
+        self.waiters.lock().1.is_empty()
+    }
+
+    fn push(&self, thread: &RefThread) {
+        let mut waiters = self.waiters.lock();
+        let (next_ticket, waiters) = &mut *waiters;
+
+        waiters.push(Waiter {
+            priority: thread.priority(),
+            ticket: *next_ticket,
+            thread: Arc::downgrade(thread),
+        });
+        *next_ticket += 1;
+    }
+
+    /// Remove the next thread to wake, skipping threads that have exited
+    fn pop(&self) -> Option<RefThread> {
+        let mut waiters = self.waiters.lock();
+        let waiters = &mut waiters.1;
+
+        loop {
+            let next = waiters
+                .iter()
+                .enumerate()
+                .max_by(|(_, lhs), (_, rhs)| {
+                    lhs.priority
+                        .cmp(&rhs.priority)
+                        .then(rhs.ticket.cmp(&lhs.ticket))
+                })
+                .map(|(index, _)| index)?;
+
+            if let Some(thread) = waiters.swap_remove(next).thread.upgrade() {
+                return Some(thread);
+            }
+        }
+    }
+
+    fn remove_current(&self) {
+        let current = Scheduler::get().current_thread();
+
+        self.waiters
+            .lock()
+            .1
+            .retain(|waiter| !waiter.thread.ptr_eq(&current) && waiter.thread.strong_count() != 0);
+    }
+}
@@ -23,66 +23,177 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use core::sync::atomic::{AtomicUsize, Ordering};
+use arch::registers::{ia32_kernel_gs_base, segment_base};
+use core::{
+    cell::SyncUnsafeCell,
+    ops::Range,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+/// Data local to each processor.
+///
+/// This is accessed through the `GS` segment, whose base points to this processor's
+/// instance of this struct.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ProcessorLocal {
+    /// A ptr to itself, this is always the first field so `gs:0` can be read to get
+    /// the address of this struct.
+    self_ptr: *const ProcessorLocal,
+    /// The id of this processor
+    pub cpu_id: usize,
+    current_thread_id: AtomicUsize,
+    current_process_id: AtomicUsize,
+    handling_irq: AtomicUsize,
+    handling_critical: AtomicUsize,
+    /// The bottom of the kernel stack this processor is running on
+    kernel_stack_bottom: AtomicU64,
+    /// The top of the kernel stack this processor is running on, zero until one is set
+    kernel_stack_top: AtomicU64,
+    /// Timer samples taken while profiling
+    #[cfg(feature = "profile")]
+    pub profile_samples: crate::profile::ProfileBuffer,
+}
+
+// Each `ProcessorLocal` is only ever accessed from its own processor.
+unsafe impl Sync for ProcessorLocal {}
+
+impl ProcessorLocal {
+    const fn new(cpu_id: usize) -> Self {
+        Self {
+            self_ptr: core::ptr::null(),
+            cpu_id,
+            current_thread_id: AtomicUsize::new(0),
+            current_process_id: AtomicUsize::new(0),
+            handling_irq: AtomicUsize::new(0),
+            handling_critical: AtomicUsize::new(0),
+            kernel_stack_bottom: AtomicU64::new(0),
+            kernel_stack_top: AtomicU64::new(0),
+            #[cfg(feature = "profile")]
+            profile_samples: crate::profile::ProfileBuffer::new(),
+        }
+    }
+}
+
+// FIXME: This should be allocated per-processor once we support SMP
+static BSP_PROCESSOR_LOCAL: SyncUnsafeCell<ProcessorLocal> =
+    SyncUnsafeCell::new(ProcessorLocal::new(0));
+
+/// Get the number of processors the kernel is using
+pub fn cpu_count() -> usize {
+    // FIXME: This should count the processors we brought up once we support SMP
+    1
+}
+
+/// Point this processor's `GS` base at its `ProcessorLocal` data.
+///
+/// # Safety
+/// Must be called once per processor, before any other function within this module is used.
+pub unsafe fn init_processor_local() {
+    let local = BSP_PROCESSOR_LOCAL.get();
+
+    unsafe {
+        (*local).self_ptr = local;
+        segment_base::write_gs_base(local as u64);
+        // Userspace's `GS` base lives here while we're in the kernel, and gets swapped in
+        // with `swapgs` on the way out.
+        ia32_kernel_gs_base::write(0);
+    }
+}
+
+/// Get this processor's local data
+#[inline]
+pub fn processor_local() -> &'static ProcessorLocal {
+    let local_ptr: *const ProcessorLocal;
+    unsafe { core::arch::asm!("mov {0}, gs:[0]", out(reg) local_ptr) };
 
-static CURRENT_THREAD_ID: AtomicUsize = AtomicUsize::new(0);
-static CURRENT_PROCESS_ID: AtomicUsize = AtomicUsize::new(0);
-static HANDLING_IRQ: AtomicUsize = AtomicUsize::new(0);
-static HANDLING_CRITICAL: AtomicUsize = AtomicUsize::new(0);
+    unsafe { &*local_ptr }
+}
 
 /// Set the processor's current thread ID
 pub fn set_current_thread_id(thread_id: usize) {
-    CURRENT_THREAD_ID.store(thread_id, Ordering::Relaxed);
+    processor_local()
+        .current_thread_id
+        .store(thread_id, Ordering::Relaxed);
 }
 
 /// Get the processor's current thread ID
 pub fn get_current_thread_id() -> usize {
-    CURRENT_THREAD_ID.load(Ordering::Relaxed)
+    processor_local().current_thread_id.load(Ordering::Relaxed)
 }
 
 /// Set the processor's current process ID
 pub fn set_current_process_id(process_id: usize) {
-    CURRENT_PROCESS_ID.store(process_id, Ordering::Relaxed);
+    processor_local()
+        .current_process_id
+        .store(process_id, Ordering::Relaxed);
 }
 
 /// Get the processor's current process ID
 pub fn get_current_process_id() -> usize {
-    CURRENT_PROCESS_ID.load(Ordering::Relaxed)
+    processor_local().current_process_id.load(Ordering::Relaxed)
+}
+
+/// Set the kernel stack this processor is running on
+pub fn set_kernel_stack(stack: Range<u64>) {
+    let local = processor_local();
+    local.kernel_stack_top.store(0, Ordering::Relaxed);
+    local
+        .kernel_stack_bottom
+        .store(stack.start, Ordering::Relaxed);
+    local.kernel_stack_top.store(stack.end, Ordering::Relaxed);
+}
+
+/// The kernel stack this processor is running on, if one was set
+pub fn kernel_stack() -> Option<Range<u64>> {
+    let local = processor_local();
+    match local.kernel_stack_top.load(Ordering::Relaxed) {
+        0 => None,
+        top => Some(local.kernel_stack_bottom.load(Ordering::Relaxed)..top),
+    }
 }
 
 /// Inform that we are begining an IRQ
 pub fn notify_begin_irq() {
-    HANDLING_IRQ.fetch_add(1, Ordering::Acquire);
+    processor_local()
+        .handling_irq
+        .fetch_add(1, Ordering::Acquire);
 }
 
 /// Inform that we are leaving an IRQ
 pub fn notify_end_irq() {
-    HANDLING_IRQ.fetch_sub(1, Ordering::Release);
+    processor_local()
+        .handling_irq
+        .fetch_sub(1, Ordering::Release);
 }
 
 /// Get if the processor is currently in an IRQ
 pub fn is_within_irq() -> bool {
-    HANDLING_IRQ.load(Ordering::Relaxed) > 0
+    processor_local().handling_irq.load(Ordering::Relaxed) > 0
 }
 
 /// Get the count of how many IRQ the processor is within
 pub fn irq_count() -> usize {
-    HANDLING_IRQ.load(Ordering::Relaxed)
+    processor_local().handling_irq.load(Ordering::Relaxed)
 }
 
 /// Inform that we are begining a critical section
 pub fn notify_begin_critical() {
-    HANDLING_CRITICAL.fetch_add(1, Ordering::Acquire);
+    processor_local()
+        .handling_critical
+        .fetch_add(1, Ordering::Acquire);
 }
 
 /// Inform that we are leaving a critical section
 pub fn notify_end_critical() {
-    HANDLING_CRITICAL.fetch_sub(1, Ordering::Release);
+    processor_local()
+        .handling_critical
+        .fetch_sub(1, Ordering::Release);
 }
 
 /// Get if the processor is currently in a critical section
 pub fn is_within_critical() -> bool {
-    HANDLING_CRITICAL.load(Ordering::Relaxed) > 0
+    processor_local().handling_critical.load(Ordering::Relaxed) > 0
 }
 
 /// Assert that we are (in/out) of a critical section
@@ -0,0 +1,175 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    processor::processor_local,
+    symbols::{KernelSymbol, KernelSymbols},
+};
+use alloc::{collections::btree_map::BTreeMap, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use lignan::logln;
+use memory_layout::KERNEL_SPACE;
+use vera_portal::ProfileError;
+
+/// The number of samples each processor keeps, once full the oldest samples are replaced
+pub const PROFILE_SAMPLES: usize = 16384;
+/// The number of symbols printed by [`dump`]
+const DUMP_TOP_SYMBOLS: usize = 32;
+
+static PROFILING: AtomicBool = AtomicBool::new(false);
+
+/// A ring buffer of the instruction pointers interrupted by the timer
+pub struct ProfileBuffer {
+    samples: [AtomicU64; PROFILE_SAMPLES],
+    written: AtomicUsize,
+}
+
+impl ProfileBuffer {
+    pub const fn new() -> Self {
+        Self {
+            samples: [const { AtomicU64::new(0) }; PROFILE_SAMPLES],
+            written: AtomicUsize::new(0),
+        }
+    }
+
+    fn record(&self, rip: u64) {
+        let index = self.written.fetch_add(1, Ordering::Relaxed) % PROFILE_SAMPLES;
+        self.samples[index].store(rip, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.written.store(0, Ordering::Relaxed);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        let len = self.written.load(Ordering::Relaxed).min(PROFILE_SAMPLES);
+        self.samples[..len]
+            .iter()
+            .map(|sample| sample.load(Ordering::Relaxed))
+    }
+}
+
+impl core::fmt::Debug for ProfileBuffer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ProfileBuffer")
+            .field("written", &self.written)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Record the instruction pointer a timer tick interrupted, if profiling
+pub fn sample(rip: u64) {
+    if PROFILING.load(Ordering::Relaxed) {
+        processor_local().profile_samples.record(rip);
+    }
+}
+
+/// Throw away any previous samples and start profiling
+pub fn start() -> Result<(), ProfileError> {
+    if PROFILING.load(Ordering::Relaxed) {
+        return Err(ProfileError::AlreadyRunning);
+    }
+
+    // FIXME: This should clear every processor's samples once we support SMP
+    processor_local().profile_samples.clear();
+    PROFILING.store(true, Ordering::Relaxed);
+
+    logln!("Profiling started");
+    Ok(())
+}
+
+/// Stop profiling, keeping the samples so they can be dumped
+pub fn stop() -> Result<(), ProfileError> {
+    if !PROFILING.swap(false, Ordering::Relaxed) {
+        return Err(ProfileError::NotRunning);
+    }
+
+    logln!("Profiling stopped");
+    Ok(())
+}
+
+/// Where a sample was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SampleLocation {
+    Kernel(&'static str),
+    UnknownKernel,
+    Userspace,
+}
+
+/// Print the functions that were sampled the most
+pub fn dump() {
+    let mut samples: Vec<u64> = processor_local().profile_samples.iter().collect();
+    samples.sort_unstable();
+
+    let total = samples.len();
+    if total == 0 {
+        logln!("No profile samples were taken");
+        return;
+    }
+
+    let symbols = KernelSymbols::get();
+    let mut kernel_symbols = symbols.iter().flat_map(KernelSymbols::iter).peekable();
+    let mut counts: BTreeMap<SampleLocation, usize> = BTreeMap::new();
+
+    // Both the samples and the symbols are sorted, so they can be matched in a single pass
+    for rip in samples {
+        let location = if !KERNEL_SPACE.contains(rip as usize) {
+            SampleLocation::Userspace
+        } else {
+            while kernel_symbols
+                .next_if(|symbol: &KernelSymbol| symbol.start + symbol.size <= rip)
+                .is_some()
+            {}
+
+            match kernel_symbols.peek() {
+                Some(symbol) if symbol.contains(rip) => SampleLocation::Kernel(symbol.name),
+                _ => SampleLocation::UnknownKernel,
+            }
+        };
+
+        *counts.entry(location).or_default() += 1;
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_unstable_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+
+    logln!(
+        "Profile ({total} samples{}):",
+        if symbols.is_none() {
+            ", no kernel symbol map"
+        } else {
+            ""
+        }
+    );
+    for (location, count) in counts.into_iter().take(DUMP_TOP_SYMBOLS) {
+        let percent = (count * 100) as f32 / total as f32;
+
+        match location {
+            SampleLocation::Kernel(name) => logln!("  {percent:>6.2}% {count:>6} {name}"),
+            SampleLocation::UnknownKernel => logln!("  {percent:>6.2}% {count:>6} [kernel]"),
+            SampleLocation::Userspace => logln!("  {percent:>6.2}% {count:>6} [userspace]"),
+        }
+    }
+}
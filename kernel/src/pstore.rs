@@ -0,0 +1,234 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Keeps the kernel's log in memory that survives a warm reboot, so a panic on hardware
+//! without a serial port can still be read on the next boot.
+//!
+//! The bootloader reserves the memory as `PhysMemoryKind::Pstore`. The log is written into
+//! it as a ring while the kernel runs, and the panic handler seals it with a checksum. Only
+//! a sealed log is trusted on the next boot, so memory left over from a clean reboot or a
+//! cold boot is never reported.
+
+use crate::{locks::ScheduleLock, vmm};
+use alloc::vec::Vec;
+use bootloader::KernelBootHeader;
+use core::fmt::Write;
+use lignan::{
+    lock::DebugMutex,
+    logln,
+    stream::{StreamConnection, add_stream_connection},
+    warnln,
+};
+use mem::{paging::CacheMode, phys::PhysMemoryKind};
+use util::crc32::{crc32, crc32_continue};
+
+/// Marks a log that was sealed by the panic handler
+const SEALED_MAGIC: u64 = u64::from_le_bytes(*b"VPSTORE!");
+/// How many of the last boot's final lines are logged at boot
+const REPUBLISH_LINES: usize = 40;
+
+static PSTORE: DebugMutex<Option<Pstore>> = DebugMutex::new(None);
+/// The log the last boot sealed, if it panicked
+static PREVIOUS_LOG: ScheduleLock<Option<Vec<u8>>> = ScheduleLock::new(None);
+
+/// Sits at the start of the pstore region, followed by the log's ring buffer
+#[repr(C)]
+struct PstoreHeader {
+    /// [`SEALED_MAGIC`] once sealed, or zero while the log is still being written
+    magic: u64,
+    /// Where the oldest byte of the ring is
+    start: u32,
+    /// How many bytes of the ring are used
+    len: u32,
+    /// The checksum of `start`, `len`, and the used bytes of the ring, oldest first
+    crc: u32,
+    _reserved: u32,
+}
+
+/// The pstore region, once mapped
+struct Pstore {
+    header: &'static mut PstoreHeader,
+    ring: &'static mut [u8],
+}
+
+impl Pstore {
+    /// The used bytes of the ring, oldest first
+    fn slices(&self) -> (&[u8], &[u8]) {
+        let start = self.header.start as usize;
+        let end = start + self.header.len as usize;
+
+        if end <= self.ring.len() {
+            (&self.ring[start..end], &[])
+        } else {
+            (&self.ring[start..], &self.ring[..end - self.ring.len()])
+        }
+    }
+
+    fn checksum(&self) -> u32 {
+        let (first, second) = self.slices();
+        let crc = crc32(&self.header.start.to_le_bytes());
+        let crc = crc32_continue(crc, &self.header.len.to_le_bytes());
+
+        crc32_continue(crc32_continue(crc, first), second)
+    }
+
+    /// The log a previous boot sealed, if this region holds one
+    fn sealed_log(&self) -> Option<Vec<u8>> {
+        let in_bounds = (self.header.start as usize) < self.ring.len()
+            && (self.header.len as usize) <= self.ring.len();
+
+        if self.header.magic != SEALED_MAGIC || !in_bounds || self.checksum() != self.header.crc {
+            return None;
+        }
+
+        let (first, second) = self.slices();
+        Some([first, second].concat())
+    }
+
+    /// Start a new empty log
+    fn reset(&mut self) {
+        self.header.magic = 0;
+        self.header.start = 0;
+        self.header.len = 0;
+        self.header.crc = 0;
+    }
+
+    /// Mark this log as complete, so the next boot will report it
+    fn seal(&mut self) {
+        self.header.crc = self.checksum();
+        self.header.magic = SEALED_MAGIC;
+    }
+}
+
+impl Write for Pstore {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let ring_len = self.ring.len();
+
+        for &byte in s.as_bytes() {
+            let (start, len) = (self.header.start as usize, self.header.len as usize);
+
+            if len == ring_len {
+                self.ring[start] = byte;
+                self.header.start = ((start + 1) % ring_len) as u32;
+            } else {
+                self.ring[(start + len) % ring_len] = byte;
+                self.header.len += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Map the pstore region, report the log of the last boot if it panicked, and start
+/// keeping this boot's log in it.
+///
+/// This maps memory, so it must happen after the kernel's page tables are ready.
+pub fn init(kbh: &KernelBootHeader) {
+    let Some(region) = kbh
+        .phys_mem_map
+        .iter()
+        .find(|region| region.kind == PhysMemoryKind::Pstore)
+    else {
+        warnln!("No pstore region, panic logs will not survive a reboot");
+        return;
+    };
+
+    // Write through, so the log is already in memory if the machine is reset
+    let virt = match vmm::map_mmio(region.start, region.len(), CacheMode::WriteThrough) {
+        Ok(virt) => virt,
+        Err(err) => {
+            warnln!("Unable to map the pstore: {err}");
+            return;
+        }
+    };
+
+    let header_len = size_of::<PstoreHeader>();
+    let mut pstore = unsafe {
+        Pstore {
+            header: &mut *virt.as_mut_ptr::<PstoreHeader>(),
+            ring: core::slice::from_raw_parts_mut(
+                virt.as_mut_ptr::<u8>().add(header_len),
+                region.len() - header_len,
+            ),
+        }
+    };
+
+    if let Some(log) = pstore.sealed_log() {
+        republish(&log);
+        *PREVIOUS_LOG.lock() = Some(log);
+    }
+
+    pstore.reset();
+    if let Some(mut slot) = PSTORE.try_lock() {
+        *slot = Some(pstore);
+    }
+
+    if add_stream_connection(StreamConnection::new(pstore_output)).is_none() {
+        warnln!("No free stream connection for the pstore");
+    }
+}
+
+/// Log the end of the last boot's log
+fn republish(log: &[u8]) {
+    let text = core::str::from_utf8(log).unwrap_or("<the last boot's log is not utf8>");
+    let lines = text.lines().count();
+
+    warnln!(
+        "The last boot panicked! Its final {} of {lines} log lines were:",
+        REPUBLISH_LINES.min(lines)
+    );
+    for line in text.lines().skip(lines.saturating_sub(REPUBLISH_LINES)) {
+        logln!("  | {line}");
+    }
+}
+
+fn pstore_output(args: core::fmt::Arguments) {
+    if let Some(pstore) = PSTORE
+        .try_lock()
+        .as_mut()
+        .and_then(|pstore| pstore.as_mut())
+    {
+        let _ = pstore.write_fmt(args);
+    }
+}
+
+/// Seal this boot's log, so the next boot reports it
+///
+/// This is called by the panic handler, after the panic has been logged.
+pub fn seal() {
+    if let Some(pstore) = PSTORE
+        .try_lock()
+        .as_mut()
+        .and_then(|pstore| pstore.as_mut())
+    {
+        pstore.seal();
+    }
+}
+
+/// A copy of the log the last boot sealed, if it panicked
+pub fn previous_log() -> Option<Vec<u8>> {
+    PREVIOUS_LOG.lock().clone()
+}
@@ -46,12 +46,8 @@ pub enum QemuExitStatus {
 pub fn exit_emulator(exit_status: QemuExitStatus) -> ! {
     let status = exit_status as u8;
 
-    unsafe {
-        QEMU_ISA_DEBUG_EXIT_IO_BASE.write_byte(status);
-
-        // Busy loop if we couldn't exit
-        loop {
-            core::arch::asm!("hlt");
-        }
-    }
+    unsafe { QEMU_ISA_DEBUG_EXIT_IO_BASE.write_byte(status) };
+
+    // Stop here if we couldn't exit
+    arch::idle::halt_forever()
 }
@@ -0,0 +1,216 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Which driver owns which I/O ports, MMIO regions, and IRQ lines.
+//!
+//! Drivers must [`claim`] the resources of a device before touching it, so two drivers can
+//! never end up programming the same hardware. The claims are the system's hardware
+//! inventory, which userspace can list with the `resource_info` syscall. A device's first
+//! claim, and its release, are published as system events.
+
+use crate::{
+    events::{self, SystemEvent},
+    locks::ScheduleLock,
+};
+use alloc::{string::String, vec::Vec};
+use core::fmt::Display;
+use mem::addr::PhysAddr;
+
+static CLAIMS: ScheduleLock<Vec<Claim>> = ScheduleLock::new(Vec::new());
+
+/// A piece of hardware a driver can own
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resource {
+    /// `len` I/O ports, starting at `base`
+    IoPorts { base: u16, len: u16 },
+    /// `len` bytes of registers, starting at `base`
+    Mmio { base: PhysAddr, len: usize },
+    /// An interrupt line on the PIC
+    Irq(u8),
+}
+
+impl Resource {
+    /// The first port, address, or IRQ line, and how many of them this covers
+    pub fn span(&self) -> (u64, u64) {
+        match *self {
+            Resource::IoPorts { base, len } => (base as u64, len as u64),
+            Resource::Mmio { base, len } => (base.addr() as u64, len as u64),
+            Resource::Irq(irq) => (irq as u64, 1),
+        }
+    }
+
+    /// If this and `other` cover any of the same hardware
+    pub fn overlaps(&self, other: &Resource) -> bool {
+        if core::mem::discriminant(self) != core::mem::discriminant(other) {
+            return false;
+        }
+
+        let (start, len) = self.span();
+        let (other_start, other_len) = other.span();
+        start < other_start + other_len && other_start < start + len
+    }
+
+    /// If this covers all of `other`
+    pub fn contains(&self, other: &Resource) -> bool {
+        if core::mem::discriminant(self) != core::mem::discriminant(other) {
+            return false;
+        }
+
+        let (start, len) = self.span();
+        let (other_start, other_len) = other.span();
+        start <= other_start && other_start + other_len <= start + len
+    }
+}
+
+impl Display for Resource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (start, len) = self.span();
+        match self {
+            Resource::IoPorts { .. } => write!(f, "IO {:#06x}-{:#06x}", start, start + len - 1),
+            Resource::Mmio { .. } => write!(f, "MMIO {:#x}-{:#x}", start, start + len - 1),
+            Resource::Irq(irq) => write!(f, "IRQ {irq}"),
+        }
+    }
+}
+
+/// How a claimed resource can be used by others
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sharing {
+    /// Only this driver may use it
+    Exclusive,
+    /// Other drivers may claim it as `Shared` too, like a PCI interrupt line
+    Shared,
+}
+
+/// A resource owned by a driver
+#[derive(Clone, Debug)]
+pub struct Claim {
+    pub owner: String,
+    pub resource: Resource,
+    pub sharing: Sharing,
+}
+
+/// Why a claim was refused
+#[derive(Clone, Debug)]
+pub enum ClaimError {
+    /// `resource` overlaps something `owner` has already claimed
+    Conflict { resource: Resource, owner: String },
+}
+
+impl Display for ClaimError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ClaimError::Conflict { resource, owner } => {
+                write!(f, "{resource} is already claimed by '{owner}'")
+            }
+        }
+    }
+}
+
+/// Claim every one of `resources` for `owner`, or none of them if any is already taken
+///
+/// An owner never conflicts with itself, claiming something it already has does nothing.
+pub fn claim(owner: &str, resources: &[(Resource, Sharing)]) -> Result<(), ClaimError> {
+    let mut claims = CLAIMS.lock();
+
+    for &(resource, sharing) in resources {
+        if let Some(existing) = claims.iter().find(|existing| {
+            existing.owner != owner
+                && existing.resource.overlaps(&resource)
+                && (sharing == Sharing::Exclusive || existing.sharing == Sharing::Exclusive)
+        }) {
+            return Err(ClaimError::Conflict {
+                resource,
+                owner: existing.owner.clone(),
+            });
+        }
+    }
+
+    let new_device = !claims.iter().any(|claim| claim.owner == owner);
+    for &(resource, sharing) in resources {
+        if !claims
+            .iter()
+            .any(|existing| existing.owner == owner && existing.resource == resource)
+        {
+            claims.push(Claim {
+                owner: String::from(owner),
+                resource,
+                sharing,
+            });
+        }
+    }
+
+    if new_device && !resources.is_empty() {
+        events::publish(SystemEvent::DeviceAdded {
+            owner: String::from(owner),
+        });
+    }
+    Ok(())
+}
+
+/// Give up everything `owner` has claimed, like when its device failed to start
+pub fn release(owner: &str) {
+    let mut claims = CLAIMS.lock();
+    let before = claims.len();
+    claims.retain(|claim| claim.owner != owner);
+
+    if claims.len() != before {
+        events::publish(SystemEvent::DeviceRemoved {
+            owner: String::from(owner),
+        });
+    }
+}
+
+/// Give up `owner`'s claim on exactly `resource`, returning `false` if it had no such claim
+pub fn release_one(owner: &str, resource: &Resource) -> bool {
+    let mut claims = CLAIMS.lock();
+    let Some(index) = claims
+        .iter()
+        .position(|claim| claim.owner == owner && claim.resource == *resource)
+    else {
+        return false;
+    };
+    claims.remove(index);
+
+    if !claims.iter().any(|claim| claim.owner == owner) {
+        events::publish(SystemEvent::DeviceRemoved {
+            owner: String::from(owner),
+        });
+    }
+    true
+}
+
+/// If `owner` has claimed all of `resource` with a single claim
+pub fn is_claimed_by(owner: &str, resource: &Resource) -> bool {
+    CLAIMS
+        .lock()
+        .iter()
+        .any(|claim| claim.owner == owner && claim.resource.contains(resource))
+}
+
+/// Get the claim numbered `index`, claims are numbered from 0 in the order they were made
+pub fn claim_at(index: usize) -> Option<Claim> {
+    CLAIMS.lock().get(index).cloned()
+}
@@ -0,0 +1,46 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use arch::pit825x::{channel_2_wait_cycles, speaker_play, speaker_stop};
+
+/// The tones played when the kernel panics, as `(hz, milliseconds)`
+const PANIC_TONES: [(u32, u32); 3] = [(880, 120), (660, 120), (440, 400)];
+
+/// Play `hz` on the PC speaker for `duration_ms`, spinning until it is done.
+///
+/// This counts the speaker's own square wave instead of using the timer, so it works with
+/// interrupts disabled.
+pub fn beep(hz: u32, duration_ms: u32) {
+    speaker_play(hz);
+    channel_2_wait_cycles(hz * duration_ms / 1000);
+    speaker_stop();
+}
+
+/// Play a falling tone so a panic can be noticed without a display or serial.
+pub fn panic_beep() {
+    for (hz, duration_ms) in PANIC_TONES {
+        beep(hz, duration_ms);
+    }
+}
@@ -0,0 +1,132 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::process::scheduler::Scheduler;
+use core::{
+    fmt::Display,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+use lignan::warnln;
+
+/// The name of the kernel's symbol map within the initfs
+///
+/// Each line of the map is `<start> <size> <name>`, with `start` and `size` in hex, and the
+/// lines sorted by `start`. It is generated from the kernel's ELF when the initfs is built.
+const SYMBOL_MAP_FILENAME: &str = "kernel.sym";
+
+// These are atomics instead of a lock so symbols can still be looked up while panicking
+static SYMBOL_MAP_PTR: AtomicPtr<u8> = AtomicPtr::new(core::ptr::null_mut());
+static SYMBOL_MAP_LEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Load the kernel's symbol map from the initfs
+pub fn init() {
+    let Some(map) = Scheduler::get().initfs_file(SYMBOL_MAP_FILENAME) else {
+        warnln!(
+            "No '{SYMBOL_MAP_FILENAME}' found in the initfs, kernel addresses will not be named"
+        );
+        return;
+    };
+
+    SYMBOL_MAP_LEN.store(map.len(), Ordering::Relaxed);
+    SYMBOL_MAP_PTR.store(map.as_ptr().cast_mut(), Ordering::Release);
+}
+
+/// A function within the kernel
+#[derive(Debug, Clone, Copy)]
+pub struct KernelSymbol {
+    pub name: &'static str,
+    pub start: u64,
+    pub size: u64,
+}
+
+impl KernelSymbol {
+    /// Check if `addr` is within this symbol
+    pub const fn contains(&self, addr: u64) -> bool {
+        addr >= self.start && addr - self.start < self.size
+    }
+}
+
+/// The kernel's symbol map
+#[derive(Clone, Copy)]
+pub struct KernelSymbols {
+    map: &'static str,
+}
+
+impl KernelSymbols {
+    /// Get the symbol map, if it has been loaded
+    pub fn get() -> Option<Self> {
+        let map_ptr = SYMBOL_MAP_PTR.load(Ordering::Acquire);
+        if map_ptr.is_null() {
+            return None;
+        }
+
+        let map =
+            unsafe { core::slice::from_raw_parts(map_ptr, SYMBOL_MAP_LEN.load(Ordering::Relaxed)) };
+        Some(Self {
+            map: core::str::from_utf8(map).ok()?,
+        })
+    }
+
+    /// All symbols, sorted by their start address
+    pub fn iter(&self) -> impl Iterator<Item = KernelSymbol> + use<> {
+        self.map.lines().filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let start = u64::from_str_radix(fields.next()?, 16).ok()?;
+            let size = u64::from_str_radix(fields.next()?, 16).ok()?;
+
+            Some(KernelSymbol {
+                name: fields.next()?,
+                start,
+                size,
+            })
+        })
+    }
+
+    /// Find the symbol that contains `addr`
+    pub fn lookup(&self, addr: u64) -> Option<KernelSymbol> {
+        self.iter()
+            .take_while(|symbol| symbol.start <= addr)
+            .find(|symbol| symbol.contains(addr))
+    }
+}
+
+/// Displays a kernel address along with the function it is within
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolizedAddr(pub u64);
+
+impl Display for SymbolizedAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match KernelSymbols::get().and_then(|symbols| symbols.lookup(self.0)) {
+            Some(symbol) => write!(
+                f,
+                "{:#018x} {}+{:#x}",
+                self.0,
+                symbol.name,
+                self.0 - symbol.start
+            ),
+            None => write!(f, "{:#018x}", self.0),
+        }
+    }
+}
@@ -23,15 +23,40 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use crate::process::{HandleError, Process, scheduler::Scheduler};
-use alloc::{format, string::String};
+use crate::{
+    console, events, heap_tracking, kconfig, keyboard,
+    process::{
+        ExitStatus, HandleError, HandleRights, Process, RefProcess, run_queue::CpuSet,
+        scheduler::Scheduler, shared::SharedMemory, thread::ThreadState,
+    },
+    processor, pstore,
+    resources::{self, Resource, Sharing},
+    timer,
+    usercopy::{UserCopyError, UserPtr, UserSlice, copy_from_user, copy_to_user, is_user_range},
+    vmm,
+};
+use alloc::{format, string::String, vec};
 use arch::io::IOPort;
+use core::mem::MaybeUninit;
 use lignan::{LogKind, warnln};
-use mem::paging::VmPermissions;
+use mem::{
+    addr::VirtAddr,
+    page::VirtPage,
+    paging::{CacheMode, VmPermissions},
+    pmm::use_pmm_ref,
+    vm::VmRegion,
+};
 use util::consts::PAGE_4K;
 use vera_portal::{
-    ConnectHandleError, DebugMsgError, ExitReason, MapMemoryError, MemoryLocation,
-    MemoryProtections, RecvHandleError, SendHandleError, ServeHandleError, VeraPortal, WaitSignal,
+    AffinityError, ChildStatus, CmdlineError, ConnectHandleError, CrashLogError, DebugMsgError,
+    DiskError, DiskInfo, ExitReason, FaultHandlerError, FramebufferError, FramebufferInfo,
+    FutexError, HandleDuplicateError, HandleOriginError, HandleTransferError, HandleWaitError,
+    HeapDumpError, InitfsError, IoClaimError, KeymapError, LogRingError, LogRingMapping,
+    MAX_DISK_READ, MapMemoryError, MemoryInfo, MemoryLocation, MemoryProtections, NICE_RANGE,
+    NiceError, PipeHandles, ProfileCommand, ProfileError, RecvHandleError, ResourceInfo,
+    ResourceInfoError, ResourceKind, ScreenshotError, SendHandleError, ServeHandleError,
+    SharedMemoryError, SpawnError, TaskInfo, TaskInfoError, TaskState, TraceError, VeraPortal,
+    VideoModeError, VmCacheMode, VmDebugError, VmTranslation, WaitError, WaitSignal, capabilities,
     sys_server::VeraPortalServer,
 };
 
@@ -44,6 +69,8 @@ extern "C" fn syscall_handler(
     r8: u64,
     syscall_number: u64,
 ) -> u64 {
+    // Syscalls run with user access denied, every touch of user memory goes through the
+    // user copies in `usercopy`
     unsafe {
         crate::syscall_handler::KernelSyscalls::from_syscall(syscall_number, rdi, rsi, rdx, r8)
     }
@@ -51,9 +78,78 @@ extern "C" fn syscall_handler(
 
 pub struct KernelSyscalls {}
 
+/// Copy a name (like an endpoint) out of user memory
+///
+/// The portal hands us references straight into user memory, so they need to be copied
+/// before we can trust them.
+fn read_user_name(name: &str) -> Result<String, UserCopyError> {
+    UserSlice::new(name.as_ptr(), name.len())
+        .with_max_len(UserSlice::MAX_STR_LEN)?
+        .read_to_string()
+}
+
+/// If the current process has claimed the `len` IO ports starting at `base`
+fn owns_io_ports(base: u16, len: u16) -> bool {
+    let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+    resources::is_claimed_by(
+        &current_thread.process.resource_owner(),
+        &Resource::IoPorts { base, len },
+    )
+}
+
+/// Why the caller can't change another process
+enum TargetError {
+    NoSuchProcess,
+    PermissionDenied,
+}
+
+/// Find process `pid` if `caller` is allowed to change it, which is only itself and its
+/// own children.
+fn controlled_process(caller: &RefProcess, pid: usize) -> Result<RefProcess, TargetError> {
+    if pid == caller.id {
+        return Ok(caller.clone());
+    }
+
+    match caller.child(pid) {
+        Some(child) => Ok(child),
+        None if Scheduler::get().process(pid).is_some() => Err(TargetError::PermissionDenied),
+        None => Err(TargetError::NoSuchProcess),
+    }
+}
+
 impl VeraPortalServer for KernelSyscalls {
-    fn verify_user_ptr<T: Sized>(_ptr: *const T) -> bool {
-        true
+    fn verify_user_ptr<T: Sized>(ptr: *const T) -> bool {
+        UserPtr::new(ptr.cast_mut()).check_readable().is_ok()
+    }
+
+    unsafe fn read_user_input<T: Sized>(ptr: *const T) -> Option<T> {
+        let mut input = MaybeUninit::<T>::zeroed();
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(input.as_mut_ptr().cast::<u8>(), size_of::<T>())
+        };
+        copy_from_user(bytes, ptr.cast()).ok()?;
+
+        Some(unsafe { input.assume_init() })
+    }
+
+    unsafe fn write_user_output<T: Sized>(ptr: *mut T, value: T) -> bool {
+        // Start from zeros so no stale kernel stack ends up in the user's padding
+        let mut output = MaybeUninit::<T>::zeroed();
+        output.write(value);
+        let bytes =
+            unsafe { core::slice::from_raw_parts(output.as_ptr().cast::<u8>(), size_of::<T>()) };
+
+        copy_to_user(ptr.cast(), bytes).is_ok()
+    }
+
+    #[cfg(feature = "syscall-trace")]
+    fn trace_enter(endpoint: &'static str, arguments: core::fmt::Arguments) {
+        crate::syscall_trace::enter(endpoint, arguments);
+    }
+
+    #[cfg(feature = "syscall-trace")]
+    fn trace_exit(_endpoint: &'static str, output: Option<&dyn core::fmt::Debug>) {
+        crate::syscall_trace::exit(output);
     }
 }
 
@@ -65,6 +161,11 @@ impl VeraPortal for KernelSyscalls {
             current_thread.process.name,
             exit_reason
         );
+        current_thread
+            .process
+            .record_exit(ExitStatus::Exited(exit_reason));
+        drop(current_thread);
+
         Scheduler::crash_current();
         unreachable!();
     }
@@ -102,7 +203,192 @@ impl VeraPortal for KernelSyscalls {
         current_thread.process.id
     }
 
+    fn vm_dump(pid: usize, start: usize, end: usize) -> Result<usize, VmDebugError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread.process.has_capability(capabilities::DEBUG) {
+            return Err(VmDebugError::PermissionDenied);
+        }
+
+        let process = Scheduler::get()
+            .process(pid)
+            .ok_or(VmDebugError::NoSuchProcess)?;
+        let start = VirtAddr::new_checked(start).map_err(|_| VmDebugError::InvalidRange)?;
+        let end = VirtAddr::new_checked(end).map_err(|_| VmDebugError::InvalidRange)?;
+
+        if start > end {
+            return Err(VmDebugError::InvalidRange);
+        }
+
+        Ok(vmm::dump(&process, VmRegion::from_containing(start, end)))
+    }
+
+    fn vm_translate(pid: usize, virt: usize) -> Result<VmTranslation, VmDebugError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread.process.has_capability(capabilities::DEBUG) {
+            return Err(VmDebugError::PermissionDenied);
+        }
+
+        let process = Scheduler::get()
+            .process(pid)
+            .ok_or(VmDebugError::NoSuchProcess)?;
+        let virt = VirtAddr::new_checked(virt).map_err(|_| VmDebugError::InvalidRange)?;
+        let mapping = process.translate(virt).ok_or(VmDebugError::NotMapped)?;
+
+        Ok(VmTranslation {
+            phys: mapping.translate(virt).addr() as u64,
+            page_size: mapping.size as u64,
+            writable: mapping.writable,
+            no_exec: mapping.no_exec,
+            user: mapping.user,
+            global: mapping.global,
+            cache: match mapping.cache {
+                CacheMode::WriteBack => VmCacheMode::WriteBack,
+                CacheMode::WriteThrough => VmCacheMode::WriteThrough,
+                CacheMode::WriteCombining => VmCacheMode::WriteCombining,
+                CacheMode::Uncached => VmCacheMode::Uncached,
+            },
+        })
+    }
+
+    fn previous_crash_log(buf: &mut [u8]) -> Result<usize, CrashLogError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread
+            .process
+            .has_capability(capabilities::LOG_RING)
+            && !current_thread.process.has_capability(capabilities::DEBUG)
+        {
+            return Err(CrashLogError::PermissionDenied);
+        }
+
+        let user_buf =
+            UserSlice::new_mut(buf.as_mut_ptr(), buf.len()).truncate(UserSlice::MAX_TRANSFER_LEN);
+        user_buf
+            .check_writable()
+            .map_err(|_| CrashLogError::InvalidPtr)?;
+
+        let log = pstore::previous_log().ok_or(CrashLogError::NoCrashLog)?;
+        let copied = log.len().min(user_buf.len());
+        user_buf
+            .write_from(&log[..copied])
+            .map_err(|_| CrashLogError::InvalidPtr)?;
+
+        Ok(log.len())
+    }
+
+    fn resource_info(index: usize) -> Result<ResourceInfo, ResourceInfoError> {
+        let claim = resources::claim_at(index).ok_or(ResourceInfoError::NoSuchClaim)?;
+
+        let mut owner = [0; 32];
+        let owner_len = claim.owner.len().min(owner.len());
+        owner[..owner_len].copy_from_slice(&claim.owner.as_bytes()[..owner_len]);
+
+        let (start, len) = claim.resource.span();
+        Ok(ResourceInfo {
+            owner,
+            kind: match claim.resource {
+                Resource::IoPorts { .. } => ResourceKind::IoPorts,
+                Resource::Mmio { .. } => ResourceKind::Mmio,
+                Resource::Irq(_) => ResourceKind::Irq,
+            },
+            start,
+            len,
+            shared: claim.sharing == Sharing::Shared,
+        })
+    }
+
+    fn claim_io_ports(base: u16, len: u16, shared: bool) -> Result<(), IoClaimError> {
+        if len == 0 || base.checked_add(len - 1).is_none() {
+            return Err(IoClaimError::InvalidRange);
+        }
+
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread
+            .process
+            .has_capability(capabilities::IO_PORTS)
+        {
+            return Err(IoClaimError::PermissionDenied);
+        }
+
+        let ports = Resource::IoPorts { base, len };
+        let sharing = if shared {
+            Sharing::Shared
+        } else {
+            Sharing::Exclusive
+        };
+        resources::claim(
+            &current_thread.process.resource_owner(),
+            &[(ports, sharing)],
+        )
+        .map_err(|_| IoClaimError::AlreadyClaimed)
+    }
+
+    fn release_io_ports(base: u16, len: u16) -> Result<(), IoClaimError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        let ports = Resource::IoPorts { base, len };
+
+        if resources::release_one(&current_thread.process.resource_owner(), &ports) {
+            Ok(())
+        } else {
+            Err(IoClaimError::NotClaimed)
+        }
+    }
+
+    fn kernel_cmdline(buf: &mut [u8]) -> Result<usize, CmdlineError> {
+        let user_buf =
+            UserSlice::new_mut(buf.as_mut_ptr(), buf.len()).truncate(UserSlice::MAX_TRANSFER_LEN);
+        user_buf
+            .check_writable()
+            .map_err(|_| CmdlineError::InvalidPtr)?;
+
+        let cmdline = kconfig::cmdline();
+        let cmdline = cmdline.as_str().as_bytes();
+        let copied = cmdline.len().min(user_buf.len());
+        user_buf
+            .write_from(&cmdline[..copied])
+            .map_err(|_| CmdlineError::InvalidPtr)?;
+
+        Ok(cmdline.len())
+    }
+
+    fn framebuffer_set_mode(
+        width: u32,
+        height: u32,
+        bits_per_pixel: u8,
+    ) -> Result<FramebufferInfo, VideoModeError> {
+        #[cfg(feature = "gfx")]
+        {
+            let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+            crate::gfx::set_mode_for(&current_thread.process, width, height, bits_per_pixel)
+        }
+
+        #[cfg(not(feature = "gfx"))]
+        {
+            let _ = (width, height, bits_per_pixel);
+            Err(VideoModeError::NotSupported)
+        }
+    }
+
+    fn framebuffer_flush(x: u32, y: u32, width: u32, height: u32) -> Result<(), VideoModeError> {
+        #[cfg(feature = "gfx")]
+        {
+            let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+            crate::gfx::flush_for(&current_thread.process, x, y, width, height)
+        }
+
+        #[cfg(not(feature = "gfx"))]
+        {
+            let _ = (x, y, width, height);
+            Err(VideoModeError::NotOwner)
+        }
+    }
+
     fn debug_msg(msg: &str) -> Result<(), DebugMsgError> {
+        let msg = UserSlice::new(msg.as_ptr(), msg.len())
+            .with_max_len(UserSlice::MAX_TRANSFER_LEN)
+            .map_err(|_| DebugMsgError::InvalidLength(msg.len()))?
+            .read_to_string()
+            .map_err(|_| DebugMsgError::InvalidPtr(msg.as_ptr()))?;
+
         let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
         let process_fmt = format!(
             "{:<24}p{:02x}t{:02x}",
@@ -110,6 +396,7 @@ impl VeraPortal for KernelSyscalls {
         );
 
         lignan::priv_print(LogKind::Log, &process_fmt, format_args!("{}", msg));
+        console::write(console::USER_CONSOLE, format_args!("{}", msg));
 
         Ok(())
     }
@@ -119,49 +406,75 @@ impl VeraPortal for KernelSyscalls {
     }
 
     fn recv(handle: u64, buf: &mut [u8]) -> Result<usize, RecvHandleError> {
+        let user_buf =
+            UserSlice::new_mut(buf.as_mut_ptr(), buf.len()).truncate(UserSlice::MAX_TRANSFER_LEN);
+        user_buf
+            .check_writable()
+            .map_err(|_| RecvHandleError::InvalidPtr)?;
+
         let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
-        current_thread
+        let mut kernel_buf = vec![0; user_buf.len()];
+        let bytes = current_thread
             .process
-            .handle_rx(handle, buf)
+            .handle_rx(handle, &mut kernel_buf)
             .map_err(|err| match err {
                 HandleError::HandleDoesntExist(_) => RecvHandleError::InvalidHandle,
-                HandleError::InvalidSocketKind | HandleError::HostDisconnect => {
-                    RecvHandleError::RecvFailed
-                }
+                HandleError::InvalidSocketKind
+                | HandleError::HostDisconnect
+                | HandleError::BrokenPipe => RecvHandleError::RecvFailed,
                 HandleError::WouldBlock => RecvHandleError::WouldBlock,
-            })
+                HandleError::PermissionDenied => RecvHandleError::PermissionDenied,
+            })?;
+
+        user_buf
+            .write_from(&kernel_buf[..bytes])
+            .map_err(|_| RecvHandleError::InvalidPtr)?;
+
+        Ok(bytes)
     }
 
     fn send(handle: u64, buf: &[u8]) -> Result<usize, SendHandleError> {
+        let buf = UserSlice::new(buf.as_ptr(), buf.len())
+            .with_max_len(UserSlice::MAX_TRANSFER_LEN)
+            .map_err(|_| SendHandleError::InvalidLength(buf.len()))?
+            .read_to_vec()
+            .map_err(|_| SendHandleError::InvalidPtr)?;
+
         let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
         current_thread
             .process
-            .handle_tx(handle, buf)
+            .handle_tx(handle, &buf)
             .map_err(|err| match err {
                 HandleError::HandleDoesntExist(_) => SendHandleError::InvalidHandle,
                 HandleError::InvalidSocketKind | HandleError::HostDisconnect => {
                     SendHandleError::SendFailed
                 }
                 HandleError::WouldBlock => SendHandleError::WouldBlock,
+                HandleError::BrokenPipe => SendHandleError::BrokenPipe,
+                HandleError::PermissionDenied => SendHandleError::PermissionDenied,
             })
     }
 
     fn serve(endpoint: &str) -> Result<u64, ServeHandleError> {
+        let endpoint = read_user_name(endpoint).map_err(|_| ServeHandleError::InvalidName)?;
+
         let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
-        Process::new_connection_handle(current_thread.process.clone(), String::from(endpoint))
+        Process::new_connection_handle(current_thread.process.clone(), endpoint)
             .ok_or(ServeHandleError::AlreadyBound)
     }
 
     fn connect(endpoint: &str) -> Result<u64, ConnectHandleError> {
+        let endpoint = read_user_name(endpoint).map_err(|_| ConnectHandleError::InvalidName)?;
+
         let s = Scheduler::get();
         let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
 
         // Get the handle owner
-        let Some((owner, owner_id)) = s.serve_sockets.lock().get(endpoint).cloned() else {
+        let Some((owner, owner_id)) = s.serve_sockets.lock().get(&endpoint).cloned() else {
             return Err(ConnectHandleError::EndpointDoesNotExist);
         };
         let Some(owner) = owner.upgrade() else {
-            s.serve_sockets.lock().remove(endpoint);
+            s.serve_sockets.lock().remove(&endpoint);
             return Err(ConnectHandleError::EndpointDoesNotExist);
         };
 
@@ -175,6 +488,543 @@ impl VeraPortal for KernelSyscalls {
         Process::disconnect_handle(current_thread.process.clone(), handle);
     }
 
+    fn fault_handler(endpoint: &str) -> Result<(), FaultHandlerError> {
+        let endpoint = read_user_name(endpoint).map_err(|_| FaultHandlerError::InvalidName)?;
+
+        let s = Scheduler::get();
+        let current_thread = s.current_thread().upgrade().unwrap();
+
+        let Some((owner, _)) = s.serve_sockets.lock().get(&endpoint).cloned() else {
+            return Err(FaultHandlerError::EndpointDoesNotExist);
+        };
+
+        current_thread.process.set_fault_handler(owner);
+        Ok(())
+    }
+
+    fn spawn(name: &str) -> Result<usize, SpawnError> {
+        Self::spawn_with(name, 0)
+    }
+
+    fn spawn_with(name: &str, capabilities: u64) -> Result<usize, SpawnError> {
+        let name = read_user_name(name).map_err(|_| SpawnError::InvalidName)?;
+
+        let s = Scheduler::get();
+        let current_thread = s.current_thread().upgrade().unwrap();
+
+        // A process can only hand out capabilities it has itself
+        if !current_thread.process.has_capability(capabilities) {
+            return Err(SpawnError::PermissionDenied);
+        }
+
+        s.spawn_initfs_child(&name, &current_thread.process, capabilities)
+            .map(|child| child.id)
+            .ok_or(SpawnError::NotFound)
+    }
+
+    fn wait(pid: usize) -> Result<ChildStatus, WaitError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+
+        match current_thread.process.wait_child(pid) {
+            Some(ExitStatus::Exited(ExitReason::Success)) => Ok(ChildStatus::Success),
+            Some(ExitStatus::Exited(ExitReason::Failure)) => Ok(ChildStatus::Failure),
+            Some(ExitStatus::Faulted(fault)) => Ok(ChildStatus::Faulted { fault }),
+            None => Err(WaitError::NotAChild),
+        }
+    }
+
+    fn shared_create(bytes: usize) -> Result<u64, SharedMemoryError> {
+        if bytes == 0 {
+            return Err(SharedMemoryError::InvalidLength(bytes));
+        }
+
+        let shared = SharedMemory::create(bytes.div_ceil(PAGE_4K))?;
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+
+        Ok(current_thread.process.new_shared_handle(shared))
+    }
+
+    fn shared_map(handle: u64, writable: bool) -> Result<*mut u8, SharedMemoryError> {
+        let needed = if writable {
+            HandleRights::MAP.union(HandleRights::WRITE)
+        } else {
+            HandleRights::MAP
+        };
+
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        let shared = current_thread
+            .process
+            .shared_memory(handle, needed)
+            .map_err(|err| match err {
+                HandleError::PermissionDenied => SharedMemoryError::PermissionDenied,
+                _ => SharedMemoryError::InvalidId,
+            })?;
+
+        current_thread
+            .process
+            .map_shared_memory(shared, writable)
+            .map(|page| page.addr().as_mut_ptr())
+    }
+
+    fn shared_len(handle: u64) -> Result<usize, SharedMemoryError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+
+        current_thread
+            .process
+            .shared_memory(handle, HandleRights::empty())
+            .map(|shared| shared.n_pages() * PAGE_4K)
+            .map_err(|_| SharedMemoryError::InvalidId)
+    }
+
+    fn shared_unmap(ptr: *mut u8) -> Result<(), SharedMemoryError> {
+        let start = VirtAddr::new_checked(ptr.addr()).map_err(|_| SharedMemoryError::NotMapped)?;
+        let start = VirtPage::containing_addr(start);
+        if start.addr().addr() != ptr.addr() {
+            return Err(SharedMemoryError::NotMapped);
+        }
+
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        current_thread.process.unmap_shared_memory(start)
+    }
+
+    fn pipe_create() -> PipeHandles {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        let (read, write) = current_thread.process.new_pipe();
+
+        PipeHandles { read, write }
+    }
+
+    fn handle_transfer(handle: u64, pid: usize) -> Result<u64, HandleTransferError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        let child = current_thread
+            .process
+            .child(pid)
+            .ok_or(HandleTransferError::NotAChild)?;
+
+        current_thread
+            .process
+            .transfer_handle(handle, &child)
+            .map_err(|err| match err {
+                HandleError::InvalidSocketKind => HandleTransferError::NotTransferable,
+                HandleError::PermissionDenied => HandleTransferError::PermissionDenied,
+                _ => HandleTransferError::InvalidHandle,
+            })
+    }
+
+    fn handle_send(handle: u64, connection: u64) -> Result<u64, HandleTransferError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+
+        current_thread
+            .process
+            .send_handle(handle, connection)
+            .map_err(|err| match err {
+                HandleError::PermissionDenied => HandleTransferError::PermissionDenied,
+                HandleError::InvalidSocketKind | HandleError::HostDisconnect => {
+                    HandleTransferError::NotAConnection
+                }
+                _ => HandleTransferError::InvalidHandle,
+            })
+    }
+
+    fn handle_origin(handle: u64) -> Result<u64, HandleOriginError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+
+        current_thread
+            .process
+            .handle_origin(handle)
+            .map_err(|_| HandleOriginError::InvalidHandle)?
+            .ok_or(HandleOriginError::NotSent)
+    }
+
+    fn handle_duplicate(handle: u64, rights: u64) -> Result<u64, HandleDuplicateError> {
+        let rights = HandleRights::from_bits(rights).ok_or(HandleDuplicateError::InvalidRights)?;
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+
+        current_thread
+            .process
+            .duplicate_handle(handle, rights)
+            .map_err(|err| match err {
+                HandleError::InvalidSocketKind => HandleDuplicateError::NotDuplicable,
+                HandleError::PermissionDenied => HandleDuplicateError::PermissionDenied,
+                _ => HandleDuplicateError::InvalidHandle,
+            })
+    }
+
+    fn now_unix() -> u64 {
+        timer::now_unix()
+    }
+
+    fn monotonic_ns() -> u64 {
+        timer::monotonic_ns()
+    }
+
+    fn sleep_ns(ns: u64) {
+        let ticks = ns.div_ceil(timer::NS_PER_TICK);
+        Scheduler::sleep_until(timer::kernel_ticks() + ticks);
+    }
+
+    fn debug_screenshot() -> Result<(), ScreenshotError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread.process.has_capability(capabilities::DEBUG) {
+            return Err(ScreenshotError::PermissionDenied);
+        }
+
+        #[cfg(feature = "gfx")]
+        return crate::gfx::dump_framebuffer();
+
+        // The framebuffer always belongs to the kernel's consoles without `gfx`
+        #[cfg(not(feature = "gfx"))]
+        Err(ScreenshotError::NoFramebuffer)
+    }
+
+    fn debug_screenshot_read(buf: &mut [u8]) -> Result<usize, ScreenshotError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread.process.has_capability(capabilities::DEBUG) {
+            return Err(ScreenshotError::PermissionDenied);
+        }
+
+        #[cfg(feature = "gfx")]
+        {
+            let user_buf = UserSlice::new_mut(buf.as_mut_ptr(), buf.len())
+                .truncate(crate::gfx::MAX_SCREENSHOT_LEN);
+            user_buf
+                .check_writable()
+                .map_err(|_| ScreenshotError::InvalidPtr)?;
+
+            let image = crate::gfx::snapshot_framebuffer()?;
+            let copied = image.len().min(user_buf.len());
+            user_buf
+                .write_from(&image[..copied])
+                .map_err(|_| ScreenshotError::InvalidPtr)?;
+
+            Ok(image.len())
+        }
+
+        #[cfg(not(feature = "gfx"))]
+        {
+            let _ = buf;
+            Err(ScreenshotError::NoFramebuffer)
+        }
+    }
+
+    fn disk_info(index: usize) -> Result<DiskInfo, DiskError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread.process.has_capability(capabilities::DISKS) {
+            return Err(DiskError::PermissionDenied);
+        }
+
+        #[cfg(feature = "usb")]
+        {
+            let (model, blocks) =
+                crate::usb::storage::disk_info(index).ok_or(DiskError::NoSuchDisk)?;
+            Ok(DiskInfo {
+                model,
+                blocks,
+                block_size: crate::usb::storage::BLOCK_SIZE as u64,
+            })
+        }
+
+        // USB disks are the only disks the kernel drives
+        #[cfg(not(feature = "usb"))]
+        {
+            let _ = index;
+            Err(DiskError::NoSuchDisk)
+        }
+    }
+
+    fn disk_read(index: usize, block: u64, buf: &mut [u8]) -> Result<(), DiskError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread.process.has_capability(capabilities::DISKS) {
+            return Err(DiskError::PermissionDenied);
+        }
+        if buf.len() > MAX_DISK_READ {
+            return Err(DiskError::OutOfRange);
+        }
+
+        let user_buf = UserSlice::new_mut(buf.as_mut_ptr(), buf.len());
+        user_buf
+            .check_writable()
+            .map_err(|_| DiskError::InvalidPtr)?;
+
+        #[cfg(feature = "usb")]
+        {
+            use crate::usb::storage::{StorageError, read_disk};
+
+            let mut data = vec![0; user_buf.len()];
+            match read_disk(index, block, &mut data).ok_or(DiskError::NoSuchDisk)? {
+                Ok(()) => (),
+                Err(StorageError::OutOfRange) => return Err(DiskError::OutOfRange),
+                Err(_) => return Err(DiskError::ReadFailed),
+            }
+
+            user_buf
+                .write_from(&data)
+                .map_err(|_| DiskError::InvalidPtr)
+        }
+
+        #[cfg(not(feature = "usb"))]
+        {
+            let _ = (index, block);
+            Err(DiskError::NoSuchDisk)
+        }
+    }
+
+    fn framebuffer_map() -> Result<FramebufferInfo, FramebufferError> {
+        #[cfg(feature = "gfx")]
+        {
+            let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+            crate::gfx::take_framebuffer(&current_thread.process)
+        }
+
+        #[cfg(not(feature = "gfx"))]
+        Err(FramebufferError::NoFramebuffer)
+    }
+
+    fn heap_dump(since_generation: u64) -> Result<u64, HeapDumpError> {
+        heap_tracking::dump(since_generation)
+    }
+
+    fn profile(command: ProfileCommand) -> Result<(), ProfileError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread.process.has_capability(capabilities::DEBUG) {
+            return Err(ProfileError::PermissionDenied);
+        }
+
+        #[cfg(feature = "profile")]
+        match command {
+            ProfileCommand::Start => crate::profile::start(),
+            ProfileCommand::Stop => crate::profile::stop(),
+            ProfileCommand::Dump => {
+                crate::profile::dump();
+                Ok(())
+            }
+        }
+
+        #[cfg(not(feature = "profile"))]
+        {
+            let _ = command;
+            Err(ProfileError::ProfilingDisabled)
+        }
+    }
+
+    fn futex_wait(word: *const u32, expected: u32) -> Result<(), FutexError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        let read_word = || {
+            let mut bytes = [0; size_of::<u32>()];
+            copy_from_user(&mut bytes, word.cast())
+                .map(|_| u32::from_ne_bytes(bytes))
+                .map_err(|_| FutexError::InvalidPtr)
+        };
+
+        if !word.is_aligned() {
+            return Err(FutexError::InvalidPtr);
+        }
+        read_word()?;
+
+        // The word is checked again once we are queued, so a wake between here and
+        // blocking is not lost.
+        current_thread.process.futexes.wait(word.addr(), || {
+            read_word().is_ok_and(|value| value == expected)
+        });
+
+        Ok(())
+    }
+
+    fn futex_wake(word: *const u32, count: usize) -> Result<usize, FutexError> {
+        if !word.is_aligned() || !is_user_range(word.addr(), size_of::<u32>()) {
+            return Err(FutexError::InvalidPtr);
+        }
+
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        Ok(current_thread.process.futexes.wake(word.addr(), count))
+    }
+
+    fn task_info(index: usize) -> Result<TaskInfo, TaskInfoError> {
+        let thread = Scheduler::get()
+            .thread_at(index)
+            .ok_or(TaskInfoError::NoSuchTask)?;
+
+        let mut name = [0; 32];
+        let name_len = thread.process.name.len().min(name.len());
+        name[..name_len].copy_from_slice(&thread.process.name.as_bytes()[..name_len]);
+
+        Ok(TaskInfo {
+            pid: thread.process.id,
+            tid: thread.id,
+            name,
+            state: match thread.state() {
+                ThreadState::Running => TaskState::Running,
+                ThreadState::Ready => TaskState::Ready,
+                ThreadState::Blocked => TaskState::Blocked,
+                ThreadState::Sleeping => TaskState::Sleeping,
+                ThreadState::Zombie => TaskState::Zombie,
+            },
+            nice: thread.nice(),
+            cpu_ns: thread.cpu_ticks() * timer::NS_PER_TICK,
+            stack_used: thread.task.borrow().stack_high_water() as u64,
+            stack_len: thread.task.borrow().stack_len() as u64,
+        })
+    }
+
+    fn set_nice(pid: usize, nice: i8) -> Result<(), NiceError> {
+        if !NICE_RANGE.contains(&nice) {
+            return Err(NiceError::InvalidNice);
+        }
+
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        let target = controlled_process(&current_thread.process, pid).map_err(|err| match err {
+            TargetError::NoSuchProcess => NiceError::NoSuchProcess,
+            TargetError::PermissionDenied => NiceError::PermissionDenied,
+        })?;
+
+        if nice < current_thread.nice() {
+            return Err(NiceError::PermissionDenied);
+        }
+
+        target.set_nice(nice);
+        Ok(())
+    }
+
+    fn set_affinity(pid: usize, cpus: u64) -> Result<(), AffinityError> {
+        let cpus = CpuSet::from_bits(cpus);
+        if cpus.limit(processor::cpu_count()).is_empty() {
+            return Err(AffinityError::NoUsableCpus);
+        }
+
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        let target = controlled_process(&current_thread.process, pid).map_err(|err| match err {
+            TargetError::NoSuchProcess => AffinityError::NoSuchProcess,
+            TargetError::PermissionDenied => AffinityError::PermissionDenied,
+        })?;
+
+        target.set_affinity(cpus);
+        Ok(())
+    }
+
+    fn trace_syscalls(pid: usize, enabled: bool) -> Result<(), TraceError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        let target = controlled_process(&current_thread.process, pid).map_err(|err| match err {
+            TargetError::NoSuchProcess => TraceError::NoSuchProcess,
+            TargetError::PermissionDenied => TraceError::PermissionDenied,
+        })?;
+
+        #[cfg(feature = "syscall-trace")]
+        {
+            target.set_tracing(enabled);
+            Ok(())
+        }
+
+        #[cfg(not(feature = "syscall-trace"))]
+        {
+            let _ = (target, enabled);
+            Err(TraceError::TracingDisabled)
+        }
+    }
+
+    fn log_ring_attach() -> Result<LogRingMapping, LogRingError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread
+            .process
+            .has_capability(capabilities::LOG_RING)
+        {
+            return Err(LogRingError::PermissionDenied);
+        }
+
+        #[cfg(feature = "log-ring")]
+        return crate::log_ring::attach(&current_thread.process);
+
+        #[cfg(not(feature = "log-ring"))]
+        Err(LogRingError::Unavailable)
+    }
+
+    fn system_events(subscribe: bool) {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        events::set_process_subscribed(&current_thread.process, subscribe);
+    }
+
+    fn signal_timer(ns: u64) {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        timer::add_signal_timer(&current_thread.process, ns);
+    }
+
+    fn memory_info() -> MemoryInfo {
+        let (total_pages, free_pages) =
+            use_pmm_ref(|pmm| (pmm.usable_pages(), pmm.pages_free().unwrap_or(0)));
+
+        #[cfg(feature = "virtio-balloon")]
+        let (balloon_pages, balloon_target_pages) = crate::virtio::balloon::pages();
+        #[cfg(not(feature = "virtio-balloon"))]
+        let (balloon_pages, balloon_target_pages) = (0, 0);
+
+        MemoryInfo {
+            total_pages,
+            free_pages,
+            balloon_pages,
+            balloon_target_pages,
+        }
+    }
+
+    fn initfs_read(name: &str, buf: &mut [u8]) -> Result<usize, InitfsError> {
+        let name = read_user_name(name).map_err(|_| InitfsError::InvalidName)?;
+        let user_buf =
+            UserSlice::new_mut(buf.as_mut_ptr(), buf.len()).truncate(UserSlice::MAX_TRANSFER_LEN);
+        user_buf
+            .check_writable()
+            .map_err(|_| InitfsError::InvalidPtr)?;
+
+        let file = Scheduler::get()
+            .initfs_file(&name)
+            .ok_or(InitfsError::NotFound)?;
+        let copied = file.len().min(user_buf.len());
+        user_buf
+            .write_from(&file[..copied])
+            .map_err(|_| InitfsError::InvalidPtr)?;
+
+        Ok(file.len())
+    }
+
+    fn keymap_set(name: &str) -> Result<(), KeymapError> {
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        if !current_thread.process.has_capability(capabilities::SYSTEM) {
+            return Err(KeymapError::PermissionDenied);
+        }
+
+        let name = read_user_name(name).map_err(|_| KeymapError::InvalidName)?;
+        keyboard::set_keymap(&name)
+    }
+
+    fn framebuffer_release() -> Result<(), FramebufferError> {
+        #[cfg(feature = "gfx")]
+        {
+            let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+            crate::gfx::release_framebuffer(&current_thread.process)
+        }
+
+        #[cfg(not(feature = "gfx"))]
+        Err(FramebufferError::NoFramebuffer)
+    }
+
+    fn handle_wait(handle: u64, timeout_ns: u64) -> Result<(), HandleWaitError> {
+        let deadline = match timeout_ns {
+            u64::MAX => None,
+            ns => Some(timer::kernel_ticks() + ns.div_ceil(timer::NS_PER_TICK)),
+        };
+
+        let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
+        match current_thread.process.handle_wait(handle, deadline) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(HandleWaitError::TimedOut),
+            Err(HandleError::HandleDoesntExist(_)) => Err(HandleWaitError::InvalidHandle),
+            Err(HandleError::PermissionDenied) => Err(HandleWaitError::PermissionDenied),
+            Err(HandleError::HostDisconnect | HandleError::BrokenPipe) => {
+                Err(HandleWaitError::Disconnected)
+            }
+            Err(HandleError::InvalidSocketKind | HandleError::WouldBlock) => {
+                Err(HandleWaitError::NotSupported)
+            }
+        }
+    }
+
     fn signal_wait() -> WaitSignal {
         let current_thread = Scheduler::get().current_thread().upgrade().unwrap();
         current_thread.process.next_signal()
@@ -187,18 +1037,30 @@ impl VeraPortal for KernelSyscalls {
     }
 
     fn fixme_cpuio_read_u8(address: u16) -> u8 {
+        if !owns_io_ports(address, 1) {
+            return u8::MAX;
+        }
+
         unsafe { IOPort::new(address).read_byte() }
     }
 
     fn fixme_cpuio_write_u8(address: u16, data: u8) {
-        unsafe { IOPort::new(address).write_byte(data) }
+        if owns_io_ports(address, 1) {
+            unsafe { IOPort::new(address).write_byte(data) }
+        }
     }
 
     fn fixme_cpuio_read_u16(address: u16) -> u16 {
+        if !owns_io_ports(address, 2) {
+            return u16::MAX;
+        }
+
         unsafe { IOPort::new(address).read_word() }
     }
 
     fn fixme_cpuio_write_u16(address: u16, data: u16) {
-        unsafe { IOPort::new(address).write_word(data) }
+        if owns_io_ports(address, 2) {
+            unsafe { IOPort::new(address).write_word(data) }
+        }
     }
 }
@@ -0,0 +1,169 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Traces the syscalls of chosen processes into the log ring, like `strace`.
+//!
+//! Each traced syscall becomes one line in the log ring, starting with
+//! [`TRACE_LINE_PREFIX`] so readers of the ring can pick them out of the rest of the log:
+//!
+//! `strace: [pid name:tid] endpoint(arguments) = output <cycles>`
+//!
+//! Syscalls are timed in TSC cycles, as the timer only ticks once a millisecond.
+//!
+//! Processes are traced once they ask for it with `trace_syscalls`, or from when they
+//! are spawned if their name is listed with `strace=name,name` on the kernel command line.
+
+use crate::{locks::ScheduleLock, log_ring, process::scheduler::Scheduler};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use bootloader::cmdline::KernelCmdline;
+use core::{
+    arch::x86_64::_rdtsc,
+    fmt::{Arguments, Debug, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use lignan::logln;
+use vera_portal::TRACE_LINE_PREFIX;
+
+/// Set once any process has been traced, so untraced syscalls don't need to look up
+/// their process until then
+static ANY_TRACED: AtomicBool = AtomicBool::new(false);
+/// The names of processes to trace from when they are spawned
+static TRACED_AT_SPAWN: ScheduleLock<Vec<String>> = ScheduleLock::new(Vec::new());
+
+/// A syscall that was entered, but has not returned yet
+#[derive(Debug)]
+pub struct PendingTrace {
+    call: String,
+    started_tsc: u64,
+}
+
+/// Read which processes should be traced from when they are spawned
+pub fn init(cmdline: &KernelCmdline) {
+    let Some(names) = cmdline.value_of("strace") else {
+        return;
+    };
+
+    let mut traced = TRACED_AT_SPAWN.lock();
+    traced.extend(
+        names
+            .split(',')
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string()),
+    );
+    logln!("Tracing syscalls of: {:?}", &*traced);
+}
+
+/// Should a process named `name` be traced from when it is spawned?
+pub fn traced_at_spawn(name: &str) -> bool {
+    let traced = TRACED_AT_SPAWN.lock().iter().any(|traced| traced == name);
+    if traced {
+        ANY_TRACED.store(true, Ordering::Relaxed);
+    }
+
+    traced
+}
+
+/// Note that a process started being traced
+pub fn tracing_enabled() {
+    ANY_TRACED.store(true, Ordering::Relaxed);
+}
+
+/// Start tracing a syscall of the current thread, if its process is traced
+pub fn enter(endpoint: &'static str, arguments: Arguments) {
+    if !ANY_TRACED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let Some(thread) = Scheduler::get().current_thread().upgrade() else {
+        return;
+    };
+    if !thread.process.is_tracing() {
+        return;
+    }
+
+    let mut call = String::new();
+    let _ = write!(call, "{endpoint}({arguments})");
+
+    thread.begin_trace(PendingTrace {
+        call,
+        started_tsc: unsafe { _rdtsc() },
+    });
+}
+
+/// Finish tracing the current thread's syscall, writing it into the log ring
+///
+/// `output` is `None` for syscalls that never return.
+pub fn exit(output: Option<&dyn Debug>) {
+    if !ANY_TRACED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let Some(thread) = Scheduler::get().current_thread().upgrade() else {
+        return;
+    };
+    let Some(trace) = thread.take_trace() else {
+        return;
+    };
+
+    // Syscalls that never return are traced before they run, so there is nothing to time
+    let elapsed = match output {
+        Some(_) => Some(unsafe { _rdtsc() } - trace.started_tsc),
+        None => None,
+    };
+    log_ring::write(format_args!(
+        "{TRACE_LINE_PREFIX}[{} {}:{}] {} = {:?} <{}>\n",
+        thread.process.id,
+        thread.process.name,
+        thread.id,
+        trace.call,
+        TraceOutput(output),
+        TraceCycles(elapsed),
+    ));
+}
+
+struct TraceOutput<'a>(Option<&'a dyn Debug>);
+
+impl Debug for TraceOutput<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            Some(output) => output.fmt(f),
+            None => f.write_str("?"),
+        }
+    }
+}
+
+struct TraceCycles(Option<u64>);
+
+impl core::fmt::Display for TraceCycles {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            Some(cycles) => write!(f, "{cycles} cycles"),
+            None => f.write_str("?"),
+        }
+    }
+}
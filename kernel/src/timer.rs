@@ -23,19 +23,65 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
-use crate::{int::attach_irq_handler, process::scheduler::Scheduler};
+use crate::{
+    int::attach_irq_handler,
+    locks::ScheduleLock,
+    process::{
+        RefProcess, WeakProcess,
+        scheduler::Scheduler,
+        thread::{RefThread, WeakThread},
+    },
+    resources::{self, Resource, Sharing},
+};
+use alloc::{sync::Arc, vec::Vec};
 use arch::{
     critcal_section,
     idt64::InterruptInfo,
-    pit825x::{PitAccessMode, PitOperatingMode, PitSelectChannel, pit_command, set_pit_hz},
+    pit825x::{
+        PIT_BASE_HZ, PitAccessMode, PitOperatingMode, PitSelectChannel, pit_command, set_pit_reload,
+    },
+    rtc::read_rtc,
 };
 use lignan::{log, logln};
+use vera_portal::WaitSignal;
 
-const TIMER_HZ: f32 = 1000_f32;
+/// The rate we ask the PIT to tick at
+const TIMER_HZ: u32 = 1000;
+/// The PIT reload count closest to `TIMER_HZ`
+const PIT_RELOAD: u16 = (PIT_BASE_HZ / TIMER_HZ) as u16;
+/// The ports of the PIT's channels 0 and 1, which only the kernel uses
+const PIT_TIMER_PORTS: u16 = 0x40;
+/// Channel 2 and the command port, shared with the sound server which drives the speaker
+/// from channel 2
+const PIT_SPEAKER_PORTS: u16 = 0x42;
 
 pub fn init_timer() {
+    let claims = [
+        (
+            Resource::IoPorts {
+                base: PIT_TIMER_PORTS,
+                len: 2,
+            },
+            Sharing::Exclusive,
+        ),
+        (
+            Resource::IoPorts {
+                base: PIT_SPEAKER_PORTS,
+                len: 2,
+            },
+            Sharing::Shared,
+        ),
+        (Resource::Irq(0), Sharing::Exclusive),
+    ];
+    if let Err(err) = resources::claim("pit", &claims) {
+        panic!("Unable to claim the PIT, {err}");
+    }
+
     log!("Enabling PIT...");
     critcal_section! {
         // Put the pit in repeted trigger mode
@@ -47,21 +93,164 @@ pub fn init_timer() {
         );
 
         // Set the trigger time
-        log!("({}Hz)", set_pit_hz(TIMER_HZ));
+        set_pit_reload(PIT_RELOAD);
+        log!("({}Hz)", PIT_BASE_HZ / PIT_RELOAD as u32);
 
         // Attach our IRQ
         attach_irq_handler(pit_interrupt_handler, 0);
     }
     logln!("OK");
+
+    let rtc = read_rtc();
+    BOOT_UNIX_TIME.store(rtc.to_unix(), Ordering::Relaxed);
+    lignan::stream::set_wall_clock_fn(now_unix);
+    logln!(
+        "RTC: {:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        rtc.year,
+        rtc.month,
+        rtc.day,
+        rtc.hour,
+        rtc.minute,
+        rtc.second
+    );
 }
 
 static KERNEL_TICKS: AtomicU64 = AtomicU64::new(0);
+/// Timers processes are waiting on with `signal_timer`
+static SIGNAL_TIMERS: ScheduleLock<Vec<SignalTimer>> = ScheduleLock::new(Vec::new());
+/// The tick the next signal timer goes off on, so the tick doesn't need to lock the timers
+static NEXT_SIGNAL_TIMER: AtomicU64 = AtomicU64::new(u64::MAX);
+/// Blocked threads to wake once a tick is reached, and the tick
+static WAKE_TIMERS: ScheduleLock<Vec<(u64, WeakThread)>> = ScheduleLock::new(Vec::new());
+/// The tick the next wake timer goes off on
+static NEXT_WAKE_TIMER: AtomicU64 = AtomicU64::new(u64::MAX);
+/// The unix time when the PIT was enabled
+static BOOT_UNIX_TIME: AtomicU64 = AtomicU64::new(0);
 
-fn pit_interrupt_handler(_args: &InterruptInfo) {
+/// How many nanoseconds pass each kernel tick
+///
+/// This comes from the reload count we actually programmed, not `TIMER_HZ`, so uptime
+/// doesn't drift from the PIT's real rate.
+pub const NS_PER_TICK: u64 = PIT_RELOAD as u64 * 1_000_000_000 / PIT_BASE_HZ as u64;
+
+#[cfg_attr(
+    not(any(feature = "profile", feature = "lock-debug")),
+    allow(unused_variables)
+)]
+fn pit_interrupt_handler(args: &InterruptInfo) {
     KERNEL_TICKS.fetch_add(1, Ordering::AcqRel);
+    #[cfg(feature = "profile")]
+    crate::profile::sample(args.context.rip);
+    #[cfg(feature = "lock-debug")]
+    crate::locks::watchdog::timer_tick(args.context.rip);
     Scheduler::tick();
 }
 
 pub fn kernel_ticks() -> u64 {
     KERNEL_TICKS.load(Ordering::Relaxed)
 }
+
+/// The time since the PIT was enabled
+pub fn kernel_uptime() -> Duration {
+    Duration::from_nanos(monotonic_ns())
+}
+
+/// Nanoseconds since the PIT was enabled, this never goes backwards
+pub fn monotonic_ns() -> u64 {
+    kernel_ticks() * NS_PER_TICK
+}
+
+/// Seconds since the unix epoch
+pub fn now_unix() -> u64 {
+    BOOT_UNIX_TIME.load(Ordering::Relaxed) + kernel_uptime().as_secs()
+}
+
+/// A `WaitSignal::TimerUpdate` to send to a process
+struct SignalTimer {
+    process: WeakProcess,
+    /// The tick this timer goes off on
+    deadline: u64,
+    ms_duration: u64,
+}
+
+/// Send `process` a `WaitSignal::TimerUpdate` once `ns` nanoseconds have passed
+pub fn add_signal_timer(process: &RefProcess, ns: u64) {
+    let deadline = kernel_ticks() + ns.div_ceil(NS_PER_TICK).max(1);
+
+    SIGNAL_TIMERS.lock().push(SignalTimer {
+        process: Arc::downgrade(process),
+        deadline,
+        ms_duration: ns / 1_000_000,
+    });
+    NEXT_SIGNAL_TIMER.fetch_min(deadline, Ordering::SeqCst);
+}
+
+/// Wake `thread` once the kernel reaches tick `deadline`, if it is still blocked
+pub fn add_wake_timer(thread: &RefThread, deadline: u64) {
+    WAKE_TIMERS.lock().push((deadline, Arc::downgrade(thread)));
+    NEXT_WAKE_TIMER.fetch_min(deadline, Ordering::SeqCst);
+}
+
+/// Drop the wake timers of `thread`, after it was woken by something else
+pub fn remove_wake_timers(thread: &WeakThread) {
+    WAKE_TIMERS
+        .lock()
+        .retain(|(_, timer_thread)| !timer_thread.ptr_eq(thread));
+}
+
+/// Send the signals and wake the threads of every timer that went off by `now`
+///
+/// Called from the scheduler's tick, once no schedule locks are held.
+pub fn tick(now: u64) {
+    wake_threads(now);
+
+    if NEXT_SIGNAL_TIMER.load(Ordering::Relaxed) > now {
+        return;
+    }
+
+    let mut timers = SIGNAL_TIMERS.lock();
+    let mut next = u64::MAX;
+    timers.retain(|timer| {
+        if timer.deadline > now {
+            next = next.min(timer.deadline);
+            return true;
+        }
+
+        // Nobody is left to tell if the process exited
+        if let Some(process) = timer.process.upgrade() {
+            process.push_signal(WaitSignal::TimerUpdate {
+                ms_duration: timer.ms_duration,
+            });
+        }
+        false
+    });
+    NEXT_SIGNAL_TIMER.store(next, Ordering::SeqCst);
+}
+
+/// Wake the threads of every wake timer that went off by `now`
+fn wake_threads(now: u64) {
+    if NEXT_WAKE_TIMER.load(Ordering::Relaxed) > now {
+        return;
+    }
+
+    let mut expired = Vec::new();
+    {
+        let mut timers = WAKE_TIMERS.lock();
+        let mut next = u64::MAX;
+        timers.retain(|(deadline, thread)| {
+            if *deadline > now {
+                next = next.min(*deadline);
+                return true;
+            }
+
+            expired.extend(thread.upgrade());
+            false
+        });
+        NEXT_WAKE_TIMER.store(next, Ordering::SeqCst);
+    }
+
+    // Waking takes the run queue locks, so don't hold onto the timers while we do
+    for thread in expired {
+        Scheduler::get().wake(&thread);
+    }
+}
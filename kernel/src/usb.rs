@@ -0,0 +1,371 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::pci::{self, PciDevice};
+use alloc::vec::Vec;
+use arch::pit825x::spin_delay_ms;
+use core::fmt::Display;
+use lignan::{logln, warnln};
+
+mod ehci;
+mod hid;
+pub mod storage;
+mod uhci;
+mod xhci;
+
+/// The PCI class and subclass of USB controllers
+const PCI_CLASS_SERIAL_BUS: u8 = 0x0C;
+const PCI_SUBCLASS_USB: u8 = 0x03;
+
+/// The PCI programming interface of each kind of USB controller
+const PROG_IF_UHCI: u8 = 0x00;
+const PROG_IF_OHCI: u8 = 0x10;
+const PROG_IF_EHCI: u8 = 0x20;
+const PROG_IF_XHCI: u8 = 0x30;
+
+/// Standard device requests
+const REQUEST_SET_ADDRESS: u8 = 5;
+const REQUEST_GET_DESCRIPTOR: u8 = 6;
+const REQUEST_SET_CONFIGURATION: u8 = 9;
+
+/// Descriptor types
+const DESCRIPTOR_DEVICE: u8 = 1;
+const DESCRIPTOR_CONFIGURATION: u8 = 2;
+const DESCRIPTOR_INTERFACE: u8 = 4;
+const DESCRIPTOR_ENDPOINT: u8 = 5;
+
+const DEVICE_DESCRIPTOR_LEN: usize = 18;
+const CONFIGURATION_HEADER_LEN: usize = 9;
+/// The most of a configuration descriptor (and the descriptors after it) we read
+const MAX_CONFIGURATION_LEN: usize = 256;
+
+/// How long a device has to settle after being given an address
+const SET_ADDRESS_RECOVERY_MS: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UsbError {
+    /// The device refused the request
+    Stalled,
+    /// The transfer failed on the bus, like a CRC error or the device not responding
+    TransferError,
+    /// The transfer did not finish in time
+    Timeout,
+    /// The transfer is larger than the controller can send at once
+    TooLarge,
+    /// The controller could not get memory for the device
+    NoMemory,
+}
+
+/// The first stage of a control transfer, saying what is being asked for
+#[derive(Clone, Copy, Debug)]
+pub struct SetupPacket {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+impl SetupPacket {
+    /// Set in `request_type` when data is sent from the device to us
+    const DEVICE_TO_HOST: u8 = 1 << 7;
+    /// `request_type` for class specific requests sent to an interface
+    const CLASS_INTERFACE: u8 = 0b01 << 5 | 0b00001;
+
+    pub const fn get_descriptor(kind: u8, length: u16) -> Self {
+        Self {
+            request_type: Self::DEVICE_TO_HOST,
+            request: REQUEST_GET_DESCRIPTOR,
+            value: (kind as u16) << 8,
+            index: 0,
+            length,
+        }
+    }
+
+    pub const fn set_address(address: u8) -> Self {
+        Self {
+            request_type: 0,
+            request: REQUEST_SET_ADDRESS,
+            value: address as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    pub const fn set_configuration(configuration: u8) -> Self {
+        Self {
+            request_type: 0,
+            request: REQUEST_SET_CONFIGURATION,
+            value: configuration as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    /// A class specific request, with no data, sent to `interface`
+    pub const fn class_interface(request: u8, value: u16, interface: u8) -> Self {
+        Self {
+            request_type: Self::CLASS_INTERFACE,
+            request,
+            value,
+            index: interface as u16,
+            length: 0,
+        }
+    }
+
+    pub const fn is_device_to_host(&self) -> bool {
+        self.request_type & Self::DEVICE_TO_HOST != 0
+    }
+
+    pub const fn to_bytes(&self) -> [u8; 8] {
+        let [value_lo, value_hi] = self.value.to_le_bytes();
+        let [index_lo, index_hi] = self.index.to_le_bytes();
+        let [length_lo, length_hi] = self.length.to_le_bytes();
+
+        [
+            self.request_type,
+            self.request,
+            value_lo,
+            value_hi,
+            index_lo,
+            index_hi,
+            length_lo,
+            length_hi,
+        ]
+    }
+}
+
+/// How fast a device talks on the bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    Low,
+    Full,
+    High,
+    Super,
+}
+
+impl Display for Speed {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Speed::Low => write!(f, "low speed"),
+            Speed::Full => write!(f, "full speed"),
+            Speed::High => write!(f, "high speed"),
+            Speed::Super => write!(f, "super speed"),
+        }
+    }
+}
+
+/// A device plugged directly into a root hub port
+#[derive(Clone, Copy, Debug)]
+pub struct UsbDevice {
+    /// The device's address on the bus, or its slot on xHCI controllers which pick the
+    /// address themselves
+    pub address: u8,
+    pub speed: Speed,
+    /// The largest packet the device's control endpoint takes
+    pub max_packet: u16,
+}
+
+/// The interrupt endpoint a HID boot keyboard sends its reports on
+#[derive(Clone, Copy, Debug)]
+pub struct KeyboardEndpoint {
+    pub interface: u8,
+    pub endpoint: u8,
+    pub max_packet: u16,
+}
+
+/// A controller that can send control transfers to its devices
+pub trait ControlPipe {
+    /// Send `setup` to `device`, then send or receive `data` depending on its direction.
+    ///
+    /// Returns the amount of bytes transferred in the data stage.
+    fn control_transfer(
+        &mut self,
+        device: &UsbDevice,
+        setup: SetupPacket,
+        data: &mut [u8],
+    ) -> Result<usize, UsbError>;
+}
+
+/// Find and start every USB controller we support.
+pub fn init() {
+    let controllers = pci::scan()
+        .into_iter()
+        .filter(|device| {
+            device.class == PCI_CLASS_SERIAL_BUS && device.subclass == PCI_SUBCLASS_USB
+        })
+        .collect::<Vec<PciDevice>>();
+
+    // EHCI controllers must hand their ports to their companions before those are started
+    for device in controllers
+        .iter()
+        .filter(|device| device.prog_if == PROG_IF_EHCI)
+    {
+        ehci::init(device);
+    }
+
+    for device in &controllers {
+        match device.prog_if {
+            PROG_IF_UHCI => uhci::init(device),
+            PROG_IF_XHCI => xhci::init(device),
+            PROG_IF_EHCI => (),
+            PROG_IF_OHCI => warnln!("USB: OHCI controller {device:?} is not supported yet"),
+            _ => warnln!("USB: Unknown controller {device:?}"),
+        }
+    }
+}
+
+/// Give a device that was just reset `address`, and configure it.
+///
+/// Returns where its keyboard reports are sent, if it is a boot keyboard.
+pub fn enumerate(
+    pipe: &mut impl ControlPipe,
+    address: u8,
+    speed: Speed,
+) -> Result<(UsbDevice, Option<KeyboardEndpoint>), UsbError> {
+    let mut device = UsbDevice {
+        address: 0,
+        speed,
+        max_packet: 8,
+    };
+
+    device.max_packet = read_max_packet(pipe, &device)?;
+
+    pipe.control_transfer(&device, SetupPacket::set_address(address), &mut [])?;
+    spin_delay_ms(SET_ADDRESS_RECOVERY_MS);
+    device.address = address;
+
+    let configuration = configure(pipe, &device)?;
+    let keyboard = find_boot_keyboard(&configuration);
+    if let Some(keyboard) = keyboard {
+        hid::set_boot_protocol(pipe, &device, keyboard.interface)?;
+    }
+
+    Ok((device, keyboard))
+}
+
+/// Read the largest packet `device`'s control endpoint takes from its device descriptor.
+pub fn read_max_packet(pipe: &mut impl ControlPipe, device: &UsbDevice) -> Result<u16, UsbError> {
+    // We can only be sure the first 8 bytes fit in a packet until we know the real size
+    let mut descriptor = [0; 8];
+    pipe.control_transfer(
+        device,
+        SetupPacket::get_descriptor(DESCRIPTOR_DEVICE, 8),
+        &mut descriptor,
+    )?;
+
+    Ok(match device.speed {
+        // Super speed devices give the size as a power of two
+        Speed::Super => 1 << descriptor[7].min(9),
+        _ => descriptor[7].max(8) as u16,
+    })
+}
+
+/// Select the first configuration of an addressed device.
+///
+/// Returns the configuration's descriptors, for finding the interfaces we drive.
+pub fn configure(pipe: &mut impl ControlPipe, device: &UsbDevice) -> Result<Vec<u8>, UsbError> {
+    let mut descriptor = [0; DEVICE_DESCRIPTOR_LEN];
+    pipe.control_transfer(
+        device,
+        SetupPacket::get_descriptor(DESCRIPTOR_DEVICE, DEVICE_DESCRIPTOR_LEN as u16),
+        &mut descriptor,
+    )?;
+    logln!(
+        "USB device {}: {:04x}:{:04x} ({})",
+        device.address,
+        u16::from_le_bytes([descriptor[8], descriptor[9]]),
+        u16::from_le_bytes([descriptor[10], descriptor[11]]),
+        device.speed
+    );
+
+    let mut configuration = alloc::vec![0; MAX_CONFIGURATION_LEN];
+    pipe.control_transfer(
+        device,
+        SetupPacket::get_descriptor(DESCRIPTOR_CONFIGURATION, CONFIGURATION_HEADER_LEN as u16),
+        &mut configuration[..CONFIGURATION_HEADER_LEN],
+    )?;
+
+    let total_len = (u16::from_le_bytes([configuration[2], configuration[3]]) as usize)
+        .clamp(CONFIGURATION_HEADER_LEN, MAX_CONFIGURATION_LEN);
+    let len = pipe.control_transfer(
+        device,
+        SetupPacket::get_descriptor(DESCRIPTOR_CONFIGURATION, total_len as u16),
+        &mut configuration[..total_len],
+    )?;
+    if len < CONFIGURATION_HEADER_LEN {
+        return Err(UsbError::TransferError);
+    }
+    configuration.truncate(len);
+
+    pipe.control_transfer(
+        device,
+        SetupPacket::set_configuration(configuration[5]),
+        &mut [],
+    )?;
+
+    Ok(configuration)
+}
+
+/// Split a configuration into each of its descriptors.
+fn descriptors(configuration: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = configuration;
+
+    core::iter::from_fn(move || {
+        let len = *rest.first()? as usize;
+        let descriptor = rest.get(..len).filter(|_| len >= 2)?;
+        rest = &rest[len..];
+
+        Some(descriptor)
+    })
+}
+
+/// Look through a configuration's descriptors for a HID boot keyboard interface.
+fn find_boot_keyboard(configuration: &[u8]) -> Option<KeyboardEndpoint> {
+    let mut keyboard_interface = None;
+
+    for descriptor in descriptors(configuration) {
+        match (descriptor[1], descriptor) {
+            (DESCRIPTOR_INTERFACE, &[_, _, number, _, _, class, subclass, protocol, ..]) => {
+                keyboard_interface =
+                    hid::is_boot_keyboard(class, subclass, protocol).then_some(number);
+            }
+            (DESCRIPTOR_ENDPOINT, &[_, _, address, attributes, max_lo, max_hi, ..]) => {
+                let is_interrupt_in = address & 0x80 != 0 && attributes & 0b11 == 0b11;
+
+                if let Some(interface) = keyboard_interface.filter(|_| is_interrupt_in) {
+                    return Some(KeyboardEndpoint {
+                        interface,
+                        endpoint: address & 0xF,
+                        max_packet: u16::from_le_bytes([max_lo, max_hi]),
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
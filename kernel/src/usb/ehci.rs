@@ -0,0 +1,138 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    pci::{Bar, PciDevice},
+    resources::{self, Resource, Sharing},
+    vmm::map_mmio,
+};
+use arch::pit825x::spin_delay_ms;
+use lignan::{logln, warnln};
+use mem::{addr::PhysAddr, paging::CacheMode};
+
+/// Capability register offsets
+const CAPLENGTH: usize = 0x00;
+const HCSPARAMS: usize = 0x04;
+const HCCPARAMS: usize = 0x08;
+
+/// Operational register offsets, from the end of the capability registers
+const USBCMD: usize = 0x00;
+const USBSTS: usize = 0x04;
+const CONFIGFLAG: usize = 0x40;
+
+const CMD_RUN: u32 = 1 << 0;
+const CMD_RESET: u32 = 1 << 1;
+const STS_HALTED: u32 = 1 << 12;
+
+/// The legacy support capability in PCI config space, used to take the controller from
+/// the BIOS
+const LEGSUP_CAPABILITY_ID: u32 = 1;
+const LEGSUP_BIOS_OWNED: u32 = 1 << 16;
+const LEGSUP_OS_OWNED: u32 = 1 << 24;
+
+/// How much of the controller's registers to map
+const REGISTERS_LEN: usize = 0x100;
+/// How long to wait for the controller to do what it is told
+const TIMEOUT_MS: usize = 100;
+/// How long the BIOS gets to give up the controller
+const BIOS_HANDOFF_TIMEOUT_MS: usize = 1000;
+
+/// Reset a USB 2.0 controller, routing all of its ports to its USB 1.1 companion
+/// controllers so they can handle low and full speed devices.
+///
+/// FIXME: High speed devices are not supported yet, they will fall back to full speed
+///        through the companion controllers.
+pub fn init(device: &PciDevice) {
+    let Some(Bar::Memory(base)) = device.bar(0) else {
+        warnln!("EHCI {device:?} has no registers");
+        return;
+    };
+
+    let owner = device.owner_name("ehci");
+    let base = PhysAddr::new(base as usize);
+    let claim = Resource::Mmio {
+        base,
+        len: REGISTERS_LEN,
+    };
+    if let Err(err) = resources::claim(&owner, &[(claim, Sharing::Exclusive)]) {
+        warnln!("EHCI {device:?}: {err}");
+        return;
+    }
+
+    let registers = match map_mmio(base, REGISTERS_LEN, CacheMode::Uncached) {
+        Ok(registers) => registers.as_mut_ptr::<u8>(),
+        Err(err) => {
+            warnln!("EHCI {device:?}: Unable to map registers ({err})");
+            resources::release(&owner);
+            return;
+        }
+    };
+
+    device.enable();
+
+    let read = |offset: usize| unsafe { registers.add(offset).cast::<u32>().read_volatile() };
+    let write = |offset: usize, value: u32| unsafe {
+        registers.add(offset).cast::<u32>().write_volatile(value)
+    };
+    let wait_for = |condition: &dyn Fn() -> bool, timeout_ms: usize| {
+        (0..timeout_ms).any(|_| {
+            spin_delay_ms(1);
+            condition()
+        })
+    };
+
+    let operational = (read(CAPLENGTH) & 0xFF) as usize;
+    let n_ports = read(HCSPARAMS) & 0xF;
+
+    // Ask the BIOS to stop using the controller for its keyboard emulation
+    let legsup = ((read(HCCPARAMS) >> 8) & 0xFF) as u8;
+    if legsup >= 0x40 && device.read_u32(legsup) & 0xFF == LEGSUP_CAPABILITY_ID {
+        device.write_u32(legsup, device.read_u32(legsup) | LEGSUP_OS_OWNED);
+
+        if !wait_for(
+            &|| device.read_u32(legsup) & LEGSUP_BIOS_OWNED == 0,
+            BIOS_HANDOFF_TIMEOUT_MS,
+        ) {
+            warnln!("EHCI {device:?}: BIOS did not give up the controller, taking it anyway");
+            device.write_u32(legsup, LEGSUP_OS_OWNED);
+        }
+    }
+
+    write(operational + USBCMD, read(operational + USBCMD) & !CMD_RUN);
+    if !wait_for(&|| read(operational + USBSTS) & STS_HALTED != 0, TIMEOUT_MS) {
+        warnln!("EHCI {device:?}: Controller did not halt");
+        return;
+    }
+
+    write(operational + USBCMD, CMD_RESET);
+    if !wait_for(&|| read(operational + USBCMD) & CMD_RESET == 0, TIMEOUT_MS) {
+        warnln!("EHCI {device:?}: Controller did not reset");
+        return;
+    }
+
+    // With no configuration set, every port belongs to a companion controller
+    write(operational + CONFIGFLAG, 0);
+    logln!("EHCI {device:?}: Routed {n_ports} ports to companion controllers");
+}
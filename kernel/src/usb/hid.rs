@@ -0,0 +1,156 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{ControlPipe, SetupPacket, UsbDevice, UsbError};
+use crate::keyboard::{Key, key_event};
+
+/// The HID interface class, with the boot subclass and keyboard protocol
+const CLASS_HID: u8 = 3;
+const SUBCLASS_BOOT: u8 = 1;
+const PROTOCOL_KEYBOARD: u8 = 1;
+
+/// HID class requests
+const REQUEST_SET_IDLE: u8 = 0x0A;
+const REQUEST_SET_PROTOCOL: u8 = 0x0B;
+const BOOT_PROTOCOL: u16 = 0;
+
+/// The length of a boot keyboard report
+pub const REPORT_LEN: usize = 8;
+
+/// Modifier bits in the first byte of a report
+const MODIFIER_LEFT_CTRL: u8 = 1 << 0;
+const MODIFIER_LEFT_SHIFT: u8 = 1 << 1;
+const MODIFIER_LEFT_ALT: u8 = 1 << 2;
+const MODIFIER_RIGHT_CTRL: u8 = 1 << 4;
+const MODIFIER_RIGHT_SHIFT: u8 = 1 << 5;
+const MODIFIER_RIGHT_ALT: u8 = 1 << 6;
+
+/// The key each modifier bit is reported as
+const MODIFIER_KEYS: [(u8, Key); 6] = [
+    (MODIFIER_LEFT_CTRL, Key::LeftCtrl),
+    (MODIFIER_LEFT_SHIFT, Key::LeftShift),
+    (MODIFIER_LEFT_ALT, Key::Alt),
+    (MODIFIER_RIGHT_CTRL, Key::RightCtrl),
+    (MODIFIER_RIGHT_SHIFT, Key::RightShift),
+    (MODIFIER_RIGHT_ALT, Key::AltGr),
+];
+
+/// Keyboard usage ids for the keys we handle
+const USAGE_ROLLOVER_ERROR: u8 = 0x01;
+const USAGE_A: u8 = 0x04;
+const USAGE_SLASH: u8 = 0x38;
+const USAGE_CAPS_LOCK: u8 = 0x39;
+const USAGE_F1: u8 = 0x3A;
+const USAGE_F12: u8 = 0x45;
+const USAGE_NON_US_BACKSLASH: u8 = 0x64;
+
+pub const fn is_boot_keyboard(class: u8, subclass: u8, protocol: u8) -> bool {
+    class == CLASS_HID && subclass == SUBCLASS_BOOT && protocol == PROTOCOL_KEYBOARD
+}
+
+/// Switch a keyboard to the simple boot report format, so we don't need to parse its
+/// report descriptor.
+pub fn set_boot_protocol(
+    pipe: &mut impl ControlPipe,
+    device: &UsbDevice,
+    interface: u8,
+) -> Result<(), UsbError> {
+    pipe.control_transfer(
+        device,
+        SetupPacket::class_interface(REQUEST_SET_PROTOCOL, BOOT_PROTOCOL, interface),
+        &mut [],
+    )?;
+
+    // Only report when something changes. Some keyboards don't support this, which is fine
+    // since repeated reports are ignored anyway.
+    let _ = pipe.control_transfer(
+        device,
+        SetupPacket::class_interface(REQUEST_SET_IDLE, 0, interface),
+        &mut [],
+    );
+
+    Ok(())
+}
+
+/// Turns boot keyboard reports into key events
+pub struct BootKeyboard {
+    modifiers: u8,
+    keys: [u8; 6],
+}
+
+impl BootKeyboard {
+    pub const fn new() -> Self {
+        Self {
+            modifiers: 0,
+            keys: [0; 6],
+        }
+    }
+
+    /// Send a key event for every key that was pressed or released since the last report.
+    pub fn handle_report(&mut self, report: &[u8]) {
+        let Some(&[modifiers, _, ref keys @ ..]) = report.first_chunk::<REPORT_LEN>() else {
+            return;
+        };
+
+        // The keyboard can't tell which keys are held, so wait for a report that can
+        if keys.contains(&USAGE_ROLLOVER_ERROR) {
+            return;
+        }
+
+        for (mask, key) in MODIFIER_KEYS {
+            let held = |modifiers: u8| modifiers & mask != 0;
+            if held(modifiers) != held(self.modifiers) {
+                key_event(key, held(modifiers));
+            }
+        }
+
+        for &usage in self
+            .keys
+            .iter()
+            .filter(|&&usage| usage != 0 && !keys.contains(&usage))
+        {
+            key_event(usage_to_key(usage), false);
+        }
+
+        for &usage in keys
+            .iter()
+            .filter(|&&usage| usage != 0 && !self.keys.contains(&usage))
+        {
+            key_event(usage_to_key(usage), true);
+        }
+
+        self.modifiers = modifiers;
+        self.keys.copy_from_slice(keys);
+    }
+}
+
+fn usage_to_key(usage: u8) -> Key {
+    match usage {
+        USAGE_F1..=USAGE_F12 => Key::Function(usage - USAGE_F1 + 1),
+        USAGE_CAPS_LOCK => Key::CapsLock,
+        USAGE_A..=USAGE_SLASH | USAGE_NON_US_BACKSLASH => Key::Usage(usage),
+        _ => Key::Unknown,
+    }
+}
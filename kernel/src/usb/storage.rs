@@ -0,0 +1,382 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{DESCRIPTOR_ENDPOINT, DESCRIPTOR_INTERFACE, UsbDevice, UsbError, descriptors};
+use crate::locks::ScheduleLock;
+use alloc::{boxed::Box, format, vec::Vec};
+use arch::pit825x::spin_delay_ms;
+use lignan::logln;
+
+/// The interface class, subclass, and protocol of SCSI disks using the bulk-only transport
+const CLASS_MASS_STORAGE: u8 = 0x08;
+const SUBCLASS_SCSI: u8 = 0x06;
+const PROTOCOL_BULK_ONLY: u8 = 0x50;
+
+/// Command block wrapper, sent before each command
+const CBW_SIGNATURE: u32 = 0x43425355;
+const CBW_LEN: usize = 31;
+const CBW_DATA_IN: u8 = 1 << 7;
+
+/// Command status wrapper, received after each command
+const CSW_SIGNATURE: u32 = 0x53425355;
+const CSW_LEN: usize = 13;
+const CSW_PASSED: u8 = 0;
+const CSW_FAILED: u8 = 1;
+
+/// SCSI commands
+const SCSI_TEST_UNIT_READY: u8 = 0x00;
+const SCSI_REQUEST_SENSE: u8 = 0x03;
+const SCSI_INQUIRY: u8 = 0x12;
+const SCSI_READ_CAPACITY: u8 = 0x25;
+const SCSI_READ: u8 = 0x28;
+
+const INQUIRY_LEN: usize = 36;
+const REQUEST_SENSE_LEN: usize = 18;
+const READ_CAPACITY_LEN: usize = 8;
+
+/// The only block size we support, which is also what partition tables expect
+pub const BLOCK_SIZE: usize = 512;
+/// Controllers move at most a page in each bulk transfer, so reads are split into this
+/// many blocks
+const MAX_BLOCKS_PER_READ: usize = 8;
+/// How long a disk's name can be, its vendor and product from the inquiry data
+pub const MODEL_LEN: usize = 32;
+
+/// How many times to ask a disk if it is ready, as sticks can take a while to spin up
+const READY_ATTEMPTS: usize = 10;
+const READY_DELAY_MS: u32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageError {
+    Usb(UsbError),
+    /// The disk ran the command, but said it failed
+    CommandFailed,
+    /// The disk replied with something that is not a command status
+    InvalidStatus,
+    /// The disk has blocks we cannot read
+    UnsupportedBlockSize(u32),
+    /// The read is not whole blocks, or goes past the end of the disk
+    OutOfRange,
+}
+
+impl From<UsbError> for StorageError {
+    fn from(value: UsbError) -> Self {
+        Self::Usb(value)
+    }
+}
+
+/// A controller that can send bulk transfers to its devices
+pub trait BulkPipe {
+    /// Send or receive `data` on `endpoint`, depending on the direction bit of its address.
+    ///
+    /// Returns the amount of bytes transferred.
+    fn bulk_transfer(
+        &mut self,
+        device: &UsbDevice,
+        endpoint: u8,
+        data: &mut [u8],
+    ) -> Result<usize, UsbError>;
+}
+
+impl<P: BulkPipe + ?Sized> BulkPipe for Box<P> {
+    fn bulk_transfer(
+        &mut self,
+        device: &UsbDevice,
+        endpoint: u8,
+        data: &mut [u8],
+    ) -> Result<usize, UsbError> {
+        (**self).bulk_transfer(device, endpoint, data)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BulkEndpoint {
+    /// The endpoint's address, including its direction bit
+    pub address: u8,
+    pub max_packet: u16,
+}
+
+impl BulkEndpoint {
+    pub const fn is_in(&self) -> bool {
+        self.address & 0x80 != 0
+    }
+}
+
+/// The endpoints of a bulk-only SCSI disk
+#[derive(Clone, Copy, Debug)]
+pub struct StorageInterface {
+    pub bulk_in: BulkEndpoint,
+    pub bulk_out: BulkEndpoint,
+}
+
+/// Look through a configuration's descriptors for a bulk-only SCSI disk.
+pub fn find_mass_storage(configuration: &[u8]) -> Option<StorageInterface> {
+    let mut in_storage_interface = false;
+    let mut bulk_in = None;
+    let mut bulk_out = None;
+
+    for descriptor in descriptors(configuration) {
+        match (descriptor[1], descriptor) {
+            (DESCRIPTOR_INTERFACE, &[_, _, _, _, _, class, subclass, protocol, ..]) => {
+                if bulk_in.is_some() && bulk_out.is_some() {
+                    break;
+                }
+
+                in_storage_interface = class == CLASS_MASS_STORAGE
+                    && subclass == SUBCLASS_SCSI
+                    && protocol == PROTOCOL_BULK_ONLY;
+                bulk_in = None;
+                bulk_out = None;
+            }
+            (DESCRIPTOR_ENDPOINT, &[_, _, address, attributes, max_lo, max_hi, ..])
+                if in_storage_interface && attributes & 0b11 == 0b10 =>
+            {
+                let endpoint = BulkEndpoint {
+                    address,
+                    max_packet: u16::from_le_bytes([max_lo, max_hi]),
+                };
+
+                if endpoint.is_in() {
+                    bulk_in.get_or_insert(endpoint);
+                } else {
+                    bulk_out.get_or_insert(endpoint);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Some(StorageInterface {
+        bulk_in: bulk_in.filter(|_| in_storage_interface)?,
+        bulk_out: bulk_out.filter(|_| in_storage_interface)?,
+    })
+}
+
+/// A USB disk, like a memory stick, using the bulk-only transport.
+///
+/// FIXME: Only the first logical unit is used, and a disk that gets confused is not
+///        recovered with a bulk-only reset.
+pub struct MassStorage<P: BulkPipe> {
+    pipe: P,
+    device: UsbDevice,
+    interface: StorageInterface,
+    tag: u32,
+    blocks: u64,
+    /// The vendor and product, padded with zeros
+    model: [u8; MODEL_LEN],
+}
+
+impl<P: BulkPipe> MassStorage<P> {
+    /// Wait for the disk to be ready, and find out how large it is.
+    pub fn new(
+        pipe: P,
+        device: UsbDevice,
+        interface: StorageInterface,
+    ) -> Result<Self, StorageError> {
+        let mut disk = Self {
+            pipe,
+            device,
+            interface,
+            tag: 0,
+            blocks: 0,
+            model: [0; MODEL_LEN],
+        };
+
+        let mut inquiry = [0; INQUIRY_LEN];
+        disk.command(
+            &[SCSI_INQUIRY, 0, 0, 0, INQUIRY_LEN as u8, 0],
+            &mut inquiry,
+            true,
+        )?;
+
+        disk.wait_until_ready()?;
+
+        let mut capacity = [0; READ_CAPACITY_LEN];
+        disk.command(
+            &[SCSI_READ_CAPACITY, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            &mut capacity,
+            true,
+        )?;
+
+        let last_block = u32::from_be_bytes(capacity[0..4].try_into().unwrap());
+        let block_size = u32::from_be_bytes(capacity[4..8].try_into().unwrap());
+        if block_size as usize != BLOCK_SIZE {
+            return Err(StorageError::UnsupportedBlockSize(block_size));
+        }
+        disk.blocks = last_block as u64 + 1;
+
+        let model = format!(
+            "{} {}",
+            core::str::from_utf8(&inquiry[8..16]).unwrap_or("?").trim(),
+            core::str::from_utf8(&inquiry[16..32]).unwrap_or("?").trim(),
+        );
+        let model_len = model.len().min(MODEL_LEN);
+        disk.model[..model_len].copy_from_slice(&model.as_bytes()[..model_len]);
+
+        logln!(
+            "USB disk {}: '{model}' with {} blocks",
+            device.address,
+            disk.blocks
+        );
+
+        Ok(disk)
+    }
+
+    /// The amount of blocks on the disk.
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    fn wait_until_ready(&mut self) -> Result<(), StorageError> {
+        for _ in 0..READY_ATTEMPTS {
+            match self.command(&[SCSI_TEST_UNIT_READY, 0, 0, 0, 0, 0], &mut [], false) {
+                Ok(_) => return Ok(()),
+                Err(StorageError::CommandFailed) => {
+                    // Reading the sense data clears the 'unit attention' most disks report
+                    // after they are powered on
+                    let mut sense = [0; REQUEST_SENSE_LEN];
+                    self.command(
+                        &[SCSI_REQUEST_SENSE, 0, 0, 0, REQUEST_SENSE_LEN as u8, 0],
+                        &mut sense,
+                        true,
+                    )?;
+                    spin_delay_ms(READY_DELAY_MS);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(StorageError::Usb(UsbError::Timeout))
+    }
+
+    /// Read the blocks starting at `lba` into `data`, which must be a multiple of the
+    /// block size.
+    pub fn read_blocks(&mut self, lba: u64, data: &mut [u8]) -> Result<(), StorageError> {
+        let count = (data.len() / BLOCK_SIZE) as u64;
+        if data.len() % BLOCK_SIZE != 0
+            || lba.checked_add(count).is_none_or(|end| end > self.blocks)
+        {
+            return Err(StorageError::OutOfRange);
+        }
+
+        for (index, chunk) in data
+            .chunks_mut(MAX_BLOCKS_PER_READ * BLOCK_SIZE)
+            .enumerate()
+        {
+            // READ(10) only has 32 bits for the block, which `blocks` always fits in since
+            // it came from READ CAPACITY(10)
+            let lba = (lba + (index * MAX_BLOCKS_PER_READ) as u64) as u32;
+            let count = (chunk.len() / BLOCK_SIZE) as u16;
+            let [lba0, lba1, lba2, lba3] = lba.to_be_bytes();
+            let [count0, count1] = count.to_be_bytes();
+
+            self.command(
+                &[SCSI_READ, 0, lba0, lba1, lba2, lba3, 0, count0, count1, 0],
+                chunk,
+                true,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Run a SCSI command, returning the amount of data transferred.
+    fn command(
+        &mut self,
+        command: &[u8],
+        data: &mut [u8],
+        device_to_host: bool,
+    ) -> Result<usize, StorageError> {
+        self.tag = self.tag.wrapping_add(1);
+
+        let mut wrapper = [0; CBW_LEN];
+        wrapper[0..4].copy_from_slice(&CBW_SIGNATURE.to_le_bytes());
+        wrapper[4..8].copy_from_slice(&self.tag.to_le_bytes());
+        wrapper[8..12].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        wrapper[12] = if device_to_host { CBW_DATA_IN } else { 0 };
+        wrapper[14] = command.len() as u8;
+        wrapper[15..15 + command.len()].copy_from_slice(command);
+
+        let bulk_in = self.interface.bulk_in.address;
+        let bulk_out = self.interface.bulk_out.address;
+
+        self.pipe
+            .bulk_transfer(&self.device, bulk_out, &mut wrapper)?;
+
+        let transferred = if data.is_empty() {
+            0
+        } else {
+            let endpoint = if device_to_host { bulk_in } else { bulk_out };
+            self.pipe.bulk_transfer(&self.device, endpoint, data)?
+        };
+
+        let mut status = [0; CSW_LEN];
+        self.pipe
+            .bulk_transfer(&self.device, bulk_in, &mut status)?;
+
+        if status[0..4] != CSW_SIGNATURE.to_le_bytes() || status[4..8] != self.tag.to_le_bytes() {
+            return Err(StorageError::InvalidStatus);
+        }
+
+        match status[12] {
+            CSW_PASSED => Ok(transferred),
+            CSW_FAILED => Err(StorageError::CommandFailed),
+            _ => Err(StorageError::InvalidStatus),
+        }
+    }
+}
+
+/// A bulk pipe that can be kept after the controller finished starting up
+pub type SharedPipe = Box<dyn BulkPipe + Send>;
+
+/// USB disks the fs server can read, by the index it asks for them with
+static DISKS: ScheduleLock<Vec<MassStorage<SharedPipe>>> = ScheduleLock::new(Vec::new());
+
+/// Let the fs server read `disk`, it looks for filesystems on it itself.
+pub fn register(disk: MassStorage<SharedPipe>) {
+    let mut disks = DISKS.lock();
+    logln!(
+        "USB disk {}: Registered as disk {}",
+        disk.device.address,
+        disks.len()
+    );
+    disks.push(disk);
+}
+
+/// The name and amount of blocks of the disk registered at `index`.
+pub fn disk_info(index: usize) -> Option<([u8; MODEL_LEN], u64)> {
+    DISKS
+        .lock()
+        .get(index)
+        .map(|disk| (disk.model, disk.blocks))
+}
+
+/// Read the blocks starting at `lba` of the disk registered at `index` into `data`.
+pub fn read_disk(index: usize, lba: u64, data: &mut [u8]) -> Option<Result<(), StorageError>> {
+    DISKS
+        .lock()
+        .get_mut(index)
+        .map(|disk| disk.read_blocks(lba, data))
+}
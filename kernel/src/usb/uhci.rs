@@ -0,0 +1,616 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{
+    ControlPipe, KeyboardEndpoint, SetupPacket, Speed, UsbDevice, UsbError, enumerate,
+    hid::{self, BootKeyboard},
+};
+use crate::{
+    int::attach_irq_handler,
+    pci::{Bar, PciDevice},
+    resources::{self, Resource, Sharing},
+    vmm::DmaPage,
+};
+use alloc::vec::Vec;
+use arch::{idt64::InterruptInfo, io::IOPort, pic8259::pic_unmask_irq, pit825x::spin_delay_ms};
+use lignan::{lock::DebugMutex, logln, warnln};
+
+/// IO register offsets
+const USBCMD: u16 = 0x00;
+const USBSTS: u16 = 0x02;
+const USBINTR: u16 = 0x04;
+const FRNUM: u16 = 0x06;
+const FRBASEADD: u16 = 0x08;
+const PORTSC1: u16 = 0x10;
+
+/// How many IO ports the registers take up
+const REGISTERS_LEN: u16 = 0x20;
+
+/// USBCMD bits
+const CMD_RUN: u16 = 1 << 0;
+const CMD_HOST_RESET: u16 = 1 << 1;
+const CMD_GLOBAL_RESET: u16 = 1 << 2;
+const CMD_CONFIGURED: u16 = 1 << 6;
+const CMD_MAX_PACKET_64: u16 = 1 << 7;
+
+/// Every USBSTS bit, they are all cleared by writing a 1
+const STS_ALL: u16 = 0x3F;
+
+/// USBINTR bit to interrupt when a transfer with `TD_IOC` completes
+const INTR_ON_COMPLETE: u16 = 1 << 2;
+
+/// PORTSC bits
+const PORT_CONNECTED: u16 = 1 << 0;
+const PORT_CONNECT_CHANGE: u16 = 1 << 1;
+const PORT_ENABLED: u16 = 1 << 2;
+const PORT_ENABLE_CHANGE: u16 = 1 << 3;
+/// This reserved bit always reads as 1, so we can tell where the ports end
+const PORT_ALWAYS_SET: u16 = 1 << 7;
+const PORT_LOW_SPEED: u16 = 1 << 8;
+const PORT_RESET: u16 = 1 << 9;
+/// UHCI has 2 ports, but some controllers have a few more
+const MAX_PORTS: u16 = 8;
+
+/// The PCI config register controlling the BIOS's PS/2 keyboard emulation
+const PCI_LEGSUP: u8 = 0xC0;
+/// Turn off emulation, and clear its status bits
+const LEGSUP_DISABLE_EMULATION: u16 = 0x8F00;
+
+/// Link pointer bits
+const LINK_TERMINATE: u32 = 1 << 0;
+const LINK_QUEUE_HEAD: u32 = 1 << 1;
+const LINK_DEPTH_FIRST: u32 = 1 << 2;
+
+/// Transfer descriptor control and status bits
+const TD_ACTIVE: u32 = 1 << 23;
+const TD_STALLED: u32 = 1 << 22;
+/// Stalled, data buffer, babble, CRC/timeout, and bitstuff errors
+const TD_ERRORS: u32 = 0b0111_0110 << 16;
+const TD_IOC: u32 = 1 << 24;
+const TD_LOW_SPEED: u32 = 1 << 26;
+/// Retry a transfer up to 3 times before giving up on it
+const TD_THREE_RETRIES: u32 = 3 << 27;
+/// The bits of the actual length, which is stored as one less than the length
+const TD_ACTUAL_LEN: u32 = 0x7FF;
+
+/// Packet ids
+const PID_SETUP: u8 = 0x2D;
+const PID_IN: u8 = 0x69;
+const PID_OUT: u8 = 0xE1;
+
+const FRAME_LIST_ENTRIES: usize = 1024;
+
+/// Where everything is in the transfers page
+const CONTROL_QH: usize = 0x000;
+const INTERRUPT_QH: usize = 0x010;
+const CONTROL_TDS: usize = 0x100;
+const TD_SIZE: usize = 32;
+const MAX_CONTROL_TDS: usize = 48;
+const INTERRUPT_TD: usize = CONTROL_TDS + MAX_CONTROL_TDS * TD_SIZE;
+const SETUP_BUFFER: usize = INTERRUPT_TD + TD_SIZE;
+const REPORT_BUFFER: usize = SETUP_BUFFER + 32;
+const CONTROL_BUFFER: usize = 0x800;
+const CONTROL_BUFFER_LEN: usize = 0x800;
+
+/// How long to wait for a control transfer before giving up
+const CONTROL_TIMEOUT_MS: usize = 500;
+/// How long to hold a port in reset
+const PORT_RESET_MS: u32 = 50;
+
+/// Controllers with a keyboard, polled from their interrupt handler
+static CONTROLLERS: DebugMutex<Vec<Uhci>> = DebugMutex::new(Vec::new());
+
+/// A transfer descriptor, the hardware also requires 16 bytes after this for software use
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TransferDescriptor {
+    link: u32,
+    status: u32,
+    token: u32,
+    buffer: u32,
+}
+
+impl TransferDescriptor {
+    /// The amount of bytes actually transferred
+    const fn actual_len(&self) -> usize {
+        ((self.status + 1) & TD_ACTUAL_LEN) as usize
+    }
+}
+
+/// A queue head, which the controller walks the transfer descriptors of
+#[repr(C)]
+struct QueueHead {
+    head: u32,
+    element: u32,
+}
+
+/// A boot keyboard plugged into one of our ports
+struct Keyboard {
+    device: UsbDevice,
+    endpoint: KeyboardEndpoint,
+    toggle: bool,
+    boot: BootKeyboard,
+}
+
+/// A USB 1.1 host controller
+pub struct Uhci {
+    io_base: IOPort,
+    frame_list: DmaPage,
+    transfers: DmaPage,
+    keyboard: Option<Keyboard>,
+}
+
+/// Start the UHCI controller `device`, and configure anything plugged into it.
+pub fn init(device: &PciDevice) {
+    let Some(Bar::Io(io_base)) = device.bar(4) else {
+        warnln!("UHCI {device:?} has no IO ports");
+        return;
+    };
+
+    let owner = device.owner_name("uhci");
+    let registers = Resource::IoPorts {
+        base: io_base,
+        len: REGISTERS_LEN,
+    };
+    if let Err(err) = resources::claim(&owner, &[(registers, Sharing::Exclusive)]) {
+        warnln!("UHCI {device:?}: {err}");
+        return;
+    }
+
+    device.enable();
+    // Stop the BIOS from pretending this controller's keyboards are PS/2 keyboards
+    device.write_u16(PCI_LEGSUP, LEGSUP_DISABLE_EMULATION);
+
+    let Some(mut uhci) = Uhci::new(IOPort::new(io_base)) else {
+        warnln!("UHCI {device:?}: Unable to allocate memory below 4GiB for transfers");
+        resources::release(&owner);
+        return;
+    };
+
+    uhci.start();
+    uhci.enumerate_ports();
+
+    if uhci.keyboard.is_none() {
+        return;
+    }
+
+    let Some(irq) = device.interrupt_line() else {
+        warnln!("UHCI {device:?} has no interrupt line, its keyboard will not work");
+        return;
+    };
+    // PCI interrupt lines can be shared, the handler checks every controller
+    if let Err(err) = resources::claim(&owner, &[(Resource::Irq(irq), Sharing::Shared)]) {
+        warnln!("UHCI {device:?}: {err}, its keyboard will not work");
+        return;
+    }
+
+    uhci.poll_keyboard();
+    if let Some(mut controllers) = CONTROLLERS.try_lock() {
+        controllers.push(uhci);
+    }
+
+    attach_irq_handler(uhci_interrupt_handler, irq);
+    unsafe {
+        if irq >= 8 {
+            // The second PIC is chained through the first PIC's IRQ 2
+            pic_unmask_irq(2);
+        }
+        pic_unmask_irq(irq);
+    }
+}
+
+fn uhci_interrupt_handler(_args: &InterruptInfo) {
+    // The interrupt line can be shared, so check every controller
+    if let Some(mut controllers) = CONTROLLERS.try_lock() {
+        controllers.iter_mut().for_each(Uhci::handle_interrupt);
+    }
+}
+
+const fn token(pid: u8, address: u8, endpoint: u8, toggle: bool, len: usize) -> u32 {
+    // Lengths are stored as one less, so zero length packets are 0x7FF
+    let max_len = if len == 0 { 0x7FF } else { (len - 1) as u32 };
+
+    max_len << 21
+        | (toggle as u32) << 19
+        | (endpoint as u32) << 15
+        | (address as u32) << 8
+        | pid as u32
+}
+
+impl Uhci {
+    fn new(io_base: IOPort) -> Option<Self> {
+        let frame_list = DmaPage::new()?;
+        let transfers = DmaPage::new()?;
+
+        // The controller only takes 32-bit addresses
+        if frame_list.phys(0).addr() > u32::MAX as usize
+            || transfers.phys(0).addr() > u32::MAX as usize
+        {
+            return None;
+        }
+
+        Some(Self {
+            io_base,
+            frame_list,
+            transfers,
+            keyboard: None,
+        })
+    }
+
+    fn read16(&self, register: u16) -> u16 {
+        unsafe { (self.io_base + register).read_word() }
+    }
+
+    fn write16(&self, register: u16, value: u16) {
+        unsafe { (self.io_base + register).write_word(value) }
+    }
+
+    fn write32(&self, register: u16, value: u32) {
+        unsafe { (self.io_base + register).write_dword(value) }
+    }
+
+    /// The physical address of `offset` into the transfers page
+    fn phys(&self, offset: usize) -> u32 {
+        self.transfers.phys(offset).addr() as u32
+    }
+
+    fn write_td(&self, offset: usize, td: TransferDescriptor) {
+        unsafe {
+            self.transfers
+                .ptr::<TransferDescriptor>(offset)
+                .write_volatile(td)
+        };
+    }
+
+    fn read_td(&self, offset: usize) -> TransferDescriptor {
+        unsafe {
+            self.transfers
+                .ptr::<TransferDescriptor>(offset)
+                .read_volatile()
+        }
+    }
+
+    fn set_element(&self, queue_head: usize, element: u32) {
+        unsafe { (*self.transfers.ptr::<QueueHead>(queue_head)).element = element };
+    }
+
+    /// Reset the controller, and start it running an empty schedule.
+    fn start(&mut self) {
+        self.write16(USBINTR, 0);
+        self.write16(USBCMD, CMD_GLOBAL_RESET);
+        spin_delay_ms(PORT_RESET_MS);
+        self.write16(USBCMD, 0);
+
+        self.write16(USBCMD, CMD_HOST_RESET);
+        for _ in 0..PORT_RESET_MS {
+            if self.read16(USBCMD) & CMD_HOST_RESET == 0 {
+                break;
+            }
+            spin_delay_ms(1);
+        }
+
+        // Every frame runs the keyboard's queue, followed by the control queue
+        let interrupt_qh = self.phys(INTERRUPT_QH) | LINK_QUEUE_HEAD;
+        for entry in 0..FRAME_LIST_ENTRIES {
+            unsafe {
+                self.frame_list
+                    .ptr::<u32>(entry * 4)
+                    .write_volatile(interrupt_qh)
+            };
+        }
+
+        unsafe {
+            self.transfers
+                .ptr::<QueueHead>(INTERRUPT_QH)
+                .write_volatile(QueueHead {
+                    head: self.phys(CONTROL_QH) | LINK_QUEUE_HEAD,
+                    element: LINK_TERMINATE,
+                });
+            self.transfers
+                .ptr::<QueueHead>(CONTROL_QH)
+                .write_volatile(QueueHead {
+                    head: LINK_TERMINATE,
+                    element: LINK_TERMINATE,
+                });
+        }
+
+        self.write32(FRBASEADD, self.frame_list.phys(0).addr() as u32);
+        self.write16(FRNUM, 0);
+        self.write16(USBSTS, STS_ALL);
+        self.write16(USBINTR, INTR_ON_COMPLETE);
+        self.write16(USBCMD, CMD_RUN | CMD_CONFIGURED | CMD_MAX_PACKET_64);
+    }
+
+    /// Reset and configure whatever is plugged into each port.
+    ///
+    /// FIXME: Devices are only found at boot, and hubs are not supported.
+    fn enumerate_ports(&mut self) {
+        let mut next_address = 1;
+
+        for port in 0..MAX_PORTS {
+            let register = PORTSC1 + port * 2;
+            let status = self.read16(register);
+            if status & PORT_ALWAYS_SET == 0 || status == 0xFFFF {
+                break;
+            }
+
+            let Some(speed) = self.reset_port(register) else {
+                continue;
+            };
+
+            match enumerate(self, next_address, speed) {
+                Ok((device, Some(endpoint))) if self.keyboard.is_none() => {
+                    logln!("USB device {}: Using as a boot keyboard", device.address);
+                    self.keyboard = Some(Keyboard {
+                        device,
+                        endpoint,
+                        toggle: false,
+                        boot: BootKeyboard::new(),
+                    });
+                }
+                Ok(_) => (),
+                Err(err) => warnln!("UHCI port {port}: Unable to enumerate device ({err:?})"),
+            }
+
+            next_address += 1;
+        }
+    }
+
+    /// Reset a port, returning the speed of its device once it is enabled.
+    fn reset_port(&self, register: u16) -> Option<Speed> {
+        if self.read16(register) & PORT_CONNECTED == 0 {
+            return None;
+        }
+
+        self.write16(register, PORT_RESET);
+        spin_delay_ms(PORT_RESET_MS);
+        self.write16(register, 0);
+
+        for _ in 0..10 {
+            spin_delay_ms(10);
+            let status = self.read16(register);
+
+            if status & PORT_CONNECTED == 0 {
+                return None;
+            }
+
+            if status & PORT_ENABLED != 0 {
+                self.write16(
+                    register,
+                    PORT_ENABLED | PORT_CONNECT_CHANGE | PORT_ENABLE_CHANGE,
+                );
+                return Some(if status & PORT_LOW_SPEED != 0 {
+                    Speed::Low
+                } else {
+                    Speed::Full
+                });
+            }
+
+            self.write16(
+                register,
+                PORT_ENABLED | PORT_CONNECT_CHANGE | PORT_ENABLE_CHANGE,
+            );
+        }
+
+        None
+    }
+
+    /// Queue a transfer for the next report from the keyboard.
+    fn poll_keyboard(&self) {
+        let Some(keyboard) = self.keyboard.as_ref() else {
+            return;
+        };
+
+        let len = (keyboard.endpoint.max_packet as usize).min(hid::REPORT_LEN);
+        let low_speed = if keyboard.device.speed == Speed::Low {
+            TD_LOW_SPEED
+        } else {
+            0
+        };
+
+        self.write_td(
+            INTERRUPT_TD,
+            TransferDescriptor {
+                link: LINK_TERMINATE,
+                status: TD_ACTIVE | TD_IOC | TD_THREE_RETRIES | low_speed,
+                token: token(
+                    PID_IN,
+                    keyboard.device.address,
+                    keyboard.endpoint.endpoint,
+                    keyboard.toggle,
+                    len,
+                ),
+                buffer: self.phys(REPORT_BUFFER),
+            },
+        );
+        self.set_element(INTERRUPT_QH, self.phys(INTERRUPT_TD));
+    }
+
+    fn handle_interrupt(&mut self) {
+        let status = self.read16(USBSTS);
+        if status == 0 {
+            return;
+        }
+        self.write16(USBSTS, status);
+
+        let td = self.read_td(INTERRUPT_TD);
+        let Some(keyboard) = self.keyboard.as_mut() else {
+            return;
+        };
+
+        if td.status & TD_ACTIVE != 0 {
+            return;
+        }
+
+        if td.status & TD_ERRORS == 0 {
+            keyboard.toggle = !keyboard.toggle;
+
+            let report = unsafe {
+                core::slice::from_raw_parts(
+                    self.transfers.ptr::<u8>(REPORT_BUFFER) as *const u8,
+                    td.actual_len(),
+                )
+            };
+            keyboard.boot.handle_report(report);
+        }
+
+        self.poll_keyboard();
+    }
+}
+
+impl ControlPipe for Uhci {
+    fn control_transfer(
+        &mut self,
+        device: &UsbDevice,
+        setup: SetupPacket,
+        data: &mut [u8],
+    ) -> Result<usize, UsbError> {
+        let len = (setup.length as usize).min(data.len());
+        let max_packet = device.max_packet as usize;
+        let data_packets = len.div_ceil(max_packet);
+        let device_to_host = setup.is_device_to_host();
+
+        // The setup and status stages take one descriptor each
+        if data_packets + 2 > MAX_CONTROL_TDS || len > CONTROL_BUFFER_LEN {
+            return Err(UsbError::TooLarge);
+        }
+
+        unsafe {
+            self.transfers
+                .ptr::<[u8; 8]>(SETUP_BUFFER)
+                .write_volatile(setup.to_bytes());
+
+            if !device_to_host {
+                core::ptr::copy_nonoverlapping(
+                    data.as_ptr(),
+                    self.transfers.ptr::<u8>(CONTROL_BUFFER),
+                    len,
+                );
+            }
+        }
+
+        let low_speed = if device.speed == Speed::Low {
+            TD_LOW_SPEED
+        } else {
+            0
+        };
+        let status = TD_ACTIVE | TD_THREE_RETRIES | low_speed;
+        let n_tds = data_packets + 2;
+        let td_offset = |index: usize| CONTROL_TDS + index * TD_SIZE;
+        let link = |index: usize| {
+            if index + 1 == n_tds {
+                LINK_TERMINATE
+            } else {
+                self.phys(td_offset(index + 1)) | LINK_DEPTH_FIRST
+            }
+        };
+
+        self.write_td(
+            td_offset(0),
+            TransferDescriptor {
+                link: link(0),
+                status,
+                token: token(PID_SETUP, device.address, 0, false, 8),
+                buffer: self.phys(SETUP_BUFFER),
+            },
+        );
+
+        // Data packets alternate their toggle, starting from 1 after the setup packet
+        let data_pid = if device_to_host { PID_IN } else { PID_OUT };
+        for packet in 0..data_packets {
+            let offset = packet * max_packet;
+            let packet_len = max_packet.min(len - offset);
+
+            self.write_td(
+                td_offset(packet + 1),
+                TransferDescriptor {
+                    link: link(packet + 1),
+                    status,
+                    token: token(data_pid, device.address, 0, packet % 2 == 0, packet_len),
+                    buffer: self.phys(CONTROL_BUFFER + offset),
+                },
+            );
+        }
+
+        // The status stage goes the other way to the data, and is always an IN without data
+        let status_pid = if device_to_host && len != 0 {
+            PID_OUT
+        } else {
+            PID_IN
+        };
+        self.write_td(
+            td_offset(n_tds - 1),
+            TransferDescriptor {
+                link: LINK_TERMINATE,
+                status,
+                token: token(status_pid, device.address, 0, true, 0),
+                buffer: 0,
+            },
+        );
+
+        self.set_element(CONTROL_QH, self.phys(td_offset(0)));
+
+        let mut result: Result<usize, UsbError> = Err(UsbError::Timeout);
+        for _ in 0..CONTROL_TIMEOUT_MS {
+            let tds = (0..n_tds).map(|index| self.read_td(td_offset(index)));
+
+            if let Some(failed) = tds.clone().find(|td| td.status & TD_ERRORS != 0) {
+                result = Err(if failed.status & TD_STALLED != 0 {
+                    UsbError::Stalled
+                } else {
+                    UsbError::TransferError
+                });
+                break;
+            }
+
+            if tds.clone().all(|td| td.status & TD_ACTIVE == 0) {
+                result = Ok(tds
+                    .skip(1)
+                    .take(data_packets)
+                    .map(|td| td.actual_len())
+                    .sum());
+                break;
+            }
+
+            spin_delay_ms(1);
+        }
+
+        // Take the transfer off the schedule, in case it failed part of the way through
+        self.set_element(CONTROL_QH, LINK_TERMINATE);
+
+        let transferred = result?;
+        if device_to_host {
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    self.transfers.ptr::<u8>(CONTROL_BUFFER) as *const u8,
+                    data.as_mut_ptr(),
+                    transferred.min(len),
+                );
+            }
+        }
+
+        Ok(transferred)
+    }
+}
@@ -0,0 +1,1087 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{
+    ControlPipe, SetupPacket, Speed, UsbDevice, UsbError, configure, find_boot_keyboard,
+    read_max_packet,
+    storage::{self, BulkEndpoint, BulkPipe, MassStorage, StorageInterface},
+};
+use crate::{
+    locks::ScheduleLock,
+    pci::{Bar, PciDevice},
+    resources::{self, ClaimError, Resource, Sharing},
+    vmm::{DmaPage, map_mmio},
+};
+use alloc::{boxed::Box, vec::Vec};
+use arch::pit825x::spin_delay_ms;
+use lignan::{logln, warnln};
+use mem::{
+    addr::{PhysAddr, VirtAddr},
+    paging::CacheMode,
+};
+use util::consts::PAGE_4K;
+
+/// Capability register offsets
+const CAPLENGTH: usize = 0x00;
+const HCSPARAMS1: usize = 0x04;
+const HCSPARAMS2: usize = 0x08;
+const HCCPARAMS1: usize = 0x10;
+const DBOFF: usize = 0x14;
+const RTSOFF: usize = 0x18;
+
+/// Operational register offsets, from the end of the capability registers
+const USBCMD: usize = 0x00;
+const USBSTS: usize = 0x04;
+const CRCR: usize = 0x18;
+const DCBAAP: usize = 0x30;
+const CONFIG: usize = 0x38;
+const PORTSC: usize = 0x400;
+const PORT_REGISTERS_LEN: usize = 0x10;
+
+/// Interrupter 0's registers, from the start of the runtime registers
+const ERSTSZ: usize = 0x28;
+const ERSTBA: usize = 0x30;
+const ERDP: usize = 0x38;
+/// Cleared by writing a 1 when we move the event ring's dequeue pointer
+const ERDP_HANDLER_BUSY: u64 = 1 << 3;
+
+const CMD_RUN: u32 = 1 << 0;
+const CMD_RESET: u32 = 1 << 1;
+const STS_HALTED: u32 = 1 << 0;
+const STS_NOT_READY: u32 = 1 << 11;
+
+/// HCCPARAMS1 bits
+const PARAMS_64BIT_ADDRESSING: u32 = 1 << 0;
+const PARAMS_64BYTE_CONTEXTS: u32 = 1 << 2;
+
+/// PORTSC bits
+const PORT_CONNECTED: u32 = 1 << 0;
+const PORT_ENABLED: u32 = 1 << 1;
+const PORT_RESET: u32 = 1 << 4;
+const PORT_POWER: u32 = 1 << 9;
+const PORT_SPEED_SHIFT: u32 = 10;
+const PORT_RESET_CHANGE: u32 = 1 << 21;
+/// Every status change bit, they are all cleared by writing a 1
+const PORT_CHANGES: u32 = 0x7F << 17;
+/// The bits that keep their value when written back, the rest are either read only or do
+/// something when a 1 is written to them
+const PORT_PRESERVE: u32 = 0x0E00_C3E0;
+
+/// The legacy support extended capability, used to take the controller from the BIOS
+const CAPABILITY_LEGACY: u32 = 1;
+const LEGACY_BIOS_OWNED: u32 = 1 << 16;
+const LEGACY_OS_OWNED: u32 = 1 << 24;
+
+/// TRB control bits
+const TRB_CYCLE: u32 = 1 << 0;
+const TRB_TOGGLE_CYCLE: u32 = 1 << 1;
+const TRB_INTERRUPT_ON_SHORT: u32 = 1 << 2;
+const TRB_IOC: u32 = 1 << 5;
+const TRB_IMMEDIATE_DATA: u32 = 1 << 6;
+const TRB_DIRECTION_IN: u32 = 1 << 16;
+const TRB_TYPE_SHIFT: u32 = 10;
+const TRB_ENDPOINT_SHIFT: u32 = 16;
+const TRB_SLOT_SHIFT: u32 = 24;
+
+/// TRB types
+const TRB_NORMAL: u32 = 1;
+const TRB_SETUP: u32 = 2;
+const TRB_DATA: u32 = 3;
+const TRB_STATUS: u32 = 4;
+const TRB_LINK: u32 = 6;
+const TRB_ENABLE_SLOT: u32 = 9;
+const TRB_ADDRESS_DEVICE: u32 = 11;
+const TRB_CONFIGURE_ENDPOINT: u32 = 12;
+const TRB_EVALUATE_CONTEXT: u32 = 13;
+const TRB_RESET_ENDPOINT: u32 = 14;
+const TRB_SET_DEQUEUE: u32 = 16;
+const TRB_TRANSFER_EVENT: u32 = 32;
+const TRB_COMMAND_COMPLETION: u32 = 33;
+
+/// The transfer type of a setup stage, saying which way its data stage goes
+const SETUP_NO_DATA: u32 = 0 << 16;
+const SETUP_OUT: u32 = 2 << 16;
+const SETUP_IN: u32 = 3 << 16;
+
+/// Completion codes
+const COMPLETION_SUCCESS: u32 = 1;
+const COMPLETION_STALL: u32 = 6;
+const COMPLETION_SHORT_PACKET: u32 = 13;
+
+/// Endpoint types
+const ENDPOINT_BULK_OUT: u32 = 2;
+const ENDPOINT_CONTROL: u32 = 4;
+const ENDPOINT_BULK_IN: u32 = 6;
+/// Retry a transfer up to 3 times before giving up on it
+const ENDPOINT_THREE_RETRIES: u32 = 3 << 1;
+/// The average transfer sizes the controller uses to plan its bandwidth
+const CONTROL_AVERAGE_LEN: u32 = 8;
+const BULK_AVERAGE_LEN: u32 = 3072;
+
+/// The device context index of the control endpoint
+const CONTROL_ENDPOINT: u8 = 1;
+/// The input control context flag for the slot context
+const ADD_SLOT_CONTEXT: u32 = 1 << 0;
+
+/// Every ring takes up one page
+const RING_TRBS: usize = PAGE_4K / size_of::<Trb>();
+
+/// Where everything is in the page holding the device context base address array
+const EVENT_RING_SEGMENTS: usize = 0x800;
+const SCRATCHPAD_ARRAY: usize = 0xC00;
+const MAX_SCRATCHPADS: usize = (PAGE_4K - SCRATCHPAD_ARRAY) / size_of::<u64>();
+/// The most device slots we use, so the base address array stays out of the way
+const MAX_SLOTS: u32 = 64;
+
+/// How long to wait for the controller, commands, and transfers
+const TIMEOUT_MS: usize = 1000;
+/// How long to wait for a port to reset, or to power on
+const PORT_TIMEOUT_MS: usize = 100;
+
+#[derive(Debug)]
+enum InitError {
+    Claim(ClaimError),
+    MapRegisters,
+    /// The registers go past the end of the controller's BAR
+    RegistersOutOfRange,
+    NoMemory,
+    TooManyScratchpads,
+    NotHalted,
+    NotReset,
+    NotRunning,
+}
+
+/// A transfer request block, the unit of every ring
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Trb {
+    parameter: u64,
+    status: u32,
+    control: u32,
+}
+
+impl Trb {
+    const fn new(kind: u32, parameter: u64, status: u32, control: u32) -> Self {
+        Self {
+            parameter,
+            status,
+            control: control | kind << TRB_TYPE_SHIFT,
+        }
+    }
+
+    /// A command or transfer addressed to an endpoint of a slot
+    const fn for_endpoint(kind: u32, parameter: u64, slot: u8, endpoint: u8) -> Self {
+        Self::new(
+            kind,
+            parameter,
+            0,
+            (slot as u32) << TRB_SLOT_SHIFT | (endpoint as u32) << TRB_ENDPOINT_SHIFT,
+        )
+    }
+
+    const fn kind(&self) -> u32 {
+        (self.control >> TRB_TYPE_SHIFT) & 0x3F
+    }
+
+    const fn slot(&self) -> u8 {
+        (self.control >> TRB_SLOT_SHIFT) as u8
+    }
+
+    const fn endpoint(&self) -> u8 {
+        ((self.control >> TRB_ENDPOINT_SHIFT) & 0x1F) as u8
+    }
+
+    /// For transfer events, the amount of bytes that were not transferred
+    const fn residual(&self) -> usize {
+        (self.status & 0xFF_FFFF) as usize
+    }
+
+    /// For events, if the command or transfer they complete succeeded
+    const fn result(&self) -> Result<(), UsbError> {
+        match self.status >> 24 {
+            COMPLETION_SUCCESS | COMPLETION_SHORT_PACKET => Ok(()),
+            COMPLETION_STALL => Err(UsbError::Stalled),
+            _ => Err(UsbError::TransferError),
+        }
+    }
+}
+
+/// A ring of commands or transfers we give to the controller
+struct Ring {
+    page: DmaPage,
+    enqueue: usize,
+    cycle: bool,
+}
+
+impl Ring {
+    const fn new(page: DmaPage) -> Self {
+        Self {
+            page,
+            enqueue: 0,
+            cycle: true,
+        }
+    }
+
+    /// Where the controller should start reading this ring from, along with its cycle bit.
+    fn dequeue_pointer(&self) -> u64 {
+        self.physical(self.enqueue) | self.cycle as u64
+    }
+
+    fn physical(&self, index: usize) -> u64 {
+        self.page.phys(index * size_of::<Trb>()).addr() as u64
+    }
+
+    /// Add `trb` to the ring, returning its physical address.
+    fn push(&mut self, trb: Trb) -> u64 {
+        let pointer = self.physical(self.enqueue);
+        self.write(self.enqueue, trb);
+        self.enqueue += 1;
+
+        // The last entry links back to the start, flipping the cycle bit the controller
+        // uses to know which entries are new
+        if self.enqueue == RING_TRBS - 1 {
+            self.write(
+                self.enqueue,
+                Trb::new(TRB_LINK, self.physical(0), 0, TRB_TOGGLE_CYCLE),
+            );
+            self.enqueue = 0;
+            self.cycle = !self.cycle;
+        }
+
+        pointer
+    }
+
+    fn write(&self, index: usize, trb: Trb) {
+        let entry = self.page.ptr::<Trb>(index * size_of::<Trb>());
+        let control = (trb.control & !TRB_CYCLE) | self.cycle as u32;
+
+        // The cycle bit hands the entry to the controller, so it must be written last
+        unsafe {
+            (&raw mut (*entry).parameter).write_volatile(trb.parameter);
+            (&raw mut (*entry).status).write_volatile(trb.status);
+            (&raw mut (*entry).control).write_volatile(control);
+        }
+    }
+}
+
+/// The ring the controller tells us about finished commands and transfers on
+struct EventRing {
+    page: DmaPage,
+    dequeue: usize,
+    cycle: bool,
+}
+
+impl EventRing {
+    fn dequeue_pointer(&self) -> u64 {
+        self.page.phys(self.dequeue * size_of::<Trb>()).addr() as u64
+    }
+
+    fn pop(&mut self) -> Option<Trb> {
+        let event = unsafe {
+            self.page
+                .ptr::<Trb>(self.dequeue * size_of::<Trb>())
+                .read_volatile()
+        };
+
+        if (event.control & TRB_CYCLE != 0) != self.cycle {
+            return None;
+        }
+
+        self.dequeue += 1;
+        if self.dequeue == RING_TRBS {
+            self.dequeue = 0;
+            self.cycle = !self.cycle;
+        }
+
+        Some(event)
+    }
+}
+
+/// A device the controller gave a slot to
+struct Slot {
+    id: u8,
+    port: u8,
+    speed: Speed,
+    /// The controller keeps the device's state here
+    _context: DmaPage,
+    /// Each endpoint's transfer ring, by device context index
+    rings: Vec<(u8, Ring)>,
+}
+
+/// Controllers with a disk, kept for the disks to send their commands through
+static CONTROLLERS: ScheduleLock<Vec<Xhci>> = ScheduleLock::new(Vec::new());
+
+/// The bulk pipe of the controller at `index` into [`CONTROLLERS`]
+struct SharedXhci {
+    index: usize,
+}
+
+impl BulkPipe for SharedXhci {
+    fn bulk_transfer(
+        &mut self,
+        device: &UsbDevice,
+        endpoint: u8,
+        data: &mut [u8],
+    ) -> Result<usize, UsbError> {
+        CONTROLLERS.lock()[self.index].bulk_transfer(device, endpoint, data)
+    }
+}
+
+/// A USB 3 host controller
+pub struct Xhci {
+    registers: VirtAddr,
+    /// How many bytes are mapped at `registers`
+    len: usize,
+    operational: usize,
+    runtime: usize,
+    doorbells: usize,
+    n_ports: u8,
+    max_slots: u32,
+    context_size: usize,
+    addressing_64: bool,
+    /// The device context base address array, event ring segment table, and scratchpad
+    /// buffer array
+    dcbaa: DmaPage,
+    /// The input context given to commands that change a slot
+    input: DmaPage,
+    /// Where data is sent from and received into
+    buffer: DmaPage,
+    commands: Ring,
+    events: EventRing,
+    slots: Vec<Slot>,
+}
+
+/// Start the xHCI controller `device`, and configure anything plugged into it.
+pub fn init(device: &PciDevice) {
+    let (Some(Bar::Memory(base)), Some(len)) = (device.bar(0), device.bar_size(0)) else {
+        warnln!("xHCI {device:?} has no registers");
+        return;
+    };
+
+    device.enable();
+
+    let owner = device.owner_name("xhci");
+    let base = PhysAddr::new(base as usize);
+    let mut xhci = match Xhci::new(base, len as usize, &owner).and_then(|mut xhci| {
+        xhci.start()?;
+        Ok(xhci)
+    }) {
+        Ok(xhci) => xhci,
+        Err(err) => {
+            warnln!("xHCI {device:?}: Unable to start controller ({err:?})");
+            resources::release(&owner);
+            return;
+        }
+    };
+
+    logln!("xHCI {device:?}: Running with {} ports", xhci.n_ports);
+
+    // FIXME: Devices are only found at boot, and hubs are not supported.
+    let mut disks = Vec::new();
+    for port in 1..=xhci.n_ports {
+        let Some(speed) = xhci.reset_port(port) else {
+            continue;
+        };
+
+        match xhci.attach(port, speed) {
+            Ok(Some(disk)) => disks.push(disk),
+            Ok(None) => (),
+            Err(err) => warnln!("xHCI port {port}: Unable to enumerate device ({err:?})"),
+        }
+    }
+
+    if disks.is_empty() {
+        return;
+    }
+
+    // The disks outlive this function, so the controller has to as well
+    let index = {
+        let mut controllers = CONTROLLERS.lock();
+        controllers.push(xhci);
+        controllers.len() - 1
+    };
+
+    for (device, interface) in disks {
+        match MassStorage::new(Box::new(SharedXhci { index }) as _, device, interface) {
+            Ok(disk) => storage::register(disk),
+            Err(err) => warnln!("USB disk {}: Unable to use disk ({err:?})", device.address),
+        }
+    }
+}
+
+/// Allocate a page the controller can reach.
+fn dma_page(addressing_64: bool) -> Option<DmaPage> {
+    DmaPage::new()
+        .filter(|page| addressing_64 || page.phys(PAGE_4K - 1).addr() <= u32::MAX as usize)
+}
+
+const fn speed_id(speed: Speed) -> u32 {
+    match speed {
+        Speed::Full => 1,
+        Speed::Low => 2,
+        Speed::High => 3,
+        Speed::Super => 4,
+    }
+}
+
+/// The device context index of a non-control endpoint's address
+const fn endpoint_index(address: u8) -> u8 {
+    (address & 0xF) * 2 + (address >> 7)
+}
+
+impl Xhci {
+    /// Map the controller's `len` bytes of registers at `base`, claiming them for `owner`
+    ///
+    /// The whole BAR is mapped, since the extended capabilities can be far past the
+    /// other registers.
+    fn new(base: PhysAddr, len: usize, owner: &str) -> Result<Self, InitError> {
+        let len = len.max(PAGE_4K);
+        resources::claim(owner, &[(Resource::Mmio { base, len }, Sharing::Exclusive)])
+            .map_err(InitError::Claim)?;
+
+        let registers =
+            map_mmio(base, len, CacheMode::Uncached).map_err(|_| InitError::MapRegisters)?;
+        let read =
+            |offset: usize| unsafe { registers.offset(offset).as_mut_ptr::<u32>().read_volatile() };
+
+        let hcsparams1 = read(HCSPARAMS1);
+        let hccparams1 = read(HCCPARAMS1);
+        let operational = (read(CAPLENGTH) & 0xFF) as usize;
+        let runtime = (read(RTSOFF) & !0x1F) as usize;
+        let doorbells = (read(DBOFF) & !0x3) as usize;
+        let n_ports = (hcsparams1 >> 24) as u8;
+        let max_slots = (hcsparams1 & 0xFF).min(MAX_SLOTS);
+
+        let end = [
+            operational + PORTSC + n_ports as usize * PORT_REGISTERS_LEN,
+            runtime + ERDP + size_of::<u64>(),
+            doorbells + (max_slots as usize + 1) * size_of::<u32>(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap();
+        if end > len {
+            return Err(InitError::RegistersOutOfRange);
+        }
+
+        let addressing_64 = hccparams1 & PARAMS_64BIT_ADDRESSING != 0;
+        let page = || dma_page(addressing_64).ok_or(InitError::NoMemory);
+
+        Ok(Self {
+            registers,
+            len,
+            operational,
+            runtime,
+            doorbells,
+            n_ports,
+            max_slots,
+            context_size: if hccparams1 & PARAMS_64BYTE_CONTEXTS != 0 {
+                64
+            } else {
+                32
+            },
+            addressing_64,
+            dcbaa: page()?,
+            input: page()?,
+            buffer: page()?,
+            commands: Ring::new(page()?),
+            events: EventRing {
+                page: page()?,
+                dequeue: 0,
+                cycle: true,
+            },
+            slots: Vec::new(),
+        })
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        unsafe {
+            self.registers
+                .offset(offset)
+                .as_mut_ptr::<u32>()
+                .read_volatile()
+        }
+    }
+
+    fn write(&self, offset: usize, value: u32) {
+        unsafe {
+            self.registers
+                .offset(offset)
+                .as_mut_ptr::<u32>()
+                .write_volatile(value)
+        }
+    }
+
+    fn write64(&self, offset: usize, value: u64) {
+        self.write(offset, value as u32);
+        self.write(offset + 4, (value >> 32) as u32);
+    }
+
+    fn wait_for(&self, condition: impl Fn(&Self) -> bool, timeout_ms: usize) -> bool {
+        (0..timeout_ms).any(|_| {
+            let done = condition(self);
+            if !done {
+                spin_delay_ms(1);
+            }
+            done
+        })
+    }
+
+    /// Take the controller from the BIOS, reset it, and start it running.
+    fn start(&mut self) -> Result<(), InitError> {
+        self.take_from_bios();
+
+        let usbcmd = self.operational + USBCMD;
+        let usbsts = self.operational + USBSTS;
+
+        self.write(usbcmd, self.read(usbcmd) & !CMD_RUN);
+        if !self.wait_for(|xhci| xhci.read(usbsts) & STS_HALTED != 0, TIMEOUT_MS) {
+            return Err(InitError::NotHalted);
+        }
+
+        self.write(usbcmd, CMD_RESET);
+        if !self.wait_for(
+            |xhci| xhci.read(usbcmd) & CMD_RESET == 0 && xhci.read(usbsts) & STS_NOT_READY == 0,
+            TIMEOUT_MS,
+        ) {
+            return Err(InitError::NotReset);
+        }
+
+        self.write(self.operational + CONFIG, self.max_slots);
+
+        // Some controllers need memory of their own to work with
+        let hcsparams2 = self.read(HCSPARAMS2);
+        let scratchpads = ((hcsparams2 >> 21) & 0x1F) << 5 | (hcsparams2 >> 27) & 0x1F;
+        if scratchpads as usize > MAX_SCRATCHPADS {
+            return Err(InitError::TooManyScratchpads);
+        }
+
+        for index in 0..scratchpads as usize {
+            let page = dma_page(self.addressing_64).ok_or(InitError::NoMemory)?;
+            let entry = self
+                .dcbaa
+                .ptr::<u64>(SCRATCHPAD_ARRAY + index * size_of::<u64>());
+            unsafe { entry.write_volatile(page.phys(0).addr() as u64) };
+        }
+        if scratchpads != 0 {
+            let array = self.dcbaa.phys(SCRATCHPAD_ARRAY).addr() as u64;
+            unsafe { self.dcbaa.ptr::<u64>(0).write_volatile(array) };
+        }
+
+        // The event ring is a single segment
+        unsafe {
+            self.dcbaa
+                .ptr::<u64>(EVENT_RING_SEGMENTS)
+                .write_volatile(self.events.page.phys(0).addr() as u64);
+            self.dcbaa
+                .ptr::<u32>(EVENT_RING_SEGMENTS + 8)
+                .write_volatile(RING_TRBS as u32);
+        }
+
+        self.write64(self.operational + DCBAAP, self.dcbaa.phys(0).addr() as u64);
+        self.write64(self.operational + CRCR, self.commands.dequeue_pointer());
+        self.write(self.runtime + ERSTSZ, 1);
+        self.write64(self.runtime + ERDP, self.events.dequeue_pointer());
+        self.write64(
+            self.runtime + ERSTBA,
+            self.dcbaa.phys(EVENT_RING_SEGMENTS).addr() as u64,
+        );
+
+        self.write(usbcmd, CMD_RUN);
+        if !self.wait_for(|xhci| xhci.read(usbsts) & STS_HALTED == 0, TIMEOUT_MS) {
+            return Err(InitError::NotRunning);
+        }
+
+        for port in 1..=self.n_ports {
+            let register = self.port_register(port);
+            let status = self.read(register);
+
+            if status & PORT_POWER == 0 {
+                self.write(register, status & PORT_PRESERVE | PORT_POWER);
+                spin_delay_ms(PORT_TIMEOUT_MS as u32);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ask the BIOS to stop using the controller for its keyboard emulation.
+    fn take_from_bios(&self) {
+        let mut capability = ((self.read(HCCPARAMS1) >> 16) as usize) * size_of::<u32>();
+
+        while capability != 0 {
+            if capability + size_of::<u32>() > self.len {
+                warnln!("xHCI: Extended capability at {capability:#x} is past the registers");
+                return;
+            }
+
+            let value = self.read(capability);
+
+            if value & 0xFF == CAPABILITY_LEGACY {
+                self.write(capability, value | LEGACY_OS_OWNED);
+
+                if !self.wait_for(
+                    |xhci| xhci.read(capability) & LEGACY_BIOS_OWNED == 0,
+                    TIMEOUT_MS,
+                ) {
+                    warnln!("xHCI: BIOS did not give up the controller, taking it anyway");
+                    self.write(capability, (value | LEGACY_OS_OWNED) & !LEGACY_BIOS_OWNED);
+                }
+
+                return;
+            }
+
+            let next = ((value >> 8) & 0xFF) as usize;
+            if next == 0 {
+                return;
+            }
+            capability += next * size_of::<u32>();
+        }
+    }
+
+    fn port_register(&self, port: u8) -> usize {
+        self.operational + PORTSC + (port as usize - 1) * PORT_REGISTERS_LEN
+    }
+
+    /// Reset a port, returning the speed of its device once it is enabled.
+    fn reset_port(&self, port: u8) -> Option<Speed> {
+        let register = self.port_register(port);
+        let status = self.read(register);
+
+        if status & PORT_CONNECTED == 0 {
+            return None;
+        }
+
+        // USB 3 ports enable themselves once their link is up, but USB 2 ports need a reset
+        if status & PORT_ENABLED == 0 {
+            self.write(register, status & PORT_PRESERVE | PORT_RESET);
+
+            if !self.wait_for(
+                |xhci| xhci.read(register) & PORT_RESET_CHANGE != 0,
+                PORT_TIMEOUT_MS,
+            ) {
+                return None;
+            }
+        }
+
+        let status = self.read(register);
+        self.write(register, status & PORT_PRESERVE | PORT_CHANGES);
+
+        if status & PORT_ENABLED == 0 {
+            return None;
+        }
+
+        match (status >> PORT_SPEED_SHIFT) & 0xF {
+            1 => Some(Speed::Full),
+            2 => Some(Speed::Low),
+            3 => Some(Speed::High),
+            4 | 5 => Some(Speed::Super),
+            _ => None,
+        }
+    }
+
+    fn ring_doorbell(&self, slot: u8, target: u8) {
+        self.write(
+            self.doorbells + slot as usize * size_of::<u32>(),
+            target as u32,
+        );
+    }
+
+    /// Wait for an event that `matches`, dropping any others.
+    fn wait_for_event(&mut self, matches: impl Fn(&Trb) -> bool) -> Result<Trb, UsbError> {
+        for _ in 0..TIMEOUT_MS {
+            while let Some(event) = self.events.pop() {
+                self.write64(
+                    self.runtime + ERDP,
+                    self.events.dequeue_pointer() | ERDP_HANDLER_BUSY,
+                );
+
+                if matches(&event) {
+                    return Ok(event);
+                }
+            }
+
+            spin_delay_ms(1);
+        }
+
+        Err(UsbError::Timeout)
+    }
+
+    /// Run a command, returning its completion event.
+    fn command(&mut self, command: Trb) -> Result<Trb, UsbError> {
+        let pointer = self.commands.push(command);
+        self.ring_doorbell(0, 0);
+
+        let event = self.wait_for_event(|event| {
+            event.kind() == TRB_COMMAND_COMPLETION && event.parameter == pointer
+        })?;
+        event.result()?;
+
+        Ok(event)
+    }
+
+    /// Wait for the next transfer on an endpoint to finish.
+    ///
+    /// Failed transfers halt the endpoint, so it is restarted past them.
+    fn wait_for_transfer(&mut self, slot: u8, endpoint: u8) -> Result<Trb, UsbError> {
+        let event = self.wait_for_event(|event| {
+            event.kind() == TRB_TRANSFER_EVENT
+                && event.slot() == slot
+                && event.endpoint() == endpoint
+        })?;
+
+        if let Err(err) = event.result() {
+            let _ = self.command(Trb::for_endpoint(TRB_RESET_ENDPOINT, 0, slot, endpoint));
+
+            let dequeue = self.ring(slot, endpoint)?.dequeue_pointer();
+            let _ = self.command(Trb::for_endpoint(TRB_SET_DEQUEUE, dequeue, slot, endpoint));
+
+            return Err(err);
+        }
+
+        Ok(event)
+    }
+
+    fn slot(&mut self, id: u8) -> Result<&mut Slot, UsbError> {
+        self.slots
+            .iter_mut()
+            .find(|slot| slot.id == id)
+            .ok_or(UsbError::TransferError)
+    }
+
+    fn ring(&mut self, slot: u8, endpoint: u8) -> Result<&mut Ring, UsbError> {
+        self.slot(slot)?
+            .rings
+            .iter_mut()
+            .find(|(index, _)| *index == endpoint)
+            .map(|(_, ring)| ring)
+            .ok_or(UsbError::TransferError)
+    }
+
+    /// Clear the input context, and fill in its slot context.
+    fn prepare_input(&self, add: u32, speed: Speed, port: u8, last_endpoint: u8) {
+        unsafe {
+            core::ptr::write_bytes(self.input.ptr::<u8>(0), 0, PAGE_4K);
+
+            // Input control context
+            self.input.ptr::<u32>(4).write_volatile(add);
+
+            // Slot context
+            let slot = self.input.ptr::<u32>(self.context_size);
+            slot.write_volatile((last_endpoint as u32) << 27 | speed_id(speed) << 20);
+            slot.add(1).write_volatile((port as u32) << 16);
+        }
+    }
+
+    /// Fill in the input context of an endpoint.
+    fn prepare_endpoint(
+        &self,
+        endpoint: u8,
+        kind: u32,
+        max_packet: u16,
+        dequeue: u64,
+        average_len: u32,
+    ) {
+        unsafe {
+            let context = self
+                .input
+                .ptr::<u32>((endpoint as usize + 1) * self.context_size);
+
+            context
+                .add(1)
+                .write_volatile(ENDPOINT_THREE_RETRIES | kind << 3 | (max_packet as u32) << 16);
+            context.add(2).write_volatile(dequeue as u32);
+            context.add(3).write_volatile((dequeue >> 32) as u32);
+            context.add(4).write_volatile(average_len);
+        }
+    }
+
+    /// Give the device on `port` a slot and an address.
+    fn address_device(&mut self, port: u8, speed: Speed) -> Result<UsbDevice, UsbError> {
+        let id = self.command(Trb::new(TRB_ENABLE_SLOT, 0, 0, 0))?.slot();
+
+        let context = dma_page(self.addressing_64).ok_or(UsbError::NoMemory)?;
+        let ring = Ring::new(dma_page(self.addressing_64).ok_or(UsbError::NoMemory)?);
+        unsafe {
+            self.dcbaa
+                .ptr::<u64>(id as usize * size_of::<u64>())
+                .write_volatile(context.phys(0).addr() as u64);
+        }
+
+        // We can only be sure of the default size until we read the device descriptor
+        let max_packet = match speed {
+            Speed::Low | Speed::Full => 8,
+            Speed::High => 64,
+            Speed::Super => 512,
+        };
+
+        self.prepare_input(
+            ADD_SLOT_CONTEXT | 1 << CONTROL_ENDPOINT,
+            speed,
+            port,
+            CONTROL_ENDPOINT,
+        );
+        self.prepare_endpoint(
+            CONTROL_ENDPOINT,
+            ENDPOINT_CONTROL,
+            max_packet,
+            ring.dequeue_pointer(),
+            CONTROL_AVERAGE_LEN,
+        );
+
+        self.slots.push(Slot {
+            id,
+            port,
+            speed,
+            _context: context,
+            rings: alloc::vec![(CONTROL_ENDPOINT, ring)],
+        });
+
+        let input = self.input.phys(0).addr() as u64;
+        self.command(Trb::for_endpoint(TRB_ADDRESS_DEVICE, input, id, 0))?;
+
+        let mut device = UsbDevice {
+            address: id,
+            speed,
+            max_packet,
+        };
+
+        let real_max_packet = read_max_packet(self, &device)?;
+        if real_max_packet != max_packet {
+            self.prepare_input(1 << CONTROL_ENDPOINT, speed, port, CONTROL_ENDPOINT);
+            self.prepare_endpoint(
+                CONTROL_ENDPOINT,
+                ENDPOINT_CONTROL,
+                real_max_packet,
+                0,
+                CONTROL_AVERAGE_LEN,
+            );
+            self.command(Trb::for_endpoint(TRB_EVALUATE_CONTEXT, input, id, 0))?;
+
+            device.max_packet = real_max_packet;
+        }
+
+        Ok(device)
+    }
+
+    /// Give the controller transfer rings for a device's bulk endpoints.
+    fn configure_endpoints(
+        &mut self,
+        device: &UsbDevice,
+        endpoints: &[BulkEndpoint],
+    ) -> Result<(), UsbError> {
+        let addressing_64 = self.addressing_64;
+        let slot = self.slot(device.address)?;
+        let (port, speed) = (slot.port, slot.speed);
+
+        for endpoint in endpoints {
+            let page = dma_page(addressing_64).ok_or(UsbError::NoMemory)?;
+            slot.rings
+                .push((endpoint_index(endpoint.address), Ring::new(page)));
+        }
+
+        let last_endpoint = endpoints
+            .iter()
+            .map(|endpoint| endpoint_index(endpoint.address))
+            .max()
+            .unwrap_or(CONTROL_ENDPOINT);
+        let add = endpoints.iter().fold(ADD_SLOT_CONTEXT, |add, endpoint| {
+            add | 1 << endpoint_index(endpoint.address)
+        });
+        self.prepare_input(add, speed, port, last_endpoint);
+
+        for endpoint in endpoints {
+            let index = endpoint_index(endpoint.address);
+            let dequeue = self.ring(device.address, index)?.dequeue_pointer();
+            let kind = if endpoint.is_in() {
+                ENDPOINT_BULK_IN
+            } else {
+                ENDPOINT_BULK_OUT
+            };
+
+            self.prepare_endpoint(index, kind, endpoint.max_packet, dequeue, BULK_AVERAGE_LEN);
+        }
+
+        let input = self.input.phys(0).addr() as u64;
+        self.command(Trb::for_endpoint(
+            TRB_CONFIGURE_ENDPOINT,
+            input,
+            device.address,
+            0,
+        ))?;
+
+        Ok(())
+    }
+
+    /// Address and configure the device on `port`, and start using it if we can.
+    ///
+    /// Returns the device and its endpoints if it is a disk, which is started once the
+    /// controller is shared.
+    fn attach(
+        &mut self,
+        port: u8,
+        speed: Speed,
+    ) -> Result<Option<(UsbDevice, StorageInterface)>, UsbError> {
+        let device = self.address_device(port, speed)?;
+        let configuration = configure(self, &device)?;
+
+        if find_boot_keyboard(&configuration).is_some() {
+            warnln!(
+                "USB device {}: Keyboards on xHCI controllers are not supported yet",
+                device.address
+            );
+        }
+
+        let Some(interface) = storage::find_mass_storage(&configuration) else {
+            return Ok(None);
+        };
+
+        self.configure_endpoints(&device, &[interface.bulk_in, interface.bulk_out])?;
+        Ok(Some((device, interface)))
+    }
+}
+
+impl ControlPipe for Xhci {
+    fn control_transfer(
+        &mut self,
+        device: &UsbDevice,
+        setup: SetupPacket,
+        data: &mut [u8],
+    ) -> Result<usize, UsbError> {
+        let len = (setup.length as usize).min(data.len());
+        let device_to_host = setup.is_device_to_host();
+
+        if len > PAGE_4K {
+            return Err(UsbError::TooLarge);
+        }
+
+        if !device_to_host {
+            unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), self.buffer.ptr::<u8>(0), len) };
+        }
+
+        let buffer = self.buffer.phys(0).addr() as u64;
+        let (transfer_type, data_direction) = match (len, device_to_host) {
+            (0, _) => (SETUP_NO_DATA, 0),
+            (_, true) => (SETUP_IN, TRB_DIRECTION_IN),
+            (_, false) => (SETUP_OUT, 0),
+        };
+        // The status stage goes the other way to the data, and is an IN without data
+        let status_direction = if data_direction == TRB_DIRECTION_IN {
+            0
+        } else {
+            TRB_DIRECTION_IN
+        };
+
+        let ring = self.ring(device.address, CONTROL_ENDPOINT)?;
+        ring.push(Trb::new(
+            TRB_SETUP,
+            u64::from_le_bytes(setup.to_bytes()),
+            8,
+            TRB_IMMEDIATE_DATA | transfer_type,
+        ));
+        let data_pointer = (len != 0).then(|| {
+            ring.push(Trb::new(
+                TRB_DATA,
+                buffer,
+                len as u32,
+                TRB_INTERRUPT_ON_SHORT | data_direction,
+            ))
+        });
+        let status_pointer = ring.push(Trb::new(TRB_STATUS, 0, 0, TRB_IOC | status_direction));
+
+        self.ring_doorbell(device.address, CONTROL_ENDPOINT);
+
+        // Short packets get an event of their own before the status stage finishes
+        let mut transferred = len;
+        loop {
+            let event = self.wait_for_transfer(device.address, CONTROL_ENDPOINT)?;
+
+            if Some(event.parameter) == data_pointer {
+                transferred = len - event.residual().min(len);
+            }
+
+            if event.parameter == status_pointer {
+                break;
+            }
+        }
+
+        if device_to_host {
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    self.buffer.ptr::<u8>(0) as *const u8,
+                    data.as_mut_ptr(),
+                    transferred,
+                )
+            };
+        }
+
+        Ok(transferred)
+    }
+}
+
+impl BulkPipe for Xhci {
+    fn bulk_transfer(
+        &mut self,
+        device: &UsbDevice,
+        endpoint: u8,
+        data: &mut [u8],
+    ) -> Result<usize, UsbError> {
+        let len = data.len();
+        let device_to_host = endpoint & 0x80 != 0;
+        let index = endpoint_index(endpoint);
+
+        if len > PAGE_4K {
+            return Err(UsbError::TooLarge);
+        }
+
+        if !device_to_host {
+            unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), self.buffer.ptr::<u8>(0), len) };
+        }
+
+        let buffer = self.buffer.phys(0).addr() as u64;
+        self.ring(device.address, index)?.push(Trb::new(
+            TRB_NORMAL,
+            buffer,
+            len as u32,
+            TRB_IOC | TRB_INTERRUPT_ON_SHORT,
+        ));
+        self.ring_doorbell(device.address, index);
+
+        let event = self.wait_for_transfer(device.address, index)?;
+        let transferred = len - event.residual().min(len);
+
+        if device_to_host {
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    self.buffer.ptr::<u8>(0) as *const u8,
+                    data.as_mut_ptr(),
+                    transferred,
+                )
+            };
+        }
+
+        Ok(transferred)
+    }
+}
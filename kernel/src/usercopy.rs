@@ -0,0 +1,404 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::{string::String, vec, vec::Vec};
+use core::{
+    arch::naked_asm,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use arch::{
+    idt64::InterruptInfo,
+    registers::cr4,
+    supports::{CpuFeature, does_cpu_support},
+    user_access,
+};
+use lignan::logln;
+use mem::{
+    addr::VirtAddr,
+    paging::VmPermissions,
+    vm::{CheckAddrResult, VmRegion},
+};
+use memory_layout::USER_SPACE;
+
+use crate::process::scheduler::Scheduler;
+
+/// Set once SMAP has been enabled, `stac`/`clac` are only valid after this point.
+static SMAP_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enable the ring3 protections this cpu supports (SMEP, SMAP, and UMIP).
+pub fn init_protections() {
+    if does_cpu_support(CpuFeature::SupportsSmep) {
+        unsafe { cr4::set_supervisor_exe_protection_flag(true) };
+        logln!("Enabled SMEP");
+    }
+
+    if does_cpu_support(CpuFeature::SupportsSmap) {
+        unsafe { cr4::set_supervisor_access_prevention_flag(true) };
+        unsafe { user_access::deny_user_access() };
+        SMAP_ENABLED.store(true, Ordering::Release);
+        logln!("Enabled SMAP");
+    }
+
+    if does_cpu_support(CpuFeature::SupportsUmip) {
+        unsafe { cr4::set_user_mode_instruction_prevention_flag(true) };
+        logln!("Enabled UMIP");
+    }
+}
+
+/// Is SMAP enabled on this cpu?
+pub fn is_smap_enabled() -> bool {
+    SMAP_ENABLED.load(Ordering::Acquire)
+}
+
+/// Allows the kernel to access user pages while held.
+///
+/// Guards can be nested, only the outer most guard will deny access again once dropped.
+struct UserAccessGuard {
+    was_allowed: bool,
+}
+
+impl UserAccessGuard {
+    pub fn new() -> Self {
+        let was_allowed = !is_smap_enabled() || user_access::is_user_access_allowed();
+
+        if !was_allowed {
+            unsafe { user_access::allow_user_access() };
+        }
+
+        Self { was_allowed }
+    }
+}
+
+impl Drop for UserAccessGuard {
+    fn drop(&mut self) {
+        if !self.was_allowed {
+            unsafe { user_access::deny_user_access() };
+        }
+    }
+}
+
+/// Deny user access before switching away from the current task, returning if access was allowed.
+pub fn suspend_user_access() -> bool {
+    if !is_smap_enabled() || !user_access::is_user_access_allowed() {
+        return false;
+    }
+
+    unsafe { user_access::deny_user_access() };
+    true
+}
+
+/// Allow user access again after switching back into a task that had it allowed.
+pub fn resume_user_access() {
+    if is_smap_enabled() {
+        unsafe { user_access::allow_user_access() };
+    }
+}
+
+/// Possible errors from copying across the user boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserCopyError {
+    /// The user range was null, wrapped, or was not within userspace
+    BadAddress,
+    /// The copy faulted on a page the user doesn't have mapped
+    Fault { copied: usize },
+    /// Part of the range is not mapped in the process
+    NotMapped,
+    /// The range is mapped, but without the needed permissions
+    NoAccess,
+    /// The range is longer than this buffer is allowed to be
+    TooLong { len: usize, max: usize },
+    /// The string was not valid UTF-8
+    InvalidUtf8,
+}
+
+impl core::fmt::Display for UserCopyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            UserCopyError::BadAddress => write!(f, "address is not within userspace"),
+            UserCopyError::Fault { copied } => {
+                write!(f, "faulted on user memory after copying {copied} bytes")
+            }
+            UserCopyError::NotMapped => write!(f, "address is not mapped"),
+            UserCopyError::NoAccess => write!(f, "address is mapped without access"),
+            UserCopyError::TooLong { len, max } => {
+                write!(
+                    f,
+                    "buffer of {len} bytes is longer than the max of {max} bytes"
+                )
+            }
+            UserCopyError::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+        }
+    }
+}
+
+impl core::error::Error for UserCopyError {}
+
+/// Check that `len` bytes at `addr` are entirely within userspace
+pub fn is_user_range(addr: usize, len: usize) -> bool {
+    addr != 0 && USER_SPACE.contains_range(addr, len)
+}
+
+/// Copy `dst.len()` bytes from the user ptr `src` into `dst`.
+pub fn copy_from_user(dst: &mut [u8], src: *const u8) -> Result<(), UserCopyError> {
+    if dst.len() == 0 {
+        return Ok(());
+    }
+
+    if !is_user_range(src.addr(), dst.len()) {
+        return Err(UserCopyError::BadAddress);
+    }
+
+    let remaining = {
+        let _user_access = UserAccessGuard::new();
+        unsafe { user_copy_raw(dst.as_mut_ptr(), src, dst.len()) }
+    };
+
+    match remaining {
+        0 => Ok(()),
+        remaining => Err(UserCopyError::Fault {
+            copied: dst.len() - remaining,
+        }),
+    }
+}
+
+/// Copy all of `src` into the user ptr `dst`.
+pub fn copy_to_user(dst: *mut u8, src: &[u8]) -> Result<(), UserCopyError> {
+    if src.len() == 0 {
+        return Ok(());
+    }
+
+    if !is_user_range(dst.addr(), src.len()) {
+        return Err(UserCopyError::BadAddress);
+    }
+
+    let remaining = {
+        let _user_access = UserAccessGuard::new();
+        unsafe { user_copy_raw(dst, src.as_ptr(), src.len()) }
+    };
+
+    match remaining {
+        0 => Ok(()),
+        remaining => Err(UserCopyError::Fault {
+            copied: src.len() - remaining,
+        }),
+    }
+}
+
+/// Check that `len` bytes at `addr` are in userspace, and mapped in the current process with at
+/// least `perms`.
+///
+/// This only checks the range at this instant, another thread could still unmap it before the
+/// copy, so copies must still go through [`copy_from_user`] or [`copy_to_user`].
+pub fn check_user_access(
+    addr: usize,
+    len: usize,
+    perms: VmPermissions,
+) -> Result<(), UserCopyError> {
+    if len == 0 {
+        return Ok(());
+    }
+
+    if !is_user_range(addr, len) {
+        return Err(UserCopyError::BadAddress);
+    }
+
+    let current_thread = Scheduler::get()
+        .current_thread()
+        .upgrade()
+        .ok_or(UserCopyError::NotMapped)?;
+    let region = VmRegion::from_containing(VirtAddr::new(addr), VirtAddr::new(addr + len - 1));
+
+    match current_thread.process.check_region_perms(&region, perms) {
+        CheckAddrResult::MappedAndValidPerms => Ok(()),
+        CheckAddrResult::NotMapped => Err(UserCopyError::NotMapped),
+        CheckAddrResult::MappedInvalidPerms { .. } => Err(UserCopyError::NoAccess),
+    }
+}
+
+/// A pointer to a single `T` in user memory
+#[derive(Debug)]
+pub struct UserPtr<T> {
+    ptr: *mut T,
+}
+
+impl<T> UserPtr<T> {
+    pub const fn new(ptr: *mut T) -> Self {
+        Self { ptr }
+    }
+
+    /// The address this ptr points to
+    pub fn addr(&self) -> usize {
+        self.ptr.addr()
+    }
+
+    /// Check that the user can read this `T`
+    pub fn check_readable(&self) -> Result<(), UserCopyError> {
+        if !self.ptr.is_aligned() {
+            return Err(UserCopyError::BadAddress);
+        }
+
+        check_user_access(self.addr(), size_of::<T>(), VmPermissions::USER_R)
+    }
+}
+
+/// A range of bytes in user memory
+#[derive(Debug, Clone, Copy)]
+pub struct UserSlice {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl UserSlice {
+    /// The most bytes a string (like an endpoint name) is allowed to be
+    pub const MAX_STR_LEN: usize = 4096;
+    /// The most bytes a single transfer is allowed to be
+    pub const MAX_TRANSFER_LEN: usize = 1024 * 1024;
+
+    /// A read only range of user memory
+    pub const fn new(ptr: *const u8, len: usize) -> Self {
+        Self {
+            ptr: ptr.cast_mut(),
+            len,
+        }
+    }
+
+    /// A writable range of user memory
+    pub const fn new_mut(ptr: *mut u8, len: usize) -> Self {
+        Self { ptr, len }
+    }
+
+    /// The user's address of this slice
+    pub fn addr(&self) -> usize {
+        self.ptr.addr()
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Fail if this slice is longer than `max` bytes
+    pub const fn with_max_len(self, max: usize) -> Result<Self, UserCopyError> {
+        if self.len > max {
+            return Err(UserCopyError::TooLong { len: self.len, max });
+        }
+
+        Ok(self)
+    }
+
+    /// Shorten this slice to at most `len` bytes
+    pub const fn truncate(mut self, len: usize) -> Self {
+        if len < self.len {
+            self.len = len;
+        }
+
+        self
+    }
+
+    /// Check that the user can read this entire slice
+    pub fn check_readable(&self) -> Result<(), UserCopyError> {
+        check_user_access(self.addr(), self.len, VmPermissions::USER_R)
+    }
+
+    /// Check that the user can write this entire slice
+    pub fn check_writable(&self) -> Result<(), UserCopyError> {
+        check_user_access(self.addr(), self.len, VmPermissions::USER_RW)
+    }
+
+    /// Copy this slice out of user memory
+    pub fn read_to_vec(&self) -> Result<Vec<u8>, UserCopyError> {
+        self.check_readable()?;
+
+        let mut bytes = vec![0; self.len];
+        copy_from_user(&mut bytes, self.ptr)?;
+
+        Ok(bytes)
+    }
+
+    /// Copy this slice out of user memory as a UTF-8 string
+    pub fn read_to_string(&self) -> Result<String, UserCopyError> {
+        String::from_utf8(self.read_to_vec()?).map_err(|_| UserCopyError::InvalidUtf8)
+    }
+
+    /// Copy all of `src` into the start of this slice
+    pub fn write_from(&self, src: &[u8]) -> Result<(), UserCopyError> {
+        if src.len() > self.len {
+            return Err(UserCopyError::TooLong {
+                len: src.len(),
+                max: self.len,
+            });
+        }
+
+        check_user_access(self.addr(), src.len(), VmPermissions::USER_RW)?;
+        copy_to_user(self.ptr, src)
+    }
+}
+
+/// If this fault happened inside of a user copy, resume at the copy's fixup so it can return
+/// the fault to its caller.
+///
+/// Returns `true` if the fault was fixed up.
+pub fn fixup_user_copy(args: &InterruptInfo) -> bool {
+    let copy_ip = &raw const vera_user_copy_ip as u64;
+    let fixup_ip = &raw const vera_user_copy_fixup as u64;
+
+    if args.context.rip != copy_ip {
+        return false;
+    }
+
+    unsafe { args.redirect_return(fixup_ip) };
+    true
+}
+
+unsafe extern "C" {
+    static vera_user_copy_ip: u8;
+    static vera_user_copy_fixup: u8;
+}
+
+/// Copy `len` bytes from `src` to `dst`, returning the number of bytes that were not copied.
+///
+/// The only instruction that can touch user memory is the `rep movsb` at `vera_user_copy_ip`,
+/// if it faults (and the page fault handler cannot map the page) the exception handler will
+/// resume at `vera_user_copy_fixup` with `rcx` still holding the bytes left to copy.
+#[unsafe(naked)]
+unsafe extern "C" fn user_copy_raw(dst: *mut u8, src: *const u8, len: usize) -> usize {
+    naked_asm!(
+        r#"
+        mov rcx, rdx
+
+        .global vera_user_copy_ip
+    vera_user_copy_ip:
+        rep movsb
+
+        xor eax, eax
+        ret
+
+        .global vera_user_copy_fixup
+    vera_user_copy_fixup:
+        mov rax, rcx
+        ret
+        "#
+    )
+}
@@ -0,0 +1,387 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::{
+    pci::{Bar, PciDevice},
+    resources::{self, ClaimError, Resource, Sharing},
+    vmm::{DmaPage, map_mmio},
+};
+use core::sync::atomic::{Ordering, fence};
+use mem::{
+    addr::{PhysAddr, VirtAddr},
+    paging::CacheMode,
+};
+
+#[cfg(feature = "virtio-balloon")]
+pub mod balloon;
+#[cfg(feature = "virtio-gpu")]
+pub mod gpu;
+
+/// The PCI vendor of every virtio device, and the device ids of modern (virtio 1.0) devices
+const PCI_VENDOR_VIRTIO: u16 = 0x1AF4;
+const PCI_DEVICE_MODERN_BASE: u16 = 0x1040;
+
+/// The vendor specific PCI capability, virtio uses it to say where its registers are
+const CAPABILITY_VENDOR: u8 = 0x09;
+
+/// `cfg_type` of each virtio PCI capability
+const CFG_COMMON: u8 = 1;
+const CFG_NOTIFY: u8 = 2;
+const CFG_DEVICE: u8 = 4;
+
+/// Common configuration register offsets
+const DEVICE_FEATURE_SELECT: usize = 0x00;
+const DEVICE_FEATURE: usize = 0x04;
+const DRIVER_FEATURE_SELECT: usize = 0x08;
+const DRIVER_FEATURE: usize = 0x0C;
+const NUM_QUEUES: usize = 0x12;
+const DEVICE_STATUS: usize = 0x14;
+const QUEUE_SELECT: usize = 0x16;
+const QUEUE_SIZE: usize = 0x18;
+const QUEUE_ENABLE: usize = 0x1C;
+const QUEUE_NOTIFY_OFF: usize = 0x1E;
+const QUEUE_DESC: usize = 0x20;
+const QUEUE_DRIVER: usize = 0x28;
+const QUEUE_DEVICE: usize = 0x30;
+
+/// Device status bits
+const STATUS_ACKNOWLEDGE: u8 = 1;
+const STATUS_DRIVER: u8 = 2;
+const STATUS_DRIVER_OK: u8 = 4;
+const STATUS_FEATURES_OK: u8 = 8;
+const STATUS_FAILED: u8 = 128;
+
+/// Set by devices that follow the virtio 1.0 spec instead of the legacy interface
+pub const FEATURE_VERSION_1: u64 = 1 << 32;
+
+/// Descriptor flags
+const DESC_NEXT: u16 = 1;
+const DESC_WRITE: u16 = 2;
+
+/// How many descriptors each of our queues has, every part of the queue fits in one page
+/// with this many
+const RING_SIZE: u16 = 16;
+/// Where each part of a queue is within its page
+const RING_AVAIL_OFFSET: usize = 0x100;
+const RING_USED_OFFSET: usize = 0x200;
+
+/// How many times a queue is polled before we give up on the device
+const POLL_LIMIT: usize = 10_000_000;
+
+#[derive(Debug)]
+pub enum VirtioError {
+    Claim(ClaimError),
+    /// The device did not say where one of its registers are
+    MissingCapability,
+    MapRegisters,
+    NoMemory,
+    /// The device does not support the features we need
+    FeaturesRejected,
+    /// The device does not have the queue we asked for
+    NoSuchQueue,
+    /// A request needs more descriptors than a queue has
+    TooManyBuffers,
+    /// The device did not finish a request in time
+    Timeout,
+    /// An earlier request timed out, so the device could still be using the queue
+    Broken,
+}
+
+/// A descriptor, pointing at one buffer of a request
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+/// Registers the device exposes through one of its BARs
+#[derive(Clone, Copy)]
+struct Registers(VirtAddr);
+
+impl Registers {
+    /// Find the capability of kind `cfg_type` and map the registers it points to.
+    fn map(device: &PciDevice, owner: &str, cfg_type: u8) -> Result<(Self, u8), VirtioError> {
+        let offset = device
+            .capabilities()
+            .filter(|&(id, _)| id == CAPABILITY_VENDOR)
+            .map(|(_, offset)| offset)
+            .find(|&offset| device.read_u8(offset + 3) == cfg_type)
+            .ok_or(VirtioError::MissingCapability)?;
+
+        let Some(Bar::Memory(bar)) = device.bar(device.read_u8(offset + 4)) else {
+            return Err(VirtioError::MissingCapability);
+        };
+        let base = PhysAddr::new(bar as usize + device.read_u32(offset + 8) as usize);
+        let len = device.read_u32(offset + 12) as usize;
+
+        resources::claim(owner, &[(Resource::Mmio { base, len }, Sharing::Exclusive)])
+            .map_err(VirtioError::Claim)?;
+
+        let registers =
+            map_mmio(base, len, CacheMode::Uncached).map_err(|_| VirtioError::MapRegisters)?;
+        Ok((Self(registers), offset))
+    }
+
+    fn ptr<T>(&self, offset: usize) -> *mut T {
+        self.0.offset(offset).as_mut_ptr()
+    }
+
+    fn read<T>(&self, offset: usize) -> T {
+        unsafe { self.ptr::<T>(offset).read_volatile() }
+    }
+
+    fn write<T>(&self, offset: usize, value: T) {
+        unsafe { self.ptr::<T>(offset).write_volatile(value) }
+    }
+}
+
+/// # Virtio Device
+/// A device using the modern virtio PCI transport.
+pub struct VirtioDevice {
+    common: Registers,
+    notify: Registers,
+    notify_multiplier: u32,
+    device: Registers,
+}
+
+impl VirtioDevice {
+    /// Is `device` the modern virtio device with virtio id `id`
+    pub fn is(device: &PciDevice, id: u16) -> bool {
+        device.vendor_id == PCI_VENDOR_VIRTIO && device.device_id == PCI_DEVICE_MODERN_BASE + id
+    }
+
+    /// Map the registers of `device`, claiming them for `owner`, and reset it.
+    pub fn new(device: &PciDevice, owner: &str) -> Result<Self, VirtioError> {
+        device.enable();
+
+        let (common, _) = Registers::map(device, owner, CFG_COMMON)?;
+        let (notify, notify_cap) = Registers::map(device, owner, CFG_NOTIFY)?;
+        let (device_registers, _) = Registers::map(device, owner, CFG_DEVICE)?;
+
+        let virtio = Self {
+            common,
+            notify,
+            notify_multiplier: device.read_u32(notify_cap + 16),
+            device: device_registers,
+        };
+
+        // Writing 0 resets the device, which it shows by reading back 0
+        virtio.common.write::<u8>(DEVICE_STATUS, 0);
+        if !(0..POLL_LIMIT).any(|_| virtio.common.read::<u8>(DEVICE_STATUS) == 0) {
+            return Err(VirtioError::Timeout);
+        }
+
+        virtio.add_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+        Ok(virtio)
+    }
+
+    fn add_status(&self, status: u8) {
+        let current = self.common.read::<u8>(DEVICE_STATUS);
+        self.common.write::<u8>(DEVICE_STATUS, current | status);
+    }
+
+    /// Agree to use the features in `wanted` the device supports, returning them.
+    ///
+    /// Fails if the device does not support everything in `required`.
+    pub fn negotiate(&mut self, wanted: u64, required: u64) -> Result<u64, VirtioError> {
+        let required = required | FEATURE_VERSION_1;
+        let mut offered = 0;
+
+        for select in 0..2 {
+            self.common.write::<u32>(DEVICE_FEATURE_SELECT, select);
+            offered |= (self.common.read::<u32>(DEVICE_FEATURE) as u64) << (select * 32);
+        }
+
+        let features = offered & (wanted | required);
+        if features & required != required {
+            self.add_status(STATUS_FAILED);
+            return Err(VirtioError::FeaturesRejected);
+        }
+
+        for select in 0..2 {
+            self.common.write::<u32>(DRIVER_FEATURE_SELECT, select);
+            self.common
+                .write::<u32>(DRIVER_FEATURE, (features >> (select * 32)) as u32);
+        }
+
+        self.add_status(STATUS_FEATURES_OK);
+        if self.common.read::<u8>(DEVICE_STATUS) & STATUS_FEATURES_OK == 0 {
+            self.add_status(STATUS_FAILED);
+            return Err(VirtioError::FeaturesRejected);
+        }
+
+        Ok(features)
+    }
+
+    /// Set up queue number `index`, this must be done before `start`.
+    pub fn queue(&mut self, index: u16) -> Result<Virtqueue, VirtioError> {
+        if index >= self.common.read::<u16>(NUM_QUEUES) {
+            return Err(VirtioError::NoSuchQueue);
+        }
+
+        self.common.write::<u16>(QUEUE_SELECT, index);
+        let size = self.common.read::<u16>(QUEUE_SIZE).min(RING_SIZE);
+        if size == 0 {
+            return Err(VirtioError::NoSuchQueue);
+        }
+
+        let ring = DmaPage::new().ok_or(VirtioError::NoMemory)?;
+        self.common.write::<u16>(QUEUE_SIZE, size);
+        self.common
+            .write::<u64>(QUEUE_DESC, ring.phys(0).addr() as u64);
+        self.common
+            .write::<u64>(QUEUE_DRIVER, ring.phys(RING_AVAIL_OFFSET).addr() as u64);
+        self.common
+            .write::<u64>(QUEUE_DEVICE, ring.phys(RING_USED_OFFSET).addr() as u64);
+
+        let notify_offset =
+            self.common.read::<u16>(QUEUE_NOTIFY_OFF) as usize * self.notify_multiplier as usize;
+        self.common.write::<u16>(QUEUE_ENABLE, 1);
+
+        Ok(Virtqueue {
+            index,
+            size,
+            ring,
+            notify: Registers(self.notify.0.offset(notify_offset)),
+            last_used: 0,
+            broken: false,
+        })
+    }
+
+    /// Tell the device we are ready to use it.
+    pub fn start(&mut self) {
+        self.add_status(STATUS_DRIVER_OK);
+    }
+
+    /// Read the device specific config at `offset`.
+    pub fn config<T>(&self, offset: usize) -> T {
+        self.device.read(offset)
+    }
+
+    /// Write the device specific config at `offset`.
+    #[cfg_attr(not(feature = "virtio-balloon"), allow(dead_code))]
+    pub fn set_config<T>(&self, offset: usize, value: T) {
+        self.device.write(offset, value)
+    }
+}
+
+/// # Virtqueue
+/// A split virtqueue, that sends one request at a time and waits for it to finish.
+///
+/// Once a request times out the queue is broken for good, since the device could still
+/// finish that request later and would be out of step with every request after it.
+pub struct Virtqueue {
+    index: u16,
+    size: u16,
+    ring: DmaPage,
+    notify: Registers,
+    last_used: u16,
+    broken: bool,
+}
+
+impl Virtqueue {
+    /// Send one request, made of `readable` buffers for the device to read followed by
+    /// `writable` buffers for it to fill, and wait for the device to finish it.
+    ///
+    /// Returns how many bytes the device wrote. If the device does not finish in time, the
+    /// buffers must not be reused as the device could still write to them.
+    pub fn send(
+        &mut self,
+        readable: &[(PhysAddr, u32)],
+        writable: &[(PhysAddr, u32)],
+    ) -> Result<u32, VirtioError> {
+        if self.broken {
+            return Err(VirtioError::Broken);
+        }
+
+        let count = readable.len() + writable.len();
+        if count == 0 || count > self.size as usize {
+            return Err(VirtioError::TooManyBuffers);
+        }
+
+        // Only one request is in flight, so it always starts at the first descriptor
+        let buffers = readable
+            .iter()
+            .map(|&buffer| (buffer, 0))
+            .chain(writable.iter().map(|&buffer| (buffer, DESC_WRITE)));
+        for (index, ((addr, len), flags)) in buffers.enumerate() {
+            let next = index + 1 < count;
+            let descriptor = Descriptor {
+                addr: addr.addr() as u64,
+                len,
+                flags: flags | if next { DESC_NEXT } else { 0 },
+                next: if next { index as u16 + 1 } else { 0 },
+            };
+
+            unsafe {
+                self.ring
+                    .ptr::<Descriptor>(0)
+                    .add(index)
+                    .write_volatile(descriptor)
+            };
+        }
+
+        // The available ring is `flags, idx, ring[size]`
+        let avail_idx = self.ring.ptr::<u16>(RING_AVAIL_OFFSET + 2);
+        unsafe {
+            let idx = avail_idx.read_volatile();
+            self.ring
+                .ptr::<u16>(RING_AVAIL_OFFSET + 4)
+                .add((idx % self.size) as usize)
+                .write_volatile(0);
+
+            fence(Ordering::SeqCst);
+            avail_idx.write_volatile(idx.wrapping_add(1));
+            fence(Ordering::SeqCst);
+        }
+        self.notify.write::<u16>(0, self.index);
+
+        // The used ring is `flags, idx, ring[size] { id: u32, len: u32 }`
+        let used_idx = self.ring.ptr::<u16>(RING_USED_OFFSET + 2);
+        if !(0..POLL_LIMIT).any(|_| unsafe { used_idx.read_volatile() } != self.last_used) {
+            self.broken = true;
+            return Err(VirtioError::Timeout);
+        }
+        fence(Ordering::SeqCst);
+
+        let written = unsafe {
+            self.ring
+                .ptr::<u32>(RING_USED_OFFSET + 4)
+                .add((self.last_used % self.size) as usize * 2 + 1)
+                .read_volatile()
+        };
+        self.last_used = self.last_used.wrapping_add(1);
+
+        Ok(written)
+    }
+
+    /// Did a request time out, so nothing more can be sent on this queue
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+}
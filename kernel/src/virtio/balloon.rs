@@ -0,0 +1,295 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+//! A driver for virtio-balloon, so the host can take back memory we are not using.
+//!
+//! The host says how many pages it would like the balloon to hold. Inflating takes free
+//! pages from the physical memory manager and hands them to the host, deflating tells the
+//! host we want pages back and frees them again. Requests are polled rather than waiting for
+//! the device's interrupt, so the balloon has its own kernel thread that checks the host's
+//! target every so often, moving at most one request's worth of pages each time.
+
+use super::{VirtioDevice, VirtioError, Virtqueue};
+use crate::{
+    locks::ScheduleLock,
+    pci::{self, PciDevice},
+    process::{scheduler::Scheduler, thread::Thread},
+    resources,
+    timer::kernel_ticks,
+    vmm::DmaPage,
+};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lignan::{logln, warnln};
+use mem::{page::PhysPage, pmm::use_pmm_mut};
+use util::{bytes::HumanBytes, consts::PAGE_4K};
+
+/// The virtio device id of memory balloons
+const DEVICE_ID: u16 = 5;
+/// The queues pages are given to, and taken back from, the host on
+const INFLATE_QUEUE: u16 = 0;
+const DEFLATE_QUEUE: u16 = 1;
+
+/// Device config offsets
+const CONFIG_NUM_PAGES: usize = 0x00;
+const CONFIG_ACTUAL: usize = 0x04;
+
+/// The host must be told before we use pages taken out of the balloon
+const FEATURE_MUST_TELL_HOST: u64 = 1 << 0;
+/// We may take pages out of the balloon when we run low on memory
+const FEATURE_DEFLATE_ON_OOM: u64 = 1 << 2;
+
+/// The most pages sent in one request, their page numbers fill a quarter of the pfn page
+const PFNS_PER_REQUEST: usize = 256;
+/// How often, in ticks, the host's target is checked
+const CHECK_TICKS: u64 = 500;
+/// The balloon never takes memory that would leave fewer free pages than this
+const RESERVED_PAGES: usize = (16 * 1024 * 1024) / PAGE_4K;
+
+/// The balloon, if one was found and its thread is not using it
+static BALLOON: ScheduleLock<Option<VirtioBalloon>> = ScheduleLock::new(None);
+/// How many pages the host has, and how many it asked for, kept outside of `BALLOON` so
+/// statistics don't have to wait for a request to finish
+static BALLOON_PAGES: AtomicUsize = AtomicUsize::new(0);
+static BALLOON_TARGET: AtomicUsize = AtomicUsize::new(0);
+
+struct VirtioBalloon {
+    device: PciDevice,
+    virtio: VirtioDevice,
+    inflate: Virtqueue,
+    deflate: Virtqueue,
+    /// Holds the page numbers of the request being sent
+    pfns: DmaPage,
+    /// The pages the host has now
+    pages: Vec<PhysPage>,
+    /// Pages from an inflate request that timed out. The host may still take them, so they
+    /// are never freed.
+    quarantined: Vec<PhysPage>,
+    deflate_on_oom: bool,
+}
+
+impl VirtioBalloon {
+    fn new(device: &PciDevice, owner: &str) -> Result<Self, VirtioError> {
+        let mut virtio = VirtioDevice::new(device, owner)?;
+        let features = virtio.negotiate(FEATURE_MUST_TELL_HOST | FEATURE_DEFLATE_ON_OOM, 0)?;
+        let inflate = virtio.queue(INFLATE_QUEUE)?;
+        let deflate = virtio.queue(DEFLATE_QUEUE)?;
+        virtio.start();
+
+        Ok(Self {
+            device: *device,
+            virtio,
+            inflate,
+            deflate,
+            pfns: DmaPage::new().ok_or(VirtioError::NoMemory)?,
+            pages: Vec::new(),
+            quarantined: Vec::new(),
+            deflate_on_oom: features & FEATURE_DEFLATE_ON_OOM != 0,
+        })
+    }
+
+    /// Send the page numbers of `pages` on `queue`.
+    fn send_pfns(
+        queue: &mut Virtqueue,
+        pfns: &DmaPage,
+        pages: &[PhysPage],
+    ) -> Result<(), VirtioError> {
+        for (index, page) in pages.iter().enumerate() {
+            unsafe {
+                pfns.ptr::<u32>(0)
+                    .add(index)
+                    .write_volatile(page.page() as u32)
+            };
+        }
+
+        queue
+            .send(
+                &[(pfns.phys(0), (pages.len() * size_of::<u32>()) as u32)],
+                &[],
+            )
+            .map(|_| ())
+    }
+
+    /// Give up to `count` free pages to the host, returning how many were given.
+    fn inflate(&mut self, count: usize) -> Result<usize, VirtioError> {
+        let mut pages = Vec::with_capacity(count);
+        while pages.len() < count {
+            // The device only takes 32-bit page numbers
+            match use_pmm_mut(|pmm| pmm.allocate_page()) {
+                Ok(page) if page.page() <= u32::MAX as usize => pages.push(page),
+                Ok(page) => {
+                    let _ = use_pmm_mut(|pmm| pmm.free_page(page));
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        if pages.is_empty() {
+            return Ok(0);
+        }
+
+        match Self::send_pfns(&mut self.inflate, &self.pfns, &pages) {
+            Ok(()) => (),
+            Err(VirtioError::Timeout) => {
+                self.quarantined.extend(pages);
+                return Err(VirtioError::Timeout);
+            }
+            Err(err) => {
+                for page in pages {
+                    let _ = use_pmm_mut(|pmm| pmm.free_page(page));
+                }
+                return Err(err);
+            }
+        }
+
+        let given = pages.len();
+        self.pages.extend(pages);
+        Ok(given)
+    }
+
+    /// Take up to `count` pages back from the host, returning how many were taken.
+    fn deflate(&mut self, count: usize) -> Result<usize, VirtioError> {
+        let first = self.pages.len().saturating_sub(count);
+        if first == self.pages.len() {
+            return Ok(0);
+        }
+
+        // The host is always told first, even if it didn't ask to be. If it never answers the
+        // pages stay in the balloon, as the host may not have given them back.
+        Self::send_pfns(&mut self.deflate, &self.pfns, &self.pages[first..])?;
+
+        let taken = self.pages.len() - first;
+        for page in self.pages.drain(first..) {
+            let _ = use_pmm_mut(|pmm| pmm.free_page(page));
+        }
+        Ok(taken)
+    }
+
+    /// Move the balloon one step towards the size the host would like.
+    fn update(&mut self) -> Result<(), VirtioError> {
+        let target = self.virtio.config::<u32>(CONFIG_NUM_PAGES) as usize;
+        let free_pages = use_pmm_mut(|pmm| pmm.pages_free()).unwrap_or(0);
+        BALLOON_TARGET.store(target, Ordering::Relaxed);
+
+        let moved = if self.deflate_on_oom && free_pages < RESERVED_PAGES {
+            self.deflate(PFNS_PER_REQUEST)?
+        } else if target > self.pages.len() {
+            let count = (target - self.pages.len())
+                .min(PFNS_PER_REQUEST)
+                .min(free_pages.saturating_sub(RESERVED_PAGES));
+            self.inflate(count)?
+        } else {
+            self.deflate((self.pages.len() - target).min(PFNS_PER_REQUEST))?
+        };
+
+        if moved != 0 {
+            self.virtio
+                .set_config::<u32>(CONFIG_ACTUAL, self.pages.len() as u32);
+            BALLOON_PAGES.store(self.pages.len(), Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+}
+
+/// Find and start the first virtio-balloon.
+pub fn init() {
+    let Some(device) = pci::scan()
+        .into_iter()
+        .find(|device| VirtioDevice::is(device, DEVICE_ID))
+    else {
+        return;
+    };
+
+    let owner = device.owner_name("virtio-balloon");
+    let balloon = match VirtioBalloon::new(&device, &owner) {
+        Ok(balloon) => balloon,
+        Err(err) => {
+            warnln!("virtio-balloon {device:?}: Unable to start device ({err:?})");
+            resources::release(&owner);
+            return;
+        }
+    };
+
+    logln!(
+        "virtio-balloon {device:?}: Started{}",
+        if balloon.deflate_on_oom {
+            ", deflating when memory is low"
+        } else {
+            ""
+        }
+    );
+    *BALLOON.lock() = Some(balloon);
+
+    let kernel = Scheduler::get().current_thread().upgrade().unwrap();
+    Thread::new_kernel(kernel.process.clone(), worker);
+}
+
+/// Inflate or deflate the balloon towards the host's target every so often.
+///
+/// Each request busy-waits for the device, so this runs on its own thread rather than in
+/// the scheduler's tick. It stops once the device stops answering, or when every other
+/// thread but the idle thread has exited so the system can shut down.
+fn worker() {
+    let Some(mut balloon) = BALLOON.lock().take() else {
+        return;
+    };
+
+    loop {
+        Scheduler::sleep_until(kernel_ticks() + CHECK_TICKS);
+
+        if Scheduler::get().threads_alive() <= 2 {
+            break;
+        }
+
+        match balloon.update() {
+            Ok(()) => (),
+            Err(VirtioError::Timeout | VirtioError::Broken) => {
+                warnln!(
+                    "virtio-balloon {:?}: Device stopped responding, {} are lost",
+                    balloon.device,
+                    HumanBytes::from((balloon.pages.len() + balloon.quarantined.len()) * PAGE_4K)
+                );
+                break;
+            }
+            Err(err) => warnln!(
+                "virtio-balloon {:?}: Unable to resize to {} ({err:?})",
+                balloon.device,
+                HumanBytes::from(BALLOON_TARGET.load(Ordering::Relaxed) * PAGE_4K)
+            ),
+        }
+    }
+
+    // The device may still be reading our buffers, so they are never freed
+    *BALLOON.lock() = Some(balloon);
+}
+
+/// How many pages the host has taken, and how many it would like
+pub fn pages() -> (usize, usize) {
+    (
+        BALLOON_PAGES.load(Ordering::Relaxed),
+        BALLOON_TARGET.load(Ordering::Relaxed),
+    )
+}
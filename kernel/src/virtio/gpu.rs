@@ -0,0 +1,624 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! A driver for the 2D part of virtio-gpu, so the display's resolution can be changed
+//! after boot.
+//!
+//! The screen shows a resource, an image the host keeps, whose pixels are copied from
+//! memory we attach to it. We draw into that memory, then ask the host to copy the changed
+//! area into the resource (`transfer`) and redraw it (`flush`).
+
+use super::{VirtioDevice, VirtioError, Virtqueue};
+use crate::{
+    locks::ScheduleLock,
+    pci::{self, PciDevice},
+    resources,
+    vmm::{DmaPage, allocate_contiguous, map_pages},
+};
+use alloc::vec::Vec;
+use lignan::{logln, warnln};
+use mem::{
+    addr::{PhysAddr, VirtAddr},
+    page::PhysPage,
+    paging::CacheMode,
+    pmm::use_pmm_mut,
+};
+use util::consts::PAGE_4K;
+
+/// The virtio device id of GPUs
+const DEVICE_ID: u16 = 16;
+/// The queue commands are sent on
+const CONTROL_QUEUE: u16 = 0;
+
+/// Device config offsets
+const CONFIG_NUM_SCANOUTS: usize = 0x08;
+
+/// Command types
+const CMD_GET_DISPLAY_INFO: u32 = 0x0100;
+const CMD_RESOURCE_CREATE_2D: u32 = 0x0101;
+const CMD_RESOURCE_UNREF: u32 = 0x0102;
+const CMD_SET_SCANOUT: u32 = 0x0103;
+const CMD_RESOURCE_FLUSH: u32 = 0x0104;
+const CMD_TRANSFER_TO_HOST_2D: u32 = 0x0105;
+const CMD_RESOURCE_ATTACH_BACKING: u32 = 0x0106;
+const CMD_RESOURCE_DETACH_BACKING: u32 = 0x0107;
+
+/// Response types
+const RESP_OK_NODATA: u32 = 0x1100;
+const RESP_OK_DISPLAY_INFO: u32 = 0x1101;
+
+/// Blue, green, red then an unused byte, which is how the VBE framebuffers we get are laid
+/// out too
+const FORMAT_B8G8R8X8_UNORM: u32 = 2;
+const BYTES_PER_PIXEL: u32 = 4;
+
+/// The most scanouts a device can have
+const MAX_SCANOUTS: usize = 16;
+
+/// The largest resolution we can switch to. Memory for it is set aside when the device is
+/// found, as new kernel mappings are not seen by processes that already exist.
+pub const MAX_WIDTH: u32 = 1920;
+pub const MAX_HEIGHT: u32 = 1200;
+const BACKING_PAGES: usize = (MAX_WIDTH * MAX_HEIGHT * BYTES_PER_PIXEL) as usize / PAGE_4K;
+
+/// Backing memory is described by a list of `(addr, len)` entries, this many fit in a page
+const ENTRY_LEN: usize = 16;
+const ENTRIES_PER_PAGE: usize = PAGE_4K / ENTRY_LEN;
+const ENTRY_PAGES: usize = BACKING_PAGES.div_ceil(ENTRIES_PER_PAGE);
+
+/// Where requests and responses go in the command page
+const REQUEST_OFFSET: usize = 0;
+const RESPONSE_OFFSET: usize = PAGE_4K / 2;
+
+/// The GPU, if one was found
+static GPU: ScheduleLock<Option<VirtioGpu>> = ScheduleLock::new(None);
+
+#[derive(Debug)]
+pub enum GpuError {
+    /// There is no virtio-gpu
+    NoDevice,
+    Virtio(VirtioError),
+    /// The device answered a command with this error response
+    Device(u32),
+    /// The resolution is zero or larger than `MAX_WIDTH` x `MAX_HEIGHT`
+    InvalidResolution,
+    /// The area is not on the screen
+    InvalidRect,
+}
+
+impl From<VirtioError> for GpuError {
+    fn from(err: VirtioError) -> Self {
+        Self::Virtio(err)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Header {
+    kind: u32,
+    flags: u32,
+    fence_id: u64,
+    context_id: u32,
+    ring_index: u8,
+    padding: [u8; 3],
+}
+
+impl Header {
+    const fn command(kind: u32) -> Self {
+        Self {
+            kind,
+            flags: 0,
+            fence_id: 0,
+            context_id: 0,
+            ring_index: 0,
+            padding: [0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct DisplayOne {
+    rect: Rect,
+    enabled: u32,
+    flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ResourceCreate2d {
+    header: Header,
+    resource_id: u32,
+    format: u32,
+    width: u32,
+    height: u32,
+}
+
+/// `RESOURCE_UNREF` and `RESOURCE_DETACH_BACKING` only name the resource
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ResourceCommand {
+    header: Header,
+    resource_id: u32,
+    padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct AttachBacking {
+    header: Header,
+    resource_id: u32,
+    entries: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct MemEntry {
+    addr: u64,
+    len: u32,
+    padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SetScanout {
+    header: Header,
+    rect: Rect,
+    scanout_id: u32,
+    resource_id: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ResourceFlush {
+    header: Header,
+    rect: Rect,
+    resource_id: u32,
+    padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TransferToHost2d {
+    header: Header,
+    rect: Rect,
+    offset: u64,
+    resource_id: u32,
+    padding: u32,
+}
+
+/// A display attached to the GPU
+#[derive(Clone, Copy, Debug)]
+pub struct Display {
+    pub scanout: u32,
+    /// The size the host would like the display to be
+    pub preferred: Rect,
+    pub enabled: bool,
+}
+
+/// The memory the screen is drawn from after a resolution switch
+#[derive(Clone, Debug)]
+pub struct Framebuffer {
+    /// Where the kernel can draw to it
+    pub virt: VirtAddr,
+    /// The pages it is made of, in order, which are not contiguous in physical memory
+    pub pages: Vec<PhysPage>,
+    pub width: u32,
+    pub height: u32,
+    pub pitch: u32,
+}
+
+/// The resource the scanout is showing
+#[derive(Clone, Copy)]
+struct Scanout {
+    resource_id: u32,
+    width: u32,
+    height: u32,
+}
+
+struct VirtioGpu {
+    device: PciDevice,
+    control: Virtqueue,
+    /// Holds the request and response of the command being sent
+    command: DmaPage,
+    /// The list of backing pages sent with `RESOURCE_ATTACH_BACKING`
+    entries: Vec<DmaPage>,
+    backing: Vec<PhysPage>,
+    framebuffer: VirtAddr,
+    scanout: Option<Scanout>,
+    next_resource_id: u32,
+}
+
+impl VirtioGpu {
+    fn new(device: &PciDevice, owner: &str) -> Result<Self, GpuError> {
+        let mut virtio = VirtioDevice::new(device, owner)?;
+        virtio.negotiate(0, 0)?;
+        let control = virtio.queue(CONTROL_QUEUE)?;
+        virtio.start();
+
+        let scanouts: u32 = virtio.config(CONFIG_NUM_SCANOUTS);
+        logln!("virtio-gpu {device:?}: {scanouts} scanouts");
+
+        let command = DmaPage::new().ok_or(VirtioError::NoMemory)?;
+        let entries = (0..ENTRY_PAGES)
+            .map(|_| DmaPage::new())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(VirtioError::NoMemory)?;
+        // A contiguous framebuffer is attached as a single entry, but any pages will do
+        let backing = match allocate_contiguous(BACKING_PAGES) {
+            Some(first) => (0..BACKING_PAGES)
+                .map(|page| PhysPage::new(first.page() + page))
+                .collect(),
+            None => (0..BACKING_PAGES)
+                .map(|_| use_pmm_mut(|pmm| pmm.allocate_page()).ok())
+                .collect::<Option<Vec<_>>>()
+                .ok_or(VirtioError::NoMemory)?,
+        };
+        let framebuffer = map_pages(&backing, CacheMode::WriteCombining)
+            .or_else(|_| map_pages(&backing, CacheMode::Uncached))
+            .map_err(|_| VirtioError::NoMemory)?;
+
+        Ok(Self {
+            device: *device,
+            control,
+            command,
+            entries,
+            backing,
+            framebuffer,
+            scanout: None,
+            next_resource_id: 1,
+        })
+    }
+
+    /// Send `request` followed by `extra` buffers, returning the response type.
+    fn send<T>(
+        &mut self,
+        request: T,
+        extra: &[(PhysAddr, u32)],
+        response_len: usize,
+    ) -> Result<u32, GpuError> {
+        // The device could still be using the command page of the request that timed out
+        if self.control.is_broken() {
+            return Err(VirtioError::Broken.into());
+        }
+
+        unsafe {
+            self.command
+                .ptr::<T>(REQUEST_OFFSET)
+                .write_volatile(request)
+        };
+
+        let mut readable = Vec::with_capacity(extra.len() + 1);
+        readable.push((self.command.phys(REQUEST_OFFSET), size_of::<T>() as u32));
+        readable.extend_from_slice(extra);
+
+        self.control.send(
+            &readable,
+            &[(self.command.phys(RESPONSE_OFFSET), response_len as u32)],
+        )?;
+
+        Ok(unsafe {
+            self.command
+                .ptr::<Header>(RESPONSE_OFFSET)
+                .read_volatile()
+                .kind
+        })
+    }
+
+    /// Send a command that has no response data.
+    fn command<T>(&mut self, request: T, extra: &[(PhysAddr, u32)]) -> Result<(), GpuError> {
+        match self.send(request, extra, size_of::<Header>())? {
+            RESP_OK_NODATA => Ok(()),
+            err => Err(GpuError::Device(err)),
+        }
+    }
+
+    fn displays(&mut self) -> Result<Vec<Display>, GpuError> {
+        let response_len = size_of::<Header>() + MAX_SCANOUTS * size_of::<DisplayOne>();
+        match self.send(Header::command(CMD_GET_DISPLAY_INFO), &[], response_len)? {
+            RESP_OK_DISPLAY_INFO => (),
+            err => return Err(GpuError::Device(err)),
+        }
+
+        let first = self
+            .command
+            .ptr::<u8>(RESPONSE_OFFSET + size_of::<Header>())
+            .cast::<DisplayOne>();
+        Ok((0..MAX_SCANOUTS)
+            .map(|scanout| {
+                let display = unsafe { first.add(scanout).read_volatile() };
+                Display {
+                    scanout: scanout as u32,
+                    preferred: display.rect,
+                    enabled: display.enabled != 0,
+                }
+            })
+            .filter(|display| display.enabled || display.preferred.width != 0)
+            .collect())
+    }
+
+    /// Attach the first `pages` backing pages to `resource_id`, merging pages that follow
+    /// on from each other into one entry.
+    fn attach_backing(&mut self, resource_id: u32, pages: usize) -> Result<(), GpuError> {
+        let mut runs: Vec<(PhysAddr, u32)> = Vec::new();
+        for page in &self.backing[..pages] {
+            match runs.last_mut() {
+                Some((start, len)) if start.addr() + *len as usize == page.addr().addr() => {
+                    *len += PAGE_4K as u32
+                }
+                _ => runs.push((page.addr(), PAGE_4K as u32)),
+            }
+        }
+
+        for (index, &(addr, len)) in runs.iter().enumerate() {
+            let entry = MemEntry {
+                addr: addr.addr() as u64,
+                len,
+                padding: 0,
+            };
+            let page = &self.entries[index / ENTRIES_PER_PAGE];
+            unsafe {
+                page.ptr::<MemEntry>((index % ENTRIES_PER_PAGE) * ENTRY_LEN)
+                    .write_volatile(entry)
+            };
+        }
+
+        let entry_buffers = runs
+            .chunks(ENTRIES_PER_PAGE)
+            .zip(&self.entries)
+            .map(|(chunk, page)| (page.phys(0), (chunk.len() * ENTRY_LEN) as u32))
+            .collect::<Vec<_>>();
+
+        self.command(
+            AttachBacking {
+                header: Header::command(CMD_RESOURCE_ATTACH_BACKING),
+                resource_id,
+                entries: runs.len() as u32,
+            },
+            &entry_buffers,
+        )
+    }
+
+    /// Drop `resource_id` and its backing.
+    fn destroy(&mut self, resource_id: u32) -> Result<(), GpuError> {
+        for kind in [CMD_RESOURCE_DETACH_BACKING, CMD_RESOURCE_UNREF] {
+            self.command(
+                ResourceCommand {
+                    header: Header::command(kind),
+                    resource_id,
+                    padding: 0,
+                },
+                &[],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn set_resolution(&mut self, width: u32, height: u32) -> Result<Framebuffer, GpuError> {
+        if width == 0 || height == 0 || width > MAX_WIDTH || height > MAX_HEIGHT {
+            return Err(GpuError::InvalidResolution);
+        }
+
+        let pitch = width * BYTES_PER_PIXEL;
+        let pages = (pitch * height).div_ceil(PAGE_4K as u32) as usize;
+        let resource_id = self.next_resource_id;
+        self.next_resource_id += 1;
+
+        self.command(
+            ResourceCreate2d {
+                header: Header::command(CMD_RESOURCE_CREATE_2D),
+                resource_id,
+                format: FORMAT_B8G8R8X8_UNORM,
+                width,
+                height,
+            },
+            &[],
+        )?;
+
+        let shown = self.attach_backing(resource_id, pages).and_then(|()| {
+            self.command(
+                SetScanout {
+                    header: Header::command(CMD_SET_SCANOUT),
+                    rect: Rect {
+                        x: 0,
+                        y: 0,
+                        width,
+                        height,
+                    },
+                    scanout_id: 0,
+                    resource_id,
+                },
+                &[],
+            )
+        });
+
+        // The old resource stays on screen if the new one could not be shown
+        if let Err(err) = shown {
+            let _ = self.destroy(resource_id);
+            return Err(err);
+        }
+
+        let old = self.scanout.replace(Scanout {
+            resource_id,
+            width,
+            height,
+        });
+        if let Some(Err(err)) = old.map(|old| self.destroy(old.resource_id)) {
+            warnln!("virtio-gpu: Unable to free the old resource ({err:?})");
+        }
+
+        Ok(Framebuffer {
+            virt: self.framebuffer,
+            pages: self.backing[..pages].to_vec(),
+            width,
+            height,
+            pitch,
+        })
+    }
+
+    fn flush(&mut self, rect: Rect) -> Result<(), GpuError> {
+        let scanout = self.scanout.ok_or(GpuError::NoDevice)?;
+        if rect.width == 0
+            || rect.height == 0
+            || rect.x.saturating_add(rect.width) > scanout.width
+            || rect.y.saturating_add(rect.height) > scanout.height
+        {
+            return Err(GpuError::InvalidRect);
+        }
+
+        let pitch = scanout.width * BYTES_PER_PIXEL;
+        self.command(
+            TransferToHost2d {
+                header: Header::command(CMD_TRANSFER_TO_HOST_2D),
+                rect,
+                offset: rect.y as u64 * pitch as u64 + (rect.x * BYTES_PER_PIXEL) as u64,
+                resource_id: scanout.resource_id,
+                padding: 0,
+            },
+            &[],
+        )?;
+
+        self.command(
+            ResourceFlush {
+                header: Header::command(CMD_RESOURCE_FLUSH),
+                rect,
+                resource_id: scanout.resource_id,
+                padding: 0,
+            },
+            &[],
+        )
+    }
+}
+
+/// Find and start the first virtio-gpu.
+pub fn init() {
+    let Some(device) = pci::scan()
+        .into_iter()
+        .find(|device| VirtioDevice::is(device, DEVICE_ID))
+    else {
+        return;
+    };
+
+    let owner = device.owner_name("virtio-gpu");
+    let mut gpu = match VirtioGpu::new(&device, &owner) {
+        Ok(gpu) => gpu,
+        Err(err) => {
+            warnln!("virtio-gpu {device:?}: Unable to start device ({err:?})");
+            resources::release(&owner);
+            return;
+        }
+    };
+
+    match gpu.displays() {
+        Ok(displays) => {
+            for display in displays {
+                logln!(
+                    "virtio-gpu {:?}: Scanout {} prefers {}x{}{}",
+                    gpu.device,
+                    display.scanout,
+                    display.preferred.width,
+                    display.preferred.height,
+                    if display.enabled { "" } else { " (disabled)" }
+                );
+            }
+        }
+        Err(err) => warnln!("virtio-gpu {device:?}: Unable to read displays ({err:?})"),
+    }
+
+    *GPU.lock() = Some(gpu);
+}
+
+/// The displays attached to the GPU, and the size the host would like each to be.
+pub fn displays() -> Result<Vec<Display>, GpuError> {
+    GPU.lock().as_mut().ok_or(GpuError::NoDevice)?.displays()
+}
+
+/// Switch the first display to `width` x `height`, returning the memory it now shows.
+///
+/// Nothing is visible until it is drawn into the returned framebuffer and `flush`ed. If the
+/// switch fails the display keeps showing what it did before.
+pub fn set_resolution(width: u32, height: u32) -> Result<Framebuffer, GpuError> {
+    GPU.lock()
+        .as_mut()
+        .ok_or(GpuError::NoDevice)?
+        .set_resolution(width, height)
+}
+
+/// Copy `rect` of the framebuffer to the screen.
+pub fn flush(rect: Rect) -> Result<(), GpuError> {
+    GPU.lock().as_mut().ok_or(GpuError::NoDevice)?.flush(rect)
+}
+
+/// Copy `rect` of the framebuffer to the display, unless the GPU is busy.
+///
+/// This is for the consoles, which draw from the log and can't wait for the GPU. Only the
+/// part of `rect` on the screen is copied.
+pub fn try_flush(rect: Rect) {
+    let Some(mut gpu) = GPU.try_lock() else {
+        return;
+    };
+
+    let Some(gpu) = gpu.as_mut() else {
+        return;
+    };
+
+    if let Some(scanout) = gpu.scanout {
+        let x = rect.x.min(scanout.width);
+        let y = rect.y.min(scanout.height);
+        let width = rect.width.min(scanout.width - x);
+        let height = rect.height.min(scanout.height - y);
+
+        if width != 0 && height != 0 {
+            let _ = gpu.flush(Rect {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+    }
+}
+
+/// Copy the whole screen to the display, unless the GPU is busy.
+///
+/// Only for when everything on screen was redrawn, see [`try_flush`].
+pub fn try_flush_all() {
+    try_flush(Rect {
+        x: 0,
+        y: 0,
+        width: u32::MAX,
+        height: u32::MAX,
+    });
+}
@@ -0,0 +1,696 @@
+/*
+  ____                 __               __ __                 __
+ / __ \__ _____ ____  / /___ ____ _    / //_/__ _______  ___ / /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / ,< / -_) __/ _ \/ -_) /
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /_/|_|\__/_/ /_//_/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::process::{Process, RefProcess, scheduler::Scheduler};
+use alloc::{collections::btree_map::BTreeMap, vec::Vec};
+use arch::{
+    critcal_section,
+    mtrr::{MemoryType, MtrrMap},
+    registers::{cr3, ia32_pat},
+    supports::{CpuFeature, does_cpu_support, physical_address_size_bits},
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lignan::{logln, warnln};
+use mem::{
+    MemoryError,
+    addr::{PhysAddr, VirtAddr},
+    page::{PhysPage, VirtPage},
+    paging::{CacheMode, PageMapping, VmOptions, VmPermissions},
+    phys::{PhysMemoryKind, PhysMemoryMap},
+    pmm::{Pmm, use_pmm_mut, use_pmm_ref},
+    vm::{InsertVmObjectError, VmObjectMappingError, VmProcess, VmRegion},
+};
+use memory_layout::{KERNEL_SPACE, MMIO_WINDOW};
+use util::{
+    bytes::HumanBytes,
+    consts::{PAGE_1G, PAGE_2M, PAGE_4K},
+};
+
+/// The next free page in the MMIO window
+static NEXT_MMIO_PAGE: AtomicUsize = AtomicUsize::new(MMIO_WINDOW.start / PAGE_4K);
+
+/// The first of the two MMIO window pages used to copy pages while compacting, or zero
+/// before the first page is moved
+static COMPACTION_SCRATCH: AtomicUsize = AtomicUsize::new(0);
+static COMPACTION_PASSES: AtomicUsize = AtomicUsize::new(0);
+static COMPACTION_FAILURES: AtomicUsize = AtomicUsize::new(0);
+static PAGES_MOVED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+pub enum MapMmioError {
+    /// Cannot map a region of zero bytes
+    InvalidLength,
+    /// The MMIO window has no more free virtual memory
+    OutOfVirtualMemory,
+    /// The cpu doesn't support the PAT, so we cannot use this cache mode
+    CacheModeNotSupported(CacheMode),
+    /// Failed to map the region
+    MappingError(InsertVmObjectError),
+}
+
+impl core::fmt::Display for MapMmioError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MapMmioError::InvalidLength => write!(f, "cannot map a zero length region"),
+            MapMmioError::OutOfVirtualMemory => write!(f, "MMIO window is full"),
+            MapMmioError::CacheModeNotSupported(mode) => {
+                write!(f, "cache mode {:?} is not supported", mode)
+            }
+            MapMmioError::MappingError(err) => write!(f, "mapping failed {:?}", err),
+        }
+    }
+}
+
+/// Read the MTRRs, turn off variable ranges without a defined meaning, and log the memory
+/// types they give.
+///
+/// Warns about RAM the MTRRs don't make write-back, since every mapping of it would end up
+/// slower than expected.
+pub fn init_mtrr<const N: usize>(phys_mem_map: &PhysMemoryMap<N>) {
+    if !does_cpu_support(CpuFeature::SupportsMtrr) {
+        logln!("CPU does not support MTRRs");
+        return;
+    }
+
+    let mut mtrrs = unsafe { MtrrMap::read(physical_address_size_bits()) };
+    if !mtrrs.enabled {
+        warnln!("MTRRs are turned off, all memory is uncacheable");
+        return;
+    }
+
+    let mut sanitized = false;
+    mtrrs.sanitize(|index, range, reason| {
+        sanitized = true;
+        warnln!(
+            "Turning off MTRR {index} ({:#014x}, type {}): {:?}",
+            range.base,
+            range.raw_type,
+            reason
+        );
+    });
+    if sanitized {
+        critcal_section! {
+            unsafe { mtrrs.write_variable_ranges() };
+        }
+    }
+
+    logln!(
+        "MTRRs: default {}, fixed ranges {}",
+        mtrrs.default_type.short_name(),
+        if mtrrs.fixed_enabled { "on" } else { "off" }
+    );
+    for (index, range) in mtrrs
+        .variable
+        .iter()
+        .enumerate()
+        .filter_map(|(index, range)| range.map(|range| (index, range)))
+    {
+        let kind = range
+            .memory_type()
+            .map(MemoryType::short_name)
+            .unwrap_or("??");
+
+        match range.size() {
+            Some(len) => logln!(
+                "  MTRR {index}: {:#014x} {} ({})",
+                range.base,
+                kind,
+                HumanBytes::from(len)
+            ),
+            None => logln!(
+                "  MTRR {index}: {:#014x} {} (mask {:#014x})",
+                range.base,
+                kind,
+                range.mask
+            ),
+        }
+    }
+
+    // Memory types only change at the edges of variable ranges
+    let edges = mtrrs
+        .variable
+        .iter()
+        .flatten()
+        .flat_map(|range| [range.base, range.base + range.size().unwrap_or(0)]);
+    for entry in phys_mem_map
+        .iter()
+        .filter(|entry| !matches!(entry.kind, PhysMemoryKind::Reserved | PhysMemoryKind::None))
+    {
+        // Low memory is covered by the fixed ranges, and is expected to hold devices
+        let start = (entry.start.addr() as u64).max(0x100000);
+        let end = entry.end.addr() as u64;
+        if start >= end {
+            continue;
+        }
+
+        if let Some(addr) = core::iter::once(start)
+            .chain(edges.clone().filter(|edge| (start..end).contains(edge)))
+            .find(|&addr| mtrrs.memory_type(addr) != MemoryType::WriteBack)
+        {
+            warnln!(
+                "{:?} memory at {addr:#014x} is {} instead of WB, it will be slow",
+                entry.kind,
+                mtrrs.memory_type(addr).short_name()
+            );
+        }
+    }
+}
+
+/// Program the PAT with [`CacheMode::PAT_LAYOUT`]
+pub fn init_pat() {
+    if !does_cpu_support(CpuFeature::SupportsPat) {
+        logln!("CPU does not support PAT, only WB and UC mappings are possible");
+        return;
+    }
+
+    unsafe { ia32_pat::write(CacheMode::PAT_LAYOUT) };
+    logln!("PAT programmed ({:#018x})", ia32_pat::read());
+}
+
+/// Map `len` bytes of physical memory at `phys` into the kernel with the given caching mode.
+///
+/// Device MMIO should use [`CacheMode::Uncached`], and framebuffers [`CacheMode::WriteCombining`].
+/// The returned address points to the same offset into the page as `phys`.
+///
+/// # Note
+/// Processes copy the kernel's page tables when created, so the mapping is also copied into
+/// every process that already exists.
+pub fn map_mmio(phys: PhysAddr, len: usize, cache: CacheMode) -> Result<VirtAddr, MapMmioError> {
+    if len == 0 {
+        return Err(MapMmioError::InvalidLength);
+    }
+
+    let page_offset = phys.addr() % PAGE_4K;
+    let phys_start: PhysPage = PhysPage::containing_addr(phys);
+    let pages = (0..(page_offset + len).div_ceil(PAGE_4K))
+        .map(|i| PhysPage::new(phys_start.page() + i))
+        .collect::<Vec<_>>();
+
+    map_pages(&pages, cache).map(|virt| virt.offset(page_offset))
+}
+
+/// Map `pages` into the kernel one after another, even if they are not contiguous in
+/// physical memory.
+///
+/// # Note
+/// Like [`map_mmio`], this is copied into every process that already exists.
+pub fn map_pages(pages: &[PhysPage], cache: CacheMode) -> Result<VirtAddr, MapMmioError> {
+    if pages.is_empty() {
+        return Err(MapMmioError::InvalidLength);
+    }
+
+    // Without the PAT only the PWT and PCD bits work, so WC would turn into UC-
+    if matches!(cache, CacheMode::WriteCombining) && !does_cpu_support(CpuFeature::SupportsPat) {
+        return Err(MapMmioError::CacheModeNotSupported(cache));
+    }
+
+    let n_pages = pages.len();
+    let start_page = NEXT_MMIO_PAGE.fetch_add(n_pages, Ordering::SeqCst);
+    if !MMIO_WINDOW.contains_range(start_page * PAGE_4K, n_pages * PAGE_4K) {
+        return Err(MapMmioError::OutOfVirtualMemory);
+    }
+
+    let region = VmRegion::new(
+        VirtPage::new(start_page),
+        VirtPage::new(start_page + n_pages - 1),
+    );
+
+    let mappings = region
+        .pages_iter()
+        .zip(pages.iter().copied())
+        .collect::<BTreeMap<_, _>>();
+
+    Scheduler::get()
+        .map_kernel_region(region, VmPermissions::SYS_RW, mappings, cache)
+        .map_err(MapMmioError::MappingError)?;
+
+    Ok(region.start.addr())
+}
+
+/// A page of physical memory mapped into the kernel, for devices to read and write with DMA.
+#[cfg_attr(
+    not(any(feature = "usb", feature = "virtio-gpu", feature = "virtio-balloon")),
+    allow(dead_code)
+)]
+pub struct DmaPage {
+    phys: PhysAddr,
+    virt: VirtAddr,
+}
+
+#[cfg_attr(
+    not(any(feature = "usb", feature = "virtio-gpu", feature = "virtio-balloon")),
+    allow(dead_code)
+)]
+impl DmaPage {
+    /// Allocate a new zeroed page.
+    pub fn new() -> Option<Self> {
+        let page = use_pmm_mut(|pmm| pmm.allocate_page()).ok()?;
+        let virt = map_mmio(page.addr(), PAGE_4K, CacheMode::Uncached).ok()?;
+        unsafe { core::ptr::write_bytes(virt.as_mut_ptr::<u8>(), 0, PAGE_4K) };
+
+        Some(Self {
+            phys: page.addr(),
+            virt,
+        })
+    }
+
+    /// The physical address of `offset` into this page, for handing to a device.
+    pub fn phys(&self, offset: usize) -> PhysAddr {
+        PhysAddr::new(self.phys.addr() + offset)
+    }
+
+    /// A pointer to `offset` into this page.
+    pub fn ptr<T>(&self, offset: usize) -> *mut T {
+        self.virt.offset(offset).as_mut_ptr()
+    }
+}
+
+/// What the compaction passes have done since boot
+#[derive(Debug, Clone, Copy)]
+pub struct CompactionStats {
+    /// Times a contiguous allocation failed and memory was compacted
+    pub passes: usize,
+    /// Passes that could not make enough room
+    pub failures: usize,
+    /// Pages of process memory moved to another physical page
+    pub pages_moved: usize,
+}
+
+impl core::fmt::Display for CompactionStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} passes, {} failed, {} pages moved",
+            self.passes, self.failures, self.pages_moved
+        )
+    }
+}
+
+/// The compaction statistics so far
+pub fn compaction_stats() -> CompactionStats {
+    CompactionStats {
+        passes: COMPACTION_PASSES.load(Ordering::Relaxed),
+        failures: COMPACTION_FAILURES.load(Ordering::Relaxed),
+        pages_moved: PAGES_MOVED.load(Ordering::Relaxed),
+    }
+}
+
+/// How much of a memory map is covered by each page size
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PageCoverage {
+    pub bytes_4k: usize,
+    pub bytes_2m: usize,
+    pub bytes_1g: usize,
+}
+
+impl PageCoverage {
+    /// Add `mapping` to the coverage
+    fn add(&mut self, mapping: &PageMapping) {
+        match mapping.size {
+            PAGE_1G => self.bytes_1g += mapping.size,
+            PAGE_2M => self.bytes_2m += mapping.size,
+            _ => self.bytes_4k += mapping.size,
+        }
+    }
+
+    /// The percent of mapped memory that is in 2Mib or 1Gib pages
+    pub fn huge_percent(&self) -> usize {
+        let total = self.bytes_4k + self.bytes_2m + self.bytes_1g;
+        if total == 0 {
+            return 0;
+        }
+
+        ((self.bytes_2m + self.bytes_1g) * 100) / total
+    }
+}
+
+impl core::fmt::Display for PageCoverage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} in 4K, {} in 2M, {} in 1G ({}% huge)",
+            HumanBytes::from(self.bytes_4k),
+            HumanBytes::from(self.bytes_2m),
+            HumanBytes::from(self.bytes_1g),
+            self.huge_percent()
+        )
+    }
+}
+
+/// How much of the kernel's memory map is in each page size
+pub fn kernel_page_coverage() -> PageCoverage {
+    let mut coverage = PageCoverage::default();
+    Scheduler::get().walk_kernel_mappings(
+        VmRegion::from_containing(
+            VirtAddr::new(KERNEL_SPACE.start),
+            VirtAddr::new(KERNEL_SPACE.last),
+        ),
+        |mapping| coverage.add(&mapping),
+    );
+
+    coverage
+}
+
+/// Allocate `count` pages that follow on from each other in physical memory, returning the
+/// first of them.
+///
+/// If free memory is too fragmented for this, process memory is moved out of the way to
+/// make a long enough run.
+#[cfg_attr(not(feature = "virtio-gpu"), allow(dead_code))]
+pub fn allocate_contiguous(count: usize) -> Option<PhysPage> {
+    match use_pmm_mut(|pmm| pmm.allocate_contiguous(count)) {
+        Ok(page) => Some(page),
+        Err(MemoryError::OutOfAllocMemory) => compact(count),
+        Err(_) => None,
+    }
+}
+
+/// Move anonymous process memory out of the way to allocate `count` contiguous pages.
+///
+/// This picks the run of pages that needs the fewest pages moved, where every page is
+/// either free or movable.
+fn compact(count: usize) -> Option<PhysPage> {
+    COMPACTION_PASSES.fetch_add(1, Ordering::Relaxed);
+
+    let mut movable: BTreeMap<usize, (RefProcess, VirtPage)> = BTreeMap::new();
+    for process in Scheduler::get().processes() {
+        process.movable_pages(|vpage, ppage| {
+            movable.insert(ppage.page(), (process.clone(), vpage));
+        });
+    }
+
+    let result = use_pmm_ref(|pmm| compaction_window(pmm, &movable, count)).and_then(|first| {
+        evacuate(first, count, &movable).map(|moved| (PhysPage::new(first), moved))
+    });
+
+    let Some((first, moved)) = result else {
+        COMPACTION_FAILURES.fetch_add(1, Ordering::Relaxed);
+        warnln!(
+            "Compaction could not make {} contiguous ({})",
+            HumanBytes::from(count * PAGE_4K),
+            compaction_stats()
+        );
+        return None;
+    };
+
+    PAGES_MOVED.fetch_add(moved, Ordering::Relaxed);
+    logln!(
+        "Compacted {} at {:#014x}, moving {moved} pages ({})",
+        HumanBytes::from(count * PAGE_4K),
+        first.addr().addr(),
+        compaction_stats()
+    );
+
+    Some(first)
+}
+
+/// Find the first page of the run of `count` pages with the fewest `movable` pages, and no
+/// pages that are allocated but not movable.
+fn compaction_window(
+    pmm: &Pmm,
+    movable: &BTreeMap<usize, (RefProcess, VirtPage)>,
+    count: usize,
+) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+
+    let mut best: Option<(usize, usize)> = None;
+    let mut window_start = pmm.managed_pages().start;
+    let mut window_moves = 0;
+
+    for page in pmm.managed_pages() {
+        let is_movable = movable.contains_key(&page);
+        if !is_movable && !pmm.is_page_free(PhysPage::new(page)) {
+            window_start = page + 1;
+            window_moves = 0;
+            continue;
+        }
+
+        window_moves += is_movable as usize;
+        if page - window_start == count {
+            window_moves -= movable.contains_key(&window_start) as usize;
+            window_start += 1;
+        }
+
+        if page + 1 - window_start == count && best.is_none_or(|(moves, _)| window_moves < moves) {
+            best = Some((window_moves, window_start));
+        }
+    }
+
+    best.map(|(_, first)| first)
+}
+
+/// Allocate the `count` pages at `first`, moving the `movable` pages within them to other
+/// physical pages, and return how many pages were moved.
+///
+/// Everything taken is given back if any page cannot be moved.
+fn evacuate(
+    first: usize,
+    count: usize,
+    movable: &BTreeMap<usize, (RefProcess, VirtPage)>,
+) -> Option<usize> {
+    let window = first..(first + count);
+    let mut taken = Vec::with_capacity(count);
+    let give_back = |taken: &[usize]| {
+        use_pmm_mut(|pmm| {
+            for &page in taken {
+                let _ = pmm.free_page(PhysPage::new(page));
+            }
+        })
+    };
+
+    // The free pages are taken first, so pages moved out cannot land back inside the window
+    for page in window.clone().filter(|page| !movable.contains_key(page)) {
+        if use_pmm_mut(|pmm| pmm.claim_page(PhysPage::new(page))).is_err() {
+            give_back(&taken);
+            return None;
+        }
+        taken.push(page);
+    }
+
+    let mut moved = 0;
+    for (&page, (process, vpage)) in movable.range(window) {
+        let Ok(new_page) = use_pmm_mut(|pmm| pmm.allocate_page()) else {
+            give_back(&taken);
+            return None;
+        };
+
+        match process.migrate_page(*vpage, new_page) {
+            Ok(old_page) if old_page.page() == page => {
+                taken.push(page);
+                moved += 1;
+            }
+            // The process remapped this page since we looked, so this window is no longer
+            // ours to take
+            Ok(old_page) => {
+                taken.push(old_page.page());
+                give_back(&taken);
+                return None;
+            }
+            Err(err) => {
+                warnln!("Could not move {vpage:x?} of '{}': {err:?}", process.name);
+                taken.push(new_page.page());
+                give_back(&taken);
+                return None;
+            }
+        }
+    }
+
+    Some(moved)
+}
+
+/// Copy the contents of `vpage` in `vm` onto `new_page`, then point `vpage` at it, returning
+/// the page that used to back it.
+///
+/// `vm` does not have to be the loaded address space.
+pub fn migrate_page(
+    vm: &VmProcess,
+    vpage: VirtPage,
+    new_page: PhysPage,
+) -> Result<PhysPage, VmObjectMappingError> {
+    // The remap only flushes this processor's TLB, by remapping with `vm` loaded and then
+    // switching back. Another processor running `vm` could keep writing to the old page
+    // after it is freed, so SMP needs a TLB shootdown here first.
+    debug_assert_eq!(
+        crate::processor::cpu_count(),
+        1,
+        "Migrating pages needs a TLB shootdown with more than one processor"
+    );
+
+    let old_page = vm
+        .page_tables
+        .read()
+        .vpage_to_ppage_lookup(vpage)
+        .map_err(|_| VmObjectMappingError::PageNotMovable(vpage))?;
+
+    let mut scratch = COMPACTION_SCRATCH.load(Ordering::SeqCst);
+    if scratch == 0 {
+        scratch = NEXT_MMIO_PAGE.fetch_add(2, Ordering::SeqCst);
+        COMPACTION_SCRATCH.store(scratch, Ordering::SeqCst);
+    }
+    let (from, to) = (VirtPage::new(scratch), VirtPage::new(scratch + 1));
+
+    // The kernel's half of each process is a copy, so the scratch pages are mapped into `vm`
+    // and `vm` is loaded for the copy. Interrupts stay off so nothing runs in the meantime.
+    critcal_section! {
+        let previous_tables = cr3::read();
+        let was_loaded = vm.page_tables.read().is_loaded();
+        if !was_loaded {
+            unsafe { vm.page_tables.read().load() }.unwrap();
+        }
+
+        let result = copy_page(vm, (from, old_page), (to, new_page))
+            .and_then(|_| vm.remap_movable_page(vpage, new_page));
+
+        if !was_loaded {
+            unsafe { cr3::write(previous_tables) };
+        }
+
+        result
+    }
+}
+
+/// Map both scratch pages in the loaded `vm`, and copy `from` onto `to`.
+fn copy_page(
+    vm: &VmProcess,
+    from: (VirtPage, PhysPage),
+    to: (VirtPage, PhysPage),
+) -> Result<(), VmObjectMappingError> {
+    for (vpage, ppage) in [from, to] {
+        vm.page_tables
+            .write()
+            .correlate_page(
+                vpage,
+                ppage,
+                VmOptions::none()
+                    .set_overwrite_flag(true)
+                    .set_increase_perm_flag(true)
+                    .set_force_permissions_on_page_flag(true),
+                VmPermissions::SYS_RW,
+            )
+            .map_err(VmObjectMappingError::MappingError)?;
+    }
+
+    unsafe {
+        core::ptr::copy_nonoverlapping(
+            from.0.addr().as_ptr::<u8>(),
+            to.0.addr().as_mut_ptr::<u8>(),
+            PAGE_4K,
+        )
+    };
+
+    Ok(())
+}
+
+/// Pages that follow on from each other, in both virtual and physical memory, with the
+/// same flags.
+struct MappingRun {
+    first: PageMapping,
+    pages: usize,
+    bytes: usize,
+}
+
+impl MappingRun {
+    /// Add `mapping` to the end of this run, if it continues it
+    fn extend(&mut self, mapping: &PageMapping) -> bool {
+        let continues = mapping.size == self.first.size
+            && mapping.virt.addr() == self.first.virt.addr() + self.bytes
+            && mapping.phys.addr() == self.first.phys.addr() + self.bytes
+            && mapping.writable == self.first.writable
+            && mapping.no_exec == self.first.no_exec
+            && mapping.user == self.first.user
+            && mapping.global == self.first.global
+            && mapping.cache == self.first.cache;
+
+        if continues {
+            self.pages += 1;
+            self.bytes += mapping.size;
+        }
+
+        continues
+    }
+}
+
+impl core::fmt::Display for MappingRun {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let page_size = match self.first.size {
+            PAGE_4K => "4K",
+            0x20_0000 => "2M",
+            _ => "1G",
+        };
+        let cache = match self.first.cache {
+            CacheMode::WriteBack => "WB",
+            CacheMode::WriteThrough => "WT",
+            CacheMode::WriteCombining => "WC",
+            CacheMode::Uncached => "UC",
+        };
+
+        write!(
+            f,
+            "{:#018x}-{:#018x} -> {:#018x} {:>10} ({} x {page_size}) {} {} {} {} {cache}",
+            self.first.virt.addr(),
+            self.first.virt.addr() + (self.bytes - 1),
+            self.first.phys.addr(),
+            HumanBytes::from(self.bytes),
+            self.pages,
+            if self.first.writable { "W " } else { "- " },
+            if self.first.no_exec { "NX" } else { "--" },
+            if self.first.user { "U" } else { "-" },
+            if self.first.global { "G" } else { "-" },
+        )
+    }
+}
+
+/// Log the pages mapped in `region` of `process`'s page tables, returning how many lines
+/// were logged.
+///
+/// Pages that follow on from each other with the same flags are logged as one line.
+pub fn dump(process: &Process, region: VmRegion) -> usize {
+    let mut runs: Vec<MappingRun> = Vec::new();
+    process.walk_mappings(region, |mapping| {
+        if runs.last_mut().is_some_and(|run| run.extend(&mapping)) {
+            return;
+        }
+
+        runs.push(MappingRun {
+            first: mapping,
+            pages: 1,
+            bytes: mapping.size,
+        });
+    });
+
+    logln!(
+        "Page tables of '{}' ({:#018x}-{:#018x}):",
+        process.name,
+        region.start.addr().addr(),
+        region.end.addr().addr() + (PAGE_4K - 1)
+    );
+    for run in runs.iter() {
+        logln!("  {run}");
+    }
+
+    runs.len()
+}
@@ -1,11 +1,18 @@
 use anyhow::{Context, Error, Result};
 use async_process::{Command, Stdio};
+use keymap::Keymap;
+use service_manifest::Manifest;
 use std::fmt::Display;
 use std::fs;
 use std::path::{Path, PathBuf};
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
 
+/// The list of services `init` starts, packed into the initfs
+const SERVICE_MANIFEST: &str = "./user/init/services.conf";
+/// The German keyboard layout, the kernel has the US layout built in
+const KEYMAP_DE: &str = "./kernel/keymaps/de.keymap";
+
 #[derive(Clone, Debug)]
 pub struct Artifacts {
     pub bootsector: PathBuf,
@@ -15,10 +22,12 @@ pub struct Artifacts {
 
     pub kernel: PathBuf,
     pub kernel_len: usize,
+    pub kernel_compressed: PathBuf,
     pub boot_cfg: PathBuf,
 
     pub initfs: PathBuf,
     pub initfs_len: usize,
+    pub initfs_compressed: PathBuf,
 }
 
 #[allow(unused)]
@@ -71,6 +80,9 @@ impl Display for ArchSelect {
     }
 }
 
+/// Kernel features that walk the stack through frame pointers
+const FRAME_POINTER_FEATURES: &[&str] = &["heap-tracking", "lock-debug", "test"];
+
 async fn cargo_helper(
     profile: Option<&str>,
     package: &str,
@@ -86,6 +98,19 @@ async fn cargo_helper(
         &["-Zbuild-std=core"]
     };
 
+    // Only the kernel's debugging features walk frame pointers, so only they pay for keeping
+    // them around
+    let rustflags = if package == "vera"
+        && feature_flags.iter().any(|flags| {
+            flags
+                .split([',', ' '])
+                .any(|feature| FRAME_POINTER_FEATURES.contains(&feature))
+        }) {
+        "-Cforce-frame-pointers=yes"
+    } else {
+        ""
+    };
+
     let feature_flags: &[&str] = if let Some(feature_flags) = feature_flags {
         &["--features", feature_flags]
     } else {
@@ -129,7 +154,7 @@ async fn cargo_helper(
 
     Command::new("cargo")
         .env_remove("RUSTFLAGS")
-        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env("CARGO_ENCODED_RUSTFLAGS", rustflags)
         .env_remove("RUSTC_WORKSPACE_WRAPPER")
         .env("CARGO_TERM_PROGRESS_WHEN", "never")
         .args(pre_build_command)
@@ -179,9 +204,38 @@ async fn convert_bin(path: &Path, arch: ArchSelect) -> Result<PathBuf> {
     Ok(bin_path)
 }
 
-async fn build_bootloader_config() -> Result<PathBuf> {
+/// Compress a boot artifact so the bootloader has less to read from disk.
+async fn compress_artifact(file: &Path) -> Result<PathBuf> {
+    let mut compressed_path = file.as_os_str().to_owned();
+    compressed_path.push(".lz");
+    let compressed_path = PathBuf::from(compressed_path);
+
+    let compressed = lzss::compress(&tokio::fs::read(file).await?);
+    tokio::fs::write(&compressed_path, compressed).await?;
+
+    Ok(compressed_path)
+}
+
+/// Get the CRC32 of a boot artifact, so the bootloader can verify it after loading.
+async fn crc32_of(file: &Path) -> Result<u32> {
+    Ok(util::crc32::crc32(&tokio::fs::read(file).await?))
+}
+
+async fn build_bootloader_config(
+    stage_32: &Path,
+    stage_64: &Path,
+    kernel: &Path,
+    initfs: &Path,
+) -> Result<PathBuf> {
     let target_location = PathBuf::from("./target/qconfig.cfg");
 
+    let (stage_32_crc, stage_64_crc, kernel_crc, initfs_crc) = tokio::try_join!(
+        crc32_of(stage_32),
+        crc32_of(stage_64),
+        crc32_of(kernel),
+        crc32_of(initfs)
+    )?;
+
     let mut file = OpenOptions::new()
         .read(true)
         .create(true)
@@ -191,18 +245,127 @@ async fn build_bootloader_config() -> Result<PathBuf> {
         .await?;
 
     file.write_all(
-        br#"bootloader32=/bootloader/stage_32.bin
+        format!(
+            r#"bootloader32=/bootloader/stage_32.bin
+bootloader32-crc32={stage_32_crc:08x}
 bootloader64=/bootloader/stage_64.bin
+bootloader64-crc32={stage_64_crc:08x}
 kernel=/kernel.elf
+kernel-crc32={kernel_crc:08x}
 vbe-mode=1280x720
+boot-mode=splash
+cmdline=debugcon
 initfs=/initfs
-"#,
+initfs-crc32={initfs_crc:08x}
+"#
+        )
+        .as_bytes(),
     )
     .await?;
 
     Ok(target_location)
 }
 
+/// Generate the boot splash image shown by the kernel while booting.
+///
+/// This draws a simple aloe leaf shape as a binary (P6) PPM image.
+pub async fn build_splash_image() -> Result<PathBuf> {
+    const SIZE: usize = 128;
+    const BACKGROUND: [u8; 3] = [0x12, 0x12, 0x12];
+    const LEAF: [u8; 3] = [0x5F, 0xAF, 0x5F];
+
+    let splash_path = PathBuf::from("./target/bin/splash.ppm");
+    let mut image = format!("P6\n{SIZE} {SIZE}\n255\n").into_bytes();
+
+    for y in 0..SIZE {
+        // Each leaf narrows as it grows away from the base of the plant
+        let leaf_width = (SIZE - y) / 6;
+
+        for x in 0..SIZE {
+            let center_distance = x.abs_diff(SIZE / 2);
+            let left_distance = x.abs_diff(SIZE / 2 - (SIZE - y) / 3);
+            let right_distance = x.abs_diff(SIZE / 2 + (SIZE - y) / 3);
+
+            let in_leaf = y >= SIZE / 8
+                && (center_distance < leaf_width
+                    || (y >= SIZE / 3
+                        && (left_distance < leaf_width / 2 || right_distance < leaf_width / 2)));
+
+            image.extend_from_slice(if in_leaf { &LEAF } else { &BACKGROUND });
+        }
+    }
+
+    tokio::fs::write(&splash_path, image).await?;
+    Ok(splash_path)
+}
+
+/// Generate the kernel's symbol map, used by the kernel to name the functions it samples
+/// while profiling.
+///
+/// Each line is `<start> <size> <name>` with `start` and `size` in hex, sorted by `start`.
+pub async fn build_kernel_symbol_map(kernel: &Path) -> Result<PathBuf> {
+    let symbol_map_path = PathBuf::from("./target/bin/kernel.sym");
+    let kernel_elf = tokio::fs::read(kernel).await?;
+
+    let elf = elf::Elf::new(&kernel_elf);
+    let symbols = elf
+        .symbols()
+        .context("Unable to read the kernel's symbols")?;
+
+    let mut functions: Vec<_> = symbols
+        .iter()
+        .filter(|symbol| {
+            symbol.symbol_kind() == elf::tables::SymbolKind::Func && symbol.size() != 0
+        })
+        .filter_map(|symbol| {
+            let name = symbols.name_of(symbol).ok()?;
+            Some((symbol.value(), symbol.size(), name))
+        })
+        .collect();
+    functions.sort_unstable_by_key(|&(start, ..)| start);
+
+    let mut symbol_map = String::new();
+    for (start, size, name) in functions {
+        symbol_map.push_str(&format!(
+            "{start:x} {size:x} {:#}\n",
+            rustc_demangle::demangle(name)
+        ));
+    }
+
+    tokio::fs::write(&symbol_map_path, symbol_map).await?;
+    Ok(symbol_map_path)
+}
+
+/// Check the service manifest `init` reads against the programs in the initfs, so a broken
+/// manifest fails the build instead of the boot.
+fn check_service_manifest(manifest_path: &Path, initfs_files: &[(PathBuf, PathBuf)]) -> Result<()> {
+    let text = fs::read_to_string(manifest_path).context("Unable to read the service manifest")?;
+    let invalid = |err| Error::msg(format!("{}: {err}", manifest_path.display()));
+
+    let manifest = Manifest::parse(&text).map_err(invalid)?;
+    manifest
+        .check_binaries(|binary| {
+            initfs_files
+                .iter()
+                .any(|(_, to_loc)| to_loc.file_name().is_some_and(|name| name == binary))
+        })
+        .map_err(invalid)
+}
+
+/// Parse the keyboard layout at `keymap_path` the same way the kernel does, so a broken
+/// layout fails the build instead of `keymap_set`
+fn check_keymap(keymap_path: &Path) -> Result<()> {
+    let text = fs::read_to_string(keymap_path).context("Unable to read a keyboard layout")?;
+    let name = keymap_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+
+    Keymap::parse(name, &text)
+        .map(|_| ())
+        .map_err(|err| Error::msg(format!("{}: {err}", keymap_path.display())))
+}
+
 pub async fn build_initfs_file(initfs_files: &[(PathBuf, PathBuf)]) -> Result<PathBuf> {
     let tar_path = PathBuf::from("./target/bin/initfs");
     let tar_backed = std::fs::OpenOptions::new()
@@ -230,7 +393,11 @@ pub async fn file_len_of(file: &Path) -> Result<usize> {
 
 // FIXME: This 'emit_asm' thing is kinda a hack just to get it working
 //        we should change this in the future.
-pub async fn build_project(multiboot_mode: bool, emit_asm: Option<String>) -> Result<Artifacts> {
+pub async fn build_project(
+    multiboot_mode: bool,
+    emit_asm: Option<String>,
+    kernel_features: Option<&str>,
+) -> Result<Artifacts> {
     let (
         stage_bootsector,
         stage_16bit,
@@ -240,7 +407,13 @@ pub async fn build_project(multiboot_mode: bool, emit_asm: Option<String>) -> Re
         dummy_userspace,
         hello_server,
         fs_server,
-        boot_cfg,
+        console_server,
+        gfx_server,
+        sound_server,
+        bench_server,
+        debug_shell,
+        logd,
+        init,
     ) = tokio::try_join!(
         cargo_helper(
             Some("stage-bootsector"),
@@ -278,7 +451,7 @@ pub async fn build_project(multiboot_mode: bool, emit_asm: Option<String>) -> Re
             Some("vera"),
             "vera",
             ArchSelect::Kernel,
-            None,
+            kernel_features,
             emit_asm.as_ref().is_some_and(|s| s == "kernel")
         ),
         cargo_helper(
@@ -302,14 +475,81 @@ pub async fn build_project(multiboot_mode: bool, emit_asm: Option<String>) -> Re
             None,
             emit_asm.as_ref().is_some_and(|s| s == "fs-server")
         ),
-        build_bootloader_config(),
+        cargo_helper(
+            Some("userspace"),
+            "console-server",
+            ArchSelect::UserSpace,
+            None,
+            emit_asm.as_ref().is_some_and(|s| s == "console-server")
+        ),
+        cargo_helper(
+            Some("userspace"),
+            "gfx-server",
+            ArchSelect::UserSpace,
+            None,
+            emit_asm.as_ref().is_some_and(|s| s == "gfx-server")
+        ),
+        cargo_helper(
+            Some("userspace"),
+            "sound-server",
+            ArchSelect::UserSpace,
+            None,
+            emit_asm.as_ref().is_some_and(|s| s == "sound-server")
+        ),
+        cargo_helper(
+            Some("userspace"),
+            "bench-server",
+            ArchSelect::UserSpace,
+            None,
+            emit_asm.as_ref().is_some_and(|s| s == "bench-server")
+        ),
+        cargo_helper(
+            Some("userspace"),
+            "debug-shell",
+            ArchSelect::UserSpace,
+            None,
+            emit_asm.as_ref().is_some_and(|s| s == "debug-shell")
+        ),
+        cargo_helper(
+            Some("userspace"),
+            "logd",
+            ArchSelect::UserSpace,
+            None,
+            emit_asm.as_ref().is_some_and(|s| s == "logd")
+        ),
+        cargo_helper(
+            Some("userspace"),
+            "init",
+            ArchSelect::UserSpace,
+            None,
+            emit_asm.as_ref().is_some_and(|s| s == "init")
+        ),
     )?;
 
+    let (splash_image, kernel_symbols) =
+        tokio::try_join!(build_splash_image(), build_kernel_symbol_map(&kernel))?;
+
     let ue_slice = [
         (hello_server, PathBuf::from("./helloServ")),
         (dummy_userspace, PathBuf::from("./dummy")),
         (fs_server, PathBuf::from("./fs-server")),
+        (console_server, PathBuf::from("./console-server")),
+        (gfx_server, PathBuf::from("./gfx-server")),
+        (sound_server, PathBuf::from("./sound-server")),
+        (bench_server, PathBuf::from("./bench-server")),
+        (debug_shell, PathBuf::from("./debug-shell")),
+        (logd, PathBuf::from("./logd")),
+        (init, PathBuf::from("./init")),
+        (
+            PathBuf::from(SERVICE_MANIFEST),
+            PathBuf::from("./services.conf"),
+        ),
+        (splash_image, PathBuf::from("./splash.ppm")),
+        (PathBuf::from(KEYMAP_DE), PathBuf::from("./de.keymap")),
+        (kernel_symbols, PathBuf::from("./kernel.sym")),
     ];
+    check_service_manifest(Path::new(SERVICE_MANIFEST), &ue_slice)?;
+    check_keymap(Path::new(KEYMAP_DE))?;
 
     let (bootsector, stage_16, stage_32, stage_64, initfs) = tokio::try_join!(
         convert_bin(&stage_bootsector, ArchSelect::I386),
@@ -319,7 +559,13 @@ pub async fn build_project(multiboot_mode: bool, emit_asm: Option<String>) -> Re
         build_initfs_file(&ue_slice),
     )?;
 
-    let (kernel_len, initfs_len) = tokio::try_join!(file_len_of(&kernel), file_len_of(&initfs))?;
+    let (kernel_len, initfs_len, boot_cfg, kernel_compressed, initfs_compressed) = tokio::try_join!(
+        file_len_of(&kernel),
+        file_len_of(&initfs),
+        build_bootloader_config(&stage_32, &stage_64, &kernel, &initfs),
+        compress_artifact(&kernel),
+        compress_artifact(&initfs),
+    )?;
 
     Ok(Artifacts {
         bootsector,
@@ -335,6 +581,8 @@ pub async fn build_project(multiboot_mode: bool, emit_asm: Option<String>) -> Re
         initfs,
         kernel_len,
         initfs_len,
+        kernel_compressed,
+        initfs_compressed,
     })
 }
 
@@ -0,0 +1,103 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+use util::crashdump::BEGIN_MARKER;
+
+/// How long the whole boot gets before the test gives up, qemu without kvm is slow.
+const BOOT_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// What the serial port has to show, in order, for the boot to count as working.
+///
+/// Each step waits for its text, then types its input into the console.
+const STEPS: &[(&str, Option<&str>)] = &[
+    // The kernel made it to userspace
+    ("Starting init!", None),
+    // init read its manifest and the shell's dependencies came up
+    ("init: started 'debug-shell'", None),
+    // The shell is connected to the console and waiting for a line
+    ("> ", Some("help\n")),
+    // The shell ran the command and printed through the console server
+    ("show this message", None),
+];
+
+/// Boot `qemu` headless and drive the debug shell over the serial port.
+///
+/// Everything qemu prints is passed through to our stdout, so a failing run can be read
+/// back from the CI log.
+pub fn run_boot_test(mut qemu: Command) -> Result<()> {
+    let mut child = qemu
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context(anyhow!("Could not start qemu-system-x86_64!"))?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+
+    let (sender, output) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        while let Ok(len @ 1..) = stdout.read(&mut buf) {
+            if sender.send(buf[..len].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let result = wait_for_steps(&output, &mut stdin);
+    let _ = child.kill();
+    let status = child.wait()?;
+
+    println!("\n");
+    match result {
+        Ok(()) => {
+            println!("QuantumOS Boot Test Success!");
+            Ok(())
+        }
+        Err(err) => Err(err.context(anyhow!("QuantumOS Boot Test Failure! (qemu {status})"))),
+    }
+}
+
+fn wait_for_steps(output: &mpsc::Receiver<Vec<u8>>, stdin: &mut impl Write) -> Result<()> {
+    let deadline = Instant::now() + BOOT_TIMEOUT;
+    let mut seen = String::new();
+    let mut searched_from = 0;
+
+    for &(expect, input) in STEPS {
+        loop {
+            if let Some(found) = seen[searched_from..].find(expect) {
+                searched_from += found + expect.len();
+                break;
+            }
+            if seen.contains(BEGIN_MARKER) {
+                bail!("The kernel crashed while waiting for {expect:?}");
+            }
+
+            let left = deadline.saturating_duration_since(Instant::now());
+            match output.recv_timeout(left) {
+                Ok(bytes) => {
+                    std::io::stdout().write_all(&bytes)?;
+                    seen.push_str(&String::from_utf8_lossy(&bytes));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    bail!("Timed out waiting for {expect:?}")
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    bail!("qemu exited before printing {expect:?}")
+                }
+            }
+        }
+
+        if let Some(input) = input {
+            stdin.write_all(input.as_bytes())?;
+            stdin.flush()?;
+        }
+    }
+
+    Ok(())
+}
@@ -35,13 +35,17 @@ pub struct CommandLine {
     /// Run clippy durning build
     #[arg(long = "clippy", default_value_t = false)]
     pub enable_clippy: bool,
+
+    /// Extra features to build the kernel with, like `heap-tracking`
+    #[arg(long = "kernel-features")]
+    pub kernel_features: Option<String>,
 }
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum TaskOption {
     /// Build Quantum OS
     Build,
-    /// Run CI/CD Actions
+    /// Run CI/CD Actions, booting headless and checking init and the shell over serial
     Actions,
     /// Run + Build Quantum OS
     Run,
@@ -53,4 +57,6 @@ pub enum TaskOption {
     BuildDisk,
     /// Emit asm for this crate
     AsmAt { file: String, ip: String },
+    /// Pretty-print the crash dumps in a serial log
+    DecodeCrashDump { log: String },
 }
@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use util::{
+    base64,
+    crashdump::{self, RecordKind, BEGIN_MARKER, END_MARKER, REGISTER_NAMES},
+};
+
+/// Find every crash dump the kernel wrote into a serial log, and pretty-print them.
+pub fn decode_crash_dumps(log_path: &Path) -> Result<()> {
+    let log = std::fs::read(log_path)?;
+    let log = String::from_utf8_lossy(&log);
+
+    let mut found = 0;
+    let mut rest = &*log;
+    while let Some(start) = rest.find(BEGIN_MARKER) {
+        let encoded = &rest[start + BEGIN_MARKER.len()..];
+        found += 1;
+
+        let end = encoded.find(END_MARKER).ok_or(anyhow!(
+            "Crash dump #{found} was cut off before its end marker!"
+        ))?;
+
+        let mut dump = Vec::new();
+        base64::decode(&encoded.as_bytes()[..end], |byte| dump.push(byte)).map_err(|err| {
+            anyhow!(
+                "Crash dump #{found} is not valid base64 (at offset {})!",
+                err.offset
+            )
+        })?;
+
+        println!("========== Crash Dump #{found} ==========");
+        print_dump(&dump)?;
+
+        rest = &encoded[end + END_MARKER.len()..];
+    }
+
+    if found == 0 {
+        return Err(anyhow!(
+            "No crash dumps found in '{}'! (Was the kernel booted with `crashdump`?)",
+            log_path.display()
+        ));
+    }
+
+    Ok(())
+}
+
+fn print_dump(dump: &[u8]) -> Result<()> {
+    let records =
+        crashdump::parse(dump).map_err(|err| anyhow!("Unable to parse crash dump: {err:?}"))?;
+
+    for record in records {
+        let record = record.map_err(|err| anyhow!("Unable to parse crash dump: {err:?}"))?;
+        let Some(kind) = record.kind() else {
+            println!(
+                "\n--- Unknown record {} ({} bytes) ---",
+                record.raw_kind,
+                record.data.len()
+            );
+            continue;
+        };
+
+        println!("\n--- {} ---", kind.name());
+        match kind {
+            RecordKind::Registers => {
+                for (name, value) in REGISTER_NAMES.iter().zip(record.data.as_chunks::<8>().0) {
+                    println!("{name:>8} = {:#018x}", u64::from_le_bytes(*value));
+                }
+            }
+            RecordKind::Stack => {
+                let Some((base, stack)) = record.data.split_first_chunk::<8>() else {
+                    continue;
+                };
+                let base = u64::from_le_bytes(*base);
+
+                for (i, words) in stack.chunks(16).enumerate() {
+                    print!("{:#018x}:", base + (i * 16) as u64);
+                    for word in words.as_chunks::<8>().0 {
+                        print!(" {:#018x}", u64::from_le_bytes(*word));
+                    }
+                    println!();
+                }
+            }
+            RecordKind::Message
+            | RecordKind::Backtrace
+            | RecordKind::MemoryMap
+            | RecordKind::Log => {
+                println!("{}", String::from_utf8_lossy(record.data).trim_end())
+            }
+        }
+    }
+
+    Ok(())
+}
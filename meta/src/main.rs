@@ -14,7 +14,9 @@ use crate::{
 };
 
 mod artifacts;
+mod boot_test;
 mod cmdline;
+mod crashdump;
 mod disk;
 
 struct QuickBootImages {
@@ -34,18 +36,22 @@ struct BuildResult {
 async fn build(
     multiboot_mode: bool,
     emit_asm: Option<String>,
+    kernel_features: Option<&str>,
     should_run_clippy: bool,
 ) -> Result<BuildResult> {
     let (artifacts, disk) = if should_run_clippy {
         let (a, d, _) = tokio::join!(
-            build_project(multiboot_mode, emit_asm),
+            build_project(multiboot_mode, emit_asm, kernel_features),
             DiskImgBaker::new(),
             run_clippy(None)
         );
 
         (a, d)
     } else {
-        tokio::join!(build_project(multiboot_mode, emit_asm), DiskImgBaker::new())
+        tokio::join!(
+            build_project(multiboot_mode, emit_asm, kernel_features),
+            DiskImgBaker::new()
+        )
     };
 
     let artifacts = artifacts?;
@@ -67,8 +73,9 @@ async fn build(
                 (artifacts.boot_cfg, PathBuf::from("bootloader/qconfig.cfg")),
                 (artifacts.stage_32, PathBuf::from("bootloader/stage_32.bin")),
                 (artifacts.stage_64, PathBuf::from("bootloader/stage_64.bin")),
-                (artifacts.kernel, PathBuf::from("kernel.elf")),
-                (artifacts.initfs, PathBuf::from("initfs")),
+                // Stage32 will decompress these after they are loaded
+                (artifacts.kernel_compressed, PathBuf::from("kernel.elf")),
+                (artifacts.initfs_compressed, PathBuf::from("initfs")),
             ]
             .into_iter(),
         )
@@ -111,7 +118,8 @@ async fn build(
     })
 }
 
-fn run_qemu(
+/// Build the qemu command that boots `disk_target_path`, without starting it
+fn qemu_command(
     disk_target_path: &Path,
     enable_kvm: bool,
     enable_no_graphic: bool,
@@ -119,7 +127,7 @@ fn run_qemu(
     slow_emu: Option<usize>,
     use_gdb: bool,
     quick_boot: Option<QuickBootImages>,
-) -> Result<()> {
+) -> Command {
     let kvm: &[&str] = if enable_kvm {
         &["--enable-kvm", "--cpu", "host"]
     } else {
@@ -146,6 +154,9 @@ fn run_qemu(
             // Stage32
             "-kernel",
             &format!("{}", quick_boot.loader32.2.to_string_lossy()),
+            // Kernel command line
+            "-append",
+            "debugcon",
             // Stage64
             "-device",
             &format!(
@@ -227,14 +238,16 @@ fn run_qemu(
         &[]
     };
 
-    let qemu = Command::new("qemu-system-x86_64")
-        .args(kvm)
+    let mut qemu = Command::new("qemu-system-x86_64");
+    qemu.args(kvm)
         .args(no_graphic)
         .args(fast_boot)
         .arg("--name")
         .arg("Quantum OS")
         .arg("-device")
         .arg("isa-debug-exit,iobase=0xf4,iosize=0x04")
+        .arg("-debugcon")
+        .arg("file:target/debugcon.log")
         .arg("--no-reboot")
         .args(log_interrupts)
         .arg("-m")
@@ -249,10 +262,32 @@ fn run_qemu(
         .arg(format!(
             "format=raw,file={}",
             disk_target_path.to_str().unwrap()
-        ))
-        .stdout(std::process::Stdio::inherit())
-        .status()
-        .context(anyhow!("Could not start qemu-system-x86_64!"))?;
+        ));
+
+    qemu
+}
+
+fn run_qemu(
+    disk_target_path: &Path,
+    enable_kvm: bool,
+    enable_no_graphic: bool,
+    log_interrupts: bool,
+    slow_emu: Option<usize>,
+    use_gdb: bool,
+    quick_boot: Option<QuickBootImages>,
+) -> Result<()> {
+    let qemu = qemu_command(
+        disk_target_path,
+        enable_kvm,
+        enable_no_graphic,
+        log_interrupts,
+        slow_emu,
+        use_gdb,
+        quick_boot,
+    )
+    .stdout(std::process::Stdio::inherit())
+    .status()
+    .context(anyhow!("Could not start qemu-system-x86_64!"))?;
 
     println!("\n");
     match qemu.code().unwrap_or(0) {
@@ -336,15 +371,18 @@ async fn run_object_dump(file: &Path, ip: usize) -> Result<()> {
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = cmdline::CommandLine::parse();
+    let kernel_features = args.kernel_features.as_deref();
 
     match args.option.unwrap_or(cmdline::TaskOption::Run) {
         cmdline::TaskOption::Build => {
-            build(false, None, args.enable_clippy).await?;
+            build(false, None, kernel_features, args.enable_clippy).await?;
         }
         cmdline::TaskOption::Run => {
             if !args.use_bochs {
                 run_qemu(
-                    &build(false, None, args.enable_clippy).await?.disk_img,
+                    &build(false, None, kernel_features, args.enable_clippy)
+                        .await?
+                        .disk_img,
                     args.enable_kvm,
                     args.no_graphic,
                     args.log_interrupts,
@@ -353,7 +391,12 @@ async fn main() -> Result<()> {
                     None,
                 )?;
             } else {
-                run_bochs(&build(false, None, args.enable_clippy).await?.disk_img).await?;
+                run_bochs(
+                    &build(false, None, kernel_features, args.enable_clippy)
+                        .await?
+                        .disk_img,
+                )
+                .await?;
             }
         }
         cmdline::TaskOption::RunQuick => {
@@ -364,7 +407,7 @@ async fn main() -> Result<()> {
             let BuildResult {
                 disk_img,
                 quick_boot: Some(quick_boot),
-            } = build(true, None, args.enable_clippy).await?
+            } = build(true, None, kernel_features, args.enable_clippy).await?
             else {
                 panic!("Build didn't return expected results!");
             };
@@ -380,7 +423,12 @@ async fn main() -> Result<()> {
             )?;
         }
         cmdline::TaskOption::BuildDisk => {
-            run_mk_image(&build(false, None, args.enable_clippy).await?.disk_img).await?;
+            run_mk_image(
+                &build(false, None, kernel_features, args.enable_clippy)
+                    .await?
+                    .disk_img,
+            )
+            .await?;
         }
         cmdline::TaskOption::Clean => {
             todo!("clean")
@@ -393,8 +441,20 @@ async fn main() -> Result<()> {
             };
             run_object_dump(Path::new(&file), ip).await?;
         }
+        cmdline::TaskOption::DecodeCrashDump { log } => {
+            crashdump::decode_crash_dumps(Path::new(&log))?;
+        }
         cmdline::TaskOption::Actions => {
-            todo!()
+            let disk_img = build(false, None, kernel_features, true).await?.disk_img;
+            boot_test::run_boot_test(qemu_command(
+                &disk_img,
+                args.enable_kvm,
+                true,
+                args.log_interrupts,
+                args.slow_emulator,
+                false,
+                None,
+            ))?;
         }
     }
 
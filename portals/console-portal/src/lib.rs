@@ -0,0 +1,57 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+
+use portal::portal;
+
+#[portal(protocol = "ipc")]
+pub trait ConsolePortal {
+    /// Read up to `max_len` bytes of input from the console
+    ///
+    /// In canonical mode this blocks until a full line has been typed, and never
+    /// returns more than one line. In raw mode this blocks until any input arrives.
+    #[event = 1]
+    fn read(max_len: u64) -> Vec<u8> {}
+
+    /// Write `bytes` to the console
+    #[event = 2]
+    fn write(bytes: Vec<u8>) {}
+
+    /// Change how console input is processed
+    #[event = 3]
+    fn set_mode(mode: ConsoleMode) {
+        struct ConsoleMode {
+            /// Collect input into lines, and allow editing the line before it is read
+            canonical: bool,
+            /// Write input back to the console as it is typed
+            echo: bool,
+        }
+    }
+
+    /// Get how console input is currently processed
+    #[event = 4]
+    fn get_mode() -> ConsoleMode {}
+}
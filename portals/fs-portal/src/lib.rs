@@ -26,9 +26,201 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 #![no_std]
 
 use portal::portal;
+pub use quantum_error::QuantumError;
 
 #[portal(protocol = "ipc")]
 pub trait FsPortal {
     #[event = 1]
     fn ping() {}
+
+    /// Map `len` bytes of the file at `path`, starting at `offset`, into shared memory
+    ///
+    /// The region is filled from the file before this call returns, and is sent to the
+    /// client as a handle it maps with `shared_map`. Bytes past the end of the file are
+    /// zero, and at most 16MiB can be mapped at once.
+    #[event = 2]
+    fn mmap(
+        path: String,
+        offset: u64,
+        len: u64,
+    ) -> Result<MappedFile, quantum_error::QuantumError> {
+        struct MappedFile {
+            /// A handle to the shared memory region holding the file's contents
+            shared_id: u64,
+            /// How many bytes of the file were mapped
+            len: u64,
+        }
+    }
+
+    /// Start watching the file or directory at `path` for changes
+    ///
+    /// Watching a directory also reports changes to anything inside of it. Returns
+    /// the id of the new watch, which is only valid for this connection.
+    #[event = 3]
+    fn watch(path: String) -> Result<u64, quantum_error::QuantumError> {}
+
+    /// Stop watching, dropping any events that were not taken yet
+    #[event = 4]
+    fn unwatch(watch_id: u64) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Take all events that happened to a watch since they were last taken
+    ///
+    /// This never blocks, and returns no events if nothing has changed.
+    #[event = 5]
+    fn watch_events(watch_id: u64) -> Result<Vec<WatchEvent>, quantum_error::QuantumError> {
+        struct WatchEvent {
+            /// What happened to the file
+            kind: WatchEventKind,
+            /// The full path of the file that changed
+            path: String,
+        }
+
+        enum WatchEventKind {
+            Created,
+            Modified,
+            Deleted,
+        }
+    }
+
+    /// Change the working directory of this connection
+    ///
+    /// Relative paths given to any other endpoint are resolved against it.
+    #[event = 6]
+    fn chdir(path: String) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Get the absolute path of the working directory of this connection
+    #[event = 7]
+    fn getcwd() -> String {}
+
+    /// Read up to `len` bytes of the file at `path`, starting at `offset`
+    ///
+    /// Returns fewer bytes than asked for when the file ends first.
+    #[event = 8]
+    fn read(path: String, offset: u64, len: u64) -> Result<Vec<u8>, quantum_error::QuantumError> {}
+
+    /// Write `bytes` to the file at `path`, starting at `offset`, returning how many
+    /// were written
+    ///
+    /// Fails with `ReadOnly` if the file is on a read-only mount.
+    #[event = 9]
+    fn write(
+        path: String,
+        offset: u64,
+        bytes: Vec<u8>,
+    ) -> Result<u64, quantum_error::QuantumError> {
+    }
+
+    /// Get every mounted filesystem
+    #[event = 10]
+    fn mounts() -> Vec<MountInfo> {
+        struct MountInfo {
+            /// Where the filesystem is mounted, like `/boot`
+            point: String,
+            /// The device the filesystem is on, like `ata0p1`
+            source: String,
+            /// The kind of filesystem, like `fat16`
+            kind: String,
+            read_only: bool,
+        }
+    }
+
+    /// Make the mount at `point` read-only, or writable again
+    ///
+    /// Mounts can't be made writable when the kernel was booted with `fs.readonly`.
+    #[event = 11]
+    fn remount(point: String, read_only: bool) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Get every disk the server detected, whether or not anything on it is mounted
+    #[event = 12]
+    fn disks() -> Vec<DiskInfo> {
+        struct DiskInfo {
+            /// The name mounts refer to the disk by, like `ata0`
+            name: String,
+            /// Whether this is a packet device, like a CD drive
+            removable: bool,
+            model: String,
+            serial: String,
+            firmware: String,
+            /// How many sectors the disk holds, zero for an empty drive
+            sectors: u64,
+            sector_size: u32,
+            lba48: bool,
+            smart: bool,
+            dma: bool,
+            write_cache: bool,
+            trim: bool,
+        }
+    }
+
+    /// Append `bytes` to the end of the file at `path`, creating it if it does not exist,
+    /// and return the new size of the file
+    ///
+    /// The append is done as one request, so appends from many clients never overlap.
+    #[event = 13]
+    fn append(path: String, bytes: Vec<u8>) -> Result<u64, quantum_error::QuantumError> {}
+
+    /// Cut the file at `path` down to `len` bytes, or grow it to `len` bytes with zeros
+    ///
+    /// A file can grow by at most 16MiB at once, anything more fails with `InvalidInput`.
+    #[event = 14]
+    fn truncate(path: String, len: u64) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Move the file or directory at `from` to `to`, replacing the file at `to` if there is
+    /// one
+    ///
+    /// Both paths must be on the same mount, otherwise this fails with `NotSupported`.
+    #[event = 15]
+    fn rename(from: String, to: String) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Create an empty directory at `path`, the directory it is in must already exist
+    #[event = 16]
+    fn create_dir(path: String) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Remove the file or empty directory at `path`
+    #[event = 17]
+    fn remove(path: String) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Get the size and kind of the file or directory at `path`
+    #[event = 18]
+    fn stat(path: String) -> Result<FileStat, quantum_error::QuantumError> {
+        struct FileStat {
+            /// The size of the file in bytes, zero for directories
+            size: u64,
+            is_dir: bool,
+        }
+    }
+
+    /// Read up to `len` bytes of the file at `path`, starting at `offset`, straight into
+    /// the shared memory region `shared_id` at `shared_offset`, returning how many were
+    /// read
+    ///
+    /// Unlike `read` the bytes are never copied through the connection, so this is much
+    /// faster for large reads. `shared_id` is a handle to a region made with
+    /// `shared_create`, sent to the server over this connection with `handle_send`. The
+    /// server closes it once the read is done, so send a `handle_duplicate` of the region
+    /// to keep using it. Fails with `InvalidHandle` if the handle wasn't sent over this
+    /// connection, and `InvalidInput` if the read doesn't fit in the region.
+    #[event = 19]
+    fn read_shared(
+        path: String,
+        offset: u64,
+        len: u64,
+        shared_id: u64,
+        shared_offset: u64,
+    ) -> Result<u64, quantum_error::QuantumError> {
+    }
+
+    /// Read the file at `path` starting at `offset` into one buffer for each of `lens`,
+    /// filling them in turn
+    ///
+    /// The buffers after the end of the file come back short or empty. All the buffers
+    /// together are limited to the same size as one `read`, and asking for more than 256
+    /// buffers fails with `InvalidInput`.
+    #[event = 20]
+    fn read_vectored(
+        path: String,
+        offset: u64,
+        lens: Vec<u64>,
+    ) -> Result<Vec<Vec<u8>>, quantum_error::QuantumError> {
+    }
 }
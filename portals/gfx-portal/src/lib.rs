@@ -0,0 +1,109 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+
+use portal::portal;
+pub use quantum_error::QuantumError;
+
+#[portal(protocol = "ipc")]
+pub trait GfxPortal {
+    #[event = 1]
+    fn ping() {}
+
+    /// Get the size of the screen in pixels
+    #[event = 2]
+    fn screen_size() -> ScreenSize {
+        struct ScreenSize {
+            width: u32,
+            height: u32,
+        }
+    }
+
+    /// Create a new surface of `width` by `height` pixels, placed above all others
+    ///
+    /// The surface's pixels live in shared memory, which is sent to the client as a handle it
+    /// maps with `shared_map`.
+    /// Each pixel is a `u32` of the form `0x00RRGGBB`, and rows are packed with no padding.
+    /// Nothing is drawn until part of the surface is damaged.
+    #[event = 3]
+    fn create_surface(width: u32, height: u32) -> Result<Surface, quantum_error::QuantumError> {
+        struct Surface {
+            /// The id of this surface, only valid for this connection
+            id: u64,
+            /// A handle to the shared memory region holding the surface's pixels
+            shared_id: u64,
+        }
+    }
+
+    /// Remove a surface from the screen
+    #[event = 4]
+    fn destroy_surface(surface_id: u64) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Move the top left corner of a surface to `x`, `y` on the screen
+    ///
+    /// Surfaces can be partly, or fully, off the screen.
+    #[event = 5]
+    fn move_surface(surface_id: u64, x: i32, y: i32) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Move a surface above all other surfaces
+    #[event = 6]
+    fn raise_surface(surface_id: u64) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Tell the compositor that a rectangle of the surface has changed and should be redrawn
+    ///
+    /// The rectangle is in surface coordinates, and is clipped to the surface.
+    #[event = 7]
+    fn damage(
+        surface_id: u64,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), quantum_error::QuantumError> {
+    }
+
+    /// Change the resolution of the screen to `width` by `height` pixels
+    ///
+    /// Zero for `width` and `height` picks the size the display would like to be. Every
+    /// surface is redrawn onto the new screen, surfaces that no longer fit are left where
+    /// they are. If the switch fails the screen keeps its old resolution.
+    #[event = 8]
+    fn set_mode(width: u32, height: u32) -> Result<ScreenSize, quantum_error::QuantumError> {}
+
+    /// Start drawing the mouse pointer over every surface
+    #[event = 9]
+    fn show_cursor() {}
+
+    /// Stop drawing the mouse pointer
+    #[event = 10]
+    fn hide_cursor() {}
+
+    /// Point the mouse pointer at `x`, `y` on the screen
+    ///
+    /// The pointer is kept on the screen, and only the pixels it covers are redrawn.
+    #[event = 11]
+    fn move_cursor(x: i32, y: i32) {}
+}
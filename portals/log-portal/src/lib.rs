@@ -0,0 +1,59 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+
+use portal::portal;
+pub use quantum_error::QuantumError;
+
+/// The most bytes a single `read` returns
+pub const MAX_READ_LEN: u64 = 4096;
+
+#[portal(protocol = "ipc")]
+pub trait LogPortal {
+    #[event = 1]
+    fn ping() {}
+
+    /// Read up to `len` bytes of log, starting at `cursor`
+    ///
+    /// The cursor counts every byte logged since `logd` started, and only the newest part of
+    /// the log is kept around. Reading from a cursor that is too old skips ahead to the oldest
+    /// byte still kept, and says how many bytes were `missed`. This never blocks, and returns
+    /// no bytes once the reader has caught up.
+    #[event = 2]
+    fn read(cursor: u64, len: u64) -> LogChunk {
+        struct LogChunk {
+            /// The cursor to pass to the next `read`
+            cursor: u64,
+            /// How many bytes were skipped because they are no longer kept
+            missed: u64,
+            bytes: Vec<u8>,
+        }
+    }
+
+    /// The cursor just after the newest byte of log
+    #[event = 3]
+    fn end() -> u64 {}
+}
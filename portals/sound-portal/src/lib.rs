@@ -0,0 +1,53 @@
+/*
+  ____                 __               __   _ __
+ / __ \__ _____ ____  / /___ ____ _    / /  (_) /
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /__/ / _ \
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/ /____/_/_.__/
+    Part of the Quantum OS Project
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+
+use portal::portal;
+pub use quantum_error::QuantumError;
+
+#[portal(protocol = "ipc")]
+pub trait SoundPortal {
+    #[event = 1]
+    fn ping() {}
+
+    /// Play a tone of `frequency` hertz for `duration_ms` milliseconds
+    ///
+    /// Returns once the tone has finished playing.
+    #[event = 2]
+    fn beep(frequency: u32, duration_ms: u32) -> Result<(), quantum_error::QuantumError> {}
+
+    /// Play each note one after another
+    ///
+    /// Returns once every note has finished playing.
+    #[event = 3]
+    fn play(notes: Vec<Note>) -> Result<(), quantum_error::QuantumError> {
+        struct Note {
+            /// The frequency of the note in hertz, or `0` for a rest
+            frequency: u32,
+            duration_ms: u32,
+        }
+    }
+}
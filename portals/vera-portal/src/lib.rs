@@ -27,6 +27,58 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 use portal::portal;
 
+/// The rights a handle can carry, as bits of a rights mask
+///
+/// New handles are created with [`rights::ALL`], and can only lose rights when
+/// duplicated with `handle_duplicate`.
+pub mod rights {
+    /// Receive data from the handle
+    pub const READ: u64 = 1 << 0;
+    /// Send data to the handle
+    pub const WRITE: u64 = 1 << 1;
+    /// Map the object behind the handle into memory
+    pub const MAP: u64 = 1 << 2;
+    /// Give the handle to another process
+    pub const TRANSFER: u64 = 1 << 3;
+    /// Make new handles to the same object
+    pub const DUPLICATE: u64 = 1 << 4;
+
+    pub const ALL: u64 = READ | WRITE | MAP | TRANSFER | DUPLICATE;
+}
+
+/// The privileged things a process can be allowed to do, as bits of a capability mask
+///
+/// The programs the kernel starts have [`capabilities::ALL`], and every other process
+/// only has the capabilities its parent gave it with `spawn_with`.
+pub mod capabilities {
+    /// Claim IO ports, and use the ports that were claimed
+    pub const IO_PORTS: u64 = 1 << 0;
+    /// Take over the framebuffer
+    pub const FRAMEBUFFER: u64 = 1 << 1;
+    /// Read the kernel's log ring
+    pub const LOG_RING: u64 = 1 << 2;
+    /// Look inside the system, like taking screenshots or reading other processes' memory
+    pub const DEBUG: u64 = 1 << 3;
+    /// Change system wide settings, like the keyboard layout
+    pub const SYSTEM: u64 = 1 << 4;
+    /// Read the disks the kernel drives itself, like USB sticks
+    pub const DISKS: u64 = 1 << 5;
+
+    pub const ALL: u64 = IO_PORTS | FRAMEBUFFER | LOG_RING | DEBUG | SYSTEM | DISKS;
+}
+
+/// The most bytes a single [`disk_read`] can read
+pub const MAX_DISK_READ: usize = 64 * 1024;
+
+/// The nice values a task can have, lower values get more cpu time
+pub const NICE_RANGE: core::ops::RangeInclusive<i8> = -20..=19;
+
+/// The percentage of a task's kernel stack it can use before the kernel warns about it
+pub const STACK_WARN_PERCENT: u64 = 75;
+
+/// Every line the kernel writes into its log ring for a traced syscall starts with this
+pub const TRACE_LINE_PREFIX: &str = "strace: ";
+
 #[portal(protocol = "syscall", global = true)]
 pub trait VeraPortal {
     #[event = 0]
@@ -73,10 +125,16 @@ pub trait VeraPortal {
         enum WaitSignal {
             /// Updates for handles
             HandleUpdate { kind: HandleUpdateKind, handle: u64 },
-            /// Updates for sleep
+            /// A timer set with [`signal_timer`] went off, `ms_duration` after it was set
             TimerUpdate { ms_duration: u64 },
             /// Your process is requested to exit
             TerminationRequest,
+            /// A process that registered you as its fault handler has faulted
+            ProcessFault { pid: usize, fault: FaultKind },
+            /// One of your child processes has exited, and can be reaped with [`wait`]
+            ChildExit { pid: usize },
+            /// Something happened to the system, sent after asking with [`system_events`]
+            SystemEvent { event: SystemEventKind },
             /// There is no condition in this slot
             None,
         }
@@ -90,6 +148,41 @@ pub trait VeraPortal {
             Disconnected,
             /// This handle has accepted a new connection
             NewConnection { new_handle: u64 },
+            /// This handle was given to you by the process `from`
+            Received { from: usize },
+        }
+
+        enum FaultKind {
+            /// Accessed memory without the correct permissions
+            PageFault {
+                addr: u64,
+                write: bool,
+                execute: bool,
+            },
+            /// Divide by zero
+            DivisionError,
+            /// Tried to execute an invalid instruction
+            InvalidOpcode,
+            /// General protection fault
+            GeneralProtection,
+            /// Any other cpu exception
+            Other,
+        }
+
+        enum SystemEventKind {
+            /// A driver claimed the hardware of a new device, see [`resource_info`]
+            DeviceAdded,
+            /// A driver gave up the hardware of a device, see [`resource_info`]
+            DeviceRemoved,
+            /// Free memory dropped below a few megabytes, sent once until it recovers
+            LowMemory { free_pages: usize },
+            /// The power button was pressed
+            PowerButton,
+            /// The sleep button was pressed
+            SleepButton,
+            /// A key typed the character `codepoint` while the user console was on screen,
+            /// see [`keymap_set`]
+            KeyTyped { codepoint: u32 },
         }
     }
 
@@ -103,6 +196,10 @@ pub trait VeraPortal {
             InvalidHandle,
             RecvFailed,
             WouldBlock,
+            /// This handle does not have the `READ` right
+            PermissionDenied,
+            /// `buf` is not writable memory in this process
+            InvalidPtr,
         }
     }
 
@@ -113,6 +210,14 @@ pub trait VeraPortal {
             InvalidHandle,
             SendFailed,
             WouldBlock,
+            /// Every reader of this pipe has closed
+            BrokenPipe,
+            /// This handle does not have the `WRITE` right
+            PermissionDenied,
+            /// `buf` is not readable memory in this process
+            InvalidPtr,
+            /// `buf` is longer than a single send is allowed to be
+            InvalidLength(usize),
         }
     }
 
@@ -120,6 +225,8 @@ pub trait VeraPortal {
     fn serve(endpoint: &str) -> Result<u64, ServeHandleError> {
         enum ServeHandleError {
             AlreadyBound,
+            /// The name is not a readable, valid UTF-8 string
+            InvalidName,
         }
     }
 
@@ -127,6 +234,8 @@ pub trait VeraPortal {
     fn connect(endpoint: &str) -> Result<u64, ConnectHandleError> {
         enum ConnectHandleError {
             EndpointDoesNotExist,
+            /// The name is not a readable, valid UTF-8 string
+            InvalidName,
         }
     }
 
@@ -138,6 +247,10 @@ pub trait VeraPortal {
     #[event = 10]
     fn unmap_memory(ptr: *mut u8) {}
 
+    /// Read the IO port `address`
+    ///
+    /// Ports this process has not claimed with [`claim_io_ports`] read as all ones, like
+    /// a port nothing is connected to, and writes to them are dropped.
     #[event = 11]
     unsafe fn fixme_cpuio_read_u8(address: u16) -> u8 {}
 
@@ -150,6 +263,676 @@ pub trait VeraPortal {
     #[event = 14]
     unsafe fn fixme_cpuio_write_u16(address: u16, data: u16) {}
 
+    /// Register an endpoint to be notified if this process faults
+    ///
+    /// The owner of `endpoint` will receive a [`WaitSignal::ProcessFault`] when this
+    /// process faults, the faulting process is still terminated.
+    #[event = 15]
+    fn fault_handler(endpoint: &str) -> Result<(), FaultHandlerError> {
+        enum FaultHandlerError {
+            EndpointDoesNotExist,
+            /// The name is not a readable, valid UTF-8 string
+            InvalidName,
+        }
+    }
+
+    /// Spawn a program from the initfs as a child of this process
+    ///
+    /// Returns the pid of the new child process.
+    #[event = 16]
+    fn spawn(name: &str) -> Result<usize, SpawnError> {
+        enum SpawnError {
+            NotFound,
+            /// The name is not a readable, valid UTF-8 string
+            InvalidName,
+            /// This process does not have every capability it tried to give the child
+            PermissionDenied,
+        }
+    }
+
+    /// Block until the child process `pid` exits, and reap it
+    #[event = 17]
+    fn wait(pid: usize) -> Result<ChildStatus, WaitError> {
+        enum ChildStatus {
+            /// The child exited with `ExitReason::Success`
+            Success,
+            /// The child exited with `ExitReason::Failure`
+            Failure,
+            /// The child was terminated because of a cpu exception
+            Faulted { fault: FaultKind },
+        }
+        enum WaitError {
+            /// This pid is not a child of this process, or was already reaped
+            NotAChild,
+        }
+    }
+
+    /// Create a new region of memory that can be shared between processes, returning a
+    /// handle to it
+    ///
+    /// The region is not mapped into this process, use [`shared_map`] to access it. Only
+    /// processes holding a handle to the region can map it, give one to another process
+    /// with [`handle_send`] or [`handle_transfer`]. The region is freed once every handle
+    /// is closed and every mapping is unmapped.
+    #[event = 18]
+    fn shared_create(bytes: usize) -> Result<u64, SharedMemoryError> {
+        enum SharedMemoryError {
+            InvalidLength(usize),
+            OutOfMemory,
+            /// The handle is not a shared memory region
+            InvalidId,
+            MappingMemoryError,
+            /// There is no shared memory mapped at this address
+            NotMapped,
+            /// The handle does not have the `MAP` right, or `WRITE` for a writable mapping
+            PermissionDenied,
+        }
+    }
+
+    /// Map the shared memory region behind `handle` into this process
+    ///
+    /// `handle` needs the [`rights::MAP`] right, and [`rights::WRITE`] too when
+    /// `writable`.
+    ///
+    /// The mapping stays valid after `handle` is closed, until it is unmapped with
+    /// [`shared_unmap`].
+    #[event = 19]
+    fn shared_map(handle: u64, writable: bool) -> Result<*mut u8, SharedMemoryError> {}
+
+    /// Create a new pipe
+    ///
+    /// Bytes written to the `write` handle with [`send`] can be read from the `read`
+    /// handle with [`recv`]. Both block until they can make progress, and `recv`
+    /// returns `0` once every write handle has been closed.
+    #[event = 20]
+    fn pipe_create() -> PipeHandles {
+        struct PipeHandles {
+            read: u64,
+            write: u64,
+        }
+    }
+
+    /// Give `handle` to the child process `pid`
+    ///
+    /// The handle is closed in this process, and the child is sent a
+    /// `HandleUpdateKind::Received` signal with its new id.
+    #[event = 21]
+    fn handle_transfer(handle: u64, pid: usize) -> Result<u64, HandleTransferError> {
+        enum HandleTransferError {
+            InvalidHandle,
+            /// Only pipe and shared memory handles can be transferred
+            NotTransferable,
+            /// This pid is not a child of this process
+            NotAChild,
+            /// The connection is not a connected socket
+            NotAConnection,
+            /// This handle does not have the `TRANSFER` right
+            PermissionDenied,
+        }
+    }
+
+    /// Seconds since the unix epoch
+    #[event = 22]
+    fn now_unix() -> u64;
+
+    /// Nanoseconds since boot, this never goes backwards
+    #[event = 23]
+    fn monotonic_ns() -> u64;
+
+    /// Suspend this thread for at least `ns` nanoseconds
+    #[event = 24]
+    fn sleep_ns(ns: u64) {}
+
+    /// Write the current framebuffer contents to the kernel's serial port
+    ///
+    /// The image is sent as a base64 encoded PPM, see the kernel's log for the markers
+    /// surrounding it. This needs the [`capabilities::DEBUG`] capability.
+    #[event = 25]
+    fn debug_screenshot() -> Result<(), ScreenshotError> {
+        enum ScreenshotError {
+            /// The bootloader did not give the kernel a framebuffer
+            NoFramebuffer,
+            /// There is no serial port to write the image to
+            NoSerialDevice,
+            /// This process does not have the `DEBUG` capability
+            PermissionDenied,
+            /// There is not enough memory to copy the screen into
+            OutOfMemory,
+            /// `buf` is not writable memory in this process
+            InvalidPtr,
+        }
+    }
+
+    /// Control the kernel's sampling profiler
+    ///
+    /// While running, the kernel records where each timer tick interrupted. `Dump` prints
+    /// the most sampled kernel functions to the kernel's log. This needs the
+    /// [`capabilities::DEBUG`] capability.
+    #[event = 26]
+    fn profile(command: ProfileCommand) -> Result<(), ProfileError> {
+        enum ProfileCommand {
+            Start,
+            Stop,
+            Dump,
+        }
+        enum ProfileError {
+            AlreadyRunning,
+            NotRunning,
+            /// The kernel was not built with the `profile` feature
+            ProfilingDisabled,
+            /// This process does not have the `DEBUG` capability
+            PermissionDenied,
+        }
+    }
+
+    /// Print the kernel heap allocations made since `since_generation` that are still live
+    ///
+    /// Returns the current generation, dumping again with it shows only what was allocated
+    /// (and not yet freed) in between.
+    #[event = 27]
+    fn heap_dump(since_generation: u64) -> Result<u64, HeapDumpError> {
+        enum HeapDumpError {
+            /// The kernel was not built with the `heap-tracking` feature
+            TrackingDisabled,
+        }
+    }
+
+    /// Make a new handle to the same object as `handle`, with only the given [`rights`]
+    ///
+    /// The new handle can only have rights that `handle` already has, so this can be used
+    /// to hand out a read-only end to a less trusted process.
+    #[event = 28]
+    fn handle_duplicate(handle: u64, rights: u64) -> Result<u64, HandleDuplicateError> {
+        enum HandleDuplicateError {
+            InvalidHandle,
+            /// Only pipe and shared memory handles can be duplicated
+            NotDuplicable,
+            /// This handle does not have the `DUPLICATE` right, or is missing some of the
+            /// requested rights
+            PermissionDenied,
+            /// The rights mask has unknown bits set
+            InvalidRights,
+        }
+    }
+
+    /// Map the framebuffer into this process, taking it over from the kernel
+    ///
+    /// Only one process can own the framebuffer. Once it is taken the kernel stops drawing
+    /// its consoles or boot screen into it, until the owner gives it back with
+    /// [`framebuffer_release`] or exits. The framebuffer is mapped write-combining, so it
+    /// is fast to write to but slow to read back.
+    ///
+    /// The caller needs [`capabilities::FRAMEBUFFER`], which `init` only gives to the
+    /// services that ask for it.
+    #[event = 29]
+    fn framebuffer_map() -> Result<FramebufferInfo, FramebufferError> {
+        struct FramebufferInfo {
+            ptr: *mut u8,
+            width: u32,
+            height: u32,
+            /// The number of bytes between the start of each row
+            pitch: u32,
+            bits_per_pixel: u8,
+            /// The bit each 8-bit color channel starts at within a pixel
+            red_position: u8,
+            green_position: u8,
+            blue_position: u8,
+        }
+
+        enum FramebufferError {
+            /// The bootloader did not give the kernel a framebuffer
+            NoFramebuffer,
+            /// Another process already owns the framebuffer
+            AlreadyTaken,
+            MappingMemoryError,
+            /// This process does not have [`capabilities::FRAMEBUFFER`]
+            PermissionDenied,
+            /// This process does not own the framebuffer
+            NotOwner,
+        }
+    }
+
+    /// Block this thread while the value at `word` is still `expected`
+    ///
+    /// Returns once woken by `futex_wake`, or right away if the value has already changed.
+    /// This can also return without being woken, so callers must check `word` again.
+    #[event = 30]
+    fn futex_wait(word: *const u32, expected: u32) -> Result<(), FutexError> {
+        enum FutexError {
+            /// `word` is not a readable and 4 byte aligned address
+            InvalidPtr,
+        }
+    }
+
+    /// Wake up to `count` threads waiting in `futex_wait` on `word`, returning how many
+    /// were woken
+    #[event = 31]
+    fn futex_wake(word: *const u32, count: usize) -> Result<usize, FutexError> {}
+
+    /// Get information about the task numbered `index`, for tools like `top`
+    ///
+    /// Tasks are numbered from 0, so every task can be listed by counting up until this
+    /// returns `NoSuchTask`.
+    #[event = 32]
+    fn task_info(index: usize) -> Result<TaskInfo, TaskInfoError> {
+        struct TaskInfo {
+            pid: usize,
+            tid: usize,
+            /// The name of the task's process, padded with zeros
+            name: [u8; 32],
+            state: TaskState,
+            /// See [`NICE_RANGE`]
+            nice: i8,
+            /// How long this task has spent running on the cpu
+            cpu_ns: u64,
+            /// The deepest this task's kernel stack has ever been, in bytes
+            stack_used: u64,
+            /// How large this task's kernel stack is, in bytes
+            stack_len: u64,
+        }
+
+        enum TaskState {
+            Running,
+            Ready,
+            Blocked,
+            Sleeping,
+            Zombie,
+        }
+
+        enum TaskInfoError {
+            NoSuchTask,
+        }
+    }
+
+    /// Change the nice value of every task in the process `pid`
+    ///
+    /// A process can only change itself and its own children, and can't give a task a
+    /// lower nice value than the calling task has.
+    #[event = 33]
+    fn set_nice(pid: usize, nice: i8) -> Result<(), NiceError> {
+        enum NiceError {
+            NoSuchProcess,
+            /// `pid` is not this process or one of its children, or `nice` is lower than
+            /// this task's nice value
+            PermissionDenied,
+            /// `nice` is outside of [`NICE_RANGE`]
+            InvalidNice,
+        }
+    }
+
+    /// Limit which cpus every task in process `pid` may run on, bit `n` of `cpus` is cpu `n`.
+    ///
+    /// A process can only change itself and its own children.
+    #[event = 34]
+    fn set_affinity(pid: usize, cpus: u64) -> Result<(), AffinityError> {
+        enum AffinityError {
+            NoSuchProcess,
+            /// `pid` is not this process or one of its children
+            PermissionDenied,
+            /// `cpus` does not contain any cpu the system has
+            NoUsableCpus,
+        }
+    }
+
+    /// Map the kernel's log ring into this process, and become its logger
+    ///
+    /// The ring is laid out as a `lignan::ring::LogRing`. Only one process can be the
+    /// logger at a time, the ring becomes free again once that process exits. This needs
+    /// the [`capabilities::LOG_RING`] capability.
+    #[event = 35]
+    fn log_ring_attach() -> Result<LogRingMapping, LogRingError> {
+        struct LogRingMapping {
+            ptr: *mut u8,
+            len: usize,
+        }
+
+        enum LogRingError {
+            /// The kernel was unable to set up its log ring, or was built without the
+            /// `log-ring` feature
+            Unavailable,
+            /// Another process is already the logger
+            AlreadyAttached,
+            MappingFailed,
+            /// This process does not have the `LOG_RING` capability
+            PermissionDenied,
+        }
+    }
+
+    /// Start or stop tracing every syscall process `pid` makes into the kernel's log ring
+    ///
+    /// A process can only trace itself and its own children.
+    #[event = 36]
+    fn trace_syscalls(pid: usize, enabled: bool) -> Result<(), TraceError> {
+        enum TraceError {
+            NoSuchProcess,
+            /// `pid` is not this process or one of its children
+            PermissionDenied,
+            /// The kernel was not built with the `syscall-trace` feature
+            TracingDisabled,
+        }
+    }
+
+    /// Print the pages mapped between `start` and `end` in process `pid`'s page tables to
+    /// the kernel's log
+    ///
+    /// Returns how many lines were printed. This needs the [`capabilities::DEBUG`]
+    /// capability.
+    #[event = 37]
+    fn vm_dump(pid: usize, start: usize, end: usize) -> Result<usize, VmDebugError> {
+        enum VmDebugError {
+            NoSuchProcess,
+            /// `start` is after `end`, or one of them is not a canonical address
+            InvalidRange,
+            /// The address is not mapped
+            NotMapped,
+            /// This process does not have the `DEBUG` capability
+            PermissionDenied,
+        }
+    }
+
+    /// Find where `virt` is mapped to in process `pid`'s page tables
+    ///
+    /// This needs the [`capabilities::DEBUG`] capability.
+    #[event = 38]
+    fn vm_translate(pid: usize, virt: usize) -> Result<VmTranslation, VmDebugError> {
+        struct VmTranslation {
+            /// The physical address `virt` maps to
+            phys: u64,
+            /// The size of the page `virt` is in
+            page_size: u64,
+            writable: bool,
+            no_exec: bool,
+            user: bool,
+            global: bool,
+            cache: VmCacheMode,
+        }
+
+        enum VmCacheMode {
+            WriteBack,
+            WriteThrough,
+            WriteCombining,
+            Uncached,
+        }
+    }
+
+    /// Copy the log of the last boot into `buf`, if it ended in a panic
+    ///
+    /// Returns the length of the whole log, which can be longer than `buf`. This needs the
+    /// [`capabilities::LOG_RING`] or [`capabilities::DEBUG`] capability.
+    #[event = 39]
+    fn previous_crash_log(buf: &mut [u8]) -> Result<usize, CrashLogError> {
+        enum CrashLogError {
+            /// The last boot did not panic, or its log did not survive the reboot
+            NoCrashLog,
+            /// `buf` is not writable memory in this process
+            InvalidPtr,
+            /// This process has neither the `LOG_RING` nor the `DEBUG` capability
+            PermissionDenied,
+        }
+    }
+
+    /// Get the hardware resource claim numbered `index`, for listing the hardware inventory
+    ///
+    /// Claims are numbered from 0, so every claim can be listed by counting up until this
+    /// returns `NoSuchClaim`.
+    #[event = 40]
+    fn resource_info(index: usize) -> Result<ResourceInfo, ResourceInfoError> {
+        struct ResourceInfo {
+            /// The driver that claimed the resource, padded with zeros
+            owner: [u8; 32],
+            kind: ResourceKind,
+            /// The first port, physical address, or IRQ line
+            start: u64,
+            /// How many ports or bytes, or 1 for an IRQ line
+            len: u64,
+            /// If other drivers can claim this resource too
+            shared: bool,
+        }
+
+        enum ResourceKind {
+            IoPorts,
+            Mmio,
+            Irq,
+        }
+
+        enum ResourceInfoError {
+            NoSuchClaim,
+        }
+    }
+
+    /// Claim the `len` IO ports starting at `base` for this process, so no other driver
+    /// can claim them
+    ///
+    /// `shared` ports can also be claimed as shared by other drivers, like the command port
+    /// of a chip that drivers each use a part of. The ports stay claimed until they are
+    /// released, or the process exits. This needs the [`capabilities::IO_PORTS`] capability.
+    #[event = 41]
+    fn claim_io_ports(base: u16, len: u16, shared: bool) -> Result<(), IoClaimError> {
+        enum IoClaimError {
+            /// Another driver has already claimed some of the ports
+            AlreadyClaimed,
+            /// `len` is zero, or the ports go past the last port
+            InvalidRange,
+            /// This process does not have the `IO_PORTS` capability
+            PermissionDenied,
+            /// This process has not claimed exactly these ports
+            NotClaimed,
+        }
+    }
+
+    /// Copy the command line the kernel was booted with into `buf`
+    ///
+    /// Returns the length of the whole command line, which can be longer than `buf`.
+    #[event = 42]
+    fn kernel_cmdline(buf: &mut [u8]) -> Result<usize, CmdlineError> {
+        enum CmdlineError {
+            /// `buf` is not writable memory in this process
+            InvalidPtr,
+        }
+    }
+
+    /// Change the resolution of the framebuffer, returning where it is mapped now
+    ///
+    /// Only the process that owns the framebuffer can change it. The old mapping is
+    /// unmapped and the new framebuffer is mapped in its place. Zero for `width` and
+    /// `height` picks the size the display would like to be. If the switch fails the
+    /// previous mode and mapping are kept.
+    #[event = 43]
+    fn framebuffer_set_mode(
+        width: u32,
+        height: u32,
+        bits_per_pixel: u8,
+    ) -> Result<FramebufferInfo, VideoModeError> {
+        enum VideoModeError {
+            /// There is no device that can change modes after boot
+            NotSupported,
+            /// The device can't show this resolution or pixel format
+            InvalidMode,
+            /// Another process owns the framebuffer
+            NotOwner,
+            /// The device failed to switch, the previous mode is still being shown
+            DeviceError,
+            MappingMemoryError,
+        }
+    }
+
+    /// Tell the display that an area of the framebuffer was drawn to
+    ///
+    /// Framebuffers from `framebuffer_set_mode` may only be shown once flushed, the one
+    /// the bootloader set up is always shown and ignores this.
+    #[event = 44]
+    fn framebuffer_flush(x: u32, y: u32, width: u32, height: u32) -> Result<(), VideoModeError> {}
+
+    /// Start, or stop, receiving a [`WaitSignal::SystemEvent`] each time something happens
+    /// to the system
+    #[event = 45]
+    fn system_events(subscribe: bool) {}
+
+    /// Get a [`WaitSignal::TimerUpdate`] once at least `ns` nanoseconds have passed
+    ///
+    /// Unlike [`sleep_ns`] this doesn't block, so a process can wait for a timer and its
+    /// handles at the same time with [`signal_wait`].
+    #[event = 46]
+    fn signal_timer(ns: u64) {}
+
+    /// Get how much physical memory there is, and what is using it
+    ///
+    /// Every count is in 4Kib pages.
+    #[event = 47]
+    fn memory_info() -> MemoryInfo {
+        struct MemoryInfo {
+            /// Every page the kernel can allocate, used or not
+            total_pages: usize,
+            free_pages: usize,
+            /// Pages given back to the host by the memory balloon
+            balloon_pages: usize,
+            /// How many pages the host would like the balloon to hold
+            balloon_target_pages: usize,
+        }
+    }
+
+    /// Copy the initfs file `name` into `buf`
+    ///
+    /// Returns the length of the whole file, which can be longer than `buf`.
+    #[event = 48]
+    fn initfs_read(name: &str, buf: &mut [u8]) -> Result<usize, InitfsError> {
+        enum InitfsError {
+            NotFound,
+            /// The name is not a readable, valid UTF-8 string
+            InvalidName,
+            /// `buf` is not writable memory in this process
+            InvalidPtr,
+        }
+    }
+
+    /// Give the framebuffer back to the kernel, which draws its consoles or boot screen again
+    ///
+    /// The framebuffer is unmapped from this process, so the pointer [`framebuffer_map`]
+    /// returned must not be used after this. It is given back the same way when its owner
+    /// exits or crashes.
+    #[event = 50]
+    fn framebuffer_release() -> Result<(), FramebufferError> {}
+
+    /// Block until there is data to `recv` on the connection `handle`, or `timeout_ns` has
+    /// passed
+    ///
+    /// A `timeout_ns` of `u64::MAX` waits forever. Pipes can't be waited on.
+    #[event = 51]
+    fn handle_wait(handle: u64, timeout_ns: u64) -> Result<(), HandleWaitError> {
+        enum HandleWaitError {
+            InvalidHandle,
+            /// This handle does not have the `READ` right
+            PermissionDenied,
+            /// `handle` is not a connection
+            NotSupported,
+            /// The other side of the connection has gone away
+            Disconnected,
+            /// Nothing arrived before `timeout_ns` passed
+            TimedOut,
+        }
+    }
+
+    /// The size in bytes of the shared memory region behind `handle`
+    ///
+    /// This is rounded up to whole pages, so it can be larger than the region was
+    /// created with.
+    #[event = 52]
+    fn shared_len(handle: u64) -> Result<usize, SharedMemoryError> {}
+
+    /// Switch the layout keys are typed with to `name`
+    ///
+    /// `us` is built into the kernel, other layouts are read from the initfs file
+    /// `<name>.keymap`. This needs the [`capabilities::SYSTEM`] capability.
+    #[event = 53]
+    fn keymap_set(name: &str) -> Result<(), KeymapError> {
+        enum KeymapError {
+            NotFound,
+            /// This process does not have the `SYSTEM` capability
+            PermissionDenied,
+            /// The name is not a readable, valid UTF-8 string
+            InvalidName,
+            /// The layout file is broken at `line`
+            Malformed {
+                line: usize,
+            },
+        }
+    }
+
+    /// Give `handle` to the process on the other end of the socket `connection`,
+    /// returning its id in that process
+    ///
+    /// The handle is closed in this process. The receiver is not signaled, so the new id
+    /// should be sent to it over `connection`, where it can check the handle really came
+    /// from this connection with [`handle_origin`].
+    #[event = 54]
+    fn handle_send(handle: u64, connection: u64) -> Result<u64, HandleTransferError> {}
+
+    /// The connection `handle` was received over with [`handle_send`]
+    #[event = 55]
+    fn handle_origin(handle: u64) -> Result<u64, HandleOriginError> {
+        enum HandleOriginError {
+            InvalidHandle,
+            /// The handle was made by this process, or given to it by its parent
+            NotSent,
+        }
+    }
+
+    /// Unmap the shared memory mapping at `ptr`, which [`shared_map`] returned
+    #[event = 56]
+    fn shared_unmap(ptr: *mut u8) -> Result<(), SharedMemoryError> {}
+
+    /// Spawn a program from the initfs as a child of this process, giving it some of this
+    /// process's [`capabilities`]
+    ///
+    /// The child has its capabilities from its first instruction. Returns the pid of the
+    /// new child process.
+    #[event = 57]
+    fn spawn_with(name: &str, capabilities: u64) -> Result<usize, SpawnError> {}
+
+    /// Copy the current framebuffer contents into `buf` as a PPM image
+    ///
+    /// Returns the length of the whole image, which can be longer than `buf`. This needs
+    /// the [`capabilities::DEBUG`] capability.
+    #[event = 58]
+    fn debug_screenshot_read(buf: &mut [u8]) -> Result<usize, ScreenshotError> {}
+
+    /// Get the disk the kernel drives numbered `index`, like a USB stick
+    ///
+    /// Disks are numbered from 0, so every disk can be listed by counting up until this
+    /// returns `NoSuchDisk`. This needs the [`capabilities::DISKS`] capability.
+    #[event = 59]
+    fn disk_info(index: usize) -> Result<DiskInfo, DiskError> {
+        struct DiskInfo {
+            /// The disk's vendor and product, padded with zeros
+            model: [u8; 32],
+            blocks: u64,
+            block_size: u64,
+        }
+
+        enum DiskError {
+            NoSuchDisk,
+            /// This process does not have the `DISKS` capability
+            PermissionDenied,
+            /// `buf` is not writable memory in this process
+            InvalidPtr,
+            /// `buf` is not whole blocks, is longer than [`MAX_DISK_READ`], or goes past
+            /// the end of the disk
+            OutOfRange,
+            /// The disk did not answer, or said the read failed
+            ReadFailed,
+        }
+    }
+
+    /// Read the blocks of the disk `index` starting at `block` into `buf`
+    ///
+    /// This needs the [`capabilities::DISKS`] capability.
+    #[event = 60]
+    fn disk_read(index: usize, block: u64, buf: &mut [u8]) -> Result<(), DiskError> {}
+
+    /// Give up a claim made with [`claim_io_ports`], with the same `base` and `len`
+    #[event = 61]
+    fn release_io_ports(base: u16, len: u16) -> Result<(), IoClaimError> {}
+
     #[event = 69]
     fn debug_msg(msg: &str) -> Result<(), DebugMsgError> {
         enum DebugMsgError {
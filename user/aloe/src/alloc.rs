@@ -23,7 +23,7 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
-use crate::sync::Mutex;
+use crate::sync::{Mutex, PoisonError};
 use core::{
     alloc::{GlobalAlloc, Layout},
     fmt::{Debug, Display},
@@ -88,6 +88,9 @@ struct BuddyAllocator {
     region_end: NonNull<u8>,
 }
 
+// The allocator owns the memory its pointers point into, so it can move between threads
+unsafe impl Send for BuddyAllocator {}
+
 impl BuddyAllocator {
     const fn new(ptr: NonNull<u8>, len: usize) -> Self {
         let buddy_allocator = Self {
@@ -415,7 +418,7 @@ impl MemoryMapRegion {
     }
 
     pub unsafe fn alloc(&self, layout: Layout) -> Result<*mut u8> {
-        let mut alloc_lock = self.alloc.lock();
+        let mut alloc_lock = self.alloc.lock().unwrap_or_else(PoisonError::into_inner);
 
         // Try to create the region if one doesn't exist
         if alloc_lock.is_none() {
@@ -480,7 +483,7 @@ impl MemoryMapRegion {
     }
 
     pub unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) -> Result<()> {
-        let mut alloc_lock = self.alloc.lock();
+        let mut alloc_lock = self.alloc.lock().unwrap_or_else(PoisonError::into_inner);
 
         let Some(ref mut inner) = *alloc_lock else {
             return Err(MemoryAllocationError::NotAllocated);
@@ -514,7 +517,7 @@ impl Drop for MemoryMapRegion {
             }
         }
 
-        let alloc_lock = self.alloc.lock();
+        let alloc_lock = self.alloc.lock().unwrap_or_else(PoisonError::into_inner);
         let Some(ref alloc) = *alloc_lock else {
             return;
         };
@@ -0,0 +1,73 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use vera_portal::{CmdlineError, sys_client::kernel_cmdline};
+
+/// The most bytes of the command line that are kept, the bootloader never passes more
+const MAX_CMDLINE_LEN: usize = 128;
+
+/// The command line the kernel was booted with
+///
+/// Options are separated by whitespace, and are either a flag (`fs.readonly`) or a
+/// value (`serial.baud=9600`).
+#[derive(Debug, Clone, Copy)]
+pub struct Cmdline {
+    bytes: [u8; MAX_CMDLINE_LEN],
+    len: usize,
+}
+
+impl Cmdline {
+    /// Read the command line from the kernel
+    pub fn read() -> Result<Self, CmdlineError> {
+        let mut bytes = [0; MAX_CMDLINE_LEN];
+        let len = kernel_cmdline(&mut bytes)?.min(MAX_CMDLINE_LEN);
+
+        Ok(Self { bytes, len })
+    }
+
+    /// The full command line
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+
+    /// Each option in the command line
+    pub fn options(&self) -> impl Iterator<Item = &str> {
+        self.as_str().split_whitespace()
+    }
+
+    /// Is the flag `name` set?
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.options().any(|option| option == name)
+    }
+
+    /// The value of the option `name`, if it was given one
+    pub fn value_of(&self, name: &str) -> Option<&str> {
+        self.options().find_map(|option| {
+            option
+                .split_once('=')
+                .and_then(|(key, value)| (key == name).then_some(value))
+        })
+    }
+}
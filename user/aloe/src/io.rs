@@ -0,0 +1,242 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Buffered standard output for programs, written to the console server.
+//!
+//! [`print!`](crate::print) and [`println!`](crate::println) go to [`stdout`], and
+//! [`eprint!`](crate::eprint) and [`eprintln!`](crate::eprintln) to [`stderr`]. Both are
+//! line buffered, so each line is sent to the console in one message instead of one per
+//! write, and stdout is flushed before anything goes to stderr so the two stay in order.
+//! Whatever is left is flushed when `main` returns.
+//!
+//! The console is connected to the first time a line is flushed, and if that fails the
+//! text goes to the kernel's debug output instead so it is never lost. The console server
+//! itself must not print, as it would wait forever to connect to itself.
+
+extern crate alloc;
+
+use crate::{ipc::QuantumGlue, sync::Mutex};
+use alloc::{string::String, vec::Vec};
+use console_portal::ConsolePortalClient;
+use core::fmt::Write;
+use vera_portal::sys_client::debug_msg;
+
+/// The most bytes held before they are written even without a newline
+const BUFFER_CAPACITY: usize = 1024;
+
+static STDOUT: Mutex<LineBuffer> = Mutex::new(LineBuffer::new());
+static STDERR: Mutex<LineBuffer> = Mutex::new(LineBuffer::new());
+static CONSOLE: Mutex<Console> = Mutex::new(Console::Disconnected);
+
+/// The connection to the console server
+enum Console {
+    /// Nothing was written yet
+    Disconnected,
+    Connected(ConsolePortalClient<QuantumGlue>),
+    /// The console could not be reached, so output goes to the kernel's debug output
+    Unavailable,
+}
+
+// The client's connection callbacks aren't `Send`, but the connection is only ever used
+// behind `CONSOLE`'s lock and programs have a single thread
+unsafe impl Send for Console {}
+
+impl Console {
+    fn write(&mut self, bytes: &[u8]) {
+        if let Console::Disconnected = self {
+            *self = match QuantumGlue::connect_to("console") {
+                Ok(glue) => Console::Connected(ConsolePortalClient::new(glue)),
+                Err(_) => Console::Unavailable,
+            };
+        }
+
+        if let Console::Connected(console) = self {
+            if console.write_blocking(Vec::from(bytes)).is_ok() {
+                return;
+            }
+            *self = Console::Unavailable;
+        }
+
+        let _ = debug_msg(&String::from_utf8_lossy(bytes));
+    }
+}
+
+/// Bytes waiting to be written to the console
+struct LineBuffer {
+    bytes: Vec<u8>,
+}
+
+impl LineBuffer {
+    const fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    /// Buffer `bytes`, writing out every complete line
+    fn write(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+
+        match self.bytes.iter().rposition(|&byte| byte == b'\n') {
+            _ if self.bytes.len() >= BUFFER_CAPACITY => self.flush(),
+            Some(last_newline) => {
+                let rest = self.bytes.split_off(last_newline + 1);
+                self.flush();
+                self.bytes = rest;
+            }
+            None => (),
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.bytes.is_empty() {
+            return;
+        }
+
+        lock(&CONSOLE).write(&self.bytes);
+        self.bytes.clear();
+    }
+}
+
+/// Output is best effort, a panic while printing shouldn't stop anyone else from printing
+fn lock<T>(mutex: &Mutex<T>) -> crate::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(crate::sync::PoisonError::into_inner)
+}
+
+/// A handle to the program's standard output
+#[derive(Clone, Copy, Debug)]
+pub struct Stdout(());
+
+/// A handle to the program's standard error
+#[derive(Clone, Copy, Debug)]
+pub struct Stderr(());
+
+/// Get a handle to the program's standard output
+pub const fn stdout() -> Stdout {
+    Stdout(())
+}
+
+/// Get a handle to the program's standard error
+pub const fn stderr() -> Stderr {
+    Stderr(())
+}
+
+impl Stdout {
+    /// Buffer `bytes`, sending every complete line to the console
+    pub fn write_bytes(&self, bytes: &[u8]) {
+        lock(&STDOUT).write(bytes);
+    }
+
+    /// Send everything buffered to the console
+    pub fn flush(&self) {
+        lock(&STDOUT).flush();
+    }
+}
+
+impl Stderr {
+    /// Buffer `bytes`, sending every complete line to the console
+    pub fn write_bytes(&self, bytes: &[u8]) {
+        // Anything printed before this should show up before it
+        stdout().flush();
+        lock(&STDERR).write(bytes);
+    }
+
+    /// Send everything buffered to the console
+    pub fn flush(&self) {
+        lock(&STDERR).flush();
+    }
+}
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl Write for Stderr {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Flush stdout and stderr, called when `main` returns.
+///
+/// Locks that are already held are skipped, so this is safe from the panic handler.
+pub fn flush_all() {
+    for buffer in [&STDOUT, &STDERR] {
+        if let Ok(mut buffer) = buffer.try_lock() {
+            buffer.flush();
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn priv_print(args: core::fmt::Arguments) {
+    // Formatting into one string first keeps the whole message on one line of output
+    let _ = stdout().write_str(&alloc::fmt::format(args));
+}
+
+#[doc(hidden)]
+pub fn priv_eprint(args: core::fmt::Arguments) {
+    let _ = stderr().write_str(&alloc::fmt::format(args));
+}
+
+/// Print to the console, like `std`'s `print!`
+///
+/// Output is line buffered, call `aloe::io::stdout().flush()` to show a partial line.
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {{
+        $crate::io::priv_print(format_args!($($arg)*));
+    }};
+}
+
+/// Print a line to the console, like `std`'s `println!`
+#[macro_export]
+macro_rules! println {
+    () => {{ $crate::print!("\n") }};
+    ($($arg:tt)*) => {{
+        $crate::io::priv_print(format_args!("{}\n", format_args!($($arg)*)));
+    }};
+}
+
+/// Print to the console's standard error, like `std`'s `eprint!`
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => {{
+        $crate::io::priv_eprint(format_args!($($arg)*));
+    }};
+}
+
+/// Print a line to the console's standard error, like `std`'s `eprintln!`
+#[macro_export]
+macro_rules! eprintln {
+    () => {{ $crate::eprint!("\n") }};
+    ($($arg:tt)*) => {{
+        $crate::io::priv_eprint(format_args!("{}\n", format_args!($($arg)*)));
+    }};
+}
@@ -25,18 +25,55 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 extern crate alloc;
 
-use portal::ipc::{IpcError, IpcResult};
+use crate::time::cycles;
+use alloc::vec::Vec;
+use portal::ipc::{
+    IpcError, IpcResult,
+    frame::{FRAME_HEADER_SIZE, FrameDecoder, MAX_FRAME_SIZE, encode_frames},
+};
 use vera_portal::{
-    ConnectHandleError, HandleUpdateKind, RecvHandleError, SendHandleError, ServeHandleError,
-    WaitSignal,
-    sys_client::{close, connect, recv, send, serve, yield_now},
+    ConnectHandleError, HandleUpdateKind, HandleWaitError, RecvHandleError, SendHandleError,
+    ServeHandleError, WaitSignal,
+    sys_client::{
+        close, connect, handle_wait, monotonic_ns, recv, send, serve, sleep_ns, yield_now,
+    },
 };
 
-pub struct QuantumGlue(u64);
+/// IPC glue over a kernel handle
+///
+/// Everything sent is wrapped in `portal::ipc::frame` frames, so a peer that sends
+/// garbage can't desync the connection or grow our receive buffer without bound.
+pub struct QuantumGlue {
+    handle: u64,
+    decoder: FrameDecoder,
+    timestamps: GlueTimestamps,
+}
+
+/// Cycle counter readings from the last transfers on a connection
+///
+/// These are taken right around the syscalls, so they measure time spent in the kernel
+/// and the peer instead of in serialization.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlueTimestamps {
+    /// Right before the last send
+    pub send_start: u64,
+    /// Right after the last send
+    pub send_end: u64,
+    /// Right after the last bytes were received
+    pub recv: u64,
+}
 
 impl QuantumGlue {
     pub const fn new(handle: u64) -> Self {
-        Self(handle)
+        Self {
+            handle,
+            decoder: FrameDecoder::new(),
+            timestamps: GlueTimestamps {
+                send_start: 0,
+                send_end: 0,
+                recv: 0,
+            },
+        }
     }
 
     /// A blocking connect to the service
@@ -49,23 +86,104 @@ impl QuantumGlue {
                 Err(ConnectHandleError::EndpointDoesNotExist) => {
                     yield_now();
                 }
+                Err(ConnectHandleError::InvalidName) => return Err(IpcError::GlueError),
             }
         };
 
         Ok(Self::new(handle))
     }
+
+    /// Connect to the service without waiting for it to start
+    ///
+    /// Returns `IpcError::Disconnected` if nothing is serving `service` yet.
+    pub fn try_connect_to(service: &str) -> IpcResult<Self> {
+        match connect(service) {
+            Ok(handle) => Ok(Self::new(handle)),
+            Err(ConnectHandleError::EndpointDoesNotExist) => Err(IpcError::Disconnected),
+            Err(ConnectHandleError::InvalidName) => Err(IpcError::GlueError),
+        }
+    }
+
+    /// The kernel handle this glue sends and receives on
+    pub fn handle(&self) -> u64 {
+        self.handle
+    }
+
+    /// The number of received bytes that were dropped for not being part of a valid
+    /// frame.
+    pub fn dropped_bytes(&self) -> usize {
+        self.decoder.dropped_bytes()
+    }
+
+    /// When the last transfers on this connection happened
+    pub fn timestamps(&self) -> GlueTimestamps {
+        self.timestamps
+    }
 }
 
 impl portal::ipc::IpcGlue for QuantumGlue {
     fn disconnect(&mut self) {
-        close(self.0);
+        close(self.handle);
+    }
+
+    fn reconnect(&mut self, service: &str) -> IpcResult<()> {
+        close(self.handle);
+
+        match connect(service) {
+            Ok(handle) => {
+                *self = Self::new(handle);
+                Ok(())
+            }
+            Err(ConnectHandleError::EndpointDoesNotExist) => Err(IpcError::Disconnected),
+            Err(ConnectHandleError::InvalidName) => Err(IpcError::GlueError),
+        }
+    }
+
+    /// The kernel wakes us when a response arrives, or when the timeout passes
+    fn socket_wait_for(&self, timeout_ns: Option<u64>) -> IpcResult<()> {
+        // Bytes that were already received are still waiting in the decoder
+        if self.decoder.ready() != 0 {
+            return Ok(());
+        }
+
+        match handle_wait(self.handle, timeout_ns.unwrap_or(u64::MAX)) {
+            Ok(()) | Err(HandleWaitError::TimedOut) => Ok(()),
+            Err(HandleWaitError::InvalidHandle | HandleWaitError::Disconnected) => {
+                Err(IpcError::Disconnected)
+            }
+            Err(HandleWaitError::PermissionDenied) => Err(IpcError::GlueError),
+            Err(HandleWaitError::NotSupported) => {
+                yield_now();
+                Ok(())
+            }
+        }
+    }
+
+    fn now_ns(&self) -> Option<u64> {
+        Some(monotonic_ns())
+    }
+
+    fn sleep_ns(&self, ns: u64) {
+        sleep_ns(ns);
     }
 }
 
 impl portal::ipc::Sender for QuantumGlue {
     fn send(&mut self, bytes: &[u8]) -> IpcResult<()> {
-        send(self.0, bytes).map_err(|send_err| match send_err {
-            SendHandleError::InvalidHandle | SendHandleError::SendFailed => IpcError::GlueError,
+        let mut framed = Vec::with_capacity(bytes.len() + FRAME_HEADER_SIZE);
+        encode_frames(bytes, MAX_FRAME_SIZE, &mut framed);
+
+        self.timestamps.send_start = cycles();
+        let result = send(self.handle, &framed);
+        self.timestamps.send_end = cycles();
+
+        result.map_err(|send_err| match send_err {
+            SendHandleError::InvalidHandle
+            | SendHandleError::SendFailed
+            | SendHandleError::BrokenPipe => IpcError::Disconnected,
+            SendHandleError::PermissionDenied
+            | SendHandleError::InvalidPtr
+            | SendHandleError::InvalidLength(_) => IpcError::GlueError,
             SendHandleError::WouldBlock => IpcError::NotReady,
         })?;
 
@@ -75,10 +193,28 @@ impl portal::ipc::Sender for QuantumGlue {
 
 impl portal::ipc::Receiver for QuantumGlue {
     fn recv(&mut self, bytes: &mut [u8]) -> IpcResult<usize> {
-        recv(self.0, bytes).map_err(|recv_err| match recv_err {
-            RecvHandleError::InvalidHandle | RecvHandleError::RecvFailed => IpcError::GlueError,
-            RecvHandleError::WouldBlock => IpcError::NotReady,
-        })
+        while self.decoder.ready() == 0 {
+            let mut raw = [0; 256];
+
+            let len = recv(self.handle, &mut raw).map_err(|recv_err| match recv_err {
+                RecvHandleError::InvalidHandle | RecvHandleError::RecvFailed => {
+                    IpcError::Disconnected
+                }
+                RecvHandleError::PermissionDenied | RecvHandleError::InvalidPtr => {
+                    IpcError::GlueError
+                }
+                RecvHandleError::WouldBlock => IpcError::NotReady,
+            })?;
+
+            if len == 0 {
+                return Ok(0);
+            }
+
+            self.timestamps.recv = cycles();
+            self.decoder.push(&raw[..len]);
+        }
+
+        Ok(self.decoder.read(bytes))
     }
 }
 
@@ -96,9 +232,15 @@ impl<T> QuantumHost<T> {
                 mapping: alloc::collections::BTreeMap::new(),
             }),
             Err(ServeHandleError::AlreadyBound) => Err(IpcError::AlreadyUsed),
+            Err(ServeHandleError::InvalidName) => Err(IpcError::GlueError),
         }
     }
 
+    /// The client connected on `handle`, if it is still connected
+    pub fn client_mut(&mut self, handle: u64) -> Option<&mut T> {
+        self.mapping.get_mut(&handle)
+    }
+
     pub fn service_signal<N, R, W, D>(
         &mut self,
         signal: WaitSignal,
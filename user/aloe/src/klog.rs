@@ -0,0 +1,74 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use core::sync::atomic::Ordering;
+use lignan::ring::LogRing;
+use vera_portal::{
+    LogRingError,
+    sys_client::{futex_wait, log_ring_attach},
+};
+
+/// The kernel's log, read straight out of the ring the kernel streams it into
+#[derive(Debug)]
+pub struct KernelLog {
+    ring: LogRing,
+}
+
+impl KernelLog {
+    /// Attach to the kernel's log ring
+    ///
+    /// Only one process can be attached at a time.
+    pub fn attach() -> Result<Self, LogRingError> {
+        let mapping = log_ring_attach()?;
+        let ring = unsafe { LogRing::from_raw(mapping.ptr, mapping.len) }
+            .ok_or(LogRingError::MappingFailed)?;
+
+        Ok(Self { ring })
+    }
+
+    /// Read the log into `buf` without waiting, returning how many bytes were read
+    pub fn try_read(&self, buf: &mut [u8]) -> usize {
+        self.ring.read(buf)
+    }
+
+    /// Read the log into `buf`, waiting until the kernel writes something if it is empty
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        loop {
+            let seen = self.ring.futex().load(Ordering::Acquire);
+
+            let read = self.ring.read(buf);
+            if read != 0 || buf.is_empty() {
+                return read;
+            }
+
+            let _ = futex_wait(self.ring.futex().as_ptr(), seen);
+        }
+    }
+
+    /// How many bytes of log the kernel threw away because we fell behind
+    pub fn dropped(&self) -> u64 {
+        self.ring.dropped()
+    }
+}
@@ -26,9 +26,17 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 #![no_std]
 
 pub mod alloc;
+pub mod cmdline;
 pub mod debug;
+pub mod io;
 pub mod ipc;
+pub mod klog;
+pub mod pipe;
+pub mod process;
+pub mod reactor;
+pub mod shared;
 pub mod sync;
+pub mod time;
 pub mod uio;
 
 // Import syscall interface
@@ -70,7 +78,9 @@ macro_rules! tiny_std {
         #[cfg(not(test))]
         #[panic_handler]
         fn panic(info: &core::panic::PanicInfo) -> ! {
+            $crate::io::flush_all();
             $crate::dbugln!("{}", info);
+            $crate::process::run_panic_hook();
             $crate::exit($crate::ExitReason::Failure);
         }
 
@@ -89,6 +99,7 @@ macro_rules! tiny_std {
 
             let main_result = main();
             let exit_status = $crate::QuantumTermination::exit_status(main_result);
+            $crate::io::flush_all();
 
             $crate::exit(exit_status);
         }
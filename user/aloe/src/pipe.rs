@@ -0,0 +1,98 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::process::Child;
+use vera_portal::{
+    HandleTransferError, PipeHandles, RecvHandleError, SendHandleError,
+    sys_client::{close, handle_transfer, pipe_create, recv, send},
+};
+
+/// The read end of a pipe
+#[derive(Debug)]
+pub struct PipeReader(u64);
+
+/// The write end of a pipe
+#[derive(Debug)]
+pub struct PipeWriter(u64);
+
+/// Create a new pipe, returning both of its ends
+pub fn pipe() -> (PipeReader, PipeWriter) {
+    let PipeHandles { read, write } = pipe_create();
+    (PipeReader(read), PipeWriter(write))
+}
+
+impl PipeReader {
+    /// Take ownership of a read handle, for example one received from a parent
+    pub const fn from_handle(handle: u64) -> Self {
+        Self(handle)
+    }
+
+    /// Block until there are bytes in the pipe, and read them into `buf`
+    ///
+    /// Returns `Ok(0)` once every writer has closed and the pipe is empty.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, RecvHandleError> {
+        recv(self.0, buf)
+    }
+
+    /// Give this end of the pipe to `child`, returning its handle in the child
+    pub fn give_to(self, child: &Child) -> Result<u64, HandleTransferError> {
+        let handle = handle_transfer(self.0, child.pid())?;
+        core::mem::forget(self);
+
+        Ok(handle)
+    }
+}
+
+impl PipeWriter {
+    /// Take ownership of a write handle, for example one received from a parent
+    pub const fn from_handle(handle: u64) -> Self {
+        Self(handle)
+    }
+
+    /// Block until all of `buf` has been written to the pipe
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize, SendHandleError> {
+        send(self.0, buf)
+    }
+
+    /// Give this end of the pipe to `child`, returning its handle in the child
+    pub fn give_to(self, child: &Child) -> Result<u64, HandleTransferError> {
+        let handle = handle_transfer(self.0, child.pid())?;
+        core::mem::forget(self);
+
+        Ok(handle)
+    }
+}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        close(self.0);
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        close(self.0);
+    }
+}
@@ -0,0 +1,103 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::sync::atomic::{AtomicPtr, Ordering};
+use vera_portal::{
+    ChildStatus, InitfsError, SpawnError, TaskInfo, WaitError,
+    sys_client::{initfs_read, spawn, task_info, wait},
+};
+
+/// A handle to a spawned child process
+#[derive(Debug)]
+pub struct Child {
+    pid: usize,
+}
+
+impl Child {
+    /// Spawn the program `name` from the initfs as a child of this process
+    pub fn spawn(name: &str) -> Result<Self, SpawnError> {
+        spawn(name).map(|pid| Self { pid })
+    }
+
+    /// The pid of this child
+    pub const fn pid(&self) -> usize {
+        self.pid
+    }
+
+    /// Block until this child exits, and reap it
+    pub fn wait(self) -> Result<ChildStatus, WaitError> {
+        wait(self.pid)
+    }
+}
+
+/// Spawn `name` and wait for it to exit, returning `true` if it exited successfully.
+///
+/// This is the building block for `prog && other` style sequences.
+pub fn run(name: &str) -> bool {
+    Child::spawn(name)
+        .ok()
+        .and_then(|child| child.wait().ok())
+        .is_some_and(|status| matches!(status, ChildStatus::Success))
+}
+
+/// Get information about every task on the system
+pub fn tasks() -> impl Iterator<Item = TaskInfo> {
+    (0..).map_while(|index| task_info(index).ok())
+}
+
+/// Read the whole initfs file `name`
+pub fn initfs_file(name: &str) -> Result<Vec<u8>, InitfsError> {
+    let len = initfs_read(name, &mut [])?;
+    let mut contents = vec![0; len];
+    initfs_read(name, &mut contents)?;
+
+    Ok(contents)
+}
+
+/// Run by the panic handler, see [`set_panic_hook`]
+static PANIC_HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Run `hook` if this process panics, right before it exits.
+///
+/// The panic could have happened anywhere, so `hook` can't expect anything it looks at to
+/// be left in a consistent state.
+pub fn set_panic_hook(hook: fn()) {
+    PANIC_HOOK.store(hook as *mut (), Ordering::Release);
+}
+
+#[doc(hidden)]
+pub fn run_panic_hook() {
+    // Taken out first, so a panic inside of the hook doesn't run it again
+    let hook = PANIC_HOOK.swap(core::ptr::null_mut(), Ordering::AcqRel);
+    if hook.is_null() {
+        return;
+    }
+
+    let hook: fn() = unsafe { core::mem::transmute(hook) };
+    hook();
+}
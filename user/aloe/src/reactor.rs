@@ -0,0 +1,155 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+//! Wakes [`chloroplast`] tasks when the kernel has something for them.
+//!
+//! A program has a single stream of wait signals, so when every task is waiting the
+//! runtime from [`runtime`] parks in [`park`] until the next signal arrives. Timers
+//! from [`crate::time::sleep_async`] are waited on with `signal_timer`, and every
+//! other signal is handed to tasks awaiting [`next_signal`].
+
+extern crate alloc;
+
+use crate::sync::{Mutex, MutexGuard, PoisonError};
+use alloc::{collections::vec_deque::VecDeque, vec::Vec};
+use chloroplast::Chloroplast;
+use core::{
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use vera_portal::{
+    WaitSignal,
+    sys_client::{monotonic_ns, signal_timer, signal_wait},
+};
+
+/// Sleeping tasks, and the monotonic time to wake them at
+static TIMERS: Mutex<Vec<(u64, Waker)>> = Mutex::new(Vec::new());
+/// The deadline of the kernel timer we are waiting on, if any
+static ARMED_DEADLINE: Mutex<Option<u64>> = Mutex::new(None);
+/// Signals no task has taken yet
+static SIGNALS: Mutex<VecDeque<WaitSignal>> = Mutex::new(VecDeque::new());
+/// Tasks waiting for the next signal
+static SIGNAL_WAITERS: Mutex<Vec<Waker>> = Mutex::new(Vec::new());
+
+/// Nobody should panic while holding these, but if they do the queues are still usable
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A runtime that parks the thread with [`park`] when every task is waiting
+pub fn runtime() -> Chloroplast {
+    Chloroplast::with_idle(park)
+}
+
+/// Wake `waker` once the monotonic clock reaches `deadline_ns`
+pub(crate) fn wake_at(deadline_ns: u64, waker: &Waker) {
+    let mut timers = lock(&TIMERS);
+
+    // A timer polled again before its deadline is already waiting
+    if timers
+        .iter()
+        .any(|(deadline, timer)| *deadline == deadline_ns && timer.will_wake(waker))
+    {
+        return;
+    }
+
+    timers.push((deadline_ns, waker.clone()));
+}
+
+/// Wake every timer that went off, returning if there were any
+fn wake_timers(now: u64) -> bool {
+    let mut woke_any = false;
+
+    lock(&TIMERS).retain(|(deadline, waker)| {
+        if *deadline > now {
+            return true;
+        }
+
+        waker.wake_by_ref();
+        woke_any = true;
+        false
+    });
+
+    woke_any
+}
+
+/// Block until something might wake a task.
+///
+/// This is the runtime's idle function, it should only be called when no task can make
+/// progress.
+pub fn park() {
+    let now = monotonic_ns();
+    if wake_timers(now) {
+        return;
+    }
+
+    // Ask the kernel to wake us for the next timer, unless it already will
+    let next_deadline = lock(&TIMERS).iter().map(|(deadline, _)| *deadline).min();
+    if let Some(deadline) = next_deadline {
+        let mut armed = lock(&ARMED_DEADLINE);
+        if armed.is_none_or(|armed| armed > deadline) {
+            signal_timer(deadline - now);
+            *armed = Some(deadline);
+        }
+    }
+
+    match signal_wait() {
+        WaitSignal::TimerUpdate { .. } => {
+            *lock(&ARMED_DEADLINE) = None;
+            wake_timers(monotonic_ns());
+        }
+        signal => {
+            lock(&SIGNALS).push_back(signal);
+            lock(&SIGNAL_WAITERS)
+                .drain(..)
+                .for_each(|waker| waker.wake());
+        }
+    }
+}
+
+/// A future for the next wait signal, see [`next_signal`]
+#[derive(Debug)]
+pub struct NextSignal(());
+
+impl Future for NextSignal {
+    type Output = WaitSignal;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(signal) = lock(&SIGNALS).pop_front() {
+            return Poll::Ready(signal);
+        }
+
+        lock(&SIGNAL_WAITERS).push(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Wait for the next wait signal without blocking the other tasks.
+///
+/// This is the async version of `signal_wait`, and each signal is only given to one task.
+/// Timer signals are used by the runtime itself and never returned.
+pub fn next_signal() -> NextSignal {
+    NextSignal(())
+}
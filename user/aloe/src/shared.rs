@@ -0,0 +1,110 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use vera_portal::{
+    HandleTransferError, SharedMemoryError,
+    sys_client::{close, handle_send, shared_create, shared_len, shared_map, shared_unmap},
+};
+
+/// A shared memory region mapped into this process
+///
+/// The region is unmapped when this is dropped, and freed by the kernel once no other
+/// process has it mapped or holds a handle to it.
+#[derive(Debug)]
+pub struct SharedRegion {
+    /// Our handle to the region, until it is sent to another process
+    handle: Option<u64>,
+    ptr: *mut u8,
+    len: usize,
+    writable: bool,
+}
+
+impl SharedRegion {
+    /// Create a new shared memory region of `len` bytes, and map it into this process
+    pub fn create(len: usize) -> Result<Self, SharedMemoryError> {
+        let handle = shared_create(len)?;
+        Self::map(handle, len, true).inspect_err(|_| close(handle))
+    }
+
+    /// Map the shared memory region behind `handle` into this process
+    ///
+    /// `len` must not be larger than the region was created with.
+    pub fn map(handle: u64, len: usize, writable: bool) -> Result<Self, SharedMemoryError> {
+        let ptr = shared_map(handle, writable)?;
+
+        Ok(Self {
+            handle: Some(handle),
+            ptr,
+            len,
+            writable,
+        })
+    }
+
+    /// Map all of the shared memory region behind `handle` into this process
+    ///
+    /// Unlike `map`, the length comes from the kernel, so it can be trusted when the
+    /// handle came from another process.
+    pub fn open(handle: u64, writable: bool) -> Result<Self, SharedMemoryError> {
+        let len = shared_len(handle)?;
+        Self::map(handle, len, writable)
+    }
+
+    /// Give the region to the process on the other end of `connection`, returning the
+    /// handle it can map the region with
+    ///
+    /// The region stays mapped here, but can't be sent again.
+    pub fn send(&mut self, connection: u64) -> Result<u64, HandleTransferError> {
+        let handle = self.handle.ok_or(HandleTransferError::InvalidHandle)?;
+        let sent = handle_send(handle, connection)?;
+        self.handle = None;
+
+        Ok(sent)
+    }
+
+    /// The amount of bytes in this region
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Get the contents of this region
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// Get the contents of this region mutably, if it was mapped writable
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        self.writable
+            .then(|| unsafe { core::slice::from_raw_parts_mut(self.ptr, self.len) })
+    }
+}
+
+impl Drop for SharedRegion {
+    fn drop(&mut self) {
+        let _ = shared_unmap(self.ptr);
+        if let Some(handle) = self.handle {
+            close(handle);
+        }
+    }
+}
@@ -23,83 +23,293 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
+//! Locks for the threads of a process, built on the kernel's futexes.
+//!
+//! [`Mutex`] spins for a little while before blocking, since most critical sections are
+//! short enough that the owner lets go before a trip through the scheduler would finish.
+//! How long it spins adapts to how long the lock has recently been held.
+//!
+//! Like `std`, a lock held while its thread panics is poisoned. Processes abort on panic,
+//! so this only happens where panics unwind, like in host tests.
+
 use core::{
     cell::UnsafeCell,
     fmt::{Debug, Display},
     ops::{Deref, DerefMut},
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
 };
-use vera_portal::sys_client::yield_now;
+use vera_portal::sys_client::{futex_wait, futex_wake, yield_now};
+
+#[cfg(test)]
+extern crate std;
+
+/// The most times a lock spins before blocking
+const MAX_SPINS: u32 = 100;
+
+/// The states of a mutex's futex word
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// Locked, and some thread may be blocked waiting for it
+const CONTENDED: u32 = 2;
+
+/// If the current thread is panicking, and so should poison the locks it holds
+fn panicking() -> bool {
+    #[cfg(test)]
+    return std::thread::panicking();
+
+    // Panics abort, so a guard is never dropped during one
+    #[cfg(not(test))]
+    false
+}
+
+/// A lock was poisoned, because a thread panicked while holding it.
+///
+/// The lock was still taken, and its guard can be had with [`PoisonError::into_inner`].
+pub struct PoisonError<T> {
+    guard: T,
+}
+
+impl<T> PoisonError<T> {
+    pub const fn new(guard: T) -> Self {
+        Self { guard }
+    }
+
+    /// Take the guard, ignoring the poison
+    pub fn into_inner(self) -> T {
+        self.guard
+    }
+
+    pub const fn get_ref(&self) -> &T {
+        &self.guard
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Debug for PoisonError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PoisonError").finish_non_exhaustive()
+    }
+}
+
+impl<T> Display for PoisonError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("poisoned lock: another thread panicked while holding it")
+    }
+}
+
+impl<T> core::error::Error for PoisonError<T> {}
+
+/// Why [`Mutex::try_lock`] did not return a guard
+pub enum TryLockError<T> {
+    /// The lock is poisoned, but was still taken
+    Poisoned(PoisonError<T>),
+    /// Someone else holds the lock
+    WouldBlock,
+}
+
+impl<T> From<PoisonError<T>> for TryLockError<T> {
+    fn from(err: PoisonError<T>) -> Self {
+        TryLockError::Poisoned(err)
+    }
+}
+
+impl<T> Debug for TryLockError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TryLockError::Poisoned(err) => Debug::fmt(err, f),
+            TryLockError::WouldBlock => f.write_str("WouldBlock"),
+        }
+    }
+}
+
+impl<T> Display for TryLockError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TryLockError::Poisoned(err) => Display::fmt(err, f),
+            TryLockError::WouldBlock => f.write_str("try_lock failed because the lock is held"),
+        }
+    }
+}
 
-/// A QuantumOS Mutex with yield relax behavior.
+impl<T> core::error::Error for TryLockError<T> {}
+
+pub type LockResult<Guard> = Result<Guard, PoisonError<Guard>>;
+pub type TryLockResult<Guard> = Result<Guard, TryLockError<Guard>>;
+
+/// A mutual exclusion lock, that spins for a short while and then blocks.
 pub struct Mutex<T: ?Sized> {
-    lock: AtomicBool,
+    /// `UNLOCKED`, `LOCKED` or `CONTENDED`, threads blocked on the lock wait on this
+    state: AtomicU32,
+    /// How many spins it recently took to get the lock, or `MAX_SPINS` if it had to block
+    spins: AtomicU32,
+    poisoned: AtomicBool,
     inner: UnsafeCell<T>,
 }
 
-unsafe impl<T: ?Sized> Send for Mutex<T> {}
-unsafe impl<T: ?Sized> Sync for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
 
 impl<T> Mutex<T> {
     /// Create a new mutex lock with the provided data
     pub const fn new(value: T) -> Self {
         Self {
-            lock: AtomicBool::new(false),
+            state: AtomicU32::new(UNLOCKED),
+            spins: AtomicU32::new(0),
+            poisoned: AtomicBool::new(false),
             inner: UnsafeCell::new(value),
         }
     }
+
+    /// Take the data out of the mutex
+    pub fn into_inner(self) -> LockResult<T> {
+        let poisoned = self.is_poisoned();
+        let value = self.inner.into_inner();
+
+        match poisoned {
+            true => Err(PoisonError::new(value)),
+            false => Ok(value),
+        }
+    }
 }
 
 impl<T: ?Sized> Mutex<T> {
-    /// Try to lock this mutex
-    pub fn try_lock<'a>(&'a self) -> Option<MutexGuard<'a, T>> {
-        while let Err(failed_lock) =
-            self.lock
-                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+    /// Lock this mutex, blocking until it is free
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
         {
-            // If this lock is already set, we fail the lock
-            if failed_lock {
-                return None;
-            }
+            self.lock_contended();
         }
 
-        Some(MutexGuard {
-            lock: &self.lock,
-            ptr: self.inner.get(),
-        })
-    }
-
-    /// Lock this mutex
-    pub fn lock<'a>(&'a self) -> MutexGuard<'a, T> {
-        loop {
-            match self.try_lock() {
-                Some(lock) => return lock,
-                None => {
-                    // TODO: In the future we should add thread parking to the scheduler
-                    yield_now();
-                }
+        self.guard()
+    }
+
+    /// Lock this mutex, if no one else holds it
+    pub fn try_lock(&self) -> TryLockResult<MutexGuard<'_, T>> {
+        self.state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| TryLockError::WouldBlock)?;
+
+        Ok(self.guard()?)
+    }
+
+    /// If a thread panicked while holding this lock
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed)
+    }
+
+    /// Forget that a thread panicked while holding this lock
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Ordering::Relaxed);
+    }
+
+    /// Get the data without locking, since having `&mut self` means no one else can
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        let poisoned = self.is_poisoned();
+        let value = self.inner.get_mut();
+
+        match poisoned {
+            true => Err(PoisonError::new(value)),
+            false => Ok(value),
+        }
+    }
+
+    fn guard(&self) -> LockResult<MutexGuard<'_, T>> {
+        let guard = MutexGuard { mutex: self };
+
+        match self.is_poisoned() {
+            true => Err(PoisonError::new(guard)),
+            false => Ok(guard),
+        }
+    }
+
+    /// Spin while the owner is likely to let go soon, then block until it does
+    fn lock_contended(&self) {
+        // Spin a little longer than it took last time, so a lock held for about the same
+        // time keeps being taken without blocking
+        let max_spins = (self.spins.load(Ordering::Relaxed) * 2 + 10).min(MAX_SPINS);
+
+        for spin in 0..max_spins {
+            if self.state.load(Ordering::Relaxed) == UNLOCKED
+                && self
+                    .state
+                    .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                self.record_spins(spin);
+                return;
+            }
+
+            // Halfway through, give the owner a chance to run if it is on our cpu
+            if spin == max_spins / 2 {
+                yield_now();
+            } else {
+                core::hint::spin_loop();
             }
         }
+
+        self.record_spins(MAX_SPINS);
+
+        // Marking the lock contended makes the owner wake us when it unlocks. We can't
+        // know if anyone else is still blocked, so the lock stays contended once we get it.
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            let _ = futex_wait(self.state.as_ptr(), CONTENDED);
+        }
+    }
+
+    /// Move the spin estimate an eighth of the way towards `spins`
+    fn record_spins(&self, spins: u32) {
+        let estimate = self.spins.load(Ordering::Relaxed) as i32;
+        let estimate = estimate + (spins as i32 - estimate) / 8;
+
+        self.spins.store(estimate as u32, Ordering::Relaxed);
+    }
+
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            let _ = futex_wake(self.state.as_ptr(), 1);
+        }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Mutex::new(T::default())
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for Mutex<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut debug = f.debug_struct("Mutex");
+        match self.try_lock() {
+            Ok(guard) => debug.field("data", &&*guard),
+            Err(TryLockError::Poisoned(err)) => debug.field("data", &&**err.get_ref()),
+            Err(TryLockError::WouldBlock) => debug.field("data", &"<locked>"),
+        };
+
+        debug
+            .field("poisoned", &self.is_poisoned())
+            .finish_non_exhaustive()
     }
 }
 
 /// A protected guard for the mutex
 pub struct MutexGuard<'a, T: ?Sized> {
-    lock: &'a AtomicBool,
-    ptr: *mut T,
+    mutex: &'a Mutex<T>,
 }
 
 impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
     fn drop(&mut self) {
-        while let Err(failed_unlock) =
-            self.lock
-                .compare_exchange(true, false, Ordering::Release, Ordering::Relaxed)
-        {
-            assert!(
-                failed_unlock,
-                "Mutex is marked as unlocked while dropping MutexGuard"
-            );
+        if panicking() {
+            self.mutex.poisoned.store(true, Ordering::Relaxed);
         }
+
+        self.mutex.unlock();
     }
 }
 
@@ -107,30 +317,150 @@ impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.ptr }
+        unsafe { &*self.mutex.inner.get() }
     }
 }
 
 impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.ptr }
+        unsafe { &mut *self.mutex.inner.get() }
     }
 }
 
 impl<'a, T: ?Sized + Debug> Debug for MutexGuard<'a, T> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        unsafe { &*self.ptr }.fmt(f)
+        (**self).fmt(f)
     }
 }
 
 impl<'a, T: ?Sized + Display> Display for MutexGuard<'a, T> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        unsafe { &*self.ptr }.fmt(f)
+        (**self).fmt(f)
     }
 }
 
 impl<T: Clone> Clone for Mutex<T> {
     fn clone(&self) -> Self {
-        Mutex::new(self.lock().clone())
+        Mutex::new(self.lock().unwrap_or_else(PoisonError::into_inner).clone())
+    }
+}
+
+/// Lets threads sleep until another thread changes the data behind a [`Mutex`].
+///
+/// Like any condition variable, waiters can wake up without being notified, so they must
+/// check their condition again, or use [`Condvar::wait_while`].
+pub struct Condvar {
+    /// Bumped on every notify, waiters block until it changes
+    sequence: AtomicU32,
+}
+
+impl Condvar {
+    pub const fn new() -> Self {
+        Self {
+            sequence: AtomicU32::new(0),
+        }
+    }
+
+    /// Unlock `guard`'s mutex and block until notified, then lock it again
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> LockResult<MutexGuard<'a, T>> {
+        let mutex = guard.mutex;
+
+        // Read before unlocking, so a notify between unlocking and blocking changes it and
+        // the wait returns right away
+        let sequence = self.sequence.load(Ordering::Acquire);
+        drop(guard);
+
+        let _ = futex_wait(self.sequence.as_ptr(), sequence);
+        mutex.lock()
+    }
+
+    /// Block until `condition` returns false, checking it each time this is notified
+    pub fn wait_while<'a, T: ?Sized>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> LockResult<MutexGuard<'a, T>> {
+        while condition(&mut *guard) {
+            guard = self.wait(guard)?;
+        }
+
+        Ok(guard)
+    }
+
+    /// Wake up one thread blocked on this condition variable
+    pub fn notify_one(&self) {
+        self.sequence.fetch_add(1, Ordering::Release);
+        let _ = futex_wake(self.sequence.as_ptr(), 1);
+    }
+
+    /// Wake up every thread blocked on this condition variable
+    pub fn notify_all(&self) {
+        self.sequence.fetch_add(1, Ordering::Release);
+        let _ = futex_wake(self.sequence.as_ptr(), usize::MAX);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Condvar {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lock_and_try_lock() {
+        let mutex = Mutex::new(1);
+
+        let mut guard = mutex.lock().unwrap();
+        *guard += 1;
+        assert!(matches!(mutex.try_lock(), Err(TryLockError::WouldBlock)));
+        drop(guard);
+
+        assert_eq!(*mutex.try_lock().unwrap(), 2);
+        assert_eq!(mutex.into_inner().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_panic_poisons() {
+        let mutex = Mutex::new(5);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = mutex.lock().unwrap();
+            panic!("panicking while holding the lock");
+        }));
+        assert!(result.is_err());
+        assert!(mutex.is_poisoned());
+
+        // The lock is still usable, through the poison
+        let guard = mutex.lock().unwrap_err().into_inner();
+        assert_eq!(*guard, 5);
+        drop(guard);
+
+        mutex.clear_poison();
+        assert_eq!(*mutex.lock().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_spin_estimate_adapts() {
+        let mutex = Mutex::new(());
+
+        for _ in 0..64 {
+            mutex.record_spins(MAX_SPINS);
+        }
+        assert!(mutex.spins.load(Ordering::Relaxed) > MAX_SPINS / 2);
+
+        for _ in 0..64 {
+            mutex.record_spins(0);
+        }
+        assert!(mutex.spins.load(Ordering::Relaxed) < 10);
     }
 }
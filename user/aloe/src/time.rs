@@ -0,0 +1,70 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use core::time::Duration;
+use vera_portal::sys_client::{monotonic_ns, now_unix, sleep_ns};
+
+/// The kernel's monotonic clock, for use with `chloroplast` timers
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl chloroplast::timer::Clock for SystemClock {
+    fn monotonic_ns(&self) -> u64 {
+        monotonic_ns()
+    }
+
+    fn wake_at(&self, deadline_ns: u64, waker: &core::task::Waker) {
+        crate::reactor::wake_at(deadline_ns, waker);
+    }
+}
+
+/// The time since boot
+pub fn monotonic() -> Duration {
+    Duration::from_nanos(monotonic_ns())
+}
+
+/// The value of the cpu's cycle counter
+///
+/// This is much cheaper and finer than `monotonic`, but its rate depends on the cpu.
+pub fn cycles() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+/// The time since the unix epoch
+pub fn unix_time() -> Duration {
+    Duration::from_secs(now_unix())
+}
+
+/// Suspend this thread for at least `duration`
+pub fn sleep(duration: Duration) {
+    sleep_ns(duration.as_nanos() as u64);
+}
+
+/// A future that completes after `duration`, without blocking the thread
+///
+/// Only a runtime from [`crate::reactor::runtime`] wakes the task when the time is up.
+pub fn sleep_async(duration: Duration) -> chloroplast::timer::Sleep<SystemClock> {
+    chloroplast::timer::sleep(SystemClock, duration)
+}
@@ -26,8 +26,12 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 use core::marker::PhantomData;
 
 use private::IoInterface;
-use vera_portal::sys_client::{
-    fixme_cpuio_read_u8, fixme_cpuio_read_u16, fixme_cpuio_write_u8, fixme_cpuio_write_u16,
+use vera_portal::{
+    ResourceInfo,
+    sys_client::{
+        fixme_cpuio_read_u8, fixme_cpuio_read_u16, fixme_cpuio_write_u8, fixme_cpuio_write_u16,
+        resource_info,
+    },
 };
 
 mod private {
@@ -53,13 +57,11 @@ mod private {
     }
 
     impl IoInterface for super::CpuIO {
-        fn own(&self, _: bool) {
-            // FIXME: Add support for device ownership in the kernel
-        }
+        // Ports are owned by claiming them with `claim_io_ports` before they are used, a
+        // claim covers a whole device's ports instead of each one
+        fn own(&self, _: bool) {}
 
-        fn unown(&self) {
-            // FIXME: Add support for device ownership in the kernel
-        }
+        fn unown(&self) {}
     }
 }
 pub unsafe trait IoAccessKind {}
@@ -120,6 +122,11 @@ pub struct CpuIO(u16);
 /// # Currently Supported Interfaces
 ///  - [`CpuIO`] *CPU IO Port bus access*
 ///
+/// # Claims
+/// The kernel only lets a process use IO ports it has claimed with
+/// [`claim_io_ports`](crate::claim_io_ports). Ports that were not claimed read as all ones,
+/// and writes to them are dropped.
+///
 /// # Why use this type?
 /// `UserIO` represents an 'owned' access over some IO device on the system. This is important because future
 /// processes will not be able to access this IO device until it is dropped.
@@ -179,3 +186,8 @@ where
         fixme_cpuio_write_u16(self.interface.0, value);
     }
 }
+
+/// Get every I/O port range, MMIO region, and IRQ line the kernel's drivers have claimed
+pub fn claimed_resources() -> impl Iterator<Item = ResourceInfo> {
+    (0..).map_while(|index| resource_info(index).ok())
+}
@@ -0,0 +1,165 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+#![no_main]
+tiny_std!();
+
+use alloc::{collections::VecDeque, vec::Vec};
+use aloe::{
+    WaitSignal, dbugln,
+    ipc::{QuantumGlue, QuantumHost},
+    signal_timer, signal_wait, tiny_std,
+};
+use console_portal::{
+    ConsoleMode, ConsolePortalClientRequestRef, ConsolePortalInfo, ConsolePortalServer,
+};
+use portal::ipc::IpcDeferred;
+use serial::SerialPort;
+use tty::{LineDiscipline, TtyMode};
+
+mod serial;
+
+/// The most bytes a single read can return, no matter how many the client asked for
+const MAX_READ_LEN: usize = 4096;
+
+/// How often the serial port is checked for input
+const POLL_INTERVAL_NS: u64 = 10_000_000;
+
+/// A client waiting for input
+struct PendingRead {
+    handle: u64,
+    max_len: usize,
+    response: IpcDeferred<ConsolePortalInfo, Vec<u8>, 1>,
+}
+
+/// Take all the input waiting on the serial port
+fn poll_input(port: &mut SerialPort, tty: &mut LineDiscipline) {
+    while let Some(byte) = port.try_read() {
+        tty.input(byte, |echo| port.write(echo));
+    }
+}
+
+/// Answer waiting readers in the order they asked, for as long as there is input for them
+fn answer_readers(
+    server: &mut QuantumHost<ConsolePortalServer<QuantumGlue>>,
+    readers: &mut VecDeque<PendingRead>,
+    tty: &mut LineDiscipline,
+) {
+    while tty.can_read() {
+        let Some(reader) = readers.pop_front() else {
+            break;
+        };
+
+        // The reader might have disconnected while it was waiting
+        let Some(client) = server.client_mut(reader.handle) else {
+            continue;
+        };
+
+        let mut buf = Vec::new();
+        buf.resize(reader.max_len, 0);
+        let len = tty.read(&mut buf);
+        buf.truncate(len);
+
+        if let Err(err) = client.resume(reader.response).respond_with(buf) {
+            dbugln!("Unable to answer read on {} ({err:?})", reader.handle);
+        }
+    }
+}
+
+fn main() {
+    dbugln!("Starting Console server!");
+
+    let mut port = match SerialPort::com1() {
+        Ok(port) => port,
+        Err(err) => {
+            dbugln!("Unable to claim the serial port ({err:?}), exiting");
+            return;
+        }
+    };
+    let mut tty = LineDiscipline::new();
+    let mut readers = VecDeque::new();
+
+    let mut server = QuantumHost::<ConsolePortalServer<QuantumGlue>>::host_on("console").unwrap();
+    let mut timer_armed = false;
+    loop {
+        if !timer_armed {
+            signal_timer(POLL_INTERVAL_NS);
+            timer_armed = true;
+        }
+
+        match signal_wait() {
+            WaitSignal::TimerUpdate { .. } => timer_armed = false,
+            signal => server
+                .service_signal(
+                    signal,
+                    |handle| Ok(ConsolePortalServer::new(QuantumGlue::new(handle))),
+                    |read_cs| {
+                        let handle = read_cs.glue().handle();
+                        match read_cs.incoming_borrowed()? {
+                            ConsolePortalClientRequestRef::Read { max_len, sender } => {
+                                // Answered once there is input, so other clients are still served
+                                readers.push_back(PendingRead {
+                                    handle,
+                                    max_len: max_len.min(MAX_READ_LEN as u64) as usize,
+                                    response: sender.defer(),
+                                });
+                                Ok(())
+                            }
+                            ConsolePortalClientRequestRef::Write { bytes, sender } => {
+                                // Written straight out of the message, without copying it first
+                                port.write(bytes);
+                                sender.respond_with(())
+                            }
+                            ConsolePortalClientRequestRef::SetMode { mode, sender } => {
+                                tty.set_mode(TtyMode {
+                                    canonical: mode.canonical,
+                                    echo: mode.echo,
+                                });
+                                sender.respond_with(())
+                            }
+                            ConsolePortalClientRequestRef::GetMode { sender } => {
+                                let mode = tty.mode();
+                                sender.respond_with(ConsoleMode {
+                                    canonical: mode.canonical,
+                                    echo: mode.echo,
+                                })
+                            }
+                            _ => Ok(()),
+                        }
+                    },
+                    |_| Ok(()),
+                    |_| {
+                        dbugln!("Disconnecting Client");
+                        Ok(())
+                    },
+                )
+                .unwrap(),
+        }
+
+        poll_input(&mut port, &mut tty);
+        answer_readers(&mut server, &mut readers, &mut tty);
+    }
+}
@@ -0,0 +1,73 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use aloe::{
+    IoClaimError, claim_io_ports,
+    uio::{CpuIO, UserIO, opt},
+};
+
+/// The first serial port's io base
+const COM1: u16 = 0x3F8;
+/// Line status register offset
+const LINE_STATUS: u16 = 5;
+/// Line status bit set when there is a byte to read
+const DATA_READY: u8 = 1 << 0;
+/// Line status bit set when the transmitter can take another byte
+const TRANSMIT_EMPTY: u8 = 1 << 5;
+
+/// A polled serial port, the kernel has already configured its baud rate.
+pub struct SerialPort {
+    data: UserIO<CpuIO, opt::ReadWrite, opt::Shared>,
+    line_status: UserIO<CpuIO, opt::ReadOnly, opt::Shared>,
+}
+
+impl SerialPort {
+    /// Claim the first serial port, sharing it with the kernel which writes its log to it
+    pub fn com1() -> Result<Self, IoClaimError> {
+        claim_io_ports(COM1, LINE_STATUS + 1, true)?;
+
+        Ok(Self {
+            data: unsafe { UserIO::new(COM1) },
+            line_status: unsafe { UserIO::new(COM1 + LINE_STATUS) },
+        })
+    }
+
+    /// Read a byte if one has been received
+    pub fn try_read(&self) -> Option<u8> {
+        if unsafe { self.line_status.read_u8() } & DATA_READY == 0 {
+            return None;
+        }
+
+        Some(unsafe { self.data.read_u8() })
+    }
+
+    /// Write all of `bytes`, waiting on the transmitter between each
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            while unsafe { self.line_status.read_u8() } & TRANSMIT_EMPTY == 0 {}
+            unsafe { self.data.write_u8(*byte) };
+        }
+    }
+}
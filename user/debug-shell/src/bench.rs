@@ -0,0 +1,78 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::vec::Vec;
+use aloe::{
+    ipc::QuantumGlue,
+    time::{cycles, monotonic},
+};
+use bench_portal::{BenchPortalClient, percentile};
+
+/// How many round trips `bench` makes without a count
+pub const DEFAULT_ROUND_TRIPS: usize = 10_000;
+
+/// Measure `count` no-op round trips to the bench server, and print their latency.
+///
+/// Each round trip is timed by the glue's cycle counter readings, from right before
+/// the request is sent to right after the response arrives.
+pub fn run(shell: &mut Shell, count: usize) {
+    let mut bench = BenchPortalClient::new(QuantumGlue::connect_to("bench").unwrap());
+    let mut round_trips = Vec::with_capacity(count);
+
+    let start_time = monotonic();
+    let start_cycles = cycles();
+
+    for _ in 0..count {
+        if let Err(err) = bench.noop_blocking() {
+            shell.print(&alloc::format!("bench: request failed with {err:?}\n"));
+            return;
+        }
+
+        let timestamps = bench.glue().timestamps();
+        round_trips.push(timestamps.recv.saturating_sub(timestamps.send_start));
+    }
+
+    let elapsed_cycles = cycles() - start_cycles;
+    let elapsed_ns = (monotonic() - start_time).as_nanos().max(1) as u64;
+
+    // Use the whole run to find how fast the cycle counter ticks
+    let cycles_to_ns =
+        |cycles: u64| (cycles as u128 * elapsed_ns as u128 / elapsed_cycles.max(1) as u128) as u64;
+
+    round_trips.sort_unstable();
+    let p50 = percentile(&round_trips, 50);
+    let p99 = percentile(&round_trips, 99);
+
+    shell.print(&alloc::format!(
+        "{count} round trips in {}us\n  p50: {} cycles ({}ns)\n  p99: {} cycles ({}ns)\n  {} messages/sec\n",
+        elapsed_ns / 1000,
+        p50,
+        cycles_to_ns(p50),
+        p99,
+        cycles_to_ns(p99),
+        count as u128 * 1_000_000_000 / elapsed_ns as u128
+    ));
+}
@@ -0,0 +1,62 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::format;
+use aloe::ipc::QuantumGlue;
+use gfx_portal::GfxPortalClient;
+
+/// Show, hide, or move the compositor's mouse pointer
+pub fn run<'a>(shell: &mut Shell, mut args: impl Iterator<Item = &'a str>) {
+    let mut gfx = match QuantumGlue::connect_to("gfx") {
+        Ok(glue) => GfxPortalClient::new(glue),
+        Err(err) => {
+            shell.print(&format!(
+                "cursor: unable to connect to the gfx server ({err:?})\n"
+            ));
+            return;
+        }
+    };
+
+    let result = match (args.next(), args.next()) {
+        (Some("show"), None) => gfx.show_cursor_blocking(),
+        (Some("hide"), None) => gfx.hide_cursor_blocking(),
+        (Some(x), Some(y)) => match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => gfx.move_cursor_blocking(x, y),
+            _ => {
+                shell.print("cursor: expected a position like 100 200\n");
+                return;
+            }
+        },
+        _ => {
+            shell.print("cursor: expected show, hide, or a position\n");
+            return;
+        }
+    };
+
+    if let Err(err) = result {
+        shell.print(&format!("cursor: request failed with {err:?}\n"));
+    }
+}
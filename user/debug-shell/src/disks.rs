@@ -0,0 +1,89 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::{format, string::String, vec::Vec};
+use aloe::ipc::QuantumGlue;
+use fs_portal::FsPortalClient;
+
+/// Print every disk the fs server detected, with what the disk says about itself
+pub fn run(shell: &mut Shell) {
+    let mut fs = match QuantumGlue::connect_to("fs") {
+        Ok(glue) => FsPortalClient::new(glue),
+        Err(err) => {
+            shell.print(&format!(
+                "disks: unable to connect to the fs server ({err:?})\n"
+            ));
+            return;
+        }
+    };
+
+    let disks = match fs.disks_blocking() {
+        Ok(disks) => disks,
+        Err(err) => {
+            shell.print(&format!("disks: request failed with {err:?}\n"));
+            return;
+        }
+    };
+
+    if disks.is_empty() {
+        shell.print("disks: no disks were detected\n");
+        return;
+    }
+
+    for disk in disks {
+        let size = match disk.sectors {
+            0 => String::from("no media"),
+            sectors => format!(
+                "{} MiB ({} sectors of {} bytes)",
+                sectors * disk.sector_size as u64 / (1024 * 1024),
+                sectors,
+                disk.sector_size
+            ),
+        };
+
+        let features: Vec<&str> = [
+            ("lba48", disk.lba48),
+            ("smart", disk.smart),
+            ("dma", disk.dma),
+            ("write-cache", disk.write_cache),
+            ("trim", disk.trim),
+        ]
+        .into_iter()
+        .filter_map(|(name, supported)| supported.then_some(name))
+        .collect();
+
+        shell.print(&format!(
+            "{}{}\n  model:    {}\n  serial:   {}\n  firmware: {}\n  size:     {}\n  features: {}\n",
+            disk.name,
+            if disk.removable { " (removable)" } else { "" },
+            disk.model,
+            disk.serial,
+            disk.firmware,
+            size,
+            features.join(" ")
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::{format, string::String, vec};
+use aloe::{CrashLogError, previous_crash_log};
+
+/// The most of the last boot's log that is printed
+const MAX_LOG: usize = 64 * 1024;
+
+/// Print the log of the last boot, if it ended in a panic
+pub fn run(shell: &mut Shell) {
+    let mut log = vec![0; MAX_LOG];
+
+    match previous_crash_log(&mut log) {
+        Ok(len) => {
+            let copied = len.min(log.len());
+            shell.print(&String::from_utf8_lossy(&log[..copied]));
+            if len > copied {
+                shell.print(&format!("lastcrash: {} bytes not shown\n", len - copied));
+            }
+        }
+        Err(CrashLogError::NoCrashLog) => shell.print("lastcrash: the last boot did not panic\n"),
+        Err(err) => shell.print(&format!("lastcrash: {err:?}\n")),
+    }
+}
@@ -0,0 +1,160 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+#![no_main]
+tiny_std!();
+
+use alloc::{string::String, vec::Vec};
+use aloe::{ipc::QuantumGlue, tiny_std};
+use console_portal::ConsolePortalClient;
+
+mod bench;
+mod cursor;
+mod disks;
+mod lastcrash;
+mod meminfo;
+mod mode;
+mod profile;
+mod resources;
+mod screenshot;
+mod stacks;
+mod strace;
+mod top;
+mod vm;
+
+/// The longest line the shell will read
+const MAX_LINE: u64 = 256;
+
+/// A shell on the console for poking at the system while debugging
+pub struct Shell {
+    console: ConsolePortalClient<QuantumGlue>,
+    /// Attached to the kernel's log the first time `strace show` runs
+    trace_view: Option<strace::TraceView>,
+}
+
+impl Shell {
+    /// Write `text` to the console
+    pub fn print(&mut self, text: &str) {
+        self.console
+            .write_blocking(Vec::from(text.as_bytes()))
+            .unwrap();
+    }
+
+    /// Read one line from the console
+    fn read_line(&mut self) -> String {
+        let line = self.console.read_blocking(MAX_LINE).unwrap();
+        String::from_utf8_lossy(&line).into_owned()
+    }
+
+    fn run(&mut self, line: &str) {
+        let mut args = line.split_whitespace();
+
+        match args.next() {
+            None => (),
+            Some("help") => {
+                self.print("help            show this message\n");
+                self.print("bench [count]   measure ipc round trips to the bench server\n");
+                self.print("top             show what every task is doing\n");
+                self.print("stacks          show how deep every task's stack has been\n");
+                self.print("lastcrash       print the log of the last boot, if it panicked\n");
+                self.print("meminfo         show how physical memory is being used\n");
+                self.print("resources       list the hardware every driver has claimed\n");
+                self.print("disks           list the disks the fs server detected\n");
+                self.print("mode [WxH]      change the resolution of the screen\n");
+                self.print("screenshot [path]\n");
+                self.print("                save the screen as a PPM, or send it to serial\n");
+                self.print("cursor show|hide|x y\n");
+                self.print("                show, hide, or move the mouse pointer\n");
+                self.print("nice pid value  change the nice value of a process\n");
+                self.print("keymap name     switch the keyboard layout\n");
+                self.print("profile start|stop|dump\n");
+                self.print("                sample where the kernel spends its time\n");
+                self.print("strace pid [on|off]\n");
+                self.print("                trace the syscalls of a process\n");
+                self.print("strace show [count]\n");
+                self.print("                print the next traced syscalls\n");
+                self.print("vm dump pid [start [end]]\n");
+                self.print("                log the page tables of a process\n");
+                self.print("vm translate pid addr\n");
+                self.print("                find where an address is mapped to\n");
+            }
+            Some("bench") => match args.next().map(str::parse) {
+                None => bench::run(self, bench::DEFAULT_ROUND_TRIPS),
+                Some(Ok(count)) if count > 0 => bench::run(self, count),
+                Some(_) => self.print("bench: count must be a positive number\n"),
+            },
+            Some("top") => top::run(self),
+            Some("stacks") => stacks::run(self),
+            Some("lastcrash") => lastcrash::run(self),
+            Some("meminfo") => meminfo::run(self),
+            Some("resources") => resources::run(self),
+            Some("disks") => disks::run(self),
+            Some("mode") => mode::run(self, args.next()),
+            Some("screenshot") => screenshot::run(self, args.next()),
+            Some("cursor") => cursor::run(self, args),
+            Some("strace") => strace::run(self, args),
+            Some("vm") => vm::run(self, args),
+            Some("profile") => profile::run(self, args.next()),
+            Some("nice") => {
+                let pid = args.next().and_then(|pid| pid.parse().ok());
+                let nice = args.next().and_then(|nice| nice.parse().ok());
+
+                match pid.zip(nice) {
+                    Some((pid, nice)) => {
+                        if let Err(err) = aloe::set_nice(pid, nice) {
+                            self.print(&alloc::format!("nice: {err:?}\n"));
+                        }
+                    }
+                    None => self.print("nice: expected a pid and a nice value\n"),
+                }
+            }
+            Some("keymap") => match args.next() {
+                Some(name) => {
+                    if let Err(err) = aloe::keymap_set(name) {
+                        self.print(&alloc::format!("keymap: {err:?}\n"));
+                    }
+                }
+                None => self.print("keymap: expected the name of a layout\n"),
+            },
+            Some(unknown) => {
+                self.print(&alloc::format!("{unknown}: unknown command, try `help`\n"));
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut shell = Shell {
+        console: ConsolePortalClient::new(QuantumGlue::connect_to("console").unwrap()),
+        trace_view: None,
+    };
+
+    loop {
+        shell.print("> ");
+        let line = shell.read_line();
+        shell.run(line.trim());
+    }
+}
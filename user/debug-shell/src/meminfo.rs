@@ -0,0 +1,44 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+use crate::Shell;
+use alloc::format;
+use aloe::memory_info;
+
+/// Print how much physical memory there is, and what is using it
+pub fn run(shell: &mut Shell) {
+    let info = memory_info();
+    let used_pages = info
+        .total_pages
+        .saturating_sub(info.free_pages + info.balloon_pages);
+
+    shell.print(&format!("Total:   {:>10} KiB\n", info.total_pages * 4));
+    shell.print(&format!("Used:    {:>10} KiB\n", used_pages * 4));
+    shell.print(&format!("Free:    {:>10} KiB\n", info.free_pages * 4));
+    shell.print(&format!(
+        "Balloon: {:>10} KiB (host wants {} KiB)\n",
+        info.balloon_pages * 4,
+        info.balloon_target_pages * 4
+    ));
+}
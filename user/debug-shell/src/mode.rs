@@ -0,0 +1,63 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::format;
+use aloe::ipc::QuantumGlue;
+use gfx_portal::GfxPortalClient;
+
+/// Switch the screen to the resolution in `mode`, written as `WIDTHxHEIGHT`, or to the
+/// size the display would like to be without one
+pub fn run(shell: &mut Shell, mode: Option<&str>) {
+    let size = match mode {
+        None => Some((0, 0)),
+        Some(mode) => mode
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?))),
+    };
+
+    let Some((width, height)) = size else {
+        shell.print("mode: expected a resolution like 1024x768\n");
+        return;
+    };
+
+    let mut gfx = match QuantumGlue::connect_to("gfx") {
+        Ok(glue) => GfxPortalClient::new(glue),
+        Err(err) => {
+            shell.print(&format!(
+                "mode: unable to connect to the gfx server ({err:?})\n"
+            ));
+            return;
+        }
+    };
+
+    match gfx.set_mode_blocking(width, height) {
+        Ok(Ok(screen)) => {
+            shell.print(&format!("mode: now {}x{}\n", screen.width, screen.height));
+        }
+        Ok(Err(err)) => shell.print(&format!("mode: unable to switch ({err:?})\n")),
+        Err(err) => shell.print(&format!("mode: request failed with {err:?}\n")),
+    }
+}
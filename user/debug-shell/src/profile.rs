@@ -0,0 +1,51 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::format;
+use aloe::{ProfileCommand, profile};
+
+/// Start or stop the kernel's sampling profiler, or print what it sampled to the kernel's
+/// log
+pub fn run(shell: &mut Shell, command: Option<&str>) {
+    let command = match command {
+        Some("start") => ProfileCommand::Start,
+        Some("stop") => ProfileCommand::Stop,
+        Some("dump") => ProfileCommand::Dump,
+        _ => {
+            shell.print("profile: expected start, stop or dump\n");
+            return;
+        }
+    };
+
+    let is_dump = matches!(command, ProfileCommand::Dump);
+    match profile(command) {
+        Ok(()) if is_dump => {
+            shell.print("profile: the samples were written to the kernel's log\n");
+        }
+        Ok(()) => (),
+        Err(err) => shell.print(&format!("profile: {err:?}\n")),
+    }
+}
@@ -0,0 +1,56 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::{format, string::String};
+use aloe::{ResourceKind, uio::claimed_resources};
+
+/// Print the hardware inventory, every resource a driver has claimed
+pub fn run(shell: &mut Shell) {
+    shell.print("KIND  RANGE                                  SHARED OWNER\n");
+    for resource in claimed_resources() {
+        let owner_len = resource
+            .owner
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(32);
+        let owner = String::from_utf8_lossy(&resource.owner[..owner_len]);
+        let end = resource.start + resource.len.max(1) - 1;
+
+        let (kind, range) = match resource.kind {
+            ResourceKind::IoPorts => ("IO", format!("{:#06x}-{:#06x}", resource.start, end)),
+            ResourceKind::Mmio => ("MMIO", format!("{:#018x}-{:#018x}", resource.start, end)),
+            ResourceKind::Irq => ("IRQ", format!("{}", resource.start)),
+        };
+
+        shell.print(&format!(
+            "{:<5} {:<38} {:<6} {}\n",
+            kind,
+            range,
+            if resource.shared { "yes" } else { "no" },
+            owner
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::{format, string::String, vec, vec::Vec};
+use aloe::{debug_screenshot, debug_screenshot_read, ipc::QuantumGlue};
+use fs_portal::{FsPortalClient, QuantumError};
+
+/// The most bytes sent to the fs server in one append
+const MAX_APPEND_LEN: usize = 4096;
+
+/// Save what is on the screen as a PPM image to `path`, or send it over the kernel's
+/// serial port without one
+pub fn run(shell: &mut Shell, path: Option<&str>) {
+    let Some(path) = path else {
+        if let Err(err) = debug_screenshot() {
+            shell.print(&format!("screenshot: {err:?}\n"));
+        }
+        return;
+    };
+
+    let image = match take() {
+        Ok(image) => image,
+        Err(err) => {
+            shell.print(&format!("screenshot: {err:?}\n"));
+            return;
+        }
+    };
+
+    let mut fs = match QuantumGlue::connect_to("fs") {
+        Ok(glue) => FsPortalClient::new(glue),
+        Err(err) => {
+            shell.print(&format!(
+                "screenshot: unable to connect to the fs server ({err:?})\n"
+            ));
+            return;
+        }
+    };
+
+    match save(&mut fs, path, &image) {
+        Ok(()) => shell.print(&format!(
+            "screenshot: saved {} bytes to {path}\n",
+            image.len()
+        )),
+        Err(err) => shell.print(&format!("screenshot: unable to save {path} ({err:?})\n")),
+    }
+}
+
+/// Copy the screen out of the kernel, retrying if it grew while we were making room for it
+fn take() -> Result<Vec<u8>, aloe::ScreenshotError> {
+    let mut image = Vec::new();
+
+    loop {
+        let len = debug_screenshot_read(&mut image)?;
+        if len <= image.len() {
+            image.truncate(len);
+            return Ok(image);
+        }
+
+        image = vec![0; len];
+    }
+}
+
+/// Replace the file at `path` with `image`
+fn save(
+    fs: &mut FsPortalClient<QuantumGlue>,
+    path: &str,
+    image: &[u8],
+) -> Result<(), QuantumError> {
+    match fs.truncate_blocking(String::from(path), 0)? {
+        Ok(()) | Err(QuantumError::NotFound) => (),
+        Err(err) => return Err(err),
+    }
+
+    for chunk in image.chunks(MAX_APPEND_LEN) {
+        fs.append_blocking(String::from(path), chunk.into())??;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::{format, string::String};
+use aloe::{STACK_WARN_PERCENT, TaskState, process::tasks};
+
+/// Print the deepest every live task's kernel stack has been
+pub fn run(shell: &mut Shell) {
+    shell.print("  PID  TID NAME                         USED      SIZE  USE%\n");
+    for task in tasks().filter(|task| !matches!(task.state, TaskState::Zombie)) {
+        let name_len = task.name.iter().position(|&byte| byte == 0).unwrap_or(32);
+        let name = String::from_utf8_lossy(&task.name[..name_len]);
+        let percent = task.stack_used * 100 / task.stack_len.max(1);
+
+        shell.print(&format!(
+            "{:>5} {:>4} {:<24} {:>8} {:>9} {:>4}%{}\n",
+            task.pid,
+            task.tid,
+            name,
+            task.stack_used,
+            task.stack_len,
+            percent,
+            if percent >= STACK_WARN_PERCENT {
+                " !"
+            } else {
+                ""
+            }
+        ));
+    }
+}
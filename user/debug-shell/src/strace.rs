@@ -0,0 +1,161 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::{format, string::String, vec::Vec};
+use aloe::{TRACE_LINE_PREFIX, ipc::QuantumGlue, time::sleep, trace_syscalls};
+use core::time::Duration;
+use log_portal::LogPortalClient;
+
+/// How many traced syscalls `strace show` prints by default
+pub const DEFAULT_SHOWN: usize = 20;
+/// How long to wait before asking `logd` for more of the log
+const LOGD_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The kernel's log, read through `logd`
+///
+/// Only one process can attach to the kernel's log ring, and that has to be `logd`. So
+/// the shell never attaches to the ring itself, even when `logd` has not started yet.
+struct LogSource {
+    portal: LogPortalClient<QuantumGlue>,
+    cursor: u64,
+}
+
+impl LogSource {
+    /// Connect to `logd`, or `None` if it is not running
+    ///
+    /// Reading starts from the end of the log, so only what is traced from now on is shown.
+    fn open() -> Option<Self> {
+        let glue = QuantumGlue::try_connect_to("log").ok()?;
+        let mut portal = LogPortalClient::new(glue);
+        let cursor = portal.end_blocking().ok()?;
+
+        Some(Self { portal, cursor })
+    }
+
+    /// Read more of the log onto the end of `buf`, waiting until there is some
+    ///
+    /// Returns `false` if the log can no longer be read.
+    fn read(&mut self, buf: &mut Vec<u8>) -> bool {
+        loop {
+            let Ok(chunk) = self.portal.read_blocking(self.cursor, 512) else {
+                return false;
+            };
+
+            self.cursor = chunk.cursor;
+            if !chunk.bytes.is_empty() {
+                buf.extend_from_slice(&chunk.bytes);
+                return true;
+            }
+
+            sleep(LOGD_POLL_INTERVAL);
+        }
+    }
+}
+
+/// The kernel's log, which the traced syscalls are picked out of
+pub struct TraceView {
+    source: LogSource,
+    /// The start of a line the kernel has not finished writing yet
+    partial: Vec<u8>,
+}
+
+impl TraceView {
+    /// Wait for the next traced syscall, or `None` if the log can no longer be read
+    fn next_line(&mut self) -> Option<String> {
+        loop {
+            if let Some(end) = self.partial.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.partial.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line[..end]);
+
+                if let Some(trace) = line.strip_prefix(TRACE_LINE_PREFIX) {
+                    return Some(String::from(trace));
+                }
+                continue;
+            }
+
+            if !self.source.read(&mut self.partial) {
+                return None;
+            }
+        }
+    }
+}
+
+/// Run `strace`, with the arguments that came after it
+pub fn run<'a>(shell: &mut Shell, mut args: impl Iterator<Item = &'a str>) {
+    match args.next() {
+        Some("show") => match args.next().map(str::parse) {
+            None => show(shell, DEFAULT_SHOWN),
+            Some(Ok(count)) => show(shell, count),
+            Some(Err(_)) => shell.print("strace: count must be a number\n"),
+        },
+        Some(pid) => {
+            let Ok(pid) = pid.parse() else {
+                shell.print("strace: expected a pid or `show`\n");
+                return;
+            };
+            let enabled = match args.next() {
+                None | Some("on") => true,
+                Some("off") => false,
+                Some(_) => {
+                    shell.print("strace: expected `on` or `off`\n");
+                    return;
+                }
+            };
+
+            if let Err(err) = trace_syscalls(pid, enabled) {
+                shell.print(&format!("strace: {err:?}\n"));
+            }
+        }
+        None => shell.print("strace: expected a pid or `show`\n"),
+    }
+}
+
+/// Print the next `count` syscalls traced by the kernel
+fn show(shell: &mut Shell, count: usize) {
+    if shell.trace_view.is_none() {
+        match LogSource::open() {
+            Some(source) => {
+                shell.trace_view = Some(TraceView {
+                    source,
+                    partial: Vec::new(),
+                })
+            }
+            None => {
+                shell.print("strace: logd is not running, unable to read the kernel log\n");
+                return;
+            }
+        }
+    }
+
+    for _ in 0..count {
+        let Some(line) = shell.trace_view.as_mut().unwrap().next_line() else {
+            shell.print("strace: lost the connection to logd\n");
+            shell.trace_view = None;
+            return;
+        };
+        shell.print(&format!("{line}\n"));
+    }
+}
@@ -0,0 +1,65 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::{collections::BTreeMap, format, string::String};
+use aloe::{
+    TaskState,
+    process::tasks,
+    time::{monotonic, sleep},
+};
+use core::time::Duration;
+
+/// How long `top` watches the cpu for
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Print every live task, and how much of the cpu it used over `SAMPLE_PERIOD`
+pub fn run(shell: &mut Shell) {
+    let before: BTreeMap<(usize, usize), u64> = tasks()
+        .map(|task| ((task.pid, task.tid), task.cpu_ns))
+        .collect();
+    let start = monotonic();
+
+    sleep(SAMPLE_PERIOD);
+    let elapsed_ns = (monotonic() - start).as_nanos().max(1) as u64;
+
+    shell.print("  PID  TID NAME                     STATE     NICE  CPU%     TIME\n");
+    for task in tasks().filter(|task| !matches!(task.state, TaskState::Zombie)) {
+        let used_ns = task.cpu_ns - before.get(&(task.pid, task.tid)).copied().unwrap_or(0);
+        let name_len = task.name.iter().position(|&byte| byte == 0).unwrap_or(32);
+        let name = String::from_utf8_lossy(&task.name[..name_len]);
+
+        shell.print(&format!(
+            "{:>5} {:>4} {:<24} {:<9} {:>4} {:>5} {:>7}ms\n",
+            task.pid,
+            task.tid,
+            name,
+            format!("{:?}", task.state),
+            task.nice,
+            used_ns * 100 / elapsed_ns,
+            task.cpu_ns / 1_000_000
+        ));
+    }
+}
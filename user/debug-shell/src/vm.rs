@@ -0,0 +1,98 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::Shell;
+use alloc::format;
+use aloe::{VmCacheMode, vm_dump, vm_translate};
+
+/// Parse an address, in hex if it starts with `0x`
+fn parse_addr(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(&hex.replace('_', ""), 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Run `vm`, with the arguments that came after it
+pub fn run<'a>(shell: &mut Shell, mut args: impl Iterator<Item = &'a str>) {
+    let command = args.next();
+    let Some(pid) = args.next().and_then(|pid| pid.parse().ok()) else {
+        shell.print("vm: expected `dump` or `translate`, then a pid\n");
+        return;
+    };
+
+    match command {
+        Some("dump") => {
+            let start = args.next().map(parse_addr);
+            let end = args.next().map(parse_addr);
+
+            let (start, end) = match (start, end) {
+                (None, _) => (0, usize::MAX),
+                (Some(Some(start)), None) => (start, start),
+                (Some(Some(start)), Some(Some(end))) => (start, end),
+                _ => {
+                    shell.print("vm: addresses must be numbers\n");
+                    return;
+                }
+            };
+
+            match vm_dump(pid, start, end) {
+                Ok(lines) => shell.print(&format!(
+                    "vm: logged {lines} mappings in {start:#018x}-{end:#018x}\n"
+                )),
+                Err(err) => shell.print(&format!("vm: {err:?}\n")),
+            }
+        }
+        Some("translate") => {
+            let Some(virt) = args.next().and_then(parse_addr) else {
+                shell.print("vm: expected an address to translate\n");
+                return;
+            };
+
+            match vm_translate(pid, virt) {
+                Ok(translation) => {
+                    let cache = match translation.cache {
+                        VmCacheMode::WriteBack => "WB",
+                        VmCacheMode::WriteThrough => "WT",
+                        VmCacheMode::WriteCombining => "WC",
+                        VmCacheMode::Uncached => "UC",
+                    };
+
+                    shell.print(&format!(
+                        "{virt:#018x} -> {:#018x} ({} byte page) {}{}{}{}{cache}\n",
+                        translation.phys,
+                        translation.page_size,
+                        if translation.writable { "W " } else { "" },
+                        if translation.no_exec { "NX " } else { "" },
+                        if translation.user { "U " } else { "" },
+                        if translation.global { "G " } else { "" },
+                    ));
+                }
+                Err(err) => shell.print(&format!("vm: {err:?}\n")),
+            }
+        }
+        _ => shell.print("vm: expected `dump` or `translate`\n"),
+    }
+}
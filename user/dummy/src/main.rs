@@ -27,9 +27,72 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 #![no_main]
 tiny_std!();
 
-use aloe::{dbugln, ipc::QuantumGlue, tiny_std};
+use aloe::{dbugln, ipc::QuantumGlue, shared::SharedRegion, time::sleep, tiny_std};
 use chloroplast::Chloroplast;
+use core::time::Duration;
 use fs_portal::FsPortalClient;
+use gfx_portal::GfxPortalClient;
+
+/// The gap around each of the demo surfaces
+const MARGIN: u32 = 16;
+
+/// Create a surface filled with `color` and show it at `x`, `y`.
+fn show_surface(
+    gfx: &mut GfxPortalClient<QuantumGlue>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    color: u32,
+) {
+    let surface = gfx.create_surface_blocking(width, height).unwrap().unwrap();
+    let mut pixels = SharedRegion::map(
+        surface.shared_id,
+        width as usize * height as usize * size_of::<u32>(),
+        true,
+    )
+    .unwrap();
+
+    pixels
+        .as_mut_slice()
+        .unwrap()
+        .chunks_exact_mut(size_of::<u32>())
+        .for_each(|pixel| pixel.copy_from_slice(&color.to_le_bytes()));
+
+    gfx.move_surface_blocking(surface.id, x as i32, y as i32)
+        .unwrap()
+        .unwrap();
+    gfx.damage_blocking(surface.id, 0, 0, width, height)
+        .unwrap()
+        .unwrap();
+}
+
+/// Draw two surfaces side by side, each from its own connection to the compositor.
+fn gfx_demo() {
+    let mut left = GfxPortalClient::new(QuantumGlue::connect_to("gfx").unwrap());
+    let mut right = GfxPortalClient::new(QuantumGlue::connect_to("gfx").unwrap());
+
+    let screen = left.screen_size_blocking().unwrap();
+    let width = (screen.width / 2).saturating_sub(MARGIN * 2);
+    let height = screen.height.saturating_sub(MARGIN * 2);
+    if width == 0 || height == 0 {
+        dbugln!("No screen to draw on!");
+        return;
+    }
+
+    show_surface(&mut left, MARGIN, MARGIN, width, height, 0x00c04040);
+    show_surface(
+        &mut right,
+        screen.width / 2 + MARGIN,
+        MARGIN,
+        width,
+        height,
+        0x004040c0,
+    );
+
+    // The compositor removes our surfaces once we disconnect
+    sleep(Duration::from_secs(5));
+}
 
 fn main() {
     let runtime = Chloroplast::new();
@@ -40,5 +103,7 @@ fn main() {
         dbugln!("Ping!");
         fs.ping_blocking().unwrap();
         dbugln!("Pong!");
+
+        gfx_demo();
     });
 }
@@ -23,8 +23,50 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
+use aloe::{claim_io_ports, dbugln, release_io_ports, uio::UserIO};
+use fs::{
+    error::{DiskError, FsError, Result},
+    read_block::BlockDevice,
+};
+use pio_registers::{
+    AltStatusReg, CommandReg, DataReg, DeviceControlReg, DriveReg, ErrorReg, FeaturesReg,
+    SectorCountReg, SectorNumberRegs, StatusReg, StatusValue,
+};
+
+mod atapi;
 mod pio_registers;
 
+pub use atapi::AtapiDrive;
+
+/// How many times a failed command is attempted before the error is returned.
+const MAX_ATTEMPTS: usize = 3;
+
+/// How many times the status register is polled before giving up on the device.
+const POLL_LIMIT: usize = 100_000;
+
+const SECTOR_SIZE: usize = 512;
+
+/// How many IO ports a bus's command registers take up, starting at its io base.
+const IO_PORTS_LEN: u16 = 8;
+
+mod command {
+    pub const READ_SECTORS: u8 = 0x20;
+    pub const READ_SECTORS_EXT: u8 = 0x24;
+    pub const WRITE_SECTORS: u8 = 0x30;
+    pub const WRITE_SECTORS_EXT: u8 = 0x34;
+    pub const FLUSH_CACHE: u8 = 0xE7;
+    pub const FLUSH_CACHE_EXT: u8 = 0xEA;
+    pub const PACKET: u8 = 0xA0;
+    pub const IDENTIFY_PACKET: u8 = 0xA1;
+    pub const SMART: u8 = 0xB0;
+    pub const IDENTIFY: u8 = 0xEC;
+
+    pub const SMART_READ_DATA: u8 = 0xD0;
+    /// SMART commands must carry this signature in the LBA mid and high registers.
+    pub const SMART_SIGNATURE: (u8, u8) = (0x4F, 0xC2);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AtaLocation {
     PrimaryFirst,
     PrimarySecond,
@@ -32,4 +74,808 @@ pub enum AtaLocation {
     SecondarySecond,
 }
 
-pub struct AtaDisk(());
+impl AtaLocation {
+    pub const ALL: [AtaLocation; 4] = [
+        Self::PrimaryFirst,
+        Self::PrimarySecond,
+        Self::SecondaryFirst,
+        Self::SecondarySecond,
+    ];
+
+    /// The name of the device at this location, like `ata0`.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::PrimaryFirst => "ata0",
+            Self::PrimarySecond => "ata1",
+            Self::SecondaryFirst => "ata2",
+            Self::SecondarySecond => "ata3",
+        }
+    }
+
+    /// The `(io, control)` base ports for this location's bus.
+    const fn ports(&self) -> (u16, u16) {
+        match self {
+            Self::PrimaryFirst | Self::PrimarySecond => (0x1F0, 0x3F6),
+            Self::SecondaryFirst | Self::SecondarySecond => (0x170, 0x376),
+        }
+    }
+
+    const fn is_second(&self) -> bool {
+        matches!(self, Self::PrimarySecond | Self::SecondarySecond)
+    }
+}
+
+/// # Smart Attribute
+/// One entry of the vendor specific SMART attribute table.
+#[derive(Debug, Clone, Copy)]
+pub struct SmartAttribute {
+    pub id: u8,
+    pub flags: u16,
+    pub current: u8,
+    pub worst: u8,
+    pub raw: u64,
+}
+
+impl SmartAttribute {
+    /// The common name for attributes most drives agree on.
+    pub const fn name(&self) -> Option<&'static str> {
+        Some(match self.id {
+            1 => "Raw Read Error Rate",
+            5 => "Reallocated Sector Count",
+            9 => "Power On Hours",
+            12 => "Power Cycle Count",
+            194 => "Temperature",
+            196 => "Reallocation Event Count",
+            197 => "Current Pending Sectors",
+            198 => "Offline Uncorrectable",
+            199 => "UDMA CRC Error Count",
+            _ => return None,
+        })
+    }
+}
+
+/// # Smart Data
+/// The health attributes returned by `SMART READ DATA`.
+pub struct SmartData {
+    pub revision: u16,
+    attributes: [SmartAttribute; Self::MAX_ATTRIBUTES],
+}
+
+impl SmartData {
+    const MAX_ATTRIBUTES: usize = 30;
+    const ATTRIBUTE_SIZE: usize = 12;
+
+    fn parse(raw: &[u8; SECTOR_SIZE]) -> Self {
+        let attributes = core::array::from_fn(|index| {
+            let entry = &raw[2 + index * Self::ATTRIBUTE_SIZE..][..Self::ATTRIBUTE_SIZE];
+            let mut raw_value = [0; 8];
+            raw_value[..6].copy_from_slice(&entry[5..11]);
+
+            SmartAttribute {
+                id: entry[0],
+                flags: u16::from_le_bytes([entry[1], entry[2]]),
+                current: entry[3],
+                worst: entry[4],
+                raw: u64::from_le_bytes(raw_value),
+            }
+        });
+
+        Self {
+            revision: u16::from_le_bytes([raw[0], raw[1]]),
+            attributes,
+        }
+    }
+
+    /// The attributes the drive reported, skipping empty slots.
+    pub fn attributes(&self) -> impl Iterator<Item = &SmartAttribute> {
+        self.attributes.iter().filter(|attr| attr.id != 0)
+    }
+}
+
+/// The registers of one drive on an ATA bus, and the polling logic shared by every kind
+/// of device attached to it.
+struct AtaChannel {
+    location: AtaLocation,
+    data: DataReg,
+    error: ErrorReg,
+    features: FeaturesReg,
+    sector_count: SectorCountReg,
+    sector_number: SectorNumberRegs,
+    drive: DriveReg,
+    status: StatusReg,
+    command: CommandReg,
+    alt_status: AltStatusReg,
+    device_control: DeviceControlReg,
+}
+
+/// What kind of device answered an identify command.
+enum Identify {
+    Ata([u8; SECTOR_SIZE]),
+    Packet([u8; SECTOR_SIZE]),
+}
+
+impl AtaChannel {
+    fn new(location: AtaLocation) -> Self {
+        let (io, control) = location.ports();
+
+        unsafe {
+            Self {
+                location,
+                data: DataReg::new(UserIO::new(io)),
+                error: ErrorReg::new(UserIO::new(io + 1)),
+                features: FeaturesReg::new(UserIO::new(io + 1)),
+                sector_count: SectorCountReg::new(UserIO::new(io + 2)),
+                sector_number: SectorNumberRegs::new(
+                    UserIO::new(io + 3),
+                    UserIO::new(io + 4),
+                    UserIO::new(io + 5),
+                ),
+                drive: DriveReg::new(UserIO::new(io + 6)),
+                status: StatusReg::new(UserIO::new(io + 7)),
+                command: CommandReg::new(UserIO::new(io + 7)),
+                alt_status: AltStatusReg::new(UserIO::new(control)),
+                device_control: DeviceControlReg::new(UserIO::new(control)),
+            }
+        }
+    }
+
+    /// The status register needs ~400ns to reflect a newly selected drive, reading the
+    /// alternate status 4 times is the usual way to wait that long.
+    fn delay_400ns(&self) {
+        for _ in 0..4 {
+            unsafe { self.alt_status.read() };
+        }
+    }
+
+    fn select(&mut self, lba_top: u8) {
+        let drive = ((self.location.is_second() as u8) << DriveReg::DRV_BIT)
+            | (1 << DriveReg::LBA_BIT)
+            | DriveReg::OBSOLETE_BITS;
+
+        unsafe { self.drive.write(drive | (lba_top & 0x0F)) };
+        self.delay_400ns();
+    }
+
+    fn wait_not_busy(&self) -> Result<StatusValue> {
+        for _ in 0..POLL_LIMIT {
+            let status = unsafe { self.alt_status.read() };
+            if !status.busy() {
+                return Ok(status);
+            }
+        }
+
+        Err(FsError::DiskError(DiskError::Timeout))
+    }
+
+    /// Wait for the device to either request data or report an error.
+    fn wait_data(&self) -> Result<()> {
+        for _ in 0..POLL_LIMIT {
+            let status = unsafe { self.alt_status.read() };
+
+            if status.busy() {
+                continue;
+            }
+
+            if status.error() {
+                return Err(self.read_error());
+            }
+
+            if status.drive_fault() {
+                return Err(FsError::DiskError(DiskError::DeviceFault));
+            }
+
+            if status.data_request() {
+                return Ok(());
+            }
+        }
+
+        Err(FsError::DiskError(DiskError::Timeout))
+    }
+
+    fn read_error(&self) -> FsError {
+        let error = unsafe { self.error.read_lba28() };
+        dbugln!("ATA {:?}: error register {}", self.location, error);
+
+        FsError::DiskError(error.disk_error())
+    }
+
+    /// Decode the error of a failed packet command, falling back to `err` if the device
+    /// did not report one.
+    fn packet_error(&self, err: FsError) -> FsError {
+        if !unsafe { self.alt_status.read() }.error() {
+            return err;
+        }
+
+        let error = unsafe { self.error.read_lba28() };
+        FsError::DiskError(error.packet_error())
+    }
+
+    fn read_data(&mut self, buffer: &mut [u8]) {
+        for word in buffer.chunks_exact_mut(2) {
+            word.copy_from_slice(&unsafe { self.data.read() }.to_le_bytes());
+        }
+
+        // Reading the status register acknowledges the transfer.
+        unsafe { self.status.read() };
+    }
+
+    fn write_data(&mut self, buffer: &[u8]) {
+        for word in buffer.chunks_exact(2) {
+            unsafe { self.data.write(u16::from_le_bytes([word[0], word[1]])) };
+        }
+    }
+
+    /// Wait for the device to finish a command that transfers no more data, like the end
+    /// of a write.
+    fn wait_done(&self) -> Result<()> {
+        let status = self.wait_not_busy()?;
+
+        if status.error() {
+            return Err(self.read_error());
+        }
+
+        if status.drive_fault() {
+            return Err(FsError::DiskError(DiskError::DeviceFault));
+        }
+
+        // Reading the status register acknowledges the command.
+        unsafe { self.status.read() };
+        Ok(())
+    }
+
+    /// Reset both drives on this bus by pulsing `SRST`.
+    ///
+    /// This is the only way to recover a drive that is stuck busy, and it also clears
+    /// any error state left over from a failed command.
+    fn soft_reset(&mut self) -> Result<()> {
+        let nien = 1 << DeviceControlReg::NIEN_BIT;
+
+        unsafe {
+            self.device_control
+                .write(nien | (1 << DeviceControlReg::SRST_BIT))
+        };
+        // SRST needs to be held for at least 5us
+        for _ in 0..8 {
+            self.delay_400ns();
+        }
+        unsafe { self.device_control.write(nien) };
+
+        self.delay_400ns();
+        self.wait_not_busy()?;
+        self.select(0);
+        self.wait_not_busy()?;
+
+        Ok(())
+    }
+
+    /// Run `f` until it succeeds or `MAX_ATTEMPTS` is reached, resetting the bus between
+    /// attempts.
+    fn with_retries<T>(&mut self, mut f: impl FnMut(&mut Self) -> Result<T>) -> Result<T> {
+        let mut attempt = 1;
+
+        loop {
+            let error = match f(self) {
+                Ok(value) => return Ok(value),
+                // These will never succeed, so retrying would only waste time
+                Err(
+                    error @ FsError::DiskError(
+                        DiskError::BadBlock
+                        | DiskError::NoDevice
+                        | DiskError::NoMedia
+                        | DiskError::Aborted,
+                    ),
+                ) => return Err(error),
+                Err(error) if attempt >= MAX_ATTEMPTS => return Err(error),
+                Err(error) => error,
+            };
+
+            dbugln!(
+                "ATA {:?}: attempt {attempt}/{MAX_ATTEMPTS} failed with {error:?}, resetting...",
+                self.location
+            );
+            attempt += 1;
+
+            self.soft_reset()?;
+        }
+    }
+
+    fn identify(&mut self) -> Result<Identify> {
+        if unsafe { self.alt_status.read() }.is_floating() {
+            return Err(FsError::DiskError(DiskError::NoDevice));
+        }
+
+        self.select(0);
+
+        unsafe {
+            self.sector_count.write(0);
+            self.sector_number.write(0, 0, 0);
+            self.command.write(command::IDENTIFY);
+        }
+
+        if unsafe { self.alt_status.read() }.is_floating() {
+            return Err(FsError::DiskError(DiskError::NoDevice));
+        }
+
+        self.wait_not_busy()?;
+
+        // Packet and SATA devices abort IDENTIFY and leave their signature in LBA mid/high
+        let mut raw = [0; SECTOR_SIZE];
+        match unsafe { self.sector_number.read() } {
+            (_, 0, 0) => {
+                self.wait_data()?;
+                self.read_data(&mut raw);
+
+                Ok(Identify::Ata(raw))
+            }
+            (_, 0x14, 0xEB) => {
+                unsafe { self.command.write(command::IDENTIFY_PACKET) };
+                self.wait_data()?;
+                self.read_data(&mut raw);
+
+                Ok(Identify::Packet(raw))
+            }
+            _ => Err(FsError::NotSupported),
+        }
+    }
+}
+
+/// # Disk Features
+/// The optional features a device says it supports in its identify data.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskFeatures {
+    /// 48-bit addressing, for disks over 128GiB and reads of up to 65535 sectors
+    pub lba48: bool,
+    pub smart: bool,
+    pub dma: bool,
+    pub write_cache: bool,
+    /// `DATA SET MANAGEMENT`, which is how SSDs are told sectors are unused
+    pub trim: bool,
+}
+
+/// # Disk Identity
+/// Who made a device and what it can do, as reported by `IDENTIFY (PACKET) DEVICE`.
+#[derive(Debug, Clone, Copy)]
+pub struct DiskIdentity {
+    model: [u8; 40],
+    serial: [u8; 20],
+    firmware: [u8; 8],
+    pub features: DiskFeatures,
+}
+
+impl DiskIdentity {
+    fn parse(raw: &[u8; SECTOR_SIZE]) -> Self {
+        let word = |index: usize| u16::from_le_bytes([raw[index * 2], raw[index * 2 + 1]]);
+
+        Self {
+            model: Self::string(raw, 27),
+            serial: Self::string(raw, 10),
+            firmware: Self::string(raw, 23),
+            features: DiskFeatures {
+                lba48: word(83) & (1 << 10) != 0,
+                smart: word(82) & 1 != 0,
+                dma: word(49) & (1 << 8) != 0,
+                write_cache: word(82) & (1 << 5) != 0,
+                trim: word(169) & 1 != 0,
+            },
+        }
+    }
+
+    /// Read the string starting at `first_word`, it is stored with each pair of characters
+    /// swapped.
+    fn string<const LEN: usize>(raw: &[u8; SECTOR_SIZE], first_word: usize) -> [u8; LEN] {
+        let mut string = [b' '; LEN];
+        for (chunk, word) in string
+            .chunks_exact_mut(2)
+            .zip(raw[first_word * 2..].chunks_exact(2))
+        {
+            chunk.copy_from_slice(&[word[1], word[0]]);
+        }
+
+        string
+    }
+
+    fn str(bytes: &[u8]) -> &str {
+        core::str::from_utf8(bytes).unwrap_or("<invalid>").trim()
+    }
+
+    pub fn model(&self) -> &str {
+        Self::str(&self.model)
+    }
+
+    pub fn serial(&self) -> &str {
+        Self::str(&self.serial)
+    }
+
+    pub fn firmware(&self) -> &str {
+        Self::str(&self.firmware)
+    }
+}
+
+pub enum AtaDevice {
+    Disk(AtaDisk),
+    Packet(AtapiDrive),
+}
+
+/// Probe every legacy IDE location for a device.
+pub fn scan_for_disks() -> impl Iterator<Item = AtaDevice> {
+    AtaLocation::ALL.into_iter().filter_map(|location| {
+        let (io, control) = location.ports();
+        if let Err(err) = claim_io_ports(io, IO_PORTS_LEN, false) {
+            dbugln!("ATA {location:?}: bus is claimed by another driver ({err:?})");
+            return None;
+        }
+        if let Err(err) = claim_io_ports(control, 1, false) {
+            dbugln!("ATA {location:?}: bus is claimed by another driver ({err:?})");
+            let _ = release_io_ports(io, IO_PORTS_LEN);
+            return None;
+        }
+
+        let mut channel = AtaChannel::new(location);
+
+        match channel.identify() {
+            Ok(Identify::Ata(raw)) => Some(AtaDevice::Disk(AtaDisk::new(channel, &raw))),
+            Ok(Identify::Packet(raw)) => Some(AtaDevice::Packet(AtapiDrive::new(channel, &raw))),
+            Err(FsError::DiskError(DiskError::NoDevice)) => None,
+            Err(err) => {
+                dbugln!("ATA {location:?}: skipping device ({err:?})");
+                None
+            }
+        }
+    })
+}
+
+pub struct AtaDisk {
+    channel: AtaChannel,
+    sectors: u64,
+    identity: DiskIdentity,
+    block: [u8; SECTOR_SIZE],
+}
+
+impl AtaDisk {
+    fn new(channel: AtaChannel, raw: &[u8; SECTOR_SIZE]) -> Self {
+        let word = |index: usize| u16::from_le_bytes([raw[index * 2], raw[index * 2 + 1]]);
+
+        let identity = DiskIdentity::parse(raw);
+        let sectors = if identity.features.lba48 {
+            (0..4).fold(0, |acc, i| acc | (word(100 + i) as u64) << (i * 16))
+        } else {
+            (word(60) as u64) | (word(61) as u64) << 16
+        };
+
+        Self {
+            channel,
+            sectors,
+            identity,
+            block: [0; SECTOR_SIZE],
+        }
+    }
+
+    pub fn location(&self) -> AtaLocation {
+        self.channel.location
+    }
+
+    pub fn sectors(&self) -> u64 {
+        self.sectors
+    }
+
+    pub fn identity(&self) -> &DiskIdentity {
+        &self.identity
+    }
+
+    /// Select the drive and issue a read or write `command` for `count` sectors at `lba`.
+    fn issue_lba_command(
+        channel: &mut AtaChannel,
+        lba48: bool,
+        lba: u64,
+        count: usize,
+        command: (u8, u8),
+    ) {
+        if lba48 {
+            channel.select(0);
+            unsafe {
+                channel.sector_count.write((count >> 8) as u8);
+                channel.sector_number.write(
+                    (lba >> 24) as u8,
+                    (lba >> 32) as u8,
+                    (lba >> 40) as u8,
+                );
+                channel.sector_count.write(count as u8);
+                channel
+                    .sector_number
+                    .write(lba as u8, (lba >> 8) as u8, (lba >> 16) as u8);
+                channel.command.write(command.1);
+            }
+        } else {
+            channel.select((lba >> 24) as u8);
+            unsafe {
+                channel.sector_count.write(count as u8);
+                channel
+                    .sector_number
+                    .write(lba as u8, (lba >> 8) as u8, (lba >> 16) as u8);
+                channel.command.write(command.0);
+            }
+        }
+    }
+
+    fn read_sectors_once(
+        channel: &mut AtaChannel,
+        lba48: bool,
+        lba: u64,
+        buffers: &mut [&mut [u8]],
+    ) -> Result<()> {
+        let count = buffers.iter().map(|buffer| buffer.len()).sum::<usize>() / SECTOR_SIZE;
+        Self::issue_lba_command(
+            channel,
+            lba48,
+            lba,
+            count,
+            (command::READ_SECTORS, command::READ_SECTORS_EXT),
+        );
+
+        let sectors = buffers
+            .iter_mut()
+            .flat_map(|buffer| buffer.chunks_exact_mut(SECTOR_SIZE));
+        for sector in sectors {
+            channel.wait_data()?;
+            channel.read_data(sector);
+        }
+
+        Ok(())
+    }
+
+    fn write_sectors_once(
+        channel: &mut AtaChannel,
+        lba48: bool,
+        lba: u64,
+        buffers: &[&[u8]],
+    ) -> Result<()> {
+        let count = buffers.iter().map(|buffer| buffer.len()).sum::<usize>() / SECTOR_SIZE;
+        Self::issue_lba_command(
+            channel,
+            lba48,
+            lba,
+            count,
+            (command::WRITE_SECTORS, command::WRITE_SECTORS_EXT),
+        );
+
+        let sectors = buffers
+            .iter()
+            .flat_map(|buffer| buffer.chunks_exact(SECTOR_SIZE));
+        for sector in sectors {
+            channel.wait_data()?;
+            channel.write_data(sector);
+        }
+
+        channel.wait_done()
+    }
+
+    /// Read whole sectors starting at `lba` into `buffer`.
+    ///
+    /// Failed reads are retried with a bus reset in between, unless the drive reports an
+    /// error that will never go away.
+    pub fn read_sectors(&mut self, lba: u64, buffer: &mut [u8]) -> Result<()> {
+        self.read_sectors_vectored(lba, &mut [buffer])
+    }
+
+    /// Read whole sectors starting at `lba` into each of `buffers` in turn, with one
+    /// command to the drive.
+    pub fn read_sectors_vectored(&mut self, lba: u64, buffers: &mut [&mut [u8]]) -> Result<()> {
+        let lens = buffers.iter().map(|buffer| buffer.len());
+        self.check_transfer(lba, lens)?;
+
+        let lba48 = self.identity.features.lba48;
+        self.channel
+            .with_retries(|channel| Self::read_sectors_once(channel, lba48, lba, buffers))
+    }
+
+    /// Write whole sectors from `buffer` starting at `lba`.
+    ///
+    /// The drive may hold on to the sectors in its write cache, use `flush_cache` to make
+    /// sure they reach the media.
+    pub fn write_sectors(&mut self, lba: u64, buffer: &[u8]) -> Result<()> {
+        self.write_sectors_vectored(lba, &[buffer])
+    }
+
+    /// Write whole sectors from each of `buffers` in turn starting at `lba`, with one
+    /// command to the drive.
+    pub fn write_sectors_vectored(&mut self, lba: u64, buffers: &[&[u8]]) -> Result<()> {
+        let lens = buffers.iter().map(|buffer| buffer.len());
+        self.check_transfer(lba, lens)?;
+
+        let lba48 = self.identity.features.lba48;
+        self.channel
+            .with_retries(|channel| Self::write_sectors_once(channel, lba48, lba, buffers))
+    }
+
+    /// Make sure buffers of `lens` starting at `lba` can be moved with one command.
+    fn check_transfer(&self, lba: u64, lens: impl Iterator<Item = usize>) -> Result<()> {
+        let mut len: usize = 0;
+        for buffer_len in lens {
+            if buffer_len % SECTOR_SIZE != 0 {
+                return Err(FsError::InvalidInput);
+            }
+
+            len = len.checked_add(buffer_len).ok_or(FsError::InvalidInput)?;
+        }
+
+        let count = len / SECTOR_SIZE;
+        if count == 0 || count > self.max_sectors_per_read() {
+            return Err(FsError::InvalidInput);
+        }
+
+        if lba
+            .checked_add(count as u64)
+            .is_none_or(|end| end > self.sectors)
+        {
+            return Err(FsError::EndOfFile);
+        }
+
+        Ok(())
+    }
+
+    /// How many of the leading buffers with `lens` fit in one command, and how many
+    /// sectors they make up.
+    fn gather_sectors(&self, lens: impl Iterator<Item = usize>) -> (usize, usize) {
+        let max_count = self.max_sectors_per_read();
+        let mut count = 0;
+
+        let gathered = lens
+            .take_while(|len| {
+                let fits = count + len / SECTOR_SIZE <= max_count;
+                if fits {
+                    count += len / SECTOR_SIZE;
+                }
+                fits
+            })
+            .count();
+
+        (gathered, count)
+    }
+
+    /// Write back everything in the drive's write cache.
+    pub fn flush_cache(&mut self) -> Result<()> {
+        // Without a write cache every write goes straight to the media
+        if !self.identity.features.write_cache {
+            return Ok(());
+        }
+
+        let flush = match self.identity.features.lba48 {
+            true => command::FLUSH_CACHE_EXT,
+            false => command::FLUSH_CACHE,
+        };
+
+        self.channel.with_retries(|channel| {
+            channel.select(0);
+            unsafe { channel.command.write(flush) };
+            channel.wait_done()
+        })
+    }
+
+    /// The most sectors one read or write command can transfer.
+    fn max_sectors_per_read(&self) -> usize {
+        if self.identity.features.lba48 {
+            u16::MAX as usize
+        } else {
+            u8::MAX as usize
+        }
+    }
+
+    /// Read the drive's SMART health attributes.
+    pub fn smart_read(&mut self) -> Result<SmartData> {
+        if !self.identity.features.smart {
+            return Err(FsError::NotSupported);
+        }
+
+        let mut raw = [0; SECTOR_SIZE];
+        self.channel.with_retries(|channel| {
+            channel.select(0);
+            unsafe {
+                channel.features.write(command::SMART_READ_DATA);
+                channel.sector_count.write(0);
+                channel.sector_number.write(
+                    0,
+                    command::SMART_SIGNATURE.0,
+                    command::SMART_SIGNATURE.1,
+                );
+                channel.command.write(command::SMART);
+            }
+
+            channel.wait_data()?;
+            channel.read_data(&mut raw);
+            Ok(())
+        })?;
+
+        Ok(SmartData::parse(&raw))
+    }
+}
+
+impl BlockDevice for AtaDisk {
+    const BLOCK_SIZE: usize = SECTOR_SIZE;
+
+    fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+        let mut block = [0; SECTOR_SIZE];
+        self.read_sectors(block_offset, &mut block)?;
+        self.block = block;
+
+        Ok(&self.block)
+    }
+
+    fn read_blocks(&mut self, block_offset: u64, buf: &mut [u8]) -> Result<()> {
+        let max_count = self.max_sectors_per_read();
+
+        for (index, chunk) in buf.chunks_mut(max_count * SECTOR_SIZE).enumerate() {
+            self.read_sectors(block_offset + (index * max_count) as u64, chunk)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_blocks(&mut self, block_offset: u64, buf: &[u8]) -> Result<()> {
+        let max_count = self.max_sectors_per_read();
+
+        for (index, chunk) in buf.chunks(max_count * SECTOR_SIZE).enumerate() {
+            self.write_sectors(block_offset + (index * max_count) as u64, chunk)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_blocks_vectored(&mut self, block_offset: u64, bufs: &mut [&mut [u8]]) -> Result<()> {
+        let mut lba = block_offset;
+        let mut rest = bufs;
+
+        while !rest.is_empty() {
+            let (gathered, count) = self.gather_sectors(rest.iter().map(|buf| buf.len()));
+
+            // A buffer too large for one command on its own is split up instead
+            if gathered == 0 {
+                let (buf, tail) = core::mem::take(&mut rest).split_first_mut().unwrap();
+                self.read_blocks(lba, buf)?;
+                lba += (buf.len() / SECTOR_SIZE) as u64;
+                rest = tail;
+                continue;
+            }
+
+            let (batch, tail) = core::mem::take(&mut rest).split_at_mut(gathered);
+            if count != 0 {
+                self.read_sectors_vectored(lba, batch)?;
+            }
+
+            lba += count as u64;
+            rest = tail;
+        }
+
+        Ok(())
+    }
+
+    fn write_blocks_vectored(&mut self, block_offset: u64, bufs: &[&[u8]]) -> Result<()> {
+        let mut lba = block_offset;
+        let mut rest = bufs;
+
+        while !rest.is_empty() {
+            let (gathered, count) = self.gather_sectors(rest.iter().map(|buf| buf.len()));
+
+            // A buffer too large for one command on its own is split up instead
+            if gathered == 0 {
+                self.write_blocks(lba, rest[0])?;
+                lba += (rest[0].len() / SECTOR_SIZE) as u64;
+                rest = &rest[1..];
+                continue;
+            }
+
+            let (batch, tail) = rest.split_at(gathered);
+            if count != 0 {
+                self.write_sectors_vectored(lba, batch)?;
+            }
+
+            lba += count as u64;
+            rest = tail;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.flush_cache()
+    }
+}
@@ -0,0 +1,241 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use super::{AtaChannel, AtaLocation, DiskIdentity, SECTOR_SIZE, command};
+use aloe::dbugln;
+use fs::{
+    error::{DiskError, FsError, Result},
+    read_block::BlockDevice,
+};
+
+/// Optical media uses 2048 byte sectors.
+pub const ATAPI_SECTOR_SIZE: usize = 2048;
+
+/// The largest byte count a device is asked to transfer for each data request.
+const BYTE_COUNT_LIMIT: usize = 0xF800;
+
+mod scsi {
+    pub const TEST_UNIT_READY: u8 = 0x00;
+    pub const READ_CAPACITY: u8 = 0x25;
+    pub const READ_12: u8 = 0xA8;
+}
+
+/// # Atapi Drive
+/// A packet device (usually a CD-ROM) on an IDE bus.
+pub struct AtapiDrive {
+    channel: AtaChannel,
+    identity: DiskIdentity,
+    /// The `(blocks, block size)` of the inserted media, cleared when the media changes.
+    capacity: Option<(u64, u32)>,
+    block: [u8; ATAPI_SECTOR_SIZE],
+}
+
+impl AtapiDrive {
+    pub(super) fn new(channel: AtaChannel, raw: &[u8; SECTOR_SIZE]) -> Self {
+        Self {
+            channel,
+            identity: DiskIdentity::parse(raw),
+            capacity: None,
+            block: [0; ATAPI_SECTOR_SIZE],
+        }
+    }
+
+    pub fn location(&self) -> AtaLocation {
+        self.channel.location
+    }
+
+    pub fn identity(&self) -> &DiskIdentity {
+        &self.identity
+    }
+
+    /// Send a 12 byte SCSI `packet` and read any data the device returns into `buffer`.
+    ///
+    /// Returns the number of bytes the device transferred, which can be more than
+    /// `buffer` could hold; the extra bytes are dropped.
+    fn packet(channel: &mut AtaChannel, packet: &[u8; 12], buffer: &mut [u8]) -> Result<usize> {
+        let limit = buffer.len().clamp(2, BYTE_COUNT_LIMIT) as u16;
+
+        channel.select(0);
+        unsafe {
+            // PIO transfers, no DMA or overlap
+            channel.features.write(0);
+            channel
+                .sector_number
+                .write(0, limit as u8, (limit >> 8) as u8);
+            channel.command.write(command::PACKET);
+        }
+
+        channel
+            .wait_data()
+            .map_err(|err| channel.packet_error(err))?;
+        for word in packet.chunks_exact(2) {
+            unsafe { channel.data.write(u16::from_le_bytes([word[0], word[1]])) };
+        }
+        channel.delay_400ns();
+
+        let mut transferred = 0;
+        loop {
+            let status = channel.wait_not_busy()?;
+
+            if status.error() {
+                return Err(channel.packet_error(FsError::DiskError(DiskError::DeviceFault)));
+            }
+
+            if !status.data_request() {
+                break;
+            }
+
+            let (_, lo, hi) = unsafe { channel.sector_number.read() };
+            let bytes = u16::from_le_bytes([lo, hi]) as usize;
+
+            for _ in 0..bytes.div_ceil(2) {
+                let word = unsafe { channel.data.read() }.to_le_bytes();
+
+                for byte in word {
+                    if let Some(slot) = buffer.get_mut(transferred) {
+                        *slot = byte;
+                    }
+                    transferred += 1;
+                }
+            }
+
+            channel.delay_400ns();
+        }
+
+        // Reading the status register acknowledges the command.
+        unsafe { channel.status.read() };
+        Ok(transferred)
+    }
+
+    fn retry_packet(&mut self, packet: &[u8; 12], buffer: &mut [u8]) -> Result<usize> {
+        let result = self
+            .channel
+            .with_retries(|channel| Self::packet(channel, packet, buffer));
+
+        if let Err(FsError::DiskError(DiskError::MediaChanged | DiskError::NoMedia)) = result {
+            self.capacity = None;
+        }
+
+        result
+    }
+
+    /// Check if there is media in the drive.
+    ///
+    /// A drive reports a media change once after new media is inserted, so the check is
+    /// repeated when that happens.
+    pub fn media_present(&mut self) -> Result<bool> {
+        let packet = [scsi::TEST_UNIT_READY, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        for _ in 0..2 {
+            match self.retry_packet(&packet, &mut []) {
+                Ok(_) => return Ok(true),
+                Err(FsError::DiskError(DiskError::NoMedia)) => return Ok(false),
+                Err(FsError::DiskError(DiskError::MediaChanged)) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Get the `(blocks, block size)` of the inserted media.
+    pub fn read_capacity(&mut self) -> Result<(u64, u32)> {
+        if let Some(capacity) = self.capacity {
+            return Ok(capacity);
+        }
+
+        let packet = [scsi::READ_CAPACITY, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut response = [0; 8];
+
+        if self.retry_packet(&packet, &mut response)? < response.len() {
+            return Err(FsError::ReadError);
+        }
+
+        let last_lba = u32::from_be_bytes(response[0..4].try_into().unwrap());
+        let block_size = u32::from_be_bytes(response[4..8].try_into().unwrap());
+
+        if block_size as usize != ATAPI_SECTOR_SIZE {
+            dbugln!(
+                "ATAPI {:?}: media has {block_size} byte blocks, which is not supported",
+                self.channel.location
+            );
+            return Err(FsError::NotSupported);
+        }
+
+        let capacity = (last_lba as u64 + 1, block_size);
+        self.capacity = Some(capacity);
+
+        Ok(capacity)
+    }
+
+    /// Read whole 2048 byte sectors starting at `lba` into `buffer`.
+    pub fn read_sectors(&mut self, lba: u64, buffer: &mut [u8]) -> Result<()> {
+        let count = buffer.len() / ATAPI_SECTOR_SIZE;
+
+        if buffer.len() % ATAPI_SECTOR_SIZE != 0 || count == 0 {
+            return Err(FsError::InvalidInput);
+        }
+
+        let (blocks, _) = self.read_capacity()?;
+        if lba.checked_add(count as u64).is_none_or(|end| end > blocks) {
+            return Err(FsError::EndOfFile);
+        }
+
+        let lba = (lba as u32).to_be_bytes();
+        let count = (count as u32).to_be_bytes();
+        let packet = [
+            scsi::READ_12,
+            0,
+            lba[0],
+            lba[1],
+            lba[2],
+            lba[3],
+            count[0],
+            count[1],
+            count[2],
+            count[3],
+            0,
+            0,
+        ];
+
+        if self.retry_packet(&packet, buffer)? < buffer.len() {
+            return Err(FsError::ReadError);
+        }
+
+        Ok(())
+    }
+}
+
+impl BlockDevice for AtapiDrive {
+    const BLOCK_SIZE: usize = ATAPI_SECTOR_SIZE;
+
+    fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+        let mut block = [0; ATAPI_SECTOR_SIZE];
+        self.read_sectors(block_offset, &mut block)?;
+        self.block = block;
+
+        Ok(&self.block)
+    }
+}
@@ -23,7 +23,10 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 */
 
+use core::fmt::Display;
+
 use aloe::uio::{CpuIO, UserIO, opt};
+use fs::error::DiskError;
 
 type IoRw<OwnKind> = UserIO<CpuIO, opt::ReadWrite, OwnKind>;
 type IoRo<OwnKind> = UserIO<CpuIO, opt::ReadOnly, OwnKind>;
@@ -72,9 +75,72 @@ impl ErrorValue {
     pub const UNC_BIT: u8 = 6;
     pub const BBK_BIT: u8 = 7;
 
+    /// Each error bit with its short name and decoded error, most severe first.
+    const DECODE: [(u8, &'static str, DiskError); 8] = [
+        (Self::BBK_BIT, "BBK", DiskError::BadBlock),
+        (Self::UNC_BIT, "UNC", DiskError::Uncorrectable),
+        (Self::IDNF_BIT, "IDNF", DiskError::IdNotFound),
+        (Self::AMNF_BIT, "AMNF", DiskError::AddressMarkNotFound),
+        (Self::TKZNF_BIT, "TKZNF", DiskError::Track0NotFound),
+        (Self::MC_BIT, "MC", DiskError::MediaChanged),
+        (Self::MCR_BIT, "MCR", DiskError::MediaChangeRequest),
+        (Self::ABRT_BIT, "ABRT", DiskError::Aborted),
+    ];
+
     pub const fn any_error(&self) -> bool {
         self.0 != 0
     }
+
+    pub const fn is_set(&self, bit: u8) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    /// Decode the most severe error in this register.
+    ///
+    /// Devices sometimes set `ERR` in the status register while leaving the error register
+    /// empty, so an empty register is reported as a device fault.
+    pub fn disk_error(&self) -> DiskError {
+        Self::DECODE
+            .iter()
+            .find(|(bit, _, _)| self.is_set(*bit))
+            .map(|(_, _, error)| *error)
+            .unwrap_or(DiskError::DeviceFault)
+    }
+
+    /// Packet devices reuse the top nibble of the error register for the SCSI sense key.
+    pub const fn sense_key(&self) -> u8 {
+        self.0 >> 4
+    }
+
+    /// Decode the error of a failed packet command from its sense key.
+    pub fn packet_error(&self) -> DiskError {
+        match self.sense_key() {
+            0x2 => DiskError::NoMedia,
+            0x3 => DiskError::Uncorrectable,
+            0x4 => DiskError::DeviceFault,
+            0x5 => DiskError::Aborted,
+            0x6 => DiskError::MediaChanged,
+            _ if self.is_set(Self::ABRT_BIT) => DiskError::Aborted,
+            _ => DiskError::DeviceFault,
+        }
+    }
+}
+
+impl Display for ErrorValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:#04x} [", self.0)?;
+
+        let mut first = true;
+        for (_, name, _) in Self::DECODE.iter().filter(|(bit, _, _)| self.is_set(*bit)) {
+            if !first {
+                write!(f, " ")?;
+            }
+            write!(f, "{name}")?;
+            first = false;
+        }
+
+        write!(f, "]")
+    }
 }
 
 impl ErrorReg {
@@ -95,4 +161,136 @@ impl FeaturesReg {
     pub fn new(port: IoWo<opt::Shared>) -> Self {
         Self(port)
     }
+
+    pub unsafe fn write(&mut self, value: u8) {
+        unsafe { self.0.write_u8(value) };
+    }
+}
+
+impl SectorCountReg {
+    pub fn new(port: IoRw<opt::Owned>) -> Self {
+        Self(port)
+    }
+
+    pub unsafe fn write(&mut self, value: u8) {
+        unsafe { self.0.write_u8(value) };
+    }
+}
+
+impl SectorNumberRegs {
+    pub fn new(lo: IoRw<opt::Owned>, mi: IoRw<opt::Owned>, hi: IoRw<opt::Owned>) -> Self {
+        Self { lo, mi, hi }
+    }
+
+    /// Read the `(lo, mid, hi)` LBA bytes.
+    pub unsafe fn read(&self) -> (u8, u8, u8) {
+        unsafe { (self.lo.read_u8(), self.mi.read_u8(), self.hi.read_u8()) }
+    }
+
+    /// Write the `(lo, mid, hi)` LBA bytes.
+    pub unsafe fn write(&mut self, lo: u8, mi: u8, hi: u8) {
+        unsafe {
+            self.lo.write_u8(lo);
+            self.mi.write_u8(mi);
+            self.hi.write_u8(hi);
+        }
+    }
+}
+
+impl DriveReg {
+    /// Always set bits that were once the sector size, and must still be set.
+    pub const OBSOLETE_BITS: u8 = 0xA0;
+    /// Use LBA addressing instead of CHS.
+    pub const LBA_BIT: u8 = 6;
+    /// Select the second drive on the bus.
+    pub const DRV_BIT: u8 = 4;
+
+    pub fn new(port: IoRw<opt::Owned>) -> Self {
+        Self(port)
+    }
+
+    pub unsafe fn write(&mut self, value: u8) {
+        unsafe { self.0.write_u8(value) };
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct StatusValue(u8);
+
+impl StatusValue {
+    pub const ERR_BIT: u8 = 0;
+    pub const DRQ_BIT: u8 = 3;
+    pub const DF_BIT: u8 = 5;
+    pub const BSY_BIT: u8 = 7;
+
+    /// A floating bus reads back as all ones, and an empty one as zero.
+    pub const fn is_floating(&self) -> bool {
+        self.0 == 0xFF || self.0 == 0
+    }
+
+    pub const fn is_set(&self, bit: u8) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    pub const fn busy(&self) -> bool {
+        self.is_set(Self::BSY_BIT)
+    }
+
+    pub const fn data_request(&self) -> bool {
+        self.is_set(Self::DRQ_BIT)
+    }
+
+    pub const fn error(&self) -> bool {
+        self.is_set(Self::ERR_BIT)
+    }
+
+    pub const fn drive_fault(&self) -> bool {
+        self.is_set(Self::DF_BIT)
+    }
+}
+
+impl StatusReg {
+    pub fn new(port: IoRo<opt::Shared>) -> Self {
+        Self(port)
+    }
+
+    /// Reading this register clears a pending interrupt, use [`AltStatusReg`] to poll.
+    pub unsafe fn read(&self) -> StatusValue {
+        unsafe { StatusValue(self.0.read_u8()) }
+    }
+}
+
+impl CommandReg {
+    pub fn new(port: IoWo<opt::Shared>) -> Self {
+        Self(port)
+    }
+
+    pub unsafe fn write(&mut self, command: u8) {
+        unsafe { self.0.write_u8(command) };
+    }
+}
+
+impl AltStatusReg {
+    pub fn new(port: IoRo<opt::Shared>) -> Self {
+        Self(port)
+    }
+
+    pub unsafe fn read(&self) -> StatusValue {
+        unsafe { StatusValue(self.0.read_u8()) }
+    }
+}
+
+impl DeviceControlReg {
+    /// Disable interrupts from the device.
+    pub const NIEN_BIT: u8 = 1;
+    /// Software reset of every drive on the bus.
+    pub const SRST_BIT: u8 = 2;
+
+    pub fn new(port: IoWo<opt::Shared>) -> Self {
+        Self(port)
+    }
+
+    pub unsafe fn write(&mut self, value: u8) {
+        unsafe { self.0.write_u8(value) };
+    }
 }
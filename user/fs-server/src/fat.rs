@@ -0,0 +1,203 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::vfs::{FileSystem, MountOptions};
+use aloe::dbugln;
+use fs::{
+    error::FsError,
+    fatfs::{Fat, FatKind, ReadSeek},
+    io::{Read, Seek, SeekFrom, Write},
+};
+use fs_portal::{FileStat, QuantumError};
+
+/// # Fat Volume
+/// The FAT driver, as a filesystem that can be mounted.
+pub struct FatVolume<Part: ReadSeek + Write> {
+    fat: Fat<Part>,
+    options: MountOptions,
+}
+
+impl<Part: ReadSeek + Write> FatVolume<Part> {
+    pub fn new(disk: Part) -> Result<Self, QuantumError> {
+        let mut fat = Fat::new(disk)?;
+
+        if !fat.is_volume_clean().unwrap_or(true) {
+            dbugln!(
+                "FAT volume '{}' was not cleanly unmounted, the last writes to it may be lost",
+                fat.volume_label().trim()
+            );
+        }
+
+        Ok(Self {
+            fat,
+            options: MountOptions { read_only: true },
+        })
+    }
+
+    fn check_writable(&self) -> Result<(), QuantumError> {
+        match self.options.read_only {
+            true => Err(QuantumError::ReadOnly),
+            false => Ok(()),
+        }
+    }
+}
+
+impl<Part: ReadSeek + Write> FileSystem for FatVolume<Part> {
+    fn kind(&self) -> &'static str {
+        match self.fat.kind() {
+            FatKind::Fat12 => "fat12",
+            FatKind::Fat16 => "fat16",
+            FatKind::Fat32 => "fat32",
+        }
+    }
+
+    fn set_options(&mut self, options: MountOptions) -> Result<(), QuantumError> {
+        // Anything written so far must be on the disk before it becomes read-only
+        if options.read_only && !self.options.read_only {
+            self.fat.sync()?;
+        }
+
+        self.options = options;
+        Ok(())
+    }
+
+    fn read(&mut self, path: &str, offset: u64, buf: &mut [u8]) -> Result<usize, QuantumError> {
+        let mut file = self.fat.open(path)?;
+        let remaining = (file.filesize() as u64).saturating_sub(offset);
+        let len = buf.len().min(remaining as usize);
+        if len == 0 {
+            return Ok(0);
+        }
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.read(&mut buf[..len])?;
+        Ok(len)
+    }
+
+    fn read_vectored(
+        &mut self,
+        path: &str,
+        offset: u64,
+        bufs: &mut [&mut [u8]],
+    ) -> Result<usize, QuantumError> {
+        let mut file = self.fat.open(path)?;
+        let mut remaining = (file.filesize() as u64).saturating_sub(offset) as usize;
+
+        // Only the buffers before the end of the file are filled, the one it ends in
+        // partly
+        let whole = bufs
+            .iter()
+            .take_while(|buf| {
+                let fits = buf.len() <= remaining;
+                if fits {
+                    remaining -= buf.len();
+                }
+                fits
+            })
+            .count();
+        let (whole_bufs, rest) = bufs.split_at_mut(whole);
+        let whole_len: usize = whole_bufs.iter().map(|buf| buf.len()).sum();
+
+        file.seek(SeekFrom::Start(offset))?;
+        if whole_len != 0 {
+            file.read_vectored_exact(whole_bufs)?;
+        }
+
+        let partial = match rest.first_mut() {
+            Some(buf) if remaining != 0 => {
+                file.read_exact(&mut buf[..remaining])?;
+                remaining
+            }
+            _ => 0,
+        };
+
+        Ok(whole_len + partial)
+    }
+
+    fn write(&mut self, path: &str, offset: u64, buf: &[u8]) -> Result<usize, QuantumError> {
+        self.check_writable()?;
+
+        let mut file = self.fat.open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn append(&mut self, path: &str, buf: &[u8]) -> Result<u64, QuantumError> {
+        self.check_writable()?;
+
+        let mut file = match self.fat.open(path) {
+            Err(FsError::NotFound) => self.fat.create_file(path)?,
+            file => file?,
+        };
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(buf)?;
+        Ok(file.filesize() as u64)
+    }
+
+    fn truncate(&mut self, path: &str, len: u64) -> Result<(), QuantumError> {
+        self.check_writable()?;
+        Ok(self.fat.open(path)?.set_len(len)?)
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), QuantumError> {
+        self.check_writable()?;
+        Ok(self.fat.rename(from, to)?)
+    }
+
+    fn create_dir(&mut self, path: &str) -> Result<(), QuantumError> {
+        self.check_writable()?;
+        Ok(self.fat.create_dir(path)?)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), QuantumError> {
+        self.check_writable()?;
+        Ok(self.fat.remove(path)?)
+    }
+
+    fn stat(&mut self, path: &str) -> Result<FileStat, QuantumError> {
+        // The root of the volume has no entry of its own
+        if path.trim_matches('/').is_empty() {
+            return Ok(FileStat {
+                size: 0,
+                is_dir: true,
+            });
+        }
+
+        let entry = self.fat.entry_of(path)?;
+        Ok(FileStat {
+            size: entry.file_size() as u64,
+            is_dir: entry.is_dir(),
+        })
+    }
+
+    fn sync(&mut self) -> Result<(), QuantumError> {
+        Ok(self.fat.sync()?)
+    }
+
+    fn flush(&mut self) -> Result<(), QuantumError> {
+        Ok(self.fat.flush()?)
+    }
+}
@@ -0,0 +1,109 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::string::{String, ToString};
+use aloe::{DiskError, MAX_DISK_READ};
+use fs::{
+    block_queue::SchedulerKind,
+    error::{DiskError as FsDiskError, FsError, Result},
+    read_block::BlockDevice,
+};
+
+const BLOCK_SIZE: usize = 512;
+
+/// A disk the kernel drives itself, like a USB stick, read through syscalls
+pub struct KernelDisk {
+    index: usize,
+    model: String,
+    blocks: u64,
+    block: [u8; BLOCK_SIZE],
+}
+
+impl KernelDisk {
+    /// Every disk the kernel drives.
+    ///
+    /// Disks with blocks other than 512 bytes are skipped, since partition tables and FAT
+    /// expect them.
+    pub fn scan() -> impl Iterator<Item = Self> {
+        (0..)
+            .map_while(|index| aloe::disk_info(index).ok().map(|info| (index, info)))
+            .filter(|(_, info)| info.block_size == BLOCK_SIZE as u64)
+            .map(|(index, info)| {
+                let model_len = info.model.iter().position(|&byte| byte == 0);
+                let model = &info.model[..model_len.unwrap_or(info.model.len())];
+
+                Self {
+                    index,
+                    model: core::str::from_utf8(model).unwrap_or("?").to_string(),
+                    blocks: info.blocks,
+                    block: [0; BLOCK_SIZE],
+                }
+            })
+    }
+
+    /// The name the kernel gave this disk, its vendor and product
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// The name of this disk, as used for its mounts
+    pub fn name(&self) -> String {
+        alloc::format!("usb{}", self.index)
+    }
+}
+
+fn fs_error(err: DiskError) -> FsError {
+    match err {
+        DiskError::OutOfRange => FsError::EndOfFile,
+        DiskError::ReadFailed => FsError::DiskError(FsDiskError::Aborted),
+        _ => FsError::ReadError,
+    }
+}
+
+impl BlockDevice for KernelDisk {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    // The kernel's disks are flash, which has nothing to seek
+    const SCHEDULER: SchedulerKind = SchedulerKind::Noop;
+
+    fn read_block<'a>(&'a mut self, block_offset: u64) -> Result<&'a [u8]> {
+        aloe::disk_read(self.index, block_offset, &mut self.block).map_err(fs_error)?;
+        Ok(&self.block)
+    }
+
+    fn read_blocks(&mut self, block_offset: u64, buf: &mut [u8]) -> Result<()> {
+        let max_count = MAX_DISK_READ / BLOCK_SIZE;
+
+        for (index, chunk) in buf.chunks_mut(max_count * BLOCK_SIZE).enumerate() {
+            aloe::disk_read(self.index, block_offset + (index * max_count) as u64, chunk)
+                .map_err(fs_error)?;
+        }
+
+        Ok(())
+    }
+}
@@ -27,36 +27,542 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 #![no_main]
 tiny_std!();
 
-use fs_portal::FsPortalServer;
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use aloe::{
+    WaitSignal,
+    cmdline::Cmdline,
     dbugln,
     ipc::{QuantumGlue, QuantumHost},
     signal_wait, tiny_std,
 };
+use ata::{AtaDevice, DiskIdentity};
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+use fat::FatVolume;
+use fs::{
+    block_queue::ScheduledDisk,
+    fatfs::Fat,
+    partition::{Partition, PartitionKind},
+    read_block::BlockDevice,
+};
+use fs_portal::{DiskInfo, FsPortalServer, QuantumError};
+use kernel_disk::KernelDisk;
+use vfs::{MountOptions, Vfs};
+use watch::WatchTable;
 
 mod ata;
+mod fat;
+mod kernel_disk;
+mod path;
+mod shared;
+mod vfs;
+mod watch;
+
+/// The most bytes a single `read` can ask for
+const MAX_READ_LEN: u64 = 64 * 1024;
+/// The most buffers a single `read_vectored` can ask for
+const MAX_READ_BUFS: usize = 256;
+/// The most bytes a single `truncate` can grow a file by, as the zeros are written before
+/// any other client is answered
+const MAX_TRUNCATE_GROWTH: u64 = 16 * 1024 * 1024;
+
+/// A client connected to the fs server
+struct FsClient {
+    handle: u64,
+    portal: FsPortalServer<QuantumGlue>,
+    /// The absolute path relative paths are resolved against
+    cwd: String,
+}
+
+fn disk_info(
+    name: &str,
+    removable: bool,
+    identity: &DiskIdentity,
+    sectors: u64,
+    sector_size: u32,
+) -> DiskInfo {
+    DiskInfo {
+        name: name.to_string(),
+        removable,
+        model: identity.model().to_string(),
+        serial: identity.serial().to_string(),
+        firmware: identity.firmware().to_string(),
+        sectors,
+        sector_size,
+        lba48: identity.features.lba48,
+        smart: identity.features.smart,
+        dma: identity.features.dma,
+        write_cache: identity.features.write_cache,
+        trim: identity.features.trim,
+    }
+}
+
+/// Find every disk, mounting the filesystems on them, and return what was found.
+fn probe_disks(vfs: &mut Vfs) -> Vec<DiskInfo> {
+    let mut disks = Vec::new();
+
+    for device in ata::scan_for_disks() {
+        match device {
+            AtaDevice::Disk(mut disk) => {
+                let identity = disk.identity();
+                dbugln!(
+                    "ATA {:?}: '{}' (serial '{}', firmware '{}') with {} sectors",
+                    disk.location(),
+                    identity.model(),
+                    identity.serial(),
+                    identity.firmware(),
+                    disk.sectors()
+                );
+                disks.push(disk_info(
+                    disk.location().name(),
+                    false,
+                    identity,
+                    disk.sectors(),
+                    512,
+                ));
+
+                match disk.smart_read() {
+                    Ok(smart) => {
+                        dbugln!("  SMART revision {}", smart.revision);
+                        for attr in smart.attributes() {
+                            dbugln!(
+                                "  SMART {:>3} {:<26} flags={:#06x} current={:<3} worst={:<3} raw={}",
+                                attr.id,
+                                attr.name().unwrap_or("Unknown"),
+                                attr.flags,
+                                attr.current,
+                                attr.worst,
+                                attr.raw
+                            );
+                        }
+                    }
+                    Err(err) => dbugln!("  SMART unavailable ({err:?})"),
+                }
+
+                let name = disk.location().name();
+                let sectors = disk.sectors();
+                mount_fat(vfs, name, sectors, disk, false);
+            }
+            AtaDevice::Packet(mut drive) => {
+                dbugln!(
+                    "ATAPI {:?}: '{}'",
+                    drive.location(),
+                    drive.identity().model()
+                );
+
+                let capacity = match drive
+                    .media_present()
+                    .and_then(|present| present.then(|| drive.read_capacity()).transpose())
+                {
+                    Ok(Some((blocks, block_size))) => {
+                        dbugln!("  Media with {blocks} blocks of {block_size} bytes");
+                        (blocks, block_size)
+                    }
+                    Ok(None) => {
+                        dbugln!("  No media");
+                        (0, 0)
+                    }
+                    Err(err) => {
+                        dbugln!("  Media unavailable ({err:?})");
+                        (0, 0)
+                    }
+                };
+
+                disks.push(disk_info(
+                    drive.location().name(),
+                    true,
+                    drive.identity(),
+                    capacity.0,
+                    capacity.1,
+                ));
+            }
+        }
+    }
+
+    for disk in KernelDisk::scan() {
+        let name = disk.name();
+        dbugln!("{name}: '{}' with {} blocks", disk.model(), disk.blocks());
+        disks.push(DiskInfo {
+            name: name.clone(),
+            removable: true,
+            model: disk.model().to_string(),
+            serial: String::new(),
+            firmware: String::new(),
+            sectors: disk.blocks(),
+            sector_size: 512,
+            lba48: false,
+            smart: false,
+            dma: false,
+            write_cache: false,
+            trim: false,
+        });
+
+        // The kernel can only read its disks
+        let blocks = disk.blocks();
+        mount_fat(vfs, &name, blocks, disk, true);
+    }
+
+    disks
+}
+
+/// Mount the first FAT volume on `disk`, which is `sectors` long, looking in its MBR
+/// partitions and then at the whole disk. The first volume found is mounted at `/`, the
+/// rest under `/mnt`.
+///
+/// Every read of the volume goes through the I/O scheduler `disk` prefers.
+fn mount_fat<D: BlockDevice + 'static>(
+    vfs: &mut Vfs,
+    name: &str,
+    sectors: u64,
+    disk: D,
+    read_only: bool,
+) {
+    let mut disk = ScheduledDisk::<D>::new(disk);
+    let partition = (0..4).find(|&index| {
+        Partition::from_mbr(&mut disk, index)
+            .and_then(Fat::new)
+            .is_ok()
+    });
+
+    let (source, volume) = match partition {
+        Some(index) => (
+            format!("{name}p{}", index + 1),
+            Partition::from_mbr(disk, index).map_err(QuantumError::from),
+        ),
+        None => {
+            let len = sectors * 512;
+            (
+                String::from(name),
+                Ok(Partition::new(disk, PartitionKind::Whole, 0, len)),
+            )
+        }
+    };
+    let volume = match volume.and_then(FatVolume::new) {
+        Ok(volume) => volume,
+        Err(err) => {
+            dbugln!("{source}: No FAT volume found ({err})");
+            return;
+        }
+    };
+
+    let point = match vfs.mounts().is_empty() {
+        true => String::from("/"),
+        false => format!("/mnt/{source}"),
+    };
+    let options = MountOptions { read_only };
+    match vfs.mount(point.clone(), source.clone(), Box::new(volume), options) {
+        Ok(()) => dbugln!("Mounted {source} at {point}"),
+        Err(err) => dbugln!("Unable to mount {source} at {point} ({err})"),
+    }
+}
+
+/// What a write to `path` will do to it, so watches are told the file was created
+/// when it did not exist before.
+fn change_kind(vfs: &mut Vfs, path: &str) -> fs_portal::WatchEventKind {
+    match vfs.stat(path) {
+        Ok(_) => fs_portal::WatchEventKind::Modified,
+        Err(_) => fs_portal::WatchEventKind::Created,
+    }
+}
+
+/// The mounts to flush if the server panics, see [`flush_on_panic`]
+static PANIC_VFS: AtomicPtr<RefCell<Vfs>> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Get what was written to the disks before the server exits from a panic.
+///
+/// The volumes are only flushed and not synced, so they stay marked as not cleanly
+/// unmounted since the panic could have left a change half done. If the panic happened
+/// while the mounts were borrowed, their caches could hold a change that was cut off
+/// half way, so nothing is flushed. The volumes were already marked as not cleanly
+/// unmounted on disk before their first write, so the next mount still checks them.
+fn flush_on_panic() {
+    let vfs = PANIC_VFS.load(Ordering::Acquire);
+    if vfs.is_null() {
+        return;
+    }
+
+    // The mounts are only dropped after `PANIC_VFS` is cleared
+    let vfs = unsafe { &*vfs };
+    let Ok(mut vfs) = vfs.try_borrow_mut() else {
+        dbugln!("Panicked while using the mounts, not flushing them");
+        return;
+    };
+
+    match vfs.flush() {
+        Ok(()) => dbugln!("Flushed every mount after panicking"),
+        Err(err) => dbugln!("Unable to flush every mount after panicking ({err:?})"),
+    }
+}
 
 fn main() {
     dbugln!("Starting Filesystem server!");
 
-    let mut server = QuantumHost::<FsPortalServer<QuantumGlue>>::host_on("fs").unwrap();
+    let all_read_only = Cmdline::read().is_ok_and(|cmdline| cmdline.has_flag("fs.readonly"));
+    if all_read_only {
+        dbugln!("Mounting every filesystem read-only (fs.readonly)");
+    }
+    let mut vfs = Vfs::new(all_read_only);
+    let disks = probe_disks(&mut vfs);
+
+    let vfs = RefCell::new(vfs);
+    PANIC_VFS.store((&raw const vfs).cast_mut(), Ordering::Release);
+    aloe::process::set_panic_hook(flush_on_panic);
+    let watches = RefCell::new(WatchTable::new());
+    let mut server = QuantumHost::<FsClient>::host_on("fs").unwrap();
     loop {
         let signal = signal_wait();
 
+        // The system is shutting down, make sure everything written reaches the disks
+        if matches!(signal, WaitSignal::TerminationRequest) {
+            PANIC_VFS.store(core::ptr::null_mut(), Ordering::Release);
+            match vfs.borrow_mut().unmount_all() {
+                Ok(()) => dbugln!("Unmounted every mount, exiting"),
+                Err(err) => dbugln!("Unable to sync every mount ({err:?}), exiting"),
+            }
+            return;
+        }
+
         server
             .service_signal(
                 signal,
-                |handle| Ok(FsPortalServer::new(QuantumGlue::new(handle))),
-                |read_cs| match read_cs.incoming()? {
+                |handle| {
+                    Ok(FsClient {
+                        handle,
+                        portal: FsPortalServer::new(QuantumGlue::new(handle)),
+                        cwd: String::from("/"),
+                    })
+                },
+                |client| match client.portal.incoming()? {
                     fs_portal::FsPortalClientRequest::Ping { sender } => {
                         dbugln!("Got Ping, responding with Pong!");
                         sender.respond_with(())
                     }
+                    fs_portal::FsPortalClientRequest::Mmap {
+                        path,
+                        offset,
+                        len,
+                        sender,
+                    } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        sender.respond_with(shared::map_file(
+                            &mut vfs.borrow_mut(),
+                            &path,
+                            offset,
+                            len,
+                            client.handle,
+                        ))
+                    }
+                    fs_portal::FsPortalClientRequest::Watch { path, sender } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        sender.respond_with(Ok(watches.borrow_mut().watch(client.handle, path)))
+                    }
+                    fs_portal::FsPortalClientRequest::Unwatch { watch_id, sender } => {
+                        sender.respond_with(watches.borrow_mut().unwatch(client.handle, watch_id))
+                    }
+                    fs_portal::FsPortalClientRequest::WatchEvents { watch_id, sender } => sender
+                        .respond_with(watches.borrow_mut().take_events(client.handle, watch_id)),
+                    fs_portal::FsPortalClientRequest::Chdir { path, sender } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        // The root is always there, even before anything is mounted on it
+                        let checked = match vfs.borrow_mut().stat(&path) {
+                            _ if path == "/" => Ok(()),
+                            Ok(stat) if stat.is_dir => Ok(()),
+                            Ok(_) => Err(QuantumError::InvalidInput),
+                            Err(err) => Err(err),
+                        };
+
+                        if checked.is_ok() {
+                            client.cwd = path;
+                        }
+                        sender.respond_with(checked)
+                    }
+                    fs_portal::FsPortalClientRequest::Getcwd { sender } => {
+                        sender.respond_with(client.cwd.clone())
+                    }
+                    fs_portal::FsPortalClientRequest::Read {
+                        path,
+                        offset,
+                        len,
+                        sender,
+                    } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        let mut buf = vec![0; len.min(MAX_READ_LEN) as usize];
+
+                        sender.respond_with(vfs.borrow_mut().read(&path, offset, &mut buf).map(
+                            |read| {
+                                buf.truncate(read);
+                                buf
+                            },
+                        ))
+                    }
+                    fs_portal::FsPortalClientRequest::ReadVectored { lens, sender, .. }
+                        if lens.len() > MAX_READ_BUFS =>
+                    {
+                        sender.respond_with(Err(QuantumError::InvalidInput))
+                    }
+                    fs_portal::FsPortalClientRequest::ReadVectored {
+                        path,
+                        offset,
+                        lens,
+                        sender,
+                    } => {
+                        let path = path::resolve(&client.cwd, &path);
+
+                        // Whatever goes past `MAX_READ_LEN` in total comes back short or empty
+                        let mut budget = MAX_READ_LEN;
+                        let mut bufs: Vec<Vec<u8>> = lens
+                            .iter()
+                            .map(|&len| {
+                                let len = len.min(budget);
+                                budget -= len;
+                                vec![0; len as usize]
+                            })
+                            .collect();
+                        let mut slices: Vec<&mut [u8]> =
+                            bufs.iter_mut().map(|buf| buf.as_mut_slice()).collect();
+
+                        let read = vfs.borrow_mut().read_vectored(&path, offset, &mut slices);
+                        sender.respond_with(read.map(|mut read| {
+                            for buf in bufs.iter_mut() {
+                                let len = buf.len().min(read);
+                                buf.truncate(len);
+                                read -= len;
+                            }
+                            bufs
+                        }))
+                    }
+                    fs_portal::FsPortalClientRequest::ReadShared {
+                        path,
+                        offset,
+                        len,
+                        shared_id,
+                        shared_offset,
+                        sender,
+                    } => {
+                        let path = path::resolve(&client.cwd, &path);
+
+                        // The filesystem fills the client's memory directly
+                        sender.respond_with(shared::read_shared(
+                            &mut vfs.borrow_mut(),
+                            &path,
+                            offset,
+                            len,
+                            shared_id,
+                            shared_offset,
+                            client.handle,
+                        ))
+                    }
+                    fs_portal::FsPortalClientRequest::Write {
+                        path,
+                        offset,
+                        bytes,
+                        sender,
+                    } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        let kind = change_kind(&mut vfs.borrow_mut(), &path);
+                        let written = vfs.borrow_mut().write(&path, offset, &bytes);
+                        if written.is_ok() {
+                            watches.borrow_mut().notify(&path, kind);
+                        }
+
+                        sender.respond_with(written.map(|written| written as u64))
+                    }
+                    fs_portal::FsPortalClientRequest::Mounts { sender } => {
+                        sender.respond_with(vfs.borrow().mounts())
+                    }
+                    fs_portal::FsPortalClientRequest::Remount {
+                        point,
+                        read_only,
+                        sender,
+                    } => {
+                        let point = path::resolve(&client.cwd, &point);
+                        sender.respond_with(vfs.borrow_mut().remount(&point, read_only))
+                    }
+                    fs_portal::FsPortalClientRequest::Disks { sender } => {
+                        sender.respond_with(disks.clone())
+                    }
+                    fs_portal::FsPortalClientRequest::Append {
+                        path,
+                        bytes,
+                        sender,
+                    } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        let kind = change_kind(&mut vfs.borrow_mut(), &path);
+                        let size = vfs.borrow_mut().append(&path, &bytes);
+                        if size.is_ok() {
+                            watches.borrow_mut().notify(&path, kind);
+                        }
+
+                        sender.respond_with(size)
+                    }
+                    fs_portal::FsPortalClientRequest::Truncate { path, len, sender } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        let truncated = match vfs.borrow_mut().stat(&path) {
+                            Ok(stat) if len > stat.size.saturating_add(MAX_TRUNCATE_GROWTH) => {
+                                Err(QuantumError::InvalidInput)
+                            }
+                            _ => vfs.borrow_mut().truncate(&path, len),
+                        };
+                        if truncated.is_ok() {
+                            watches
+                                .borrow_mut()
+                                .notify(&path, fs_portal::WatchEventKind::Modified);
+                        }
+
+                        sender.respond_with(truncated)
+                    }
+                    fs_portal::FsPortalClientRequest::Rename { from, to, sender } => {
+                        let from = path::resolve(&client.cwd, &from);
+                        let to = path::resolve(&client.cwd, &to);
+                        let renamed = vfs.borrow_mut().rename(&from, &to);
+                        if renamed.is_ok() {
+                            let mut watches = watches.borrow_mut();
+                            watches.notify(&from, fs_portal::WatchEventKind::Deleted);
+                            watches.notify(&to, fs_portal::WatchEventKind::Created);
+                        }
+
+                        sender.respond_with(renamed)
+                    }
+                    fs_portal::FsPortalClientRequest::CreateDir { path, sender } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        let created = vfs.borrow_mut().create_dir(&path);
+                        if created.is_ok() {
+                            watches
+                                .borrow_mut()
+                                .notify(&path, fs_portal::WatchEventKind::Created);
+                        }
+
+                        sender.respond_with(created)
+                    }
+                    fs_portal::FsPortalClientRequest::Remove { path, sender } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        let removed = vfs.borrow_mut().remove(&path);
+                        if removed.is_ok() {
+                            watches
+                                .borrow_mut()
+                                .notify(&path, fs_portal::WatchEventKind::Deleted);
+                        }
+
+                        sender.respond_with(removed)
+                    }
+                    fs_portal::FsPortalClientRequest::Stat { path, sender } => {
+                        let path = path::resolve(&client.cwd, &path);
+                        sender.respond_with(vfs.borrow_mut().stat(&path))
+                    }
                     _ => Ok(()),
                 },
                 |_| Ok(()),
-                |_| {
+                |client| {
                     dbugln!("Disconnecting Client");
+                    watches.borrow_mut().remove_owner(client.handle);
                     Ok(())
                 },
             )
@@ -0,0 +1,104 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use crate::vfs::Vfs;
+use aloe::shared::SharedRegion;
+use fs_portal::{MappedFile, QuantumError};
+
+/// The most bytes a single `mmap` can map
+const MAX_MMAP_LEN: u64 = 16 * 1024 * 1024;
+
+/// Read `len` bytes of `path` starting at `offset` into a new shared memory region, and
+/// send it to the client on `connection`.
+///
+/// Our own mapping is dropped before returning, so the region belongs to the client.
+pub fn map_file(
+    vfs: &mut Vfs,
+    path: &str,
+    offset: u64,
+    len: u64,
+    connection: u64,
+) -> Result<MappedFile, QuantumError> {
+    if len == 0 || len > MAX_MMAP_LEN {
+        return Err(QuantumError::InvalidInput);
+    }
+
+    let mut region = SharedRegion::create(len as usize).map_err(|_| QuantumError::OutOfMemory)?;
+    let bytes = &mut region.as_mut_slice().ok_or(QuantumError::Unknown)?[..len as usize];
+
+    let mut filled = 0;
+    while filled < bytes.len() {
+        match vfs.read(path, offset + filled as u64, &mut bytes[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+
+    let shared_id = region
+        .send(connection)
+        .map_err(|_| QuantumError::Disconnected)?;
+    Ok(MappedFile {
+        shared_id,
+        len: filled as u64,
+    })
+}
+
+/// Read up to `len` bytes of `path` starting at `offset` into the shared memory region
+/// `handle` at `shared_offset`, returning how many were read.
+///
+/// The region has to have been sent to us over `connection`, so a client can only have us
+/// write into memory it owns. The handle is closed and the region unmapped before
+/// returning.
+pub fn read_shared(
+    vfs: &mut Vfs,
+    path: &str,
+    offset: u64,
+    len: u64,
+    handle: u64,
+    shared_offset: u64,
+    connection: u64,
+) -> Result<u64, QuantumError> {
+    match aloe::handle_origin(handle) {
+        Ok(origin) if origin == connection => (),
+        _ => return Err(QuantumError::InvalidHandle),
+    }
+
+    let mut region = SharedRegion::open(handle, true).map_err(|_| {
+        aloe::close(handle);
+        QuantumError::InvalidHandle
+    })?;
+    let bytes = region.as_mut_slice().ok_or(QuantumError::InvalidHandle)?;
+
+    let start = usize::try_from(shared_offset).map_err(|_| QuantumError::InvalidInput)?;
+    let end = usize::try_from(len)
+        .ok()
+        .and_then(|len| start.checked_add(len))
+        .ok_or(QuantumError::InvalidInput)?;
+    let buf = bytes
+        .get_mut(start..end)
+        .ok_or(QuantumError::InvalidInput)?;
+
+    vfs.read(path, offset, buf).map(|read| read as u64)
+}
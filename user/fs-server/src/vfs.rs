@@ -0,0 +1,319 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use fs_portal::{FileStat, MountInfo, QuantumError};
+
+/// # Mount Options
+/// How a filesystem is mounted, given to its driver so it can act on them too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MountOptions {
+    /// Nothing on the filesystem may be changed
+    pub read_only: bool,
+}
+
+/// # File System
+/// A filesystem driver, reached through the mount it is mounted at.
+///
+/// Paths given to a driver are relative to its mount point, and always start with `/`.
+pub trait FileSystem {
+    /// The kind of filesystem, like `fat16`
+    fn kind(&self) -> &'static str;
+
+    /// Take on `options`, called when the filesystem is mounted and whenever it is
+    /// remounted. A driver should fail this if it can't honor them.
+    fn set_options(&mut self, options: MountOptions) -> Result<(), QuantumError>;
+
+    /// Read the file at `path` into `buf` starting at `offset`, returning how many bytes
+    /// were read
+    fn read(&mut self, path: &str, offset: u64, buf: &mut [u8]) -> Result<usize, QuantumError>;
+
+    /// Read the file at `path` starting at `offset` into each of `bufs` in turn, returning
+    /// how many bytes were read in total
+    ///
+    /// Drivers that can fill many buffers with one pass over the disk should override
+    /// this, the default reads each buffer on its own.
+    fn read_vectored(
+        &mut self,
+        path: &str,
+        offset: u64,
+        bufs: &mut [&mut [u8]],
+    ) -> Result<usize, QuantumError> {
+        let mut total = 0;
+        for buf in bufs.iter_mut() {
+            let read = self.read(path, offset + total as u64, buf)?;
+            total += read;
+
+            if read < buf.len() {
+                break;
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// Write `buf` to the file at `path` starting at `offset`, returning how many bytes
+    /// were written
+    fn write(&mut self, path: &str, offset: u64, buf: &[u8]) -> Result<usize, QuantumError>;
+
+    /// Append `buf` to the end of the file at `path`, creating it if it does not exist,
+    /// and return the new size of the file
+    fn append(&mut self, path: &str, buf: &[u8]) -> Result<u64, QuantumError>;
+
+    /// Shrink or grow the file at `path` to `len` bytes
+    fn truncate(&mut self, path: &str, len: u64) -> Result<(), QuantumError>;
+
+    /// Move the file or directory at `from` to `to`, replacing the file at `to`
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), QuantumError>;
+
+    fn create_dir(&mut self, path: &str) -> Result<(), QuantumError>;
+
+    /// Remove the file or empty directory at `path`
+    fn remove(&mut self, path: &str) -> Result<(), QuantumError>;
+
+    fn stat(&mut self, path: &str) -> Result<FileStat, QuantumError>;
+
+    /// Write back anything the driver is holding on to, called before the system shuts
+    /// down. Drivers that write straight to the disk have nothing to do.
+    fn sync(&mut self) -> Result<(), QuantumError> {
+        Ok(())
+    }
+
+    /// Write back anything the driver is holding on to, without marking the filesystem as
+    /// cleanly unmounted. Called when the server panics, where the driver could have been
+    /// stopped half way through a change.
+    fn flush(&mut self) -> Result<(), QuantumError> {
+        Ok(())
+    }
+}
+
+struct Mount {
+    point: String,
+    source: String,
+    options: MountOptions,
+    fs: Box<dyn FileSystem>,
+}
+
+impl Mount {
+    /// `path` relative to this mount, if this mount holds it
+    fn relative<'a>(&self, path: &'a str) -> Option<&'a str> {
+        match path.strip_prefix(self.point.trim_end_matches('/')) {
+            Some("") => Some("/"),
+            Some(rest) if rest.starts_with('/') => Some(rest),
+            _ => None,
+        }
+    }
+}
+
+/// # Vfs
+/// Every mounted filesystem, each path goes to the filesystem of the deepest mount
+/// that holds it.
+///
+/// Writes to a read-only mount are refused here, before they reach the driver.
+pub struct Vfs {
+    mounts: Vec<Mount>,
+    /// Every mount is read-only, set by the `fs.readonly` kernel command line flag
+    all_read_only: bool,
+}
+
+impl Vfs {
+    pub const fn new(all_read_only: bool) -> Self {
+        Self {
+            mounts: Vec::new(),
+            all_read_only,
+        }
+    }
+
+    /// Mount `fs` from the device `source` at the absolute path `point`
+    ///
+    /// The mount is read-only if `options` asks for it, or if every mount must be.
+    pub fn mount(
+        &mut self,
+        point: String,
+        source: String,
+        mut fs: Box<dyn FileSystem>,
+        options: MountOptions,
+    ) -> Result<(), QuantumError> {
+        if self.mounts.iter().any(|mount| mount.point == point) {
+            return Err(QuantumError::AlreadyExists);
+        }
+
+        let options = MountOptions {
+            read_only: options.read_only || self.all_read_only,
+        };
+        fs.set_options(options)?;
+
+        self.mounts.push(Mount {
+            point,
+            source,
+            options,
+            fs,
+        });
+        Ok(())
+    }
+
+    /// Make the mount at `point` read-only, or writable again
+    pub fn remount(&mut self, point: &str, read_only: bool) -> Result<(), QuantumError> {
+        if !read_only && self.all_read_only {
+            return Err(QuantumError::PermissionDenied);
+        }
+
+        let mount = self
+            .mounts
+            .iter_mut()
+            .find(|mount| mount.point == point)
+            .ok_or(QuantumError::NotFound)?;
+        let options = MountOptions { read_only };
+
+        mount.fs.set_options(options)?;
+        mount.options = options;
+        Ok(())
+    }
+
+    /// The mount that holds the absolute path `path`, and `path` relative to it
+    fn resolve<'a>(&mut self, path: &'a str) -> Result<(&mut Mount, &'a str), QuantumError> {
+        self.mounts
+            .iter_mut()
+            .filter_map(|mount| Some((mount.relative(path)?, mount)))
+            .max_by_key(|(_, mount)| mount.point.len())
+            .map(|(relative, mount)| (mount, relative))
+            .ok_or(QuantumError::NotFound)
+    }
+
+    pub fn read(&mut self, path: &str, offset: u64, buf: &mut [u8]) -> Result<usize, QuantumError> {
+        let (mount, relative) = self.resolve(path)?;
+        mount.fs.read(relative, offset, buf)
+    }
+
+    pub fn read_vectored(
+        &mut self,
+        path: &str,
+        offset: u64,
+        bufs: &mut [&mut [u8]],
+    ) -> Result<usize, QuantumError> {
+        let (mount, relative) = self.resolve(path)?;
+        mount.fs.read_vectored(relative, offset, bufs)
+    }
+
+    /// The writable mount that holds the absolute path `path`, and `path` relative to it
+    fn resolve_writable<'a>(
+        &mut self,
+        path: &'a str,
+    ) -> Result<(&mut Mount, &'a str), QuantumError> {
+        let (mount, relative) = self.resolve(path)?;
+        if mount.options.read_only {
+            return Err(QuantumError::ReadOnly);
+        }
+
+        Ok((mount, relative))
+    }
+
+    pub fn write(&mut self, path: &str, offset: u64, buf: &[u8]) -> Result<usize, QuantumError> {
+        let (mount, relative) = self.resolve_writable(path)?;
+        mount.fs.write(relative, offset, buf)
+    }
+
+    pub fn append(&mut self, path: &str, buf: &[u8]) -> Result<u64, QuantumError> {
+        let (mount, relative) = self.resolve_writable(path)?;
+        mount.fs.append(relative, buf)
+    }
+
+    pub fn truncate(&mut self, path: &str, len: u64) -> Result<(), QuantumError> {
+        let (mount, relative) = self.resolve_writable(path)?;
+        mount.fs.truncate(relative, len)
+    }
+
+    /// Rename `from` to `to`, which must both be on the same mount
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), QuantumError> {
+        let (to_mount, to_relative) = self.resolve(to)?;
+        let to_point = to_mount.point.clone();
+
+        let (mount, from_relative) = self.resolve_writable(from)?;
+        if mount.point != to_point {
+            return Err(QuantumError::NotSupported);
+        }
+
+        mount.fs.rename(from_relative, to_relative)
+    }
+
+    pub fn create_dir(&mut self, path: &str) -> Result<(), QuantumError> {
+        let (mount, relative) = self.resolve_writable(path)?;
+        mount.fs.create_dir(relative)
+    }
+
+    pub fn remove(&mut self, path: &str) -> Result<(), QuantumError> {
+        let (mount, relative) = self.resolve_writable(path)?;
+        mount.fs.remove(relative)
+    }
+
+    pub fn stat(&mut self, path: &str) -> Result<FileStat, QuantumError> {
+        let (mount, relative) = self.resolve(path)?;
+        mount.fs.stat(relative)
+    }
+
+    /// Sync every mount, returning the last error if any of them failed
+    pub fn sync(&mut self) -> Result<(), QuantumError> {
+        self.mounts.iter_mut().fold(Ok(()), |result, mount| {
+            if mount.options.read_only {
+                return result;
+            }
+
+            mount.fs.sync().and(result)
+        })
+    }
+
+    /// Flush every mount, returning the last error if any of them failed
+    pub fn flush(&mut self) -> Result<(), QuantumError> {
+        self.mounts.iter_mut().fold(Ok(()), |result, mount| {
+            if mount.options.read_only {
+                return result;
+            }
+
+            mount.fs.flush().and(result)
+        })
+    }
+
+    /// Sync and then unmount every mount, returning the last error if any of them failed
+    /// to sync
+    pub fn unmount_all(&mut self) -> Result<(), QuantumError> {
+        let result = self.sync();
+        self.mounts.clear();
+
+        result
+    }
+
+    /// Describe every mount, for the `mounts` endpoint
+    pub fn mounts(&self) -> Vec<MountInfo> {
+        self.mounts
+            .iter()
+            .map(|mount| MountInfo {
+                point: mount.point.clone(),
+                source: mount.source.clone(),
+                kind: String::from(mount.fs.kind()),
+                read_only: mount.options.read_only,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,132 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::{collections::btree_map::BTreeMap, string::String, vec::Vec};
+use fs_portal::{QuantumError, WatchEvent, WatchEventKind};
+
+/// The max number of events kept for a watch before new events are dropped.
+pub const MAX_PENDING_EVENTS: usize = 64;
+
+struct Watch {
+    owner: u64,
+    path: String,
+    events: Vec<WatchEvent>,
+}
+
+impl Watch {
+    /// Does a change to `path` concern this watch?
+    fn covers(&self, path: &str) -> bool {
+        match path.strip_prefix(self.path.trim_end_matches('/')) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+
+    /// Is this watch on `path` or something under it?
+    fn is_inside(&self, path: &str) -> bool {
+        match self.path.strip_prefix(path.trim_end_matches('/')) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+/// # Watch Table
+/// Every watch of every client, so the write path can notify all of them.
+pub struct WatchTable {
+    next_id: u64,
+    watches: BTreeMap<u64, Watch>,
+}
+
+impl WatchTable {
+    pub const fn new() -> Self {
+        Self {
+            next_id: 1,
+            watches: BTreeMap::new(),
+        }
+    }
+
+    /// Start watching `path` for the client connected on `owner`
+    pub fn watch(&mut self, owner: u64, path: String) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.watches.insert(
+            id,
+            Watch {
+                owner,
+                path,
+                events: Vec::new(),
+            },
+        );
+
+        id
+    }
+
+    /// Stop the watch `id`, only if `owner` was the one who started it
+    pub fn unwatch(&mut self, owner: u64, id: u64) -> Result<(), QuantumError> {
+        self.watch_of(owner, id)?;
+        self.watches.remove(&id);
+
+        Ok(())
+    }
+
+    /// Take all pending events of the watch `id`
+    pub fn take_events(&mut self, owner: u64, id: u64) -> Result<Vec<WatchEvent>, QuantumError> {
+        Ok(core::mem::take(&mut self.watch_of(owner, id)?.events))
+    }
+
+    /// Stop every watch of a client that disconnected
+    pub fn remove_owner(&mut self, owner: u64) {
+        self.watches.retain(|_, watch| watch.owner != owner);
+    }
+
+    /// Tell every watch covering `path` that it changed.
+    ///
+    /// Deleting a directory also deletes everything in it, so watches inside a deleted
+    /// path are told too.
+    pub fn notify(&mut self, path: &str, kind: WatchEventKind) {
+        let deleted = matches!(kind, WatchEventKind::Deleted);
+        for watch in self
+            .watches
+            .values_mut()
+            .filter(|watch| watch.covers(path) || (deleted && watch.is_inside(path)))
+        {
+            if watch.events.len() < MAX_PENDING_EVENTS {
+                watch.events.push(WatchEvent {
+                    kind: kind.clone(),
+                    path: String::from(path),
+                });
+            }
+        }
+    }
+
+    fn watch_of(&mut self, owner: u64, id: u64) -> Result<&mut Watch, QuantumError> {
+        self.watches
+            .get_mut(&id)
+            .filter(|watch| watch.owner == owner)
+            .ok_or(QuantumError::InvalidHandle)
+    }
+}
@@ -0,0 +1,392 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::{vec, vec::Vec};
+use aloe::{FramebufferInfo, framebuffer_flush, shared::SharedRegion};
+use bootgfx::{Channel, Framebuffer, PixelFormat, Rect, cursor::Cursor, rect};
+use gfx_portal::QuantumError;
+
+/// The color drawn where there are no surfaces
+const BACKGROUND_COLOR: u32 = 0x00202030;
+/// The largest width or height a surface can have
+const MAX_SURFACE_SIZE: u32 = 4096;
+
+/// A client's buffer of pixels placed somewhere on the screen
+struct Surface {
+    owner: u64,
+    id: u64,
+    region: SharedRegion,
+    rect: Rect,
+    /// Surfaces are hidden until the client first damages them, so we never show a
+    /// surface before it has been drawn into.
+    visible: bool,
+}
+
+impl Surface {
+    /// The pixels of this surface's row `y`, starting at column `x`
+    fn row(&self, x: usize, y: usize, width: usize) -> &[u32] {
+        // Shared memory is always page aligned, so this never splits a pixel
+        let (_, pixels, _) = unsafe { self.region.as_slice().align_to::<u32>() };
+        let start = y * self.rect.width.get() + x;
+
+        &pixels[start..start + width]
+    }
+}
+
+/// Draws every client's surfaces into the framebuffer
+pub struct Compositor {
+    framebuffer: Option<FramebufferInfo>,
+    /// Surfaces from bottom to top
+    surfaces: Vec<Surface>,
+    next_id: u64,
+    /// One row of the background, so filling it can be done a row at a time
+    background: Vec<u32>,
+    /// The mouse pointer, drawn over every surface
+    cursor: Cursor,
+}
+
+impl Compositor {
+    /// Create a compositor drawing into `framebuffer`.
+    ///
+    /// Without a framebuffer clients can still create surfaces, but nothing is drawn.
+    pub fn new(framebuffer: Option<FramebufferInfo>) -> Self {
+        let width = framebuffer.as_ref().map_or(0, |fb| fb.width as usize);
+        let mut compositor = Self {
+            framebuffer,
+            surfaces: Vec::new(),
+            next_id: 0,
+            background: vec![BACKGROUND_COLOR; width],
+            cursor: Cursor::new(),
+        };
+
+        if let Some(screen) = compositor.screen() {
+            compositor.redraw(screen);
+        }
+        compositor
+    }
+
+    /// Start drawing into `framebuffer` after the screen changed mode, redrawing everything
+    /// onto it.
+    pub fn set_framebuffer(&mut self, framebuffer: FramebufferInfo) {
+        // The kernel unmapped the old framebuffer when it mapped this one
+        self.background = vec![BACKGROUND_COLOR; framebuffer.width as usize];
+        self.framebuffer = Some(framebuffer);
+        self.cursor.forget_screen();
+        if let Some(screen) = self.screen() {
+            self.redraw(screen);
+        }
+    }
+
+    /// The area of the screen, if there is one
+    pub fn screen(&self) -> Option<Rect> {
+        let fb = self.framebuffer.as_ref()?;
+        rect!(0, 0, fb.width, fb.height)
+    }
+
+    /// Create a new surface above all others, returning its id and the handle `owner` can
+    /// map its shared memory with.
+    pub fn create_surface(
+        &mut self,
+        owner: u64,
+        width: u32,
+        height: u32,
+    ) -> Result<(u64, u64), QuantumError> {
+        if width == 0 || height == 0 || width > MAX_SURFACE_SIZE || height > MAX_SURFACE_SIZE {
+            return Err(QuantumError::InvalidInput);
+        }
+
+        let rect = rect!(0, 0, width, height).ok_or(QuantumError::InvalidInput)?;
+        let mut region = SharedRegion::create(width as usize * height as usize * size_of::<u32>())
+            .map_err(|_| QuantumError::OutOfMemory)?;
+        let shared_id = region.send(owner).map_err(|_| QuantumError::Disconnected)?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.surfaces.push(Surface {
+            owner,
+            id,
+            region,
+            rect,
+            visible: false,
+        });
+
+        Ok((id, shared_id))
+    }
+
+    /// Remove a surface, redrawing what was below it.
+    pub fn destroy_surface(&mut self, owner: u64, id: u64) -> Result<(), QuantumError> {
+        let index = self.surface_index(owner, id)?;
+
+        // Dropping the surface unmaps its pixels, they are freed once the client unmaps them
+        let surface = self.surfaces.remove(index);
+        if surface.visible {
+            self.redraw(surface.rect);
+        }
+
+        Ok(())
+    }
+
+    /// Move a surface so its top left corner is at `x`, `y`.
+    pub fn move_surface(
+        &mut self,
+        owner: u64,
+        id: u64,
+        x: i32,
+        y: i32,
+    ) -> Result<(), QuantumError> {
+        let index = self.surface_index(owner, id)?;
+        let surface = &mut self.surfaces[index];
+        let old_rect = surface.rect;
+
+        surface.rect.x = x as isize;
+        surface.rect.y = y as isize;
+
+        if surface.visible {
+            let new_rect = surface.rect;
+            self.redraw(old_rect);
+            self.redraw(new_rect);
+        }
+
+        Ok(())
+    }
+
+    /// Move a surface above all others.
+    pub fn raise_surface(&mut self, owner: u64, id: u64) -> Result<(), QuantumError> {
+        let index = self.surface_index(owner, id)?;
+        let surface = self.surfaces.remove(index);
+        let (rect, visible) = (surface.rect, surface.visible);
+
+        self.surfaces.push(surface);
+        if visible {
+            self.redraw(rect);
+        }
+
+        Ok(())
+    }
+
+    /// Redraw the part of a surface that the client changed.
+    ///
+    /// `damage` is in surface coordinates, `None` only shows the surface.
+    pub fn damage(
+        &mut self,
+        owner: u64,
+        id: u64,
+        damage: Option<Rect>,
+    ) -> Result<(), QuantumError> {
+        let index = self.surface_index(owner, id)?;
+        let surface = &mut self.surfaces[index];
+        surface.visible = true;
+
+        let Some(damage) = damage
+            .and_then(|damage| damage.clip(surface.rect.width.get(), surface.rect.height.get()))
+        else {
+            return Ok(());
+        };
+
+        let screen_damage = Rect::from_size(
+            damage.x.saturating_add(surface.rect.x),
+            damage.y.saturating_add(surface.rect.y),
+            damage.width,
+            damage.height,
+        );
+        self.redraw(screen_damage);
+
+        Ok(())
+    }
+
+    /// Remove all surfaces owned by `owner`, used when a client disconnects.
+    pub fn remove_owner(&mut self, owner: u64) {
+        let removed: Vec<Rect> = self
+            .surfaces
+            .extract_if(.., |surface| surface.owner == owner)
+            .filter(|surface| surface.visible)
+            .map(|surface| surface.rect)
+            .collect();
+
+        for rect in removed {
+            self.redraw(rect);
+        }
+    }
+
+    fn surface_index(&self, owner: u64, id: u64) -> Result<usize, QuantumError> {
+        self.surfaces
+            .iter()
+            .position(|surface| surface.owner == owner && surface.id == id)
+            .ok_or(QuantumError::NotFound)
+    }
+
+    /// Start drawing the mouse pointer
+    pub fn show_cursor(&mut self) {
+        if let Some(mut canvas) = self.canvas() {
+            flush(self.cursor.show(&mut canvas));
+        }
+    }
+
+    /// Stop drawing the mouse pointer, putting back what was under it
+    pub fn hide_cursor(&mut self) {
+        if let Some(mut canvas) = self.canvas() {
+            flush(self.cursor.hide(&mut canvas));
+        }
+    }
+
+    /// Point the mouse pointer at `x`, `y`, which is kept on the screen
+    pub fn move_cursor(&mut self, x: i32, y: i32) {
+        let Some(screen) = self.screen() else {
+            return;
+        };
+        let x = (x as isize).clamp(0, screen.right() - 1);
+        let y = (y as isize).clamp(0, screen.bottom() - 1);
+
+        if let Some(mut canvas) = self.canvas() {
+            flush(self.cursor.move_to(&mut canvas, x, y));
+        }
+    }
+
+    /// The framebuffer as something `bootgfx` can draw into
+    fn canvas(&self) -> Option<Framebuffer> {
+        let fb = self.framebuffer.as_ref()?;
+        let format = PixelFormat {
+            bits_per_pixel: fb.bits_per_pixel,
+            red: Channel::new(fb.red_position, 8),
+            green: Channel::new(fb.green_position, 8),
+            blue: Channel::new(fb.blue_position, 8),
+        };
+
+        Some(unsafe {
+            Framebuffer::new(
+                fb.ptr,
+                fb.width as usize,
+                fb.height as usize,
+                fb.pitch as usize,
+                format,
+            )
+        })
+    }
+
+    /// Redraw `damage` on the screen, drawing each surface over the ones below it.
+    fn redraw(&mut self, damage: Rect) {
+        let Some(damage) = self.screen().and_then(|screen| damage.intersect(&screen)) else {
+            return;
+        };
+
+        // The cursor is drawn over everything, so take it off while redrawing under it
+        let mut canvas = self.canvas();
+        let covers_cursor =
+            self.cursor.is_visible() && damage.intersect(&self.cursor.area()).is_some();
+        if let Some(canvas) = canvas.as_mut().filter(|_| covers_cursor) {
+            self.cursor.hide(canvas);
+        }
+
+        // Clipping to the screen keeps the damage from starting at a negative position
+        let (x, width) = (damage.x as usize, damage.width.get());
+        for y in damage.y as usize..damage.bottom() as usize {
+            self.blit_row(x, y, &self.background[x..x + width]);
+        }
+
+        for surface in self.surfaces.iter().filter(|surface| surface.visible) {
+            let Some(area) = surface.rect.intersect(&damage) else {
+                continue;
+            };
+
+            let surface_x = area.x.abs_diff(surface.rect.x);
+            let surface_y = area.y.abs_diff(surface.rect.y);
+            for row in 0..area.height.get() {
+                self.blit_row(
+                    area.x as usize,
+                    area.y as usize + row,
+                    surface.row(surface_x, surface_y + row, area.width.get()),
+                );
+            }
+        }
+
+        if let Some(canvas) = canvas.as_mut().filter(|_| covers_cursor) {
+            flush(self.cursor.show(canvas));
+        }
+
+        // Some displays only show what was flushed, for the rest this does nothing
+        flush(Some(damage));
+    }
+
+    /// Write `pixels` to the screen starting at `x`, `y`.
+    ///
+    /// The row must fit on the screen.
+    fn blit_row(&self, x: usize, y: usize, pixels: &[u32]) {
+        let Some(fb) = self.framebuffer.as_ref() else {
+            return;
+        };
+        let row_start = y * fb.pitch as usize;
+        let native = (fb.red_position, fb.green_position, fb.blue_position) == (16, 8, 0);
+
+        // Surfaces are `0x00RRGGBB`, move each channel to where the framebuffer wants it
+        let convert = |pixel: u32| {
+            let [blue, green, red, _] = pixel.to_le_bytes();
+            (red as u32) << fb.red_position
+                | (green as u32) << fb.green_position
+                | (blue as u32) << fb.blue_position
+        };
+
+        match fb.bits_per_pixel {
+            32 if native => unsafe {
+                let dest = fb.ptr.add(row_start + x * 4) as *mut u32;
+                core::ptr::copy_nonoverlapping(pixels.as_ptr(), dest, pixels.len());
+            },
+            32 => {
+                for (i, pixel) in pixels.iter().enumerate() {
+                    let offset = row_start + (x + i) * 4;
+                    unsafe { (fb.ptr.add(offset) as *mut u32).write_unaligned(convert(*pixel)) };
+                }
+            }
+            24 => {
+                for (i, pixel) in pixels.iter().enumerate() {
+                    let offset = row_start + (x + i) * 3;
+                    let [low, middle, high, _] = convert(*pixel).to_le_bytes();
+
+                    unsafe {
+                        fb.ptr.add(offset).write(low);
+                        fb.ptr.add(offset + 1).write(middle);
+                        fb.ptr.add(offset + 2).write(high);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Show the part of the screen that changed, if any
+fn flush(area: Option<Rect>) {
+    let Some(area) = area else {
+        return;
+    };
+
+    // Areas are always clipped to the screen before they are flushed
+    let _ = framebuffer_flush(
+        area.x as u32,
+        area.y as u32,
+        area.width.get() as u32,
+        area.height.get() as u32,
+    );
+}
@@ -0,0 +1,198 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+#![no_main]
+tiny_std!();
+
+use aloe::{
+    VideoModeError, dbugln, framebuffer_map, framebuffer_set_mode,
+    ipc::{QuantumGlue, QuantumHost},
+    signal_wait, tiny_std,
+};
+use bootgfx::rect;
+use compositor::Compositor;
+use core::cell::RefCell;
+use gfx_portal::{GfxPortalClientRequest, GfxPortalServer, QuantumError, ScreenSize, Surface};
+
+mod compositor;
+
+/// Switch the screen to `width` by `height` and redraw everything onto it
+fn set_mode(
+    compositor: &mut Compositor,
+    width: u32,
+    height: u32,
+) -> Result<ScreenSize, QuantumError> {
+    let framebuffer = framebuffer_set_mode(width, height, 32).map_err(|err| match err {
+        VideoModeError::NotSupported => QuantumError::NotSupported,
+        VideoModeError::InvalidMode => QuantumError::InvalidInput,
+        VideoModeError::NotOwner => QuantumError::PermissionDenied,
+        VideoModeError::DeviceError => QuantumError::Io,
+        VideoModeError::MappingMemoryError => QuantumError::OutOfMemory,
+    })?;
+
+    let screen = ScreenSize {
+        width: framebuffer.width,
+        height: framebuffer.height,
+    };
+
+    dbugln!(
+        "Compositing onto a {}x{} framebuffer",
+        framebuffer.width,
+        framebuffer.height
+    );
+    compositor.set_framebuffer(framebuffer);
+
+    Ok(screen)
+}
+
+/// A client connected to the gfx server
+struct GfxClient {
+    handle: u64,
+    portal: GfxPortalServer<QuantumGlue>,
+}
+
+fn main() {
+    dbugln!("Starting Gfx server!");
+
+    let framebuffer = match framebuffer_map() {
+        Ok(framebuffer) if matches!(framebuffer.bits_per_pixel, 24 | 32) => {
+            dbugln!(
+                "Compositing onto a {}x{} framebuffer",
+                framebuffer.width,
+                framebuffer.height
+            );
+            Some(framebuffer)
+        }
+        Ok(framebuffer) => {
+            dbugln!(
+                "Framebuffer has an unsupported {} bits per pixel, not drawing anything",
+                framebuffer.bits_per_pixel
+            );
+            None
+        }
+        Err(err) => {
+            dbugln!("Unable to take the framebuffer ({err:?}), not drawing anything");
+            None
+        }
+    };
+
+    let compositor = RefCell::new(Compositor::new(framebuffer));
+    let mut server = QuantumHost::<GfxClient>::host_on("gfx").unwrap();
+    loop {
+        let signal = signal_wait();
+
+        server
+            .service_signal(
+                signal,
+                |handle| {
+                    Ok(GfxClient {
+                        handle,
+                        portal: GfxPortalServer::new(QuantumGlue::new(handle)),
+                    })
+                },
+                |client| match client.portal.incoming()? {
+                    GfxPortalClientRequest::Ping { sender } => sender.respond_with(()),
+                    GfxPortalClientRequest::ScreenSize { sender } => {
+                        let screen = compositor.borrow().screen();
+                        sender.respond_with(ScreenSize {
+                            width: screen.map_or(0, |screen| screen.width.get() as u32),
+                            height: screen.map_or(0, |screen| screen.height.get() as u32),
+                        })
+                    }
+                    GfxPortalClientRequest::CreateSurface {
+                        width,
+                        height,
+                        sender,
+                    } => sender.respond_with(
+                        compositor
+                            .borrow_mut()
+                            .create_surface(client.handle, width, height)
+                            .map(|(id, shared_id)| Surface { id, shared_id }),
+                    ),
+                    GfxPortalClientRequest::DestroySurface { surface_id, sender } => sender
+                        .respond_with(
+                            compositor
+                                .borrow_mut()
+                                .destroy_surface(client.handle, surface_id),
+                        ),
+                    GfxPortalClientRequest::MoveSurface {
+                        surface_id,
+                        x,
+                        y,
+                        sender,
+                    } => sender.respond_with(compositor.borrow_mut().move_surface(
+                        client.handle,
+                        surface_id,
+                        x,
+                        y,
+                    )),
+                    GfxPortalClientRequest::RaiseSurface { surface_id, sender } => sender
+                        .respond_with(
+                            compositor
+                                .borrow_mut()
+                                .raise_surface(client.handle, surface_id),
+                        ),
+                    GfxPortalClientRequest::Damage {
+                        surface_id,
+                        x,
+                        y,
+                        width,
+                        height,
+                        sender,
+                    } => sender.respond_with(compositor.borrow_mut().damage(
+                        client.handle,
+                        surface_id,
+                        rect!(x, y, width, height),
+                    )),
+                    GfxPortalClientRequest::SetMode {
+                        width,
+                        height,
+                        sender,
+                    } => sender.respond_with(set_mode(&mut compositor.borrow_mut(), width, height)),
+                    GfxPortalClientRequest::ShowCursor { sender } => {
+                        compositor.borrow_mut().show_cursor();
+                        sender.respond_with(())
+                    }
+                    GfxPortalClientRequest::HideCursor { sender } => {
+                        compositor.borrow_mut().hide_cursor();
+                        sender.respond_with(())
+                    }
+                    GfxPortalClientRequest::MoveCursor { x, y, sender } => {
+                        compositor.borrow_mut().move_cursor(x, y);
+                        sender.respond_with(())
+                    }
+                    _ => Ok(()),
+                },
+                |_| Ok(()),
+                |client| {
+                    dbugln!("Disconnecting Client");
+                    compositor.borrow_mut().remove_owner(client.handle);
+                    Ok(())
+                },
+            )
+            .unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+#![no_main]
+tiny_std!();
+
+use alloc::vec::Vec;
+use aloe::{
+    WaitSignal, dbugln, monotonic_ns, process::initfs_file, signal_timer, signal_wait, tiny_std,
+};
+use service_manifest::Manifest;
+use supervisor::Supervisor;
+
+mod supervisor;
+
+/// The initfs file listing the services to start
+const MANIFEST_PATH: &str = "services.conf";
+
+/// Read the service manifest, returning no services if it can't be used
+fn load_manifest() -> Supervisor {
+    let manifest = initfs_file(MANIFEST_PATH)
+        .map_err(|err| dbugln!("init: unable to read '{MANIFEST_PATH}' ({err:?})"))
+        .and_then(|text| {
+            let text = core::str::from_utf8(&text)
+                .map_err(|_| dbugln!("init: '{MANIFEST_PATH}' is not valid UTF-8"))?;
+
+            Manifest::parse(text)
+                .map_err(|err| dbugln!("init: '{MANIFEST_PATH}' is invalid, {err}"))
+        });
+
+    match manifest {
+        Ok(manifest) => {
+            let (services, order) = manifest.into_parts();
+            Supervisor::new(services, order)
+        }
+        Err(()) => Supervisor::new(Vec::new(), Vec::new()),
+    }
+}
+
+fn main() {
+    dbugln!("Starting init!");
+
+    let mut supervisor = load_manifest();
+    supervisor.start_ready(monotonic_ns());
+
+    // The soonest timer that has been set and has not gone off yet
+    let mut timer: Option<u64> = None;
+    loop {
+        let now = monotonic_ns();
+        if let Some(at) = supervisor.next_restart() {
+            if timer.is_none_or(|timer| at < timer) {
+                signal_timer(at.saturating_sub(now));
+                timer = Some(at);
+            }
+        }
+
+        match signal_wait() {
+            WaitSignal::ChildExit { pid } => supervisor.child_exited(pid, monotonic_ns()),
+            WaitSignal::TimerUpdate { .. } => timer = None,
+            WaitSignal::TerminationRequest => {
+                dbugln!("init: shutting down, waiting for every service to exit");
+                supervisor.shut_down();
+            }
+            _ => (),
+        }
+
+        if supervisor.finished() {
+            dbugln!("init: every service exited");
+            return;
+        }
+        supervisor.start_ready(monotonic_ns());
+    }
+}
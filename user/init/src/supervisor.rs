@@ -0,0 +1,251 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::vec::Vec;
+use aloe::{ChildStatus, capabilities, dbugln, spawn_with, wait};
+use service_manifest::{Capability, Restart, ServiceDef};
+
+/// How long to wait before restarting a service the first time it exits
+const BACKOFF_START_NS: u64 = 500_000_000;
+/// The longest to wait before restarting a service
+const BACKOFF_MAX_NS: u64 = 30_000_000_000;
+/// How long a service has to run for its earlier exits to be forgiven
+const STABLE_NS: u64 = 10_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Not started yet, waiting for the services it requires
+    Waiting,
+    Running {
+        pid: usize,
+        since: u64,
+    },
+    /// Exited, and started again once the monotonic clock reaches `at`
+    Restarting {
+        at: u64,
+    },
+    /// Exited successfully, and not restarted
+    Done,
+    /// Exited with a failure or could not be started, and not restarted
+    Failed,
+}
+
+struct Service {
+    def: ServiceDef,
+    state: State,
+    /// How many times in a row the service exited without running for long
+    exits: u32,
+}
+
+/// The kernel's capability mask for the capabilities a service asked for
+fn capability_mask(service_capabilities: &[Capability]) -> u64 {
+    service_capabilities
+        .iter()
+        .map(|capability| match capability {
+            Capability::IoPorts => capabilities::IO_PORTS,
+            Capability::Framebuffer => capabilities::FRAMEBUFFER,
+            Capability::LogRing => capabilities::LOG_RING,
+            Capability::Debug => capabilities::DEBUG,
+            Capability::Disks => capabilities::DISKS,
+            Capability::System => capabilities::SYSTEM,
+        })
+        .fold(0, |mask, capability| mask | capability)
+}
+
+/// Starts services once the services they require are up, and restarts them when they exit
+pub struct Supervisor {
+    services: Vec<Service>,
+    /// The indexes of `services`, in the order they can be started in
+    order: Vec<usize>,
+    /// Once shutting down, services are no longer started or restarted
+    shutting_down: bool,
+}
+
+impl Supervisor {
+    pub fn new(defs: Vec<ServiceDef>, order: Vec<usize>) -> Self {
+        let services = defs
+            .into_iter()
+            .map(|def| Service {
+                def,
+                state: State::Waiting,
+                exits: 0,
+            })
+            .collect();
+
+        Self {
+            services,
+            order,
+            shutting_down: false,
+        }
+    }
+
+    /// Is service `index` up, so services that require it can start?
+    fn is_up(&self, index: usize) -> bool {
+        matches!(
+            self.services[index].state,
+            State::Running { .. } | State::Done
+        )
+    }
+
+    /// Start every service that is due to start, and whose required services are up
+    ///
+    /// A service that requires a service which failed is failed too.
+    pub fn start_ready(&mut self, now: u64) {
+        if self.shutting_down {
+            return;
+        }
+
+        for order_index in 0..self.order.len() {
+            let index = self.order[order_index];
+            let service = &self.services[index];
+
+            let due = match service.state {
+                State::Waiting => true,
+                State::Restarting { at } => at <= now,
+                _ => false,
+            };
+            if !due {
+                continue;
+            }
+
+            let required: Vec<usize> = service
+                .def
+                .requires
+                .iter()
+                .filter_map(|name| self.services.iter().position(|dep| &dep.def.name == name))
+                .collect();
+
+            if let Some(&failed) = required
+                .iter()
+                .find(|&&dep| self.services[dep].state == State::Failed)
+            {
+                dbugln!(
+                    "init: not starting '{}', it requires '{}' which failed",
+                    self.services[index].def.name,
+                    self.services[failed].def.name
+                );
+                self.services[index].state = State::Failed;
+                continue;
+            }
+            if !required.iter().all(|&dep| self.is_up(dep)) {
+                continue;
+            }
+
+            let service = &mut self.services[index];
+            service.state = match spawn_with(
+                &service.def.binary,
+                capability_mask(&service.def.capabilities),
+            ) {
+                Ok(pid) => {
+                    dbugln!("init: started '{}' (pid {pid})", service.def.name);
+                    State::Running { pid, since: now }
+                }
+                Err(err) => {
+                    dbugln!("init: unable to start '{}' ({err:?})", service.def.name);
+                    State::Failed
+                }
+            };
+        }
+    }
+
+    /// Reap the child `pid`, scheduling a restart if it was a service that should restart
+    pub fn child_exited(&mut self, pid: usize, now: u64) {
+        // A child is only announced once it exits, so this never blocks
+        let Ok(status) = wait(pid) else {
+            return;
+        };
+
+        let Some(service) = self
+            .services
+            .iter_mut()
+            .find(|service| matches!(service.state, State::Running { pid: running, .. } if running == pid))
+        else {
+            dbugln!("init: reaped orphan pid {pid} ({status:?})");
+            return;
+        };
+
+        let State::Running { since, .. } = service.state else {
+            unreachable!()
+        };
+        if now.saturating_sub(since) >= STABLE_NS {
+            service.exits = 0;
+        }
+
+        let success = matches!(status, ChildStatus::Success);
+        let restart = !self.shutting_down
+            && match service.def.restart {
+                Restart::Always => true,
+                Restart::OnFailure => !success,
+                Restart::Never => false,
+            };
+
+        if !restart {
+            dbugln!("init: '{}' exited ({status:?})", service.def.name);
+            service.state = if success { State::Done } else { State::Failed };
+            return;
+        }
+
+        let delay = BACKOFF_START_NS
+            .saturating_mul(1 << service.exits.min(16))
+            .min(BACKOFF_MAX_NS);
+        service.exits += 1;
+        service.state = State::Restarting { at: now + delay };
+
+        dbugln!(
+            "init: '{}' exited ({status:?}), restarting it in {}ms",
+            service.def.name,
+            delay / 1_000_000
+        );
+    }
+
+    /// The soonest a service is due to restart
+    pub fn next_restart(&self) -> Option<u64> {
+        if self.shutting_down {
+            return None;
+        }
+
+        self.services
+            .iter()
+            .filter_map(|service| match service.state {
+                State::Restarting { at } => Some(at),
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Stop starting and restarting services, so they can all exit
+    pub fn shut_down(&mut self) {
+        self.shutting_down = true;
+    }
+
+    /// Has every service exited after shutting down?
+    pub fn finished(&self) -> bool {
+        self.shutting_down
+            && !self
+                .services
+                .iter()
+                .any(|service| matches!(service.state, State::Running { .. }))
+    }
+}
@@ -0,0 +1,79 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::{collections::vec_deque::VecDeque, vec::Vec};
+use log_portal::{LogChunk, MAX_READ_LEN};
+
+/// How many of the newest bytes of log are kept for readers
+const BACKLOG_SIZE: usize = 64 * 1024;
+
+/// The newest part of the log, which `log-portal` clients read from
+///
+/// Every byte has a cursor, counting from the first byte pushed. Once the backlog is full,
+/// the oldest bytes are thrown away to make room.
+pub struct Backlog {
+    bytes: VecDeque<u8>,
+    /// The cursor of the oldest byte still kept
+    start: u64,
+}
+
+impl Backlog {
+    pub const fn new() -> Self {
+        Self {
+            bytes: VecDeque::new(),
+            start: 0,
+        }
+    }
+
+    /// Add `data` to the end of the log
+    pub fn push(&mut self, data: &[u8]) {
+        self.bytes.extend(data);
+
+        let overflow = self.bytes.len().saturating_sub(BACKLOG_SIZE);
+        self.bytes.drain(..overflow);
+        self.start += overflow as u64;
+    }
+
+    /// The cursor just after the newest byte
+    pub fn end(&self) -> u64 {
+        self.start + self.bytes.len() as u64
+    }
+
+    /// Read up to `len` bytes starting at `cursor`
+    pub fn read(&self, cursor: u64, len: u64) -> LogChunk {
+        let missed = self.start.saturating_sub(cursor);
+        let cursor = cursor.clamp(self.start, self.end());
+
+        let from = (cursor - self.start) as usize;
+        let to = from + (len.min(MAX_READ_LEN) as usize).min(self.bytes.len() - from);
+        let bytes: Vec<u8> = self.bytes.range(from..to).copied().collect();
+
+        LogChunk {
+            cursor: cursor + bytes.len() as u64,
+            missed,
+            bytes,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use alloc::{format, string::String};
+use aloe::ipc::QuantumGlue;
+use fs_portal::{FsPortalClient, QuantumError};
+
+/// The directory the logs are kept in
+const LOG_DIR: &str = "/var/log";
+/// The log being written to
+const LOG_PATH: &str = "/var/log/kernel.log";
+/// How big the log can grow before it is rotated
+const ROTATE_SIZE: u64 = 64 * 1024;
+/// How many rotated logs are kept, `kernel.1` is the newest
+const KEPT_LOGS: usize = 3;
+/// How much of the end of the log is checked for a torn write when it is opened
+const TAIL_CHECK_LEN: u64 = 4096;
+/// The most bytes sent to the fs server in one append
+const MAX_APPEND_LEN: usize = 4096;
+/// Where the log of the last boot is kept, if it ended in a panic
+pub const CRASH_LOG_PATH: &str = "/var/log/crash.log";
+
+/// The path of the `nth` rotated log
+fn rotated_path(nth: usize) -> String {
+    format!("{LOG_DIR}/kernel.{nth}")
+}
+
+/// How many bytes at the start of `tail` are whole lines
+///
+/// A crash while appending can leave half a line, or zeros where the data never made it to
+/// the disk, at the end of the log.
+fn intact_len(tail: &[u8]) -> usize {
+    let written = tail
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(tail.len());
+
+    tail[..written]
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |newline| newline + 1)
+}
+
+/// The rotating log files on the boot filesystem
+pub struct LogFile {
+    fs: FsPortalClient<QuantumGlue>,
+    /// The size of the log being written to
+    size: u64,
+}
+
+impl LogFile {
+    /// Open the log, creating its directory and cutting off anything a crash left torn at
+    /// its end
+    pub fn open(mut fs: FsPortalClient<QuantumGlue>) -> Result<Self, QuantumError> {
+        for dir in ["/var", LOG_DIR] {
+            match fs.create_dir_blocking(String::from(dir))? {
+                Ok(()) | Err(QuantumError::AlreadyExists) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let size = match fs.stat_blocking(String::from(LOG_PATH))? {
+            Ok(stat) => stat.size,
+            Err(QuantumError::NotFound) => 0,
+            Err(err) => return Err(err),
+        };
+
+        let mut file = Self { fs, size };
+        let torn = file.repair()?;
+        if torn != 0 {
+            file.append(format!("--- logd: cut {torn} bytes torn by a crash ---\n").as_bytes())?;
+        }
+
+        Ok(file)
+    }
+
+    /// Cut the log back to its last whole line, returning how many bytes were cut
+    fn repair(&mut self) -> Result<u64, QuantumError> {
+        if self.size == 0 {
+            return Ok(0);
+        }
+
+        let tail_start = self.size.saturating_sub(TAIL_CHECK_LEN);
+        let tail =
+            self.fs
+                .read_blocking(String::from(LOG_PATH), tail_start, self.size - tail_start)??;
+
+        let intact = tail_start + intact_len(&tail) as u64;
+        if intact == self.size {
+            return Ok(0);
+        }
+
+        self.fs
+            .truncate_blocking(String::from(LOG_PATH), intact)??;
+        let torn = self.size - intact;
+        self.size = intact;
+
+        Ok(torn)
+    }
+
+    /// Move every log down one place, dropping the oldest, and start a new empty log
+    fn rotate(&mut self) -> Result<(), QuantumError> {
+        for nth in (1..KEPT_LOGS).rev() {
+            match self
+                .fs
+                .rename_blocking(rotated_path(nth), rotated_path(nth + 1))?
+            {
+                Ok(()) | Err(QuantumError::NotFound) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        self.fs
+            .rename_blocking(String::from(LOG_PATH), rotated_path(1))??;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    /// Add `bytes` to the end of the log, rotating it first if it would grow too big
+    pub fn append(&mut self, bytes: &[u8]) -> Result<(), QuantumError> {
+        if self.size != 0 && self.size + bytes.len() as u64 > ROTATE_SIZE {
+            self.rotate()?;
+        }
+
+        for chunk in bytes.chunks(MAX_APPEND_LEN) {
+            self.size = self
+                .fs
+                .append_blocking(String::from(LOG_PATH), chunk.into())??;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the saved crash log with `log`
+    pub fn save_crash_log(&mut self, log: &[u8]) -> Result<(), QuantumError> {
+        match self.fs.remove_blocking(String::from(CRASH_LOG_PATH))? {
+            Ok(()) | Err(QuantumError::NotFound) => (),
+            Err(err) => return Err(err),
+        }
+
+        for chunk in log.chunks(MAX_APPEND_LEN) {
+            self.fs
+                .append_blocking(String::from(CRASH_LOG_PATH), chunk.into())??;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+#![no_main]
+tiny_std!();
+
+use alloc::{format, vec, vec::Vec};
+use aloe::{
+    CrashLogError, WaitSignal, dbugln,
+    ipc::{QuantumGlue, QuantumHost},
+    klog::KernelLog,
+    previous_crash_log, signal_timer, signal_wait,
+    time::unix_time,
+    tiny_std,
+};
+use backlog::Backlog;
+use file::{CRASH_LOG_PATH, LogFile};
+use fs_portal::FsPortalClient;
+use log_portal::{LogPortalClientRequest, LogPortalServer};
+
+mod backlog;
+mod file;
+
+/// How often the kernel's log is drained
+const POLL_INTERVAL_NS: u64 = 200_000_000;
+/// How much of an unfinished line is held back from the log file before it is written anyway
+const MAX_PENDING: usize = 8 * 1024;
+/// The most of the last boot's crash log that is saved
+const MAX_CRASH_LOG: usize = 64 * 1024;
+
+/// A client connected to the log daemon
+struct LogClient {
+    portal: LogPortalServer<QuantumGlue>,
+}
+
+/// The log daemon
+///
+/// Only one process can read the kernel's log ring, so `logd` drains it and hands the log to
+/// everyone else. The newest part is served to `log-portal` clients on `log`, and all of it is
+/// appended to `/var/log/kernel.log` on the boot filesystem. If the last boot panicked, the log
+/// the kernel kept of it is saved to `/var/log/crash.log` when `logd` starts.
+struct Logd {
+    log: KernelLog,
+    backlog: Backlog,
+    /// The log that has not been written to the log file yet
+    pending: Vec<u8>,
+    /// The log file, or `None` if it could not be written to
+    file: Option<LogFile>,
+    /// How many dropped bytes have already been noted in the log
+    dropped: u64,
+}
+
+impl Logd {
+    /// Add `bytes` to the end of the log
+    fn push(&mut self, bytes: &[u8]) {
+        self.backlog.push(bytes);
+        if self.file.is_some() {
+            self.pending.extend_from_slice(bytes);
+        }
+    }
+
+    /// Take everything the kernel has logged since the last drain
+    fn drain(&mut self) {
+        let mut buf = [0; 1024];
+        loop {
+            let read = self.log.try_read(&mut buf);
+            if read == 0 {
+                break;
+            }
+
+            self.push(&buf[..read]);
+        }
+
+        let dropped = self.log.dropped();
+        if dropped != self.dropped {
+            let note = format!(
+                "--- logd: the kernel dropped {} bytes of log ---\n",
+                dropped - self.dropped
+            );
+            self.push(note.as_bytes());
+            self.dropped = dropped;
+        }
+    }
+
+    /// Write the pending log to the log file
+    ///
+    /// Only whole lines are written, unless `all` is set or the unfinished line has grown too
+    /// long to hold back.
+    fn flush(&mut self, all: bool) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+
+        if all && self.pending.last().is_some_and(|&byte| byte != b'\n') {
+            self.pending.push(b'\n');
+        }
+
+        let len = match self.pending.iter().rposition(|&byte| byte == b'\n') {
+            Some(newline) => newline + 1,
+            None if self.pending.len() >= MAX_PENDING => self.pending.len(),
+            None => return,
+        };
+
+        if let Err(err) = file.append(&self.pending[..len]) {
+            dbugln!("Unable to write the log file ({err:?}), only serving the log from now on");
+            self.file = None;
+            self.pending = Vec::new();
+            return;
+        }
+
+        self.pending.drain(..len);
+    }
+}
+
+/// Save the last boot's log next to the kernel's log, if that boot ended in a panic
+///
+/// The kernel only keeps it in memory, so it is lost on a cold boot unless it is saved.
+fn save_crash_log(file: &mut LogFile) {
+    let mut log = vec![0; MAX_CRASH_LOG];
+    let len = match previous_crash_log(&mut log) {
+        Ok(len) => len.min(log.len()),
+        Err(CrashLogError::NoCrashLog) => return,
+        Err(err) => {
+            dbugln!("Unable to read the last boot's crash log ({err:?})");
+            return;
+        }
+    };
+
+    match file.save_crash_log(&log[..len]) {
+        Ok(()) => dbugln!("Saved the last boot's crash log to {CRASH_LOG_PATH}"),
+        Err(err) => dbugln!("Unable to save the last boot's crash log ({err:?})"),
+    }
+}
+
+fn main() {
+    dbugln!("Starting log daemon!");
+
+    let log = match KernelLog::attach() {
+        Ok(log) => log,
+        Err(err) => {
+            dbugln!("Unable to attach to the kernel log ({err:?}), exiting");
+            return;
+        }
+    };
+
+    let mut server = QuantumHost::<LogClient>::host_on("log").unwrap();
+    let file = match LogFile::open(FsPortalClient::new(QuantumGlue::connect_to("fs").unwrap())) {
+        Ok(mut file) => {
+            save_crash_log(&mut file);
+            Some(file)
+        }
+        Err(err) => {
+            dbugln!("Unable to open the log file ({err:?}), only serving the log");
+            None
+        }
+    };
+
+    let mut logd = Logd {
+        log,
+        backlog: Backlog::new(),
+        pending: Vec::new(),
+        file,
+        dropped: 0,
+    };
+
+    let started = format!("--- logd: started at {}s ---\n", unix_time().as_secs());
+    logd.push(started.as_bytes());
+
+    let mut timer_armed = false;
+    loop {
+        if !timer_armed {
+            signal_timer(POLL_INTERVAL_NS);
+            timer_armed = true;
+        }
+
+        match signal_wait() {
+            WaitSignal::TimerUpdate { .. } => {
+                timer_armed = false;
+                logd.drain();
+                logd.flush(false);
+            }
+            WaitSignal::TerminationRequest => {
+                logd.drain();
+                logd.flush(true);
+                return;
+            }
+            signal => server
+                .service_signal(
+                    signal,
+                    |handle| {
+                        Ok(LogClient {
+                            portal: LogPortalServer::new(QuantumGlue::new(handle)),
+                        })
+                    },
+                    |client| match client.portal.incoming()? {
+                        LogPortalClientRequest::Ping { sender } => sender.respond_with(()),
+                        LogPortalClientRequest::Read {
+                            cursor,
+                            len,
+                            sender,
+                        } => {
+                            logd.drain();
+                            sender.respond_with(logd.backlog.read(cursor, len))
+                        }
+                        LogPortalClientRequest::End { sender } => {
+                            logd.drain();
+                            sender.respond_with(logd.backlog.end())
+                        }
+                        _ => Ok(()),
+                    },
+                    |_| Ok(()),
+                    |_| Ok(()),
+                )
+                .unwrap(),
+        }
+    }
+}
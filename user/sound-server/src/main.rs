@@ -0,0 +1,290 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+#![no_std]
+#![no_main]
+tiny_std!();
+
+use alloc::collections::VecDeque;
+use aloe::{
+    WaitSignal, dbugln,
+    ipc::{QuantumGlue, QuantumHost},
+    signal_timer, signal_wait,
+    time::monotonic,
+    tiny_std,
+};
+use core::{cell::RefCell, time::Duration};
+use portal::ipc::IpcDeferred;
+use sound_portal::{
+    Note, QuantumError, SoundPortalClientRequest, SoundPortalInfo, SoundPortalServer,
+};
+use speaker::PcSpeaker;
+
+mod speaker;
+
+/// The range of frequencies, in hertz, a note can have
+const FREQUENCY_RANGE: core::ops::RangeInclusive<u32> = 20..=20000;
+/// The longest a single note can play for
+const MAX_DURATION_MS: u32 = 10_000;
+/// The most notes that can be played in one request
+const MAX_NOTES: usize = 256;
+/// The most notes that can be waiting to play across every client
+const MAX_QUEUED_NOTES: usize = 1024;
+
+/// A client connected to the sound server
+struct SoundClient {
+    portal: SoundPortalServer<QuantumGlue>,
+}
+
+/// The answer owed to a client once its notes have played
+enum Response {
+    Beep(IpcDeferred<SoundPortalInfo, Result<(), QuantumError>, 2>),
+    Play(IpcDeferred<SoundPortalInfo, Result<(), QuantumError>, 3>),
+}
+
+/// A request whose notes are waiting to play
+struct PendingPlay {
+    handle: u64,
+    notes: VecDeque<Note>,
+    response: Response,
+}
+
+/// Plays queued requests one note at a time, driven by the server's timer
+struct Player {
+    speaker: PcSpeaker,
+    queue: VecDeque<PendingPlay>,
+    /// How many notes are left in `queue`
+    queued_notes: usize,
+    /// When the note on the speaker is done, if one is playing
+    note_ends: Option<Duration>,
+}
+
+impl Player {
+    fn new(speaker: PcSpeaker) -> Self {
+        Self {
+            speaker,
+            queue: VecDeque::new(),
+            queued_notes: 0,
+            note_ends: None,
+        }
+    }
+
+    /// Queue `notes` to play after everything already queued.
+    ///
+    /// Every note is checked before anything is queued, so a bad request plays nothing.
+    fn queue(
+        &mut self,
+        handle: u64,
+        notes: &[Note],
+        response: Response,
+    ) -> Result<(), (Response, QuantumError)> {
+        let valid = |note: &Note| {
+            (note.frequency == 0 || FREQUENCY_RANGE.contains(&note.frequency))
+                && note.duration_ms <= MAX_DURATION_MS
+        };
+
+        if notes.len() > MAX_NOTES || !notes.iter().all(valid) {
+            return Err((response, QuantumError::InvalidInput));
+        }
+        if self.queued_notes + notes.len() > MAX_QUEUED_NOTES {
+            return Err((response, QuantumError::Busy));
+        }
+
+        self.queued_notes += notes.len();
+        self.queue.push_back(PendingPlay {
+            handle,
+            notes: notes.iter().cloned().collect(),
+            response,
+        });
+
+        Ok(())
+    }
+
+    /// Forget everything `handle` queued, silencing the speaker if it was playing for it
+    fn drop_client(&mut self, handle: u64) {
+        if self
+            .queue
+            .front()
+            .is_some_and(|front| front.handle == handle)
+        {
+            self.speaker.stop();
+            self.note_ends = None;
+        }
+
+        self.queue.retain(|pending| pending.handle != handle);
+        self.queued_notes = self.queue.iter().map(|pending| pending.notes.len()).sum();
+    }
+
+    /// Move on to the next note once the current one is done, answering every request that
+    /// has finished.
+    ///
+    /// Returns how long until the note now playing is done.
+    fn advance(&mut self, server: &mut QuantumHost<SoundClient>) -> Option<Duration> {
+        loop {
+            let now = monotonic();
+            if let Some(ends) = self.note_ends {
+                if now < ends {
+                    return Some(ends - now);
+                }
+
+                self.speaker.stop();
+                self.note_ends = None;
+            }
+
+            let pending = self.queue.front_mut()?;
+            if let Some(note) = pending.notes.pop_front() {
+                self.queued_notes -= 1;
+                if note.frequency != 0 {
+                    self.speaker.play(note.frequency);
+                }
+
+                self.note_ends = Some(now + Duration::from_millis(note.duration_ms as u64));
+                continue;
+            }
+
+            let Some(finished) = self.queue.pop_front() else {
+                return None;
+            };
+            respond(server, finished.handle, finished.response, Ok(()));
+        }
+    }
+}
+
+/// Answer the request behind `response`, if the client that made it is still connected
+fn respond(
+    server: &mut QuantumHost<SoundClient>,
+    handle: u64,
+    response: Response,
+    result: Result<(), QuantumError>,
+) {
+    let Some(client) = server.client_mut(handle) else {
+        return;
+    };
+
+    let sent = match response {
+        Response::Beep(deferred) => client.portal.resume(deferred).respond_with(result),
+        Response::Play(deferred) => client.portal.resume(deferred).respond_with(result),
+    };
+
+    if let Err(err) = sent {
+        dbugln!("Unable to answer {handle} ({err:?})");
+    }
+}
+
+fn main() {
+    dbugln!("Starting Sound server!");
+
+    // FIXME: This only drives the PC speaker. An AC'97 or HDA driver needs PCI
+    //        enumeration and DMA buffers with known physical addresses, neither of which
+    //        userspace can get yet.
+    let speaker = match PcSpeaker::new() {
+        Ok(speaker) => speaker,
+        Err(err) => {
+            dbugln!("Unable to claim the PC speaker ({err:?}), exiting");
+            return;
+        }
+    };
+    let player = RefCell::new(Player::new(speaker));
+    let mut server = QuantumHost::<SoundClient>::host_on("sound").unwrap();
+    // When the earliest timer we asked for goes off
+    let mut timer_at = None;
+    loop {
+        match signal_wait() {
+            WaitSignal::TimerUpdate { .. } => timer_at = None,
+            signal => {
+                // Requests that can't be queued are answered right away, once the client
+                // is no longer borrowed
+                let mut rejected = None;
+
+                server
+                    .service_signal(
+                        signal,
+                        |handle| {
+                            Ok(SoundClient {
+                                portal: SoundPortalServer::new(QuantumGlue::new(handle)),
+                            })
+                        },
+                        |client| {
+                            let handle = client.portal.glue().handle();
+                            let queued = match client.portal.incoming()? {
+                                SoundPortalClientRequest::Ping { sender } => {
+                                    return sender.respond_with(());
+                                }
+                                SoundPortalClientRequest::Beep {
+                                    frequency,
+                                    duration_ms,
+                                    sender,
+                                } => {
+                                    let response = Response::Beep(sender.defer());
+                                    if frequency == 0 {
+                                        Err((response, QuantumError::InvalidInput))
+                                    } else {
+                                        let note = Note {
+                                            frequency,
+                                            duration_ms,
+                                        };
+                                        player.borrow_mut().queue(handle, &[note], response)
+                                    }
+                                }
+                                SoundPortalClientRequest::Play { notes, sender } => {
+                                    let response = Response::Play(sender.defer());
+                                    player.borrow_mut().queue(handle, &notes, response)
+                                }
+                                _ => return Ok(()),
+                            };
+
+                            if let Err((response, err)) = queued {
+                                rejected = Some((handle, response, err));
+                            }
+                            Ok(())
+                        },
+                        |_| Ok(()),
+                        |client| {
+                            dbugln!("Disconnecting Client");
+                            player
+                                .borrow_mut()
+                                .drop_client(client.portal.glue().handle());
+                            Ok(())
+                        },
+                    )
+                    .unwrap();
+
+                if let Some((handle, response, err)) = rejected {
+                    respond(&mut server, handle, response, Err(err));
+                }
+            }
+        }
+
+        // Notes are timed with the server's timer, so clients are still served while
+        // they play
+        if let Some(left) = player.borrow_mut().advance(&mut server) {
+            let at = monotonic() + left;
+            if timer_at.is_none_or(|armed| at < armed) {
+                signal_timer(left.as_nanos() as u64);
+                timer_at = Some(at);
+            }
+        }
+    }
+}
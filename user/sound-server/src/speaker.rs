@@ -0,0 +1,93 @@
+/*
+  ____                 __               __  __
+ / __ \__ _____ ____  / /___ ____ _    / / / /__ ___ ____
+/ /_/ / // / _ `/ _ \/ __/ // /  ' \  / /_/ (_-</ -_) __/
+\___\_\_,_/\_,_/_//_/\__/\_,_/_/_/_/  \____/___/\__/_/
+  Part of the Quantum OS Kernel
+
+Copyright 2025 Gavin Kellam
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or substantial
+portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+*/
+
+use aloe::{
+    IoClaimError, claim_io_ports,
+    uio::{CpuIO, UserIO, opt},
+};
+use arch::pit825x::{
+    CHANNEL_2_GATE_BIT, PitAccessMode, PitOperatingMode, PitSelectChannel, SPEAKER_DATA_BIT,
+    command_byte, square_wave_divisor,
+};
+
+/// The PIT's channel 2 data port, the command port follows it
+const CHANNEL_2_PORT: u16 = 0x42;
+/// The PIT's command port
+const COMMAND_PORT: u16 = 0x43;
+/// The system control port, which gates channel 2 onto the speaker
+const CONTROL_PORT: u16 = 0x61;
+
+/// Select channel 2, access lo then hi byte, in square wave mode
+const CHANNEL_2_SQUARE_WAVE: u8 = command_byte(
+    PitSelectChannel::Channel2,
+    PitAccessMode::AccessLoHi,
+    PitOperatingMode::SquareWave,
+    false,
+);
+/// Gate channel 2 and connect its output to the speaker
+const SPEAKER_ENABLE_BITS: u8 = CHANNEL_2_GATE_BIT | SPEAKER_DATA_BIT;
+
+/// The PC speaker, driven by channel 2 of the PIT
+pub struct PcSpeaker {
+    command: UserIO<CpuIO, opt::WriteOnly, opt::Shared>,
+    channel_2: UserIO<CpuIO, opt::WriteOnly>,
+    control: UserIO<CpuIO, opt::ReadWrite, opt::Shared>,
+}
+
+impl PcSpeaker {
+    /// Claim the speaker's ports, which are shared with the kernel's timer on the same PIT
+    pub fn new() -> Result<Self, IoClaimError> {
+        claim_io_ports(CHANNEL_2_PORT, 2, true)?;
+        claim_io_ports(CONTROL_PORT, 1, true)?;
+
+        Ok(Self {
+            command: unsafe { UserIO::new(COMMAND_PORT) },
+            channel_2: unsafe { UserIO::new(CHANNEL_2_PORT) },
+            control: unsafe { UserIO::new(CONTROL_PORT) },
+        })
+    }
+
+    /// Start playing a square wave of `hz`.
+    pub fn play(&mut self, hz: u32) {
+        let divisor = square_wave_divisor(hz);
+
+        unsafe {
+            self.command.write_u8(CHANNEL_2_SQUARE_WAVE);
+            self.channel_2.write_u8((divisor & 0xFF) as u8);
+            self.channel_2.write_u8((divisor >> 8) as u8);
+
+            let control = self.control.read_u8();
+            self.control.write_u8(control | SPEAKER_ENABLE_BITS);
+        }
+    }
+
+    /// Stop playing.
+    pub fn stop(&mut self) {
+        unsafe {
+            let control = self.control.read_u8();
+            self.control.write_u8(control & !SPEAKER_ENABLE_BITS);
+        }
+    }
+}